@@ -7,6 +7,7 @@
   perSystem = {
     pkgs,
     config,
+    self',
     ...
   }: let
     rootPath = config.forja.rootPath;
@@ -18,7 +19,7 @@
           mkdir -p "$out"
           cd ${rootPath} || exit
 
-          ${pkgs.addlicense}/bin/addlicense -s -l mpl -check .
+          ${self'.packages.forjaCli}/bin/forja license --check
         '';
 
       alejandra = pkgs.runCommand "checkAlejandra" {} ''
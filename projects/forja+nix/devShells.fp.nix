@@ -20,7 +20,6 @@
         cargo-hakari
         wasm-pack
         wasm-bindgen-cli
-        addlicense
         glow
         nix-output-monitor
         nixd
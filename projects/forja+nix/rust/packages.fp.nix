@@ -47,6 +47,13 @@
         includeProjects = ["niiebla+rust" "util+rust" "workspace_hack+rust"];
         hasBin = false;
         hasLib = true;
+      })
+      // (crane.makeCratePackages {
+        nixPackageName = "niieblaCli";
+        cargoPackageName = "zelzip_niiebla_cli";
+        includeProjects = ["niiebla_cli+rust" "niiebla+rust" "util+rust" "workspace_hack+rust"];
+        hasBin = true;
+        hasLib = false;
       });
 
     apps.forjaCli = {
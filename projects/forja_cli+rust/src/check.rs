@@ -0,0 +1,310 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use color_eyre::eyre::{bail, ContextCompat};
+use color_eyre::Result;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tracing::{error, info, warn};
+
+/// Prefix of every line of the `internal-json` log format of Nix.
+const NIX_LOG_PREFIX: &str = "@nix ";
+
+/// Activity type used by Nix for the build of a single derivation.
+const NIX_ACTIVITY_BUILD: u64 = 105;
+
+/// Result type used by Nix for each line of the log of a build.
+const NIX_RESULT_BUILD_LOG_LINE: u64 = 101;
+
+/// Number of lines of the log of a failed build kept on the report.
+const KEPT_LOG_LINES: usize = 25;
+
+/// The name used on the report for failures not related to a single project.
+const WORKSPACE_PROJECT: &str = "workspace";
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum NixLogEvent {
+    Start {
+        id: u64,
+
+        #[serde(rename = "type", default)]
+        kind: u64,
+
+        #[serde(default)]
+        text: String,
+    },
+
+    Stop {
+        id: u64,
+    },
+
+    Msg {
+        level: u8,
+        msg: String,
+    },
+
+    Result {
+        id: u64,
+
+        #[serde(rename = "type")]
+        kind: u64,
+
+        #[serde(default)]
+        fields: Vec<serde_json::Value>,
+    },
+
+    #[serde(other)]
+    Unknown,
+}
+
+impl NixLogEvent {
+    fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str(line.strip_prefix(NIX_LOG_PREFIX)?).ok()
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct CheckFailure {
+    project: String,
+    derivation: Option<String>,
+    message: String,
+    log: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Default)]
+struct CheckReport {
+    success: bool,
+    builds: usize,
+    failures: Vec<CheckFailure>,
+}
+
+#[derive(Debug, Default)]
+struct CheckState {
+    report: CheckReport,
+    build_logs: HashMap<u64, Vec<String>>,
+
+    /// The logs of the finished builds by the name of their derivation.
+    finished_build_logs: HashMap<String, Vec<String>>,
+    build_names: HashMap<u64, String>,
+}
+
+impl CheckState {
+    fn handle_event(&mut self, event: NixLogEvent) {
+        match event {
+            NixLogEvent::Start { id, kind, text } if kind == NIX_ACTIVITY_BUILD => {
+                info!("{text}");
+
+                self.report.builds += 1;
+                self.build_logs.insert(id, vec![]);
+
+                if let Some(name) = derivation_name(&text) {
+                    self.build_names.insert(id, name);
+                }
+            }
+
+            NixLogEvent::Stop { id } => {
+                if let (Some(log), Some(name)) =
+                    (self.build_logs.remove(&id), self.build_names.remove(&id))
+                {
+                    self.finished_build_logs.insert(name, log);
+                }
+            }
+
+            NixLogEvent::Result { id, kind, fields } if kind == NIX_RESULT_BUILD_LOG_LINE => {
+                let Some(log) = self.build_logs.get_mut(&id) else {
+                    return;
+                };
+
+                if let Some(line) = fields.first().and_then(|field| field.as_str()) {
+                    log.push(line.to_string());
+
+                    if log.len() > KEPT_LOG_LINES {
+                        log.remove(0);
+                    }
+                }
+            }
+
+            // Only the errors (level zero) are relevant
+            NixLogEvent::Msg { level: 0, msg } => self.handle_error(msg),
+
+            NixLogEvent::Msg { level: 1, msg } => warn!("{msg}"),
+
+            NixLogEvent::Start { .. }
+            | NixLogEvent::Msg { .. }
+            | NixLogEvent::Result { .. }
+            | NixLogEvent::Unknown => (),
+        }
+    }
+
+    fn handle_error(&mut self, msg: String) {
+        error!("{msg}");
+
+        // Failures of dependencies are already reported by the failure of the dependency itself
+        if msg.contains("dependencies of derivation") {
+            return;
+        }
+
+        let derivation = derivation_name(&msg);
+
+        let log = derivation
+            .as_ref()
+            .and_then(|name| self.finished_build_logs.get(name))
+            .cloned()
+            .unwrap_or_default();
+
+        let mut projects = mentioned_projects(&msg);
+
+        for line in &log {
+            for project in mentioned_projects(line) {
+                if !projects.contains(&project) {
+                    projects.push(project);
+                }
+            }
+        }
+
+        if projects.is_empty() {
+            projects.push(WORKSPACE_PROJECT.to_string());
+        }
+
+        for project in projects {
+            self.report.failures.push(CheckFailure {
+                project,
+                derivation: derivation.clone(),
+                message: msg.clone(),
+                log: log.clone(),
+            });
+        }
+    }
+
+    fn print_summary(&self) {
+        if self.report.failures.is_empty() {
+            info!(
+                "{} ({} builds)",
+                "All checks passed".green(),
+                self.report.builds
+            );
+            return;
+        }
+
+        let mut failures_by_project: Vec<(&str, Vec<&CheckFailure>)> = vec![];
+
+        for failure in &self.report.failures {
+            match failures_by_project
+                .iter_mut()
+                .find(|(project, _)| *project == failure.project)
+            {
+                Some((_, failures)) => failures.push(failure),
+                None => failures_by_project.push((&failure.project, vec![failure])),
+            }
+        }
+
+        error!("{}", "Some checks have failed:".red().bold());
+
+        for (project, failures) in failures_by_project {
+            error!("  - {}:", project.bold());
+
+            for failure in failures {
+                error!(
+                    "      {}",
+                    failure
+                        .derivation
+                        .as_deref()
+                        .unwrap_or("unknown derivation")
+                );
+            }
+        }
+    }
+}
+
+/// Get the name of the first derivation mentioned on a text
+/// (`/nix/store/<hash>-<name>.drv`), without its hash.
+fn derivation_name(text: &str) -> Option<String> {
+    let start = text.find("/nix/store/")? + "/nix/store/".len();
+    let path = &text[start..];
+    let end = path.find(".drv")?;
+
+    let (_, name) = path[..end].split_once('-')?;
+
+    Some(name.to_string())
+}
+
+/// Get the projects (`projects/<name>`) mentioned on a text.
+fn mentioned_projects(text: &str) -> Vec<String> {
+    let mut projects = vec![];
+
+    for (start, _) in text.match_indices("projects/") {
+        let name: String = text[start + "projects/".len()..]
+            .chars()
+            .take_while(|char| char.is_alphanumeric() || matches!(char, '_' | '+' | '-'))
+            .collect();
+
+        // Every project has its language as a suffix
+        if name.contains('+') && !projects.contains(&name) {
+            projects.push(name);
+        }
+    }
+
+    projects
+}
+
+/// Run all the checks of the Nix build system, printing their progress and a summary of the
+/// failures per project, optionally writing a JSON report into `report_path`.
+pub(crate) fn run_checks(root_path: &Path, report_path: Option<&Path>) -> Result<()> {
+    let mut child = Command::new("nix")
+        .args(["flake", "check", "--log-format", "internal-json"])
+        .arg(root_path)
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stderr = child
+        .stderr
+        .take()
+        .wrap_err("Unable to read the log of Nix")?;
+
+    let mut state = CheckState::default();
+
+    for line in BufReader::new(stderr).lines() {
+        let line = line?;
+
+        if let Some(event) = NixLogEvent::parse(&line) {
+            state.handle_event(event);
+        } else {
+            info!("{line}");
+        }
+    }
+
+    let status = child.wait()?;
+
+    // Nix may fail without reporting the error in JSON (like on evaluation errors)
+    if !status.success() && state.report.failures.is_empty() {
+        state.report.failures.push(CheckFailure {
+            project: WORKSPACE_PROJECT.to_string(),
+            derivation: None,
+            message: format!("Nix exited with {status}"),
+            log: vec![],
+        });
+    }
+
+    state.report.success = state.report.failures.is_empty();
+    state.print_summary();
+
+    if let Some(report_path) = report_path {
+        fs::write(report_path, serde_json::to_string_pretty(&state.report)?)?;
+        info!("Report written into {report_path:?}");
+    }
+
+    if !state.report.success {
+        bail!("{} checks have failed", state.report.failures.len());
+    }
+
+    Ok(())
+}
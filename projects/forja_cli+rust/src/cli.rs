@@ -4,14 +4,47 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
-use clap::{command, ArgMatches, Command};
+use clap::{arg, command, value_parser, ArgMatches, Command};
+use std::path::PathBuf;
 
 pub(crate) fn get_matches() -> ArgMatches {
     command!()
         .subcommand_required(true)
         .subcommand(Command::new("todo").about("Print the current tasks to do"))
-        .subcommand(Command::new("check").about("Check the quality of the code"))
+        .subcommand(
+            Command::new("check")
+                .about("Check the quality of the code")
+                .arg(
+                    arg!(--report <PATH> "Write a JSON report of the checks into a file")
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
         .subcommand(Command::new("fix").about("Try to fix issues in the code"))
+        .subcommand(
+            Command::new("license")
+                .about("Add the license header to the files missing it")
+                .arg(arg!(--check "Only check that no file is missing the license header")),
+        )
+        .subcommand(
+            Command::new("new")
+                .about("Create a new Rust crate following the conventions of the monorepo")
+                .arg(arg!(<NAME> "Name of the project in snake_case, without the `+rust` suffix"))
+                .arg(
+                    arg!(--kind <KIND> "Kind of the crate")
+                        .value_parser(["lib", "cli", "wasm"])
+                        .default_value("lib"),
+                ),
+        )
+        .subcommand(
+            Command::new("release")
+                .about("Bump the version of a project and add a stub to its changelog")
+                .arg(arg!(<PROJECT> "Name of the directory of the project (like `niiebla+rust`)"))
+                .arg(
+                    arg!(--bump <KIND> "Part of the version to increase")
+                        .value_parser(["major", "minor", "patch"])
+                        .default_value("patch"),
+                ),
+        )
         .subcommand(Command::new("gen").about("Regenerate all machine made files"))
         .get_matches()
 }
@@ -6,12 +6,18 @@
 
 //! Management tool for multiple tasks on the monorepo.
 
+use color_eyre::eyre::ContextCompat;
 use color_eyre::Result;
+use std::path::PathBuf;
 use tracing::info;
 use util::setup_logging_for_cli;
 
+mod check;
 mod cli;
+mod license;
+mod release;
 mod root_path;
+mod scaffold;
 mod todo;
 
 fn main() -> Result<()> {
@@ -33,18 +39,51 @@ fn main() -> Result<()> {
         }?;
     }
 
-    if let Some(_matches) = matches.subcommand_matches("check") {
+    if let Some(matches) = matches.subcommand_matches("check") {
         info!("Checking with the Nix build system");
-        cmd_lib::run_cmd! {
-            nix flake check --log-format internal-json $root_path |& nom --json
-        }?;
+        check::run_checks(
+            &root_path,
+            matches.get_one::<PathBuf>("report").map(PathBuf::as_path),
+        )?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("new") {
+        let name = matches
+            .get_one::<String>("NAME")
+            .wrap_err("Missing the name of the project")?;
+
+        let kind = matches
+            .get_one::<String>("kind")
+            .and_then(|kind| scaffold::ProjectKind::new(kind))
+            .wrap_err("Invalid kind of project")?;
+
+        scaffold::new_project(&root_path, name, kind)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("release") {
+        let project = matches
+            .get_one::<String>("PROJECT")
+            .wrap_err("Missing the project to release")?;
+
+        let bump = matches
+            .get_one::<String>("bump")
+            .and_then(|bump| release::Bump::new(bump))
+            .wrap_err("Invalid part of the version to bump")?;
+
+        release::release(&root_path, project, bump)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("license") {
+        if matches.get_flag("check") {
+            license::check_headers(&root_path)?;
+        } else {
+            license::fix_headers(&root_path)?;
+        }
     }
 
     if let Some(_matches) = matches.subcommand_matches("fix") {
         info!("Trying to fix as many files as possible");
 
-        // `addlicense` can fail to due a bug in which it assumes
-        // that any directory with a dot in its name is a file
         cmd_lib::run_cmd! {
             cd $root_path;
 
@@ -57,10 +96,10 @@ fn main() -> Result<()> {
             cargo hakari generate;
             cargo hakari manage-deps --yes;
 
-            addlicense -s -l mpl -ignore "**/*.*/**/*" .
-
             nix run .#generateFiles
-        }?
+        }?;
+
+        license::fix_headers(&root_path)?;
     }
 
     Ok(())
@@ -0,0 +1,180 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+use walkdir::WalkDir;
+
+const LICENSE_HEADER: [&str; 5] = [
+    "This Source Code Form is subject to the terms of the Mozilla Public",
+    "License, v. 2.0. If a copy of the MPL was not distributed with this",
+    "file, You can obtain one at https://mozilla.org/MPL/2.0/.",
+    "",
+    "SPDX-License-Identifier: MPL-2.0",
+];
+
+/// Marker searched to know if a file already has a license header.
+const LICENSE_MARKER: &str = "SPDX-License-Identifier:";
+
+/// Number of lines at the start of a file where the license header is searched.
+const SEARCHED_LINES: usize = 10;
+
+/// Directories never visited, the ones starting with a dot are also skipped.
+const IGNORED_DIRECTORIES: [&str; 3] = ["target", "node_modules", "result"];
+
+/// How the license header is commented on a file.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum CommentStyle {
+    /// Every line starts with the given prefix.
+    Line(&'static str),
+
+    /// A single `/** ... */` block.
+    Block,
+}
+
+/// Get the style of the comments of a file given its extension, `None` if the file should not
+/// have a license header.
+fn comment_style(path: &Path) -> Option<CommentStyle> {
+    let file_name = path.file_name()?.to_str()?;
+
+    // Lockfiles are generated by the package managers
+    if file_name.ends_with("-lock.yaml") {
+        return None;
+    }
+
+    match path.extension()?.to_str()? {
+        "rs" | "json5" | "ts" => Some(CommentStyle::Line("//")),
+        "nix" | "toml" | "yaml" | "sh" => Some(CommentStyle::Line("#")),
+        "css" | "astro" => Some(CommentStyle::Block),
+
+        _ => None,
+    }
+}
+
+/// Render the license header commented with the given style.
+pub(crate) fn render_header(style: CommentStyle) -> String {
+    let (start, prefix, end) = match style {
+        CommentStyle::Line(prefix) => ("", prefix, ""),
+        CommentStyle::Block => ("/**\n", " *", " */\n"),
+    };
+
+    let lines: String = LICENSE_HEADER
+        .iter()
+        .map(|line| {
+            if line.is_empty() {
+                format!("{prefix}\n")
+            } else {
+                format!("{prefix} {line}\n")
+            }
+        })
+        .collect();
+
+    format!("{start}{lines}{end}")
+}
+
+/// Check if the text has a license header or if it should be skipped (generated files).
+fn has_header_or_is_generated(text: &str) -> bool {
+    text.lines().take(SEARCHED_LINES).any(|line| {
+        line.contains(LICENSE_MARKER)
+            || line.contains("@generated")
+            || line.contains("This file is generated by")
+    })
+}
+
+/// Insert the license header at the start of the text, after the shebang if any.
+fn insert_header(text: &str, style: CommentStyle, path: &Path) -> String {
+    let header = render_header(style);
+    let extension = path.extension().and_then(|extension| extension.to_str());
+
+    // Nix expressions start just after the header, the rest of files are separated by a blank
+    // line
+    let separator = match extension {
+        Some("nix") => "",
+        _ => "\n",
+    };
+
+    // Astro components only allow code inside their frontmatter, that must be at the start
+    if extension == Some("astro") {
+        return match text.strip_prefix("---\n") {
+            Some(rest) => format!("---\n{header}{separator}{rest}"),
+            None => format!("---\n{header}---\n{separator}{text}"),
+        };
+    }
+
+    match text.strip_prefix("#!") {
+        Some(rest) => {
+            let (shebang, rest) = rest.split_once('\n').unwrap_or((rest, ""));
+            format!("#!{shebang}\n{header}{separator}{rest}")
+        }
+
+        None => format!("{header}{separator}{text}"),
+    }
+}
+
+fn files_missing_header(root_path: &Path) -> Result<Vec<(PathBuf, CommentStyle)>> {
+    let mut missing = vec![];
+
+    let walker = WalkDir::new(root_path).into_iter().filter_entry(|entry| {
+        let name = entry.file_name().to_string_lossy();
+
+        // Never skip the root itself, even if it is named like an ignored directory
+        entry.depth() == 0
+            || !entry.file_type().is_dir()
+            || !(name.starts_with('.') || IGNORED_DIRECTORIES.contains(&name.as_ref()))
+    });
+
+    for entry in walker {
+        let entry = entry?;
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let Some(style) = comment_style(entry.path()) else {
+            continue;
+        };
+
+        let text = fs::read_to_string(entry.path())?;
+
+        if !has_header_or_is_generated(&text) {
+            missing.push((entry.into_path(), style));
+        }
+    }
+
+    Ok(missing)
+}
+
+/// Check that every file of the monorepo has the license header, failing if any is missing.
+pub(crate) fn check_headers(root_path: &Path) -> Result<()> {
+    let missing = files_missing_header(root_path)?;
+
+    for (path, _) in &missing {
+        error!("Missing license header: {path:?}");
+    }
+
+    if !missing.is_empty() {
+        bail!("{} files are missing the license header", missing.len());
+    }
+
+    info!("All the files have the license header");
+
+    Ok(())
+}
+
+/// Insert the license header on every file of the monorepo that is missing it.
+pub(crate) fn fix_headers(root_path: &Path) -> Result<()> {
+    for (path, style) in files_missing_header(root_path)? {
+        info!("Adding the license header to {path:?}");
+
+        let text = fs::read_to_string(&path)?;
+        fs::write(&path, insert_header(&text, style, &path))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::todo::comment_block::CommentBlock;
+use crate::todo::inline_todo_entry::{InlineTodoEntry, Tag};
+use color_eyre::eyre::{bail, ContextCompat};
+use color_eyre::Result;
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// The part of a version to increase.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Bump {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl Bump {
+    pub(crate) fn new(name: &str) -> Option<Self> {
+        Some(match name {
+            "major" => Self::Major,
+            "minor" => Self::Minor,
+            "patch" => Self::Patch,
+
+            _ => return None,
+        })
+    }
+
+    fn apply(self, version: &str) -> Result<String> {
+        let components: Vec<u64> = version
+            .split('.')
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+
+        let [major, minor, patch] = components[..] else {
+            bail!("The version {version:?} is not a valid \"major.minor.patch\" version");
+        };
+
+        Ok(match self {
+            Self::Major => format!("{}.0.0", major + 1),
+            Self::Minor => format!("{major}.{}.0", minor + 1),
+            Self::Patch => format!("{major}.{minor}.{}", patch + 1),
+        })
+    }
+}
+
+/// Get the value of a `key = "value"` line of a TOML file.
+fn toml_string_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let (line_key, value) = line.split_once('=')?;
+
+    if line_key.trim() != key {
+        return None;
+    }
+
+    value.trim().strip_prefix('"')?.strip_suffix('"')
+}
+
+/// Get the name and version of the package of a `Cargo.toml` file.
+fn package_name_and_version(manifest: &str) -> Option<(String, String)> {
+    let mut name = None;
+    let mut version = None;
+    let mut in_package_table = false;
+
+    for line in manifest.lines() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            in_package_table = line == "[package]";
+            continue;
+        }
+
+        if !in_package_table {
+            continue;
+        }
+
+        if let Some(value) = toml_string_value(line, "name") {
+            name = Some(value.to_string());
+        }
+
+        if let Some(value) = toml_string_value(line, "version") {
+            version = Some(value.to_string());
+        }
+    }
+
+    Some((name?, version?))
+}
+
+/// Replace the version of the package of a `Cargo.toml` file.
+fn replace_package_version(manifest: &str, new_version: &str) -> String {
+    let mut in_package_table = false;
+
+    let lines: Vec<String> = manifest
+        .lines()
+        .map(|line| {
+            let trimmed_line = line.trim();
+
+            if trimmed_line.starts_with('[') {
+                in_package_table = trimmed_line == "[package]";
+            }
+
+            if in_package_table && toml_string_value(trimmed_line, "version").is_some() {
+                format!("version = \"{new_version}\"")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+
+    lines.join("\n") + "\n"
+}
+
+/// Replace the version requirement of the dependencies on the given package, keeping the number
+/// of components of the old requirement (`0.1` is replaced by `0.2` and not by `0.2.0`).
+fn replace_dependency_version(manifest: &str, package_name: &str, new_version: &str) -> String {
+    let lines: Vec<String> = manifest
+        .lines()
+        .map(|line| {
+            let Some((key, value)) = line.split_once('=') else {
+                return line.to_string();
+            };
+
+            if key.trim() != package_name {
+                return line.to_string();
+            }
+
+            let Some(start) = value.find("version = \"") else {
+                return line.to_string();
+            };
+
+            let start = start + "version = \"".len();
+
+            let Some(len) = value[start..].find('"') else {
+                return line.to_string();
+            };
+
+            let number_of_components = value[start..start + len].split('.').count();
+
+            let new_requirement: Vec<&str> =
+                new_version.split('.').take(number_of_components).collect();
+
+            format!(
+                "{key}={}{}{}",
+                &value[..start],
+                new_requirement.join("."),
+                &value[start + len..]
+            )
+        })
+        .collect();
+
+    lines.join("\n") + "\n"
+}
+
+/// Get the inline TODO entries removed from the project since the last tag of the repository.
+fn resolved_todo_entries(root_path: &Path, project: &str) -> Result<Vec<InlineTodoEntry>> {
+    let last_tag = cmd_lib::run_fun! {
+        cd $root_path;
+        git describe --tags --abbrev=0
+    };
+
+    let Ok(last_tag) = last_tag else {
+        warn!("No tag found on the repository, the changelog will not list resolved entries");
+        return Ok(vec![]);
+    };
+
+    let project_path = root_path.join("projects").join(project);
+
+    let diff = cmd_lib::run_fun! {
+        cd $root_path;
+        git diff -U0 $last_tag -- $project_path
+    }?;
+
+    Ok(diff
+        .lines()
+        .filter(|line| !line.starts_with("---"))
+        .filter_map(|line| {
+            let line = line.strip_prefix('-')?.trim_start();
+            let comment = line.strip_prefix("//").or_else(|| line.strip_prefix('#'))?;
+
+            InlineTodoEntry::new(&CommentBlock {
+                path: project_path.clone(),
+                line_number: 0,
+                comment: vec![comment.to_string()],
+            })
+        })
+        .collect())
+}
+
+/// Prepend a stub of the changes of the new version to the changelog of the project.
+fn write_changelog_stub(
+    root_path: &Path,
+    project: &str,
+    new_version: &str,
+    resolved_entries: &[InlineTodoEntry],
+) -> Result<()> {
+    let changelog_path = root_path
+        .join("projects")
+        .join(project)
+        .join("CHANGELOG.md");
+
+    let mut stub = format!("## {new_version}\n\n_Describe the changes of this release._\n\n");
+
+    for entry in resolved_entries {
+        let kind = if entry.tags.iter().any(|tag| matches!(tag, Tag::Fix)) {
+            "Fixed"
+        } else {
+            "Resolved"
+        };
+
+        stub.push_str(&format!("- {kind}: {}\n", entry.title));
+    }
+
+    if !resolved_entries.is_empty() {
+        stub.push('\n');
+    }
+
+    let old_changelog = if changelog_path.exists() {
+        fs::read_to_string(&changelog_path)?
+    } else {
+        String::from("# Changelog\n\n")
+    };
+
+    // Keep the title of the changelog at the top
+    let (title, rest) = match old_changelog.split_once("\n\n") {
+        Some((title, rest)) if title.starts_with("# ") => (format!("{title}\n\n"), rest),
+        _ => (String::new(), old_changelog.as_str()),
+    };
+
+    fs::write(changelog_path, format!("{title}{stub}{rest}"))?;
+
+    Ok(())
+}
+
+/// Bump the version of a project (the name of its directory inside `//projects`), updating the
+/// requirements of the other projects of the workspace on it, regenerating the lockfile and
+/// adding a stub to its changelog.
+pub(crate) fn release(root_path: &Path, project: &str, bump: Bump) -> Result<()> {
+    let manifest_path = root_path.join("projects").join(project).join("Cargo.toml");
+
+    if !manifest_path.is_file() {
+        bail!("The project {project:?} is not a Rust crate of the workspace");
+    }
+
+    let manifest = fs::read_to_string(&manifest_path)?;
+    let (package_name, old_version) = package_name_and_version(&manifest)
+        .wrap_err("Unable to find the name and version of the package")?;
+
+    let new_version = bump.apply(&old_version)?;
+    info!("Bumping {package_name} from {old_version} to {new_version}");
+
+    fs::write(
+        &manifest_path,
+        replace_package_version(&manifest, &new_version),
+    )?;
+
+    // Update the projects that depend on it (like the workspace hack crate)
+    for entry in fs::read_dir(root_path.join("projects"))? {
+        let other_manifest_path = entry?.path().join("Cargo.toml");
+
+        if other_manifest_path == manifest_path || !other_manifest_path.is_file() {
+            continue;
+        }
+
+        let other_manifest = fs::read_to_string(&other_manifest_path)?;
+        let new_other_manifest =
+            replace_dependency_version(&other_manifest, &package_name, &new_version);
+
+        if new_other_manifest != other_manifest {
+            info!("Updating the requirement on {other_manifest_path:?}");
+            fs::write(&other_manifest_path, new_other_manifest)?;
+        }
+    }
+
+    info!("Regenerating the lockfile");
+    cmd_lib::run_cmd! {
+        cd $root_path;
+        cargo update --workspace
+    }?;
+
+    let resolved_entries = resolved_todo_entries(root_path, project)?;
+    write_changelog_stub(root_path, project, &new_version, &resolved_entries)?;
+
+    info!("Remember to review the changelog of the project before tagging the release");
+
+    Ok(())
+}
@@ -0,0 +1,281 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::license::{render_header, CommentStyle};
+use color_eyre::eyre::{bail, ContextCompat};
+use color_eyre::Result;
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// The Nix plumbing of the Rust crates, new crates are registered at the end of its packages.
+const NIX_PACKAGES_PATH: &str = "projects/forja+nix/rust/packages.fp.nix";
+
+/// The end of the last crate registered on [NIX_PACKAGES_PATH].
+const NIX_PACKAGES_END: &str = "      });\n\n    apps.";
+
+/// The kinds of crates that can be scaffolded.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ProjectKind {
+    Lib,
+    Cli,
+    Wasm,
+}
+
+impl ProjectKind {
+    pub(crate) fn new(name: &str) -> Option<Self> {
+        Some(match name {
+            "lib" => Self::Lib,
+            "cli" => Self::Cli,
+            "wasm" => Self::Wasm,
+
+            _ => return None,
+        })
+    }
+}
+
+/// Convert a `snake_case` name into `camelCase`, the naming used by the Nix packages.
+fn camel_case(name: &str) -> String {
+    let mut camel_case_name = String::new();
+    let mut uppercase_next = false;
+
+    for char in name.chars() {
+        if char == '_' {
+            uppercase_next = true;
+        } else if uppercase_next {
+            camel_case_name.extend(char.to_uppercase());
+            uppercase_next = false;
+        } else {
+            camel_case_name.push(char);
+        }
+    }
+
+    camel_case_name
+}
+
+fn cargo_manifest(name: &str, kind: ProjectKind) -> String {
+    let mut manifest = render_header(CommentStyle::Line("#"));
+
+    manifest.push_str(&format!(
+        "
+[package]
+version = \"0.1.0\"
+
+name = \"zelzip_{name}\"
+description = \"\"
+
+publish = {}
+
+authors.workspace = true
+license.workspace = true
+edition.workspace = true
+homepage.workspace = true
+repository.workspace = true
+",
+        !matches!(kind, ProjectKind::Cli)
+    ));
+
+    manifest.push_str(&match kind {
+        ProjectKind::Lib => format!(
+            "
+[lib]
+path = \"src/{name}.rs\"
+crate-type = [\"rlib\"]
+
+[dependencies]
+thiserror.workspace = true
+"
+        ),
+
+        ProjectKind::Cli => format!(
+            "
+[[bin]]
+name = \"{name}\"
+path = \"src/{name}.rs\"
+
+[dependencies]
+clap.workspace = true
+color-eyre.workspace = true
+tracing.workspace = true
+util.workspace = true
+"
+        ),
+
+        ProjectKind::Wasm => format!(
+            "
+[lib]
+path = \"src/{name}.rs\"
+crate-type = [\"cdylib\", \"rlib\"]
+
+[dependencies]
+thiserror.workspace = true
+wasm-bindgen.workspace = true
+derive_jserror.workspace = true
+"
+        ),
+    });
+
+    manifest.push_str(
+        "zelzip_workspace_hack = { version = \"0.1\", path = \"../workspace_hack+rust\" }
+
+[lints]
+workspace = true
+",
+    );
+
+    manifest
+}
+
+fn crate_root(kind: ProjectKind) -> String {
+    let mut source = render_header(CommentStyle::Line("//"));
+
+    source.push_str(match kind {
+        ProjectKind::Lib | ProjectKind::Wasm => {
+            "
+//! Describe the library.
+"
+        }
+
+        ProjectKind::Cli => {
+            "
+//! Describe the CLI.
+
+use clap::command;
+use color_eyre::Result;
+use util::setup_logging_for_cli;
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    setup_logging_for_cli();
+
+    let _matches = command!().get_matches();
+
+    Ok(())
+}
+"
+        }
+    });
+
+    source
+}
+
+fn readme_template(name: &str, kind: ProjectKind) -> String {
+    let mut template = render_header(CommentStyle::Line("#"));
+
+    let title = match kind {
+        ProjectKind::Cli => format!("ZELZIP {name} CLI"),
+        ProjectKind::Lib | ProjectKind::Wasm => format!("ZELZIP {name} Library"),
+    };
+
+    template.push_str(&format!(
+        "{{...}}: {{
+  title = \"{title}\";
+
+  links = {{}};
+
+  body =
+    # markdown
+    ''
+      Describe the project.
+    '';
+}}
+"
+    ));
+
+    template
+}
+
+fn nix_packages_entry(name: &str, kind: ProjectKind) -> String {
+    let include_projects = match kind {
+        ProjectKind::Cli => format!("\"{name}+rust\" \"util+rust\" \"workspace_hack+rust\""),
+        ProjectKind::Lib | ProjectKind::Wasm => format!("\"{name}+rust\" \"workspace_hack+rust\""),
+    };
+
+    let is_cli = matches!(kind, ProjectKind::Cli);
+
+    format!(
+        "      }})
+      // (crane.makeCratePackages {{
+        nixPackageName = \"{}\";
+        cargoPackageName = \"zelzip_{name}\";
+        includeProjects = [{include_projects}];
+        hasBin = {is_cli};
+        hasLib = {};
+",
+        camel_case(name),
+        !is_cli
+    )
+}
+
+/// Create a new Rust crate on `//projects/<name>+rust` following the conventions of the
+/// monorepo, registered on the workspace hack crate and on the Nix build system.
+pub(crate) fn new_project(root_path: &Path, name: &str, kind: ProjectKind) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|char| char.is_ascii_lowercase() || char.is_ascii_digit() || char == '_')
+    {
+        bail!("The name of the project must be in snake_case: {name:?}");
+    }
+
+    let project_path = root_path.join("projects").join(format!("{name}+rust"));
+
+    if project_path.exists() {
+        bail!("The project already exists: {project_path:?}");
+    }
+
+    let nix_packages_path = root_path.join(NIX_PACKAGES_PATH);
+    let nix_packages = fs::read_to_string(&nix_packages_path)?;
+
+    let nix_packages_end = nix_packages
+        .find(NIX_PACKAGES_END)
+        .wrap_err("Unable to find where to register the crate on the Nix build system")?;
+
+    info!("Creating the project on {project_path:?}");
+
+    fs::create_dir_all(project_path.join("src"))?;
+
+    fs::write(project_path.join("Cargo.toml"), cargo_manifest(name, kind))?;
+    fs::write(
+        project_path.join("src").join(format!("{name}.rs")),
+        crate_root(kind),
+    )?;
+    fs::write(
+        project_path.join("README.md.template.nix"),
+        readme_template(name, kind),
+    )?;
+    fs::write(
+        project_path.join("TODO.md"),
+        format!("# TODO ({name}+rust)\n"),
+    )?;
+
+    info!("Registering the crate on the Nix build system");
+
+    let (start, end) = nix_packages.split_at(nix_packages_end);
+    let new_nix_packages = format!(
+        "{start}{}{}",
+        nix_packages_entry(name, kind),
+        // The old end of the last crate is now the end of the new one
+        end
+    );
+    fs::write(&nix_packages_path, new_nix_packages)?;
+
+    info!("Registering the crate on the workspace hack crate");
+
+    let hakari_result = cmd_lib::run_cmd! {
+        cd $root_path;
+        cargo hakari generate;
+        cargo hakari manage-deps --yes;
+    };
+
+    if let Err(error) = hakari_result {
+        warn!("Unable to run `cargo hakari`, run `forja fix` to register the crate: {error}");
+    }
+
+    info!("Run `forja gen` to generate the README of the project");
+
+    Ok(())
+}
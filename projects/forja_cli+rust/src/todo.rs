@@ -8,8 +8,8 @@
 // should be moved to a extra lib & CLI (with a passthrough to Forja CLI)
 // and properly documented for third-party usage.
 
-mod comment_block;
-mod inline_todo_entry;
+pub(crate) mod comment_block;
+pub(crate) mod inline_todo_entry;
 mod resource;
 
 use crate::todo::comment_block::CommentBlock;
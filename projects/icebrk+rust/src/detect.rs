@@ -0,0 +1,106 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{calculate_v1_master_key, calculate_v2_master_key, calculate_v3_master_key, Platform};
+use wasm_bindgen::prelude::*;
+
+/// The versions of the algorithm used to generate the parental control master key.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlgorithmVersion {
+    /// See [calculate_v0_master_key](crate::calculate_v0_master_key).
+    V0,
+
+    /// See [calculate_v1_master_key].
+    V1,
+
+    /// See [calculate_v2_master_key].
+    V2,
+
+    /// See [calculate_v3_master_key].
+    V3,
+}
+
+/// Detect the version of the algorithm that must be used for the given inquiry number, by
+/// looking at its length and the region and version digits encoded in it. `None` if no known
+/// algorithm (or no known key) is applicable.
+///
+/// The inquiry numbers of the v1 algorithm don't encode any version, so on the 3DS they are only
+/// detected if the digits at the position of the version are not used by any known v2 key.
+#[wasm_bindgen]
+pub fn detect_algorithm(platform: Platform, inquiry_number: u64) -> Option<AlgorithmVersion> {
+    if inquiry_number > 9_999_999_999 {
+        return None;
+    }
+
+    // Only the v0 algorithm uses inquiry numbers of 8 digits
+    let is_v0_inquiry_number = inquiry_number <= 99_999_999;
+
+    // The date is not used to select the keys
+    match platform {
+        Platform::Wii | Platform::Dsi => is_v0_inquiry_number.then_some(AlgorithmVersion::V0),
+
+        _ if is_v0_inquiry_number && platform != Platform::Switch => Some(AlgorithmVersion::V0),
+
+        Platform::WiiU => calculate_v2_master_key(Platform::WiiU, inquiry_number, 1, 1)
+            .is_ok()
+            .then_some(AlgorithmVersion::V2),
+
+        Platform::The3ds => {
+            if calculate_v2_master_key(Platform::The3ds, inquiry_number, 1, 1).is_ok() {
+                Some(AlgorithmVersion::V2)
+            } else {
+                calculate_v1_master_key(inquiry_number, 1, 1)
+                    .is_ok()
+                    .then_some(AlgorithmVersion::V1)
+            }
+        }
+
+        Platform::Switch => calculate_v3_master_key(inquiry_number)
+            .is_ok()
+            .then_some(AlgorithmVersion::V3),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_v0() {
+        assert_eq!(
+            detect_algorithm(Platform::Wii, 84293062),
+            Some(AlgorithmVersion::V0)
+        );
+        assert_eq!(
+            detect_algorithm(Platform::The3ds, 84293062),
+            Some(AlgorithmVersion::V0)
+        );
+        assert_eq!(detect_algorithm(Platform::Wii, 1123456789), None);
+    }
+
+    #[test]
+    fn detect_v1_and_v2() {
+        assert_eq!(
+            detect_algorithm(Platform::The3ds, 1123456789),
+            Some(AlgorithmVersion::V2)
+        );
+        assert_eq!(
+            detect_algorithm(Platform::The3ds, 1993456789),
+            Some(AlgorithmVersion::V1)
+        );
+        assert_eq!(detect_algorithm(Platform::WiiU, 9123456789), None);
+    }
+
+    #[test]
+    fn detect_v3() {
+        assert_eq!(
+            detect_algorithm(Platform::Switch, 1034567890),
+            Some(AlgorithmVersion::V3)
+        );
+        assert_eq!(detect_algorithm(Platform::Switch, 1934567890), None);
+    }
+}
@@ -0,0 +1,163 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use derive_jserror::JsError;
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+// The firmware versions that introduced a new version byte (a new set of keys) for the v2
+// algorithm, sorted by firmware version. A firmware uses the version byte of the last entry
+// introduced at or before it.
+// TODO(DISCOVER): Only the version byte of the first firmware with the v2 algorithm is known, the
+// later firmware versions are mapped to it until the updates that changed it are found
+const THE_3DS_VERSION_BYTES: &[((u8, u8, u8), u8)] = &[((7, 2, 0), 0x0A)];
+
+#[derive(Error, JsError, Debug)]
+#[allow(missing_docs)]
+pub enum FirmwareVersionError {
+    #[error("Invalid firmware version string: {0}")]
+    InvalidFormat(String),
+}
+
+/// A firmware version of the 3DS as shown on the System Settings (`11.15.0-47U`).
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct The3dsFirmwareVersion {
+    /// The major version (`11` on `11.15.0-47U`).
+    pub major: u8,
+
+    /// The minor version (`15` on `11.15.0-47U`).
+    pub minor: u8,
+
+    /// The micro version (`0` on `11.15.0-47U`).
+    pub micro: u8,
+
+    /// The version of the NVer title (`47` on `11.15.0-47U`).
+    pub nver: u8,
+}
+
+#[wasm_bindgen]
+impl The3dsFirmwareVersion {
+    /// Parse a firmware version string, the `Ver.` prefix and the region letter suffix are
+    /// optional (`Ver. 11.15.0-47U`, `11.15.0-47`).
+    pub fn parse(version: &str) -> Result<Self, FirmwareVersionError> {
+        let invalid_format = || FirmwareVersionError::InvalidFormat(version.to_string());
+
+        let trimmed_version = version.trim();
+        let trimmed_version = trimmed_version
+            .strip_prefix("Ver.")
+            .unwrap_or(trimmed_version)
+            .trim_start()
+            .trim_end_matches(|char: char| char.is_ascii_alphabetic());
+
+        let (system_version, nver) = trimmed_version.split_once('-').ok_or_else(invalid_format)?;
+
+        let mut numbers = system_version
+            .split('.')
+            .map(|number| number.parse::<u8>().map_err(|_| invalid_format()));
+
+        let mut next_number = || numbers.next().unwrap_or_else(|| Err(invalid_format()));
+
+        let firmware_version = Self {
+            major: next_number()?,
+            minor: next_number()?,
+            micro: next_number()?,
+            nver: nver.parse().map_err(|_| invalid_format())?,
+        };
+
+        if numbers.next().is_some() {
+            return Err(invalid_format());
+        }
+
+        Ok(firmware_version)
+    }
+
+    /// Get the version byte that the inquiry numbers of this firmware version encode (used by
+    /// [calculate_v2_master_key](crate::calculate_v2_master_key)), `None` if the firmware doesn't
+    /// use the v2 algorithm (released before `7.2.0`).
+    pub fn version_byte(&self) -> Option<u8> {
+        let system_version = (self.major, self.minor, self.micro);
+
+        THE_3DS_VERSION_BYTES
+            .iter()
+            .rev()
+            .find(|(firmware_version, _)| *firmware_version <= system_version)
+            .map(|(_, version_byte)| *version_byte)
+    }
+
+    /// Check if the given inquiry number was generated on this firmware version, `None` if the
+    /// firmware doesn't use the v2 algorithm.
+    pub fn matches_inquiry_number(&self, inquiry_number: u64) -> Option<bool> {
+        self.version_byte()
+            .map(|version_byte| version_byte == inquiry_number_version_byte(inquiry_number))
+    }
+}
+
+/// Get the version byte encoded in a v2 inquiry number of the 3DS (the second and third most
+/// significant digits).
+#[wasm_bindgen]
+pub fn inquiry_number_version_byte(inquiry_number: u64) -> u8 {
+    ((inquiry_number / 10_000_000) % 100) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_firmware_version() {
+        let expected_version = The3dsFirmwareVersion {
+            major: 11,
+            minor: 15,
+            micro: 0,
+            nver: 47,
+        };
+
+        assert_eq!(
+            The3dsFirmwareVersion::parse("11.15.0-47").unwrap(),
+            expected_version
+        );
+        assert_eq!(
+            The3dsFirmwareVersion::parse("Ver. 11.15.0-47U").unwrap(),
+            expected_version
+        );
+
+        assert!(The3dsFirmwareVersion::parse("11.15-47").is_err());
+        assert!(The3dsFirmwareVersion::parse("11.15.0.1-47").is_err());
+    }
+
+    #[test]
+    fn version_byte() {
+        let firmware_version = The3dsFirmwareVersion::parse("7.2.0-17E").unwrap();
+
+        assert_eq!(firmware_version.version_byte(), Some(0x0A));
+        assert_eq!(
+            firmware_version.matches_inquiry_number(2103456789),
+            Some(true)
+        );
+        assert_eq!(
+            firmware_version.matches_inquiry_number(2113456789),
+            Some(false)
+        );
+
+        // Firmware versions between the entries use the last version byte released before them
+        for version in ["7.2.1-17U", "8.0.0-18E", "11.15.0-47", "Ver. 11.17.0-50J"] {
+            assert_eq!(
+                The3dsFirmwareVersion::parse(version)
+                    .unwrap()
+                    .version_byte(),
+                Some(0x0A)
+            );
+        }
+
+        for version in ["7.1.0-16U", "6.3.0-12E", "1.0.0-0J"] {
+            let firmware_version = The3dsFirmwareVersion::parse(version).unwrap();
+
+            assert_eq!(firmware_version.version_byte(), None);
+            assert_eq!(firmware_version.matches_inquiry_number(2103456789), None);
+        }
+    }
+}
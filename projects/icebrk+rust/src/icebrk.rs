@@ -12,6 +12,10 @@ use wasm_bindgen::prelude::*;
 
 type HmacSha256 = Hmac<Sha256>;
 
+mod detect;
+mod firmware;
+#[cfg(feature = "research")]
+pub mod research;
 mod v0;
 mod v1;
 mod v2;
@@ -37,11 +41,20 @@ pub enum Platform {
     Switch,
 }
 
+pub use detect::{detect_algorithm, AlgorithmVersion};
+pub use firmware::{inquiry_number_version_byte, FirmwareVersionError, The3dsFirmwareVersion};
 pub use v0::calculate_v0_master_key;
 pub use v1::{calculate_v1_master_key, V1Error};
-pub use v2::{calculate_v2_master_key, V2Error};
+pub use v2::{calculate_v2_master_key, known_v2_regions, known_v2_versions, V2Error};
 pub use v3::{calculate_v3_master_key, V3Error};
 
+/// Build the message signed with HMAC by the v1 and v2 algorithms.
+fn hmac_input_v1_and_v2(inquiry_number: u64, day: u8, month: u8) -> String {
+    // The month and day with a leading zero when the number is not two digits long
+    // and the inquiry number (also padded with zeroes)
+    format!("{month:0>2}{day:0>2}{inquiry_number:0>10}")
+}
+
 fn calculate_master_key_shared_v1_and_v2(
     hmac_key: &[u8; 32],
     inquiry_number: u64,
@@ -49,9 +62,7 @@ fn calculate_master_key_shared_v1_and_v2(
     month: u8,
     big_endian: bool,
 ) -> u32 {
-    // The month and day with a leading zero when the number is not two digits long
-    // and the inquiry number (also padded with zeroes)
-    let input = format!("{month:0>2}{day:0>2}{inquiry_number:0>10}");
+    let input = hmac_input_v1_and_v2(inquiry_number, day, month);
 
     #[allow(clippy::expect_used)]
     let mut hmac = HmacSha256::new_from_slice(hmac_key).expect("Invalid lenght of the key");
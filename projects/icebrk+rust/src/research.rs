@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Internals of the algorithms, useful to debug newly found keys or to add support for missing
+//! combinations of regions and versions. Only available with the `research` feature, there are
+//! no stability guarantees.
+
+use crate::{detect_algorithm, AlgorithmVersion, Platform};
+
+/// The keys selected by an algorithm for a given inquiry number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlgorithmKeys {
+    /// The version of the algorithm, see [detect_algorithm].
+    pub algorithm: AlgorithmVersion,
+
+    /// The region encoded inside the inquiry number, if used to select the keys.
+    pub region: Option<u64>,
+
+    /// The version encoded inside the inquiry number, if used to select the keys.
+    pub version: Option<u64>,
+
+    /// The name of the file with the HMAC key (encrypted on the v2 algorithm), `None` on the v0
+    /// algorithm.
+    pub hmac_key: Option<String>,
+
+    /// The name of the file with the AES key used to decrypt the HMAC key, only on the v2
+    /// algorithm.
+    pub aes_key: Option<String>,
+}
+
+/// Get the exact message used by the algorithm detected for the given inquiry number, the one
+/// whose checksum is calculated on the v0 algorithm and the one signed with HMAC on the others.
+/// `None` if no known algorithm is applicable.
+///
+/// The date is not validated.
+pub fn algorithm_input(
+    platform: Platform,
+    inquiry_number: u64,
+    day: u8,
+    month: u8,
+) -> Option<String> {
+    Some(match detect_algorithm(platform, inquiry_number)? {
+        // Only inquiry numbers of 8 digits are detected as v0
+        AlgorithmVersion::V0 => crate::v0::crc_input(inquiry_number as u32, day, month),
+        AlgorithmVersion::V1 | AlgorithmVersion::V2 => {
+            crate::hmac_input_v1_and_v2(inquiry_number, day, month)
+        }
+        AlgorithmVersion::V3 => crate::v3::hmac_input(inquiry_number),
+    })
+}
+
+/// Get the keys selected by the algorithm detected for the given inquiry number, named like
+/// the files where they are stored on the source code. `None` if no known algorithm (or no
+/// known key) is applicable.
+pub fn algorithm_keys(platform: Platform, inquiry_number: u64) -> Option<AlgorithmKeys> {
+    let is_wii_u = platform == Platform::WiiU;
+
+    let algorithm = detect_algorithm(platform, inquiry_number)?;
+
+    Some(match algorithm {
+        AlgorithmVersion::V0 => AlgorithmKeys {
+            algorithm,
+            region: None,
+            version: None,
+            hmac_key: None,
+            aes_key: None,
+        },
+
+        AlgorithmVersion::V1 => {
+            let region = inquiry_number / 1_000_000_000;
+
+            AlgorithmKeys {
+                algorithm,
+                region: Some(region),
+                version: None,
+                hmac_key: Some(format!("3ds_hmac_key_region_{region:02x}.bin")),
+                aes_key: None,
+            }
+        }
+
+        AlgorithmVersion::V2 if is_wii_u => {
+            let region = inquiry_number / 1_000_000_000;
+
+            AlgorithmKeys {
+                algorithm,
+                region: Some(region),
+                version: None,
+                hmac_key: Some(format!("wii_u_hmac_key_region_{region:02x}.bin.enc")),
+                aes_key: Some(format!("wii_u_aes_key_region_{region:02x}.bin")),
+            }
+        }
+
+        AlgorithmVersion::V2 => {
+            let region = inquiry_number / 1_000_000_000;
+            let version = (inquiry_number / 10_000_000) % 100;
+
+            // The same AES key is shared between both regions
+            let aes_key = if region == 0x00 || region == 0x09 {
+                "3ds_aes_key_region_00_and_09.bin".to_string()
+            } else {
+                format!("3ds_aes_key_region_{region:02x}.bin")
+            };
+
+            AlgorithmKeys {
+                algorithm,
+                region: Some(region),
+                version: Some(version),
+                hmac_key: Some(format!(
+                    "3ds_hmac_key_region_{region:02x}_version_{version:02x}.bin.enc"
+                )),
+                aes_key: Some(aes_key),
+            }
+        }
+
+        AlgorithmVersion::V3 => {
+            let version = (inquiry_number / 100_000_000) % 100;
+
+            AlgorithmKeys {
+                algorithm,
+                region: None,
+                version: Some(version),
+                hmac_key: Some(format!("switch_hmac_key_version_{version:02X}.bin")),
+                aes_key: None,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs() {
+        assert_eq!(
+            algorithm_input(Platform::Wii, 84293062, 5, 3).as_deref(),
+            Some("03053062")
+        );
+        assert_eq!(
+            algorithm_input(Platform::The3ds, 1123456789, 5, 3).as_deref(),
+            Some("03051123456789")
+        );
+        assert_eq!(
+            algorithm_input(Platform::Switch, 1034567890, 5, 3).as_deref(),
+            Some("1034567890")
+        );
+        assert_eq!(algorithm_input(Platform::Wii, 1123456789, 5, 3), None);
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            algorithm_keys(Platform::The3ds, 1123456789),
+            Some(AlgorithmKeys {
+                algorithm: AlgorithmVersion::V2,
+                region: Some(1),
+                version: Some(12),
+                hmac_key: Some("3ds_hmac_key_region_01_version_0c.bin.enc".to_string()),
+                aes_key: Some("3ds_aes_key_region_01.bin".to_string()),
+            })
+        );
+        assert_eq!(
+            algorithm_keys(Platform::The3ds, 1993456789).and_then(|keys| keys.hmac_key),
+            Some("3ds_hmac_key_region_01.bin".to_string())
+        );
+        assert_eq!(
+            algorithm_keys(Platform::Switch, 1034567890).and_then(|keys| keys.hmac_key),
+            Some("switch_hmac_key_version_0A.bin".to_string())
+        );
+        assert_eq!(algorithm_keys(Platform::Switch, 1934567890), None);
+    }
+}
@@ -49,6 +49,13 @@ const fn get_crc(platform: Platform) -> (&'static crc::Algorithm<u32>, u32) {
     }
 }
 
+/// Build the message whose checksum is calculated by the v0 algorithm.
+pub(crate) fn crc_input(inquiry_number: u32, day: u8, month: u8) -> String {
+    // The month and day with a leading zero when the number is not two digits long
+    // and the last four digits of the inquiry number (also padded with zeroes)
+    format!("{month:0>2}{day:0>2}{:0>4}", inquiry_number % 10000)
+}
+
 /// Calculate the master key for the parental control using the v0 algorithm. The inquire number
 /// cannot be bigger than 8 digits and the date must be valid (there are some loose checks).
 ///
@@ -68,9 +75,7 @@ pub fn calculate_v0_master_key(platform: Platform, inquiry_number: u32, day: u8,
 
     let (algorithm, addout) = get_crc(platform);
 
-    // The month and day with a leading zero when the number is not two digits long
-    // and the last four digits of the inquiry number (also padded with zeroes)
-    let input = format!("{month:0>2}{day:0>2}{:0>4}", inquiry_number % 10000);
+    let input = crc_input(inquiry_number, day, month);
 
     let crc = crc::Crc::<u32>::new(algorithm);
     let checksum = (crc.checksum(input.as_bytes())) + addout;
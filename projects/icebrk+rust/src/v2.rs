@@ -287,6 +287,145 @@ const WII_U_HMAC_KEY_ENC_REGION_01: &[u8] = include_bytes!("v2/wii_u_hmac_key_re
 const WII_U_HMAC_KEY_ENC_REGION_02: &[u8] = include_bytes!("v2/wii_u_hmac_key_region_02.bin.enc");
 const WII_U_HMAC_KEY_ENC_REGION_03: &[u8] = include_bytes!("v2/wii_u_hmac_key_region_03.bin.enc");
 
+/// The encrypted HMAC keys of the 3DS indexed by the region and version encoded inside the
+/// inquiry number.
+const THE_3DS_HMAC_KEYS_ENC: &[(u64, u64, &[u8])] = &[
+    (0x00, 0x0A, THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0A),
+    (0x00, 0x0B, THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0B),
+    (0x00, 0x0C, THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0C),
+    (0x00, 0x0D, THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0D),
+    (0x00, 0x0E, THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0E),
+    (0x00, 0x0F, THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0F),
+    (0x00, 0x10, THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_10),
+    (0x00, 0x11, THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_11),
+    (0x01, 0x0A, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0A),
+    (0x01, 0x0B, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0B),
+    (0x01, 0x0C, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0C),
+    (0x01, 0x0D, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0D),
+    (0x01, 0x0E, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0E),
+    (0x01, 0x0F, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0F),
+    (0x01, 0x1A, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1A),
+    (0x01, 0x1B, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1B),
+    (0x01, 0x1C, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1C),
+    (0x01, 0x1D, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1D),
+    (0x01, 0x1E, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1E),
+    (0x01, 0x1F, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1F),
+    (0x01, 0x2A, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_2A),
+    (0x01, 0x2B, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_2B),
+    (0x01, 0x10, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_10),
+    (0x01, 0x11, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_11),
+    (0x01, 0x12, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_12),
+    (0x01, 0x13, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_13),
+    (0x01, 0x14, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_14),
+    (0x01, 0x15, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_15),
+    (0x01, 0x16, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_16),
+    (0x01, 0x17, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_17),
+    (0x01, 0x18, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_18),
+    (0x01, 0x19, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_19),
+    (0x01, 0x20, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_20),
+    (0x01, 0x21, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_21),
+    (0x01, 0x22, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_22),
+    (0x01, 0x23, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_23),
+    (0x01, 0x24, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_24),
+    (0x01, 0x25, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_25),
+    (0x01, 0x26, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_26),
+    (0x01, 0x27, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_27),
+    (0x01, 0x28, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_28),
+    (0x01, 0x29, THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_29),
+    (0x02, 0x0A, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0A),
+    (0x02, 0x0B, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0B),
+    (0x02, 0x0C, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0C),
+    (0x02, 0x0D, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0D),
+    (0x02, 0x0E, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0E),
+    (0x02, 0x0F, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0F),
+    (0x02, 0x1A, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1A),
+    (0x02, 0x1B, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1B),
+    (0x02, 0x1C, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1C),
+    (0x02, 0x1D, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1D),
+    (0x02, 0x1E, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1E),
+    (0x02, 0x1F, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1F),
+    (0x02, 0x2A, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_2A),
+    (0x02, 0x2B, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_2B),
+    (0x02, 0x10, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_10),
+    (0x02, 0x11, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_11),
+    (0x02, 0x12, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_12),
+    (0x02, 0x13, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_13),
+    (0x02, 0x14, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_14),
+    (0x02, 0x15, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_15),
+    (0x02, 0x16, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_16),
+    (0x02, 0x17, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_17),
+    (0x02, 0x18, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_18),
+    (0x02, 0x19, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_19),
+    (0x02, 0x20, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_20),
+    (0x02, 0x21, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_21),
+    (0x02, 0x22, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_22),
+    (0x02, 0x23, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_23),
+    (0x02, 0x24, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_24),
+    (0x02, 0x25, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_25),
+    (0x02, 0x26, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_26),
+    (0x02, 0x27, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_27),
+    (0x02, 0x28, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_28),
+    (0x02, 0x29, THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_29),
+    (0x05, 0x1A, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1A),
+    (0x05, 0x1B, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1B),
+    (0x05, 0x1C, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1C),
+    (0x05, 0x1D, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1D),
+    (0x05, 0x1E, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1E),
+    (0x05, 0x1F, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1F),
+    (0x05, 0x2A, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_2A),
+    (0x05, 0x12, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_12),
+    (0x05, 0x13, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_13),
+    (0x05, 0x14, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_14),
+    (0x05, 0x15, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_15),
+    (0x05, 0x16, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_16),
+    (0x05, 0x17, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_17),
+    (0x05, 0x18, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_18),
+    (0x05, 0x19, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_19),
+    (0x05, 0x20, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_20),
+    (0x05, 0x21, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_21),
+    (0x05, 0x22, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_22),
+    (0x05, 0x23, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_23),
+    (0x05, 0x24, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_24),
+    (0x05, 0x25, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_25),
+    (0x05, 0x26, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_26),
+    (0x05, 0x27, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_27),
+    (0x05, 0x28, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_28),
+    (0x05, 0x29, THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_29),
+    (0x09, 0x1A, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1A),
+    (0x09, 0x1B, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1B),
+    (0x09, 0x1C, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1C),
+    (0x09, 0x1D, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1D),
+    (0x09, 0x1E, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1E),
+    (0x09, 0x1F, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1F),
+    (0x09, 0x2A, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_2A),
+    (0x09, 0x2B, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_2B),
+    (0x09, 0x12, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_12),
+    (0x09, 0x13, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_13),
+    (0x09, 0x14, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_14),
+    (0x09, 0x15, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_15),
+    (0x09, 0x16, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_16),
+    (0x09, 0x17, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_17),
+    (0x09, 0x18, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_18),
+    (0x09, 0x19, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_19),
+    (0x09, 0x20, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_20),
+    (0x09, 0x21, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_21),
+    (0x09, 0x22, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_22),
+    (0x09, 0x23, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_23),
+    (0x09, 0x24, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_24),
+    (0x09, 0x25, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_25),
+    (0x09, 0x26, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_26),
+    (0x09, 0x27, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_27),
+    (0x09, 0x28, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_28),
+    (0x09, 0x29, THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_29),
+];
+
+/// The encrypted HMAC keys of the Wii U indexed by the region encoded inside the inquiry number.
+const WII_U_HMAC_KEYS_ENC: &[(u64, &[u8])] = &[
+    (0x01, WII_U_HMAC_KEY_ENC_REGION_01),
+    (0x02, WII_U_HMAC_KEY_ENC_REGION_02),
+    (0x03, WII_U_HMAC_KEY_ENC_REGION_03),
+];
+
 #[derive(Error, JsError, Debug)]
 #[allow(missing_docs)]
 pub enum V2Error {
@@ -350,149 +489,18 @@ pub fn calculate_v2_master_key(
     .expect("The v2 AES key is not long enough");
 
     let hmac_enc = match platform {
-        Platform::WiiU => match region {
-            0x01 => WII_U_HMAC_KEY_ENC_REGION_01,
-            0x02 => WII_U_HMAC_KEY_ENC_REGION_02,
-            0x03 => WII_U_HMAC_KEY_ENC_REGION_03,
+        Platform::WiiU => WII_U_HMAC_KEYS_ENC
+            .iter()
+            .find(|(key_region, _)| *key_region == region)
+            .map(|(_, hmac_enc)| *hmac_enc)
+            .ok_or(V2Error::UnknownRegion(region))?,
+
+        Platform::The3ds => THE_3DS_HMAC_KEYS_ENC
+            .iter()
+            .find(|(key_region, key_version, _)| (*key_region, *key_version) == (region, version))
+            .map(|(_, _, hmac_enc)| *hmac_enc)
+            .ok_or(V2Error::UnknownRegionOrVersion(region, version))?,
 
-            _ => return Err(V2Error::UnknownRegion(region)),
-        },
-
-        Platform::The3ds => match (region, version) {
-            (0x00, 0x0A) => THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0A,
-            (0x00, 0x0B) => THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0B,
-            (0x00, 0x0C) => THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0C,
-            (0x00, 0x0D) => THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0D,
-            (0x00, 0x0E) => THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0E,
-            (0x00, 0x0F) => THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_0F,
-            (0x00, 0x10) => THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_10,
-            (0x00, 0x11) => THE_3DS_HMAC_KEY_ENC_REGION_00_VERSION_11,
-
-            (0x01, 0x0A) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0A,
-            (0x01, 0x0B) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0B,
-            (0x01, 0x0C) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0C,
-            (0x01, 0x0D) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0D,
-            (0x01, 0x0E) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0E,
-            (0x01, 0x0F) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_0F,
-            (0x01, 0x1A) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1A,
-            (0x01, 0x1B) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1B,
-            (0x01, 0x1C) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1C,
-            (0x01, 0x1D) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1D,
-            (0x01, 0x1E) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1E,
-            (0x01, 0x1F) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_1F,
-            (0x01, 0x2A) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_2A,
-            (0x01, 0x2B) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_2B,
-            (0x01, 0x10) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_10,
-            (0x01, 0x11) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_11,
-            (0x01, 0x12) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_12,
-            (0x01, 0x13) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_13,
-            (0x01, 0x14) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_14,
-            (0x01, 0x15) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_15,
-            (0x01, 0x16) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_16,
-            (0x01, 0x17) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_17,
-            (0x01, 0x18) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_18,
-            (0x01, 0x19) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_19,
-            (0x01, 0x20) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_20,
-            (0x01, 0x21) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_21,
-            (0x01, 0x22) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_22,
-            (0x01, 0x23) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_23,
-            (0x01, 0x24) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_24,
-            (0x01, 0x25) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_25,
-            (0x01, 0x26) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_26,
-            (0x01, 0x27) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_27,
-            (0x01, 0x28) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_28,
-            (0x01, 0x29) => THE_3DS_HMAC_KEY_ENC_REGION_01_VERSION_29,
-
-            (0x02, 0x0A) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0A,
-            (0x02, 0x0B) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0B,
-            (0x02, 0x0C) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0C,
-            (0x02, 0x0D) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0D,
-            (0x02, 0x0E) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0E,
-            (0x02, 0x0F) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_0F,
-            (0x02, 0x1A) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1A,
-            (0x02, 0x1B) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1B,
-            (0x02, 0x1C) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1C,
-            (0x02, 0x1D) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1D,
-            (0x02, 0x1E) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1E,
-            (0x02, 0x1F) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_1F,
-            (0x02, 0x2A) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_2A,
-            (0x02, 0x2B) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_2B,
-            (0x02, 0x10) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_10,
-            (0x02, 0x11) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_11,
-            (0x02, 0x12) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_12,
-            (0x02, 0x13) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_13,
-            (0x02, 0x14) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_14,
-            (0x02, 0x15) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_15,
-            (0x02, 0x16) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_16,
-            (0x02, 0x17) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_17,
-            (0x02, 0x18) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_18,
-            (0x02, 0x19) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_19,
-            (0x02, 0x20) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_20,
-            (0x02, 0x21) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_21,
-            (0x02, 0x22) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_22,
-            (0x02, 0x23) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_23,
-            (0x02, 0x24) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_24,
-            (0x02, 0x25) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_25,
-            (0x02, 0x26) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_26,
-            (0x02, 0x27) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_27,
-            (0x02, 0x28) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_28,
-            (0x02, 0x29) => THE_3DS_HMAC_KEY_ENC_REGION_02_VERSION_29,
-
-            (0x05, 0x1A) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1A,
-            (0x05, 0x1B) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1B,
-            (0x05, 0x1C) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1C,
-            (0x05, 0x1D) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1D,
-            (0x05, 0x1E) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1E,
-            (0x05, 0x1F) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_1F,
-            (0x05, 0x2A) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_2A,
-            (0x05, 0x12) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_12,
-            (0x05, 0x13) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_13,
-            (0x05, 0x14) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_14,
-            (0x05, 0x15) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_15,
-            (0x05, 0x16) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_16,
-            (0x05, 0x17) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_17,
-            (0x05, 0x18) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_18,
-            (0x05, 0x19) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_19,
-            (0x05, 0x20) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_20,
-            (0x05, 0x21) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_21,
-            (0x05, 0x22) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_22,
-            (0x05, 0x23) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_23,
-            (0x05, 0x24) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_24,
-            (0x05, 0x25) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_25,
-            (0x05, 0x26) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_26,
-            (0x05, 0x27) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_27,
-            (0x05, 0x28) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_28,
-            (0x05, 0x29) => THE_3DS_HMAC_KEY_ENC_REGION_05_VERSION_29,
-
-            (0x09, 0x1A) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1A,
-            (0x09, 0x1B) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1B,
-            (0x09, 0x1C) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1C,
-            (0x09, 0x1D) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1D,
-            (0x09, 0x1E) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1E,
-            (0x09, 0x1F) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_1F,
-            (0x09, 0x2A) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_2A,
-            (0x09, 0x2B) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_2B,
-            (0x09, 0x12) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_12,
-            (0x09, 0x13) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_13,
-            (0x09, 0x14) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_14,
-            (0x09, 0x15) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_15,
-            (0x09, 0x16) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_16,
-            (0x09, 0x17) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_17,
-            (0x09, 0x18) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_18,
-            (0x09, 0x19) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_19,
-            (0x09, 0x20) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_20,
-            (0x09, 0x21) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_21,
-            (0x09, 0x22) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_22,
-            (0x09, 0x23) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_23,
-            (0x09, 0x24) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_24,
-            (0x09, 0x25) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_25,
-            (0x09, 0x26) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_26,
-            (0x09, 0x27) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_27,
-            (0x09, 0x28) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_28,
-            (0x09, 0x29) => THE_3DS_HMAC_KEY_ENC_REGION_09_VERSION_29,
-
-            _ => return Err(V2Error::UnknownRegionOrVersion(region, version)),
-        },
         _ => panic!("The v2 algorithm is only available on the 3DS and the Wii U platforms"),
     };
 
@@ -518,4 +526,83 @@ pub fn calculate_v2_master_key(
     ))
 }
 
-// NOTE: No tests because making all combinations would be insane
+/// Get the regions encoded inside the inquiry number with known keys for the v2 algorithm on the
+/// given platform, sorted. Empty on the platforms without the v2 algorithm.
+#[wasm_bindgen]
+pub fn known_v2_regions(platform: Platform) -> Vec<u64> {
+    let mut regions: Vec<u64> = match platform {
+        Platform::WiiU => WII_U_HMAC_KEYS_ENC
+            .iter()
+            .map(|(region, _)| *region)
+            .collect(),
+        Platform::The3ds => THE_3DS_HMAC_KEYS_ENC
+            .iter()
+            .map(|(region, _, _)| *region)
+            .collect(),
+
+        _ => vec![],
+    };
+
+    regions.sort_unstable();
+    regions.dedup();
+
+    regions
+}
+
+/// Get the versions encoded inside the inquiry number with known keys for the v2 algorithm on the
+/// given platform and region, sorted. Empty if the region is not known.
+///
+/// `None` on the Wii U, where the version is not used to select the keys (any version works for
+/// the [known regions](known_v2_regions)).
+#[wasm_bindgen]
+pub fn known_v2_versions(platform: Platform, region: u64) -> Option<Vec<u64>> {
+    let mut versions: Vec<u64> = match platform {
+        Platform::WiiU => return None,
+        Platform::The3ds => THE_3DS_HMAC_KEYS_ENC
+            .iter()
+            .filter(|(key_region, _, _)| *key_region == region)
+            .map(|(_, version, _)| *version)
+            .collect(),
+
+        _ => vec![],
+    };
+
+    versions.sort_unstable();
+
+    Some(versions)
+}
+
+// NOTE: Only the catalog of keys is tested, testing all the combinations of the algorithm would be
+// insane
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_regions() {
+        assert_eq!(
+            known_v2_regions(Platform::The3ds),
+            [0x00, 0x01, 0x02, 0x05, 0x09]
+        );
+        assert_eq!(known_v2_regions(Platform::WiiU), [0x01, 0x02, 0x03]);
+        assert!(known_v2_regions(Platform::Switch).is_empty());
+    }
+
+    #[test]
+    fn known_versions() {
+        let versions = known_v2_versions(Platform::The3ds, 0x00).unwrap();
+
+        assert_eq!(versions, (0x0A..=0x11).collect::<Vec<_>>());
+        assert_eq!(known_v2_versions(Platform::The3ds, 0x07), Some(vec![]));
+        assert_eq!(known_v2_versions(Platform::WiiU, 0x01), None);
+
+        // Every known combination must be accepted by the algorithm
+        for region in known_v2_regions(Platform::The3ds) {
+            for version in known_v2_versions(Platform::The3ds, region).unwrap() {
+                let inquiry_number = region * 1_000_000_000 + version * 10_000_000;
+
+                assert!(calculate_v2_master_key(Platform::The3ds, inquiry_number, 1, 1).is_ok());
+            }
+        }
+    }
+}
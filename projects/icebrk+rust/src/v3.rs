@@ -22,6 +22,11 @@ pub enum V3Error {
     UnknownVersion(u8),
 }
 
+/// Build the message signed with HMAC by the v3 algorithm, the inquiry number padded with zeroes.
+pub(crate) fn hmac_input(inquiry_number: u64) -> String {
+    format!("{inquiry_number:0>10}")
+}
+
 /// Calculate the master key for the parental control using the v3 algorithm. The inquire number
 /// cannot be bigger than 10 digits.
 ///
@@ -44,7 +49,7 @@ pub fn calculate_v3_master_key(inquiry_number: u64) -> Result<u64, V3Error> {
         _ => return Err(V3Error::UnknownVersion(version as u8)),
     };
 
-    let input = format!("{inquiry_number:0>10}");
+    let input = hmac_input(inquiry_number);
 
     #[allow(clippy::expect_used)]
     let mut hmac = HmacSha256::new_from_slice(hmac_key).expect("Invalid lenght of the key");
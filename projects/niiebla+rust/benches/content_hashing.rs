@@ -0,0 +1,61 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Throughput of the hashes used to verify the contents, compare the default backend with the
+//! assembly one:
+//!
+//! ```sh
+//! cargo bench -p zelzip_niiebla --bench content_hashing
+//! cargo bench -p zelzip_niiebla --bench content_hashing --features asm_hashes
+//! ```
+
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::hint::black_box;
+use std::io;
+use std::time::{Duration, Instant};
+
+const CONTENT_SIZE: usize = 256 * 1024 * 1024;
+const ITERATIONS: u32 = 4;
+
+/// Hash the content the same way the content verification does, streaming it into the hasher.
+fn hash_content<D: Digest + io::Write>(content: &[u8]) -> Duration {
+    let start = Instant::now();
+
+    let mut hasher = D::new();
+    io::copy(&mut &content[..], &mut hasher).expect("Writing into a hasher never fails");
+    black_box(hasher.finalize());
+
+    start.elapsed()
+}
+
+fn bench<D: Digest + io::Write>(name: &str, content: &[u8]) {
+    let best = (0..ITERATIONS)
+        .map(|_| hash_content::<D>(content))
+        .min()
+        .unwrap_or_default();
+
+    let mib_per_second = (content.len() as f64 / (1024.0 * 1024.0)) / best.as_secs_f64();
+    println!("{name}: {best:?} ({mib_per_second:.0} MiB/s)");
+}
+
+fn main() {
+    let backend = if cfg!(feature = "asm_hashes") {
+        "assembly"
+    } else {
+        "default"
+    };
+
+    println!(
+        "Hashing {} MiB with the {backend} backend",
+        CONTENT_SIZE / (1024 * 1024)
+    );
+
+    let content: Vec<u8> = (0..CONTENT_SIZE).map(|i| (i % 251) as u8).collect();
+
+    bench::<Sha1>("SHA-1", &content);
+    bench::<Sha256>("SHA-256", &content);
+}
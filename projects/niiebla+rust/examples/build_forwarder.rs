@@ -0,0 +1,46 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Build a forwarder channel from a base one, replacing its boot content with the given DOL and
+//! renaming it. The result is fakesigned, so it can only be installed on a console running an IOS
+//! vulnerable to the [Trucha bug](https://wiibrew.org/wiki/Signing_bug):
+//!
+//! ```sh
+//! cargo run -p zelzip_niiebla --example build_forwarder -- base.wad boot.dol "My App" out.wad
+//! ```
+
+use std::fs;
+use zelzip_niiebla::prelude::*;
+
+fn main() -> Result<(), Error> {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+
+    let [base_path, dol_path, name, out_path] = &args[..] else {
+        eprintln!("Usage: build_forwarder <BASE WAD> <DOL> <NAME> <OUTPUT WAD>");
+        std::process::exit(2);
+    };
+
+    fs::copy(base_path, out_path)?;
+    let dol = fs::read(dol_path)?;
+
+    let (mut wad, mut wad_file) = Wad::open(out_path)?;
+
+    // Rewrites the banner and fakesigns the title metadata
+    wad.rename_channel(&mut wad_file, name)?;
+
+    let mut session = wad.edit_session(&mut wad_file)?;
+    let boot_content = session
+        .title_metadata
+        .select_with_index(session.title_metadata.boot_content_index);
+
+    session.replace_content(boot_content, &dol, CryptographicMethod::Wii)?;
+    session.title_metadata.fakesign()?;
+    session.flush_file()?;
+
+    println!("Forwarder written into {out_path}");
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Extract the decrypted contents of a WAD into a directory, along with its ticket and title
+//! metadata:
+//!
+//! ```sh
+//! cargo run -p zelzip_niiebla --example extract_wad -- title.wad out/
+//! ```
+
+use std::fs::File;
+use std::path::PathBuf;
+use zelzip_niiebla::prelude::*;
+
+fn main() -> Result<(), Error> {
+    let mut args = std::env::args_os().skip(1);
+
+    let (Some(wad_path), Some(out_dir)) = (args.next(), args.next().map(PathBuf::from)) else {
+        eprintln!("Usage: extract_wad <WAD> <OUTPUT DIRECTORY>");
+        std::process::exit(2);
+    };
+
+    let (wad, mut wad_file) = Wad::open_readonly(wad_path)?;
+
+    let count = wad.extract_contents(&mut wad_file, &out_dir, CryptographicMethod::Wii)?;
+
+    let ticket = wad.ticket(&mut wad_file)?;
+    ticket.dump(File::create(
+        out_dir.join(InstallableWad::SPLIT_TICKET_FILE_NAME),
+    )?)?;
+
+    let title_metadata = wad.title_metadata(&mut wad_file)?;
+    title_metadata.dump(File::create(
+        out_dir.join(InstallableWad::SPLIT_TITLE_METADATA_FILE_NAME),
+    )?)?;
+
+    println!(
+        "Extracted {count} contents of {:016X} into {}",
+        title_metadata.title_id.inner(),
+        out_dir.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Replace the ticket of a WAD with another one of the same title (like a common ticket instead
+//! of a console-unique one), re-encrypting its contents with the new title key:
+//!
+//! ```sh
+//! cargo run -p zelzip_niiebla --example rekey_ticket -- title.wad cetk
+//! ```
+
+use std::fs::File;
+use std::io::BufReader;
+use zelzip_niiebla::prelude::*;
+
+fn main() -> Result<(), Error> {
+    let mut args = std::env::args_os().skip(1);
+
+    let (Some(wad_path), Some(ticket_path)) = (args.next(), args.next()) else {
+        eprintln!("Usage: rekey_ticket <WAD> <NEW TICKET>");
+        std::process::exit(2);
+    };
+
+    let (mut wad, mut wad_file) = Wad::open(wad_path)?;
+
+    let old_ticket = wad.ticket(&mut wad_file)?;
+    let new_ticket = PreSwitchTicket::new(BufReader::new(File::open(ticket_path)?))?;
+
+    if old_ticket.title_id.inner() != new_ticket.title_id.inner() {
+        eprintln!(
+            "The new ticket is for {:016X} but the WAD stores {:016X}",
+            new_ticket.title_id.inner(),
+            old_ticket.title_id.inner()
+        );
+        std::process::exit(1);
+    }
+
+    wad.rekey(
+        &mut wad_file,
+        &old_ticket,
+        &new_ticket,
+        CryptographicMethod::Wii,
+    )?;
+
+    println!("Rekeyed {:016X}", new_ticket.title_id.inner());
+
+    Ok(())
+}
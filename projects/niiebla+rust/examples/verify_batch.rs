@@ -0,0 +1,65 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Check the signatures and content hashes of every WAD inside a directory, exits with an error
+//! code if any of them is broken:
+//!
+//! ```sh
+//! cargo run -p zelzip_niiebla --example verify_batch -- wads/
+//! ```
+
+use zelzip_niiebla::prelude::*;
+use zelzip_niiebla::wad::installable::TrustReport;
+
+fn main() -> Result<(), Error> {
+    let Some(in_dir) = std::env::args_os().nth(1) else {
+        eprintln!("Usage: verify_batch <DIRECTORY>");
+        std::process::exit(2);
+    };
+
+    let mut broken = 0;
+
+    for entry in std::fs::read_dir(in_dir)? {
+        let path = entry?.path();
+
+        if !path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("wad"))
+        {
+            continue;
+        }
+
+        // A file that is not even a WAD is reported but does not stop the batch
+        let report = Wad::open_readonly(&path)
+            .map_err(Error::from)
+            .and_then(|(wad, mut wad_file)| Ok(wad.trust_report(&mut wad_file)?));
+
+        match report {
+            Ok(TrustReport::Legit) => println!("{}: legit", path.display()),
+
+            Ok(TrustReport::Fakesigned { blobs }) => {
+                println!("{}: fakesigned {blobs:?}", path.display());
+            }
+
+            Ok(TrustReport::Broken { reasons }) => {
+                println!("{}: broken {reasons:?}", path.display());
+                broken += 1;
+            }
+
+            Err(err) => {
+                println!("{}: unreadable ({err})", path.display());
+                broken += 1;
+            }
+        }
+    }
+
+    if broken > 0 {
+        eprintln!("{broken} broken WADs");
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
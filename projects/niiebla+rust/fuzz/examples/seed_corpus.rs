@@ -0,0 +1,93 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Generate the structured seed corpus of the fuzz targets from the templates of the crate.
+//!
+//! Usage: `cargo run --example seed_corpus -- <corpus directory>`
+
+use std::env;
+use std::fs;
+use std::io::{self, Cursor, Seek, SeekFrom};
+use std::path::Path;
+use zelzip_niiebla::templates;
+use zelzip_niiebla::wad::installable::{InstallableWad, InstallableWadKind};
+
+fn main() -> io::Result<()> {
+    let corpus_path = env::args().nth(1).unwrap_or_else(|| String::from("corpus"));
+    let corpus_path = Path::new(&corpus_path);
+
+    let ticket = templates::homebrew_channel_ticket();
+    let title_metadata = templates::homebrew_channel_tmd();
+
+    let mut ticket_bytes = Cursor::new(Vec::new());
+    ticket.dump(&mut ticket_bytes)?;
+    let ticket_bytes = ticket_bytes.into_inner();
+
+    let mut title_metadata_bytes = Cursor::new(Vec::new());
+    title_metadata.dump(&mut title_metadata_bytes)?;
+    let title_metadata_bytes = title_metadata_bytes.into_inner();
+
+    let wad = InstallableWad {
+        header_size: 0x20,
+        kind: InstallableWadKind::Normal,
+        certificate_chain_size: 0,
+        ticket_size: ticket.size(),
+        title_metadata_size: title_metadata.size(),
+        content_size: 0,
+        footer_size: 0,
+    };
+
+    let mut wad_bytes = Cursor::new(Vec::new());
+    wad.dump(&mut wad_bytes)?;
+
+    // All the sections are aligned to 64 bytes
+    wad_bytes.seek(SeekFrom::Start(64))?;
+    ticket.dump(&mut wad_bytes)?;
+    wad_bytes.seek(SeekFrom::Start(
+        64 + (ticket.size() as u64).div_ceil(64) * 64,
+    ))?;
+    title_metadata.dump(&mut wad_bytes)?;
+
+    write_seed(
+        corpus_path,
+        "wad",
+        "homebrew_channel.wad",
+        &wad_bytes.into_inner(),
+    )?;
+    write_seed(corpus_path, "ticket", "homebrew_channel.tik", &ticket_bytes)?;
+    write_seed(
+        corpus_path,
+        "title_metadata",
+        "homebrew_channel.tmd",
+        &title_metadata_bytes,
+    )?;
+
+    // The signed blob header is at the start of both tickets and title metadata
+    write_seed(
+        corpus_path,
+        "signed_blob_header",
+        "homebrew_channel.tik",
+        &ticket_bytes,
+    )?;
+    write_seed(
+        corpus_path,
+        "signed_blob_header",
+        "homebrew_channel.tmd",
+        &title_metadata_bytes,
+    )?;
+
+    // The first byte of the certificate chain target is the number of certificates
+    write_seed(corpus_path, "certificate_chain", "empty", &[0])?;
+
+    Ok(())
+}
+
+fn write_seed(corpus_path: &Path, target: &str, name: &str, data: &[u8]) -> io::Result<()> {
+    let target_path = corpus_path.join(target);
+    fs::create_dir_all(&target_path)?;
+
+    fs::write(target_path.join(name), data)
+}
@@ -0,0 +1,29 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use zelzip_niiebla::CertificateChain;
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte selects the number of certificates, the usual chains have three of them
+    let Some((&number_of_certificates, data)) = data.split_first() else {
+        return;
+    };
+
+    let Ok(certificate_chain) =
+        CertificateChain::new(Cursor::new(data), number_of_certificates as usize % 8)
+    else {
+        return;
+    };
+
+    let mut buffer = Cursor::new(Vec::new());
+    certificate_chain
+        .dump(&mut buffer)
+        .expect("Unable to dump a parsed certificate chain");
+});
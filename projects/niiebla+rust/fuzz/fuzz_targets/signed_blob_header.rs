@@ -0,0 +1,22 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use zelzip_niiebla::signed_blob_header::SignedBlobHeader;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(signed_blob_header) = SignedBlobHeader::new(Cursor::new(data)) else {
+        return;
+    };
+
+    let mut buffer = Cursor::new(Vec::new());
+    signed_blob_header
+        .dump(&mut buffer)
+        .expect("Unable to dump a parsed signed blob header");
+});
@@ -0,0 +1,25 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use zelzip_niiebla::TitleMetadata;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(title_metadata) = TitleMetadata::new(Cursor::new(data)) else {
+        return;
+    };
+
+    let _ = title_metadata.select_last().content_entry(&title_metadata);
+
+    // Parsed title metadata must always be dumpable
+    let mut buffer = Cursor::new(Vec::new());
+    title_metadata
+        .dump(&mut buffer)
+        .expect("Unable to dump a parsed title metadata");
+});
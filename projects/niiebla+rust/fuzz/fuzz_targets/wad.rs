@@ -0,0 +1,33 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::{self, Cursor};
+use zelzip_niiebla::wad::installable::InstallableWad;
+use zelzip_niiebla::{CryptographicMethod, Wad};
+
+fuzz_target!(|data: &[u8]| {
+    let mut stream = Cursor::new(data);
+
+    if let Ok(Wad::Installable(wad)) = Wad::new(&mut stream) {
+        fuzz_installable_wad(&wad, &mut stream);
+    }
+});
+
+fn fuzz_installable_wad(wad: &InstallableWad, stream: &mut Cursor<&[u8]>) {
+    let _ = wad.certificate_chain(&mut *stream);
+    let _ = wad.ticket(&mut *stream);
+
+    let Ok(title_metadata) = wad.title_metadata(&mut *stream) else {
+        return;
+    };
+
+    let _ = wad.footer(&mut *stream, &title_metadata);
+    let _ = wad.has_aligned_contents(&mut *stream, &title_metadata);
+    let _ = wad.copy_to(&mut *stream, io::sink(), CryptographicMethod::Wii, true);
+}
@@ -0,0 +1,21 @@
+#!/bin/bash -eu
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+#
+# SPDX-License-Identifier: MPL-2.0
+
+# Build script of the OSS-Fuzz integration, expects the usual `$SRC` and `$OUT` variables.
+
+FUZZ_PATH="$SRC/zelzip/projects/niiebla+rust/fuzz"
+TARGETS="wad title_metadata ticket certificate_chain signed_blob_header"
+
+cd "$FUZZ_PATH"
+
+cargo fuzz build --release
+cargo run --example seed_corpus -- "$WORK/corpus"
+
+for target in $TARGETS; do
+  cp "target/x86_64-unknown-linux-gnu/release/$target" "$OUT/"
+  (cd "$WORK/corpus/$target" && zip -q "$OUT/${target}_seed_corpus.zip" ./*)
+done
@@ -0,0 +1,769 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [BNS](https://wiibrew.org/wiki/BNS_sound) sound format used by the
+//! Nintendo Wii on the banners of the channels, encoded with DSP ADPCM.
+
+use byteorder::{BE, LE, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Seek, Write};
+use thiserror::Error;
+use util::{ParseContext, ReadEx, StreamPin, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("BNS");
+
+const BNS_MAGIC_NUMBERS: [u8; 4] = *b"BNS ";
+const BNS_BYTE_ORDER_MARK_AND_VERSION: u32 = 0xFEFF0100;
+const INFO_MAGIC_NUMBERS: [u8; 4] = *b"INFO";
+const DATA_MAGIC_NUMBERS: [u8; 4] = *b"DATA";
+
+/// A BNS sound, with one or more channels encoded with DSP ADPCM.
+#[derive(Debug, Clone)]
+pub struct Bns {
+    /// Number of samples per second.
+    pub sample_rate: u16,
+
+    /// The sample where the loop starts, if the sound loops.
+    pub loop_start: Option<u32>,
+
+    /// Number of samples of each channel.
+    pub sample_count: u32,
+
+    /// The channels of the sound.
+    pub channels: Vec<BnsChannel>,
+}
+
+/// A channel of a [Bns] sound.
+#[derive(Debug, Clone)]
+pub struct BnsChannel {
+    /// The coefficients used by the predictors, two per predictor.
+    pub coefficients: [i16; 16],
+
+    /// The predictor and scale of the first frame.
+    pub predictor_and_scale: u16,
+
+    /// The two samples decoded before the first frame.
+    pub history: [i16; 2],
+
+    /// The predictor and scale of the frame where the loop starts.
+    pub loop_predictor_and_scale: u16,
+
+    /// The two samples decoded before the start of the loop.
+    pub loop_history: [i16; 2],
+
+    /// The DSP ADPCM frames of the channel.
+    pub data: Vec<u8>,
+}
+
+impl Bns {
+    const HEADER_SIZE: u16 = 0x20;
+    const CHUNK_HEADER_SIZE: u32 = 8;
+    const INFO_FIXED_SIZE: u32 = 0x18;
+    const CHANNEL_INFO_SIZE: u32 = 0x10;
+    const ADPCM_INFO_SIZE: u32 = 0x30;
+
+    /// Number of samples stored in each DSP ADPCM frame.
+    pub const SAMPLES_PER_FRAME: u32 = 14;
+
+    /// Size of a DSP ADPCM frame in bytes.
+    pub const FRAME_SIZE: u32 = 8;
+
+    /// Coefficients used when encoding, DSP ADPCM allows choosing the best ones for each sound but
+    /// a fixed set of common linear predictors is good enough for the banner sounds.
+    const ENCODING_COEFFICIENTS: [i16; 16] = [
+        0, 0, 2048, 0, 4096, -2048, 3840, -1792, 3584, -1536, 3072, -1024, 2560, -512, 1536, 0,
+    ];
+
+    /// Create a new [Bns] by parsing a stream.
+    pub fn new<T: Read + Seek>(stream: T) -> Result<Self, BnsError> {
+        let mut stream = StreamPin::new(stream)?;
+
+        let magic_numbers = CONTEXT.field(&mut stream, "magic_numbers", |stream| {
+            util::read_exact!(stream, 4)
+        })?;
+
+        if magic_numbers != BNS_MAGIC_NUMBERS {
+            return Err(BnsError::InvalidMagicNumbers(magic_numbers));
+        }
+
+        // Skip the byte order mark, version, file size, header size and number of chunks
+        stream.seek_relative(12)?;
+
+        let info_offset =
+            CONTEXT.field(&mut stream, "info_offset", |stream| stream.read_u32::<BE>())?;
+
+        // Skip the size of the info chunk
+        stream.seek_relative(4)?;
+
+        let data_offset =
+            CONTEXT.field(&mut stream, "data_offset", |stream| stream.read_u32::<BE>())?;
+
+        stream.seek_from_pin(info_offset as i64)?;
+
+        let info_magic_numbers = CONTEXT.field(&mut stream, "info_magic_numbers", |stream| {
+            util::read_exact!(stream, 4)
+        })?;
+
+        if info_magic_numbers != INFO_MAGIC_NUMBERS {
+            return Err(BnsError::InvalidChunkMagicNumbers(info_magic_numbers));
+        }
+
+        // Skip the size of the chunk
+        stream.seek_relative(4)?;
+
+        let info_body_offset = info_offset as i64 + Self::CHUNK_HEADER_SIZE as i64;
+
+        let codec = CONTEXT.field(&mut stream, "codec", |stream| stream.read_u8())?;
+
+        if codec != 0 {
+            return Err(BnsError::UnknownCodec(codec));
+        }
+
+        let has_loop = CONTEXT.field(&mut stream, "has_loop", |stream| stream.read_u8())? != 0;
+        let number_of_channels =
+            CONTEXT.field(&mut stream, "number_of_channels", |stream| stream.read_u8())?;
+
+        // Skip one padding byte
+        stream.seek_relative(1)?;
+
+        let sample_rate =
+            CONTEXT.field(&mut stream, "sample_rate", |stream| stream.read_u16::<BE>())?;
+
+        // Skip two padding bytes
+        stream.seek_relative(2)?;
+
+        let loop_start =
+            CONTEXT.field(&mut stream, "loop_start", |stream| stream.read_u32::<BE>())?;
+
+        let sample_count = CONTEXT.field(&mut stream, "sample_count", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+
+        let channel_info_table_offset =
+            CONTEXT.field(&mut stream, "channel_info_table_offset", |stream| {
+                stream.read_u32::<BE>()
+            })?;
+
+        let channel_data_size = frame_count(sample_count) as u64 * Self::FRAME_SIZE as u64;
+
+        let mut channels = Vec::with_capacity(number_of_channels as usize);
+
+        for i in 0..number_of_channels as i64 {
+            stream.seek_from_pin(info_body_offset + channel_info_table_offset as i64 + i * 4)?;
+
+            let channel_info_offset =
+                CONTEXT.field(&mut stream, "channel_info_offset", |stream| {
+                    stream.read_u32::<BE>()
+                })?;
+
+            stream.seek_from_pin(info_body_offset + channel_info_offset as i64)?;
+
+            let channel_data_offset =
+                CONTEXT.field(&mut stream, "channel_data_offset", |stream| {
+                    stream.read_u32::<BE>()
+                })?;
+
+            let adpcm_info_offset = CONTEXT.field(&mut stream, "adpcm_info_offset", |stream| {
+                stream.read_u32::<BE>()
+            })?;
+
+            stream.seek_from_pin(info_body_offset + adpcm_info_offset as i64)?;
+
+            let mut channel = CONTEXT.field(&mut stream, "adpcm_info", |stream| {
+                let mut coefficients = [0; 16];
+                stream.read_i16_into::<BE>(&mut coefficients)?;
+
+                // Skip the gain, always zero
+                stream.seek_relative(2)?;
+
+                Ok(BnsChannel {
+                    coefficients,
+                    predictor_and_scale: stream.read_u16::<BE>()?,
+                    history: [stream.read_i16::<BE>()?, stream.read_i16::<BE>()?],
+                    loop_predictor_and_scale: stream.read_u16::<BE>()?,
+                    loop_history: [stream.read_i16::<BE>()?, stream.read_i16::<BE>()?],
+                    data: Vec::new(),
+                })
+            })?;
+
+            stream.seek_from_pin(
+                data_offset as i64 + Self::CHUNK_HEADER_SIZE as i64 + channel_data_offset as i64,
+            )?;
+
+            channel.data = CONTEXT.field(&mut stream, "channel_data", |stream| {
+                stream.read_exact_vec(channel_data_size)
+            })?;
+
+            channels.push(channel);
+        }
+
+        Ok(Self {
+            sample_rate,
+            loop_start: has_loop.then_some(loop_start),
+            sample_count,
+            channels,
+        })
+    }
+
+    /// Dump the BNS into a stream, all the channels must have the data of [Self::sample_count]
+    /// samples.
+    pub fn dump<T: Write>(&self, mut stream: T) -> Result<(), BnsError> {
+        let number_of_channels = self.check_channels()? as u32;
+
+        let channel_info_table_offset = Self::INFO_FIXED_SIZE;
+        let first_channel_info_offset = channel_info_table_offset + number_of_channels * 4;
+        let first_adpcm_info_offset =
+            first_channel_info_offset + number_of_channels * Self::CHANNEL_INFO_SIZE;
+
+        let info_size = util::align_to_boundary(
+            (Self::CHUNK_HEADER_SIZE
+                + first_adpcm_info_offset
+                + number_of_channels * Self::ADPCM_INFO_SIZE) as u64,
+            0x20,
+        ) as u32;
+
+        let channel_data_size = self.channel_data_size()?;
+        let data_size = number_of_channels
+            .checked_mul(channel_data_size)
+            .and_then(|size| size.checked_add(Self::CHUNK_HEADER_SIZE))
+            .ok_or(BnsError::SoundTooBig)?;
+
+        let info_offset = Self::HEADER_SIZE as u32;
+        let data_offset = info_offset + info_size;
+
+        let file_size = data_offset
+            .checked_add(data_size)
+            .ok_or(BnsError::SoundTooBig)?;
+
+        stream.write_all(&BNS_MAGIC_NUMBERS)?;
+        stream.write_u32::<BE>(BNS_BYTE_ORDER_MARK_AND_VERSION)?;
+        stream.write_u32::<BE>(file_size)?;
+        stream.write_u16::<BE>(Self::HEADER_SIZE)?;
+        stream.write_u16::<BE>(2)?;
+        stream.write_u32::<BE>(info_offset)?;
+        stream.write_u32::<BE>(info_size)?;
+        stream.write_u32::<BE>(data_offset)?;
+        stream.write_u32::<BE>(data_size)?;
+
+        stream.write_all(&INFO_MAGIC_NUMBERS)?;
+        stream.write_u32::<BE>(info_size)?;
+
+        // DSP ADPCM codec
+        stream.write_u8(0)?;
+        stream.write_u8(self.loop_start.is_some() as u8)?;
+        stream.write_u8(number_of_channels as u8)?;
+        stream.write_zeroed(1)?;
+        stream.write_u16::<BE>(self.sample_rate)?;
+        stream.write_zeroed(2)?;
+        stream.write_u32::<BE>(self.loop_start.unwrap_or(0))?;
+        stream.write_u32::<BE>(self.sample_count)?;
+        stream.write_u32::<BE>(channel_info_table_offset)?;
+        stream.write_zeroed(4)?;
+
+        for i in 0..number_of_channels {
+            stream.write_u32::<BE>(first_channel_info_offset + i * Self::CHANNEL_INFO_SIZE)?;
+        }
+
+        for i in 0..number_of_channels {
+            stream.write_u32::<BE>(i * channel_data_size)?;
+            stream.write_u32::<BE>(first_adpcm_info_offset + i * Self::ADPCM_INFO_SIZE)?;
+            stream.write_zeroed(8)?;
+        }
+
+        for channel in &self.channels {
+            for coefficient in channel.coefficients {
+                stream.write_i16::<BE>(coefficient)?;
+            }
+
+            // Gain
+            stream.write_zeroed(2)?;
+
+            stream.write_u16::<BE>(channel.predictor_and_scale)?;
+            stream.write_i16::<BE>(channel.history[0])?;
+            stream.write_i16::<BE>(channel.history[1])?;
+            stream.write_u16::<BE>(channel.loop_predictor_and_scale)?;
+            stream.write_i16::<BE>(channel.loop_history[0])?;
+            stream.write_i16::<BE>(channel.loop_history[1])?;
+            stream.write_zeroed(2)?;
+        }
+
+        stream.write_zeroed(
+            (info_size
+                - Self::CHUNK_HEADER_SIZE
+                - first_adpcm_info_offset
+                - number_of_channels * Self::ADPCM_INFO_SIZE) as usize,
+        )?;
+
+        stream.write_all(&DATA_MAGIC_NUMBERS)?;
+        stream.write_u32::<BE>(data_size)?;
+
+        for channel in &self.channels {
+            stream.write_all(&channel.data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Decode the sound into 16 bits PCM samples, one buffer per channel.
+    pub fn decode(&self) -> Vec<Vec<i16>> {
+        self.channels
+            .iter()
+            .map(|channel| channel.decode(self.sample_count))
+            .collect()
+    }
+
+    /// Create a new [Bns] by encoding 16 bits PCM samples, one buffer per channel (all of them
+    /// must have the same length).
+    pub fn encode(
+        channels: &[Vec<i16>],
+        sample_rate: u16,
+        loop_start: Option<u32>,
+    ) -> Result<Self, BnsError> {
+        let sample_count = channels.first().map_or(0, |channel| channel.len());
+
+        if channels.iter().any(|channel| channel.len() != sample_count) {
+            return Err(BnsError::ChannelLengthMismatch);
+        }
+
+        if let Some(loop_start) = loop_start {
+            if loop_start as usize >= sample_count {
+                return Err(BnsError::LoopStartOutOfBounds(loop_start));
+            }
+        }
+
+        Ok(Self {
+            sample_rate,
+            loop_start,
+            sample_count: sample_count as u32,
+            channels: channels
+                .iter()
+                .map(|samples| BnsChannel::encode(samples, loop_start))
+                .collect(),
+        })
+    }
+
+    /// Create a new [Bns] by encoding a 16 bits PCM WAV file.
+    pub fn from_wav<T: Read>(mut stream: T) -> Result<Self, BnsError> {
+        let wav = util::read_exact!(stream, 12)?;
+
+        if &wav[..4] != b"RIFF" || &wav[8..] != b"WAVE" {
+            return Err(BnsError::InvalidWav);
+        }
+
+        let mut format = None;
+
+        loop {
+            let chunk_id = util::read_exact!(stream, 4)?;
+            let chunk_size = stream.read_u32::<LE>()?;
+
+            let chunk = stream.read_exact_vec(chunk_size.into())?;
+
+            // Chunks are aligned to two bytes
+            if chunk_size % 2 != 0 {
+                stream.read_u8()?;
+            }
+
+            match &chunk_id {
+                b"fmt " => {
+                    let mut chunk = chunk.as_slice();
+
+                    let audio_format = chunk.read_u16::<LE>()?;
+                    let number_of_channels = chunk.read_u16::<LE>()?;
+                    let sample_rate = chunk.read_u32::<LE>()?;
+
+                    // Skip the byte rate and block align
+                    chunk = chunk.get(6..).ok_or(BnsError::InvalidWav)?;
+                    let bits_per_sample = chunk.read_u16::<LE>()?;
+
+                    if audio_format != 1 || bits_per_sample != 16 || number_of_channels == 0 {
+                        return Err(BnsError::UnsupportedWav);
+                    }
+
+                    let sample_rate =
+                        u16::try_from(sample_rate).map_err(|_| BnsError::UnsupportedWav)?;
+
+                    format = Some((number_of_channels as usize, sample_rate));
+                }
+
+                b"data" => {
+                    let (number_of_channels, sample_rate) = format.ok_or(BnsError::InvalidWav)?;
+
+                    let mut channels = vec![Vec::new(); number_of_channels];
+
+                    for (i, sample) in chunk.chunks_exact(2).enumerate() {
+                        channels[i % number_of_channels]
+                            .push(i16::from_le_bytes([sample[0], sample[1]]));
+                    }
+
+                    // Drop the samples of an incomplete last group of interleaved samples
+                    let sample_count = channels[number_of_channels - 1].len();
+                    channels
+                        .iter_mut()
+                        .for_each(|channel| channel.truncate(sample_count));
+
+                    return Self::encode(&channels, sample_rate, None);
+                }
+
+                _ => (),
+            }
+        }
+    }
+
+    /// Dump the sound decoded as a 16 bits PCM WAV file into a stream, all the channels must
+    /// have the data of [Self::sample_count] samples.
+    pub fn dump_wav<T: Write>(&self, mut stream: T) -> Result<(), BnsError> {
+        let number_of_channels = self.check_channels()? as u32;
+        let channels = self.decode();
+
+        let data_size = self
+            .sample_count
+            .checked_mul(number_of_channels * 2)
+            .filter(|&size| size <= u32::MAX - 36)
+            .ok_or(BnsError::SoundTooBig)?;
+
+        stream.write_all(b"RIFF")?;
+        stream.write_u32::<LE>(36 + data_size)?;
+        stream.write_all(b"WAVE")?;
+
+        stream.write_all(b"fmt ")?;
+        stream.write_u32::<LE>(16)?;
+        stream.write_u16::<LE>(1)?;
+        stream.write_u16::<LE>(number_of_channels as u16)?;
+        stream.write_u32::<LE>(self.sample_rate as u32)?;
+        stream.write_u32::<LE>(self.sample_rate as u32 * number_of_channels * 2)?;
+        stream.write_u16::<LE>(number_of_channels as u16 * 2)?;
+        stream.write_u16::<LE>(16)?;
+
+        stream.write_all(b"data")?;
+        stream.write_u32::<LE>(data_size)?;
+
+        for i in 0..self.sample_count as usize {
+            for channel in &channels {
+                stream.write_i16::<LE>(channel[i])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the size in bytes of the data of each channel.
+    fn channel_data_size(&self) -> Result<u32, BnsError> {
+        frame_count(self.sample_count)
+            .checked_mul(Self::FRAME_SIZE)
+            .ok_or(BnsError::SoundTooBig)
+    }
+
+    /// Check that the number of channels fits in its field and that the data of all the channels
+    /// has the size of [Self::sample_count] samples, returns the number of channels.
+    fn check_channels(&self) -> Result<u8, BnsError> {
+        let number_of_channels = u8::try_from(self.channels.len())
+            .map_err(|_| BnsError::TooManyChannels(self.channels.len()))?;
+
+        let channel_data_size = self.channel_data_size()?;
+
+        if self
+            .channels
+            .iter()
+            .any(|channel| channel.data.len() as u64 != channel_data_size as u64)
+        {
+            return Err(BnsError::ChannelLengthMismatch);
+        }
+
+        Ok(number_of_channels)
+    }
+}
+
+impl BnsChannel {
+    /// Decode the given number of samples of the channel into 16 bits PCM.
+    pub fn decode(&self, sample_count: u32) -> Vec<i16> {
+        let mut samples = Vec::with_capacity(sample_count as usize);
+        let mut history = self.history;
+
+        for frame in self.data.chunks_exact(Bns::FRAME_SIZE as usize) {
+            let (first_coefficient, second_coefficient, scale) = self.frame_parameters(frame[0]);
+
+            for byte in &frame[1..] {
+                for nibble in [byte >> 4, byte & 0xF] {
+                    if samples.len() == sample_count as usize {
+                        return samples;
+                    }
+
+                    let sample = decode_sample(
+                        nibble,
+                        scale,
+                        first_coefficient,
+                        second_coefficient,
+                        history,
+                    );
+
+                    history = [sample, history[0]];
+                    samples.push(sample);
+                }
+            }
+        }
+
+        samples
+    }
+
+    fn frame_parameters(&self, predictor_and_scale: u8) -> (i16, i16, u8) {
+        let predictor = (predictor_and_scale >> 4) as usize & 0x7;
+
+        (
+            self.coefficients[predictor * 2],
+            self.coefficients[predictor * 2 + 1],
+            predictor_and_scale & 0xF,
+        )
+    }
+
+    fn encode(samples: &[i16], loop_start: Option<u32>) -> Self {
+        let coefficients = Bns::ENCODING_COEFFICIENTS;
+
+        let mut data = Vec::new();
+        let mut decoded = Vec::with_capacity(samples.len());
+        let mut history = [0; 2];
+
+        for frame in samples.chunks(Bns::SAMPLES_PER_FRAME as usize) {
+            let mut best =
+                EncodedFrame::new(frame, 0, 0, coefficients[0], coefficients[1], history);
+
+            // Try all the combinations of predictor and scale and keep the most accurate one
+            for predictor in 0..8 {
+                for scale in 0..=12 {
+                    let encoded = EncodedFrame::new(
+                        frame,
+                        predictor,
+                        scale,
+                        coefficients[predictor as usize * 2],
+                        coefficients[predictor as usize * 2 + 1],
+                        history,
+                    );
+
+                    if encoded.error < best.error {
+                        best = encoded;
+                    }
+                }
+            }
+
+            history = best.history;
+            data.extend_from_slice(&best.bytes);
+            decoded.extend_from_slice(&best.samples[..frame.len()]);
+        }
+
+        let (loop_predictor_and_scale, loop_history) = match loop_start {
+            Some(loop_start) => {
+                let loop_start = loop_start as usize;
+                let frame = loop_start / Bns::SAMPLES_PER_FRAME as usize;
+
+                let sample_before = |offset: usize| {
+                    loop_start
+                        .checked_sub(offset)
+                        .map_or(0, |position| decoded[position])
+                };
+
+                (
+                    data[frame * Bns::FRAME_SIZE as usize] as u16,
+                    [sample_before(1), sample_before(2)],
+                )
+            }
+
+            None => (0, [0; 2]),
+        };
+
+        Self {
+            coefficients,
+            predictor_and_scale: data.first().copied().unwrap_or(0) as u16,
+            history: [0; 2],
+            loop_predictor_and_scale,
+            loop_history,
+            data,
+        }
+    }
+}
+
+/// A frame encoded with a concrete predictor and scale.
+struct EncodedFrame {
+    bytes: [u8; Bns::FRAME_SIZE as usize],
+    samples: [i16; Bns::SAMPLES_PER_FRAME as usize],
+    history: [i16; 2],
+    error: u64,
+}
+
+impl EncodedFrame {
+    fn new(
+        frame: &[i16],
+        predictor: u8,
+        scale: u8,
+        first_coefficient: i16,
+        second_coefficient: i16,
+        mut history: [i16; 2],
+    ) -> Self {
+        let mut bytes = [0; Bns::FRAME_SIZE as usize];
+        let mut samples = [0; Bns::SAMPLES_PER_FRAME as usize];
+        let mut error = 0;
+
+        bytes[0] = (predictor << 4) | scale;
+
+        for (i, &sample) in frame.iter().enumerate() {
+            let prediction = (first_coefficient as i32 * history[0] as i32
+                + second_coefficient as i32 * history[1] as i32
+                + 1024)
+                >> 11;
+
+            let step = 1 << scale;
+            let difference = sample as i32 - prediction;
+            let nibble = ((difference + difference.signum() * step / 2) / step).clamp(-8, 7);
+            let nibble = (nibble & 0xF) as u8;
+
+            let decoded = decode_sample(
+                nibble,
+                scale,
+                first_coefficient,
+                second_coefficient,
+                history,
+            );
+
+            error += (sample as i64 - decoded as i64).pow(2) as u64;
+
+            bytes[1 + i / 2] |= if i % 2 == 0 { nibble << 4 } else { nibble };
+            samples[i] = decoded;
+            history = [decoded, history[0]];
+        }
+
+        Self {
+            bytes,
+            samples,
+            history,
+            error,
+        }
+    }
+}
+
+/// Decode a DSP ADPCM sample, the history stores the last two decoded samples (the most recent
+/// first).
+fn decode_sample(
+    nibble: u8,
+    scale: u8,
+    first_coefficient: i16,
+    second_coefficient: i16,
+    history: [i16; 2],
+) -> i16 {
+    // Sign extend the nibble
+    let nibble = ((nibble << 4) as i8 >> 4) as i32;
+
+    let sample = (((nibble << scale) << 11)
+        + 1024
+        + first_coefficient as i32 * history[0] as i32
+        + second_coefficient as i32 * history[1] as i32)
+        >> 11;
+
+    sample.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+/// Get the number of DSP ADPCM frames needed to store the given number of samples.
+fn frame_count(sample_count: u32) -> u32 {
+    sample_count.div_ceil(Bns::SAMPLES_PER_FRAME)
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum BnsError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("The given stream is not a BNS, invalid magic numbers: {0:?}")]
+    InvalidMagicNumbers([u8; 4]),
+
+    #[error("Invalid magic numbers of a chunk: {0:?}")]
+    InvalidChunkMagicNumbers([u8; 4]),
+
+    #[error("Unknown codec: {0}")]
+    UnknownCodec(u8),
+
+    #[error("All the channels must have the same number of samples")]
+    ChannelLengthMismatch,
+
+    #[error("The loop start is after the last sample: {0}")]
+    LoopStartOutOfBounds(u32),
+
+    #[error("The given stream is not a valid WAV file")]
+    InvalidWav,
+
+    #[error("Only 16 bits PCM WAV files with a sample rate up to 65535 Hz are supported")]
+    UnsupportedWav,
+
+    #[error("A BNS cannot have more than 255 channels: {0}")]
+    TooManyChannels(usize),
+
+    #[error("The sound is too big to be dumped")]
+    SoundTooBig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let samples: Vec<i16> = (0..100)
+            .map(|i| ((i as f32 / 8.0).sin() * 8000.0) as i16)
+            .collect();
+
+        let bns = Bns::encode(&[samples.clone(), samples.clone()], 32000, Some(20)).unwrap();
+
+        let mut buffer = vec![];
+        bns.dump(&mut buffer).unwrap();
+
+        let bns = Bns::new(Cursor::new(buffer)).unwrap();
+        assert_eq!(bns.loop_start, Some(20));
+
+        let decoded = bns.decode();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].len(), samples.len());
+
+        for (original, decoded) in samples.iter().zip(&decoded[1]) {
+            assert!((*original as i32 - *decoded as i32).abs() < 256);
+        }
+    }
+
+    #[test]
+    fn wav_round_trip() {
+        let bns = Bns::encode(&[vec![0, 8, 16, 24]], 22050, None).unwrap();
+
+        let mut wav = vec![];
+        bns.dump_wav(&mut wav).unwrap();
+
+        let bns = Bns::from_wav(Cursor::new(wav)).unwrap();
+
+        assert_eq!(bns.sample_rate, 22050);
+        assert_eq!(bns.decode(), [[0, 8, 16, 24]]);
+    }
+
+    #[test]
+    fn dump_invalid_channels() {
+        let mut bns = Bns::encode(&[vec![0; 28], vec![0; 28]], 32000, None).unwrap();
+
+        // The data of a channel does not match the number of samples
+        bns.channels[1].data.truncate(8);
+        assert!(matches!(
+            bns.dump_wav(&mut vec![]),
+            Err(BnsError::ChannelLengthMismatch)
+        ));
+        assert!(matches!(
+            bns.dump(&mut vec![]),
+            Err(BnsError::ChannelLengthMismatch)
+        ));
+
+        bns.channels = vec![bns.channels[0].clone(); 256];
+        assert!(matches!(
+            bns.dump_wav(&mut vec![]),
+            Err(BnsError::TooManyChannels(256))
+        ));
+        assert!(matches!(
+            bns.dump(&mut vec![]),
+            Err(BnsError::TooManyChannels(256))
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Report of the formats and features compiled into the crate, useful for frontends bundling it
+//! with only some of its features enabled.
+
+use crate::CryptographicMethod;
+
+/// The formats, cryptographic methods and features compiled into the crate, see [capabilities].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The version of the crate.
+    pub version: &'static str,
+
+    /// The enabled format features (like `wad` or `cia`), in the same order as in the
+    /// documentation of the crate.
+    pub formats: &'static [&'static str],
+
+    /// The supported methods to decrypt the contents of the titles, the 3DS method is not listed
+    /// as it needs the keys of a console.
+    pub cryptographic_methods: &'static [CryptographicMethod],
+
+    /// Either if the assembly implementations of the hashes are used (the `asm_hashes` feature).
+    pub asm_hashes: bool,
+
+    /// Either if the metrics of the crate are reported (the `metrics` feature), see
+    /// [telemetry](crate::telemetry).
+    pub metrics: bool,
+}
+
+impl Capabilities {
+    /// Check if the given format feature (like `wad`) has been compiled in.
+    pub fn has_format(&self, format: &str) -> bool {
+        self.formats.contains(&format)
+    }
+}
+
+const FORMATS: &[&str] = &[
+    #[cfg(feature = "wad")]
+    "wad",
+    #[cfg(feature = "ios_patch")]
+    "ios_patch",
+    #[cfg(feature = "cia")]
+    "cia",
+    #[cfg(feature = "smdh")]
+    "smdh",
+    #[cfg(feature = "seed_db")]
+    "seed_db",
+    #[cfg(feature = "title_keys")]
+    "title_keys",
+    #[cfg(feature = "pfs0")]
+    "pfs0",
+    #[cfg(feature = "nca")]
+    "nca",
+    #[cfg(feature = "wup")]
+    "wup",
+    #[cfg(feature = "tpl")]
+    "tpl",
+    #[cfg(feature = "bns")]
+    "bns",
+    #[cfg(feature = "compressed_contents")]
+    "compressed_contents",
+    #[cfg(feature = "sysconf")]
+    "sysconf",
+    #[cfg(feature = "imet")]
+    "imet",
+    #[cfg(feature = "version_list")]
+    "version_list",
+    #[cfg(feature = "vfs")]
+    "vfs",
+    #[cfg(feature = "u8_archive")]
+    "u8_archive",
+    #[cfg(feature = "lz77")]
+    "lz77",
+    #[cfg(feature = "vc")]
+    "vc",
+];
+
+/// Get the formats, cryptographic methods and features compiled into the crate. Computed at
+/// compile time, it can be called from any platform (including WASM).
+pub const fn capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        formats: FORMATS,
+        cryptographic_methods: &[
+            CryptographicMethod::Wii,
+            CryptographicMethod::WiiDevelopment,
+        ],
+        asm_hashes: cfg!(feature = "asm_hashes"),
+        metrics: cfg!(feature = "metrics"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_follow_the_features() {
+        let capabilities = capabilities();
+
+        assert_eq!(capabilities.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(capabilities.has_format("wad"), cfg!(feature = "wad"));
+        assert_eq!(capabilities.has_format("imet"), cfg!(feature = "imet"));
+        assert!(!capabilities.has_format("unknown"));
+        assert!(
+            capabilities
+                .cryptographic_methods
+                .contains(&CryptographicMethod::Wii)
+        );
+    }
+}
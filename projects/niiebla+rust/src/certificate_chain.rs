@@ -6,14 +6,25 @@
 
 //! Implementation of the binary file format used by Nintendo to store certificate chains.
 
+pub mod signer;
+pub mod verifier;
+
+use crate::certificate_chain::verifier::{CertificateVerifier, EccVerifier, RsaVerifier};
+use crate::parse_limits::{ParseLimitError, ParseLimits};
 use crate::signed_blob_header::{SignedBlobHeader, SignedBlobHeaderError};
+use crate::telemetry;
 use byteorder::{BE, ReadBytesExt, WriteBytesExt};
-use std::io::{self, Read, Seek, Write};
+use sha2::{Digest, Sha256};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Cursor, Read, Seek, Write};
 use std::string::FromUtf8Error;
 use thiserror::Error;
+use util::ParseContext;
 use util::StreamPin;
 use util::WriteEx;
 
+const CONTEXT: ParseContext = ParseContext::new("certificate");
+
 #[derive(Debug)]
 /// A set of certificates.
 pub struct CertificateChain {
@@ -27,6 +38,18 @@ impl CertificateChain {
         stream: T,
         number_of_certificates: usize,
     ) -> Result<Self, CertificateChainError> {
+        Self::new_with_limits(stream, number_of_certificates, &ParseLimits::UNLIMITED)
+    }
+
+    /// Like [Self::new] but fails if the number of certificates exceeds the maximum number of
+    /// sections of the given [ParseLimits], useful when the number comes from an untrusted file.
+    pub fn new_with_limits<T: Read + Seek>(
+        stream: T,
+        number_of_certificates: usize,
+        limits: &ParseLimits,
+    ) -> Result<Self, CertificateChainError> {
+        limits.check_sections(number_of_certificates)?;
+
         let mut stream = StreamPin::new(stream)?;
         let mut certificates = Vec::new();
 
@@ -35,6 +58,8 @@ impl CertificateChain {
             stream.align_position(64)?;
         }
 
+        telemetry::file_parsed("certificate_chain");
+
         Ok(Self { certificates })
     }
 
@@ -75,10 +100,22 @@ pub enum CertificateChainError {
 
     #[error("Unable to parse the signed blob header: {0}")]
     SignedBlobHeaderError(#[from] SignedBlobHeaderError),
+
+    #[error("The kind of the signature does not match the key of the certificate")]
+    SignatureKindMismatch,
+
+    #[error("The public key is not a point of the elliptic curve")]
+    InvalidEccPublicKey,
+
+    #[error("A parse limit has been exceeded: {0}")]
+    ParseLimitError(#[from] ParseLimitError),
 }
 
 #[derive(Debug, Clone)]
 /// A single certificate.
+///
+/// Two certificates are equal if they have the same identity and key, regardless of their
+/// signatures.
 pub struct Certificate {
     /// Header with data to prove the authenticity that this data
     /// has being created by an authorized entity.
@@ -96,12 +133,15 @@ impl Certificate {
     pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, CertificateChainError> {
         let signed_blob_header = SignedBlobHeader::new(&mut stream)?;
 
-        let key_value_kind_identifier = stream.read_u32::<BE>()?;
+        let key_value_kind_identifier =
+            CONTEXT.field(&mut stream, "key_kind", |stream| stream.read_u32::<BE>())?;
 
-        let identity = util::read_string!(stream, 64)?;
+        let identity = CONTEXT.field(&mut stream, "identity", |stream| {
+            util::read_string!(stream, 64)
+        })?;
 
         let key = CertificateKey {
-            id: stream.read_u32::<BE>()?,
+            id: CONTEXT.field(&mut stream, "key_id", |stream| stream.read_u32::<BE>())?,
             value: CertificateKeyValue::new(key_value_kind_identifier, &mut stream)?,
         };
 
@@ -124,6 +164,18 @@ impl Certificate {
         Ok(())
     }
 
+    /// Get the SHA-256 hash of the whole dumped certificate (including its signature), useful
+    /// to recognize a known certificate.
+    pub fn fingerprint_sha256(&self) -> [u8; 32] {
+        let mut buffer = Cursor::new(Vec::with_capacity(self.size() as usize));
+
+        #[allow(clippy::expect_used)]
+        self.dump(&mut buffer)
+            .expect("This will never panic as writing into memory cannot fail");
+
+        Sha256::digest(buffer.get_ref()).into()
+    }
+
     /// Get the sizes of the certificate in bytes.
     pub fn size(&self) -> u32 {
         let size = match self.key.value {
@@ -135,9 +187,72 @@ impl Certificate {
 
         util::align_to_boundary(size as u64, 64) as u32
     }
+
+    /// Get a verifier of the signatures made with the private key of the certificate, it can be
+    /// reused to verify any number of signed blobs.
+    pub fn verifier(&self) -> Result<CertificateVerifier, CertificateChainError> {
+        Ok(match &self.key.value {
+            CertificateKeyValue::Rsa4096(value) => {
+                let (modulus, public_exponent) = value.split_at(512);
+                CertificateVerifier::Rsa(RsaVerifier::new(
+                    modulus,
+                    u32::from_be_bytes([
+                        public_exponent[0],
+                        public_exponent[1],
+                        public_exponent[2],
+                        public_exponent[3],
+                    ]),
+                ))
+            }
+
+            CertificateKeyValue::Rsa2048(value) => {
+                let (modulus, public_exponent) = value.split_at(256);
+                CertificateVerifier::Rsa(RsaVerifier::new(
+                    modulus,
+                    u32::from_be_bytes([
+                        public_exponent[0],
+                        public_exponent[1],
+                        public_exponent[2],
+                        public_exponent[3],
+                    ]),
+                ))
+            }
+
+            CertificateKeyValue::EccB223(value) => CertificateVerifier::Ecc(
+                EccVerifier::new(value).ok_or(CertificateChainError::InvalidEccPublicKey)?,
+            ),
+        })
+    }
+
+    /// Get the NG ID (the ID of the console) of a device certificate, named `NGxxxxxxxx` after
+    /// it. `None` if the certificate is not the one of a console.
+    pub fn ng_id(&self) -> Option<u32> {
+        let ng_id = self.identity.strip_prefix("NG")?;
+
+        if ng_id.len() != 8 {
+            return None;
+        }
+
+        u32::from_str_radix(ng_id, 16).ok()
+    }
 }
 
-#[derive(Debug, Clone)]
+impl PartialEq for Certificate {
+    fn eq(&self, other: &Self) -> bool {
+        self.identity == other.identity && self.key == other.key
+    }
+}
+
+impl Eq for Certificate {}
+
+impl Hash for Certificate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity.hash(state);
+        self.key.hash(state);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// The public key stored inside a certificate.
 pub struct CertificateKey {
     /// The ID of the certificate.
@@ -149,7 +264,7 @@ pub struct CertificateKey {
 
 /// The public key data stored inside a certificate.
 #[allow(clippy::large_enum_variant)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CertificateKeyValue {
     /// The key is stored as RSA-4096 data.
     Rsa4096(Box<[u8; 512 + 4]>),
@@ -163,24 +278,26 @@ pub enum CertificateKeyValue {
 
 impl CertificateKeyValue {
     fn new<T: Read + Seek>(identifier: u32, mut stream: T) -> Result<Self, CertificateChainError> {
-        let public_key = match identifier {
-            0 => {
-                let buf = util::read_exact!(stream, 512 + 4)?;
-                Self::Rsa4096(Box::new(buf))
-            }
-            1 => {
-                let buf = util::read_exact!(stream, 256 + 4)?;
-                Self::Rsa2048(Box::new(buf))
-            }
-            2 => {
-                let buf = util::read_exact!(stream, 60)?;
-                Self::EccB223(Box::new(buf))
-            }
-
-            _ => return Err(CertificateChainError::UnknownKeyKind(identifier)),
-        };
+        let public_key = CONTEXT.field(&mut stream, "key_value", |stream| {
+            Ok(Some(match identifier {
+                0 => {
+                    let buf = util::read_exact!(stream, 512 + 4)?;
+                    Self::Rsa4096(Box::new(buf))
+                }
+                1 => {
+                    let buf = util::read_exact!(stream, 256 + 4)?;
+                    Self::Rsa2048(Box::new(buf))
+                }
+                2 => {
+                    let buf = util::read_exact!(stream, 60)?;
+                    Self::EccB223(Box::new(buf))
+                }
+
+                _ => return Ok(None),
+            }))
+        })?;
 
-        Ok(public_key)
+        public_key.ok_or(CertificateChainError::UnknownKeyKind(identifier))
     }
 
     fn dump_kind_identifier<T: Write>(&self, mut stream: T) -> io::Result<()> {
@@ -203,3 +320,55 @@ impl CertificateKeyValue {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signed_blob_header::SignedBlobHeaderSignature;
+    use std::collections::HashSet;
+
+    fn certificate(identity: &str, key: u8) -> Certificate {
+        Certificate {
+            signed_blob_header: SignedBlobHeader {
+                signature: SignedBlobHeaderSignature::Rsa2048Sha1(Box::new([0; 256])),
+                issuer: String::from("Root-CA00000001"),
+            },
+            identity: identity.to_string(),
+            key: CertificateKey {
+                id: 0,
+                value: CertificateKeyValue::Rsa2048(Box::new([key; 256 + 4])),
+            },
+        }
+    }
+
+    #[test]
+    fn fingerprint_and_identity() {
+        let certificate_a = certificate("XS00000003", 0xAA);
+
+        let mut resigned_certificate_a = certificate_a.clone();
+        resigned_certificate_a.signed_blob_header.signature =
+            SignedBlobHeaderSignature::Rsa2048Sha1(Box::new([0x42; 256]));
+
+        assert_eq!(certificate_a, resigned_certificate_a);
+        assert_ne!(
+            certificate_a.fingerprint_sha256(),
+            resigned_certificate_a.fingerprint_sha256()
+        );
+        assert_eq!(
+            certificate_a.fingerprint_sha256(),
+            certificate_a.clone().fingerprint_sha256()
+        );
+
+        assert_ne!(certificate_a, certificate("CP00000004", 0xAA));
+        assert_ne!(certificate_a, certificate("XS00000003", 0xBB));
+
+        let certificates: HashSet<_> = [
+            certificate_a.clone(),
+            resigned_certificate_a,
+            certificate("CP00000004", 0xAA),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(certificates.len(), 2);
+    }
+}
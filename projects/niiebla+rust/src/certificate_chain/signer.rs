@@ -0,0 +1,180 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of [EccSigner], the private key of a console ready to sign data.
+
+use crate::certificate_chain::verifier::{EccPoint, curve_order};
+use num_bigint::BigUint;
+use sha1::{Digest, Sha1};
+use sha2::Sha512;
+use std::fmt;
+
+/// Private key of ECDSA over the `sect233r1` curve (also known as B-233), like the NG key unique
+/// to each console, the signatures can be verified with
+/// [EccVerifier](crate::certificate_chain::verifier::EccVerifier).
+#[derive(Clone)]
+pub struct EccSigner {
+    private_key: BigUint,
+}
+
+impl EccSigner {
+    /// Create a new [EccSigner] from the private key (30 bytes in big-endian). Returns `None` if
+    /// the key is zero or not lower than the order of the curve.
+    pub fn new(private_key: &[u8; 30]) -> Option<Self> {
+        let private_key = BigUint::from_bytes_be(private_key);
+
+        if private_key == BigUint::ZERO || private_key >= curve_order() {
+            return None;
+        }
+
+        Some(Self { private_key })
+    }
+
+    /// Create a new [EccSigner] with a private key derived from the given seed, the same seed
+    /// always gives the same key.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let order = curve_order();
+
+        // A 512 bits hash reduced modulo the 233 bits order is not biased in practice
+        let private_key = BigUint::from_bytes_be(&Sha512::digest(seed)) % (&order - 1u32) + 1u32;
+
+        Self { private_key }
+    }
+
+    /// Get the private key (30 bytes in big-endian).
+    pub fn private_key(&self) -> [u8; 30] {
+        to_bytes(&self.private_key)
+    }
+
+    /// Get the public key, the X coordinate followed by the Y one (30 bytes each), like stored
+    /// in the certificates.
+    pub fn public_key(&self) -> [u8; 60] {
+        let mut public_key = [0; 60];
+
+        if let Some(point) = EccPoint::generator().multiply(&self.private_key) {
+            public_key[..30].copy_from_slice(&to_bytes(&point.x.to_biguint()));
+            public_key[30..].copy_from_slice(&to_bytes(&point.y.to_biguint()));
+        }
+
+        public_key
+    }
+
+    /// Sign the SHA-1 hash of the data, the signature is the R value followed by the S one (30
+    /// bytes each), like the one of
+    /// [SignedBlobHeaderSignature::EcdsaSha1](crate::signed_blob_header::SignedBlobHeaderSignature::EcdsaSha1).
+    ///
+    /// The nonce is derived from the private key and the hash (like in RFC 6979) instead of
+    /// being random, so signing the same data twice gives the same signature.
+    pub fn sign(&self, data: &[u8]) -> [u8; 60] {
+        let order = curve_order();
+        let digest = Sha1::digest(data);
+
+        // A SHA-1 hash is shorter than the order of the curve, so it is not truncated
+        let e = BigUint::from_bytes_be(&digest);
+
+        for counter in 0u32.. {
+            let mut nonce_hasher = Sha512::new();
+            nonce_hasher.update(self.private_key());
+            nonce_hasher.update(digest);
+            nonce_hasher.update(counter.to_be_bytes());
+
+            let k = BigUint::from_bytes_be(&nonce_hasher.finalize()) % &order;
+
+            let Some(point) = EccPoint::generator().multiply(&k) else {
+                continue;
+            };
+
+            let r = point.x.to_biguint() % &order;
+
+            // The order is prime, so its inverse can be computed with the little Fermat theorem
+            let k_inverse = k.modpow(&(&order - 2u32), &order);
+            let s = (k_inverse * (&e + &r * &self.private_key)) % &order;
+
+            if r == BigUint::ZERO || s == BigUint::ZERO {
+                continue;
+            }
+
+            let mut signature = [0; 60];
+            signature[..30].copy_from_slice(&to_bytes(&r));
+            signature[30..].copy_from_slice(&to_bytes(&s));
+
+            return signature;
+        }
+
+        unreachable!("a valid nonce is always found")
+    }
+}
+
+impl fmt::Debug for EccSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the private key
+        f.debug_struct("EccSigner").finish_non_exhaustive()
+    }
+}
+
+fn to_bytes(value: &BigUint) -> [u8; 30] {
+    let bytes = value.to_bytes_be();
+
+    let mut padded = [0; 30];
+    padded[30 - bytes.len()..].copy_from_slice(&bytes);
+
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate_chain::verifier::{CertificateVerifier, EccVerifier};
+    use crate::signed_blob_header::SignedBlobHeaderSignature;
+
+    // Key pair made by OpenSSL (`openssl ecparam -name sect233r1 -genkey`)
+    const OPENSSL_ECC_PRIVATE_KEY: [u8; 30] = [
+        0x00, 0x54, 0x16, 0x13, 0x00, 0x3C, 0xFE, 0x6D, 0x8F, 0x0A, 0xAB, 0x63, 0x45, 0xF5, 0x92,
+        0xD0, 0xF0, 0x5D, 0xD0, 0x8D, 0xB7, 0x9F, 0x65, 0xB8, 0x80, 0xCB, 0xB0, 0xEB, 0xAA, 0x3E,
+    ];
+
+    const OPENSSL_ECC_PUBLIC_KEY: [u8; 60] = [
+        0x00, 0x7B, 0x70, 0xAE, 0x18, 0x9A, 0x5F, 0x4F, 0xD0, 0x52, 0xCD, 0x0E, 0xE7, 0xCA, 0x3A,
+        0xCA, 0x7F, 0x60, 0xA4, 0x7D, 0x19, 0x8E, 0xEF, 0x4A, 0xBB, 0x09, 0xB9, 0x5B, 0x2D, 0xD8,
+        0x00, 0x44, 0xA0, 0x40, 0xAC, 0xAF, 0x73, 0x8C, 0x38, 0x25, 0x5A, 0x76, 0xB6, 0x2F, 0x36,
+        0xC1, 0x20, 0x01, 0x9A, 0x59, 0x75, 0xD5, 0x9B, 0xEA, 0x3E, 0xD9, 0x0C, 0x25, 0xB9, 0x96,
+    ];
+
+    #[test]
+    fn sign_and_verify() {
+        let mut private_key = [0; 30];
+        private_key[28..].copy_from_slice(&[0x5E, 0xED]);
+
+        let signer = EccSigner::new(&private_key).unwrap();
+        assert_eq!(signer.private_key(), private_key);
+
+        let signature = signer.sign(b"NiiEBLA");
+        assert_eq!(signer.sign(b"NiiEBLA"), signature);
+
+        let verifier = CertificateVerifier::Ecc(EccVerifier::new(&signer.public_key()).unwrap());
+        let signature = SignedBlobHeaderSignature::EcdsaSha1(Box::new(signature));
+
+        assert!(verifier.verify(&signature, b"NiiEBLA").unwrap());
+        assert!(!verifier.verify(&signature, b"NiiEBLa").unwrap());
+
+        let seeded_signer = EccSigner::from_seed(b"NiiEBLA");
+        assert!(EccVerifier::new(&seeded_signer.public_key()).is_some());
+        assert_eq!(
+            EccSigner::from_seed(b"NiiEBLA").private_key(),
+            seeded_signer.private_key()
+        );
+
+        assert!(EccSigner::new(&[0; 30]).is_none());
+        assert!(EccSigner::new(&[0xFF; 30]).is_none());
+    }
+
+    #[test]
+    fn public_key_known_answer() {
+        let signer = EccSigner::new(&OPENSSL_ECC_PRIVATE_KEY).unwrap();
+
+        assert_eq!(signer.public_key(), OPENSSL_ECC_PUBLIC_KEY);
+    }
+}
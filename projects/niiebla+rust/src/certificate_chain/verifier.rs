@@ -0,0 +1,667 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of [CertificateVerifier], the public key of a
+//! [Certificate](crate::certificate_chain::Certificate) ready to verify signatures.
+
+use crate::certificate_chain::CertificateChainError;
+use crate::signed_blob_header::{SignedBlobHeader, SignedBlobHeaderSignature};
+use num_bigint::BigUint;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::io::Cursor;
+
+/// DER encoded `DigestInfo` prefix of a SHA-1 hash, used by the PKCS#1 v1.5 padding.
+const SHA1_DIGEST_INFO_PREFIX: [u8; 15] = [
+    0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2B, 0x0E, 0x03, 0x02, 0x1A, 0x05, 0x00, 0x04, 0x14,
+];
+
+/// DER encoded `DigestInfo` prefix of a SHA-256 hash, used by the PKCS#1 v1.5 padding.
+const SHA256_DIGEST_INFO_PREFIX: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0D, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+/// The public key of a certificate parsed and ready to verify any number of signatures.
+#[derive(Debug, Clone)]
+pub enum CertificateVerifier {
+    /// Verifier of RSA PKCS#1 v1.5 signatures.
+    Rsa(RsaVerifier),
+
+    /// Verifier of ECDSA signatures over the `sect233r1` curve.
+    Ecc(EccVerifier),
+}
+
+impl CertificateVerifier {
+    /// Verify that the signature of the given data was made with the private key of the
+    /// certificate.
+    ///
+    /// An error is returned if the kind of the signature does not match the one of the key.
+    pub fn verify(
+        &self,
+        signature: &SignedBlobHeaderSignature,
+        data: &[u8],
+    ) -> Result<bool, CertificateChainError> {
+        match (self, signature) {
+            (Self::Rsa(verifier), SignedBlobHeaderSignature::Rsa4096Sha1(signature))
+                if verifier.len() == 512 =>
+            {
+                Ok(verifier.verify_digest(
+                    signature.as_slice(),
+                    &SHA1_DIGEST_INFO_PREFIX,
+                    &Sha1::digest(data),
+                ))
+            }
+
+            (Self::Rsa(verifier), SignedBlobHeaderSignature::Rsa2048Sha1(signature))
+                if verifier.len() == 256 =>
+            {
+                Ok(verifier.verify_digest(
+                    signature.as_slice(),
+                    &SHA1_DIGEST_INFO_PREFIX,
+                    &Sha1::digest(data),
+                ))
+            }
+
+            (Self::Rsa(verifier), SignedBlobHeaderSignature::Rsa4096Sha256(signature))
+                if verifier.len() == 512 =>
+            {
+                Ok(verifier.verify_digest(
+                    signature.as_slice(),
+                    &SHA256_DIGEST_INFO_PREFIX,
+                    &Sha256::digest(data),
+                ))
+            }
+
+            (Self::Rsa(verifier), SignedBlobHeaderSignature::Rsa2048Sha256(signature))
+                if verifier.len() == 256 =>
+            {
+                Ok(verifier.verify_digest(
+                    signature.as_slice(),
+                    &SHA256_DIGEST_INFO_PREFIX,
+                    &Sha256::digest(data),
+                ))
+            }
+
+            (Self::Ecc(verifier), SignedBlobHeaderSignature::EcdsaSha1(signature)) => {
+                Ok(verifier.verify_digest(signature, &Sha1::digest(data)))
+            }
+
+            (Self::Ecc(verifier), SignedBlobHeaderSignature::EcdsaSha256(signature)) => {
+                Ok(verifier.verify_digest(signature, &Sha256::digest(data)))
+            }
+
+            _ => Err(CertificateChainError::SignatureKindMismatch),
+        }
+    }
+
+    /// Verify a whole signed blob (ticket, title metadata, certificate, etc) already dumped into
+    /// memory, the signed data starts at the issuer of its [SignedBlobHeader].
+    pub fn verify_signed_blob(&self, blob: &[u8]) -> Result<bool, CertificateChainError> {
+        let signed_blob_header = SignedBlobHeader::new(Cursor::new(blob))?;
+
+        // The issuer is stored at the end of the header
+        let signed_data_offset = signed_blob_header.size() as usize - 64;
+
+        let signed_data = blob.get(signed_data_offset..).unwrap_or_default();
+
+        self.verify(&signed_blob_header.signature, signed_data)
+    }
+}
+
+/// Public key of RSA.
+#[derive(Debug, Clone)]
+pub struct RsaVerifier {
+    modulus: BigUint,
+    public_exponent: BigUint,
+    len: usize,
+}
+
+impl RsaVerifier {
+    /// Create a new [RsaVerifier] from the big endian bytes of the modulus and the public
+    /// exponent.
+    pub fn new(modulus: &[u8], public_exponent: u32) -> Self {
+        Self {
+            modulus: BigUint::from_bytes_be(modulus),
+            public_exponent: BigUint::from(public_exponent),
+            len: modulus.len(),
+        }
+    }
+
+    /// Get the size of the key (and of its signatures) in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the key has no size, such key cannot verify any signature.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn verify_digest(&self, signature: &[u8], digest_info_prefix: &[u8], digest: &[u8]) -> bool {
+        let signature = BigUint::from_bytes_be(signature);
+
+        if signature >= self.modulus {
+            return false;
+        }
+
+        let decrypted = signature
+            .modpow(&self.public_exponent, &self.modulus)
+            .to_bytes_be();
+
+        // The leading zero of the padding is lost when converting into bytes
+        let Some(padding_len) = self
+            .len
+            .checked_sub(3 + digest_info_prefix.len() + digest.len())
+        else {
+            return false;
+        };
+
+        let mut expected = Vec::with_capacity(self.len - 1);
+        expected.push(0x01);
+        expected.extend(std::iter::repeat_n(0xFF, padding_len));
+        expected.push(0x00);
+        expected.extend_from_slice(digest_info_prefix);
+        expected.extend_from_slice(digest);
+
+        decrypted == expected
+    }
+}
+
+/// Public key of ECDSA over the `sect233r1` curve (also known as B-233), used by the
+/// certificates unique to each console.
+#[derive(Debug, Clone)]
+pub struct EccVerifier {
+    public_key: EccPoint,
+}
+
+impl EccVerifier {
+    /// Create a new [EccVerifier] from the coordinates of the public key (the X coordinate
+    /// followed by the Y one, 30 bytes each). Returns `None` if the key is not a point of the
+    /// curve.
+    pub fn new(public_key: &[u8; 60]) -> Option<Self> {
+        let (x, y) = public_key.split_at(30);
+
+        let public_key = EccPoint {
+            x: FieldElement::from_bytes(x)?,
+            y: FieldElement::from_bytes(y)?,
+        };
+
+        if !public_key.is_on_curve() {
+            return None;
+        }
+
+        Some(Self { public_key })
+    }
+
+    fn verify_digest(&self, signature: &[u8; 60], digest: &[u8]) -> bool {
+        let order = curve_order();
+
+        let r = BigUint::from_bytes_be(&signature[..30]);
+        let s = BigUint::from_bytes_be(&signature[30..]);
+
+        let zero = BigUint::ZERO;
+
+        if r == zero || s == zero || r >= order || s >= order {
+            return false;
+        }
+
+        // The digest is truncated to the bit length of the order of the curve
+        let mut e = BigUint::from_bytes_be(digest);
+        let digest_bits = digest.len() as u64 * 8;
+        if digest_bits > order.bits() {
+            e >>= digest_bits - order.bits();
+        }
+
+        // The order is prime, so its inverse can be computed with the little Fermat theorem
+        let w = s.modpow(&(&order - 2u32), &order);
+        let u1 = (e * &w) % &order;
+        let u2 = (&r * &w) % &order;
+
+        let point = EccPoint::add(
+            EccPoint::generator().multiply(&u1),
+            self.public_key.multiply(&u2),
+        );
+
+        match point {
+            Some(point) => point.x.to_biguint() % &order == r,
+            None => false,
+        }
+    }
+}
+
+pub(super) fn curve_order() -> BigUint {
+    BigUint::from_bytes_be(&[
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x13, 0xE9, 0x74, 0xE7, 0x2F, 0x8A, 0x69, 0x22, 0x03, 0x1D, 0x26, 0x03, 0xCF, 0xE0, 0xD7,
+    ])
+}
+
+/// Element of the binary field GF(2^233), with `x^233 + x^74 + 1` as reduction polynomial.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct FieldElement([u64; 4]);
+
+impl FieldElement {
+    const ZERO: Self = Self([0; 4]);
+    const ONE: Self = Self([1, 0, 0, 0]);
+    const DEGREE: usize = 233;
+    const REDUCTION_POLYNOMIAL: Self = Self([1, 1 << 10, 0, 1 << 41]);
+
+    pub(super) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut words = [0; 4];
+
+        for (i, byte) in bytes.iter().rev().enumerate() {
+            *words.get_mut(i / 8)? |= (*byte as u64) << ((i % 8) * 8);
+        }
+
+        let element = Self(words);
+
+        match element.degree() {
+            Some(degree) if degree >= Self::DEGREE => None,
+            _ => Some(element),
+        }
+    }
+
+    pub(super) fn to_biguint(self) -> BigUint {
+        BigUint::from_slice(
+            &self
+                .0
+                .iter()
+                .flat_map(|word| [*word as u32, (word >> 32) as u32])
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.0[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    fn degree(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, word)| **word != 0)
+            .map(|(i, word)| i * 64 + 63 - word.leading_zeros() as usize)
+    }
+
+    fn shift_left(&self, bits: usize) -> Self {
+        let mut words = [0; 4];
+
+        for (i, word) in self.0.iter().enumerate() {
+            let target = i + bits / 64;
+
+            if target < 4 {
+                words[target] |= word << (bits % 64);
+            }
+
+            if !bits.is_multiple_of(64) && target + 1 < 4 {
+                words[target + 1] |= word >> (64 - bits % 64);
+            }
+        }
+
+        Self(words)
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self([
+            self.0[0] ^ other.0[0],
+            self.0[1] ^ other.0[1],
+            self.0[2] ^ other.0[2],
+            self.0[3] ^ other.0[3],
+        ])
+    }
+
+    fn multiply(self, other: Self) -> Self {
+        let mut result = Self::ZERO;
+        let mut shifted = self;
+
+        for i in 0..Self::DEGREE {
+            if other.bit(i) {
+                result = result.add(shifted);
+            }
+
+            shifted = shifted.shift_left(1);
+
+            if shifted.bit(Self::DEGREE) {
+                shifted = shifted.add(Self::REDUCTION_POLYNOMIAL);
+            }
+        }
+
+        result
+    }
+
+    fn square(self) -> Self {
+        self.multiply(self)
+    }
+
+    /// Inverse using the extended Euclidean algorithm for polynomials, `None` for zero.
+    fn inverse(self) -> Option<Self> {
+        let mut u = self;
+        let mut v = Self::REDUCTION_POLYNOMIAL;
+        let mut g1 = Self::ONE;
+        let mut g2 = Self::ZERO;
+
+        while u != Self::ONE {
+            let u_degree = u.degree()?;
+            let v_degree = v.degree()?;
+
+            if u_degree < v_degree {
+                std::mem::swap(&mut u, &mut v);
+                std::mem::swap(&mut g1, &mut g2);
+            }
+
+            let shift = u_degree.abs_diff(v_degree);
+
+            u = u.add(v.shift_left(shift));
+            g1 = g1.add(g2.shift_left(shift));
+        }
+
+        Some(g1)
+    }
+}
+
+/// Affine point of the `sect233r1` curve (`y^2 + xy = x^3 + x^2 + b`), the point at infinity is
+/// represented with `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct EccPoint {
+    pub(super) x: FieldElement,
+    pub(super) y: FieldElement,
+}
+
+impl EccPoint {
+    const B: [u8; 30] = [
+        0x00, 0x66, 0x64, 0x7E, 0xDE, 0x6C, 0x33, 0x2C, 0x7F, 0x8C, 0x09, 0x23, 0xBB, 0x58, 0x21,
+        0x3B, 0x33, 0x3B, 0x20, 0xE9, 0xCE, 0x42, 0x81, 0xFE, 0x11, 0x5F, 0x7D, 0x8F, 0x90, 0xAD,
+    ];
+
+    const GENERATOR_X: [u8; 30] = [
+        0x00, 0xFA, 0xC9, 0xDF, 0xCB, 0xAC, 0x83, 0x13, 0xBB, 0x21, 0x39, 0xF1, 0xBB, 0x75, 0x5F,
+        0xEF, 0x65, 0xBC, 0x39, 0x1F, 0x8B, 0x36, 0xF8, 0xF8, 0xEB, 0x73, 0x71, 0xFD, 0x55, 0x8B,
+    ];
+
+    const GENERATOR_Y: [u8; 30] = [
+        0x01, 0x00, 0x6A, 0x08, 0xA4, 0x19, 0x03, 0x35, 0x06, 0x78, 0xE5, 0x85, 0x28, 0xBE, 0xBF,
+        0x8A, 0x0B, 0xEF, 0xF8, 0x67, 0xA7, 0xCA, 0x36, 0x71, 0x6F, 0x7E, 0x01, 0xF8, 0x10, 0x52,
+    ];
+
+    pub(super) fn generator() -> Self {
+        Self {
+            x: FieldElement::from_bytes(&Self::GENERATOR_X).unwrap_or(FieldElement::ZERO),
+            y: FieldElement::from_bytes(&Self::GENERATOR_Y).unwrap_or(FieldElement::ZERO),
+        }
+    }
+
+    fn is_on_curve(&self) -> bool {
+        let b = FieldElement::from_bytes(&Self::B).unwrap_or(FieldElement::ZERO);
+        let x_squared = self.x.square();
+
+        let left = self.y.square().add(self.x.multiply(self.y));
+        let right = x_squared.multiply(self.x).add(x_squared).add(b);
+
+        left == right
+    }
+
+    fn add(first: Option<Self>, second: Option<Self>) -> Option<Self> {
+        let (first, second) = match (first, second) {
+            (None, point) | (point, None) => return point,
+            (Some(first), Some(second)) => (first, second),
+        };
+
+        if first.x == second.x {
+            // The opposite of a point is `(x, x + y)`
+            if first.y != second.y || first.x == FieldElement::ZERO {
+                return None;
+            }
+
+            return Some(first.double());
+        }
+
+        let lambda = first
+            .y
+            .add(second.y)
+            .multiply(first.x.add(second.x).inverse()?);
+
+        let x = lambda
+            .square()
+            .add(lambda)
+            .add(first.x)
+            .add(second.x)
+            .add(FieldElement::ONE);
+        let y = lambda.multiply(first.x.add(x)).add(x).add(first.y);
+
+        Some(Self { x, y })
+    }
+
+    fn double(self) -> Self {
+        let lambda = self.x.add(
+            self.y
+                .multiply(self.x.inverse().unwrap_or(FieldElement::ZERO)),
+        );
+
+        let x = lambda.square().add(lambda).add(FieldElement::ONE);
+        let y = self
+            .x
+            .square()
+            .add(lambda.add(FieldElement::ONE).multiply(x));
+
+        Self { x, y }
+    }
+
+    pub(super) fn multiply(self, scalar: &BigUint) -> Option<Self> {
+        let mut result = None;
+
+        for i in (0..scalar.bits()).rev() {
+            result = Self::add(result, result);
+
+            if scalar.bit(i) {
+                result = Self::add(result, Some(self));
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Vectors of Project Wycheproof (`rsa_signature_2048_sha256_test.json`), with a public
+    // exponent of 65537
+    const WYCHEPROOF_RSA_2048_MODULUS: [u8; 256] = [
+        0xA2, 0xB4, 0x51, 0xA0, 0x7D, 0x0A, 0xA5, 0xF9, 0x6E, 0x45, 0x56, 0x71, 0x51, 0x35, 0x50,
+        0x51, 0x4A, 0x8A, 0x5B, 0x46, 0x2E, 0xBE, 0xF7, 0x17, 0x09, 0x4F, 0xA1, 0xFE, 0xE8, 0x22,
+        0x24, 0xE6, 0x37, 0xF9, 0x74, 0x6D, 0x3F, 0x7C, 0xAF, 0xD3, 0x18, 0x78, 0xD8, 0x03, 0x25,
+        0xB6, 0xEF, 0x5A, 0x17, 0x00, 0xF6, 0x59, 0x03, 0xB4, 0x69, 0x42, 0x9E, 0x89, 0xD6, 0xEA,
+        0xC8, 0x84, 0x50, 0x97, 0xB5, 0xAB, 0x39, 0x31, 0x89, 0xDB, 0x92, 0x51, 0x2E, 0xD8, 0xA7,
+        0x71, 0x1A, 0x12, 0x53, 0xFA, 0xCD, 0x20, 0xF7, 0x9C, 0x15, 0xE8, 0x24, 0x7F, 0x3D, 0x3E,
+        0x42, 0xE4, 0x6E, 0x48, 0xC9, 0x8E, 0x25, 0x4A, 0x2F, 0xE9, 0x76, 0x53, 0x13, 0xA0, 0x3E,
+        0xFF, 0x8F, 0x17, 0xE1, 0xA0, 0x29, 0x39, 0x7A, 0x1F, 0xA2, 0x6A, 0x8D, 0xCE, 0x26, 0xF4,
+        0x90, 0xED, 0x81, 0x29, 0x96, 0x15, 0xD9, 0x81, 0x4C, 0x22, 0xDA, 0x61, 0x04, 0x28, 0xE0,
+        0x9C, 0x7D, 0x96, 0x58, 0x59, 0x42, 0x66, 0xF5, 0xC0, 0x21, 0xD0, 0xFC, 0xEC, 0xA0, 0x8D,
+        0x94, 0x5A, 0x12, 0xBE, 0x82, 0xDE, 0x4D, 0x1E, 0xCE, 0x6B, 0x4C, 0x03, 0x14, 0x5B, 0x5D,
+        0x34, 0x95, 0xD4, 0xED, 0x54, 0x11, 0xEB, 0x87, 0x8D, 0xAF, 0x05, 0xFD, 0x7A, 0xFC, 0x3E,
+        0x09, 0xAD, 0xA0, 0xF1, 0x12, 0x64, 0x22, 0xF5, 0x90, 0x97, 0x5A, 0x19, 0x69, 0x81, 0x6F,
+        0x48, 0x69, 0x8B, 0xCB, 0xBA, 0x1B, 0x4D, 0x9C, 0xAE, 0x79, 0xD4, 0x60, 0xD8, 0xF9, 0xF8,
+        0x5E, 0x79, 0x75, 0x00, 0x5D, 0x9B, 0xC2, 0x2C, 0x4E, 0x5A, 0xC0, 0xF7, 0xC1, 0xA4, 0x5D,
+        0x12, 0x56, 0x9A, 0x62, 0x80, 0x7D, 0x3B, 0x9A, 0x02, 0xE5, 0xA5, 0x30, 0xE7, 0x73, 0x06,
+        0x6F, 0x45, 0x3D, 0x1F, 0x5B, 0x4C, 0x2E, 0x9C, 0xF7, 0x82, 0x02, 0x83, 0xF7, 0x42, 0xB9,
+        0xD5,
+    ];
+
+    // Test case 3 (valid signature of "Test")
+    const WYCHEPROOF_RSA_2048_SHA256_VALID: [u8; 256] = [
+        0x26, 0x44, 0x91, 0xE8, 0x44, 0xC1, 0x19, 0xF1, 0x4E, 0x42, 0x5C, 0x03, 0x28, 0x21, 0x39,
+        0xA5, 0x58, 0xDC, 0xDA, 0xEB, 0x82, 0xA4, 0x62, 0x81, 0x73, 0xCD, 0x40, 0x7F, 0xD3, 0x19,
+        0xF9, 0x07, 0x6E, 0xAE, 0xBC, 0x0D, 0xD8, 0x7A, 0x1C, 0x22, 0xE4, 0xD1, 0x78, 0x39, 0x09,
+        0x68, 0x86, 0xD5, 0x8A, 0x9D, 0x5B, 0x7F, 0x7A, 0xEB, 0x63, 0xEF, 0xEC, 0x56, 0xC4, 0x5A,
+        0xC7, 0xBE, 0xAD, 0x42, 0x03, 0xB6, 0x88, 0x6E, 0x1F, 0xAA, 0x90, 0xE0, 0x28, 0xEC, 0x0A,
+        0xE0, 0x94, 0xD4, 0x6B, 0xF3, 0xF9, 0x7E, 0xFD, 0xD1, 0x90, 0x45, 0xCF, 0xBC, 0x25, 0xA1,
+        0xAB, 0xDA, 0x24, 0x32, 0x63, 0x9F, 0x98, 0x76, 0x40, 0x5C, 0x0D, 0x68, 0xF8, 0xED, 0xBF,
+        0x04, 0x7C, 0x12, 0xA4, 0x54, 0xF7, 0x68, 0x1D, 0x5D, 0x5A, 0x2B, 0x54, 0xBD, 0x37, 0x23,
+        0xD1, 0x93, 0xDB, 0xAD, 0x43, 0x38, 0xBA, 0xAD, 0x75, 0x32, 0x64, 0x00, 0x6E, 0x2D, 0x08,
+        0x93, 0x1C, 0x4B, 0x8B, 0xB7, 0x9A, 0xA1, 0xC9, 0xCA, 0xD1, 0x0E, 0xB6, 0x60, 0x5F, 0x87,
+        0xC5, 0x83, 0x1F, 0x6E, 0x2B, 0x08, 0xE0, 0x02, 0xF9, 0xC6, 0xF2, 0x11, 0x41, 0xF5, 0x84,
+        0x1D, 0x92, 0x72, 0x7D, 0xD3, 0xE1, 0xD9, 0x9C, 0x36, 0xBC, 0x56, 0x0D, 0xA3, 0xC9, 0x06,
+        0x7D, 0xF9, 0x9F, 0xCA, 0xF8, 0x18, 0x94, 0x1F, 0x72, 0x58, 0x8B, 0xE3, 0x30, 0x32, 0xBA,
+        0xD2, 0x2C, 0xAF, 0x67, 0x04, 0x22, 0x3B, 0xB1, 0x14, 0xD5, 0x75, 0xB6, 0xD0, 0x2D, 0x9D,
+        0x22, 0x2B, 0x58, 0x00, 0x05, 0xD9, 0x30, 0xE8, 0xF4, 0x0C, 0xCE, 0x9F, 0x67, 0x2E, 0xEB,
+        0xB6, 0x34, 0xA2, 0x01, 0x77, 0xD8, 0x43, 0x51, 0x62, 0x79, 0x64, 0xB8, 0x3F, 0x20, 0x53,
+        0xD7, 0x36, 0xA8, 0x4A, 0xB1, 0xA0, 0x05, 0xF6, 0x3B, 0xD5, 0xBA, 0x94, 0x3D, 0xE6, 0x20,
+        0x5C,
+    ];
+
+    // Test case 9 (signature of "123400" with the length of the `DigestInfo` in long form)
+    const WYCHEPROOF_RSA_2048_SHA256_INVALID: [u8; 256] = [
+        0x8A, 0x0E, 0x82, 0xD4, 0xF3, 0x34, 0xAC, 0xA7, 0x2A, 0x71, 0x8B, 0x6F, 0x8F, 0x64, 0x3F,
+        0x69, 0xC6, 0x04, 0x8D, 0x61, 0x9D, 0x5E, 0xBA, 0x71, 0x26, 0x3A, 0x5D, 0x2D, 0x3A, 0xC7,
+        0xA7, 0xAF, 0x13, 0x3B, 0x78, 0x0C, 0x75, 0x95, 0xCA, 0x2C, 0x98, 0x2C, 0x00, 0x6E, 0x86,
+        0x19, 0x48, 0xF5, 0x43, 0xCD, 0x02, 0x10, 0xC7, 0x58, 0x69, 0x1F, 0x96, 0x26, 0x84, 0x2C,
+        0x25, 0xDE, 0x28, 0x43, 0xEA, 0x31, 0x4C, 0xA8, 0xEF, 0xF2, 0x85, 0xD4, 0x7E, 0x0C, 0xFF,
+        0xA5, 0x41, 0x80, 0xAF, 0x41, 0x60, 0x04, 0x01, 0x70, 0x34, 0x96, 0x2A, 0x80, 0xA5, 0x95,
+        0x9C, 0x3D, 0x3F, 0x1B, 0xFC, 0x15, 0x4A, 0x48, 0x24, 0x46, 0x2A, 0x93, 0xC0, 0x14, 0xF6,
+        0xDF, 0xE9, 0x20, 0x82, 0x4F, 0x41, 0x4A, 0x6E, 0x30, 0xE0, 0xE5, 0x94, 0x4D, 0x48, 0x0B,
+        0x99, 0x94, 0x9E, 0xC7, 0xBF, 0x64, 0xC4, 0xA7, 0x46, 0x15, 0x75, 0x93, 0x3B, 0x17, 0xD7,
+        0xD6, 0xB0, 0x17, 0x8B, 0x51, 0x92, 0x39, 0x2A, 0xCE, 0x3E, 0xD8, 0x46, 0x9E, 0x8A, 0xC1,
+        0xDA, 0xCA, 0xE5, 0x1A, 0xD5, 0xD3, 0xAD, 0x73, 0xDC, 0xB2, 0x13, 0xE0, 0x56, 0xD0, 0x45,
+        0x96, 0xE6, 0x4D, 0x8C, 0x07, 0x03, 0xDE, 0xF3, 0x11, 0x0C, 0x7C, 0xEC, 0xC9, 0x3B, 0xA8,
+        0xAC, 0x80, 0x59, 0xF0, 0xF3, 0xD6, 0x6B, 0x89, 0xD8, 0x71, 0x2C, 0x62, 0xB4, 0x1D, 0x40,
+        0x7E, 0x4B, 0x4A, 0x10, 0x4F, 0x45, 0x69, 0x55, 0x8B, 0x02, 0x90, 0xC2, 0x13, 0xCA, 0xD6,
+        0xD0, 0x65, 0x38, 0x98, 0xB9, 0xDF, 0x8F, 0x4A, 0x45, 0x3A, 0xB0, 0x7D, 0x13, 0xBF, 0xB7,
+        0x59, 0x8D, 0x50, 0x5A, 0xBB, 0xD0, 0xC4, 0x98, 0x7E, 0x4F, 0x1A, 0xC6, 0x6E, 0x1A, 0x52,
+        0x3E, 0x11, 0x3E, 0x37, 0x0A, 0xB7, 0xCF, 0x99, 0x0F, 0x0C, 0xC6, 0x3F, 0x50, 0xF4, 0xF0,
+        0xA4,
+    ];
+
+    // Key and signature made by OpenSSL (`openssl dgst -sha1 -sign` with a `sect233r1` key)
+    const OPENSSL_ECC_PUBLIC_KEY: [u8; 60] = [
+        0x00, 0x7B, 0x70, 0xAE, 0x18, 0x9A, 0x5F, 0x4F, 0xD0, 0x52, 0xCD, 0x0E, 0xE7, 0xCA, 0x3A,
+        0xCA, 0x7F, 0x60, 0xA4, 0x7D, 0x19, 0x8E, 0xEF, 0x4A, 0xBB, 0x09, 0xB9, 0x5B, 0x2D, 0xD8,
+        0x00, 0x44, 0xA0, 0x40, 0xAC, 0xAF, 0x73, 0x8C, 0x38, 0x25, 0x5A, 0x76, 0xB6, 0x2F, 0x36,
+        0xC1, 0x20, 0x01, 0x9A, 0x59, 0x75, 0xD5, 0x9B, 0xEA, 0x3E, 0xD9, 0x0C, 0x25, 0xB9, 0x96,
+    ];
+
+    // Signature of "NiiEBLA"
+    const OPENSSL_ECC_SIGNATURE: [u8; 60] = [
+        0x00, 0x53, 0x9A, 0x58, 0x30, 0x24, 0x4E, 0x0F, 0xF1, 0x13, 0xE0, 0xBE, 0xAE, 0xA0, 0xE3,
+        0xF2, 0x2D, 0xAB, 0x87, 0x9C, 0xBE, 0xC3, 0xB6, 0x0F, 0x82, 0xA4, 0xFE, 0x10, 0xC3, 0xC8,
+        0x00, 0x40, 0x63, 0x83, 0x2B, 0x9B, 0xCB, 0x95, 0xED, 0xD2, 0x37, 0xBB, 0xCC, 0x08, 0x78,
+        0x24, 0x86, 0x6F, 0xB0, 0x76, 0x54, 0xC9, 0x84, 0x57, 0x7B, 0xED, 0x9C, 0x77, 0x08, 0xA0,
+    ];
+
+    fn to_bytes_30(value: &BigUint) -> [u8; 30] {
+        let bytes = value.to_bytes_be();
+
+        let mut padded = [0; 30];
+        padded[30 - bytes.len()..].copy_from_slice(&bytes);
+
+        padded
+    }
+
+    #[test]
+    fn curve_parameters() {
+        let generator = EccPoint::generator();
+
+        assert!(generator.is_on_curve());
+        assert_eq!(generator.multiply(&curve_order()), None);
+
+        let element = FieldElement::from_bytes(&EccPoint::B).unwrap();
+        assert_eq!(
+            element.multiply(element.inverse().unwrap()),
+            FieldElement::ONE
+        );
+    }
+
+    #[test]
+    fn verify_ecdsa() {
+        let order = curve_order();
+        let private_key = BigUint::from(0x5EED_u32);
+        let public_key = EccPoint::generator().multiply(&private_key).unwrap();
+
+        let digest = Sha1::digest(b"NiiEBLA");
+        let e = BigUint::from_bytes_be(&digest);
+
+        // Sign the digest with a fixed nonce
+        let k = BigUint::from(0xC0FFEE_u32);
+        let r = EccPoint::generator().multiply(&k).unwrap().x.to_biguint() % &order;
+        let k_inverse = k.modpow(&(&order - 2u32), &order);
+        let s = (k_inverse * (e + &r * private_key)) % &order;
+
+        let mut signature = [0; 60];
+        signature[..30].copy_from_slice(&to_bytes_30(&r));
+        signature[30..].copy_from_slice(&to_bytes_30(&s));
+
+        let mut key = [0; 60];
+        key[..30].copy_from_slice(&to_bytes_30(&public_key.x.to_biguint()));
+        key[30..].copy_from_slice(&to_bytes_30(&public_key.y.to_biguint()));
+
+        let verifier = CertificateVerifier::Ecc(EccVerifier::new(&key).unwrap());
+        let signature = SignedBlobHeaderSignature::EcdsaSha1(Box::new(signature));
+
+        assert!(verifier.verify(&signature, b"NiiEBLA").unwrap());
+        assert!(!verifier.verify(&signature, b"NiiEBLa").unwrap());
+
+        key[59] ^= 1;
+        assert!(EccVerifier::new(&key).is_none());
+    }
+
+    #[test]
+    fn verify_rsa_padding() {
+        // With a public exponent of one the signature is the padded digest itself
+        let verifier = CertificateVerifier::Rsa(RsaVerifier::new(&[0xFF; 256], 1));
+
+        let mut signature = [0xFF; 256];
+        signature[0] = 0x00;
+        signature[1] = 0x01;
+        signature[256 - 36] = 0x00;
+        signature[256 - 35..256 - 20].copy_from_slice(&SHA1_DIGEST_INFO_PREFIX);
+        signature[256 - 20..].copy_from_slice(&Sha1::digest(b"NiiEBLA"));
+
+        let signature = SignedBlobHeaderSignature::Rsa2048Sha1(Box::new(signature));
+
+        assert!(verifier.verify(&signature, b"NiiEBLA").unwrap());
+        assert!(!verifier.verify(&signature, b"NiiEBLa").unwrap());
+
+        assert!(matches!(
+            verifier.verify(
+                &SignedBlobHeaderSignature::Rsa4096Sha1(Box::new([0; 512])),
+                b"NiiEBLA"
+            ),
+            Err(CertificateChainError::SignatureKindMismatch)
+        ));
+    }
+
+    #[test]
+    fn verify_rsa_known_answer() {
+        let verifier =
+            CertificateVerifier::Rsa(RsaVerifier::new(&WYCHEPROOF_RSA_2048_MODULUS, 0x10001));
+
+        let signature =
+            SignedBlobHeaderSignature::Rsa2048Sha256(Box::new(WYCHEPROOF_RSA_2048_SHA256_VALID));
+        assert!(verifier.verify(&signature, b"Test").unwrap());
+        assert!(!verifier.verify(&signature, b"Tesu").unwrap());
+
+        let signature =
+            SignedBlobHeaderSignature::Rsa2048Sha256(Box::new(WYCHEPROOF_RSA_2048_SHA256_INVALID));
+        assert!(!verifier.verify(&signature, b"123400").unwrap());
+    }
+
+    #[test]
+    fn verify_ecdsa_known_answer() {
+        let verifier = CertificateVerifier::Ecc(EccVerifier::new(&OPENSSL_ECC_PUBLIC_KEY).unwrap());
+
+        let signature = SignedBlobHeaderSignature::EcdsaSha1(Box::new(OPENSSL_ECC_SIGNATURE));
+        assert!(verifier.verify(&signature, b"NiiEBLA").unwrap());
+        assert!(!verifier.verify(&signature, b"NiiEBLa").unwrap());
+
+        let mut signature = OPENSSL_ECC_SIGNATURE;
+        signature[59] ^= 1;
+
+        let signature = SignedBlobHeaderSignature::EcdsaSha1(Box::new(signature));
+        assert!(!verifier.verify(&signature, b"NiiEBLA").unwrap());
+    }
+}
@@ -0,0 +1,17 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the CTR Importable Archive (CIA), the binary file format used by Nintendo to
+//! store installable titles on the 3DS family.
+//!
+//! Only the header and the optional meta section can be parsed for now, the rest of sections use
+//! the same formats as other platforms (like [TitleMetadata](crate::title_metadata::TitleMetadata)).
+
+pub mod header;
+pub mod meta;
+
+pub use header::CiaHeader;
+pub use meta::CiaMeta;
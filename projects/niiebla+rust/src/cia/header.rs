@@ -0,0 +1,244 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the header of a CIA, with the sizes of its sections and the contents stored.
+
+use byteorder::{LE, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Seek, Write};
+use thiserror::Error;
+use util::ParseContext;
+
+const CONTEXT: ParseContext = ParseContext::new("CIA header");
+
+/// Header of a CIA. The sections (certificate chain, ticket, title metadata, contents and
+/// meta) are stored after it in that order, each one aligned to a 64 bytes boundary.
+#[derive(Debug, Clone)]
+pub struct CiaHeader {
+    /// The kind of the archive, always zero.
+    pub kind: u16,
+
+    /// The version of the format, always zero.
+    pub version: u16,
+
+    /// The size of the certificate chain.
+    pub certificate_chain_size: u32,
+
+    /// The size of the ticket.
+    pub ticket_size: u32,
+
+    /// The size of the title metadata.
+    pub title_metadata_size: u32,
+
+    /// The size of the meta section, zero if the CIA has none.
+    pub meta_size: u32,
+
+    /// The size of all the contents.
+    pub content_size: u64,
+
+    /// Bitfield of the indexes of the contents stored (the most significant bit of the first
+    /// byte is the index zero), not every content of the title metadata must be stored.
+    pub content_index: Box<[u8; Self::CONTENT_INDEX_SIZE]>,
+}
+
+impl CiaHeader {
+    /// Size of the header of a CIA in bytes, also stored as its first field.
+    pub const SIZE: u32 = 0x2020;
+
+    const CONTENT_INDEX_SIZE: usize = 0x2000;
+    const SECTION_BOUNDARY: u64 = 64;
+
+    /// Number of certificates stored on the certificate chain of a CIA.
+    pub const NUMBER_OF_CERTIFICATES_STORED: usize = 3;
+
+    /// Create a new [CiaHeader] by parsing a stream.
+    pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, CiaHeaderError> {
+        let header_size =
+            CONTEXT.field(&mut stream, "header_size", |stream| stream.read_u32::<LE>())?;
+
+        if header_size != Self::SIZE {
+            return Err(CiaHeaderError::InvalidHeaderSize(header_size));
+        }
+
+        let kind = CONTEXT.field(&mut stream, "kind", |stream| stream.read_u16::<LE>())?;
+        let version = CONTEXT.field(&mut stream, "version", |stream| stream.read_u16::<LE>())?;
+
+        let certificate_chain_size =
+            CONTEXT.field(&mut stream, "certificate_chain_size", |stream| {
+                stream.read_u32::<LE>()
+            })?;
+
+        let ticket_size =
+            CONTEXT.field(&mut stream, "ticket_size", |stream| stream.read_u32::<LE>())?;
+
+        let title_metadata_size = CONTEXT.field(&mut stream, "title_metadata_size", |stream| {
+            stream.read_u32::<LE>()
+        })?;
+
+        let meta_size =
+            CONTEXT.field(&mut stream, "meta_size", |stream| stream.read_u32::<LE>())?;
+
+        let content_size = CONTEXT.field(&mut stream, "content_size", |stream| {
+            stream.read_u64::<LE>()
+        })?;
+
+        let mut content_index = Box::new([0; Self::CONTENT_INDEX_SIZE]);
+        CONTEXT.field(&mut stream, "content_index", |stream| {
+            stream.read_exact(content_index.as_mut_slice())
+        })?;
+
+        Ok(Self {
+            kind,
+            version,
+            certificate_chain_size,
+            ticket_size,
+            title_metadata_size,
+            meta_size,
+            content_size,
+            content_index,
+        })
+    }
+
+    /// Dump the CIA header into a stream.
+    pub fn dump<T: Write>(&self, mut stream: T) -> Result<(), CiaHeaderError> {
+        stream.write_u32::<LE>(Self::SIZE)?;
+        stream.write_u16::<LE>(self.kind)?;
+        stream.write_u16::<LE>(self.version)?;
+        stream.write_u32::<LE>(self.certificate_chain_size)?;
+        stream.write_u32::<LE>(self.ticket_size)?;
+        stream.write_u32::<LE>(self.title_metadata_size)?;
+        stream.write_u32::<LE>(self.meta_size)?;
+        stream.write_u64::<LE>(self.content_size)?;
+        stream.write_all(self.content_index.as_slice())?;
+
+        Ok(())
+    }
+
+    /// Check if the content with the given index is stored inside the CIA.
+    pub fn has_content(&self, index: u16) -> bool {
+        self.content_index[index as usize / 8] & (0x80 >> (index % 8)) != 0
+    }
+
+    /// Mark the content with the given index as stored (or not) inside the CIA.
+    pub fn set_content(&mut self, index: u16, stored: bool) {
+        let mask = 0x80 >> (index % 8);
+
+        if stored {
+            self.content_index[index as usize / 8] |= mask;
+        } else {
+            self.content_index[index as usize / 8] &= !mask;
+        }
+    }
+
+    /// Get the offset of the certificate chain inside the CIA.
+    pub fn certificate_chain_offset(&self) -> u64 {
+        Self::align(Self::SIZE.into())
+    }
+
+    /// Get the offset of the ticket inside the CIA.
+    pub fn ticket_offset(&self) -> u64 {
+        self.certificate_chain_offset() + Self::align(self.certificate_chain_size.into())
+    }
+
+    /// Get the offset of the title metadata inside the CIA.
+    pub fn title_metadata_offset(&self) -> u64 {
+        self.ticket_offset() + Self::align(self.ticket_size.into())
+    }
+
+    /// Get the offset of the first content inside the CIA.
+    pub fn content_offset(&self) -> u64 {
+        self.title_metadata_offset() + Self::align(self.title_metadata_size.into())
+    }
+
+    /// Get the offset of the meta section inside the CIA, `None` if the size of the contents
+    /// overflows.
+    pub fn meta_offset(&self) -> Option<u64> {
+        self.content_offset()
+            .checked_add(self.content_size)?
+            .checked_next_multiple_of(Self::SECTION_BOUNDARY)
+    }
+
+    /// Align a size to the boundary of the sections.
+    pub fn align(size: u64) -> u64 {
+        util::align_to_boundary(size, Self::SECTION_BOUNDARY)
+    }
+}
+
+impl Default for CiaHeader {
+    fn default() -> Self {
+        Self {
+            kind: 0,
+            version: 0,
+            certificate_chain_size: 0,
+            ticket_size: 0,
+            title_metadata_size: 0,
+            meta_size: 0,
+            content_size: 0,
+            content_index: Box::new([0; Self::CONTENT_INDEX_SIZE]),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum CiaHeaderError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("Invalid size of the CIA header: {0:#X}")]
+    InvalidHeaderSize(u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn dump_and_parse() {
+        let mut header = CiaHeader {
+            certificate_chain_size: 0xA00,
+            ticket_size: 0x350,
+            title_metadata_size: 0xB34,
+            meta_size: 0x3AC0,
+            content_size: 0x1234,
+            ..CiaHeader::default()
+        };
+        header.set_content(0, true);
+        header.set_content(9, true);
+
+        let mut buffer = vec![];
+        header.dump(&mut buffer).unwrap();
+
+        assert_eq!(buffer.len(), CiaHeader::SIZE as usize);
+        assert_eq!(buffer[0x20..0x22], [0x80, 0x40]);
+
+        let header = CiaHeader::new(Cursor::new(buffer)).unwrap();
+
+        assert!(header.has_content(0));
+        assert!(!header.has_content(1));
+        assert!(header.has_content(9));
+
+        assert_eq!(header.certificate_chain_offset(), 0x2040);
+        assert_eq!(header.ticket_offset(), 0x2A40);
+        assert_eq!(header.title_metadata_offset(), 0x2DC0);
+        assert_eq!(header.content_offset(), 0x3900);
+        assert_eq!(header.meta_offset(), Some(0x4B40));
+
+        let header = CiaHeader {
+            content_size: u64::MAX - 0x10,
+            ..header
+        };
+        assert_eq!(header.meta_offset(), None);
+    }
+
+    #[test]
+    fn invalid_header_size() {
+        assert!(matches!(
+            CiaHeader::new(Cursor::new([0x20, 0, 0, 0])),
+            Err(CiaHeaderError::InvalidHeaderSize(0x20))
+        ));
+    }
+}
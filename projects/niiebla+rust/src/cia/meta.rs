@@ -0,0 +1,150 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the optional meta section stored at the end of a CIA.
+
+use crate::smdh::{Smdh, SmdhError};
+use crate::title_id::TitleId;
+use byteorder::{LE, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Seek, Write};
+use thiserror::Error;
+use util::{ParseContext, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("CIA meta");
+
+/// Meta section of a CIA, with the titles needed by the stored one and its icon.
+#[derive(Debug)]
+pub struct CiaMeta {
+    /// The IDs of the titles that must be installed for the title to work.
+    pub dependencies: Vec<TitleId>,
+
+    /// The version of the core (the firmware) needed by the title.
+    pub core_version: u32,
+
+    /// The icon and names of the title.
+    pub smdh: Smdh,
+}
+
+impl CiaMeta {
+    /// Size of a CIA meta section in bytes.
+    pub const SIZE: u32 = 0x400 + Smdh::SIZE;
+
+    const MAX_NUMBER_OF_DEPENDENCIES: usize = 48;
+
+    /// Create a new [CiaMeta] by parsing a stream.
+    pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, CiaMetaError> {
+        let mut dependencies = Vec::new();
+
+        for _ in 0..Self::MAX_NUMBER_OF_DEPENDENCIES {
+            let title_id = CONTEXT.field(&mut stream, "dependencies", |stream| {
+                stream.read_u64::<LE>()
+            })?;
+
+            // Unused slots are zeroed
+            if title_id != 0 {
+                dependencies.push(TitleId::new(title_id));
+            }
+        }
+
+        // Skip 0x180 reserved bytes
+        stream.seek_relative(0x180)?;
+
+        let core_version = CONTEXT.field(&mut stream, "core_version", |stream| {
+            stream.read_u32::<LE>()
+        })?;
+
+        // Skip 0xFC reserved bytes
+        stream.seek_relative(0xFC)?;
+
+        let smdh = Smdh::new(&mut stream)?;
+
+        Ok(Self {
+            dependencies,
+            core_version,
+            smdh,
+        })
+    }
+
+    /// Dump the CIA meta section into a stream.
+    ///
+    /// # Errors
+    /// Fails with [CiaMetaError::TooManyDependencies] if there are more than 48 dependencies.
+    pub fn dump<T: Write>(&self, mut stream: T) -> Result<(), CiaMetaError> {
+        if self.dependencies.len() > Self::MAX_NUMBER_OF_DEPENDENCIES {
+            return Err(CiaMetaError::TooManyDependencies(self.dependencies.len()));
+        }
+
+        for title_id in &self.dependencies {
+            stream.write_u64::<LE>(title_id.inner())?;
+        }
+
+        stream.write_zeroed((Self::MAX_NUMBER_OF_DEPENDENCIES - self.dependencies.len()) * 8)?;
+        stream.write_zeroed(0x180)?;
+
+        stream.write_u32::<LE>(self.core_version)?;
+        stream.write_zeroed(0xFC)?;
+
+        self.smdh.dump(&mut stream)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum CiaMetaError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("An error has occurred while parsing the SMDH: {0}")]
+    SmdhError(#[from] SmdhError),
+
+    #[error("A CIA meta section can store up to 48 dependencies, found {0}")]
+    TooManyDependencies(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smdh::tests::dummy_smdh;
+    use std::io::Cursor;
+
+    #[test]
+    fn dump_and_parse() {
+        let meta = CiaMeta {
+            dependencies: vec![TitleId::new(0x0004013000001502)],
+            core_version: 2,
+            smdh: dummy_smdh(),
+        };
+
+        let mut buffer = vec![];
+        meta.dump(&mut buffer).unwrap();
+
+        assert_eq!(buffer.len(), CiaMeta::SIZE as usize);
+
+        let meta = CiaMeta::new(Cursor::new(buffer)).unwrap();
+
+        assert_eq!(meta.dependencies.len(), 1);
+        assert_eq!(meta.dependencies[0].inner(), 0x0004013000001502);
+        assert_eq!(meta.core_version, 2);
+    }
+
+    #[test]
+    fn dump_too_many_dependencies() {
+        let meta = CiaMeta {
+            dependencies: (0..49)
+                .map(|i| TitleId::new(0x0004013000001502 + i))
+                .collect(),
+            core_version: 0,
+            smdh: dummy_smdh(),
+        };
+
+        assert!(matches!(
+            meta.dump(vec![]),
+            Err(CiaMetaError::TooManyDependencies(49))
+        ));
+    }
+}
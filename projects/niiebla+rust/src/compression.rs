@@ -0,0 +1,359 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Transparent decompression of the streams used by some homebrew distribution formats, like
+//! `title.wad.gz` archives or contents stored compressed next to the title metadata.
+//!
+//! The compression formats used cannot be seeked, so compressed streams are fully decompressed
+//! into memory.
+
+use crate::parse_limits::{ParseLimitError, ParseLimits};
+use flate2::read::GzDecoder;
+use ruzstd::decoding::StreamingDecoder;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use thiserror::Error;
+
+/// The compression formats that can be detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionFormat {
+    /// [gzip](https://en.wikipedia.org/wiki/Gzip) (DEFLATE).
+    Gzip,
+
+    /// [Zstandard](https://en.wikipedia.org/wiki/Zstd).
+    Zstd,
+
+    /// [XZ](https://en.wikipedia.org/wiki/XZ_Utils) (LZMA2).
+    Xz,
+
+    /// Raw [LZMA](https://en.wikipedia.org/wiki/Lempel%E2%80%93Ziv%E2%80%93Markov_chain_algorithm)
+    /// ("LZMA alone"), detected by its most common properties byte and the sizes of its header.
+    Lzma,
+}
+
+impl CompressionFormat {
+    const GZIP_MAGIC_NUMBERS: &[u8] = &[0x1F, 0x8B];
+    const ZSTD_MAGIC_NUMBERS: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+    const XZ_MAGIC_NUMBERS: &[u8] = &[0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00];
+    const LZMA_MAGIC_NUMBERS: &[u8] = &[0x5D, 0x00, 0x00];
+    const LZMA_HEADER_SIZE: usize = 13;
+
+    /// Number of bytes needed by [Self::detect] to detect all the formats.
+    pub const DETECTION_SIZE: usize = Self::LZMA_HEADER_SIZE;
+
+    /// Detect the compression format from the first bytes of a stream (see
+    /// [Self::DETECTION_SIZE]), `None` if the data is not compressed (or the format is unknown).
+    pub fn detect(magic_numbers: &[u8]) -> Option<Self> {
+        if magic_numbers.starts_with(Self::GZIP_MAGIC_NUMBERS) {
+            Some(Self::Gzip)
+        } else if magic_numbers.starts_with(Self::ZSTD_MAGIC_NUMBERS) {
+            Some(Self::Zstd)
+        } else if magic_numbers.starts_with(Self::XZ_MAGIC_NUMBERS) {
+            Some(Self::Xz)
+        } else if Self::is_lzma_header(magic_numbers) {
+            Some(Self::Lzma)
+        } else {
+            None
+        }
+    }
+
+    /// The LZMA header has no magic numbers, plain data starting with the properties byte is
+    /// told apart by its sizes (the same heuristic used by XZ Utils).
+    fn is_lzma_header(header: &[u8]) -> bool {
+        let Some(header) = header.get(..Self::LZMA_HEADER_SIZE) else {
+            return false;
+        };
+
+        if !header.starts_with(Self::LZMA_MAGIC_NUMBERS) {
+            return false;
+        }
+
+        let dictionary_size = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+        let uncompressed_size = u64::from_le_bytes([
+            header[5], header[6], header[7], header[8], header[9], header[10], header[11],
+            header[12],
+        ]);
+
+        // Encoders only use dictionaries of 2^n or 2^n + 2^(n - 1) bytes
+        let highest_bit = dictionary_size & dictionary_size.wrapping_sub(1);
+        let is_valid_dictionary_size = dictionary_size.is_power_of_two()
+            || highest_bit.is_power_of_two()
+                && dictionary_size.trailing_zeros() + 1 == highest_bit.trailing_zeros();
+
+        // The size is either unknown or lower than 256 GiB
+        let is_valid_uncompressed_size =
+            uncompressed_size == u64::MAX || uncompressed_size < 1 << 38;
+
+        is_valid_dictionary_size && is_valid_uncompressed_size
+    }
+}
+
+/// A stream that is decompressed in memory if a compression format is detected at its start,
+/// otherwise the data is read as is.
+///
+/// ```no_run
+/// use std::fs::File;
+/// use zelzip_niiebla::Wad;
+/// use zelzip_niiebla::compression::DecompressedStream;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut wad_stream = DecompressedStream::new(File::open("title.wad.gz")?)?;
+/// let wad = Wad::try_new_installable(&mut wad_stream)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum DecompressedStream<T> {
+    /// The stream was not compressed.
+    Plain(T),
+
+    /// The stream was compressed and has been decompressed into memory.
+    Decompressed {
+        /// The detected compression format.
+        format: CompressionFormat,
+
+        /// The decompressed data.
+        data: Cursor<Vec<u8>>,
+    },
+}
+
+impl<T: Read + Seek> DecompressedStream<T> {
+    /// Maximum size in bytes of the decompressed data allowed by [Self::new], the biggest size
+    /// that fits in the 32 bit size fields of the formats.
+    pub const MAX_SIZE: u64 = u32::MAX as u64;
+
+    /// Create a new [DecompressedStream] detecting the compression format of the data from the
+    /// current position of the stream, failing if the decompressed data is bigger than
+    /// [Self::MAX_SIZE].
+    pub fn new(stream: T) -> Result<Self, CompressionError> {
+        Self::new_with_limits(
+            stream,
+            &ParseLimits {
+                max_alloc: Self::MAX_SIZE,
+                ..ParseLimits::UNLIMITED
+            },
+        )
+    }
+
+    /// Create a new [DecompressedStream] like [Self::new], the decompression is stopped (and
+    /// fails) as soon as the decompressed data exceeds the allocation limit.
+    pub fn new_with_limits(mut stream: T, limits: &ParseLimits) -> Result<Self, CompressionError> {
+        let start_position = stream.stream_position()?;
+
+        let mut magic_numbers = vec![];
+        (&mut stream)
+            .take(CompressionFormat::DETECTION_SIZE as u64)
+            .read_to_end(&mut magic_numbers)?;
+
+        stream.seek(SeekFrom::Start(start_position))?;
+
+        let Some(format) = CompressionFormat::detect(&magic_numbers) else {
+            return Ok(Self::Plain(stream));
+        };
+
+        let mut output = LimitedWriter {
+            data: vec![],
+            limit: limits.max_alloc,
+            exceeded: false,
+        };
+
+        let result = match format {
+            CompressionFormat::Gzip => io::copy(&mut GzDecoder::new(stream), &mut output)
+                .map(|_| ())
+                .map_err(CompressionError::from),
+
+            CompressionFormat::Zstd => StreamingDecoder::new(stream)
+                .map_err(|err| CompressionError::DecompressionFailed(err.to_string()))
+                .and_then(|mut decoder| Ok(io::copy(&mut decoder, &mut output).map(|_| ())?)),
+
+            CompressionFormat::Xz => {
+                lzma_rs::xz_decompress(&mut BufReader::new(stream), &mut output)
+                    .map_err(|err| CompressionError::DecompressionFailed(err.to_string()))
+            }
+
+            CompressionFormat::Lzma => {
+                lzma_rs::lzma_decompress(&mut BufReader::new(stream), &mut output)
+                    .map_err(|err| CompressionError::DecompressionFailed(err.to_string()))
+            }
+        };
+
+        // The decoders wrap the error of the writer
+        if output.exceeded {
+            limits.check_alloc(limits.max_alloc.saturating_add(1))?;
+        }
+
+        result?;
+        let data = output.data;
+
+        Ok(Self::Decompressed {
+            format,
+            data: Cursor::new(data),
+        })
+    }
+
+    /// Get the detected compression format, `None` if the stream was not compressed.
+    pub fn format(&self) -> Option<CompressionFormat> {
+        match self {
+            Self::Plain(_) => None,
+            Self::Decompressed { format, data: _ } => Some(*format),
+        }
+    }
+}
+
+impl<T: Read> Read for DecompressedStream<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buf),
+            Self::Decompressed { format: _, data } => data.read(buf),
+        }
+    }
+}
+
+impl<T: Seek> Seek for DecompressedStream<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::Plain(stream) => stream.seek(pos),
+            Self::Decompressed { format: _, data } => data.seek(pos),
+        }
+    }
+}
+
+/// Writer into memory that fails once more data than its limit is written.
+struct LimitedWriter {
+    data: Vec<u8>,
+    limit: u64,
+    exceeded: bool,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.data.len() as u64 + buf.len() as u64 > self.limit {
+            self.exceeded = true;
+
+            return Err(io::Error::other("The decompressed data exceeds the limit"));
+        }
+
+        self.data.extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum CompressionError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("Unable to decompress the stream: {0}")]
+    DecompressionFailed(String),
+
+    #[error("Parse limit exceeded: {0}")]
+    ParseLimitError(#[from] ParseLimitError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    #[test]
+    fn gzip_and_plain_streams() {
+        let data = b"Installable WAD data".to_vec();
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed_data = encoder.finish().unwrap();
+
+        let mut stream = DecompressedStream::new(Cursor::new(compressed_data)).unwrap();
+        assert_eq!(stream.format(), Some(CompressionFormat::Gzip));
+
+        let mut decompressed_data = vec![];
+        stream.read_to_end(&mut decompressed_data).unwrap();
+        assert_eq!(decompressed_data, data);
+
+        let stream = DecompressedStream::new(Cursor::new(data)).unwrap();
+        assert_eq!(stream.format(), None);
+    }
+
+    #[test]
+    fn decompression_limit() {
+        let data = vec![0x42; 0x1000];
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed_data = encoder.finish().unwrap();
+
+        let limits = ParseLimits {
+            max_alloc: 0x1000,
+            ..ParseLimits::default()
+        };
+        assert!(
+            DecompressedStream::new_with_limits(Cursor::new(&compressed_data), &limits).is_ok()
+        );
+
+        let limits = ParseLimits {
+            max_alloc: 0xFFF,
+            ..ParseLimits::default()
+        };
+        assert!(matches!(
+            DecompressedStream::new_with_limits(Cursor::new(&compressed_data), &limits),
+            Err(CompressionError::ParseLimitError(_))
+        ));
+
+        let mut compressed_data = vec![];
+        lzma_rs::lzma_compress(&mut &data[..], &mut compressed_data).unwrap();
+
+        let limits = ParseLimits {
+            max_alloc: 0x100,
+            ..ParseLimits::default()
+        };
+        assert!(matches!(
+            DecompressedStream::new_with_limits(Cursor::new(&compressed_data), &limits),
+            Err(CompressionError::ParseLimitError(_))
+        ));
+    }
+
+    #[test]
+    fn detect_lzma_header() {
+        let mut compressed_data = vec![];
+        lzma_rs::lzma_compress(&mut &b"Installable WAD data"[..], &mut compressed_data).unwrap();
+
+        assert_eq!(
+            CompressionFormat::detect(&compressed_data),
+            Some(CompressionFormat::Lzma)
+        );
+
+        // Dictionary of 8 MiB and 12 MiB, with a known size
+        let mut header = [0x5D, 0x00, 0x00, 0x80, 0x00, 0x20, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(
+            CompressionFormat::detect(&header),
+            Some(CompressionFormat::Lzma)
+        );
+        header[3] = 0xC0;
+        assert_eq!(
+            CompressionFormat::detect(&header),
+            Some(CompressionFormat::Lzma)
+        );
+
+        // Plain data that happens to start with the properties byte
+        header[3] = 0xC1;
+        assert_eq!(CompressionFormat::detect(&header), None);
+        header[3] = 0x80;
+        header[12] = 0x01;
+        assert_eq!(CompressionFormat::detect(&header), None);
+        assert_eq!(CompressionFormat::detect(&[0x5D, 0x00, 0x00]), None);
+        assert_eq!(CompressionFormat::detect(&[0x5D; 13]), None);
+        assert_eq!(
+            CompressionFormat::detect(&[0x5D, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            None
+        );
+    }
+}
@@ -0,0 +1,446 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Composable transforms over the data of the contents of a title (decryption, encryption,
+//! hashing, compression, etc), streamed in chunks without loading the whole content into memory.
+//!
+//! Each step is a [ContentTransform], a [ContentPipeline] chains them in order, so the support of
+//! a new platform (like other cryptographic methods or hash trees) only needs new transforms
+//! instead of new read and write paths:
+//!
+//! ```
+//! use std::io::Cursor;
+//! use zelzip_niiebla::content_transform::{
+//!     AesCbcDecrypt, AesCbcEncrypt, ContentHasher, ContentPipeline, Truncate,
+//! };
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let content = b"Some content with an unaligned size";
+//!
+//! let mut encrypted = vec![];
+//! ContentPipeline::new()
+//!     .then(AesCbcEncrypt::new([0x11; 16], [0; 16]))
+//!     .run(&content[..], &mut encrypted)?;
+//!
+//! let mut hasher = ContentHasher::sha1();
+//! let mut decrypted = vec![];
+//!
+//! ContentPipeline::new()
+//!     .then(AesCbcDecrypt::new([0x11; 16], [0; 16]))
+//!     .then(Truncate::new(content.len() as u64))
+//!     .then(&mut hasher)
+//!     .run(Cursor::new(encrypted), &mut decrypted)?;
+//!
+//! assert_eq!(decrypted, content);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::title_metadata::TitleMetadataContentEntryHashKind;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::io::{self, Read, Write};
+use util::{Aes128CbcDec, Aes128CbcEnc};
+
+/// A step applied to the data of a content, chunk by chunk.
+pub trait ContentTransform {
+    /// Transform the next chunk of the data, appending the result into `output`. The result
+    /// does not need to have the size of the chunk, data can be kept until the next one.
+    fn update(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Finish the transform after the last chunk, appending the data still kept into `output`.
+    fn finish(&mut self, output: &mut Vec<u8>) -> io::Result<()> {
+        let _ = output;
+        Ok(())
+    }
+}
+
+impl<T: ContentTransform + ?Sized> ContentTransform for &mut T {
+    fn update(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        (**self).update(input, output)
+    }
+
+    fn finish(&mut self, output: &mut Vec<u8>) -> io::Result<()> {
+        (**self).finish(output)
+    }
+}
+
+impl<T: ContentTransform + ?Sized> ContentTransform for Box<T> {
+    fn update(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        (**self).update(input, output)
+    }
+
+    fn finish(&mut self, output: &mut Vec<u8>) -> io::Result<()> {
+        (**self).finish(output)
+    }
+}
+
+/// A chain of [ContentTransform], the output of each one is the input of the next one.
+///
+/// Transforms borrowed with `&mut` can be inspected after the pipeline is dropped, useful to get
+/// the digest of a [ContentHasher].
+#[derive(Default)]
+pub struct ContentPipeline<'a> {
+    transforms: Vec<Box<dyn ContentTransform + 'a>>,
+}
+
+impl<'a> ContentPipeline<'a> {
+    /// Size of the chunks read from the input.
+    pub const CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Create a new empty pipeline, it copies the data as is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a transform at the end of the pipeline.
+    pub fn then<T: ContentTransform + 'a>(mut self, transform: T) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Stream all the data of `input` through the transforms into `output`. Returns the number of
+    /// written bytes.
+    pub fn run<R: Read, W: Write>(mut self, mut input: R, mut output: W) -> io::Result<u64> {
+        let mut chunk = vec![0; Self::CHUNK_SIZE];
+        let mut written_bytes = 0;
+
+        loop {
+            let read_bytes = match input.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read_bytes) => read_bytes,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            let data = self.update(&chunk[..read_bytes])?;
+            output.write_all(&data)?;
+            written_bytes += data.len() as u64;
+        }
+
+        let data = self.finish()?;
+        output.write_all(&data)?;
+        written_bytes += data.len() as u64;
+
+        Ok(written_bytes)
+    }
+
+    /// Apply the pipeline over data already in memory.
+    pub fn run_in_memory(self, input: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = vec![];
+        self.run(input, &mut output)?;
+
+        Ok(output)
+    }
+
+    fn update(&mut self, input: &[u8]) -> io::Result<Vec<u8>> {
+        let mut data = input.to_vec();
+
+        for transform in &mut self.transforms {
+            let mut output = Vec::with_capacity(data.len());
+            transform.update(&data, &mut output)?;
+            data = output;
+        }
+
+        Ok(data)
+    }
+
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        let mut data = vec![];
+
+        // The data kept by a transform still has to go through the next ones
+        for transform in &mut self.transforms {
+            let mut output = Vec::with_capacity(data.len());
+            transform.update(&data, &mut output)?;
+            transform.finish(&mut output)?;
+            data = output;
+        }
+
+        Ok(data)
+    }
+}
+
+/// Decryption of AES-128-CBC data, the size of the whole data must be a multiple of the block
+/// size.
+pub struct AesCbcDecrypt {
+    decryptor: Aes128CbcDec,
+    pending: Vec<u8>,
+}
+
+impl AesCbcDecrypt {
+    /// Create a new decryption with the given key and IV.
+    pub fn new(key: [u8; 16], iv: [u8; 16]) -> Self {
+        Self {
+            decryptor: Aes128CbcDec::new(&key.into(), &iv.into()),
+            pending: vec![],
+        }
+    }
+}
+
+impl ContentTransform for AesCbcDecrypt {
+    fn update(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        self.pending.extend_from_slice(input);
+
+        let len = self.pending.len() - self.pending.len() % 16;
+
+        for block in self.pending[..len].chunks_exact_mut(16) {
+            self.decryptor.decrypt_block_mut(block.into());
+        }
+
+        output.extend(self.pending.drain(..len));
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _: &mut Vec<u8>) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The encrypted data is not a multiple of the block size",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Encryption of data with AES-128-CBC, the last block is padded with zeroes (as done with the
+/// contents of a title).
+pub struct AesCbcEncrypt {
+    encryptor: Aes128CbcEnc,
+    pending: Vec<u8>,
+}
+
+impl AesCbcEncrypt {
+    /// Create a new encryption with the given key and IV.
+    pub fn new(key: [u8; 16], iv: [u8; 16]) -> Self {
+        Self {
+            encryptor: Aes128CbcEnc::new(&key.into(), &iv.into()),
+            pending: vec![],
+        }
+    }
+}
+
+impl ContentTransform for AesCbcEncrypt {
+    fn update(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        self.pending.extend_from_slice(input);
+
+        let len = self.pending.len() - self.pending.len() % 16;
+
+        for block in self.pending[..len].chunks_exact_mut(16) {
+            self.encryptor.encrypt_block_mut(block.into());
+        }
+
+        output.extend(self.pending.drain(..len));
+
+        Ok(())
+    }
+
+    fn finish(&mut self, output: &mut Vec<u8>) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        self.pending.resize(16, 0);
+        self.update(&[], output)
+    }
+}
+
+/// Keep only the first bytes of the data, like the size of a content without the padding of its
+/// encryption.
+pub struct Truncate {
+    remaining: u64,
+}
+
+impl Truncate {
+    /// Create a new truncation to the given size.
+    pub fn new(len: u64) -> Self {
+        Self { remaining: len }
+    }
+}
+
+impl ContentTransform for Truncate {
+    fn update(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        let len = (input.len() as u64).min(self.remaining) as usize;
+
+        output.extend_from_slice(&input[..len]);
+        self.remaining -= len as u64;
+
+        Ok(())
+    }
+}
+
+/// Hashing of the data with the algorithm used by the title metadata, the data is passed as is.
+pub enum ContentHasher {
+    /// SHA-1, used by the entries of the V0 title metadata.
+    Sha1(Sha1),
+
+    /// SHA-256, used by the entries of the V1 title metadata.
+    Sha256(Sha256),
+}
+
+impl ContentHasher {
+    /// Create a new SHA-1 hasher.
+    pub fn sha1() -> Self {
+        Self::Sha1(Sha1::new())
+    }
+
+    /// Create a new SHA-256 hasher.
+    pub fn sha256() -> Self {
+        Self::Sha256(Sha256::new())
+    }
+
+    /// Create a hasher with the algorithm of the given hash.
+    pub fn for_hash(hash: &TitleMetadataContentEntryHashKind) -> Self {
+        match hash {
+            TitleMetadataContentEntryHashKind::Version0(_) => Self::sha1(),
+            TitleMetadataContentEntryHashKind::Version1(_) => Self::sha256(),
+        }
+    }
+
+    /// Get the hash of the data passed until now, in the format of the title metadata.
+    pub fn digest(&self) -> TitleMetadataContentEntryHashKind {
+        match self {
+            Self::Sha1(hasher) => {
+                TitleMetadataContentEntryHashKind::Version0(hasher.clone().finalize().into())
+            }
+            Self::Sha256(hasher) => {
+                TitleMetadataContentEntryHashKind::Version1(hasher.clone().finalize().into())
+            }
+        }
+    }
+}
+
+impl ContentTransform for ContentHasher {
+    fn update(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Self::Sha1(hasher) => hasher.update(input),
+            Self::Sha256(hasher) => hasher.update(input),
+        }
+
+        output.extend_from_slice(input);
+
+        Ok(())
+    }
+}
+
+/// Compression of the data with [gzip](https://en.wikipedia.org/wiki/Gzip), as read by
+/// [crate::compression::DecompressedStream].
+#[cfg(feature = "compressed_contents")]
+pub struct GzipCompress {
+    encoder: flate2::write::GzEncoder<Vec<u8>>,
+}
+
+#[cfg(feature = "compressed_contents")]
+impl GzipCompress {
+    /// Create a new compression with the default level.
+    pub fn new() -> Self {
+        Self {
+            encoder: flate2::write::GzEncoder::new(vec![], flate2::Compression::default()),
+        }
+    }
+}
+
+#[cfg(feature = "compressed_contents")]
+impl Default for GzipCompress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "compressed_contents")]
+impl ContentTransform for GzipCompress {
+    fn update(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        self.encoder.write_all(input)?;
+        output.append(self.encoder.get_mut());
+
+        Ok(())
+    }
+
+    fn finish(&mut self, output: &mut Vec<u8>) -> io::Result<()> {
+        self.encoder.try_finish()?;
+        output.append(self.encoder.get_mut());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn encrypt_and_decrypt_in_chunks() {
+        // Bigger than a chunk and not aligned to the block size
+        let plaintext: Vec<u8> = (0..ContentPipeline::CHUNK_SIZE + 0x25)
+            .map(|i| i as u8)
+            .collect();
+
+        let encrypted = ContentPipeline::new()
+            .then(AesCbcEncrypt::new([0x11; 16], [0x22; 16]))
+            .run_in_memory(&plaintext)
+            .unwrap();
+
+        assert_eq!(
+            encrypted.len(),
+            util::align_to_boundary(plaintext.len() as u64, 16) as usize
+        );
+
+        // The same as encrypting everything at once with zeroed padding
+        let mut padded_plaintext = plaintext.clone();
+        padded_plaintext.resize(encrypted.len(), 0);
+
+        let mut expected = vec![0; padded_plaintext.len()];
+        util::AesCbcStream::new(Cursor::new(&mut expected[..]), [0x11; 16], [0x22; 16])
+            .unwrap()
+            .write(&padded_plaintext)
+            .unwrap();
+        assert_eq!(encrypted, expected);
+
+        let mut hasher = ContentHasher::sha1();
+
+        let decrypted = ContentPipeline::new()
+            .then(AesCbcDecrypt::new([0x11; 16], [0x22; 16]))
+            .then(Truncate::new(plaintext.len() as u64))
+            .then(&mut hasher)
+            .run_in_memory(&encrypted)
+            .unwrap();
+
+        assert_eq!(decrypted, plaintext);
+        assert_eq!(
+            hasher.digest().as_bytes(),
+            Sha1::digest(&plaintext).as_slice()
+        );
+
+        assert!(
+            ContentPipeline::new()
+                .then(AesCbcDecrypt::new([0x11; 16], [0x22; 16]))
+                .run_in_memory(&encrypted[..0x25])
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "compressed_contents")]
+    #[test]
+    fn compress() {
+        let data = vec![0x42; ContentPipeline::CHUNK_SIZE * 2];
+
+        let compressed = ContentPipeline::new()
+            .then(GzipCompress::new())
+            .run_in_memory(&data)
+            .unwrap();
+        assert!(compressed.len() < data.len());
+
+        let mut decompressed = vec![];
+        crate::compression::DecompressedStream::new(Cursor::new(compressed))
+            .unwrap()
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+}
@@ -0,0 +1,279 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of an error type that unifies the errors of all the formats of the crate.
+
+#[cfg(feature = "bns")]
+use crate::bns::BnsError;
+use crate::certificate_chain::CertificateChainError;
+#[cfg(feature = "cia")]
+use crate::cia::meta::CiaMetaError;
+#[cfg(feature = "compressed_contents")]
+use crate::compression::CompressionError;
+#[cfg(feature = "imet")]
+use crate::imet::ImetError;
+#[cfg(feature = "lz77")]
+use crate::lz77::Lz77Error;
+#[cfg(feature = "nca")]
+use crate::nca::NcaHeaderError;
+#[cfg(feature = "pfs0")]
+use crate::pfs0::Pfs0Error;
+#[cfg(feature = "seed_db")]
+use crate::seed_db::SeedDbError;
+use crate::signed_blob_header::SignedBlobHeaderError;
+#[cfg(feature = "smdh")]
+use crate::smdh::SmdhError;
+#[cfg(feature = "sysconf")]
+use crate::sysconf::SysConfError;
+use crate::ticket::PreSwitchTicketError;
+use crate::ticket::v1::PreSwitchTicketV1Error;
+#[cfg(feature = "title_keys")]
+use crate::title_keys::TitleKeysError;
+use crate::title_metadata::TitleMetadataError;
+#[cfg(feature = "tpl")]
+use crate::tpl::TplError;
+#[cfg(feature = "u8_archive")]
+use crate::u8_archive::U8ArchiveError;
+#[cfg(feature = "vc")]
+use crate::vc::VcError;
+#[cfg(feature = "version_list")]
+use crate::version_list::VersionListError;
+#[cfg(feature = "vfs")]
+use crate::vfs::VfsError;
+#[cfg(feature = "wad")]
+use crate::wad::WadError;
+#[cfg(feature = "wad")]
+use crate::wad::installable::InstallableWadError;
+use crate::wii_common_key::CommonKeyKindError;
+#[cfg(feature = "wup")]
+use crate::wup::WupExportError;
+use std::io;
+use thiserror::Error;
+
+/// Error that can wrap the error of any of the formats of the crate, the original error is kept
+/// as its source. Useful for applications that handle multiple formats, the errors of each
+/// format can still be used for a more detailed handling.
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("An IO error has occurred: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Certificate chain error: {0}")]
+    CertificateChain(#[from] CertificateChainError),
+
+    #[error("Signed blob header error: {0}")]
+    SignedBlobHeader(#[from] SignedBlobHeaderError),
+
+    #[error("Ticket error: {0}")]
+    Ticket(#[from] PreSwitchTicketError),
+
+    #[error("Ticket V1 error: {0}")]
+    TicketV1(#[from] PreSwitchTicketV1Error),
+
+    #[error("Title metadata error: {0}")]
+    TitleMetadata(#[from] TitleMetadataError),
+
+    #[error("Common key error: {0}")]
+    CommonKey(#[from] CommonKeyKindError),
+
+    #[cfg(feature = "wad")]
+    #[error("WAD error: {0}")]
+    Wad(#[from] WadError),
+
+    #[cfg(feature = "wad")]
+    #[error("Installable WAD error: {0}")]
+    InstallableWad(#[from] InstallableWadError),
+
+    #[cfg(feature = "cia")]
+    #[error("CIA meta error: {0}")]
+    CiaMeta(#[from] CiaMetaError),
+
+    #[cfg(feature = "smdh")]
+    #[error("SMDH error: {0}")]
+    Smdh(#[from] SmdhError),
+
+    #[cfg(feature = "seed_db")]
+    #[error("Seed database error: {0}")]
+    SeedDb(#[from] SeedDbError),
+
+    #[cfg(feature = "title_keys")]
+    #[error("Title keys error: {0}")]
+    TitleKeys(#[from] TitleKeysError),
+
+    #[cfg(feature = "pfs0")]
+    #[error("PFS0 error: {0}")]
+    Pfs0(#[from] Pfs0Error),
+
+    #[cfg(feature = "nca")]
+    #[error("NCA header error: {0}")]
+    NcaHeader(#[from] NcaHeaderError),
+
+    #[cfg(feature = "wup")]
+    #[error("WUP export error: {0}")]
+    WupExport(#[from] WupExportError),
+
+    #[cfg(feature = "tpl")]
+    #[error("TPL error: {0}")]
+    Tpl(#[from] TplError),
+
+    #[cfg(feature = "bns")]
+    #[error("BNS error: {0}")]
+    Bns(#[from] BnsError),
+
+    #[cfg(feature = "compressed_contents")]
+    #[error("Compression error: {0}")]
+    Compression(#[from] CompressionError),
+
+    #[cfg(feature = "sysconf")]
+    #[error("SYSCONF error: {0}")]
+    SysConf(#[from] SysConfError),
+
+    #[cfg(feature = "imet")]
+    #[error("IMET error: {0}")]
+    Imet(#[from] ImetError),
+
+    #[cfg(feature = "version_list")]
+    #[error("Version list error: {0}")]
+    VersionList(#[from] VersionListError),
+
+    #[cfg(feature = "vfs")]
+    #[error("Virtual filesystem error: {0}")]
+    Vfs(#[from] VfsError),
+
+    #[cfg(feature = "u8_archive")]
+    #[error("U8 archive error: {0}")]
+    U8Archive(#[from] U8ArchiveError),
+
+    #[cfg(feature = "lz77")]
+    #[error("LZ77 error: {0}")]
+    Lz77(#[from] Lz77Error),
+
+    #[cfg(feature = "vc")]
+    #[error("Virtual Console error: {0}")]
+    Vc(#[from] VcError),
+}
+
+/// The format (or operation) that raised an [Error], its variants depend on the enabled cargo
+/// features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    Io,
+    CertificateChain,
+    SignedBlobHeader,
+    Ticket,
+    TitleMetadata,
+    CommonKey,
+    #[cfg(feature = "wad")]
+    Wad,
+    #[cfg(feature = "cia")]
+    CiaMeta,
+    #[cfg(feature = "smdh")]
+    Smdh,
+    #[cfg(feature = "seed_db")]
+    SeedDb,
+    #[cfg(feature = "title_keys")]
+    TitleKeys,
+    #[cfg(feature = "pfs0")]
+    Pfs0,
+    #[cfg(feature = "nca")]
+    NcaHeader,
+    #[cfg(feature = "wup")]
+    WupExport,
+    #[cfg(feature = "tpl")]
+    Tpl,
+    #[cfg(feature = "bns")]
+    Bns,
+    #[cfg(feature = "compressed_contents")]
+    Compression,
+    #[cfg(feature = "sysconf")]
+    SysConf,
+    #[cfg(feature = "imet")]
+    Imet,
+    #[cfg(feature = "version_list")]
+    VersionList,
+    #[cfg(feature = "vfs")]
+    Vfs,
+    #[cfg(feature = "u8_archive")]
+    U8Archive,
+    #[cfg(feature = "lz77")]
+    Lz77,
+    #[cfg(feature = "vc")]
+    Vc,
+}
+
+impl Error {
+    /// Get the kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Io,
+            Self::CertificateChain(_) => ErrorKind::CertificateChain,
+            Self::SignedBlobHeader(_) => ErrorKind::SignedBlobHeader,
+            Self::Ticket(_) | Self::TicketV1(_) => ErrorKind::Ticket,
+            Self::TitleMetadata(_) => ErrorKind::TitleMetadata,
+            Self::CommonKey(_) => ErrorKind::CommonKey,
+            #[cfg(feature = "wad")]
+            Self::Wad(_) | Self::InstallableWad(_) => ErrorKind::Wad,
+            #[cfg(feature = "cia")]
+            Self::CiaMeta(_) => ErrorKind::CiaMeta,
+            #[cfg(feature = "smdh")]
+            Self::Smdh(_) => ErrorKind::Smdh,
+            #[cfg(feature = "seed_db")]
+            Self::SeedDb(_) => ErrorKind::SeedDb,
+            #[cfg(feature = "title_keys")]
+            Self::TitleKeys(_) => ErrorKind::TitleKeys,
+            #[cfg(feature = "pfs0")]
+            Self::Pfs0(_) => ErrorKind::Pfs0,
+            #[cfg(feature = "nca")]
+            Self::NcaHeader(_) => ErrorKind::NcaHeader,
+            #[cfg(feature = "wup")]
+            Self::WupExport(_) => ErrorKind::WupExport,
+            #[cfg(feature = "tpl")]
+            Self::Tpl(_) => ErrorKind::Tpl,
+            #[cfg(feature = "bns")]
+            Self::Bns(_) => ErrorKind::Bns,
+            #[cfg(feature = "compressed_contents")]
+            Self::Compression(_) => ErrorKind::Compression,
+            #[cfg(feature = "sysconf")]
+            Self::SysConf(_) => ErrorKind::SysConf,
+            #[cfg(feature = "imet")]
+            Self::Imet(_) => ErrorKind::Imet,
+            #[cfg(feature = "version_list")]
+            Self::VersionList(_) => ErrorKind::VersionList,
+            #[cfg(feature = "vfs")]
+            Self::Vfs(_) => ErrorKind::Vfs,
+            #[cfg(feature = "u8_archive")]
+            Self::U8Archive(_) => ErrorKind::U8Archive,
+            #[cfg(feature = "lz77")]
+            Self::Lz77(_) => ErrorKind::Lz77,
+            #[cfg(feature = "vc")]
+            Self::Vc(_) => ErrorKind::Vc,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn kind_and_source() {
+        let error = Error::from(TitleMetadataError::UnknownPlatform(2));
+
+        assert_eq!(error.kind(), ErrorKind::TitleMetadata);
+        assert!(
+            error
+                .source()
+                .unwrap()
+                .downcast_ref::<TitleMetadataError>()
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,42 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Quirks of the files found in the wild that can be accepted on demand.
+//!
+//! By default the crate behaves like the consoles, rejecting any file that they would reject.
+//! Some tools and platforms produce files that deviate slightly from the format while still
+//! being usable, the `*_with_compat(...)` functions accept them when the matching [FormatCompat]
+//! option is set. New quirks are added as new options disabled by default, so strict users are
+//! never affected.
+
+/// Set of quirks accepted by the `*_with_compat(...)` functions of the crate.
+///
+/// The default accepts none of them (the behaviour of the console and of the functions without
+/// the `_with_compat` suffix).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FormatCompat {
+    /// Accept a SHA-1 hash padded with zeroes on the entries of a V1 title metadata where a
+    /// SHA-256 hash is expected, as stored by the Wii U.
+    pub padded_sha1_hashes: bool,
+
+    /// Accept the contents of a WAD stored back to back without padding between them (common on
+    /// WADs created by buggy tools) instead of aligned to the section boundary.
+    pub unaligned_contents: bool,
+}
+
+impl FormatCompat {
+    /// Accept no quirk, the behaviour of the console.
+    pub const STRICT: Self = Self {
+        padded_sha1_hashes: false,
+        unaligned_contents: false,
+    };
+
+    /// Accept all the known quirks.
+    pub const PERMISSIVE: Self = Self {
+        padded_sha1_hashes: true,
+        unaligned_contents: true,
+    };
+}
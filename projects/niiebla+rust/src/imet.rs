@@ -0,0 +1,263 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [IMET header](https://wiibrew.org/wiki/Opening.bnr) of the banners of
+//! the Nintendo Wii channels, with the names of the channel shown on the System Menu.
+
+use crate::telemetry;
+use byteorder::{BE, ReadBytesExt, WriteBytesExt};
+use md5::{Digest, Md5};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::string::FromUtf16Error;
+use thiserror::Error;
+use util::{ParseContext, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("IMET");
+
+const IMET_MAGIC_NUMBERS: [u8; 4] = *b"IMET";
+
+/// Header of the banner of a Wii channel (its `opening.bnr` file), stored at the start of the
+/// content with index 0 of the channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Imet {
+    /// Sizes of the `icon.bin`, `banner.bin` and `sound.bin` files of the banner.
+    pub file_sizes: [u32; 3],
+
+    /// Unknown flag, usually zero.
+    pub flag: u32,
+
+    /// The names of the channel, one per slot of [ImetLanguage]. Cannot be longer than 42 UTF-16
+    /// code units.
+    pub names: [String; Self::NUMBER_OF_NAMES],
+}
+
+impl Imet {
+    /// Size of an IMET header in bytes, including its leading padding.
+    pub const SIZE: u64 = 0x600;
+
+    /// Number of names of the channel, one per slot of [ImetLanguage].
+    pub const NUMBER_OF_NAMES: usize = 10;
+
+    const PADDING_SIZE: u64 = 0x40;
+    const NAME_LENGTH: usize = 42;
+    const HASH_OFFSET: usize = 0x5F0;
+
+    /// Create a new [Imet] by parsing a stream, its MD5 hash is validated.
+    pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, ImetError> {
+        let mut data = CONTEXT.field(&mut stream, "header", |stream| {
+            util::read_exact!(stream, Self::SIZE as usize)
+        })?;
+
+        let mut stream = Cursor::new(&data);
+        stream.seek(SeekFrom::Start(Self::PADDING_SIZE))?;
+
+        let magic_numbers = CONTEXT.field(&mut stream, "magic_numbers", |stream| {
+            util::read_exact!(stream, 4)
+        })?;
+
+        if magic_numbers != IMET_MAGIC_NUMBERS {
+            return Err(ImetError::InvalidMagicNumbers(magic_numbers));
+        }
+
+        let hash_size =
+            CONTEXT.field(&mut stream, "hash_size", |stream| stream.read_u32::<BE>())?;
+
+        if hash_size as u64 != Self::SIZE {
+            return Err(ImetError::UnknownHashSize(hash_size));
+        }
+
+        // Skip an unknown value, always 3
+        stream.seek_relative(4)?;
+
+        let mut file_sizes = [0; 3];
+
+        for file_size in &mut file_sizes {
+            *file_size =
+                CONTEXT.field(&mut stream, "file_sizes", |stream| stream.read_u32::<BE>())?;
+        }
+
+        let flag = CONTEXT.field(&mut stream, "flag", |stream| stream.read_u32::<BE>())?;
+
+        let mut names = [const { String::new() }; Self::NUMBER_OF_NAMES];
+
+        for name in &mut names {
+            let bytes = CONTEXT.field(&mut stream, "names", |stream| {
+                util::read_exact!(stream, Self::NAME_LENGTH * 2)
+            })?;
+
+            *name = utf16_string_from_null_terminated_bytes(&bytes)?;
+        }
+
+        let mut hash = [0; 16];
+        hash.copy_from_slice(&data[Self::HASH_OFFSET..Self::HASH_OFFSET + 16]);
+
+        // The hash is calculated with its own field zeroed
+        data[Self::HASH_OFFSET..Self::HASH_OFFSET + 16].fill(0);
+
+        if <[u8; 16]>::from(Md5::digest(data)) != hash {
+            telemetry::verification_failed("imet_hash");
+            return Err(ImetError::HashMismatch);
+        }
+
+        Ok(Self {
+            file_sizes,
+            flag,
+            names,
+        })
+    }
+
+    /// Find the position of the IMET header inside the data of the content with index 0 of a
+    /// channel, some channels have an extra padding of 64 bytes before it. `None` if not found.
+    pub fn position_in_content(data: &[u8]) -> Option<u64> {
+        [0, Self::PADDING_SIZE].into_iter().find(|&position| {
+            let magic_numbers_position = (position + Self::PADDING_SIZE) as usize;
+
+            data.get(magic_numbers_position..magic_numbers_position + 4)
+                == Some(IMET_MAGIC_NUMBERS.as_slice())
+        })
+    }
+
+    /// Dump the IMET header into a stream, its MD5 hash is recalculated.
+    pub fn dump<T: Write>(&self, mut stream: T) -> Result<(), ImetError> {
+        let mut data = Vec::with_capacity(Self::SIZE as usize);
+
+        data.write_zeroed(Self::PADDING_SIZE as usize)?;
+        data.write_all(&IMET_MAGIC_NUMBERS)?;
+        data.write_u32::<BE>(Self::SIZE as u32)?;
+        data.write_u32::<BE>(3)?;
+
+        for file_size in self.file_sizes {
+            data.write_u32::<BE>(file_size)?;
+        }
+
+        data.write_u32::<BE>(self.flag)?;
+
+        for name in &self.names {
+            let bytes = utf16_string_to_bytes(name);
+
+            if bytes.len() > Self::NAME_LENGTH * 2 {
+                return Err(ImetError::NameTooLong(name.clone()));
+            }
+
+            data.write_bytes_padded(&bytes, Self::NAME_LENGTH * 2)?;
+        }
+
+        data.write_zeroed(Self::SIZE as usize - data.len())?;
+
+        let hash = Md5::digest(&data);
+        data[Self::HASH_OFFSET..Self::HASH_OFFSET + 16].copy_from_slice(&hash);
+
+        stream.write_all(&data)?;
+
+        Ok(())
+    }
+
+    /// Get the name of the channel in the given language.
+    pub fn name(&self, language: ImetLanguage) -> &str {
+        &self.names[language as usize]
+    }
+
+    /// Set the name of the channel in all the languages.
+    pub fn set_name(&mut self, name: &str) -> Result<(), ImetError> {
+        if name.encode_utf16().count() > Self::NAME_LENGTH {
+            return Err(ImetError::NameTooLong(name.to_string()));
+        }
+
+        self.names = std::array::from_fn(|_| name.to_string());
+
+        Ok(())
+    }
+}
+
+fn utf16_string_from_null_terminated_bytes(buffer: &[u8]) -> Result<String, FromUtf16Error> {
+    let code_units: Vec<u16> = buffer
+        .chunks_exact(2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .take_while(|&code_unit| code_unit != 0)
+        .collect();
+
+    String::from_utf16(&code_units)
+}
+
+fn utf16_string_to_bytes(string: &str) -> Vec<u8> {
+    string.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+/// The slots of the names of a channel inside an [Imet].
+#[derive(Debug, Clone, Copy)]
+#[allow(missing_docs)]
+pub enum ImetLanguage {
+    Japanese = 0,
+    English = 1,
+    German = 2,
+    French = 3,
+    Spanish = 4,
+    Italian = 5,
+    Dutch = 6,
+    SimplifiedChinese = 7,
+    TraditionalChinese = 8,
+    Korean = 9,
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum ImetError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("The given stream is not an IMET header, invalid magic numbers: {0:?}")]
+    InvalidMagicNumbers([u8; 4]),
+
+    #[error("Unknown size of the hashed data of the IMET header: {0:#X}")]
+    UnknownHashSize(u32),
+
+    #[error("The MD5 hash of the IMET header does not match its data")]
+    HashMismatch,
+
+    #[error("Unable to parse an UTF-16 string: {0}")]
+    Utf16Error(#[from] FromUtf16Error),
+
+    #[error("The name is longer than 42 UTF-16 code units: {0:?}")]
+    NameTooLong(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_and_parse() {
+        let mut imet = Imet {
+            file_sizes: [0x1000, 0x2000, 0x3000],
+            flag: 0,
+            names: [const { String::new() }; Imet::NUMBER_OF_NAMES],
+        };
+
+        imet.set_name("Homebrew Channel").unwrap();
+        assert!(matches!(
+            imet.set_name(&"A".repeat(43)),
+            Err(ImetError::NameTooLong(_))
+        ));
+
+        let mut data = vec![0; 0x40];
+        imet.dump(&mut data).unwrap();
+
+        assert_eq!(data.len() as u64, 0x40 + Imet::SIZE);
+        assert_eq!(Imet::position_in_content(&data), Some(0x40));
+        assert_eq!(Imet::position_in_content(&data[0x40..]), Some(0));
+        assert_eq!(Imet::position_in_content(&[0; 0x100]), None);
+
+        let parsed_imet = Imet::new(Cursor::new(&data[0x40..])).unwrap();
+        assert_eq!(parsed_imet, imet);
+        assert_eq!(parsed_imet.name(ImetLanguage::Korean), "Homebrew Channel");
+
+        data[0x40 + 0x60] ^= 0xFF;
+        assert!(matches!(
+            Imet::new(Cursor::new(&data[0x40..])),
+            Err(ImetError::HashMismatch)
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the classic binary patches applied by the community to the modules of the
+//! [IOS](https://wiibrew.org/wiki/IOS) of the Nintendo Wii.
+
+use crate::CryptographicMethod;
+use crate::content_transform::{ContentPipeline, Truncate};
+use crate::ticket::PreSwitchTicket;
+use crate::title_metadata::TitleMetadata;
+use crate::wad::installable::{InstallableWad, InstallableWadError};
+use std::any::Any;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+/// A binary patch over the decrypted contents of an IOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IosPatch {
+    /// Make the hash comparisons of signatures always pass, allowing fakesigned (see the
+    /// [Trucha bug](https://wiibrew.org/wiki/Signing_bug)) titles to be installed.
+    FakeSigning,
+
+    /// Allow `ES_Identify` to be used by any title.
+    EsIdentify,
+
+    /// Give access to all the NAND filesystem regardless of its permissions.
+    NandPermissions,
+
+    /// Allow titles to be installed over others with a greater version.
+    VersionDowngrade,
+}
+
+impl IosPatch {
+    /// All the known patches.
+    pub const ALL: [Self; 4] = [
+        Self::FakeSigning,
+        Self::EsIdentify,
+        Self::NandPermissions,
+        Self::VersionDowngrade,
+    ];
+
+    /// Get the byte patterns (the original one and, if any, alternative versions) that this patch
+    /// replaces.
+    fn patterns(&self) -> &'static [&'static [u8]] {
+        match self {
+            Self::FakeSigning => &[&[0x20, 0x07, 0x23, 0xA2], &[0x20, 0x07, 0x4B, 0x0B]],
+            Self::EsIdentify => &[&[0x28, 0x03, 0xD1, 0x23]],
+            Self::NandPermissions => &[&[0x42, 0x8B, 0xD0, 0x01, 0x25, 0x66]],
+            Self::VersionDowngrade => &[&[0xD2, 0x01, 0x4E, 0x56]],
+        }
+    }
+
+    fn patch_match(&self, found: &mut [u8]) {
+        match self {
+            Self::FakeSigning => found[1] = 0x00,
+
+            Self::EsIdentify => {
+                found[2] = 0x00;
+                found[3] = 0x00;
+            }
+
+            Self::NandPermissions => found[2] = 0xE0,
+            Self::VersionDowngrade => found[0] = 0xE0,
+        }
+    }
+
+    /// Apply the patch over a decrypted content, returning the number of places where it was
+    /// applied.
+    pub fn apply(&self, data: &mut [u8]) -> usize {
+        let mut number_of_patches = 0;
+
+        for pattern in self.patterns() {
+            let mut i = 0;
+
+            while i + pattern.len() <= data.len() {
+                let found = &mut data[i..i + pattern.len()];
+
+                if found == *pattern {
+                    self.patch_match(found);
+                    number_of_patches += 1;
+
+                    i += pattern.len();
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        number_of_patches
+    }
+}
+
+/// Apply a set of patches over a decrypted content, returning the number of places where they
+/// were applied.
+pub fn patch_content(data: &mut [u8], patches: &[IosPatch]) -> usize {
+    patches.iter().map(|patch| patch.apply(data)).sum()
+}
+
+/// Apply a set of patches over all the contents of an IOS stored inside a WAD, the patched
+/// contents are reinserted using [InstallableWad::modify_content]. Returns the number of places
+/// where the patches were applied.
+///
+/// Be aware that the title metadata will need to be signed again (usually fakesigned) as the
+/// hashes of the patched contents will change.
+pub fn patch_installable_wad<T: Read + Write + Seek + Any>(
+    wad: &mut InstallableWad,
+    wad_stream: &mut T,
+    ticket: &PreSwitchTicket,
+    title_metadata: &mut TitleMetadata,
+    patches: &[IosPatch],
+) -> Result<usize, InstallableWadError> {
+    // Each modification moves the stream away from the start of the WAD
+    let wad_position = wad_stream.stream_position()?;
+    let mut total_number_of_patches = 0;
+
+    for i in 0..title_metadata.content_chunk_entries.len() {
+        let selector = title_metadata.select_with_physical_position(i);
+        let size = selector.content_entry(title_metadata)?.size;
+
+        wad_stream.seek(SeekFrom::Start(wad_position))?;
+
+        // The encrypted data is padded to the AES block size
+        let mut data = vec![];
+        ContentPipeline::new()
+            .then(ticket.decryption_transform(
+                title_metadata,
+                selector,
+                CryptographicMethod::Wii,
+            )?)
+            .then(Truncate::new(size))
+            .run(
+                wad.encrypted_content_view(&mut *wad_stream, title_metadata, selector)?,
+                &mut data,
+            )?;
+
+        let number_of_patches = patch_content(&mut data, patches);
+
+        if number_of_patches == 0 {
+            continue;
+        }
+
+        wad_stream.seek(SeekFrom::Start(wad_position))?;
+        wad.modify_content(wad_stream)
+            .set_cryptography(ticket, CryptographicMethod::Wii)
+            .replace(Cursor::new(data), selector, title_metadata)?;
+
+        total_number_of_patches += number_of_patches;
+    }
+
+    Ok(total_number_of_patches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+
+    #[test]
+    fn apply_patches() {
+        let mut data = vec![
+            0xFF, 0x20, 0x07, 0x23, 0xA2, 0x28, 0x03, 0xD1, 0x23, 0x42, 0x8B, 0xD0, 0x01, 0x25,
+            0x66, 0xD2, 0x01, 0x4E, 0x56, 0x20, 0x07, 0x4B, 0x0B,
+        ];
+
+        assert_eq!(patch_content(&mut data, &IosPatch::ALL), 5);
+
+        assert_eq!(
+            data,
+            [
+                0xFF, 0x20, 0x00, 0x23, 0xA2, 0x28, 0x03, 0x00, 0x00, 0x42, 0x8B, 0xE0, 0x01, 0x25,
+                0x66, 0xE0, 0x01, 0x4E, 0x56, 0x20, 0x00, 0x4B, 0x0B,
+            ]
+        );
+
+        // Patching twice is a no-op
+        assert_eq!(patch_content(&mut data, &IosPatch::ALL), 0);
+    }
+
+    #[test]
+    fn patch_wad() {
+        // Sizes that are not a multiple of the AES block size, with the patterns near their end
+        let mut contents = [vec![0x11; 0x25], vec![0x22; 0x9], vec![0x33; 0x53]];
+        contents[0][0x20..0x24].copy_from_slice(&[0x20, 0x07, 0x23, 0xA2]);
+        contents[2][0x4E..0x52].copy_from_slice(&[0xD2, 0x01, 0x4E, 0x56]);
+
+        let TestWad {
+            mut wad,
+            mut stream,
+            ticket,
+            mut title_metadata,
+        } = TestWadBuilder::new()
+            .content(0, contents[0].clone())
+            .content(1, contents[1].clone())
+            .content(2, contents[2].clone())
+            .build();
+
+        stream.set_position(0);
+        assert_eq!(
+            patch_installable_wad(
+                &mut wad,
+                &mut stream,
+                &ticket,
+                &mut title_metadata,
+                &IosPatch::ALL,
+            )
+            .unwrap(),
+            2
+        );
+
+        contents[0][0x21] = 0x00;
+        contents[2][0x4E] = 0xE0;
+
+        stream.set_position(0);
+        let title_metadata = wad.title_metadata(&mut stream).unwrap();
+
+        for (position, content) in contents.iter().enumerate() {
+            let mut data = vec![];
+            wad.decrypted_content_view(
+                &mut stream,
+                &ticket,
+                &title_metadata,
+                CryptographicMethod::Wii,
+                title_metadata.select_with_physical_position(position),
+            )
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+            assert_eq!(&data, content);
+        }
+    }
+}
@@ -0,0 +1,335 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [LZ77 variants](https://wiibrew.org/wiki/LZ77) used by Nintendo (types
+//! `0x10` and `0x11`), like the ones of the files of the Virtual Console titles. The data can be
+//! prefixed by the `LZ77` magic numbers.
+//!
+//! The data is always compressed with the type `0x10`, the one supported by all the consoles.
+
+use crate::parse_limits::{ParseLimitError, ParseLimits};
+use thiserror::Error;
+
+/// Magic numbers that can prefix the LZ77 header on the Nintendo Wii.
+pub const LZ77_MAGIC_NUMBERS: [u8; 4] = *b"LZ77";
+
+const LZ10_TYPE: u8 = 0x10;
+const LZ11_TYPE: u8 = 0x11;
+
+const LZ10_MIN_LENGTH: usize = 3;
+const LZ10_MAX_LENGTH: usize = 0x12;
+const WINDOW_SIZE: usize = 0x1000;
+
+// Positions of a hash chain checked for each match, trades compression ratio for speed
+const MAX_CHAIN_LENGTH: usize = 128;
+
+/// Check if the data starts with a LZ77 header (with or without the `LZ77` magic numbers).
+pub fn is_compressed(data: &[u8]) -> bool {
+    let data = data.strip_prefix(&LZ77_MAGIC_NUMBERS).unwrap_or(data);
+
+    matches!(data.first(), Some(&LZ10_TYPE | &LZ11_TYPE)) && data.len() >= 4
+}
+
+/// Decompress LZ77 data, see [decompress_with_limits].
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Lz77Error> {
+    decompress_with_limits(data, &ParseLimits::UNLIMITED)
+}
+
+/// Decompress LZ77 data, failing before decompressing if its decompressed size exceeds the
+/// allocation limit.
+pub fn decompress_with_limits(data: &[u8], limits: &ParseLimits) -> Result<Vec<u8>, Lz77Error> {
+    let data = data.strip_prefix(&LZ77_MAGIC_NUMBERS).unwrap_or(data);
+
+    let header = data.get(..4).ok_or(Lz77Error::Truncated)?;
+    let compression_type = header[0];
+    let mut size = u32::from_le_bytes([header[1], header[2], header[3], 0]) as usize;
+    let mut position = 4;
+
+    // Sizes above 16 MiB are stored in an extra field
+    if size == 0 {
+        let extended_size = data
+            .get(4..8)
+            .and_then(|bytes| <[u8; 4]>::try_from(bytes).ok())
+            .ok_or(Lz77Error::Truncated)?;
+        size = u32::from_le_bytes(extended_size) as usize;
+        position = 8;
+    }
+
+    if compression_type != LZ10_TYPE && compression_type != LZ11_TYPE {
+        return Err(Lz77Error::UnknownCompressionType(compression_type));
+    }
+
+    limits.check_alloc(size as u64)?;
+
+    // The declared size is not trusted until the data is decompressed
+    let mut output = Vec::with_capacity(size.min(data.len() * 8));
+    let mut next_byte = || {
+        let byte = data.get(position).copied().ok_or(Lz77Error::Truncated);
+        position += 1;
+        byte
+    };
+
+    while output.len() < size {
+        let flags = next_byte()?;
+
+        for bit in (0..8).rev() {
+            if output.len() >= size {
+                break;
+            }
+
+            if flags & (1 << bit) == 0 {
+                output.push(next_byte()?);
+                continue;
+            }
+
+            let first_byte = next_byte()? as usize;
+            let second_byte = next_byte()? as usize;
+
+            let (length, displacement_high, displacement_low) = if compression_type == LZ10_TYPE {
+                ((first_byte >> 4) + LZ10_MIN_LENGTH, first_byte, second_byte)
+            } else {
+                match first_byte >> 4 {
+                    0 => {
+                        let third_byte = next_byte()? as usize;
+                        let length = ((first_byte & 0xF) << 4 | second_byte >> 4) + 0x11;
+
+                        (length, second_byte, third_byte)
+                    }
+
+                    1 => {
+                        let third_byte = next_byte()? as usize;
+                        let fourth_byte = next_byte()? as usize;
+                        let length =
+                            ((first_byte & 0xF) << 12 | second_byte << 4 | third_byte >> 4) + 0x111;
+
+                        (length, third_byte, fourth_byte)
+                    }
+
+                    indicator => (indicator + 1, first_byte, second_byte),
+                }
+            };
+
+            let displacement = ((displacement_high & 0xF) << 8 | displacement_low) + 1;
+
+            if displacement > output.len() {
+                return Err(Lz77Error::InvalidDisplacement(displacement, output.len()));
+            }
+
+            // The copy can overlap with the bytes being written
+            for _ in 0..length.min(size - output.len()) {
+                output.push(output[output.len() - displacement]);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Compress data with the LZ77 type `0x10`, without the `LZ77` magic numbers.
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, Lz77Error> {
+    let size = u32::try_from(data.len()).map_err(|_| Lz77Error::DataTooBig(data.len()))?;
+
+    let mut output = vec![LZ10_TYPE];
+
+    // A zero size marks the extra size field
+    if size != 0 && size <= 0xFF_FFFF {
+        output.extend_from_slice(&size.to_le_bytes()[..3]);
+    } else {
+        output.extend_from_slice(&[0; 3]);
+        output.extend_from_slice(&size.to_le_bytes());
+    }
+
+    let mut hash_chains = HashChains::new(data);
+    let mut position = 0;
+
+    while position < data.len() {
+        let flags_position = output.len();
+        output.push(0);
+
+        for bit in (0..8).rev() {
+            if position >= data.len() {
+                break;
+            }
+
+            let (mut length, displacement) = hash_chains.longest_match(position);
+
+            if length >= LZ10_MIN_LENGTH {
+                let token = ((length - LZ10_MIN_LENGTH) << 12 | (displacement - 1)) as u16;
+
+                output[flags_position] |= 1 << bit;
+                output.extend_from_slice(&token.to_be_bytes());
+            } else {
+                length = 1;
+                output.push(data[position]);
+            }
+
+            for _ in 0..length {
+                hash_chains.insert(position);
+                position += 1;
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+// Chains of the previous positions of the data with the same three leading bytes
+struct HashChains<'a> {
+    data: &'a [u8],
+    heads: Vec<usize>,
+    previous: Vec<usize>,
+}
+
+impl<'a> HashChains<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            heads: vec![usize::MAX; 1 << 16],
+            previous: vec![usize::MAX; data.len()],
+        }
+    }
+
+    fn hash(&self, position: usize) -> usize {
+        let bytes = &self.data[position..position + LZ10_MIN_LENGTH];
+
+        ((bytes[0] as usize) << 8 ^ (bytes[1] as usize) << 4 ^ bytes[2] as usize) & 0xFFFF
+    }
+
+    fn insert(&mut self, position: usize) {
+        if position + LZ10_MIN_LENGTH <= self.data.len() {
+            let hash = self.hash(position);
+
+            self.previous[position] = self.heads[hash];
+            self.heads[hash] = position;
+        }
+    }
+
+    // Find the longest match inside the window, returns its length and displacement
+    fn longest_match(&self, position: usize) -> (usize, usize) {
+        if position + LZ10_MIN_LENGTH > self.data.len() {
+            return (0, 0);
+        }
+
+        let max_length = LZ10_MAX_LENGTH.min(self.data.len() - position);
+        let mut best = (0, 0);
+        let mut candidate = self.heads[self.hash(position)];
+
+        for _ in 0..MAX_CHAIN_LENGTH {
+            if candidate == usize::MAX || position - candidate > WINDOW_SIZE {
+                break;
+            }
+
+            let length = self.data[candidate..]
+                .iter()
+                .zip(&self.data[position..position + max_length])
+                .take_while(|(a, b)| a == b)
+                .count();
+
+            if length > best.0 {
+                best = (length, position - candidate);
+
+                if length == max_length {
+                    break;
+                }
+            }
+
+            candidate = self.previous[candidate];
+        }
+
+        best
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum Lz77Error {
+    #[error("The LZ77 data ends before its declared decompressed size")]
+    Truncated,
+
+    #[error("Unknown type of LZ77 compression: {0:#X}")]
+    UnknownCompressionType(u8),
+
+    #[error("A LZ77 back-reference of {0} bytes points before the start of the data ({1} bytes)")]
+    InvalidDisplacement(usize, usize),
+
+    #[error("The data is too big to be compressed with LZ77: {0} bytes")]
+    DataTooBig(usize),
+
+    #[error("Parse limit exceeded: {0}")]
+    ParseLimitError(#[from] ParseLimitError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data() -> Vec<u8> {
+        let mut data = b"NES\x1A".to_vec();
+
+        for i in 0..0x3000u32 {
+            data.push((i % 7) as u8);
+            data.push((i.wrapping_mul(2654435761) >> 24) as u8);
+        }
+
+        data
+    }
+
+    #[test]
+    fn compress_and_decompress() {
+        let data = sample_data();
+        let compressed = compress(&data).unwrap();
+
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < data.len());
+        assert_eq!(&compressed[..4], &[0x10, 0x04, 0x60, 0x00]);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        let with_magic_numbers = [LZ77_MAGIC_NUMBERS.as_slice(), &compressed].concat();
+        assert_eq!(decompress(&with_magic_numbers).unwrap(), data);
+
+        assert!(decompress(&compress(&[]).unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decompress_known_data() {
+        // "abc" followed by a back-reference of 6 bytes with a displacement of 3 (type 0x10)
+        assert_eq!(
+            decompress(&[0x10, 9, 0, 0, 0x10, b'a', b'b', b'c', 0x30, 0x02]).unwrap(),
+            b"abcabcabc"
+        );
+
+        // The same back-reference with the type 0x11
+        assert_eq!(
+            decompress(&[0x11, 9, 0, 0, 0x10, b'a', b'b', b'c', 0x50, 0x02]).unwrap(),
+            b"abcabcabc"
+        );
+    }
+
+    #[test]
+    fn decompress_invalid_data() {
+        assert!(matches!(
+            decompress(&[0x10, 9, 0, 0, 0x00, b'a']),
+            Err(Lz77Error::Truncated)
+        ));
+        assert!(matches!(
+            decompress(&[0x10, 9, 0, 0, 0x80, 0x30, 0x02]),
+            Err(Lz77Error::InvalidDisplacement(3, 0))
+        ));
+        assert!(matches!(
+            decompress(&[0x40, 9, 0, 0]),
+            Err(Lz77Error::UnknownCompressionType(0x40))
+        ));
+
+        let limits = ParseLimits {
+            max_alloc: 8,
+            ..ParseLimits::default()
+        };
+        assert!(matches!(
+            decompress_with_limits(&compress(&sample_data()).unwrap(), &limits),
+            Err(Lz77Error::ParseLimitError(_))
+        ));
+    }
+}
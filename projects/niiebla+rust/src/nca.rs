@@ -0,0 +1,265 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the header of the Nintendo Content Archive (NCA) format used by Nintendo on
+//! the Switch to store the contents of a title.
+//!
+//! Only enough of the header to identify the content is parsed, the sections stored inside the
+//! archive are not decrypted.
+
+use crate::title_id::TitleId;
+use aes::Aes128;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use byteorder::{LE, ReadBytesExt};
+use std::fmt::{self, Display};
+use std::io::{self, Cursor, Read};
+use thiserror::Error;
+use util::ParseContext;
+
+const CONTEXT: ParseContext = ParseContext::new("NCA header");
+
+/// The header of a NCA.
+#[derive(Debug)]
+pub struct NcaHeader {
+    /// Version of the format (`3` on `NCA3`).
+    pub version: u8,
+
+    /// The way the NCA is distributed, `0` when downloaded and `1` when stored in a game card.
+    pub distribution_type: u8,
+
+    /// The kind of the data stored inside the NCA.
+    pub content_type: NcaContentType,
+
+    /// Generation of the key used to encrypt the key area, the biggest one of the two fields
+    /// stored in the header.
+    pub key_generation: u8,
+
+    /// Size of the whole NCA in bytes.
+    pub content_size: u64,
+
+    /// The ID of the title that owns the content.
+    pub title_id: TitleId,
+
+    /// The index of the content.
+    pub content_index: u32,
+
+    /// The version of the SDK used to build the content.
+    pub sdk_version: NcaSdkVersion,
+
+    /// The rights ID of the title, all zeroes if the content is not encrypted with a title key.
+    pub rights_id: [u8; 16],
+}
+
+impl NcaHeader {
+    /// Size of the header in bytes.
+    pub const SIZE: usize = 0xC00;
+
+    const SECTOR_SIZE: usize = 0x200;
+
+    /// Create a new [NcaHeader] by decrypting and parsing a stream, the header key (usually known
+    /// as `header_key`) must be provided by the user.
+    pub fn new<T: Read>(mut stream: T, header_key: &[u8; 32]) -> Result<Self, NcaHeaderError> {
+        let mut header = vec![0; Self::SIZE];
+        stream.read_exact(&mut header)?;
+
+        // Only the first two sectors are needed, they store the main header
+        decrypt_xts_sectors(&mut header[..Self::SECTOR_SIZE * 2], header_key, 0);
+
+        let mut stream = Cursor::new(header);
+
+        // Skip the two signatures
+        stream.set_position(0x200);
+
+        let magic_numbers = CONTEXT.field(&mut stream, "magic_numbers", |stream| {
+            util::read_exact!(stream, 4)
+        })?;
+
+        let version = match &magic_numbers {
+            b"NCA3" => 3,
+            b"NCA2" => 2,
+
+            _ => return Err(NcaHeaderError::InvalidMagicNumbers(magic_numbers)),
+        };
+
+        let distribution_type =
+            CONTEXT.field(&mut stream, "distribution_type", |stream| stream.read_u8())?;
+
+        let content_type = CONTEXT.field(&mut stream, "content_type", |stream| stream.read_u8())?;
+        let content_type = NcaContentType::new(content_type)?;
+
+        let old_key_generation =
+            CONTEXT.field(&mut stream, "old_key_generation", |stream| stream.read_u8())?;
+
+        // Skip the key area encryption key index
+        stream.set_position(stream.position() + 1);
+
+        let content_size = CONTEXT.field(&mut stream, "content_size", |stream| {
+            stream.read_u64::<LE>()
+        })?;
+
+        let title_id = CONTEXT.field(&mut stream, "title_id", |stream| stream.read_u64::<LE>())?;
+
+        let content_index = CONTEXT.field(&mut stream, "content_index", |stream| {
+            stream.read_u32::<LE>()
+        })?;
+
+        let sdk_version =
+            CONTEXT.field(&mut stream, "sdk_version", |stream| stream.read_u32::<LE>())?;
+
+        let key_generation =
+            CONTEXT.field(&mut stream, "key_generation", |stream| stream.read_u8())?;
+
+        // Skip the signature key generation and the reserved bytes
+        stream.set_position(0x230);
+
+        let rights_id = CONTEXT.field(&mut stream, "rights_id", |stream| {
+            util::read_exact!(stream, 16)
+        })?;
+
+        Ok(Self {
+            version,
+            distribution_type,
+            content_type,
+            key_generation: old_key_generation.max(key_generation),
+            content_size,
+            title_id: TitleId::new(title_id),
+            content_index,
+            sdk_version: NcaSdkVersion(sdk_version),
+            rights_id,
+        })
+    }
+
+    /// Check if the content is encrypted with a title key (the rights ID is not empty).
+    pub fn has_rights_id(&self) -> bool {
+        self.rights_id != [0; 16]
+    }
+}
+
+/// Decrypt in place consecutive sectors of data encrypted with AES-128-XTS, using the non standard
+/// big-endian tweak used by Nintendo.
+fn decrypt_xts_sectors(data: &mut [u8], key: &[u8; 32], first_sector: u128) {
+    let data_cipher = Aes128::new(key[..16].into());
+    let tweak_cipher = Aes128::new(key[16..].into());
+
+    for (i, sector) in data.chunks_mut(NcaHeader::SECTOR_SIZE).enumerate() {
+        let mut tweak = (first_sector + i as u128).to_be_bytes();
+        tweak_cipher.encrypt_block((&mut tweak).into());
+
+        for block in sector.chunks_exact_mut(16) {
+            block.iter_mut().zip(tweak).for_each(|(byte, t)| *byte ^= t);
+            data_cipher.decrypt_block(block.into());
+            block.iter_mut().zip(tweak).for_each(|(byte, t)| *byte ^= t);
+
+            // Multiply the tweak by x on GF(2^128), stored as little-endian
+            let carry = tweak[15] >> 7;
+            for j in (1..16).rev() {
+                tweak[j] = (tweak[j] << 1) | (tweak[j - 1] >> 7);
+            }
+            tweak[0] = (tweak[0] << 1) ^ (carry * 0x87);
+        }
+    }
+}
+
+/// The kind of the data stored inside a NCA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum NcaContentType {
+    Program,
+    Meta,
+    Control,
+    Manual,
+    Data,
+    PublicData,
+}
+
+impl NcaContentType {
+    fn new(identifier: u8) -> Result<Self, NcaHeaderError> {
+        Ok(match identifier {
+            0 => Self::Program,
+            1 => Self::Meta,
+            2 => Self::Control,
+            3 => Self::Manual,
+            4 => Self::Data,
+            5 => Self::PublicData,
+
+            identifier => return Err(NcaHeaderError::UnknownContentType(identifier)),
+        })
+    }
+}
+
+/// Newtype wrapper around the version of the SDK used to build a NCA, formatted as
+/// `major.minor.micro-revision`.
+#[derive(Debug, Clone, Copy)]
+pub struct NcaSdkVersion(pub u32);
+
+impl Display for NcaSdkVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [revision, micro, minor, major] = self.0.to_le_bytes();
+
+        write!(f, "{major}.{minor}.{micro}-{revision}")
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum NcaHeaderError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error(
+        "The given stream is not a NCA or the header key is wrong, invalid magic numbers: {0:?}"
+    )]
+    InvalidMagicNumbers([u8; 4]),
+
+    #[error("Unknown content type: {0}")]
+    UnknownContentType(u8),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypt_xts_ieee_vector() {
+        // Vector 1 of the IEEE 1619 standard
+        let mut data = [
+            0x91, 0x7c, 0xf6, 0x9e, 0xbd, 0x68, 0xb2, 0xec, 0x9b, 0x9f, 0xe9, 0xa3, 0xea, 0xdd,
+            0xa6, 0x92, 0xcd, 0x43, 0xd2, 0xf5, 0x95, 0x98, 0xed, 0x85, 0x8c, 0x02, 0xc2, 0x65,
+            0x2f, 0xbf, 0x92, 0x2e,
+        ];
+
+        decrypt_xts_sectors(&mut data, &[0; 32], 0);
+
+        assert_eq!(data, [0; 32]);
+    }
+
+    #[test]
+    fn decrypt_xts_big_endian_sector_number() {
+        // Vector 2 of the IEEE 1619 standard, its little-endian data unit number `0x3333333333`
+        // is the big-endian sector number `0x3333333333 << 88`. It is stored on the second
+        // sector so the increment of the sector number carries through many bytes
+        let ciphertext = [
+            0xc4, 0x54, 0x18, 0x5e, 0x6a, 0x16, 0x93, 0x6e, 0x39, 0x33, 0x40, 0x38, 0xac, 0xef,
+            0x83, 0x8b, 0xfb, 0x18, 0x6f, 0xff, 0x74, 0x80, 0xad, 0xc4, 0x28, 0x93, 0x82, 0xec,
+            0xd6, 0xd3, 0x94, 0xf0,
+        ];
+
+        let mut data = vec![0; NcaHeader::SECTOR_SIZE];
+        data.extend_from_slice(&ciphertext);
+
+        let mut key = [0x11; 32];
+        key[16..].fill(0x22);
+
+        decrypt_xts_sectors(&mut data, &key, (0x3333333333 << 88) - 1);
+
+        assert_eq!(data[NcaHeader::SECTOR_SIZE..], [0x44; 32]);
+    }
+
+    #[test]
+    fn sdk_version_display() {
+        assert_eq!(NcaSdkVersion(0x000B0E00).to_string(), "0.11.14-0");
+    }
+}
@@ -7,18 +7,303 @@
 //! Crate to parse binary formats used on the
 //! [Nintendo](https://en.wikipedia.org/wiki/Nintendo) [Wii](https://en.wikipedia.org/wiki/Wii), [DSi](https://en.wikipedia.org/wiki/Nintendo_DSi), [3DS family](https://en.wikipedia.org/wiki/Nintendo_3DS) and [Wii U](https://en.wikipedia.org/wiki/Wii_U) consoles and
 //! [NUS (Nintendo Update Server)](https://wiibrew.org/wiki/NUS) and [iQue](https://en.wikipedia.org/wiki/IQue) platforms.
+//!
+//! Each major format is gated behind a cargo feature of the same name (`wad`, `ios_patch`, `cia`,
+//! `smdh`, `seed_db`, `title_keys`, `pfs0`, `nca`, `wup`, `tpl`, `bns`, `compressed_contents`,
+//! `sysconf`, `imet`, `version_list`, `vfs`, `u8_archive`, `lz77` and `vc`), all of them are enabled by default with the `full`
+//! feature. The formats shared by all of them (tickets, title metadata, certificate chains, etc)
+//! are always available.
+//!
+//! With the opt-in `metrics` feature the crate reports counters and histograms (like the number
+//! of parsed files or failed verifications) through the [`metrics`](https://docs.rs/metrics)
+//! facade, see [telemetry] for their names.
+//!
+//! Verifying the hashes of big contents (like the multi-GB ones of the Wii U) is dominated by
+//! SHA-1. The default backend already uses the SHA extensions of the CPU when available, on CPUs
+//! without them the opt-in `asm_hashes` feature switches SHA-1 and SHA-256 to their assembly
+//! implementations (needs a C compiler, only on x86, x86-64 and AArch64).
+//!
+//! All the `dump(...)` methods are reproducible: dumping the same structure always yields the
+//! same bytes, as every byte of the output is written (reserved and padding bytes are zeroed
+//! instead of skipped with a seek) and nothing depends on the iteration order of hash-based
+//! collections. Dumping into a stream with old data gives the same bytes as dumping into an
+//! empty one, so the checksums of the dumps can be safely used for preservation or to compare
+//! them on CI.
 
+#[cfg(feature = "bns")]
+pub mod bns;
+pub mod capabilities;
 pub mod certificate_chain;
+pub mod content_transform;
+#[cfg(feature = "cia")]
+pub mod cia;
+#[cfg(feature = "compressed_contents")]
+pub mod compression;
+pub mod error;
+pub mod format_compat;
+#[cfg(feature = "imet")]
+pub mod imet;
+#[cfg(feature = "lz77")]
+pub mod lz77;
+#[cfg(feature = "ios_patch")]
+pub mod ios_patch;
+#[cfg(feature = "nca")]
+pub mod nca;
+pub mod parse_limits;
+#[cfg(feature = "pfs0")]
+pub mod pfs0;
+pub mod prelude;
+#[cfg(feature = "seed_db")]
+pub mod seed_db;
 pub mod signed_blob_header;
+#[cfg(feature = "smdh")]
+pub mod smdh;
+pub mod sniff;
+#[cfg(feature = "sysconf")]
+pub mod sysconf;
+pub mod telemetry;
+pub mod templates;
 pub mod ticket;
 pub mod title_id;
+#[cfg(feature = "title_keys")]
+pub mod title_keys;
 pub mod title_metadata;
+pub mod title_version;
+#[cfg(feature = "tpl")]
+pub mod tpl;
+#[cfg(feature = "u8_archive")]
+pub mod u8_archive;
+#[cfg(feature = "vc")]
+pub mod vc;
+#[cfg(feature = "version_list")]
+pub mod version_list;
+#[cfg(feature = "vfs")]
+pub mod vfs;
+#[cfg(feature = "wad")]
 pub mod wad;
 pub mod wii_common_key;
+#[cfg(feature = "wup")]
+pub mod wup;
 
+pub use capabilities::{Capabilities, capabilities};
 pub use certificate_chain::CertificateChain;
+#[cfg(feature = "cia")]
+pub use cia::CiaMeta;
+pub use error::{Error, ErrorKind};
+pub use format_compat::FormatCompat;
+pub use parse_limits::ParseLimits;
+#[cfg(feature = "smdh")]
+pub use smdh::Smdh;
 pub use ticket::{CryptographicMethod, PreSwitchTicket};
 pub use title_metadata::{
-    TitleMetadata, TitleMetadataContentEntryKind, content_selector::ContentSelector,
+    TitleMetadata, TitleMetadataContentEntryKind,
+    content_selector::{ContentSelector, ResolvedContent},
 };
+pub use title_version::TitleVersion;
+#[cfg(feature = "wad")]
 pub use wad::Wad;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Deterministic garbage bytes (xorshift).
+    fn garbage(len: usize, mut seed: u32) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+
+                seed as u8
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "wad")]
+    fn dummy_wad() -> Vec<u8> {
+        wad::installable::test_wad::TestWadBuilder::new()
+            .content(0, [0xAA; 16])
+            .build()
+            .stream
+            .into_inner()
+    }
+
+    /// Run all the parsers of the crate over the data, only checking that none of them panics.
+    fn parse_everything(data: &[u8]) {
+        let _ = TitleMetadata::new(Cursor::new(data)).map(|title_metadata| {
+            let _ = title_metadata.select_last().content_entry(&title_metadata);
+            let _ = title_metadata.has_ppc_access_wii();
+        });
+        let _ = PreSwitchTicket::new(Cursor::new(data));
+        let _ = CertificateChain::new(Cursor::new(data), 3);
+
+        #[cfg(feature = "wad")]
+        {
+            let _ = wad::backup::BackupWadHeader::new(Cursor::new(data));
+
+            let mut stream = Cursor::new(data);
+
+            if let Ok(mut wad) = Wad::try_new_installable(&mut stream) {
+                let _ = wad.certificate_chain(&mut stream);
+                let _ = wad.ticket(&mut stream);
+
+                if let Ok(title_metadata) = wad.title_metadata(&mut stream) {
+                    let _ = wad.footer(&mut stream, &title_metadata);
+                    let _ = wad.encrypted_content_view(
+                        &mut stream,
+                        &title_metadata,
+                        title_metadata.select_last(),
+                    );
+                    let _ = wad.copy_to(&mut stream, std::io::sink(), CryptographicMethod::Wii, true);
+
+                    let mut stream = Cursor::new(data.to_vec());
+                    let _ = wad.normalize_padding(&mut stream, &title_metadata);
+                }
+            }
+        }
+
+        #[cfg(feature = "bns")]
+        {
+            let _ = bns::Bns::new(Cursor::new(data)).map(|bns| bns.decode());
+            let _ = bns::Bns::from_wav(Cursor::new(data));
+        }
+
+        #[cfg(feature = "tpl")]
+        let _ = tpl::Tpl::new(Cursor::new(data))
+            .map(|tpl| tpl.images.iter().for_each(|image| drop(image.to_rgba8())));
+
+        #[cfg(feature = "cia")]
+        let _ = CiaMeta::new(Cursor::new(data));
+
+        #[cfg(feature = "smdh")]
+        let _ = Smdh::new(Cursor::new(data));
+
+        #[cfg(feature = "sysconf")]
+        let _ = sysconf::SysConf::new(Cursor::new(data));
+
+        #[cfg(feature = "imet")]
+        let _ = imet::Imet::new(Cursor::new(data));
+
+        #[cfg(feature = "seed_db")]
+        let _ = seed_db::SeedDb::new(Cursor::new(data));
+
+        #[cfg(feature = "title_keys")]
+        let _ = title_keys::TitleKeys::new(Cursor::new(data));
+
+        #[cfg(feature = "pfs0")]
+        let _ = pfs0::Pfs0::new(Cursor::new(data));
+
+        #[cfg(feature = "nca")]
+        let _ = nca::NcaHeader::new(Cursor::new(data), &[0; 32]);
+
+        #[cfg(feature = "compressed_contents")]
+        let _ = compression::DecompressedStream::new(Cursor::new(data));
+
+        #[cfg(feature = "version_list")]
+        {
+            let _ = version_list::VersionList::new(Cursor::new(data));
+            let _ = version_list::VersionListInfo::new(Cursor::new(data));
+        }
+
+        #[cfg(feature = "vfs")]
+        if let Ok(mut wad_vfs) = vfs::WadVfs::new(Cursor::new(data)) {
+            use vfs::Vfs;
+
+            for entry in wad_vfs.read_dir("/contents").unwrap_or_default() {
+                let mut buf = [0; 0x20];
+                let _ = wad_vfs.read_at(&format!("/contents/{}", entry.name), 0, &mut buf);
+            }
+        }
+    }
+
+    /// Dump twice into buffers filled with different garbage, checking that both dumps are equal.
+    fn assert_reproducible_dump(dump: impl Fn(&mut Cursor<Vec<u8>>) -> std::io::Result<()>) {
+        let mut dumps = vec![];
+
+        for garbage_byte in [0x00, 0xFF] {
+            let mut buffer = Cursor::new(vec![garbage_byte; 0x1000]);
+            dump(&mut buffer).unwrap();
+
+            let len = buffer.position() as usize;
+            dumps.push(buffer.into_inner()[..len].to_vec());
+        }
+
+        assert_eq!(dumps[0], dumps[1]);
+    }
+
+    #[test]
+    fn reproducible_dumps() {
+        use crate::ticket::v1::{
+            PreSwitchTicketV1, PreSwitchTicketV1RecordContentConsumption, PreSwitchTicketV1Records,
+            PreSwitchTicketV1Section,
+        };
+
+        let title_metadata = templates::homebrew_channel_tmd();
+        assert_reproducible_dump(|buffer| title_metadata.dump(buffer));
+
+        let mut ticket = templates::homebrew_channel_ticket();
+        assert_reproducible_dump(|buffer| ticket.dump(buffer));
+
+        ticket.version_1_extension = Some(PreSwitchTicketV1 {
+            sections: vec![],
+            flags: 0,
+        });
+        assert_reproducible_dump(|buffer| ticket.dump(buffer));
+
+        ticket.version_1_extension = Some(PreSwitchTicketV1 {
+            sections: vec![PreSwitchTicketV1Section {
+                records: PreSwitchTicketV1Records::ContentConsumption(vec![
+                    PreSwitchTicketV1RecordContentConsumption {
+                        content_index: 1,
+                        limit_code: 2,
+                        limit_value: 3,
+                    },
+                ]),
+                flags: 0,
+            }],
+            flags: 0,
+        });
+        assert_reproducible_dump(|buffer| ticket.dump(buffer));
+
+        #[cfg(feature = "wad")]
+        assert_eq!(dummy_wad(), dummy_wad());
+    }
+
+    #[test]
+    fn truncated_and_garbage_inputs() {
+        let mut samples = vec![];
+
+        let mut buffer = Cursor::new(vec![]);
+        templates::homebrew_channel_tmd().dump(&mut buffer).unwrap();
+        samples.push(buffer.into_inner());
+
+        let mut buffer = Cursor::new(vec![]);
+        templates::homebrew_channel_ticket()
+            .dump(&mut buffer)
+            .unwrap();
+        samples.push(buffer.into_inner());
+
+        #[cfg(feature = "wad")]
+        samples.push(dummy_wad());
+
+        for sample in &samples {
+            for len in 0..sample.len() {
+                parse_everything(&sample[..len]);
+            }
+
+            // Corrupt one byte at a time
+            for i in 0..sample.len() {
+                let mut corrupted_sample = sample.clone();
+                corrupted_sample[i] ^= 0xFF;
+
+                parse_everything(&corrupted_sample);
+            }
+        }
+
+        for seed in 1..64 {
+            parse_everything(&garbage(0x400, seed));
+        }
+    }
+}
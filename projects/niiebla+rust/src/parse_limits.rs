@@ -0,0 +1,153 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Limits over the memory used when parsing untrusted files.
+//!
+//! Some of the parsed values (like the number of contents of a title metadata or the number of
+//! records of a V1 ticket) are used as counts without any kind of validation, a crafted file can
+//! use them to make the parser allocate big amounts of memory. All the `new_with_limits(...)`
+//! and `*_with_limits(...)` functions of the crate fail before allocating if any of the given
+//! [ParseLimits] is exceeded.
+
+use thiserror::Error;
+
+/// Upper bounds of the counts and sizes read from a file while parsing it.
+///
+/// The defaults are generous enough for any title distributed by Nintendo, the limits are only
+/// applied when explicitly requested (the plain `new(...)` functions stay unbounded).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Maximum number of contents of a title metadata.
+    pub max_contents: usize,
+
+    /// Maximum number of sections of a structure (like the sections of a V1 ticket or the
+    /// certificates of a certificate chain).
+    pub max_sections: usize,
+
+    /// Maximum size in bytes of a single buffer allocated from a size stored in the file.
+    pub max_alloc: u64,
+}
+
+impl ParseLimits {
+    /// Limits that never fail, the behaviour of the plain `new(...)` functions.
+    pub const UNLIMITED: Self = Self {
+        max_contents: usize::MAX,
+        max_sections: usize::MAX,
+        max_alloc: u64::MAX,
+    };
+
+    /// Check the number of contents of a title metadata.
+    pub fn check_contents(&self, number_of_contents: usize) -> Result<(), ParseLimitError> {
+        if number_of_contents > self.max_contents {
+            return Err(ParseLimitError::TooManyContents(
+                number_of_contents,
+                self.max_contents,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Check the number of sections of a structure.
+    pub fn check_sections(&self, number_of_sections: usize) -> Result<(), ParseLimitError> {
+        if number_of_sections > self.max_sections {
+            return Err(ParseLimitError::TooManySections(
+                number_of_sections,
+                self.max_sections,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Check the size of a buffer before allocating it.
+    pub fn check_alloc(&self, size: u64) -> Result<(), ParseLimitError> {
+        if size > self.max_alloc {
+            return Err(ParseLimitError::AllocationTooBig(size, self.max_alloc));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_contents: 4096,
+            max_sections: 64,
+            // 16 MiB
+            max_alloc: 16 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum ParseLimitError {
+    #[error("Too many contents: {0} (limit: {1})")]
+    TooManyContents(usize, usize),
+
+    #[error("Too many sections: {0} (limit: {1})")]
+    TooManySections(usize, usize),
+
+    #[error("The size to allocate is too big: {0} bytes (limit: {1} bytes)")]
+    AllocationTooBig(u64, u64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title_metadata::{
+        TitleMetadata, TitleMetadataContentEntry, TitleMetadataContentEntryHashKind,
+        TitleMetadataContentEntryKind, TitleMetadataError,
+    };
+    use crate::{CertificateChain, certificate_chain::CertificateChainError, templates};
+    use std::io::Cursor;
+
+    #[test]
+    fn limit_number_of_contents() {
+        let mut title_metadata = templates::homebrew_channel_tmd();
+
+        for id in 0..2 {
+            title_metadata
+                .content_chunk_entries
+                .push(TitleMetadataContentEntry {
+                    id,
+                    index: id as u16,
+                    kind: TitleMetadataContentEntryKind::Normal,
+                    size: 0x10,
+                    hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+                });
+        }
+
+        let mut buffer = Cursor::new(vec![]);
+        title_metadata.dump(&mut buffer).unwrap();
+
+        buffer.set_position(0);
+        TitleMetadata::new_with_limits(&mut buffer, &ParseLimits::default()).unwrap();
+
+        let limits = ParseLimits {
+            max_contents: 1,
+            ..ParseLimits::default()
+        };
+
+        buffer.set_position(0);
+        assert!(matches!(
+            TitleMetadata::new_with_limits(&mut buffer, &limits),
+            Err(TitleMetadataError::ParseLimitError(
+                ParseLimitError::TooManyContents(2, 1)
+            ))
+        ));
+
+        // The limits are checked before reading anything
+        assert!(matches!(
+            CertificateChain::new_with_limits(Cursor::new(vec![]), 1000, &limits),
+            Err(CertificateChainError::ParseLimitError(
+                ParseLimitError::TooManySections(1000, 64)
+            ))
+        ));
+    }
+}
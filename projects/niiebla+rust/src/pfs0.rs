@@ -0,0 +1,229 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the PFS0 (Partition File System) archive format used by Nintendo on the
+//! Switch, also used as the container of the NSP packages.
+
+use byteorder::{LE, ReadBytesExt};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::string::FromUtf8Error;
+use thiserror::Error;
+use util::{ParseContext, ReadEx, StreamPin, StringEx, View};
+
+const CONTEXT: ParseContext = ParseContext::new("PFS0");
+
+const PFS0_MAGIC_NUMBERS: [u8; 4] = *b"PFS0";
+
+/// A PFS0 archive, only its header is kept in memory, the data of the entries must be read from
+/// the original stream.
+#[derive(Debug)]
+pub struct Pfs0 {
+    /// The entries stored inside the archive.
+    pub entries: Vec<Pfs0Entry>,
+
+    data_offset: u64,
+}
+
+/// A file stored inside a PFS0 archive.
+#[derive(Debug)]
+pub struct Pfs0Entry {
+    /// The name of the file.
+    pub name: String,
+
+    /// Offset of the data of the file, relative to the start of the data section of the archive.
+    pub offset: u64,
+
+    /// Size of the file in bytes.
+    pub size: u64,
+}
+
+impl Pfs0 {
+    /// Create a new [Pfs0] by parsing a stream.
+    pub fn new<T: Read + Seek>(stream: T) -> Result<Self, Pfs0Error> {
+        let mut stream = StreamPin::new(stream)?;
+
+        let magic_numbers = CONTEXT.field(&mut stream, "magic_numbers", |stream| {
+            util::read_exact!(stream, 4)
+        })?;
+
+        if magic_numbers != PFS0_MAGIC_NUMBERS {
+            return Err(Pfs0Error::InvalidMagicNumbers(magic_numbers));
+        }
+
+        let number_of_entries = CONTEXT.field(&mut stream, "number_of_entries", |stream| {
+            stream.read_u32::<LE>()
+        })?;
+
+        let string_table_size = CONTEXT.field(&mut stream, "string_table_size", |stream| {
+            stream.read_u32::<LE>()
+        })?;
+
+        // Skip four reserved bytes
+        stream.seek_relative(4)?;
+
+        let mut raw_entries = Vec::new();
+
+        for _ in 0..number_of_entries {
+            raw_entries.push(CONTEXT.field(&mut stream, "entry", |stream| {
+                let offset = stream.read_u64::<LE>()?;
+                let size = stream.read_u64::<LE>()?;
+                let name_offset = stream.read_u32::<LE>()?;
+
+                // Skip four reserved bytes
+                stream.seek_relative(4)?;
+
+                Ok((offset, size, name_offset))
+            })?);
+        }
+
+        let string_table = CONTEXT.field(&mut stream, "string_table", |stream| {
+            stream.read_exact_vec(string_table_size.into())
+        })?;
+
+        let mut entries = Vec::new();
+
+        for (offset, size, name_offset) in raw_entries {
+            let name_bytes = string_table
+                .get(name_offset as usize..)
+                .ok_or(Pfs0Error::NameOutOfBounds(name_offset))?;
+
+            entries.push(Pfs0Entry {
+                name: String::from_null_terminated_bytes(name_bytes)?,
+                offset,
+                size,
+            });
+        }
+
+        Ok(Self {
+            entries,
+            data_offset: stream.stream_position()?,
+        })
+    }
+
+    /// Get the entry with the given name.
+    pub fn entry(&self, name: &str) -> Option<&Pfs0Entry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Get all the entries whose name ends with the given extension (like `.tik` or `.cnmt.nca`).
+    pub fn entries_with_extension<'a>(
+        &'a self,
+        extension: &'a str,
+    ) -> impl Iterator<Item = &'a Pfs0Entry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.name.ends_with(extension))
+    }
+
+    /// Get the tickets (`.tik`) stored inside the archive.
+    pub fn tickets(&self) -> impl Iterator<Item = &Pfs0Entry> {
+        self.entries_with_extension(".tik")
+    }
+
+    /// Get the certificate chains (`.cert`) stored inside the archive.
+    pub fn certificate_chains(&self) -> impl Iterator<Item = &Pfs0Entry> {
+        self.entries_with_extension(".cert")
+    }
+
+    /// Get the NCAs with content metadata (`.cnmt.nca`) stored inside the archive.
+    pub fn content_metadata_ncas(&self) -> impl Iterator<Item = &Pfs0Entry> {
+        self.entries_with_extension(".cnmt.nca")
+    }
+
+    /// Seek the stream of the archive to the start of the data of an entry.
+    ///
+    /// The position of the stream is assumed to be the same one used when the archive was parsed.
+    pub fn seek_entry<T: Seek>(&self, mut stream: T, entry: &Pfs0Entry) -> io::Result<()> {
+        stream.seek(SeekFrom::Start(self.data_offset + entry.offset))?;
+
+        Ok(())
+    }
+
+    /// Create a [View] into the data of an entry stored inside the archive stream.
+    pub fn entry_view<T: Read + Seek>(
+        &self,
+        mut stream: T,
+        entry: &Pfs0Entry,
+    ) -> Result<View<T>, Pfs0Error> {
+        if entry.size == 0 {
+            return Err(Pfs0Error::EmptyEntry);
+        }
+
+        self.seek_entry(&mut stream, entry)?;
+
+        Ok(View::new(stream, entry.size as usize)?)
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum Pfs0Error {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("The given stream is not a PFS0, invalid magic numbers: {0:?}")]
+    InvalidMagicNumbers([u8; 4]),
+
+    #[error("The offset of the name of an entry is out of the string table: {0:#X}")]
+    NameOutOfBounds(u32),
+
+    #[error("Unable to parse the name of an entry: {0}")]
+    Utf8Error(#[from] FromUtf8Error),
+
+    #[error("The entry has no data")]
+    EmptyEntry,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::WriteBytesExt;
+    use std::io::Cursor;
+
+    fn dummy_pfs0() -> Vec<u8> {
+        let mut buffer = vec![];
+
+        buffer.extend_from_slice(b"PFS0");
+        buffer.write_u32::<LE>(2).unwrap();
+        buffer.write_u32::<LE>(0x20).unwrap();
+        buffer.write_u32::<LE>(0).unwrap();
+
+        for (offset, size, name_offset) in [(0, 3, 0), (3, 2, 12)] {
+            buffer.write_u64::<LE>(offset).unwrap();
+            buffer.write_u64::<LE>(size).unwrap();
+            buffer.write_u32::<LE>(name_offset).unwrap();
+            buffer.write_u32::<LE>(0).unwrap();
+        }
+
+        let mut string_table = b"0123456.tik\0abcd.cnmt.nca\0".to_vec();
+        string_table.resize(0x20, 0);
+        buffer.extend_from_slice(&string_table);
+
+        buffer.extend_from_slice(b"TIKNC");
+
+        buffer
+    }
+
+    #[test]
+    fn parse_and_read_entries() {
+        let mut stream = Cursor::new(dummy_pfs0());
+        let pfs0 = Pfs0::new(&mut stream).unwrap();
+
+        assert_eq!(pfs0.entries.len(), 2);
+        assert_eq!(pfs0.tickets().next().unwrap().name, "0123456.tik");
+
+        let entry = pfs0.content_metadata_ncas().next().unwrap();
+        assert_eq!(entry.name, "abcd.cnmt.nca");
+
+        let mut data = vec![];
+        pfs0.entry_view(&mut stream, entry)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(data, b"NC");
+    }
+}
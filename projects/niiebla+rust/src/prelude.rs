@@ -0,0 +1,23 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! The commonly used types of the crate, import all of them at once with:
+//!
+//! ```
+//! use zelzip_niiebla::prelude::*;
+//! ```
+
+pub use crate::certificate_chain::{CertificateChain, CertificateChainError};
+pub use crate::error::{Error, ErrorKind};
+pub use crate::ticket::{CryptographicMethod, PreSwitchTicket, PreSwitchTicketError};
+pub use crate::title_id::TitleId;
+pub use crate::title_metadata::content_selector::ContentSelector;
+pub use crate::title_metadata::{TitleMetadata, TitleMetadataError};
+pub use crate::title_version::TitleVersion;
+#[cfg(feature = "wad")]
+pub use crate::wad::installable::{InstallableWad, InstallableWadError};
+#[cfg(feature = "wad")]
+pub use crate::wad::{Wad, WadError};
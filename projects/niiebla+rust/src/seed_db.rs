@@ -0,0 +1,320 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the seed database (`seeddb.bin`) used to decrypt 3DS titles released with
+//! the 9.6.0 system update and forward, of the derivation of their seeded keys and of the keys
+//! used by [CryptographicMethod::The3ds].
+
+use crate::title_id::TitleId;
+use crate::wii_common_key::CommonKeyKindError;
+use byteorder::{LE, ReadBytesExt, WriteBytesExt};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Seek, Write};
+
+#[cfg(doc)]
+use crate::ticket::CryptographicMethod;
+use thiserror::Error;
+use util::{ParseContext, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("seed database");
+
+/// Collection of the seeds associated to 3DS titles.
+#[derive(Debug)]
+pub struct SeedDb {
+    /// The entries stored inside the database.
+    pub entries: Vec<SeedDbEntry>,
+}
+
+/// A seed associated to a title.
+#[derive(Debug)]
+pub struct SeedDbEntry {
+    /// The ID of the title.
+    pub title_id: TitleId,
+
+    /// The seed of the title.
+    pub seed: [u8; 16],
+}
+
+impl SeedDb {
+    /// Create a new [SeedDb] by parsing a stream.
+    pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, SeedDbError> {
+        let number_of_entries = CONTEXT.field(&mut stream, "number_of_entries", |stream| {
+            stream.read_u32::<LE>()
+        })?;
+
+        // Skip twelve padding bytes
+        stream.seek_relative(12)?;
+
+        let mut entries = Vec::new();
+
+        for _ in 0..number_of_entries {
+            let (title_id, seed) = CONTEXT.field(&mut stream, "entry", |stream| {
+                let title_id = TitleId::new(stream.read_u64::<LE>()?);
+                let seed = util::read_exact!(stream, 16)?;
+
+                // Skip eight padding bytes
+                stream.seek_relative(8)?;
+
+                Ok((title_id, seed))
+            })?;
+
+            entries.push(SeedDbEntry { title_id, seed });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Dump the seed database into a stream.
+    pub fn dump<T: Write>(&self, mut stream: T) -> io::Result<()> {
+        stream.write_u32::<LE>(self.entries.len() as u32)?;
+        stream.write_zeroed(12)?;
+
+        for entry in &self.entries {
+            stream.write_u64::<LE>(entry.title_id.inner())?;
+            stream.write_all(&entry.seed)?;
+            stream.write_zeroed(8)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the seed associated with a title.
+    pub fn seed(&self, title_id: &TitleId) -> Option<&[u8; 16]> {
+        self.entries
+            .iter()
+            .find(|entry| entry.title_id.inner() == title_id.inner())
+            .map(|entry| &entry.seed)
+    }
+}
+
+/// Keys of the 3DS needed by [CryptographicMethod::The3ds] to decrypt the title keys of the
+/// tickets, they are not bundled with the crate and must be dumped from a console.
+///
+/// The title keys are encrypted with the normal key of the keyslot `0x3D`, derived from its keyX
+/// and the keyY selected by the common key index of the ticket.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct The3dsKeys {
+    /// The keyX of the keyslot `0x3D`.
+    pub ticket_key_x: [u8; 16],
+
+    /// The keyYs of the keyslot `0x3D`, one per common key index of the tickets.
+    pub ticket_common_key_ys: [[u8; 16]; 6],
+}
+
+impl The3dsKeys {
+    /// Get the normal key used to decrypt the title key of a ticket given its common key index.
+    pub fn ticket_normal_key(&self, common_key_index: u8) -> Result<[u8; 16], CommonKeyKindError> {
+        let key_y = self
+            .ticket_common_key_ys
+            .get(common_key_index as usize)
+            .ok_or(CommonKeyKindError::UnknownCommonKeyIndex(common_key_index))?;
+
+        Ok(normal_key(&self.ticket_key_x, key_y))
+    }
+}
+
+/// Derive the normal key of a keyslot from its keyX and keyY with the key scrambler of the 3DS.
+pub fn normal_key(key_x: &[u8; 16], key_y: &[u8; 16]) -> [u8; 16] {
+    const SCRAMBLER_CONSTANT: u128 = 0x1FF9E9AAC5FE0408024591DC5D52768A;
+
+    let key_x = u128::from_be_bytes(*key_x);
+    let key_y = u128::from_be_bytes(*key_y);
+
+    ((key_x.rotate_left(2) ^ key_y).wrapping_add(SCRAMBLER_CONSTANT))
+        .rotate_left(87)
+        .to_be_bytes()
+}
+
+/// Derive the normal key used to decrypt the sections of a NCCH, the seed of the title (see
+/// [SeedDb::seed]) must be given if it is seed encrypted.
+pub fn ncch_normal_key(key_x: &[u8; 16], key_y: &[u8; 16], seed: Option<&[u8; 16]>) -> [u8; 16] {
+    match seed {
+        Some(seed) => normal_key(key_x, &seeded_key_y(key_y, seed)),
+        None => normal_key(key_x, key_y),
+    }
+}
+
+/// Derive the keyY used to decrypt a seed encrypted title from the original keyY (the first 16
+/// bytes of the signature of its NCCH header) and its seed.
+pub fn seeded_key_y(key_y: &[u8; 16], seed: &[u8; 16]) -> [u8; 16] {
+    let mut hasher = Sha256::new();
+    hasher.update(key_y);
+    hasher.update(seed);
+
+    let mut seeded_key_y = [0; 16];
+    seeded_key_y.copy_from_slice(&hasher.finalize()[..16]);
+
+    seeded_key_y
+}
+
+/// Check if a seed is the one of the title using the seed verification hash stored inside its
+/// NCCH header.
+pub fn verify_seed(seed: &[u8; 16], title_id: &TitleId, seed_verification_hash: &[u8; 4]) -> bool {
+    let mut hasher = Sha256::new();
+    hasher.update(seed);
+    hasher.update(title_id.inner().to_le_bytes());
+
+    hasher.finalize()[..4] == *seed_verification_hash
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum SeedDbError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content_transform::ContentTransform;
+    use crate::templates;
+    use crate::ticket::{CryptographicMethod, PreSwitchTicketError};
+    use crate::title_metadata::{
+        TitleMetadataContentEntry, TitleMetadataContentEntryHashKind, TitleMetadataContentEntryKind,
+    };
+    use aes::cipher::{BlockEncryptMut, KeyIvInit};
+    use std::io::{Cursor, Read};
+    use util::Aes128CbcEnc;
+
+    const TEST_TITLE_ID: u64 = 0x0004000000123400;
+
+    #[test]
+    fn dump_and_parse() {
+        let seed_db = SeedDb {
+            entries: vec![SeedDbEntry {
+                title_id: TitleId::new(TEST_TITLE_ID),
+                seed: [7; 16],
+            }],
+        };
+
+        let mut buffer = vec![];
+        seed_db.dump(&mut buffer).unwrap();
+
+        assert_eq!(buffer.len(), 16 + 32);
+
+        let seed_db = SeedDb::new(Cursor::new(buffer)).unwrap();
+
+        assert_eq!(seed_db.seed(&TitleId::new(TEST_TITLE_ID)), Some(&[7; 16]));
+        assert_eq!(seed_db.seed(&TitleId::new(0)), None);
+    }
+
+    #[test]
+    fn seeded_key_y_is_sha256_prefix() {
+        let key_y = [1; 16];
+        let seed = [2; 16];
+
+        let mut data = key_y.to_vec();
+        data.extend_from_slice(&seed);
+
+        assert_eq!(seeded_key_y(&key_y, &seed), Sha256::digest(&data)[..16]);
+    }
+
+    #[test]
+    fn normal_key_scrambler() {
+        let key_x = 0x0123456789ABCDEF_FEDCBA9876543210u128;
+        let key_y = 0x00112233445566778899AABBCCDDEEFFu128;
+
+        let expected = ((key_x.rotate_left(2) ^ key_y)
+            .wrapping_add(0x1FF9E9AAC5FE0408024591DC5D52768A))
+        .rotate_left(87);
+
+        assert_eq!(
+            normal_key(&key_x.to_be_bytes(), &key_y.to_be_bytes()),
+            expected.to_be_bytes()
+        );
+
+        let seed = [2; 16];
+        assert_eq!(
+            ncch_normal_key(&[1; 16], &[3; 16], Some(&seed)),
+            normal_key(&[1; 16], &seeded_key_y(&[3; 16], &seed))
+        );
+        assert_eq!(
+            ncch_normal_key(&[1; 16], &[3; 16], None),
+            normal_key(&[1; 16], &[3; 16])
+        );
+    }
+
+    #[test]
+    fn the_3ds_cryptographic_method() {
+        let mut ticket_common_key_ys = [[0; 16]; 6];
+        ticket_common_key_ys[2] = [0x22; 16];
+
+        let keys = The3dsKeys {
+            ticket_key_x: [0x3D; 16],
+            ticket_common_key_ys,
+        };
+        let cryptographic_method = CryptographicMethod::The3ds(keys);
+
+        let mut ticket = templates::homebrew_channel_ticket();
+        ticket.common_key_kind_index = 2;
+
+        let title_key = [0x42; 16];
+        ticket
+            .encrypt_title_key(title_key, cryptographic_method)
+            .unwrap();
+
+        // The title key is encrypted with the normal key of the keyslot 0x3D and the title ID as
+        // the IV
+        let mut iv = [0; 16];
+        iv[..8].copy_from_slice(&ticket.title_id.inner().to_be_bytes());
+
+        let mut encrypted_title_key = title_key;
+        Aes128CbcEnc::new((&normal_key(&[0x3D; 16], &[0x22; 16])).into(), &iv.into())
+            .encrypt_block_mut((&mut encrypted_title_key).into());
+
+        assert_eq!(ticket.encrypted_title_key, encrypted_title_key);
+        assert_eq!(
+            ticket.decrypt_title_key(cryptographic_method).unwrap(),
+            title_key
+        );
+
+        // The contents are encrypted with the title key
+        let mut title_metadata = templates::homebrew_channel_tmd();
+        title_metadata
+            .content_chunk_entries
+            .push(TitleMetadataContentEntry {
+                id: 0,
+                index: 0,
+                kind: TitleMetadataContentEntryKind::Normal,
+                size: 32,
+                hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+            });
+
+        let content_selector = title_metadata.select_with_physical_position(0);
+        let data: Vec<u8> = (0..32).collect();
+
+        let mut encrypted = vec![];
+        let mut transform = ticket
+            .encryption_transform(&title_metadata, content_selector, cryptographic_method)
+            .unwrap();
+        transform.update(&data, &mut encrypted).unwrap();
+        transform.finish(&mut encrypted).unwrap();
+
+        let mut decrypted = [0; 32];
+        ticket
+            .cryptographic_stream(
+                Cursor::new(encrypted),
+                &title_metadata,
+                content_selector,
+                cryptographic_method,
+            )
+            .unwrap()
+            .read_exact(&mut decrypted)
+            .unwrap();
+
+        assert_eq!(decrypted[..], data);
+
+        ticket.common_key_kind_index = 6;
+
+        assert!(matches!(
+            ticket.decrypt_title_key(cryptographic_method),
+            Err(PreSwitchTicketError::CommonKeyError(
+                CommonKeyKindError::UnknownCommonKeyIndex(6)
+            ))
+        ));
+    }
+}
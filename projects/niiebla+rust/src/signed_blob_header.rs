@@ -7,11 +7,14 @@
 //! Implementation of the binary format used by Nintendo to sign files.
 
 use byteorder::{BE, ReadBytesExt, WriteBytesExt};
+use sha1::{Digest, Sha1};
 use std::boxed::Box;
 use std::io::{self, Read, Seek, Write};
 use std::string::{FromUtf8Error, String};
 use thiserror::Error;
-use util::{StreamPin, WriteEx};
+use util::{ParseContext, StreamPin, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("signed blob header");
 
 /// Blob placed at the start of some binary data to denote the entity that issued them.
 #[derive(Debug, Clone)]
@@ -31,11 +34,40 @@ impl SignedBlobHeader {
         let signature = SignedBlobHeaderSignature::new(&mut stream)?;
         stream.align_position(64)?;
 
-        let issuer = util::read_string!(stream, 64)?;
+        let issuer = CONTEXT.field(&mut stream, "issuer", |stream| {
+            util::read_string!(stream, 64)
+        })?;
 
         Ok(Self { signature, issuer })
     }
 
+    /// Move the stream to the end of a signed blob header without parsing it, only its
+    /// signature kind is read.
+    pub fn skip<T: Read + Seek>(stream: T) -> Result<(), SignedBlobHeaderError> {
+        let mut stream = StreamPin::new(stream)?;
+
+        let kind = CONTEXT.field(&mut stream, "signature_kind", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+
+        let signature_size = match kind {
+            0x010000 | 0x010003 => 512,
+            0x010001 | 0x010004 => 256,
+            0x010002 | 0x010005 => 60,
+            0x010006 => 20,
+
+            kind => return Err(SignedBlobHeaderError::UnknownSignatureKind(kind)),
+        };
+
+        stream.seek_relative(signature_size)?;
+        stream.align_position(64)?;
+
+        // Skip the issuer
+        stream.seek_relative(64)?;
+
+        Ok(())
+    }
+
     /// Dump the signed blob header..
     pub fn dump<T: Write + Seek>(&self, stream: T) -> io::Result<()> {
         let mut stream = StreamPin::new(stream)?;
@@ -47,6 +79,59 @@ impl SignedBlobHeader {
         Ok(())
     }
 
+    /// Zero the signature keeping its kind, needed to exploit the
+    /// [Trucha bug](https://wiibrew.org/wiki/Signing_bug) when fakesigning.
+    pub fn zero_signature(&mut self) {
+        match &mut self.signature {
+            SignedBlobHeaderSignature::Rsa4096Sha1(signature)
+            | SignedBlobHeaderSignature::Rsa4096Sha256(signature) => signature.fill(0),
+
+            SignedBlobHeaderSignature::Rsa2048Sha1(signature)
+            | SignedBlobHeaderSignature::Rsa2048Sha256(signature) => signature.fill(0),
+
+            SignedBlobHeaderSignature::EcdsaSha1(signature)
+            | SignedBlobHeaderSignature::EcdsaSha256(signature) => signature.fill(0),
+
+            SignedBlobHeaderSignature::HmacSha1(signature) => signature.fill(0),
+        }
+    }
+
+    /// Check if the signature is zeroed, as left by [Self::zero_signature].
+    pub fn is_signature_zeroed(&self) -> bool {
+        let signature: &[u8] = match &self.signature {
+            SignedBlobHeaderSignature::Rsa4096Sha1(signature)
+            | SignedBlobHeaderSignature::Rsa4096Sha256(signature) => signature.as_slice(),
+
+            SignedBlobHeaderSignature::Rsa2048Sha1(signature)
+            | SignedBlobHeaderSignature::Rsa2048Sha256(signature) => signature.as_slice(),
+
+            SignedBlobHeaderSignature::EcdsaSha1(signature)
+            | SignedBlobHeaderSignature::EcdsaSha256(signature) => signature.as_slice(),
+
+            SignedBlobHeaderSignature::HmacSha1(signature) => signature.as_slice(),
+        };
+
+        signature.iter().all(|&byte| byte == 0)
+    }
+
+    /// Find the value of a 16 bits filler (a field not used by the system) of a dumped blob that
+    /// makes the SHA-1 hash of its signed data (from the issuer to the end) start with a zero
+    /// byte, as needed by the [Trucha bug](https://wiibrew.org/wiki/Signing_bug). The offset of
+    /// the filler is relative to the start of the dumped blob, which is left with the found value.
+    pub(crate) fn brute_force_trucha(
+        &self,
+        dumped_blob: &mut [u8],
+        filler_offset: usize,
+    ) -> Option<u16> {
+        let signed_data_offset = self.size() as usize - 64;
+
+        (0..=u16::MAX).find(|filler| {
+            dumped_blob[filler_offset..filler_offset + 2].copy_from_slice(&filler.to_be_bytes());
+
+            Sha1::digest(&dumped_blob[signed_data_offset..])[0] == 0
+        })
+    }
+
     /// Get the sizes of the signed blob header in bytes.
     pub fn size(&self) -> u32 {
         let size = match self.signature {
@@ -105,45 +190,53 @@ pub enum SignedBlobHeaderSignature {
 }
 
 impl SignedBlobHeaderSignature {
-    fn new<T: Read>(mut stream: T) -> Result<Self, SignedBlobHeaderError> {
-        Ok(match stream.read_u32::<BE>()? {
-            0x010000 => {
-                let buf = util::read_exact!(stream, 512)?;
-                Self::Rsa4096Sha1(Box::new(buf))
-            }
-
-            0x010001 => {
-                let buf = util::read_exact!(stream, 256)?;
-                Self::Rsa2048Sha1(Box::new(buf))
-            }
-
-            0x010002 => {
-                let buf = util::read_exact!(stream, 60)?;
-                Self::EcdsaSha1(Box::new(buf))
-            }
-
-            0x010003 => {
-                let buf = util::read_exact!(stream, 512)?;
-                Self::Rsa4096Sha256(Box::new(buf))
-            }
-
-            0x010004 => {
-                let buf = util::read_exact!(stream, 256)?;
-                Self::Rsa2048Sha256(Box::new(buf))
-            }
-
-            0x010005 => {
-                let buf = util::read_exact!(stream, 60)?;
-                Self::EcdsaSha256(Box::new(buf))
-            }
-
-            0x010006 => {
-                let buf = util::read_exact!(stream, 20)?;
-                Self::HmacSha1(Box::new(buf))
-            }
-
-            kind => return Err(SignedBlobHeaderError::UnknownSignatureKind(kind)),
-        })
+    fn new<T: Read + Seek>(mut stream: T) -> Result<Self, SignedBlobHeaderError> {
+        let kind = CONTEXT.field(&mut stream, "signature_kind", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+
+        let signature = CONTEXT.field(&mut stream, "signature", |stream| {
+            Ok(Some(match kind {
+                0x010000 => {
+                    let buf = util::read_exact!(stream, 512)?;
+                    Self::Rsa4096Sha1(Box::new(buf))
+                }
+
+                0x010001 => {
+                    let buf = util::read_exact!(stream, 256)?;
+                    Self::Rsa2048Sha1(Box::new(buf))
+                }
+
+                0x010002 => {
+                    let buf = util::read_exact!(stream, 60)?;
+                    Self::EcdsaSha1(Box::new(buf))
+                }
+
+                0x010003 => {
+                    let buf = util::read_exact!(stream, 512)?;
+                    Self::Rsa4096Sha256(Box::new(buf))
+                }
+
+                0x010004 => {
+                    let buf = util::read_exact!(stream, 256)?;
+                    Self::Rsa2048Sha256(Box::new(buf))
+                }
+
+                0x010005 => {
+                    let buf = util::read_exact!(stream, 60)?;
+                    Self::EcdsaSha256(Box::new(buf))
+                }
+
+                0x010006 => {
+                    let buf = util::read_exact!(stream, 20)?;
+                    Self::HmacSha1(Box::new(buf))
+                }
+
+                _ => return Ok(None),
+            }))
+        })?;
+
+        signature.ok_or(SignedBlobHeaderError::UnknownSignatureKind(kind))
     }
 
     fn dump<T: Write>(&self, mut stream: T) -> io::Result<()> {
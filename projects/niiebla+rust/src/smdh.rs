@@ -0,0 +1,438 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the binary file format used by Nintendo to store the icon, names and
+//! settings of a 3DS title (SMDH).
+
+use byteorder::{LE, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Seek, Write};
+use std::string::FromUtf16Error;
+use thiserror::Error;
+use util::{ParseContext, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("SMDH");
+
+const SMDH_MAGIC_NUMBERS: [u8; 4] = *b"SMDH";
+
+/// Metadata of a 3DS title, with the names of the title in all the supported languages and its
+/// icon.
+#[derive(Debug, Clone)]
+pub struct Smdh {
+    /// Version of the format.
+    pub version: u16,
+
+    /// The names of the title, one per slot of [SmdhLanguage] (some of them are unused).
+    pub application_titles: Vec<SmdhApplicationTitle>,
+
+    /// Settings of the title.
+    pub application_settings: SmdhApplicationSettings,
+
+    /// Icon of 24x24 pixels in the tiled RGB565 format used by the 3DS GPU.
+    pub small_icon: Box<[u8; Self::SMALL_ICON_SIZE]>,
+
+    /// Icon of 48x48 pixels in the tiled RGB565 format used by the 3DS GPU.
+    pub large_icon: Box<[u8; Self::LARGE_ICON_SIZE]>,
+}
+
+impl Smdh {
+    /// Size of a SMDH in bytes.
+    pub const SIZE: u32 = 0x36C0;
+
+    const NUMBER_OF_APPLICATION_TITLES: usize = 16;
+    const SMALL_ICON_SIZE: usize = 0x480;
+    const LARGE_ICON_SIZE: usize = 0x1200;
+
+    /// Create a new [Smdh] by parsing a stream.
+    pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, SmdhError> {
+        let magic_numbers = CONTEXT.field(&mut stream, "magic_numbers", |stream| {
+            util::read_exact!(stream, 4)
+        })?;
+
+        if magic_numbers != SMDH_MAGIC_NUMBERS {
+            return Err(SmdhError::InvalidMagicNumbers(magic_numbers));
+        }
+
+        let version = CONTEXT.field(&mut stream, "version", |stream| stream.read_u16::<LE>())?;
+
+        // Skip two reserved bytes
+        stream.seek_relative(2)?;
+
+        let mut application_titles = Vec::with_capacity(Self::NUMBER_OF_APPLICATION_TITLES);
+
+        for _ in 0..Self::NUMBER_OF_APPLICATION_TITLES {
+            application_titles.push(SmdhApplicationTitle::new(&mut stream)?);
+        }
+
+        let application_settings = SmdhApplicationSettings::new(&mut stream)?;
+
+        // Skip eight reserved bytes
+        stream.seek_relative(8)?;
+
+        let small_icon = CONTEXT.field(&mut stream, "small_icon", |stream| {
+            util::read_exact!(stream, Self::SMALL_ICON_SIZE)
+        })?;
+
+        let large_icon = CONTEXT.field(&mut stream, "large_icon", |stream| {
+            util::read_exact!(stream, Self::LARGE_ICON_SIZE)
+        })?;
+
+        Ok(Self {
+            version,
+            application_titles,
+            application_settings,
+            small_icon: Box::new(small_icon),
+            large_icon: Box::new(large_icon),
+        })
+    }
+
+    /// Dump the SMDH into a stream.
+    ///
+    /// # Errors
+    /// Fails with [SmdhError::InvalidNumberOfApplicationTitles] if there is not exactly one
+    /// application title per slot of [SmdhLanguage].
+    pub fn dump<T: Write>(&self, mut stream: T) -> Result<(), SmdhError> {
+        if self.application_titles.len() != Self::NUMBER_OF_APPLICATION_TITLES {
+            return Err(SmdhError::InvalidNumberOfApplicationTitles(
+                self.application_titles.len(),
+            ));
+        }
+
+        stream.write_all(&SMDH_MAGIC_NUMBERS)?;
+        stream.write_u16::<LE>(self.version)?;
+        stream.write_zeroed(2)?;
+
+        for application_title in &self.application_titles {
+            application_title.dump(&mut stream)?;
+        }
+
+        self.application_settings.dump(&mut stream)?;
+        stream.write_zeroed(8)?;
+
+        stream.write_all(self.small_icon.as_slice())?;
+        stream.write_all(self.large_icon.as_slice())?;
+
+        Ok(())
+    }
+
+    /// Get the names of the title in the given language.
+    pub fn application_title(&self, language: SmdhLanguage) -> Option<&SmdhApplicationTitle> {
+        self.application_titles.get(language as usize)
+    }
+
+    /// Decode the small icon into a buffer of 24x24 RGBA8 pixels in row-major order.
+    pub fn small_icon_rgba8(&self) -> Vec<u8> {
+        decode_icon(self.small_icon.as_slice(), 24)
+    }
+
+    /// Decode the large icon into a buffer of 48x48 RGBA8 pixels in row-major order.
+    pub fn large_icon_rgba8(&self) -> Vec<u8> {
+        decode_icon(self.large_icon.as_slice(), 48)
+    }
+}
+
+/// Decode an icon stored as 8x8 tiles of RGB565 pixels, the pixels inside a tile follow the
+/// Z-order curve.
+fn decode_icon(data: &[u8], side: usize) -> Vec<u8> {
+    let mut decoded = vec![0; side * side * 4];
+
+    for (i, pixel) in data.chunks_exact(2).enumerate() {
+        let tile = i / 64;
+        let position_in_tile = i % 64;
+
+        let tile_x = (tile % (side / 8)) * 8;
+        let tile_y = (tile / (side / 8)) * 8;
+
+        let x = tile_x
+            + (position_in_tile & 1)
+            + ((position_in_tile >> 1) & 2)
+            + ((position_in_tile >> 2) & 4);
+        let y = tile_y
+            + ((position_in_tile >> 1) & 1)
+            + ((position_in_tile >> 2) & 2)
+            + ((position_in_tile >> 3) & 4);
+
+        let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+
+        let red = ((value >> 11) & 0x1F) as u8;
+        let green = ((value >> 5) & 0x3F) as u8;
+        let blue = (value & 0x1F) as u8;
+
+        let offset = (y * side + x) * 4;
+        decoded[offset] = (red << 3) | (red >> 2);
+        decoded[offset + 1] = (green << 2) | (green >> 4);
+        decoded[offset + 2] = (blue << 3) | (blue >> 2);
+        decoded[offset + 3] = 0xFF;
+    }
+
+    decoded
+}
+
+/// The slots of the names of a title inside a [Smdh].
+#[derive(Debug, Clone, Copy)]
+#[allow(missing_docs)]
+pub enum SmdhLanguage {
+    Japanese = 0,
+    English = 1,
+    French = 2,
+    German = 3,
+    Italian = 4,
+    Spanish = 5,
+    SimplifiedChinese = 6,
+    Korean = 7,
+    Dutch = 8,
+    Portuguese = 9,
+    Russian = 10,
+    TraditionalChinese = 11,
+}
+
+/// The names of a title in a concrete language.
+#[derive(Debug, Clone)]
+pub struct SmdhApplicationTitle {
+    /// Short description of the title, usually its name.
+    pub short_description: String,
+
+    /// Long description of the title.
+    pub long_description: String,
+
+    /// The publisher of the title.
+    pub publisher: String,
+}
+
+impl SmdhApplicationTitle {
+    const SHORT_DESCRIPTION_SIZE: usize = 0x80;
+    const LONG_DESCRIPTION_SIZE: usize = 0x100;
+    const PUBLISHER_SIZE: usize = 0x80;
+
+    fn new<T: Read + Seek>(mut stream: T) -> Result<Self, SmdhError> {
+        let short_description = CONTEXT.field(&mut stream, "short_description", |stream| {
+            util::read_exact!(stream, Self::SHORT_DESCRIPTION_SIZE)
+        })?;
+
+        let long_description = CONTEXT.field(&mut stream, "long_description", |stream| {
+            util::read_exact!(stream, Self::LONG_DESCRIPTION_SIZE)
+        })?;
+
+        let publisher = CONTEXT.field(&mut stream, "publisher", |stream| {
+            util::read_exact!(stream, Self::PUBLISHER_SIZE)
+        })?;
+
+        Ok(Self {
+            short_description: utf16_string_from_null_terminated_bytes(&short_description)?,
+            long_description: utf16_string_from_null_terminated_bytes(&long_description)?,
+            publisher: utf16_string_from_null_terminated_bytes(&publisher)?,
+        })
+    }
+
+    fn dump<T: Write>(&self, mut stream: T) -> io::Result<()> {
+        stream.write_bytes_padded(
+            &utf16_string_to_bytes(&self.short_description),
+            Self::SHORT_DESCRIPTION_SIZE,
+        )?;
+
+        stream.write_bytes_padded(
+            &utf16_string_to_bytes(&self.long_description),
+            Self::LONG_DESCRIPTION_SIZE,
+        )?;
+
+        stream.write_bytes_padded(
+            &utf16_string_to_bytes(&self.publisher),
+            Self::PUBLISHER_SIZE,
+        )?;
+
+        Ok(())
+    }
+}
+
+fn utf16_string_from_null_terminated_bytes(buffer: &[u8]) -> Result<String, FromUtf16Error> {
+    let code_units: Vec<u16> = buffer
+        .chunks_exact(2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .take_while(|&code_unit| code_unit != 0)
+        .collect();
+
+    String::from_utf16(&code_units)
+}
+
+fn utf16_string_to_bytes(string: &str) -> Vec<u8> {
+    string.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+/// Settings of a 3DS title.
+#[derive(Debug, Clone)]
+pub struct SmdhApplicationSettings {
+    /// Age ratings of the title, one per rating organization.
+    pub age_ratings: [u8; 16],
+
+    /// Bitmask of the regions where the title can be launched.
+    pub region_lockout: u32,
+
+    /// ID used for online play.
+    pub match_maker_id: u32,
+
+    /// Bit ID used for online play.
+    pub match_maker_bit_id: u64,
+
+    /// Bitmask of flags of the title.
+    pub flags: u32,
+
+    /// Version of the EULA that the user must have accepted.
+    pub eula_version: u16,
+
+    /// Frame of the banner animation shown as the default one.
+    pub optimal_animation_default_frame: f32,
+
+    /// ID used for StreetPass.
+    pub cec_id: u32,
+}
+
+impl SmdhApplicationSettings {
+    fn new<T: Read + Seek>(mut stream: T) -> Result<Self, SmdhError> {
+        let age_ratings = CONTEXT.field(&mut stream, "age_ratings", |stream| {
+            util::read_exact!(stream, 16)
+        })?;
+
+        let region_lockout = CONTEXT.field(&mut stream, "region_lockout", |stream| {
+            stream.read_u32::<LE>()
+        })?;
+
+        let match_maker_id = CONTEXT.field(&mut stream, "match_maker_id", |stream| {
+            stream.read_u32::<LE>()
+        })?;
+
+        let match_maker_bit_id = CONTEXT.field(&mut stream, "match_maker_bit_id", |stream| {
+            stream.read_u64::<LE>()
+        })?;
+
+        let flags = CONTEXT.field(&mut stream, "flags", |stream| stream.read_u32::<LE>())?;
+
+        let eula_version = CONTEXT.field(&mut stream, "eula_version", |stream| {
+            stream.read_u16::<LE>()
+        })?;
+
+        // Skip two reserved bytes
+        stream.seek_relative(2)?;
+
+        let optimal_animation_default_frame =
+            CONTEXT.field(&mut stream, "optimal_animation_default_frame", |stream| {
+                stream.read_f32::<LE>()
+            })?;
+
+        let cec_id = CONTEXT.field(&mut stream, "cec_id", |stream| stream.read_u32::<LE>())?;
+
+        Ok(Self {
+            age_ratings,
+            region_lockout,
+            match_maker_id,
+            match_maker_bit_id,
+            flags,
+            eula_version,
+            optimal_animation_default_frame,
+            cec_id,
+        })
+    }
+
+    fn dump<T: Write>(&self, mut stream: T) -> io::Result<()> {
+        stream.write_all(&self.age_ratings)?;
+        stream.write_u32::<LE>(self.region_lockout)?;
+        stream.write_u32::<LE>(self.match_maker_id)?;
+        stream.write_u64::<LE>(self.match_maker_bit_id)?;
+        stream.write_u32::<LE>(self.flags)?;
+        stream.write_u16::<LE>(self.eula_version)?;
+        stream.write_zeroed(2)?;
+        stream.write_f32::<LE>(self.optimal_animation_default_frame)?;
+        stream.write_u32::<LE>(self.cec_id)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum SmdhError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("The given stream is not a SMDH, invalid magic numbers: {0:?}")]
+    InvalidMagicNumbers([u8; 4]),
+
+    #[error("Unable to parse an UTF-16 string: {0}")]
+    Utf16Error(#[from] FromUtf16Error),
+
+    #[error("A SMDH must have 16 application titles, found {0}")]
+    InvalidNumberOfApplicationTitles(usize),
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    pub(crate) fn dummy_smdh() -> Smdh {
+        let application_title = SmdhApplicationTitle {
+            short_description: String::from("NiiEBLA"),
+            long_description: String::from("NiiEBLA test title"),
+            publisher: String::from("ZEL.ZIP"),
+        };
+
+        Smdh {
+            version: 0,
+            application_titles: vec![application_title; Smdh::NUMBER_OF_APPLICATION_TITLES],
+            application_settings: SmdhApplicationSettings {
+                age_ratings: [0; 16],
+                region_lockout: 0x7FFFFFFF,
+                match_maker_id: 0,
+                match_maker_bit_id: 0,
+                flags: 0,
+                eula_version: 0,
+                optimal_animation_default_frame: 0.0,
+                cec_id: 0,
+            },
+            small_icon: Box::new([0; Smdh::SMALL_ICON_SIZE]),
+            large_icon: Box::new([0xFF; Smdh::LARGE_ICON_SIZE]),
+        }
+    }
+
+    #[test]
+    fn dump_and_parse() {
+        let mut buffer = vec![];
+        dummy_smdh().dump(&mut buffer).unwrap();
+
+        assert_eq!(buffer.len(), Smdh::SIZE as usize);
+
+        let smdh = Smdh::new(Cursor::new(buffer)).unwrap();
+        let application_title = smdh.application_title(SmdhLanguage::English).unwrap();
+
+        assert_eq!(application_title.short_description, "NiiEBLA");
+        assert_eq!(application_title.publisher, "ZEL.ZIP");
+        assert_eq!(smdh.application_settings.region_lockout, 0x7FFFFFFF);
+    }
+
+    #[test]
+    fn dump_wrong_number_of_application_titles() {
+        let mut smdh = dummy_smdh();
+        smdh.application_titles.pop();
+
+        let mut buffer = vec![];
+
+        assert!(matches!(
+            smdh.dump(&mut buffer),
+            Err(SmdhError::InvalidNumberOfApplicationTitles(15))
+        ));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn decode_icon_colors() {
+        let smdh = dummy_smdh();
+
+        assert!(
+            smdh.small_icon_rgba8()
+                .chunks(4)
+                .all(|p| p == [0, 0, 0, 0xFF])
+        );
+        assert!(smdh.large_icon_rgba8().iter().all(|&byte| byte == 0xFF));
+    }
+}
@@ -0,0 +1,219 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Identification of the kind of data stored inside a content by its magic numbers, useful to
+//! give meaningful names to the extracted contents.
+
+use std::io::{self, Read};
+
+/// Number of bytes read by [identify].
+pub const SNIFF_SIZE: usize = 0x100;
+
+const DOL_SECTIONS: usize = 18;
+const DOL_TEXT_SECTIONS: usize = 7;
+const DOL_HEADER_PADDING_OFFSET: usize = 0xE4;
+
+/// The guessed kind of the data of a content, see [identify].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    /// An U8 archive, like the ones with the files of a banner or a channel.
+    U8Archive,
+
+    /// A banner with an [IMET header](https://wiibrew.org/wiki/Opening.bnr) (`opening.bnr`),
+    /// usually the content with index 0 of a channel.
+    Imet,
+
+    /// A file with an IMD5 header, like the `icon.bin` or `banner.bin` of a banner.
+    Imd5,
+
+    /// Data compressed with LZ77 and its `LZ77` header.
+    Lz77,
+
+    /// A PowerPC executable in the DOL format.
+    Dol,
+
+    /// An ELF executable.
+    Elf(ElfMachine),
+
+    /// None of the known kinds.
+    Unknown,
+}
+
+impl ContentKind {
+    /// Get the file extension usually given to the data of this kind (without the dot).
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::U8Archive => "arc",
+            Self::Imet => "bnr",
+            Self::Imd5 => "bin",
+            Self::Lz77 => "lz77",
+            Self::Dol => "dol",
+            Self::Elf(_) => "elf",
+            Self::Unknown => "app",
+        }
+    }
+}
+
+/// The architectures of the ELF executables found inside the contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfMachine {
+    /// The ARM9 (Starlet) of the Wii, the IOS modules.
+    Arm,
+
+    /// The PowerPC (Broadway) of the Wii.
+    PowerPc,
+
+    /// Other architecture, holds the value of the `e_machine` field.
+    Other(u16),
+}
+
+/// Guess the kind of the data of a content by its magic numbers, only the first [SNIFF_SIZE]
+/// bytes of the reader are read.
+///
+/// The DOL format has no magic numbers, its header is checked to be sane instead.
+pub fn identify<T: Read>(reader: T) -> io::Result<ContentKind> {
+    let mut data = Vec::with_capacity(SNIFF_SIZE);
+    reader.take(SNIFF_SIZE as u64).read_to_end(&mut data)?;
+
+    Ok(identify_bytes(&data))
+}
+
+/// Like [identify] but over the (first bytes of the) data of a content already in memory.
+pub fn identify_bytes(data: &[u8]) -> ContentKind {
+    let magic_at =
+        |offset: usize, magic: &[u8]| data.get(offset..offset + magic.len()) == Some(magic);
+
+    if magic_at(0, &[0x55, 0xAA, 0x38, 0x2D]) {
+        return ContentKind::U8Archive;
+    }
+
+    // Some banners have an extra padding of 64 bytes
+    if magic_at(0x40, b"IMET") || magic_at(0x80, b"IMET") {
+        return ContentKind::Imet;
+    }
+
+    if magic_at(0, b"IMD5") {
+        return ContentKind::Imd5;
+    }
+
+    if magic_at(0, b"LZ77") {
+        return ContentKind::Lz77;
+    }
+
+    if magic_at(0, b"\x7FELF") {
+        return ContentKind::Elf(elf_machine(data));
+    }
+
+    if is_dol(data) {
+        return ContentKind::Dol;
+    }
+
+    ContentKind::Unknown
+}
+
+fn elf_machine(data: &[u8]) -> ElfMachine {
+    let Some(&[first, second]) = data.get(0x12..0x14) else {
+        return ElfMachine::Other(0);
+    };
+
+    // The endianness is given by the `EI_DATA` field
+    let machine = if data.get(5) == Some(&2) {
+        u16::from_be_bytes([first, second])
+    } else {
+        u16::from_le_bytes([first, second])
+    };
+
+    match machine {
+        0x14 => ElfMachine::PowerPc,
+        0x28 => ElfMachine::Arm,
+        machine => ElfMachine::Other(machine),
+    }
+}
+
+fn is_dol(data: &[u8]) -> bool {
+    if data.len() < SNIFF_SIZE || data[DOL_HEADER_PADDING_OFFSET..SNIFF_SIZE] != [0; 28] {
+        return false;
+    }
+
+    let field = |offset: usize| {
+        u32::from_be_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    };
+
+    // The main memory of the Wii, MEM1 and MEM2 (cached)
+    let is_address = |address: u32| (0x80000000..0x94000000).contains(&address);
+
+    let mut has_text = false;
+
+    for i in 0..DOL_SECTIONS {
+        let offset = field(i * 4);
+        let address = field(0x48 + i * 4);
+        let size = field(0x90 + i * 4);
+
+        if size == 0 {
+            continue;
+        }
+
+        if offset < SNIFF_SIZE as u32 || !is_address(address) {
+            return false;
+        }
+
+        has_text |= i < DOL_TEXT_SECTIONS;
+    }
+
+    has_text && is_address(field(0xE0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn identify_magic_numbers() {
+        let mut banner = vec![0; 0x80];
+        banner[0x40..0x44].copy_from_slice(b"IMET");
+        assert_eq!(identify(Cursor::new(banner)).unwrap(), ContentKind::Imet);
+
+        assert_eq!(
+            identify_bytes(&[0x55, 0xAA, 0x38, 0x2D, 0, 0]),
+            ContentKind::U8Archive
+        );
+        assert_eq!(identify_bytes(b"IMD5\0\0\0\0"), ContentKind::Imd5);
+        assert_eq!(identify_bytes(b"LZ77\x10"), ContentKind::Lz77);
+        assert_eq!(identify_bytes(&[0; 0x200]), ContentKind::Unknown);
+        assert_eq!(identify_bytes(&[]), ContentKind::Unknown);
+
+        let mut elf = vec![0; 0x34];
+        elf[..6].copy_from_slice(b"\x7FELF\x01\x02");
+        elf[0x12..0x14].copy_from_slice(&0x28u16.to_be_bytes());
+        assert_eq!(identify_bytes(&elf), ContentKind::Elf(ElfMachine::Arm));
+
+        elf[0x12..0x14].copy_from_slice(&0x14u16.to_be_bytes());
+        assert_eq!(identify_bytes(&elf), ContentKind::Elf(ElfMachine::PowerPc));
+    }
+
+    #[test]
+    fn identify_dol() {
+        let mut dol = vec![0; 0x200];
+
+        // A single text section loaded at 0x80003100
+        dol[0..4].copy_from_slice(&0x100u32.to_be_bytes());
+        dol[0x48..0x4C].copy_from_slice(&0x80003100u32.to_be_bytes());
+        dol[0x90..0x94].copy_from_slice(&0x100u32.to_be_bytes());
+        dol[0xE0..0xE4].copy_from_slice(&0x80003100u32.to_be_bytes());
+
+        assert_eq!(identify_bytes(&dol), ContentKind::Dol);
+        assert_eq!(ContentKind::Dol.extension(), "dol");
+
+        dol[0xE0..0xE4].copy_from_slice(&0x1000u32.to_be_bytes());
+        assert_eq!(identify_bytes(&dol), ContentKind::Unknown);
+    }
+}
@@ -0,0 +1,567 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the file used by the Nintendo Wii to store the configuration of the system
+//! (`/shared2/sys/SYSCONF` on the NAND).
+
+use byteorder::{BE, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Seek, Write};
+use thiserror::Error;
+use util::{ParseContext, ReadEx, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("SYSCONF");
+
+const SYSCONF_MAGIC_NUMBERS: [u8; 4] = *b"SCv0";
+const SYSCONF_FOOTER_MAGIC_NUMBERS: [u8; 4] = *b"SCed";
+
+/// The configuration of a Wii console, a list of named entries.
+#[derive(Debug, Clone, Default)]
+pub struct SysConf {
+    /// The entries of the configuration, in the order they are stored.
+    pub entries: Vec<SysConfEntry>,
+}
+
+impl SysConf {
+    /// Size of the SYSCONF file in bytes, it is always padded to this size.
+    pub const SIZE: u64 = 0x4000;
+
+    /// Name of the entry with the language of the system.
+    pub const LANGUAGE_ENTRY_NAME: &str = "IPL.LNG";
+
+    /// Name of the entry with the aspect ratio of the screen.
+    pub const ASPECT_RATIO_ENTRY_NAME: &str = "IPL.AR";
+
+    /// Name of the entry with the sound mode.
+    pub const SOUND_MODE_ENTRY_NAME: &str = "IPL.SND";
+
+    /// Name of the entry that enables the screen saver.
+    pub const SCREEN_SAVER_ENTRY_NAME: &str = "IPL.SSV";
+
+    /// Name of the entry that enables the progressive scan video mode.
+    pub const PROGRESSIVE_SCAN_ENTRY_NAME: &str = "IPL.PGS";
+
+    /// Name of the entry that enables the EuRGB60 video mode (PAL consoles).
+    pub const EURGB60_ENTRY_NAME: &str = "IPL.E60";
+
+    /// Name of the entry with the number of times the connection test to the internet passed.
+    pub const NETWORK_CONNECTION_TEST_ENTRY_NAME: &str = "NET.CTPC";
+
+    const HEADER_SIZE: u64 = 6;
+
+    /// Create a new [SysConf] by parsing a stream.
+    pub fn new<T: Read + Seek>(stream: T) -> Result<Self, SysConfError> {
+        let mut stream = util::StreamPin::new(stream)?;
+
+        let magic_numbers = CONTEXT.field(&mut stream, "magic_numbers", |stream| {
+            util::read_exact!(stream, 4)
+        })?;
+
+        if magic_numbers != SYSCONF_MAGIC_NUMBERS {
+            return Err(SysConfError::InvalidMagicNumbers(magic_numbers));
+        }
+
+        let number_of_entries = CONTEXT.field(&mut stream, "number_of_entries", |stream| {
+            stream.read_u16::<BE>()
+        })?;
+
+        let mut offsets = Vec::with_capacity(number_of_entries as usize);
+
+        for _ in 0..number_of_entries {
+            offsets.push(CONTEXT.field(&mut stream, "entry_offset", |stream| {
+                stream.read_u16::<BE>()
+            })?);
+        }
+
+        let mut entries = Vec::with_capacity(offsets.len());
+
+        for offset in offsets {
+            stream.seek_from_pin(offset as i64)?;
+            entries.push(SysConfEntry::new(&mut stream)?);
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Dump the configuration into a stream, padded to [Self::SIZE].
+    pub fn dump<T: Write>(&self, mut stream: T) -> Result<(), SysConfError> {
+        let mut data = vec![];
+
+        // The last offset points to the end of the data of the entries
+        let mut offsets = Vec::with_capacity(self.entries.len() + 1);
+
+        let data_offset = Self::HEADER_SIZE + (self.entries.len() as u64 + 1) * 2;
+
+        for entry in &self.entries {
+            offsets.push(data_offset + data.len() as u64);
+            entry.dump(&mut data)?;
+        }
+
+        offsets.push(data_offset + data.len() as u64);
+
+        let data_end = data_offset + data.len() as u64;
+
+        if data_end > Self::SIZE - SYSCONF_FOOTER_MAGIC_NUMBERS.len() as u64 {
+            return Err(SysConfError::TooLarge(data_end));
+        }
+
+        stream.write_all(&SYSCONF_MAGIC_NUMBERS)?;
+        stream.write_u16::<BE>(self.entries.len() as u16)?;
+
+        for offset in offsets {
+            stream.write_u16::<BE>(offset as u16)?;
+        }
+
+        stream.write_all(&data)?;
+        stream
+            .write_zeroed((Self::SIZE - data_end) as usize - SYSCONF_FOOTER_MAGIC_NUMBERS.len())?;
+        stream.write_all(&SYSCONF_FOOTER_MAGIC_NUMBERS)?;
+
+        Ok(())
+    }
+
+    /// Get the value of an entry, `None` if it is not present.
+    pub fn get(&self, name: &str) -> Option<&SysConfValue> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| &entry.value)
+    }
+
+    /// Set the value of an entry, it is added at the end if not present.
+    pub fn set(&mut self, name: &str, value: SysConfValue) {
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => entry.value = value,
+            None => self.entries.push(SysConfEntry {
+                name: name.to_string(),
+                value,
+            }),
+        }
+    }
+
+    /// Remove an entry, returns its value if it was present.
+    pub fn remove(&mut self, name: &str) -> Option<SysConfValue> {
+        let position = self.entries.iter().position(|entry| entry.name == name)?;
+
+        Some(self.entries.remove(position).value)
+    }
+
+    /// Get the language of the system.
+    pub fn language(&self) -> Result<SysConfLanguage, SysConfError> {
+        SysConfLanguage::from_identifier(self.byte(Self::LANGUAGE_ENTRY_NAME)?)
+    }
+
+    /// Set the language of the system.
+    pub fn set_language(&mut self, language: SysConfLanguage) {
+        self.set(
+            Self::LANGUAGE_ENTRY_NAME,
+            SysConfValue::Byte(language.identifier()),
+        );
+    }
+
+    /// Get the aspect ratio of the screen.
+    pub fn aspect_ratio(&self) -> Result<SysConfAspectRatio, SysConfError> {
+        match self.byte(Self::ASPECT_RATIO_ENTRY_NAME)? {
+            0 => Ok(SysConfAspectRatio::Standard),
+            1 => Ok(SysConfAspectRatio::Widescreen),
+
+            identifier => Err(SysConfError::UnknownAspectRatio(identifier)),
+        }
+    }
+
+    /// Set the aspect ratio of the screen.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: SysConfAspectRatio) {
+        self.set(
+            Self::ASPECT_RATIO_ENTRY_NAME,
+            SysConfValue::Byte(aspect_ratio as u8),
+        );
+    }
+
+    /// Get the sound mode.
+    pub fn sound_mode(&self) -> Result<SysConfSoundMode, SysConfError> {
+        match self.byte(Self::SOUND_MODE_ENTRY_NAME)? {
+            0 => Ok(SysConfSoundMode::Mono),
+            1 => Ok(SysConfSoundMode::Stereo),
+            2 => Ok(SysConfSoundMode::Surround),
+
+            identifier => Err(SysConfError::UnknownSoundMode(identifier)),
+        }
+    }
+
+    /// Set the sound mode.
+    pub fn set_sound_mode(&mut self, sound_mode: SysConfSoundMode) {
+        self.set(
+            Self::SOUND_MODE_ENTRY_NAME,
+            SysConfValue::Byte(sound_mode as u8),
+        );
+    }
+
+    /// Check if the screen saver is enabled.
+    pub fn screen_saver(&self) -> Result<bool, SysConfError> {
+        Ok(self.byte(Self::SCREEN_SAVER_ENTRY_NAME)? != 0)
+    }
+
+    /// Enable or disable the screen saver.
+    pub fn set_screen_saver(&mut self, enabled: bool) {
+        self.set(
+            Self::SCREEN_SAVER_ENTRY_NAME,
+            SysConfValue::Byte(enabled as u8),
+        );
+    }
+
+    /// Check if the progressive scan video mode is enabled.
+    pub fn progressive_scan(&self) -> Result<bool, SysConfError> {
+        Ok(self.byte(Self::PROGRESSIVE_SCAN_ENTRY_NAME)? != 0)
+    }
+
+    /// Enable or disable the progressive scan video mode.
+    pub fn set_progressive_scan(&mut self, enabled: bool) {
+        self.set(
+            Self::PROGRESSIVE_SCAN_ENTRY_NAME,
+            SysConfValue::Byte(enabled as u8),
+        );
+    }
+
+    /// Check if the EuRGB60 video mode is enabled.
+    pub fn eurgb60(&self) -> Result<bool, SysConfError> {
+        Ok(self.byte(Self::EURGB60_ENTRY_NAME)? != 0)
+    }
+
+    /// Enable or disable the EuRGB60 video mode.
+    pub fn set_eurgb60(&mut self, enabled: bool) {
+        self.set(Self::EURGB60_ENTRY_NAME, SysConfValue::Byte(enabled as u8));
+    }
+
+    /// Get the number of times the connection test to the internet has passed, the system only
+    /// allows to connect to online services if it is not zero.
+    pub fn network_connection_test_passed_count(&self) -> Result<u32, SysConfError> {
+        match self.get(Self::NETWORK_CONNECTION_TEST_ENTRY_NAME) {
+            Some(SysConfValue::Long(value)) => Ok(*value),
+            Some(_) => Err(SysConfError::UnexpectedValueKind(
+                Self::NETWORK_CONNECTION_TEST_ENTRY_NAME,
+            )),
+            None => Err(SysConfError::MissingEntry(
+                Self::NETWORK_CONNECTION_TEST_ENTRY_NAME,
+            )),
+        }
+    }
+
+    /// Set the number of times the connection test to the internet has passed.
+    pub fn set_network_connection_test_passed_count(&mut self, count: u32) {
+        self.set(
+            Self::NETWORK_CONNECTION_TEST_ENTRY_NAME,
+            SysConfValue::Long(count),
+        );
+    }
+
+    fn byte(&self, name: &'static str) -> Result<u8, SysConfError> {
+        match self.get(name) {
+            Some(SysConfValue::Byte(value)) => Ok(*value),
+            Some(_) => Err(SysConfError::UnexpectedValueKind(name)),
+            None => Err(SysConfError::MissingEntry(name)),
+        }
+    }
+}
+
+/// A named entry of a [SysConf].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SysConfEntry {
+    /// The name of the entry (like `IPL.LNG`), between 1 and 32 ASCII characters.
+    pub name: String,
+
+    /// The value of the entry.
+    pub value: SysConfValue,
+}
+
+impl SysConfEntry {
+    const MAX_NAME_LEN: usize = 32;
+
+    fn new<T: Read + Seek>(mut stream: T) -> Result<Self, SysConfError> {
+        let descriptor =
+            CONTEXT.field(&mut stream, "entry_descriptor", |stream| stream.read_u8())?;
+
+        // The kind of the value is stored on the upper 3 bits and the length of the name (minus
+        // one) on the lower 5 bits
+        let kind = descriptor >> 5;
+        let name_len = (descriptor & 0x1F) as u64 + 1;
+
+        let name = CONTEXT.field(&mut stream, "entry_name", |stream| {
+            stream.read_exact_vec(name_len)
+        })?;
+        let name = String::from_utf8_lossy(&name).into_owned();
+
+        let value = match kind {
+            1 => {
+                let len =
+                    CONTEXT.field(&mut stream, "entry_len", |stream| stream.read_u16::<BE>())?;
+
+                SysConfValue::BigArray(CONTEXT.field(&mut stream, "entry_value", |stream| {
+                    stream.read_exact_vec(len as u64 + 1)
+                })?)
+            }
+
+            2 => {
+                let len = CONTEXT.field(&mut stream, "entry_len", |stream| stream.read_u8())?;
+
+                SysConfValue::SmallArray(CONTEXT.field(&mut stream, "entry_value", |stream| {
+                    stream.read_exact_vec(len as u64 + 1)
+                })?)
+            }
+
+            3 => SysConfValue::Byte(
+                CONTEXT.field(&mut stream, "entry_value", |stream| stream.read_u8())?,
+            ),
+
+            4 => SysConfValue::Short(
+                CONTEXT.field(&mut stream, "entry_value", |stream| stream.read_u16::<BE>())?,
+            ),
+
+            5 => SysConfValue::Long(
+                CONTEXT.field(&mut stream, "entry_value", |stream| stream.read_u32::<BE>())?,
+            ),
+
+            6 => SysConfValue::LongLong(
+                CONTEXT.field(&mut stream, "entry_value", |stream| stream.read_u64::<BE>())?,
+            ),
+
+            7 => SysConfValue::Bool(
+                CONTEXT.field(&mut stream, "entry_value", |stream| stream.read_u8())? != 0,
+            ),
+
+            kind => return Err(SysConfError::UnknownValueKind(kind)),
+        };
+
+        Ok(Self { name, value })
+    }
+
+    fn dump<T: Write>(&self, mut stream: T) -> Result<(), SysConfError> {
+        if self.name.is_empty() || self.name.len() > Self::MAX_NAME_LEN {
+            return Err(SysConfError::InvalidEntryName(self.name.clone()));
+        }
+
+        let kind: u8 = match self.value {
+            SysConfValue::BigArray(_) => 1,
+            SysConfValue::SmallArray(_) => 2,
+            SysConfValue::Byte(_) => 3,
+            SysConfValue::Short(_) => 4,
+            SysConfValue::Long(_) => 5,
+            SysConfValue::LongLong(_) => 6,
+            SysConfValue::Bool(_) => 7,
+        };
+
+        stream.write_u8((kind << 5) | (self.name.len() - 1) as u8)?;
+        stream.write_all(self.name.as_bytes())?;
+
+        match &self.value {
+            SysConfValue::BigArray(data) => {
+                if data.is_empty() || data.len() > u16::MAX as usize + 1 {
+                    return Err(SysConfError::InvalidArrayLen(self.name.clone()));
+                }
+
+                stream.write_u16::<BE>((data.len() - 1) as u16)?;
+                stream.write_all(data)?;
+            }
+
+            SysConfValue::SmallArray(data) => {
+                if data.is_empty() || data.len() > u8::MAX as usize + 1 {
+                    return Err(SysConfError::InvalidArrayLen(self.name.clone()));
+                }
+
+                stream.write_u8((data.len() - 1) as u8)?;
+                stream.write_all(data)?;
+            }
+
+            SysConfValue::Byte(value) => stream.write_u8(*value)?,
+            SysConfValue::Short(value) => stream.write_u16::<BE>(*value)?,
+            SysConfValue::Long(value) => stream.write_u32::<BE>(*value)?,
+            SysConfValue::LongLong(value) => stream.write_u64::<BE>(*value)?,
+            SysConfValue::Bool(value) => stream.write_u8(*value as u8)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// The value of a [SysConfEntry].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SysConfValue {
+    /// Data of up to 65536 bytes, it cannot be empty.
+    BigArray(Vec<u8>),
+
+    /// Data of up to 256 bytes, it cannot be empty.
+    SmallArray(Vec<u8>),
+
+    /// An 8 bit number.
+    Byte(u8),
+
+    /// A 16 bit number.
+    Short(u16),
+
+    /// A 32 bit number.
+    Long(u32),
+
+    /// A 64 bit number.
+    LongLong(u64),
+
+    /// A boolean.
+    Bool(bool),
+}
+
+/// The languages the system can be configured with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum SysConfLanguage {
+    Japanese,
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Dutch,
+    SimplifiedChinese,
+    TraditionalChinese,
+    Korean,
+}
+
+impl SysConfLanguage {
+    fn from_identifier(identifier: u8) -> Result<Self, SysConfError> {
+        match identifier {
+            0 => Ok(Self::Japanese),
+            1 => Ok(Self::English),
+            2 => Ok(Self::German),
+            3 => Ok(Self::French),
+            4 => Ok(Self::Spanish),
+            5 => Ok(Self::Italian),
+            6 => Ok(Self::Dutch),
+            7 => Ok(Self::SimplifiedChinese),
+            8 => Ok(Self::TraditionalChinese),
+            9 => Ok(Self::Korean),
+
+            identifier => Err(SysConfError::UnknownLanguage(identifier)),
+        }
+    }
+
+    fn identifier(self) -> u8 {
+        self as u8
+    }
+}
+
+/// The aspect ratios of the screen the system can be configured with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SysConfAspectRatio {
+    /// 4:3.
+    Standard = 0,
+
+    /// 16:9.
+    Widescreen = 1,
+}
+
+/// The sound modes the system can be configured with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum SysConfSoundMode {
+    Mono = 0,
+    Stereo = 1,
+    Surround = 2,
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum SysConfError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("The given stream is not a SYSCONF, invalid magic numbers: {0:?}")]
+    InvalidMagicNumbers([u8; 4]),
+
+    #[error("Unknown kind of value of an entry: {0}")]
+    UnknownValueKind(u8),
+
+    #[error("The entry is not present: {0}")]
+    MissingEntry(&'static str),
+
+    #[error("The value of the entry is not of the expected kind: {0}")]
+    UnexpectedValueKind(&'static str),
+
+    #[error("The name of the entry is empty or longer than 32 bytes: {0:?}")]
+    InvalidEntryName(String),
+
+    #[error("The array of the entry is empty or too long: {0}")]
+    InvalidArrayLen(String),
+
+    #[error("The entries do not fit inside a SYSCONF file, they end at: {0:#X}")]
+    TooLarge(u64),
+
+    #[error("Unknown language: {0}")]
+    UnknownLanguage(u8),
+
+    #[error("Unknown aspect ratio: {0}")]
+    UnknownAspectRatio(u8),
+
+    #[error("Unknown sound mode: {0}")]
+    UnknownSoundMode(u8),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn dump_and_parse() {
+        let mut sysconf = SysConf::default();
+
+        sysconf.set_language(SysConfLanguage::Dutch);
+        sysconf.set_aspect_ratio(SysConfAspectRatio::Widescreen);
+        sysconf.set_network_connection_test_passed_count(3);
+        sysconf.set("IPL.NIK", SysConfValue::SmallArray(b"NiiEBLA".to_vec()));
+        sysconf.set("BT.DINF", SysConfValue::BigArray(vec![0xAA; 0x461]));
+        sysconf.set("IPL.CB", SysConfValue::Long(0x1234));
+
+        let mut buffer = Cursor::new(vec![]);
+        sysconf.dump(&mut buffer).unwrap();
+
+        assert_eq!(buffer.get_ref().len() as u64, SysConf::SIZE);
+        assert_eq!(&buffer.get_ref()[0x3FFC..], b"SCed");
+
+        buffer.set_position(0);
+        let parsed = SysConf::new(&mut buffer).unwrap();
+
+        assert_eq!(parsed.entries, sysconf.entries);
+        assert_eq!(parsed.language().unwrap(), SysConfLanguage::Dutch);
+        assert_eq!(
+            parsed.aspect_ratio().unwrap(),
+            SysConfAspectRatio::Widescreen
+        );
+        assert_eq!(parsed.network_connection_test_passed_count().unwrap(), 3);
+
+        assert!(matches!(
+            parsed.sound_mode(),
+            Err(SysConfError::MissingEntry("IPL.SND"))
+        ));
+        assert!(matches!(
+            parsed.progressive_scan(),
+            Err(SysConfError::MissingEntry(_))
+        ));
+    }
+
+    #[test]
+    fn reject_invalid_entries() {
+        let mut sysconf = SysConf::default();
+        sysconf.set("IPL.NIK", SysConfValue::SmallArray(vec![]));
+
+        assert!(matches!(
+            sysconf.dump(io::sink()),
+            Err(SysConfError::InvalidArrayLen(_))
+        ));
+
+        sysconf.set("IPL.NIK", SysConfValue::BigArray(vec![0; 0x4000]));
+
+        assert!(matches!(
+            sysconf.dump(io::sink()),
+            Err(SysConfError::TooLarge(_))
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Names of the metrics reported by the crate through the [`metrics`](https://docs.rs/metrics)
+//! facade when the `metrics` feature is enabled, useful for services embedding the crate. Without
+//! an installed recorder (or with the feature disabled) reporting them has no effect.
+
+/// Counter of the files parsed successfully, labeled with their `format` (`ticket`,
+/// `title_metadata`, `certificate_chain` or `wad`).
+pub const PARSED_FILES: &str = "niiebla_parsed_files_total";
+
+/// Counter of the bytes of the contents given to a decryption stream, counted when the stream
+/// is created.
+pub const DECRYPTED_BYTES: &str = "niiebla_decrypted_bytes_total";
+
+/// Histogram of the sizes in bytes of the contents given to a decryption stream.
+pub const DECRYPTED_CONTENT_SIZE: &str = "niiebla_decrypted_content_size_bytes";
+
+/// Counter of the failed verifications, labeled with their `kind` (`content_hash`, `signature`
+/// or `imet_hash`).
+pub const VERIFICATION_FAILURES: &str = "niiebla_verification_failures_total";
+
+pub(crate) fn file_parsed(format: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(PARSED_FILES, "format" => format).increment(1);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = format;
+}
+
+pub(crate) fn content_decrypted(size: u64) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(DECRYPTED_BYTES).increment(size);
+        metrics::histogram!(DECRYPTED_CONTENT_SIZE).record(size as f64);
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = size;
+}
+
+#[cfg(any(feature = "wad", feature = "imet"))]
+pub(crate) fn verification_failed(kind: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(VERIFICATION_FAILURES, "kind" => kind).increment(1);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = kind;
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::ticket::PreSwitchTicket;
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex, PoisonError};
+
+    /// Recorder that only keeps the counters, with their keys.
+    #[derive(Default)]
+    struct CounterRecorder(Mutex<Vec<(Key, Arc<AtomicU64>)>>);
+
+    struct AtomicCounter(Arc<AtomicU64>);
+
+    impl CounterFn for AtomicCounter {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::Relaxed);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0.store(value, Ordering::Relaxed);
+        }
+    }
+
+    impl CounterRecorder {
+        fn value(&self, name: &str, label: (&str, &str)) -> u64 {
+            self.0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .filter(|(key, _)| {
+                    key.name() == name
+                        && key.labels().any(|key_label| {
+                            key_label.key() == label.0 && key_label.value() == label.1
+                        })
+                })
+                .map(|(_, value)| value.load(Ordering::Relaxed))
+                .sum()
+        }
+    }
+
+    impl Recorder for CounterRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            let value = Arc::new(AtomicU64::new(0));
+            self.0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((key.clone(), value.clone()));
+
+            Counter::from_arc(Arc::new(AtomicCounter(value)))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn count_parsed_files() {
+        let mut data = Cursor::new(vec![]);
+        templates::homebrew_channel_ticket()
+            .dump(&mut data)
+            .unwrap();
+
+        let recorder = CounterRecorder::default();
+
+        metrics::with_local_recorder(&recorder, || {
+            PreSwitchTicket::new(Cursor::new(data.get_ref())).unwrap();
+            PreSwitchTicket::new(Cursor::new(data.get_ref())).unwrap();
+            assert!(PreSwitchTicket::new(Cursor::new(&data.get_ref()[..0x10])).is_err());
+        });
+
+        assert_eq!(recorder.value(PARSED_FILES, ("format", "ticket")), 2);
+        assert_eq!(recorder.value(PARSED_FILES, ("format", "wad")), 0);
+    }
+}
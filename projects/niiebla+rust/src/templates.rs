@@ -0,0 +1,297 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Ready-made title metadata and tickets for homebrew channels of the Nintendo Wii, known-good
+//! baselines for tools that create forwarders or inject data into channels.
+//!
+//! The created data is not signed (its signature is zeroed), it must be fakesigned after being
+//! edited.
+
+#[cfg(feature = "wad")]
+use crate::CryptographicMethod;
+use crate::signed_blob_header::{SignedBlobHeader, SignedBlobHeaderSignature};
+use crate::ticket::{
+    PreSwitchTicket, PreSwitchTicketLimitEntry, PreSwitchTicketSystemAppContentAccessFlags,
+    PreTicketLicense,
+};
+use crate::title_id::{TitleId, WiiTitleKind};
+use crate::title_metadata::ipc_mask::TitleMetadataIpcMask;
+use crate::title_metadata::ratings::TitleMetadataRatings;
+use crate::title_metadata::{
+    TitleMetadata, TitleMetadataPlatformData, TitleMetadataPlatformDataWiiRegion,
+};
+#[cfg(feature = "wad")]
+use crate::title_metadata::{
+    TitleMetadataContentEntry, TitleMetadataContentEntryHashKind, TitleMetadataContentEntryKind,
+};
+#[cfg(feature = "wad")]
+use crate::wad::installable::{InstallableWad, InstallableWadError, InstallableWadKind};
+#[cfg(feature = "wad")]
+use std::io::{Cursor, Seek, SeekFrom};
+
+/// Title ID of the current versions of the Homebrew Channel (`LULZ`).
+pub const HOMEBREW_CHANNEL_TITLE_ID: u64 = 0x00010001_4C554C5A;
+
+/// Title ID of the first versions of the Homebrew Channel (`HAXX`).
+pub const HAXX_TITLE_ID: u64 = 0x00010001_48415858;
+
+/// Title ID of the versions of the Homebrew Channel between `HAXX` and `LULZ` (`JODI`).
+pub const JODI_TITLE_ID: u64 = 0x00010001_4A4F4449;
+
+/// Title ID of the IOS used by default by homebrew channels (IOS58, the one with USB 2.0 support).
+pub const DEFAULT_HOMEBREW_IOS_TITLE_ID: u64 = 0x00000001_0000003A;
+
+const TITLE_METADATA_ISSUER: &str = "Root-CA00000001-CP00000004";
+const TICKET_ISSUER: &str = "Root-CA00000001-XS00000003";
+
+/// Create the title metadata of a homebrew channel with the given title ID. The title is region
+/// free, runs on IOS58 with full hardware access (`AHBPROT` disabled) and has no contents.
+pub fn homebrew_title_metadata(title_id: u64) -> TitleMetadata {
+    TitleMetadata {
+        signed_blob_header: unsigned_blob_header(TITLE_METADATA_ISSUER),
+        certificate_authority_certificate_revocation_list_version: 0,
+        signer_certificate_revocation_list_version: 0,
+        system_runtime_title_id: Some(TitleId::new(DEFAULT_HOMEBREW_IOS_TITLE_ID)),
+        title_id: TitleId::new(title_id),
+        // Channels are usually given an unused group ID (zero)
+        group_id: 0,
+        // Access to the hardware from the PPC and to the DVD drive
+        access_rights: 0b11,
+        title_version: 0,
+        title_minor_version: 0,
+        boot_content_index: 0,
+        platform_data: TitleMetadataPlatformData::Wii {
+            is_wii_u_vwii_only_title: false,
+            region: TitleMetadataPlatformDataWiiRegion::RegionFree,
+            ratings: TitleMetadataRatings::default(),
+            ipc_mask: TitleMetadataIpcMask::default(),
+        },
+        version_1_extension: None,
+        content_chunk_entries: vec![],
+    }
+}
+
+/// Create the title metadata of a homebrew hidden channel (not shown on the System Menu) with the
+/// given four character code, see [homebrew_title_metadata].
+pub fn hidden_channel_title_metadata(code: [u8; 4]) -> TitleMetadata {
+    homebrew_title_metadata(TitleId::new_wii(WiiTitleKind::HiddenChannel, code).inner())
+}
+
+/// Create the title metadata of a homebrew system channel with the given four character code,
+/// see [homebrew_title_metadata]. Be aware that a code already used by the console replaces its
+/// channel.
+pub fn system_channel_title_metadata(code: [u8; 4]) -> TitleMetadata {
+    homebrew_title_metadata(TitleId::new_wii(WiiTitleKind::SystemChannel, code).inner())
+}
+
+/// Create a ticket of a homebrew channel with the given title ID. The ticket is valid for all
+/// consoles, has no limits and gives access to all the contents.
+///
+/// The encrypted title key is zeroed, it can be replaced with any value as long as the contents
+/// are encrypted with the decrypted one.
+pub fn homebrew_ticket(title_id: u64) -> PreSwitchTicket {
+    PreSwitchTicket {
+        signed_blob_header: unsigned_blob_header(TICKET_ISSUER),
+        ecc_public_key: [0; 60],
+        certificate_authority_certificate_revocation_list_version: 0,
+        signer_certificate_revocation_list_version: 0,
+        encrypted_title_key: [0; 16],
+        ticket_id: 0,
+        device_id: None,
+        title_id: TitleId::new(title_id),
+        system_app_content_access: PreSwitchTicketSystemAppContentAccessFlags::empty(),
+        title_version: 0,
+        permitted_generic_title_id: 0,
+        permitted_generic_title_id_mask: 0,
+        license: PreTicketLicense::Normal,
+        common_key_kind_index: 0,
+        reserved: [0; 42],
+        account_id: None,
+        audit_reserved: 0,
+        audit: 0,
+        content_access_permissions: [0xFF; 64],
+        padding: 0,
+        limit_entries: [const { PreSwitchTicketLimitEntry::NoLimit { kind: 0 } }; 8],
+        version_1_extension: None,
+        truncated: false,
+    }
+}
+
+/// Create the title metadata of the Homebrew Channel, see [homebrew_title_metadata].
+pub fn homebrew_channel_tmd() -> TitleMetadata {
+    homebrew_title_metadata(HOMEBREW_CHANNEL_TITLE_ID)
+}
+
+/// Create the ticket of the Homebrew Channel, see [homebrew_ticket].
+pub fn homebrew_channel_ticket() -> PreSwitchTicket {
+    homebrew_ticket(HOMEBREW_CHANNEL_TITLE_ID)
+}
+
+/// Create an installable WAD of a homebrew channel with the given title ID and contents (their
+/// IDs and indexes are their positions), see [homebrew_title_metadata] and [homebrew_ticket].
+///
+/// The WAD has no certificate chain and its title metadata is not fakesigned, useful as the base
+/// of a new channel or as an in-memory fixture.
+#[cfg(feature = "wad")]
+pub fn homebrew_wad(title_id: u64, contents: &[&[u8]]) -> Result<Vec<u8>, InstallableWadError> {
+    let ticket = homebrew_ticket(title_id);
+    let mut title_metadata = homebrew_title_metadata(title_id);
+
+    // Placeholder sizes until the contents are replaced, an empty view cannot be created
+    for i in 0..contents.len() {
+        title_metadata
+            .content_chunk_entries
+            .push(TitleMetadataContentEntry {
+                id: i as u32,
+                index: i as u16,
+                kind: TitleMetadataContentEntryKind::Normal,
+                hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+                size: 0x10,
+            });
+    }
+
+    let mut wad = InstallableWad {
+        header_size: 0x20,
+        kind: InstallableWadKind::Normal,
+        certificate_chain_size: 0,
+        ticket_size: ticket.size(),
+        title_metadata_size: 0,
+        content_size: 0,
+        footer_size: 0,
+    };
+
+    let mut stream = Cursor::new(vec![]);
+    wad.dump(&mut stream)?;
+
+    // The ticket is the first section after the aligned header
+    stream.seek(SeekFrom::Start(64))?;
+    ticket.dump(&mut stream)?;
+
+    stream.set_position(0);
+    wad.write_title_metadata_safe(&mut stream, &title_metadata)?;
+
+    for (i, data) in contents.iter().enumerate() {
+        stream.set_position(0);
+        wad.modify_content(&mut stream)
+            .set_cryptography(&ticket, CryptographicMethod::Wii)
+            .replace(
+                Cursor::new(data.to_vec()),
+                title_metadata.select_with_physical_position(i),
+                &mut title_metadata,
+            )?;
+    }
+
+    Ok(stream.into_inner())
+}
+
+fn unsigned_blob_header(issuer: &str) -> SignedBlobHeader {
+    SignedBlobHeader {
+        signature: SignedBlobHeaderSignature::Rsa2048Sha1(Box::new([0; 256])),
+        issuer: issuer.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn templates_round_trip() {
+        let mut buffer = Cursor::new(vec![]);
+        homebrew_channel_tmd().dump(&mut buffer).unwrap();
+        buffer.set_position(0);
+
+        let title_metadata = TitleMetadata::new(&mut buffer).unwrap();
+        assert_eq!(title_metadata.title_id.inner(), HOMEBREW_CHANNEL_TITLE_ID);
+        assert!(title_metadata.has_ppc_access_wii().unwrap());
+
+        let mut buffer = Cursor::new(vec![]);
+        homebrew_channel_ticket().dump(&mut buffer).unwrap();
+        buffer.set_position(0);
+
+        let ticket = PreSwitchTicket::new(&mut buffer).unwrap();
+        assert_eq!(ticket.signed_blob_header.issuer, TICKET_ISSUER);
+        assert!(ticket.can_access_content(5));
+    }
+
+    #[test]
+    fn channel_kinds() {
+        let title_metadata = hidden_channel_title_metadata(*b"HAKE");
+        assert_eq!(title_metadata.title_id.inner(), 0x00010008_48414B45);
+
+        let title_metadata = system_channel_title_metadata(*b"HAXX");
+        assert_eq!(
+            title_metadata.title_id.wii_kind(),
+            WiiTitleKind::SystemChannel
+        );
+    }
+
+    #[test]
+    fn fakesign_templates() {
+        use sha1::{Digest, Sha1};
+
+        let mut ticket = homebrew_channel_ticket();
+        ticket.fakesign().unwrap();
+
+        let mut buffer = Cursor::new(vec![]);
+        ticket.dump(&mut buffer).unwrap();
+
+        let signed_data_offset = ticket.signed_blob_header.size() as usize - 64;
+        assert_eq!(Sha1::digest(&buffer.get_ref()[signed_data_offset..])[0], 0);
+
+        let mut title_metadata = homebrew_channel_tmd();
+        title_metadata.fakesign().unwrap();
+
+        let mut buffer = Cursor::new(vec![]);
+        title_metadata.dump(&mut buffer).unwrap();
+        buffer.set_position(0);
+
+        assert_eq!(
+            TitleMetadata::new(&mut buffer).unwrap().title_minor_version,
+            title_metadata.title_minor_version
+        );
+        assert_eq!(Sha1::digest(&buffer.get_ref()[signed_data_offset..])[0], 0);
+    }
+
+    #[cfg(feature = "wad")]
+    #[test]
+    fn homebrew_wad_contents() {
+        use crate::wad::Wad;
+        use std::io::Read;
+
+        let mut stream =
+            Cursor::new(homebrew_wad(JODI_TITLE_ID, &[b"boot.dol", &[0x42; 0x50]]).unwrap());
+        let wad = Wad::try_new_installable(&mut stream).unwrap();
+
+        let ticket = wad.ticket(&mut stream).unwrap();
+        let title_metadata = wad.title_metadata(&mut stream).unwrap();
+        assert_eq!(title_metadata.title_id.inner(), JODI_TITLE_ID);
+        assert_eq!(title_metadata.content_chunk_entries.len(), 2);
+
+        let content_selector = title_metadata.select_with_index(1);
+        let mut content = vec![0; 0x50];
+        wad.decrypted_content_view(
+            &mut stream,
+            &ticket,
+            &title_metadata,
+            CryptographicMethod::Wii,
+            content_selector,
+        )
+        .unwrap()
+        .read_exact(&mut content)
+        .unwrap();
+
+        assert_eq!(content, [0x42; 0x50]);
+        assert_eq!(
+            content_selector
+                .content_entry(&title_metadata)
+                .unwrap()
+                .size,
+            0x50
+        );
+    }
+}
@@ -8,30 +8,59 @@
 
 use crate::ContentSelector;
 use crate::TitleMetadata;
+use crate::certificate_chain::Certificate;
+use crate::content_transform::{AesCbcDecrypt, AesCbcEncrypt, ContentTransform};
+use crate::parse_limits::ParseLimits;
+#[cfg(feature = "seed_db")]
+use crate::seed_db::The3dsKeys;
 use crate::signed_blob_header::{SignedBlobHeader, SignedBlobHeaderError};
+use crate::telemetry;
 use crate::title_id::TitleId;
-use crate::title_metadata::TitleMetadataError;
+use crate::title_metadata::{
+    TitleMetadataError, TitleMetadataPlatformData, TitleMetadataPlatformDataWiiRegion,
+};
+use crate::title_version::TitleVersion;
 use crate::wii_common_key::{CommonKeyKindError, WiiCommonKeyKind};
-use aes::cipher::{BlockDecryptMut, KeyIvInit, block_padding::NoPadding};
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit, block_padding::NoPadding};
 use bitflags::bitflags;
 use byteorder::{BE, ReadBytesExt, WriteBytesExt};
+use std::collections::HashSet;
+use std::fmt::{self, Display};
 use std::io;
+use std::io::Cursor;
 use std::io::Read;
 use std::io::{Seek, Write};
 use std::string::FromUtf8Error;
+use std::time::SystemTime;
 use thiserror::Error;
 use util::Aes128CbcDec;
+use util::Aes128CbcEnc;
 use util::AesCbcStream;
+use util::ParseContext;
 use util::WriteEx;
 
+pub mod key_cache;
 pub mod v1;
 
+pub use key_cache::KeyCache;
+
+const CONTEXT: ParseContext = ParseContext::new("ticket");
+
 /// The different cryptographic methods that can be used to decrypt the content stored inside a
 /// title.
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CryptographicMethod {
     /// The method used in the Nintendo Wii (and Wii U vWii) platform.
     Wii,
+
+    /// The method used in development consoles of the Nintendo Wii, the same as [Self::Wii] but
+    /// with the development common keys. Needed for titles signed with the SDK.
+    WiiDevelopment,
+
+    /// The method used in the Nintendo 3DS platform, the title keys are encrypted with a keyslot
+    /// of the console whose keys must be given.
+    #[cfg(feature = "seed_db")]
+    The3ds(The3dsKeys),
 }
 
 /// Manifest data regard the ownership of a title and its permissions over the hardware.
@@ -99,7 +128,21 @@ pub struct PreSwitchTicket {
     /// common key is platform dependant.
     pub common_key_kind_index: u8,
 
-    /// Audit or revision of the title. The meaning is still not clear.
+    /// Block of 42 bytes after the common key index whose use is still unknown, it is kept
+    /// verbatim as it is covered by the signature of the ticket.
+    // TODO(DISCOVER)
+    pub reserved: [u8; 42],
+
+    /// The ID of the eShop account that purchased the title (only used on the Wii U and 3DS),
+    /// `None` if the ticket is not associated with an account.
+    pub account_id: Option<u32>,
+
+    /// Reserved byte before [Self::audit], usually zero but kept verbatim for the same reasons
+    /// as [Self::reserved].
+    pub audit_reserved: u8,
+
+    /// Audit or revision of the title, present on the Wii U and 3DS (on the Wii it is always
+    /// zero). The meaning is still not clear, its value is only preserved.
     // TODO(DISCOVER)
     pub audit: u8,
 
@@ -107,77 +150,186 @@ pub struct PreSwitchTicket {
     // TODO(DISCOVER)
     pub content_access_permissions: [u8; 64],
 
+    /// Two bytes of padding, not used by the system. Changed to brute force the hash of the
+    /// ticket when fakesigning.
+    pub padding: u16,
+
     /// A set of limits over the use of the title.
     pub limit_entries: [PreSwitchTicketLimitEntry; 8],
 
     /// Extra data only present on the v1 version of a ticket.
     pub version_1_extension: Option<v1::PreSwitchTicketV1>,
+
+    /// The ticket was parsed with [Self::new_lenient] from truncated data, the missing limit
+    /// entries and V1 extension were filled with empty defaults. Not stored when dumping.
+    pub truncated: bool,
 }
 
 impl PreSwitchTicket {
     /// Parse a ticket.
-    pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, PreSwitchTicketError> {
+    pub fn new<T: Read + Seek>(stream: T) -> Result<Self, PreSwitchTicketError> {
+        Self::new_with_limits(stream, &ParseLimits::UNLIMITED)
+    }
+
+    /// Like [Self::new] but fails if the V1 extension of the ticket exceeds the given
+    /// [ParseLimits], useful to parse untrusted files.
+    pub fn new_with_limits<T: Read + Seek>(
+        stream: T,
+        limits: &ParseLimits,
+    ) -> Result<Self, PreSwitchTicketError> {
+        Self::parse(stream, limits, false)
+    }
+
+    /// Like [Self::new] but recovers tickets truncated at the end of the fixed size fields or
+    /// of the limit entries, like some tickets exported from the NAND of a console that are
+    /// missing their V1 extension despite of their version. The missing data is filled with
+    /// empty defaults and [Self::truncated] is set.
+    ///
+    /// Tickets truncated at any other offset still fail.
+    pub fn new_lenient<T: Read + Seek>(stream: T) -> Result<Self, PreSwitchTicketError> {
+        Self::new_lenient_with_limits(stream, &ParseLimits::UNLIMITED)
+    }
+
+    /// Like [Self::new_lenient] but fails if the V1 extension of the ticket exceeds the given
+    /// [ParseLimits].
+    pub fn new_lenient_with_limits<T: Read + Seek>(
+        stream: T,
+        limits: &ParseLimits,
+    ) -> Result<Self, PreSwitchTicketError> {
+        Self::parse(stream, limits, true)
+    }
+
+    fn parse<T: Read + Seek>(
+        mut stream: T,
+        limits: &ParseLimits,
+        lenient: bool,
+    ) -> Result<Self, PreSwitchTicketError> {
         let signed_blob_header = SignedBlobHeader::new(&mut stream)?;
-        let ecc_public_key = util::read_exact!(stream, 60)?;
 
-        let format_version = stream.read_u8()?;
+        let ecc_public_key = CONTEXT.field(&mut stream, "ecc_public_key", |stream| {
+            util::read_exact!(stream, 60)
+        })?;
+
+        let format_version =
+            CONTEXT.field(&mut stream, "format_version", |stream| stream.read_u8())?;
+
+        let certificate_authority_certificate_revocation_list_version = CONTEXT.field(
+            &mut stream,
+            "certificate_authority_certificate_revocation_list_version",
+            |stream| stream.read_u8(),
+        )?;
 
-        let certificate_authority_certificate_revocation_list_version = stream.read_u8()?;
-        let signer_certificate_revocation_list_version = stream.read_u8()?;
+        let signer_certificate_revocation_list_version = CONTEXT.field(
+            &mut stream,
+            "signer_certificate_revocation_list_version",
+            |stream| stream.read_u8(),
+        )?;
 
-        let encrypted_title_key = util::read_exact!(stream, 16)?;
+        let encrypted_title_key = CONTEXT.field(&mut stream, "encrypted_title_key", |stream| {
+            util::read_exact!(stream, 16)
+        })?;
 
         // Skip 1 reserved byte
         stream.seek_relative(1)?;
 
-        let ticket_id = stream.read_u64::<BE>()?;
+        let ticket_id =
+            CONTEXT.field(&mut stream, "ticket_id", |stream| stream.read_u64::<BE>())?;
 
-        let device_id = match stream.read_u32::<BE>()? {
-            0 => None,
-            value => Some(value),
-        };
+        let device_id =
+            match CONTEXT.field(&mut stream, "device_id", |stream| stream.read_u32::<BE>())? {
+                0 => None,
+                value => Some(value),
+            };
 
-        let title_id = TitleId::new(stream.read_u64::<BE>()?);
+        let title_id =
+            TitleId::new(CONTEXT.field(&mut stream, "title_id", |stream| stream.read_u64::<BE>())?);
+
+        let system_app_content_access =
+            CONTEXT.field(&mut stream, "system_app_content_access", |stream| {
+                stream.read_u16::<BE>()
+            })?;
 
         #[allow(clippy::expect_used)]
         let system_app_content_access =
-            PreSwitchTicketSystemAppContentAccessFlags::from_bits(stream.read_u16::<BE>()?)
+            PreSwitchTicketSystemAppContentAccessFlags::from_bits(system_app_content_access)
                 .expect("This will never panic as the bitflags covers all the 16bit range");
 
-        let title_version = stream.read_u16::<BE>()?;
+        let title_version = CONTEXT.field(&mut stream, "title_version", |stream| {
+            stream.read_u16::<BE>()
+        })?;
+
+        let permitted_generic_title_id =
+            CONTEXT.field(&mut stream, "permitted_generic_title_id", |stream| {
+                stream.read_u32::<BE>()
+            })?;
+
+        let permitted_generic_title_id_mask =
+            CONTEXT.field(&mut stream, "permitted_generic_title_id_mask", |stream| {
+                stream.read_u32::<BE>()
+            })?;
+
+        let license = PreTicketLicense::new(
+            CONTEXT.field(&mut stream, "license", |stream| stream.read_u8())?,
+        )?;
 
-        let permitted_generic_title_id = stream.read_u32::<BE>()?;
-        let permitted_generic_title_id_mask = stream.read_u32::<BE>()?;
+        let common_key_kind_index =
+            CONTEXT.field(&mut stream, "common_key_kind_index", |stream| {
+                stream.read_u8()
+            })?;
 
-        let license = PreTicketLicense::new(stream.read_u8()?)?;
-        let common_key_kind_index = stream.read_u8()?;
+        let reserved = CONTEXT.field(&mut stream, "reserved", |stream| {
+            util::read_exact!(stream, 42)
+        })?;
+
+        let account_id =
+            match CONTEXT.field(&mut stream, "account_id", |stream| stream.read_u32::<BE>())? {
+                0 => None,
+                value => Some(value),
+            };
+
+        let audit_reserved =
+            CONTEXT.field(&mut stream, "audit_reserved", |stream| stream.read_u8())?;
 
-        // Skip 47 byte whose use is still unknown
-        stream.seek_relative(47)?;
+        let audit = CONTEXT.field(&mut stream, "audit", |stream| stream.read_u8())?;
 
-        let audit = stream.read_u8()?;
-        let content_access_permissions = util::read_exact!(stream, 64)?;
+        let content_access_permissions =
+            CONTEXT.field(&mut stream, "content_access_permissions", |stream| {
+                util::read_exact!(stream, 64)
+            })?;
 
-        // Skip padding of 2 bytes
-        stream.seek_relative(2)?;
+        let padding = CONTEXT.field(&mut stream, "padding", |stream| stream.read_u16::<BE>())?;
+
+        let mut truncated = lenient && is_at_end(&mut stream)?;
 
         let mut limit_entries = [const { PreSwitchTicketLimitEntry::NoLimit { kind: 0 } }; 8];
-        for limit_entry in &mut limit_entries {
-            *limit_entry = PreSwitchTicketLimitEntry::new(
-                // Kind
-                stream.read_u32::<BE>()?,
-                // Associated value
-                stream.read_u32::<BE>()?,
-            )?;
+        if !truncated {
+            for limit_entry in &mut limit_entries {
+                let (kind, associated_value) =
+                    CONTEXT.field(&mut stream, "limit_entries", |stream| {
+                        Ok((stream.read_u32::<BE>()?, stream.read_u32::<BE>()?))
+                    })?;
+
+                *limit_entry = PreSwitchTicketLimitEntry::new(kind, associated_value)?;
+            }
+        }
+
+        if format_version == 1 && !truncated {
+            truncated = lenient && is_at_end(&mut stream)?;
         }
 
         let version_1_extension = match format_version {
             0 => None,
-            1 => Some(v1::PreSwitchTicketV1::new(&mut stream)?),
+            1 if truncated => Some(v1::PreSwitchTicketV1 {
+                sections: vec![],
+                flags: 0,
+            }),
+            1 => Some(v1::PreSwitchTicketV1::new(&mut stream, limits)?),
 
             _ => return Err(PreSwitchTicketError::IncompatibleVersion(format_version)),
         };
 
+        telemetry::file_parsed("ticket");
+
         Ok(Self {
             signed_blob_header,
             ecc_public_key,
@@ -193,10 +345,15 @@ impl PreSwitchTicket {
             permitted_generic_title_id_mask,
             license,
             common_key_kind_index,
+            reserved,
+            account_id,
+            audit_reserved,
             audit,
             content_access_permissions,
+            padding,
             limit_entries,
             version_1_extension,
+            truncated,
         })
     }
 
@@ -222,14 +379,14 @@ impl PreSwitchTicket {
         self.license.dump(&mut stream)?;
         stream.write_u8(self.common_key_kind_index)?;
 
-        // Skip 47 assigned but unused bytes
-        stream.write_zeroed(47)?;
+        stream.write_all(&self.reserved)?;
+        stream.write_u32::<BE>(self.account_id.unwrap_or(0))?;
+        stream.write_u8(self.audit_reserved)?;
 
         stream.write_u8(self.audit)?;
         stream.write_all(&self.content_access_permissions)?;
 
-        // Skip 2 bytes of padding
-        stream.write_zeroed(2)?;
+        stream.write_u16::<BE>(self.padding)?;
 
         for limit_entry in &self.limit_entries {
             limit_entry.dump(&mut stream)?;
@@ -253,41 +410,389 @@ impl PreSwitchTicket {
         size
     }
 
+    /// Get the version of the title.
+    pub fn version(&self) -> TitleVersion {
+        TitleVersion::new(self.title_version)
+    }
+
     /// Either if this ticket was generated to be used only in a specific console (the associated
     /// title was purchased) or not.
     pub fn is_device_unique(&self) -> bool {
         self.device_id.is_some()
     }
 
+    /// Get a report of the console (and account) the ticket is bound to, if any. When the
+    /// device certificate of a console is given its NG ID is included, to detect tickets of
+    /// other consoles before trying to install them.
+    pub fn device_association(
+        &self,
+        device_certificate: Option<&Certificate>,
+    ) -> TicketDeviceAssociation {
+        if !self.is_personalized() {
+            return TicketDeviceAssociation::Common;
+        }
+
+        TicketDeviceAssociation::Personalized {
+            device_id: self.device_id,
+            account_id: self.account_id,
+            ng_id: device_certificate.and_then(Certificate::ng_id),
+        }
+    }
+
+    /// Either if this ticket has been personalized for a console or an account, see
+    /// [Self::strip_personalization].
+    pub fn is_personalized(&self) -> bool {
+        self.device_id.is_some()
+            || self.account_id.is_some()
+            || self
+                .version_1_extension
+                .as_ref()
+                .is_some_and(|version_1_extension| version_1_extension.is_personalized())
+    }
+
+    /// Remove all the console and account specific data of the ticket, creating a "common"
+    /// ticket that can be used by backup installers. The device ID, account ID and ECC public
+    /// key are cleared and the account bound sections of the V1 extension are removed.
+    ///
+    /// Be aware that the title key of tickets personalized on the Wii U is encrypted again with
+    /// a key derived from the console, it will need to be replaced with the plain encrypted one.
+    /// The ticket will also need to be signed again (usually fakesigned).
+    pub fn strip_personalization(&mut self) {
+        self.device_id = None;
+        self.account_id = None;
+        self.ecc_public_key = [0; 60];
+
+        if let Some(version_1_extension) = &mut self.version_1_extension {
+            version_1_extension.strip_personalization();
+        }
+    }
+
+    /// Bind the ticket to the given console, storing its ID on [Self::device_id] as expected by
+    /// its platform (see [ConsoleId::ticket_device_id]).
+    ///
+    /// Fails with [PreSwitchTicketError::ConsoleIdNotValidForTitle] if the console is not of the
+    /// platform of the title of the ticket (like the ID of a Wii on a 3DS ticket). The ticket
+    /// will need to be signed again.
+    pub fn personalize(&mut self, console_id: ConsoleId) -> Result<(), PreSwitchTicketError> {
+        if !console_id.is_valid_for_title(&self.title_id) {
+            return Err(PreSwitchTicketError::ConsoleIdNotValidForTitle(
+                console_id.platform(),
+                self.title_id.inner(),
+            ));
+        }
+
+        self.device_id = Some(console_id.ticket_device_id());
+
+        Ok(())
+    }
+
+    /// Check if the ticket can be used by the given console, always `true` for tickets not bound
+    /// to a console.
+    pub fn is_usable_by(&self, console_id: ConsoleId) -> bool {
+        console_id.is_valid_for_title(&self.title_id)
+            && self
+                .device_id
+                .is_none_or(|device_id| device_id == console_id.ticket_device_id())
+    }
+
+    /// Check if the content with the given index can be accessed, following
+    /// [Self::content_access_permissions].
+    pub fn can_access_content(&self, index: u16) -> bool {
+        self.content_access_permissions
+            .get(index as usize / 8)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    /// Allow or disallow the access to the content with the given index, `None` if the index is
+    /// out of the bounds of [Self::content_access_permissions].
+    pub fn set_content_access(&mut self, index: u16, allowed: bool) -> Option<()> {
+        let byte = self
+            .content_access_permissions
+            .get_mut(index as usize / 8)?;
+
+        if allowed {
+            *byte |= 1 << (index % 8);
+        } else {
+            *byte &= !(1 << (index % 8));
+        }
+
+        Some(())
+    }
+
+    /// Evaluate if the ticket still allows to use the title, following its limits (with the usage
+    /// tracked by the console) and the subscription records of its V1 extension (at the given
+    /// time). The title is allowed while any of its subscription records is active.
+    ///
+    /// The system clock is never read, so the result is reproducible and can be computed on
+    /// platforms without one (like WASM).
+    pub fn entitlement(
+        &self,
+        usage: &PreSwitchTicketUsage,
+        now: SystemTime,
+    ) -> PreSwitchTicketEntitlement<'_> {
+        if let Some(limit_entry) = self
+            .limit_entries
+            .iter()
+            .find(|limit_entry| limit_entry.is_exhausted(usage))
+        {
+            return PreSwitchTicketEntitlement::LimitExhausted(limit_entry);
+        }
+
+        let Some(version_1_extension) = &self.version_1_extension else {
+            return PreSwitchTicketEntitlement::Allowed;
+        };
+
+        let statuses: Vec<_> = version_1_extension.expiring_records(now).collect();
+
+        if statuses.iter().any(|status| !status.is_expired()) {
+            return PreSwitchTicketEntitlement::Allowed;
+        }
+
+        match statuses
+            .iter()
+            .map(|status| status.record())
+            .max_by_key(|record| record.expiration_time)
+        {
+            Some(record) => PreSwitchTicketEntitlement::SubscriptionExpired(record),
+            None => PreSwitchTicketEntitlement::Allowed,
+        }
+    }
+
+    /// Cross-reference the content access permissions of the ticket (and the content records of
+    /// its V1 extension) with the contents of the title metadata, a common reason of DLCs not
+    /// unlocking after being repacked.
+    ///
+    /// Permissions (or content records) allowing the access to every content are ignored when
+    /// looking for orphaned permissions, most tickets are issued that way.
+    pub fn content_access_diff(
+        &self,
+        title_metadata: &TitleMetadata,
+    ) -> PreSwitchTicketContentAccessDiff {
+        let indexes: HashSet<u32> = title_metadata
+            .content_chunk_entries
+            .iter()
+            .map(|content_entry| content_entry.index.into())
+            .collect();
+
+        let content_records: Vec<_> = self
+            .version_1_extension
+            .iter()
+            .flat_map(|version_1_extension| version_1_extension.content_records())
+            .collect();
+
+        let mut diff = PreSwitchTicketContentAccessDiff::default();
+
+        for content_entry in &title_metadata.content_chunk_entries {
+            let index = content_entry.index;
+
+            if !self.can_access_content(index)
+                && !content_records
+                    .iter()
+                    .any(|record| record.can_access_content(index.into()))
+            {
+                diff.inaccessible_contents.push(index);
+            }
+        }
+
+        if self.content_access_permissions != [0xFF; 64] {
+            diff.orphaned_permissions = (0..self.content_access_permissions.len() as u16 * 8)
+                .filter(|&index| self.can_access_content(index) && !indexes.contains(&index.into()))
+                .collect();
+        }
+
+        for record in content_records {
+            if record.access_mask != [0xFF; 128] {
+                diff.orphaned_record_permissions.extend(
+                    record
+                        .allowed_content_indexes()
+                        .filter(|index| !indexes.contains(index)),
+                );
+            }
+        }
+
+        diff
+    }
+
+    /// Fakesign the ticket exploiting the [Trucha bug](https://wiibrew.org/wiki/Signing_bug), its
+    /// signature is zeroed and the padding is changed. Only useful on the Wii (and Wii U vWii)
+    /// platform, the console must be running an IOS patched to not check the signatures properly.
+    pub fn fakesign(&mut self) -> Result<(), PreSwitchTicketError> {
+        // Offset of the padding starting from the issuer
+        const PADDING_OFFSET: usize = 290;
+
+        self.signed_blob_header.zero_signature();
+
+        let mut buffer = Cursor::new(vec![]);
+        self.dump(&mut buffer)?;
+
+        let filler_offset = self.signed_blob_header.size() as usize - 64 + PADDING_OFFSET;
+
+        self.padding = self
+            .signed_blob_header
+            .brute_force_trucha(buffer.get_mut(), filler_offset)
+            .ok_or(PreSwitchTicketError::FakesignFailed)?;
+
+        Ok(())
+    }
+
     /// Decrypt the title key.
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use zelzip_niiebla::templates::{self, HOMEBREW_CHANNEL_TITLE_ID};
+    /// use zelzip_niiebla::{CryptographicMethod, Wad};
+    ///
+    /// # fn main() -> Result<(), zelzip_niiebla::Error> {
+    /// let wad_data = templates::homebrew_wad(HOMEBREW_CHANNEL_TITLE_ID, &[b"Homebrew channel"])?;
+    /// let mut stream = Cursor::new(wad_data);
+    /// let wad = Wad::try_new_installable(&mut stream)?;
+    ///
+    /// let mut ticket = wad.ticket(&mut stream)?;
+    /// let title_key = ticket.decrypt_title_key(CryptographicMethod::Wii)?;
+    ///
+    /// // Encrypting the key again gives back the stored one
+    /// let encrypted_title_key = ticket.encrypted_title_key;
+    /// ticket.encrypt_title_key(title_key, CryptographicMethod::Wii)?;
+    /// assert_eq!(ticket.encrypted_title_key, encrypted_title_key);
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn decrypt_title_key(
         &self,
         cryptographic_method: CryptographicMethod,
     ) -> Result<[u8; 16], PreSwitchTicketError> {
+        let (common_key, iv) = self.title_key_cipher_parameters(cryptographic_method)?;
+        let cipher = Aes128CbcDec::new((&common_key).into(), &iv.into());
+
+        let mut title_key = self.encrypted_title_key;
+
+        cipher.decrypt_padded_mut::<NoPadding>(&mut title_key)?;
+
+        Ok(title_key)
+    }
+
+    /// Like [Self::decrypt_title_key] but the title key is taken from the given [KeyCache] if
+    /// it has already been decrypted.
+    pub fn decrypt_title_key_with_cache(
+        &self,
+        cryptographic_method: CryptographicMethod,
+        key_cache: &KeyCache,
+    ) -> Result<[u8; 16], PreSwitchTicketError> {
+        key_cache.title_key(self, cryptographic_method)
+    }
+
+    fn title_key(
+        &self,
+        cryptographic_method: CryptographicMethod,
+        key_cache: Option<&KeyCache>,
+    ) -> Result<[u8; 16], PreSwitchTicketError> {
+        match key_cache {
+            Some(key_cache) => key_cache.title_key(self, cryptographic_method),
+            None => self.decrypt_title_key(cryptographic_method),
+        }
+    }
+
+    /// Encrypt the given title key with the common key of the ticket (see
+    /// [Self::common_key_kind_index]) and store it, the counterpart of [Self::decrypt_title_key].
+    pub fn encrypt_title_key(
+        &mut self,
+        title_key: [u8; 16],
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<(), PreSwitchTicketError> {
+        let (common_key, iv) = self.title_key_cipher_parameters(cryptographic_method)?;
+        let mut cipher = Aes128CbcEnc::new((&common_key).into(), &iv.into());
+
+        let mut encrypted_title_key = title_key;
+        cipher.encrypt_block_mut((&mut encrypted_title_key).into());
+
+        self.encrypted_title_key = encrypted_title_key;
+
+        Ok(())
+    }
+
+    /// Get the common key and IV used to encrypt the title key.
+    fn title_key_cipher_parameters(
+        &self,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<([u8; 16], [u8; 16]), PreSwitchTicketError> {
         match cryptographic_method {
-            CryptographicMethod::Wii => {
+            CryptographicMethod::Wii | CryptographicMethod::WiiDevelopment => {
                 let id = if self.is_device_unique() {
                     self.ticket_id
                 } else {
                     self.title_id.inner()
                 };
 
-                #[allow(clippy::expect_used)]
-                let iv: [u8; 16] = [id.to_be_bytes(), [0; 8]]
-                    .concat()
-                    .try_into()
-                    .expect("Will never fail, the `id` slice has always a size of 8");
+                let common_key_kind = match cryptographic_method {
+                    CryptographicMethod::WiiDevelopment => {
+                        WiiCommonKeyKind::new_development(self.common_key_kind_index)?
+                    }
+                    _ => WiiCommonKeyKind::new(self.common_key_kind_index)?,
+                };
 
-                let common_key_kind = WiiCommonKeyKind::new(self.common_key_kind_index)?;
-                let cipher = Aes128CbcDec::new((&common_key_kind.bytes()).into(), &iv.into());
+                Ok((common_key_kind.bytes(), Self::title_key_iv(id)))
+            }
 
-                let mut title_key = self.encrypted_title_key;
+            #[cfg(feature = "seed_db")]
+            CryptographicMethod::The3ds(keys) => Ok((
+                keys.ticket_normal_key(self.common_key_kind_index)?,
+                Self::title_key_iv(self.title_id.inner()),
+            )),
+        }
+    }
 
-                cipher.decrypt_padded_mut::<NoPadding>(&mut title_key)?;
+    /// Get the IV used to encrypt the title key, the given ID followed by 8 zeroed bytes.
+    fn title_key_iv(id: u64) -> [u8; 16] {
+        let mut iv = [0; 16];
+        iv[..8].copy_from_slice(&id.to_be_bytes());
 
-                Ok(title_key)
+        iv
+    }
+
+    /// Check that the common key index of the ticket can be used on the platform of its title,
+    /// a mismatch (like a Korean key on a vWii title) makes the console reject the installation
+    /// (the infamous `-1022` error on the Wii).
+    ///
+    /// Only the platform data of the title metadata is taken into account (and the title ID of
+    /// the ticket to detect vWii system titles), the signatures are not checked.
+    pub fn check_common_key_kind(
+        &self,
+        title_metadata: &TitleMetadata,
+    ) -> Result<(), PreSwitchTicketError> {
+        let index = self.common_key_kind_index;
+
+        let (platform, is_valid) = match &title_metadata.platform_data {
+            TitleMetadataPlatformData::Wii {
+                is_wii_u_vwii_only_title,
+                region,
+                ..
+            } => {
+                // vWii system titles (IOS, etc) have their own higher half of the title ID
+                if *is_wii_u_vwii_only_title || self.title_id.higher_half() == 0x00000007 {
+                    ("vWii", index == 2)
+                } else if matches!(region, TitleMetadataPlatformDataWiiRegion::Korea) {
+                    // Korean consoles have both the normal and the Korean keys
+                    ("Korean Wii", index <= 1)
+                } else {
+                    ("non-Korean Wii", index == 0)
+                }
             }
+
+            TitleMetadataPlatformData::DSi => ("DSi", index == 0),
+
+            // The index selects one of the six KeyY slots of the common key
+            TitleMetadataPlatformData::Console3ds(_) => ("3DS", index <= 5),
+
+            TitleMetadataPlatformData::WiiU => ("Wii U", index == 0),
+        };
+
+        if !is_valid {
+            return Err(PreSwitchTicketError::CommonKeyKindNotValidForPlatform(
+                index, platform,
+            ));
         }
+
+        Ok(())
     }
 
     /// Get a decryptor of a content, where the `stream` is the content bytes.
@@ -298,25 +803,329 @@ impl PreSwitchTicket {
         content_selector: ContentSelector,
         cryptographic_method: CryptographicMethod,
     ) -> Result<AesCbcStream<T>, PreSwitchTicketError> {
-        match cryptographic_method {
-            CryptographicMethod::Wii => {
-                let title_key = self.decrypt_title_key(cryptographic_method)?;
+        self.cryptographic_stream_with_optional_cache(
+            stream,
+            title_metadata,
+            content_selector,
+            cryptographic_method,
+            None,
+        )
+    }
 
-                // Add 14 trailing zeroed bytes to the IV
-                let mut iv = Vec::from(content_selector.index(title_metadata)?.to_be_bytes());
-                iv.append(&mut Vec::from([0; 14]));
+    /// Like [Self::cryptographic_stream] but the title key is taken from the given [KeyCache].
+    pub fn cryptographic_stream_with_cache<T: Seek>(
+        &self,
+        stream: T,
+        title_metadata: &TitleMetadata,
+        content_selector: ContentSelector,
+        cryptographic_method: CryptographicMethod,
+        key_cache: &KeyCache,
+    ) -> Result<AesCbcStream<T>, PreSwitchTicketError> {
+        self.cryptographic_stream_with_optional_cache(
+            stream,
+            title_metadata,
+            content_selector,
+            cryptographic_method,
+            Some(key_cache),
+        )
+    }
 
-                #[allow(clippy::expect_used)]
-                let iv: [u8; 16] = iv
-                    .try_into()
-                    .expect("Will never fail, the `content_index` is always 16 bits");
+    fn cryptographic_stream_with_optional_cache<T: Seek>(
+        &self,
+        stream: T,
+        title_metadata: &TitleMetadata,
+        content_selector: ContentSelector,
+        cryptographic_method: CryptographicMethod,
+        key_cache: Option<&KeyCache>,
+    ) -> Result<AesCbcStream<T>, PreSwitchTicketError> {
+        // All the methods encrypt the contents with the title key in the same way
+        let title_key = self.title_key(cryptographic_method, key_cache)?;
+        let iv = Self::content_iv(title_metadata, content_selector)?;
 
-                Ok(AesCbcStream::new(stream, title_key, iv)?)
+        telemetry::content_decrypted(content_selector.content_entry(title_metadata)?.size);
+
+        Ok(AesCbcStream::new(stream, title_key, iv)?)
+    }
+
+    /// Get a [ContentTransform] that decrypts a content, like [Self::cryptographic_stream] but
+    /// to be chained in a [ContentPipeline](crate::content_transform::ContentPipeline). The
+    /// output keeps the padding of the encrypted data.
+    pub fn decryption_transform(
+        &self,
+        title_metadata: &TitleMetadata,
+        content_selector: ContentSelector,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<Box<dyn ContentTransform>, PreSwitchTicketError> {
+        self.decryption_transform_with_optional_cache(
+            title_metadata,
+            content_selector,
+            cryptographic_method,
+            None,
+        )
+    }
+
+    /// Like [Self::decryption_transform] but the title key is taken from the given [KeyCache].
+    pub fn decryption_transform_with_cache(
+        &self,
+        title_metadata: &TitleMetadata,
+        content_selector: ContentSelector,
+        cryptographic_method: CryptographicMethod,
+        key_cache: &KeyCache,
+    ) -> Result<Box<dyn ContentTransform>, PreSwitchTicketError> {
+        self.decryption_transform_with_optional_cache(
+            title_metadata,
+            content_selector,
+            cryptographic_method,
+            Some(key_cache),
+        )
+    }
+
+    fn decryption_transform_with_optional_cache(
+        &self,
+        title_metadata: &TitleMetadata,
+        content_selector: ContentSelector,
+        cryptographic_method: CryptographicMethod,
+        key_cache: Option<&KeyCache>,
+    ) -> Result<Box<dyn ContentTransform>, PreSwitchTicketError> {
+        let title_key = self.title_key(cryptographic_method, key_cache)?;
+        let iv = Self::content_iv(title_metadata, content_selector)?;
+
+        telemetry::content_decrypted(content_selector.content_entry(title_metadata)?.size);
+
+        Ok(Box::new(AesCbcDecrypt::new(title_key, iv)))
+    }
+
+    /// Get a [ContentTransform] that encrypts a content, the output is padded to the block size
+    /// of the cipher.
+    pub fn encryption_transform(
+        &self,
+        title_metadata: &TitleMetadata,
+        content_selector: ContentSelector,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<Box<dyn ContentTransform>, PreSwitchTicketError> {
+        let title_key = self.decrypt_title_key(cryptographic_method)?;
+        let iv = Self::content_iv(title_metadata, content_selector)?;
+
+        Ok(Box::new(AesCbcEncrypt::new(title_key, iv)))
+    }
+
+    /// Get the IV used to encrypt a content, its index followed by 14 zeroed bytes.
+    pub(crate) fn content_iv(
+        title_metadata: &TitleMetadata,
+        content_selector: ContentSelector,
+    ) -> Result<[u8; 16], PreSwitchTicketError> {
+        let mut iv = [0; 16];
+        iv[..2].copy_from_slice(&content_selector.index(title_metadata)?.to_be_bytes());
+
+        Ok(iv)
+    }
+}
+
+/// Check if there is no data left to read on the stream, its position is kept.
+fn is_at_end<T: Seek>(mut stream: T) -> io::Result<bool> {
+    let position = stream.stream_position()?;
+    let end = stream.seek(io::SeekFrom::End(0))?;
+    stream.seek(io::SeekFrom::Start(position))?;
+
+    Ok(position >= end)
+}
+
+/// A compact one-line summary of the ticket, like `00010001-HAGA v1.0 ticket 0002000012345678`
+/// followed by the console it is bound to, if any.
+impl Display for PreSwitchTicket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ticket {:016x}",
+            self.title_id.display_ascii(),
+            self.version(),
+            self.ticket_id
+        )?;
+
+        if let Some(device_id) = self.device_id {
+            write!(f, " (console {device_id:08x})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Usage of a title tracked by the console, needed to evaluate the limits of its ticket, see
+/// [PreSwitchTicket::entitlement].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreSwitchTicketUsage {
+    /// The number of minutes the title has been played.
+    pub minutes_played: u32,
+
+    /// The number of times the title has been launched.
+    pub number_of_launches: u32,
+}
+
+/// Whether a ticket allows to use its title, see [PreSwitchTicket::entitlement].
+#[derive(Debug)]
+pub enum PreSwitchTicketEntitlement<'a> {
+    /// The title can be used.
+    Allowed,
+
+    /// One of the limits of the ticket has been reached.
+    LimitExhausted(&'a PreSwitchTicketLimitEntry),
+
+    /// All the subscription records of the ticket have expired, holds the one that expired the
+    /// latest.
+    SubscriptionExpired(&'a v1::PreSwitchTicketV1RecordSubscription),
+}
+
+impl PreSwitchTicketEntitlement<'_> {
+    /// Either if the title can be used.
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Self::Allowed)
+    }
+}
+
+/// Differences between the content access permissions of a ticket and the contents of a title
+/// metadata, see [PreSwitchTicket::content_access_diff].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreSwitchTicketContentAccessDiff {
+    /// Indexes of the contents of the title metadata the ticket does not allow to access.
+    pub inaccessible_contents: Vec<u16>,
+
+    /// Indexes allowed by [PreSwitchTicket::content_access_permissions] without a content on the
+    /// title metadata.
+    pub orphaned_permissions: Vec<u16>,
+
+    /// Indexes allowed by the content records of the V1 extension without a content on the title
+    /// metadata.
+    pub orphaned_record_permissions: Vec<u32>,
+}
+
+impl PreSwitchTicketContentAccessDiff {
+    /// Either if the permissions of the ticket match the contents of the title metadata.
+    pub fn is_consistent(&self) -> bool {
+        self.inaccessible_contents.is_empty()
+            && self.orphaned_permissions.is_empty()
+            && self.orphaned_record_permissions.is_empty()
+    }
+}
+
+/// The console (and account) a ticket is bound to, see [PreSwitchTicket::device_association].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TicketDeviceAssociation {
+    /// The ticket can be installed on any console.
+    Common,
+
+    /// The ticket has been personalized, usually when the title was purchased.
+    Personalized {
+        /// The ID of the console the ticket is bound to, if any.
+        device_id: Option<u32>,
+
+        /// The ID of the eShop account the ticket is bound to, if any.
+        account_id: Option<u32>,
+
+        /// The NG ID of the given device certificate, `None` if no certificate (or not the one
+        /// of a console) was given.
+        ng_id: Option<u32>,
+    },
+}
+
+impl TicketDeviceAssociation {
+    /// Get the ID of the console the ticket is bound to as a hexadecimal string, the format used
+    /// in the device certificates (`NGxxxxxxxx`) and by the system menu.
+    pub fn device_id_hex(&self) -> Option<String> {
+        match self {
+            Self::Personalized {
+                device_id: Some(device_id),
+                ..
+            } => Some(format!("{device_id:08x}")),
+
+            _ => None,
+        }
+    }
+
+    /// Check if the ticket can be installed on the console of the given device certificate.
+    /// `None` if it cannot be known (no certificate was given).
+    pub fn matches_console(&self) -> Option<bool> {
+        match self {
+            Self::Common => Some(true),
+
+            Self::Personalized {
+                device_id, ng_id, ..
+            } => {
+                let ng_id = (*ng_id)?;
+
+                Some(device_id.is_none_or(|device_id| device_id == ng_id))
             }
         }
     }
 }
 
+/// The ID of a console, each platform has its own width and uses it differently on the
+/// personalized tickets, see [PreSwitchTicket::personalize].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleId {
+    /// The NG ID of a Wii (or of the vWii of a Wii U), stored as is on the tickets.
+    Wii(u32),
+
+    /// The 64 bit console ID of a DSi (like `08A1xxxxxxxxxxxx`), only its lower half is stored
+    /// on the tickets.
+    Dsi(u64),
+
+    /// The device ID of a 3DS (stored on its OTP region), stored as is on the tickets.
+    Console3ds(u32),
+}
+
+impl ConsoleId {
+    /// Get the console ID of a device certificate: `NGxxxxxxxx` on the Wii and
+    /// `CTxxxxxxxx-xx` on the 3DS. `None` if the certificate is not the one of a console (the
+    /// certificates of the DSi do not store its whole console ID).
+    pub fn from_device_certificate(certificate: &Certificate) -> Option<Self> {
+        if let Some(ng_id) = certificate.ng_id() {
+            return Some(Self::Wii(ng_id));
+        }
+
+        // The suffix is the kind of the console (retail or development)
+        let (device_id, _) = certificate.identity.strip_prefix("CT")?.split_once('-')?;
+
+        if device_id.len() != 8 {
+            return None;
+        }
+
+        u32::from_str_radix(device_id, 16)
+            .ok()
+            .map(Self::Console3ds)
+    }
+
+    /// Get the value stored on [PreSwitchTicket::device_id] of the tickets personalized for
+    /// this console.
+    pub fn ticket_device_id(&self) -> u32 {
+        match self {
+            Self::Wii(id) | Self::Console3ds(id) => *id,
+            Self::Dsi(id) => *id as u32,
+        }
+    }
+
+    /// Check if the title ID is from the platform of the console, the only titles that can
+    /// have a ticket personalized for it.
+    pub fn is_valid_for_title(&self, title_id: &TitleId) -> bool {
+        // The higher 16 bits of the title ID are the platform
+        let platform = title_id.higher_half() >> 16;
+
+        match self {
+            // Also includes the vWii system titles (`00000007`)
+            Self::Wii(_) => platform <= 0x0001,
+            Self::Dsi(_) => platform == 0x0003,
+            Self::Console3ds(_) => platform == 0x0004,
+        }
+    }
+
+    fn platform(&self) -> &'static str {
+        match self {
+            Self::Wii(_) => "Wii",
+            Self::Dsi(_) => "DSi",
+            Self::Console3ds(_) => "3DS",
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 #[allow(missing_docs)]
 pub enum PreSwitchTicketError {
@@ -335,6 +1144,9 @@ pub enum PreSwitchTicketError {
     #[error("An error has occurred while handling the common key: {0}")]
     CommonKeyError(#[from] CommonKeyKindError),
 
+    #[error("The common key kind index {0} is not valid for {1} titles")]
+    CommonKeyKindNotValidForPlatform(u8, &'static str),
+
     #[error("Unknown limit entry type: {0:#X}")]
     UnknownLimitEntryType(u32),
 
@@ -355,6 +1167,12 @@ pub enum PreSwitchTicketError {
 
     #[error("Title metadata error: {0}")]
     TitleMetadataError(#[from] TitleMetadataError),
+
+    #[error("Unable to find a padding value that fakesigns the ticket")]
+    FakesignFailed,
+
+    #[error("The ID of a {0} console cannot be used on a ticket of the title {1:016X}")]
+    ConsoleIdNotValidForTitle(&'static str, u64),
 }
 
 bitflags! {
@@ -473,6 +1291,17 @@ pub enum PreSwitchTicketLimitEntry {
 }
 
 impl PreSwitchTicketLimitEntry {
+    /// Check if the limit has been reached with the given usage of the title.
+    pub fn is_exhausted(&self, usage: &PreSwitchTicketUsage) -> bool {
+        match self {
+            Self::NoLimit { .. } => false,
+            Self::TimeLimit { minutes } => usage.minutes_played >= *minutes,
+            Self::LaunchLimit { number_of_launches } => {
+                usage.number_of_launches >= *number_of_launches
+            }
+        }
+    }
+
     fn new(kind: u32, associated_value: u32) -> Result<Self, PreSwitchTicketError> {
         Ok(match kind {
             0 | 3 => Self::NoLimit { kind },
@@ -481,7 +1310,7 @@ impl PreSwitchTicketLimitEntry {
                 minutes: associated_value,
             },
 
-            2 => Self::LaunchLimit {
+            4 => Self::LaunchLimit {
                 number_of_launches: associated_value,
             },
 
@@ -508,3 +1337,434 @@ impl PreSwitchTicketLimitEntry {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate_chain::{CertificateKey, CertificateKeyValue};
+    use crate::signed_blob_header::SignedBlobHeaderSignature;
+    use crate::templates::{homebrew_ticket, homebrew_title_metadata};
+
+    #[test]
+    fn launch_limit_entry_round_trip() {
+        let limit_entry = PreSwitchTicketLimitEntry::LaunchLimit {
+            number_of_launches: 10,
+        };
+
+        let mut buffer = vec![];
+        limit_entry.dump(&mut buffer).unwrap();
+
+        assert_eq!(buffer, [0, 0, 0, 4, 0, 0, 0, 10]);
+
+        let kind = u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+        let associated_value = u32::from_be_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]);
+
+        assert!(matches!(
+            PreSwitchTicketLimitEntry::new(kind, associated_value).unwrap(),
+            PreSwitchTicketLimitEntry::LaunchLimit {
+                number_of_launches: 10
+            }
+        ));
+    }
+
+    #[test]
+    fn strip_personalization() {
+        let mut ticket = homebrew_ticket(0x00050000_10101D00);
+        ticket.device_id = Some(0x1234_5678);
+        ticket.account_id = Some(0x0ABC_DEF0);
+
+        let mut buffer = Cursor::new(vec![]);
+        ticket.dump(&mut buffer).unwrap();
+        buffer.set_position(0);
+
+        let mut ticket = PreSwitchTicket::new(&mut buffer).unwrap();
+        assert_eq!(ticket.account_id, Some(0x0ABC_DEF0));
+        assert!(ticket.is_personalized());
+
+        ticket.strip_personalization();
+        assert!(!ticket.is_personalized());
+    }
+
+    #[test]
+    fn entitlement() {
+        use std::time::{Duration, UNIX_EPOCH};
+        use v1::{
+            PreSwitchTicketV1, PreSwitchTicketV1RecordSubscription, PreSwitchTicketV1Records,
+            PreSwitchTicketV1RefereceId, PreSwitchTicketV1Section,
+        };
+
+        let mut ticket = homebrew_ticket(0x00010001_48414741);
+        let usage = PreSwitchTicketUsage {
+            minutes_played: 30,
+            number_of_launches: 2,
+        };
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+
+        assert!(ticket.entitlement(&usage, now).is_allowed());
+
+        ticket.limit_entries[0] = PreSwitchTicketLimitEntry::LaunchLimit {
+            number_of_launches: 3,
+        };
+        ticket.limit_entries[1] = PreSwitchTicketLimitEntry::TimeLimit { minutes: 30 };
+
+        assert!(matches!(
+            ticket.entitlement(&usage, now),
+            PreSwitchTicketEntitlement::LimitExhausted(PreSwitchTicketLimitEntry::TimeLimit {
+                minutes: 30
+            })
+        ));
+
+        ticket.limit_entries[1] = PreSwitchTicketLimitEntry::NoLimit { kind: 0 };
+
+        let subscription = |expiration_time| PreSwitchTicketV1RecordSubscription {
+            expiration_time,
+            reference_id: PreSwitchTicketV1RefereceId {
+                id: [0; 16],
+                attributes: 0,
+            },
+        };
+
+        ticket.version_1_extension = Some(PreSwitchTicketV1 {
+            sections: vec![PreSwitchTicketV1Section {
+                records: PreSwitchTicketV1Records::Subscription(vec![
+                    subscription(500),
+                    subscription(900),
+                ]),
+                flags: 0,
+            }],
+            flags: 0,
+        });
+
+        assert!(matches!(
+            ticket.entitlement(&usage, now),
+            PreSwitchTicketEntitlement::SubscriptionExpired(record)
+                if record.expiration_time == 900
+        ));
+        assert!(
+            ticket
+                .entitlement(&usage, UNIX_EPOCH + Duration::from_secs(600))
+                .is_allowed()
+        );
+    }
+
+    #[test]
+    fn content_access_diff() {
+        use crate::title_metadata::{
+            TitleMetadataContentEntry, TitleMetadataContentEntryHashKind,
+            TitleMetadataContentEntryKind,
+        };
+        use v1::{
+            PreSwitchTicketV1, PreSwitchTicketV1RecordContent, PreSwitchTicketV1Records,
+            PreSwitchTicketV1Section,
+        };
+
+        let mut title_metadata = homebrew_title_metadata(0x00010005_48414741);
+        let mut ticket = homebrew_ticket(0x00010005_48414741);
+
+        for index in [0, 1, 600] {
+            title_metadata
+                .content_chunk_entries
+                .push(TitleMetadataContentEntry {
+                    id: index.into(),
+                    index,
+                    kind: TitleMetadataContentEntryKind::Dlc,
+                    size: 0,
+                    hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+                });
+        }
+
+        // Every permission is set, the content out of its range is still inaccessible
+        let diff = ticket.content_access_diff(&title_metadata);
+        assert_eq!(diff.inaccessible_contents, [600]);
+        assert!(diff.orphaned_permissions.is_empty());
+        assert!(!diff.is_consistent());
+
+        ticket.content_access_permissions = [0; 64];
+        ticket.set_content_access(0, true).unwrap();
+        ticket.set_content_access(2, true).unwrap();
+
+        let content_record = |mask| {
+            let mut access_mask = [0; 128];
+            access_mask[0] = mask;
+
+            Some(PreSwitchTicketV1 {
+                sections: vec![PreSwitchTicketV1Section {
+                    records: PreSwitchTicketV1Records::Content(vec![
+                        PreSwitchTicketV1RecordContent {
+                            offset_content_index: 600,
+                            access_mask,
+                        },
+                    ]),
+                    flags: 0,
+                }],
+                flags: 0,
+            })
+        };
+
+        ticket.version_1_extension = content_record(0b101);
+
+        let diff = ticket.content_access_diff(&title_metadata);
+        assert_eq!(
+            diff,
+            PreSwitchTicketContentAccessDiff {
+                inaccessible_contents: vec![1],
+                orphaned_permissions: vec![2],
+                orphaned_record_permissions: vec![602],
+            }
+        );
+
+        ticket.set_content_access(1, true).unwrap();
+        ticket.set_content_access(2, false).unwrap();
+        ticket.version_1_extension = content_record(0b1);
+
+        assert!(ticket.content_access_diff(&title_metadata).is_consistent());
+    }
+
+    #[test]
+    fn check_common_key_kind() {
+        let mut title_metadata = homebrew_title_metadata(0x00010001_48414741);
+        let mut ticket = homebrew_ticket(0x00010001_48414741);
+
+        ticket.check_common_key_kind(&title_metadata).unwrap();
+
+        ticket.common_key_kind_index = 1;
+        assert!(matches!(
+            ticket.check_common_key_kind(&title_metadata),
+            Err(PreSwitchTicketError::CommonKeyKindNotValidForPlatform(
+                1,
+                "non-Korean Wii"
+            ))
+        ));
+
+        if let TitleMetadataPlatformData::Wii { region, .. } = &mut title_metadata.platform_data {
+            *region = TitleMetadataPlatformDataWiiRegion::Korea;
+        }
+        ticket.check_common_key_kind(&title_metadata).unwrap();
+
+        if let TitleMetadataPlatformData::Wii {
+            is_wii_u_vwii_only_title,
+            ..
+        } = &mut title_metadata.platform_data
+        {
+            *is_wii_u_vwii_only_title = true;
+        }
+        assert!(matches!(
+            ticket.check_common_key_kind(&title_metadata),
+            Err(PreSwitchTicketError::CommonKeyKindNotValidForPlatform(
+                1, "vWii"
+            ))
+        ));
+
+        ticket.common_key_kind_index = 2;
+        ticket.check_common_key_kind(&title_metadata).unwrap();
+    }
+
+    #[test]
+    fn device_association() {
+        let mut ticket = homebrew_ticket(0x00010001_48414741);
+        assert_eq!(
+            ticket.device_association(None),
+            TicketDeviceAssociation::Common
+        );
+
+        let device_certificate = Certificate {
+            signed_blob_header: SignedBlobHeader {
+                signature: SignedBlobHeaderSignature::EcdsaSha1(Box::new([0; 60])),
+                issuer: String::from("Root-CA00000001-MS00000002"),
+            },
+            identity: String::from("NG0123abcd"),
+            key: CertificateKey {
+                id: 0,
+                value: CertificateKeyValue::EccB223(Box::new([0; 60])),
+            },
+        };
+
+        ticket.device_id = Some(0x0123_ABCD);
+        let association = ticket.device_association(Some(&device_certificate));
+
+        assert_eq!(association.device_id_hex().as_deref(), Some("0123abcd"));
+        assert_eq!(association.matches_console(), Some(true));
+
+        ticket.device_id = Some(0x0BAD_CAFE);
+        let association = ticket.device_association(Some(&device_certificate));
+        assert_eq!(association.matches_console(), Some(false));
+        assert_eq!(ticket.device_association(None).matches_console(), None);
+    }
+
+    #[test]
+    fn personalize_for_console() {
+        let mut ticket = homebrew_ticket(0x00010001_48414741);
+
+        ticket.personalize(ConsoleId::Wii(0x0123_ABCD)).unwrap();
+        assert_eq!(ticket.device_id, Some(0x0123_ABCD));
+        assert!(ticket.is_usable_by(ConsoleId::Wii(0x0123_ABCD)));
+        assert!(!ticket.is_usable_by(ConsoleId::Wii(0x0BAD_CAFE)));
+
+        assert!(matches!(
+            ticket.personalize(ConsoleId::Console3ds(0x0123_ABCD)),
+            Err(PreSwitchTicketError::ConsoleIdNotValidForTitle(
+                "3DS",
+                0x00010001_48414741
+            ))
+        ));
+        assert!(!ticket.is_usable_by(ConsoleId::Console3ds(0x0123_ABCD)));
+
+        // Only the lower half of the console ID of a DSi is stored
+        ticket.title_id = TitleId::new(0x00030004_48414741);
+        ticket
+            .personalize(ConsoleId::Dsi(0x08A1_9876_0123_ABCD))
+            .unwrap();
+        assert_eq!(ticket.device_id, Some(0x0123_ABCD));
+
+        let mut device_certificate = Certificate {
+            signed_blob_header: SignedBlobHeader {
+                signature: SignedBlobHeaderSignature::EcdsaSha1(Box::new([0; 60])),
+                issuer: String::from("Root-CA00000003-MS00000012"),
+            },
+            identity: String::from("CT0123ABCD-00"),
+            key: CertificateKey {
+                id: 0,
+                value: CertificateKeyValue::EccB223(Box::new([0; 60])),
+            },
+        };
+
+        assert_eq!(
+            ConsoleId::from_device_certificate(&device_certificate),
+            Some(ConsoleId::Console3ds(0x0123_ABCD))
+        );
+
+        device_certificate.identity = String::from("NG0123abcd");
+        assert_eq!(
+            ConsoleId::from_device_certificate(&device_certificate),
+            Some(ConsoleId::Wii(0x0123_ABCD))
+        );
+
+        device_certificate.identity = String::from("XS00000003");
+        assert_eq!(
+            ConsoleId::from_device_certificate(&device_certificate),
+            None
+        );
+    }
+
+    #[test]
+    fn display_summary() {
+        let mut ticket = homebrew_ticket(0x00010001_48414741);
+        ticket.title_version = 256;
+        ticket.ticket_id = 0x0002_0000_1234_5678;
+
+        assert_eq!(
+            "00010001-HAGA v1.0 ticket 0002000012345678",
+            format!("{ticket}")
+        );
+
+        ticket.device_id = Some(0x0ABC_DEF0);
+        assert!(format!("{ticket}").ends_with(" (console 0abcdef0)"));
+    }
+
+    #[test]
+    fn decrypt_title_key_with_development_keys() {
+        use aes::cipher::BlockEncryptMut;
+
+        let title_key = [0x42; 16];
+        let mut ticket = homebrew_ticket(0x00010001_4C554C5A);
+
+        let iv: [u8; 16] = [ticket.title_id.inner().to_be_bytes(), [0; 8]]
+            .concat()
+            .try_into()
+            .unwrap();
+
+        ticket.encrypted_title_key = title_key;
+        cbc::Encryptor::<aes::Aes128>::new(
+            (&WiiCommonKeyKind::Development.bytes()).into(),
+            &iv.into(),
+        )
+        .encrypt_padded_mut::<NoPadding>(&mut ticket.encrypted_title_key, 16)
+        .unwrap();
+
+        assert_eq!(
+            ticket
+                .decrypt_title_key(CryptographicMethod::WiiDevelopment)
+                .unwrap(),
+            title_key
+        );
+        assert_ne!(
+            ticket.decrypt_title_key(CryptographicMethod::Wii).unwrap(),
+            title_key
+        );
+
+        ticket.common_key_kind_index = 1;
+        assert!(
+            ticket
+                .decrypt_title_key(CryptographicMethod::WiiDevelopment)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn preserve_reserved_bytes() {
+        let mut ticket = homebrew_ticket(0x00010001_4C554C5A);
+        ticket.reserved = [0x5A; 42];
+        ticket.audit_reserved = 0x01;
+        ticket.audit = 0x01;
+
+        let mut buffer = Cursor::new(vec![]);
+        ticket.dump(&mut buffer).unwrap();
+        buffer.set_position(0);
+
+        let parsed = PreSwitchTicket::new(&mut buffer).unwrap();
+        assert_eq!(parsed.reserved, [0x5A; 42]);
+        assert_eq!(parsed.audit_reserved, 0x01);
+
+        let mut dumped = Cursor::new(vec![]);
+        parsed.dump(&mut dumped).unwrap();
+        assert_eq!(dumped.get_ref(), buffer.get_ref());
+    }
+
+    #[test]
+    fn parse_truncated_tickets() {
+        // Offsets of the format version and the limit entries of a ticket signed with RSA-2048
+        const FORMAT_VERSION_OFFSET: usize = 0x1BC;
+        const LIMIT_ENTRIES_OFFSET: usize = 0x264;
+
+        let mut ticket = homebrew_ticket(0x00010001_4C554C5A);
+        ticket.limit_entries[0] = PreSwitchTicketLimitEntry::TimeLimit { minutes: 30 };
+
+        let mut buffer = Cursor::new(vec![]);
+        ticket.dump(&mut buffer).unwrap();
+        let mut data = buffer.into_inner();
+
+        let parsed = PreSwitchTicket::new_lenient(Cursor::new(&data)).unwrap();
+        assert!(!parsed.truncated);
+        assert!(parsed.version_1_extension.is_none());
+
+        // Exported with the version of a V1 ticket but without its extension
+        data[FORMAT_VERSION_OFFSET] = 1;
+
+        assert!(PreSwitchTicket::new(Cursor::new(&data)).is_err());
+
+        let parsed = PreSwitchTicket::new_lenient(Cursor::new(&data)).unwrap();
+        assert!(parsed.truncated);
+        assert!(matches!(
+            parsed.limit_entries[0],
+            PreSwitchTicketLimitEntry::TimeLimit { minutes: 30 }
+        ));
+        assert!(
+            parsed
+                .version_1_extension
+                .is_some_and(|version_1_extension| version_1_extension.sections.is_empty())
+        );
+
+        data.truncate(LIMIT_ENTRIES_OFFSET);
+
+        let parsed = PreSwitchTicket::new_lenient(Cursor::new(&data)).unwrap();
+        assert!(parsed.truncated);
+        assert!(matches!(
+            parsed.limit_entries[0],
+            PreSwitchTicketLimitEntry::NoLimit { kind: 0 }
+        ));
+
+        // Not a known boundary
+        data.truncate(LIMIT_ENTRIES_OFFSET - 1);
+        assert!(PreSwitchTicket::new_lenient(Cursor::new(&data)).is_err());
+    }
+}
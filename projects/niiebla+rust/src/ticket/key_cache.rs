@@ -0,0 +1,176 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [KeyCache], memoized title keys shared between the operations of a
+//! session (like a scan of a whole catalog of WADs).
+
+use crate::ticket::{CryptographicMethod, PreSwitchTicket, PreSwitchTicketError};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// The data of a ticket that determines its decrypted title key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct KeyCacheEntry {
+    ticket_id: u64,
+    title_id: u64,
+    common_key_kind_index: u8,
+    encrypted_title_key: [u8; 16],
+    cryptographic_method: CryptographicMethod,
+}
+
+/// Cache of the decrypted title keys, given to the `*_with_cache(...)` variants of the
+/// cryptographic methods (like [PreSwitchTicket::decrypt_title_key_with_cache]) so the title
+/// key of a ticket is only decrypted once per session.
+///
+/// The keys are stored by the ticket ID, title ID and common key index of the ticket, along with
+/// its encrypted title key and the [CryptographicMethod], so a modified ticket (like a rekeyed
+/// one) is never given a stale key. The cache can be shared between threads.
+///
+/// ```
+/// use zelzip_niiebla::CryptographicMethod;
+/// use zelzip_niiebla::templates;
+/// use zelzip_niiebla::ticket::KeyCache;
+///
+/// # fn main() -> Result<(), zelzip_niiebla::Error> {
+/// let key_cache = KeyCache::new();
+/// let ticket = templates::homebrew_channel_ticket();
+///
+/// for _ in 0..3 {
+///     ticket.decrypt_title_key_with_cache(CryptographicMethod::Wii, &key_cache)?;
+/// }
+///
+/// assert_eq!((key_cache.hits(), key_cache.misses()), (2, 1));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct KeyCache {
+    title_keys: Mutex<HashMap<KeyCacheEntry, [u8; 16]>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl KeyCache {
+    /// Create a new empty [KeyCache].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the decrypted title key of the ticket, decrypting it only if it is not cached yet.
+    pub(crate) fn title_key(
+        &self,
+        ticket: &PreSwitchTicket,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<[u8; 16], PreSwitchTicketError> {
+        let entry = KeyCacheEntry {
+            ticket_id: ticket.ticket_id,
+            title_id: ticket.title_id.inner(),
+            common_key_kind_index: ticket.common_key_kind_index,
+            encrypted_title_key: ticket.encrypted_title_key,
+            cryptographic_method,
+        };
+
+        if let Some(title_key) = self.lock().get(&entry) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(*title_key);
+        }
+
+        // Decrypted without holding the lock, at worst two threads decrypt the same key
+        let title_key = ticket.decrypt_title_key(cryptographic_method)?;
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        self.lock().insert(entry, title_key);
+
+        Ok(title_key)
+    }
+
+    /// Get the number of cached title keys.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Check if no title key has been cached yet.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Get the number of title keys taken from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of title keys decrypted because they were not cached.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Remove all the cached title keys, the counters are kept.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<KeyCacheEntry, [u8; 16]>> {
+        // The map is always left in a valid state
+        self.title_keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+
+    #[test]
+    fn cache_title_keys() {
+        let key_cache = KeyCache::new();
+        let ticket = templates::homebrew_channel_ticket();
+
+        let title_key = ticket
+            .decrypt_title_key_with_cache(CryptographicMethod::Wii, &key_cache)
+            .unwrap();
+
+        assert_eq!(
+            title_key,
+            ticket.decrypt_title_key(CryptographicMethod::Wii).unwrap()
+        );
+        assert_eq!(
+            ticket
+                .decrypt_title_key_with_cache(CryptographicMethod::Wii, &key_cache)
+                .unwrap(),
+            title_key
+        );
+        assert_eq!((key_cache.hits(), key_cache.misses()), (1, 1));
+
+        // A rekeyed ticket is not given the stale key
+        let mut rekeyed_ticket = templates::homebrew_channel_ticket();
+        rekeyed_ticket.encrypted_title_key = [0x42; 16];
+
+        assert_eq!(
+            rekeyed_ticket
+                .decrypt_title_key_with_cache(CryptographicMethod::Wii, &key_cache)
+                .unwrap(),
+            rekeyed_ticket
+                .decrypt_title_key(CryptographicMethod::Wii)
+                .unwrap()
+        );
+        assert_eq!(
+            ticket
+                .decrypt_title_key_with_cache(CryptographicMethod::WiiDevelopment, &key_cache)
+                .unwrap(),
+            ticket
+                .decrypt_title_key(CryptographicMethod::WiiDevelopment)
+                .unwrap()
+        );
+        assert_eq!(key_cache.len(), 3);
+        assert_eq!((key_cache.hits(), key_cache.misses()), (1, 3));
+
+        key_cache.clear();
+        assert!(key_cache.is_empty());
+    }
+}
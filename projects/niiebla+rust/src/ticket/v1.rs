@@ -6,11 +6,15 @@
 
 //! Implementation of the Ticket V1 extension.
 
+use crate::parse_limits::{ParseLimitError, ParseLimits};
 use crate::title_id::TitleId;
 use byteorder::{BE, ReadBytesExt, WriteBytesExt};
 use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
-use util::StreamPin;
+use util::{ParseContext, StreamPin, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("ticket V1 extension");
 
 // WARNING! HAZMAT! ACHTUNG! PELIGRO! THIS FORMAT IS REALLY SHITTY SO THIS IS
 // THE CLEANEST WAY TO WRITE THIS AND PRESERVE PROPER TYPING.
@@ -37,41 +41,57 @@ impl PreSwitchTicketV1 {
     const HEADER_SIZE: u16 = 20;
     const SECTION_HEADER_SIZE: u16 = 20;
 
-    pub(super) fn new<T: Read + Seek>(stream: T) -> Result<Self, PreSwitchTicketV1Error> {
+    pub(super) fn new<T: Read + Seek>(
+        stream: T,
+        limits: &ParseLimits,
+    ) -> Result<Self, PreSwitchTicketV1Error> {
         let mut stream = StreamPin::new(stream)?;
 
-        let version = stream.read_u16::<BE>()?;
+        let version = CONTEXT.field(&mut stream, "version", |stream| stream.read_u16::<BE>())?;
         if version != 1 {
             return Err(PreSwitchTicketV1Error::UnknownTicketV1Version(version));
         };
 
-        let header_size = stream.read_u16::<BE>()?;
+        let header_size =
+            CONTEXT.field(&mut stream, "header_size", |stream| stream.read_u16::<BE>())?;
         if header_size != Self::HEADER_SIZE {
             return Err(PreSwitchTicketV1Error::UnknownTicketV1HeaderSize(
                 header_size,
             ));
         }
 
-        let v1_data_size = stream.read_u32::<BE>()?;
+        let v1_data_size = CONTEXT.field(&mut stream, "v1_data_size", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+
+        let first_section_header_offset =
+            CONTEXT.field(&mut stream, "first_section_header_offset", |stream| {
+                stream.read_u32::<BE>()
+            })?;
+
+        let number_of_sections = CONTEXT.field(&mut stream, "number_of_sections", |stream| {
+            stream.read_u16::<BE>()
+        })?;
 
-        let first_section_header_offset = stream.read_u32::<BE>()?;
-        let number_of_sections = stream.read_u16::<BE>()?;
+        limits.check_sections(number_of_sections.into())?;
 
-        let section_header_size = stream.read_u16::<BE>()?;
+        let section_header_size = CONTEXT.field(&mut stream, "section_header_size", |stream| {
+            stream.read_u16::<BE>()
+        })?;
         if section_header_size != Self::SECTION_HEADER_SIZE {
             return Err(PreSwitchTicketV1Error::UnknownTicketV1SectionHeaderSize(
                 section_header_size,
             ));
         }
 
-        let flags = stream.read_u32::<BE>()?;
+        let flags = CONTEXT.field(&mut stream, "flags", |stream| stream.read_u32::<BE>())?;
 
         let mut sections = Vec::new();
 
         stream.seek_from_pin(first_section_header_offset.into())?;
 
         for _ in 0..number_of_sections {
-            sections.push(PreSwitchTicketV1Section::new(&mut stream)?);
+            sections.push(PreSwitchTicketV1Section::new(&mut stream, limits)?);
         }
 
         let v1 = Self { sections, flags };
@@ -94,9 +114,10 @@ impl PreSwitchTicketV1 {
         stream.write_u16::<BE>(Self::HEADER_SIZE)?;
         stream.write_u32::<BE>(self.size())?;
 
-        // Skip this for now as we cannot know the position of the first section yet
-        let first_section_byte_header_position = stream.relative_position()? as u64;
-        stream.seek_relative(4)?;
+        // Zeroed for now as we cannot know the position of the first section yet, it is never
+        // skipped with a seek to not leave the old bytes of the stream if there are no sections
+        let first_section_byte_header_position = stream.stream_position()?;
+        stream.write_zeroed(4)?;
 
         stream.write_u16::<BE>(self.sections.len() as u16)?;
         stream.write_u16::<BE>(Self::SECTION_HEADER_SIZE)?;
@@ -108,16 +129,13 @@ impl PreSwitchTicketV1 {
             section.records.dump(&mut stream)?;
         }
 
-        for (i, section) in self.sections.iter().enumerate() {
-            if i == 0 {
-                let first_section_byte_position = stream.relative_position()? as u64;
-
-                stream.seek_from_pin(first_section_byte_header_position as i64)?;
-                stream.write_u32::<BE>(first_section_byte_position as u32)?;
-
-                stream.seek_from_pin(first_section_byte_position as i64)?;
-            }
+        let first_section_byte_position = stream.relative_position()? as u32;
+        stream.write_at(
+            first_section_byte_header_position,
+            &first_section_byte_position.to_be_bytes(),
+        )?;
 
+        for (i, section) in self.sections.iter().enumerate() {
             stream.write_u32::<BE>(start_of_records[i])?;
 
             stream.write_u32::<BE>(section.records.len())?;
@@ -130,6 +148,7 @@ impl PreSwitchTicketV1 {
                 PreSwitchTicketV1Records::Content(_) => 3,
                 PreSwitchTicketV1Records::ContentConsumption(_) => 4,
                 PreSwitchTicketV1Records::AccessTitle(_) => 5,
+                PreSwitchTicketV1Records::LimitedResource(_) => 6,
             })?;
 
             stream.write_u16::<BE>(section.flags)?;
@@ -138,6 +157,46 @@ impl PreSwitchTicketV1 {
         Ok(())
     }
 
+    /// Either if any of the sections holds records bound to an account.
+    pub fn is_personalized(&self) -> bool {
+        self.sections
+            .iter()
+            .any(|section| section.records.is_account_bound())
+    }
+
+    /// Walk all the subscription records of the ticket, reporting if they have expired at the
+    /// given time (usually [SystemTime::now]).
+    pub fn expiring_records(
+        &self,
+        now: SystemTime,
+    ) -> impl Iterator<Item = PreSwitchTicketV1SubscriptionStatus<'_>> {
+        self.sections
+            .iter()
+            .filter_map(|section| match &section.records {
+                PreSwitchTicketV1Records::Subscription(records) => Some(records),
+                _ => None,
+            })
+            .flatten()
+            .map(move |record| record.status(now))
+    }
+
+    /// Walk all the content records of the ticket.
+    pub fn content_records(&self) -> impl Iterator<Item = &PreSwitchTicketV1RecordContent> {
+        self.sections
+            .iter()
+            .filter_map(|section| match &section.records {
+                PreSwitchTicketV1Records::Content(records) => Some(records),
+                _ => None,
+            })
+            .flatten()
+    }
+
+    /// Remove the sections whose records are bound to an account.
+    pub fn strip_personalization(&mut self) {
+        self.sections
+            .retain(|section| !section.records.is_account_bound());
+    }
+
     pub(super) fn size(&self) -> u32 {
         let mut size = Self::HEADER_SIZE as u32
             + (Self::SECTION_HEADER_SIZE as u32 * self.sections.len() as u32);
@@ -171,6 +230,12 @@ pub enum PreSwitchTicketV1Error {
     #[error("Unknown ticket record size: {0}")]
     UnknownTicketV1RecordSize(u32),
 
+    #[error("A parse limit has been exceeded: {0}")]
+    ParseLimitError(#[from] ParseLimitError),
+
+    #[error("The time cannot be stored as a 32 bits UNIX timestamp: {0:?}")]
+    TimestampOutOfRange(SystemTime),
+
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
 }
@@ -203,6 +268,9 @@ pub enum PreSwitchTicketV1Records {
 
     /// A set of "access title" records.
     AccessTitle(Vec<PreSwitchTicketV1RecordAccessTitle>),
+
+    /// A set of "limited resource" records, seen on the Wii U and 3DS.
+    LimitedResource(Vec<PreSwitchTicketV1RecordLimitedResource>),
 }
 
 impl PreSwitchTicketV1Records {
@@ -213,13 +281,23 @@ impl PreSwitchTicketV1Records {
     fn size_of_one_record(&self) -> u32 {
         match self {
             Self::Permanent(_) => 16 + 4,
-            Self::Subscription(_) => 16 + 4 + 4,
+            // Both are a 32 bits value followed by a reference ID
+            Self::Subscription(_) | Self::LimitedResource(_) => 4 + 16 + 4,
             Self::Content(_) => 128 + 4,
             Self::ContentConsumption(_) => 2 + 2 + 4,
             Self::AccessTitle(_) => 8 + 8,
         }
     }
 
+    /// Either if the records are bound to the account that purchased the title (as they hold
+    /// the reference IDs given by the eShop).
+    pub fn is_account_bound(&self) -> bool {
+        matches!(
+            self,
+            Self::Permanent(_) | Self::Subscription(_) | Self::LimitedResource(_)
+        )
+    }
+
     fn len(&self) -> u32 {
         (match self {
             Self::Permanent(data) => data.len(),
@@ -227,6 +305,7 @@ impl PreSwitchTicketV1Records {
             Self::Content(data) => data.len(),
             Self::ContentConsumption(data) => data.len(),
             Self::AccessTitle(data) => data.len(),
+            Self::LimitedResource(data) => data.len(),
         }) as u32
     }
 
@@ -266,6 +345,13 @@ impl PreSwitchTicketV1Records {
                     stream.write_u64::<BE>(record.title_mask)?;
                 }
             }
+
+            Self::LimitedResource(data) => {
+                for record in data {
+                    stream.write_u32::<BE>(record.limit)?;
+                    record.reference_id.dump(&mut stream)?;
+                }
+            }
         }
 
         Ok(())
@@ -273,16 +359,29 @@ impl PreSwitchTicketV1Records {
 }
 
 impl PreSwitchTicketV1Section {
-    fn new<T: Read + Seek>(stream: &mut StreamPin<T>) -> Result<Self, PreSwitchTicketV1Error> {
-        let section_records_offset = stream.read_u32::<BE>()?;
-        let number_of_records = stream.read_u32::<BE>()?;
-
-        // NOTE: Not worth checking
-        let _size_of_a_record = stream.read_u32::<BE>()?;
-        let _section_total_size = stream.read_u32::<BE>()?;
-
-        let section_kind = stream.read_u16::<BE>()?;
-        let flags = stream.read_u16::<BE>()?;
+    fn new<T: Read + Seek>(
+        stream: &mut StreamPin<T>,
+        limits: &ParseLimits,
+    ) -> Result<Self, PreSwitchTicketV1Error> {
+        let (section_records_offset, number_of_records, section_kind, flags) =
+            CONTEXT.field(stream, "section_header", |stream| {
+                let section_records_offset = stream.read_u32::<BE>()?;
+                let number_of_records = stream.read_u32::<BE>()?;
+
+                // NOTE: Not worth checking
+                let _size_of_a_record = stream.read_u32::<BE>()?;
+                let _section_total_size = stream.read_u32::<BE>()?;
+
+                let section_kind = stream.read_u16::<BE>()?;
+                let flags = stream.read_u16::<BE>()?;
+
+                Ok((
+                    section_records_offset,
+                    number_of_records,
+                    section_kind,
+                    flags,
+                ))
+            })?;
 
         let next_section_position = stream.stream_position()?;
 
@@ -292,66 +391,90 @@ impl PreSwitchTicketV1Section {
             3 => PreSwitchTicketV1Records::Content(vec![]),
             4 => PreSwitchTicketV1Records::ContentConsumption(vec![]),
             5 => PreSwitchTicketV1Records::AccessTitle(vec![]),
+            6 => PreSwitchTicketV1Records::LimitedResource(vec![]),
 
             kind => return Err(PreSwitchTicketV1Error::UnknownTicketV1SectionKind(kind)),
         };
 
+        limits.check_alloc(number_of_records as u64 * records.size_of_one_record() as u64)?;
+
         stream.seek_from_pin(section_records_offset.into())?;
 
         for _ in 0..number_of_records {
-            match records {
-                PreSwitchTicketV1Records::Permanent(ref mut data) => {
-                    let reference_id = PreSwitchTicketV1RefereceId::new(&mut *stream)?;
+            CONTEXT.field(stream, "section_records", |stream| {
+                Self::read_record(stream, &mut records)
+            })?;
+        }
 
-                    data.push(PreSwitchTicketV1RecordPermanent { reference_id });
-                }
+        stream.seek(SeekFrom::Start(next_section_position))?;
+        Ok(Self { records, flags })
+    }
 
-                PreSwitchTicketV1Records::Subscription(ref mut data) => {
-                    let expiration_time = stream.read_u32::<BE>()?;
-                    let reference_id = PreSwitchTicketV1RefereceId::new(&mut *stream)?;
+    fn read_record<T: Read + Seek>(
+        stream: &mut StreamPin<T>,
+        records: &mut PreSwitchTicketV1Records,
+    ) -> io::Result<()> {
+        match records {
+            PreSwitchTicketV1Records::Permanent(data) => {
+                let reference_id = PreSwitchTicketV1RefereceId::new(&mut *stream)?;
 
-                    data.push(PreSwitchTicketV1RecordSubscription {
-                        expiration_time,
-                        reference_id,
-                    })
-                }
+                data.push(PreSwitchTicketV1RecordPermanent { reference_id });
+            }
 
-                PreSwitchTicketV1Records::Content(ref mut data) => {
-                    let offset_content_index = stream.read_u32::<BE>()?;
-                    let access_mask = util::read_exact!(stream, 128)?;
+            PreSwitchTicketV1Records::Subscription(data) => {
+                let expiration_time = stream.read_u32::<BE>()?;
+                let reference_id = PreSwitchTicketV1RefereceId::new(&mut *stream)?;
 
-                    data.push(PreSwitchTicketV1RecordContent {
-                        offset_content_index,
-                        access_mask,
-                    })
-                }
+                data.push(PreSwitchTicketV1RecordSubscription {
+                    expiration_time,
+                    reference_id,
+                })
+            }
 
-                PreSwitchTicketV1Records::ContentConsumption(ref mut data) => {
-                    let content_index = stream.read_u16::<BE>()?;
-                    let limit_code = stream.read_u16::<BE>()?;
-                    let limit_value = stream.read_u32::<BE>()?;
+            PreSwitchTicketV1Records::Content(data) => {
+                let offset_content_index = stream.read_u32::<BE>()?;
+                let access_mask = util::read_exact!(stream, 128)?;
 
-                    data.push(PreSwitchTicketV1RecordContentConsumption {
-                        content_index,
-                        limit_code,
-                        limit_value,
-                    })
-                }
+                data.push(PreSwitchTicketV1RecordContent {
+                    offset_content_index,
+                    access_mask,
+                })
+            }
 
-                PreSwitchTicketV1Records::AccessTitle(ref mut data) => {
-                    let title_id = TitleId::new(stream.read_u64::<BE>()?);
-                    let title_mask = stream.read_u64::<BE>()?;
+            PreSwitchTicketV1Records::ContentConsumption(data) => {
+                let content_index = stream.read_u16::<BE>()?;
+                let limit_code = stream.read_u16::<BE>()?;
+                let limit_value = stream.read_u32::<BE>()?;
 
-                    data.push(PreSwitchTicketV1RecordAccessTitle {
-                        title_id,
-                        title_mask,
-                    })
-                }
+                data.push(PreSwitchTicketV1RecordContentConsumption {
+                    content_index,
+                    limit_code,
+                    limit_value,
+                })
+            }
+
+            PreSwitchTicketV1Records::AccessTitle(data) => {
+                let title_id = TitleId::new(stream.read_u64::<BE>()?);
+                let title_mask = stream.read_u64::<BE>()?;
+
+                data.push(PreSwitchTicketV1RecordAccessTitle {
+                    title_id,
+                    title_mask,
+                })
+            }
+
+            PreSwitchTicketV1Records::LimitedResource(data) => {
+                let limit = stream.read_u32::<BE>()?;
+                let reference_id = PreSwitchTicketV1RefereceId::new(&mut *stream)?;
+
+                data.push(PreSwitchTicketV1RecordLimitedResource {
+                    limit,
+                    reference_id,
+                })
             }
         }
 
-        stream.seek(SeekFrom::Start(next_section_position))?;
-        Ok(Self { records, flags })
+        Ok(())
     }
 }
 
@@ -367,7 +490,7 @@ pub struct PreSwitchTicketV1RefereceId {
 }
 
 impl PreSwitchTicketV1RefereceId {
-    fn new<T: Read>(mut stream: T) -> Result<Self, PreSwitchTicketV1Error> {
+    fn new<T: Read>(mut stream: T) -> io::Result<Self> {
         let id = util::read_exact!(stream, 16)?;
         let attributes = stream.read_u32::<BE>()?;
 
@@ -401,19 +524,113 @@ pub struct PreSwitchTicketV1RecordSubscription {
     pub reference_id: PreSwitchTicketV1RefereceId,
 }
 
-/// A record of kind "content", its meaning is still unknown.
+impl PreSwitchTicketV1RecordSubscription {
+    /// Get the time for the record to expire.
+    pub fn expiration(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.expiration_time as u64)
+    }
+
+    /// Set the time for the record to expire, rounded down to whole seconds.
+    ///
+    /// # Errors
+    /// Fails with [PreSwitchTicketV1Error::TimestampOutOfRange] if the time is before the UNIX
+    /// epoch or after the year 2106.
+    pub fn set_expiration(&mut self, expiration: SystemTime) -> Result<(), PreSwitchTicketV1Error> {
+        self.expiration_time = expiration
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .and_then(|since_epoch| u32::try_from(since_epoch.as_secs()).ok())
+            .ok_or(PreSwitchTicketV1Error::TimestampOutOfRange(expiration))?;
+
+        Ok(())
+    }
+
+    /// Check if the record has expired at the given time.
+    pub fn status(&self, now: SystemTime) -> PreSwitchTicketV1SubscriptionStatus<'_> {
+        match self.expiration().duration_since(now) {
+            Ok(remaining) if !remaining.is_zero() => PreSwitchTicketV1SubscriptionStatus::Active {
+                record: self,
+                remaining,
+            },
+
+            _ => PreSwitchTicketV1SubscriptionStatus::Expired {
+                record: self,
+                since: now
+                    .duration_since(self.expiration())
+                    .unwrap_or(Duration::ZERO),
+            },
+        }
+    }
+}
+
+/// The state of a subscription record at a given time, see
+/// [PreSwitchTicketV1::expiring_records].
+#[derive(Debug)]
+pub enum PreSwitchTicketV1SubscriptionStatus<'a> {
+    /// The record has not expired yet.
+    Active {
+        /// The subscription record.
+        record: &'a PreSwitchTicketV1RecordSubscription,
+
+        /// The time left for the record to expire.
+        remaining: Duration,
+    },
+
+    /// The record has already expired.
+    Expired {
+        /// The subscription record.
+        record: &'a PreSwitchTicketV1RecordSubscription,
+
+        /// The time passed since the record expired.
+        since: Duration,
+    },
+}
+
+impl<'a> PreSwitchTicketV1SubscriptionStatus<'a> {
+    /// Get the subscription record.
+    pub fn record(&self) -> &'a PreSwitchTicketV1RecordSubscription {
+        match self {
+            Self::Active { record, .. } | Self::Expired { record, .. } => record,
+        }
+    }
+
+    /// Either if the record has already expired.
+    pub fn is_expired(&self) -> bool {
+        matches!(self, Self::Expired { .. })
+    }
+}
+
+/// A record of kind "content", allows the access to up to 1024 contents starting at an index.
 #[derive(Debug)]
-// TODO(DISCOVER)
 pub struct PreSwitchTicketV1RecordContent {
-    /// The index offset of the content.
-    // TODO(DISCOVER)
+    /// The index of the content of the first bit of the access mask.
     pub offset_content_index: u32,
 
-    /// The access mark.
-    // TODO(DISCOVER)
+    /// The access mask, one bit per content (starting from the least significant bit of each
+    /// byte).
     pub access_mask: [u8; 128],
 }
 
+impl PreSwitchTicketV1RecordContent {
+    /// Check if the record allows the access to the content with the given index.
+    pub fn can_access_content(&self, index: u32) -> bool {
+        let Some(bit) = index.checked_sub(self.offset_content_index) else {
+            return false;
+        };
+
+        self.access_mask
+            .get(bit as usize / 8)
+            .is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+    }
+
+    /// Iterate the indexes of the contents the record allows to access.
+    pub fn allowed_content_indexes(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.access_mask.len() as u32 * 8)
+            .filter(|bit| self.access_mask[*bit as usize / 8] & (1 << (bit % 8)) != 0)
+            .filter_map(|bit| self.offset_content_index.checked_add(bit))
+    }
+}
+
 /// A record of kind "content consumption", its meaning is still unknown.
 #[derive(Debug)]
 // TODO(DISCOVER)
@@ -441,3 +658,93 @@ pub struct PreSwitchTicketV1RecordAccessTitle {
     /// The mask of title IDs.
     pub title_mask: u64,
 }
+
+/// A record of kind "limited resource", its meaning is still unknown.
+#[derive(Debug)]
+// TODO(DISCOVER)
+pub struct PreSwitchTicketV1RecordLimitedResource {
+    /// The limit applied to the resource.
+    // TODO(DISCOVER)
+    pub limit: u32,
+
+    /// The reference ID attach to the record.
+    pub reference_id: PreSwitchTicketV1RefereceId,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(expiration_time: u32) -> PreSwitchTicketV1RecordSubscription {
+        PreSwitchTicketV1RecordSubscription {
+            expiration_time,
+            reference_id: PreSwitchTicketV1RefereceId {
+                id: [0; 16],
+                attributes: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn expiring_records() {
+        let ticket_v1 = PreSwitchTicketV1 {
+            sections: vec![
+                PreSwitchTicketV1Section {
+                    records: PreSwitchTicketV1Records::Subscription(vec![
+                        subscription(1000),
+                        subscription(3000),
+                    ]),
+                    flags: 0,
+                },
+                PreSwitchTicketV1Section {
+                    records: PreSwitchTicketV1Records::AccessTitle(vec![]),
+                    flags: 0,
+                },
+                PreSwitchTicketV1Section {
+                    records: PreSwitchTicketV1Records::Subscription(vec![subscription(2000)]),
+                    flags: 0,
+                },
+            ],
+            flags: 0,
+        };
+
+        let now = UNIX_EPOCH + Duration::from_secs(2000);
+        let statuses: Vec<_> = ticket_v1.expiring_records(now).collect();
+
+        assert_eq!(statuses.len(), 3);
+
+        assert!(matches!(
+            statuses[0],
+            PreSwitchTicketV1SubscriptionStatus::Expired { since, .. }
+                if since == Duration::from_secs(1000)
+        ));
+        assert!(matches!(
+            statuses[1],
+            PreSwitchTicketV1SubscriptionStatus::Active { remaining, .. }
+                if remaining == Duration::from_secs(1000)
+        ));
+
+        // Expires right now
+        assert!(statuses[2].is_expired());
+        assert_eq!(statuses[2].record().expiration_time, 2000);
+    }
+
+    #[test]
+    fn set_expiration() {
+        let mut record = subscription(0);
+
+        let expiration = UNIX_EPOCH + Duration::from_secs(1_750_000_000);
+        record.set_expiration(expiration).unwrap();
+        assert_eq!(record.expiration_time, 1_750_000_000);
+        assert_eq!(record.expiration(), expiration);
+
+        assert!(matches!(
+            record.set_expiration(UNIX_EPOCH - Duration::from_secs(1)),
+            Err(PreSwitchTicketV1Error::TimestampOutOfRange(_))
+        ));
+        assert!(matches!(
+            record.set_expiration(UNIX_EPOCH + Duration::from_secs(u32::MAX as u64 + 1)),
+            Err(PreSwitchTicketV1Error::TimestampOutOfRange(_))
+        ));
+    }
+}
@@ -7,6 +7,7 @@
 //! Implementation of a newtype wrapper around the title ID of a title.
 
 use byteorder::{BE, WriteBytesExt};
+use serde::{Serialize, Serializer};
 use std::fmt::{self, Display};
 use std::io;
 use std::io::Write;
@@ -24,6 +25,12 @@ impl TitleId {
         Self(title_id_value)
     }
 
+    /// Create a new [TitleId] of a Wii title of the given kind, the lower half is its four
+    /// character code (like `*b"HAGA"`).
+    pub fn new_wii(kind: WiiTitleKind, code: [u8; 4]) -> Self {
+        Self::new_with_halfs(kind.higher_half(), u32::from_be_bytes(code))
+    }
+
     /// Create a new [TitleId] given a lower and a higher halfs.
     pub fn new_with_halfs(higher_half: u32, lower_half: u32) -> Self {
         Self(((higher_half as u64) << 32) | lower_half as u64)
@@ -61,6 +68,25 @@ impl TitleId {
         *self = Self::new_with_halfs(higher_half, self.lower_half());
     }
 
+    /// Get the kind of Wii title given by the higher half of the ID.
+    pub fn wii_kind(&self) -> WiiTitleKind {
+        WiiTitleKind::new(self.higher_half())
+    }
+
+    /// Check the ID before installing a WAD with it on a Wii, injecting a title into the wrong
+    /// kind of ID is a common cause of unusable (or even harmful) installs. An empty list if the
+    /// ID is the one of a normal channel.
+    pub fn wii_install_warnings(&self) -> Vec<WiiTitleIdWarning> {
+        match self.wii_kind() {
+            WiiTitleKind::System => vec![WiiTitleIdWarning::SystemTitle],
+            WiiTitleKind::Disc => vec![WiiTitleIdWarning::DiscTitle],
+            WiiTitleKind::Channel | WiiTitleKind::Dlc | WiiTitleKind::DiscChannel => vec![],
+            WiiTitleKind::SystemChannel => vec![WiiTitleIdWarning::SystemChannel],
+            WiiTitleKind::HiddenChannel => vec![WiiTitleIdWarning::HiddenChannel],
+            WiiTitleKind::Unknown(higher_half) => vec![WiiTitleIdWarning::UnknownKind(higher_half)],
+        }
+    }
+
     /// Get a wrapper that can display the title ID with ASCII characters in its lower half, if the
     /// character is not visible a fallback to the normal display will be made.
     pub fn display_ascii(&self) -> TitleIdAsciiDisplay<'_> {
@@ -87,6 +113,112 @@ impl Display for TitleId {
     }
 }
 
+/// Title IDs are serialized like they are displayed (`00010001-48424346`).
+impl Serialize for TitleId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The kinds of titles of the Wii platform, given by the higher half of their title ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WiiTitleKind {
+    /// `00000001`: BOOT2, the System Menu and the IOSes.
+    System,
+
+    /// `00010000`: The titles of the game discs (only used for their saves).
+    Disc,
+
+    /// `00010001`: Channels downloaded from the Wii Shop Channel (and homebrew ones).
+    Channel,
+
+    /// `00010002`: Channels preinstalled on the console or from Nintendo (Mii Channel, etc).
+    SystemChannel,
+
+    /// `00010004`: Channels installed by a game disc (like the Wii Fit Channel).
+    DiscChannel,
+
+    /// `00010005`: Downloadable content of a game.
+    Dlc,
+
+    /// `00010008`: Titles not shown on the System Menu (like the EULA or the region select).
+    HiddenChannel,
+
+    /// Other higher half.
+    Unknown(u32),
+}
+
+impl WiiTitleKind {
+    /// Get the kind of title of the given higher half of a title ID.
+    pub const fn new(higher_half: u32) -> Self {
+        match higher_half {
+            0x00000001 => Self::System,
+            0x00010000 => Self::Disc,
+            0x00010001 => Self::Channel,
+            0x00010002 => Self::SystemChannel,
+            0x00010004 => Self::DiscChannel,
+            0x00010005 => Self::Dlc,
+            0x00010008 => Self::HiddenChannel,
+            higher_half => Self::Unknown(higher_half),
+        }
+    }
+
+    /// Get the higher half of the title IDs of this kind.
+    pub const fn higher_half(&self) -> u32 {
+        match self {
+            Self::System => 0x00000001,
+            Self::Disc => 0x00010000,
+            Self::Channel => 0x00010001,
+            Self::SystemChannel => 0x00010002,
+            Self::DiscChannel => 0x00010004,
+            Self::Dlc => 0x00010005,
+            Self::HiddenChannel => 0x00010008,
+            Self::Unknown(higher_half) => *higher_half,
+        }
+    }
+}
+
+/// Possible problems of installing a title with a given ID, see
+/// [TitleId::wii_install_warnings].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WiiTitleIdWarning {
+    /// A system title (BOOT2, System Menu or IOS), a bad install can brick the console.
+    SystemTitle,
+
+    /// The ID of a game disc, it cannot be launched as a channel.
+    DiscTitle,
+
+    /// A system channel, it may replace one of the channels of the console.
+    SystemChannel,
+
+    /// A hidden channel, it will not be shown on the System Menu.
+    HiddenChannel,
+
+    /// An unknown kind of title, the console may not be able to launch it.
+    UnknownKind(u32),
+}
+
+impl Display for WiiTitleIdWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SystemTitle => write!(f, "A system title, a bad install can brick the console"),
+            Self::DiscTitle => write!(f, "The title of a game disc, it cannot be launched"),
+            Self::SystemChannel => write!(
+                f,
+                "A system channel, it may replace a channel of the console"
+            ),
+            Self::HiddenChannel => write!(
+                f,
+                "A hidden channel, it will not be shown on the System Menu"
+            ),
+            Self::UnknownKind(higher_half) => write!(
+                f,
+                "Unknown kind of title ({higher_half:08X}), it may not be launchable"
+            ),
+        }
+    }
+}
+
 /// Wrapper that can display the title ID with ASCII characters in its lower half, if the
 /// character is not visible a fallback to the normal display will be made.
 pub struct TitleIdAsciiDisplay<'a>(&'a TitleId);
@@ -154,6 +286,27 @@ mod tests {
 
     const TEST_ID_NUMBER_NOT_VALID_ASCII: u64 = 5350613615614431505;
 
+    #[test]
+    fn wii_kind() {
+        let title_id = TitleId::new_wii(WiiTitleKind::HiddenChannel, *b"HAKE");
+
+        assert_eq!(title_id.inner(), 0x00010008_48414B45);
+        assert_eq!(title_id.wii_kind(), WiiTitleKind::HiddenChannel);
+        assert_eq!(
+            title_id.wii_install_warnings(),
+            [WiiTitleIdWarning::HiddenChannel]
+        );
+
+        let title_id = TitleId::new(0x00010001_4C554C5A);
+        assert_eq!(title_id.wii_kind(), WiiTitleKind::Channel);
+        assert!(title_id.wii_install_warnings().is_empty());
+
+        assert_eq!(
+            TitleId::new(0x00020000_00000000).wii_install_warnings(),
+            [WiiTitleIdWarning::UnknownKind(0x00020000)]
+        );
+    }
+
     #[test]
     fn default_display() {
         let title_id = TitleId::new(TEST_ID_NUMBER);
@@ -0,0 +1,174 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the `title.keys` text format used by the community to store the title keys
+//! of Nintendo Switch titles, with one `rights_id = title_key` pair (both in hexadecimal) per
+//! line.
+
+use std::io::{self, Read, Write};
+use thiserror::Error;
+
+/// Keystore of title keys indexed by their rights ID.
+#[derive(Debug, Default)]
+pub struct TitleKeys {
+    /// The entries stored inside the keystore.
+    pub entries: Vec<TitleKeysEntry>,
+}
+
+/// A title key associated to a rights ID.
+#[derive(Debug, Clone, Copy)]
+pub struct TitleKeysEntry {
+    /// The rights ID (title ID and master key revision) of the title.
+    pub rights_id: [u8; 16],
+
+    /// The title key, still encrypted with the titlekek.
+    pub title_key: [u8; 16],
+}
+
+impl TitleKeys {
+    /// Create a new [TitleKeys] by parsing a stream. Empty lines and the ones starting with `#`
+    /// are ignored.
+    pub fn new<T: Read>(mut stream: T) -> Result<Self, TitleKeysError> {
+        let mut text = String::new();
+        stream.read_to_string(&mut text)?;
+
+        let mut title_keys = Self::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let line_number = i + 1;
+
+            let (rights_id, title_key) = line
+                .split_once('=')
+                .ok_or(TitleKeysError::InvalidLine(line_number))?;
+
+            title_keys.insert(
+                util::hex::decode(rights_id.trim())
+                    .ok_or(TitleKeysError::InvalidHexValue(line_number))?,
+                util::hex::decode(title_key.trim())
+                    .ok_or(TitleKeysError::InvalidHexValue(line_number))?,
+            );
+        }
+
+        Ok(title_keys)
+    }
+
+    /// Dump the keystore into a stream.
+    pub fn dump<T: Write>(&self, mut stream: T) -> io::Result<()> {
+        for entry in &self.entries {
+            writeln!(
+                stream,
+                "{} = {}",
+                util::hex::encode(&entry.rights_id),
+                util::hex::encode(&entry.title_key)
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the title key associated with a rights ID.
+    pub fn title_key(&self, rights_id: &[u8; 16]) -> Option<&[u8; 16]> {
+        self.entries
+            .iter()
+            .find(|entry| entry.rights_id == *rights_id)
+            .map(|entry| &entry.title_key)
+    }
+
+    /// Add a title key to the keystore, replacing the previous one with the same rights ID if
+    /// present.
+    pub fn insert(&mut self, rights_id: [u8; 16], title_key: [u8; 16]) {
+        match self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.rights_id == rights_id)
+        {
+            Some(entry) => entry.title_key = title_key,
+
+            None => self.entries.push(TitleKeysEntry {
+                rights_id,
+                title_key,
+            }),
+        }
+    }
+
+    /// Add all the title keys of another keystore, the ones of the given keystore have
+    /// precedence.
+    pub fn extend(&mut self, other: Self) {
+        for entry in other.entries {
+            self.insert(entry.rights_id, entry.title_key);
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum TitleKeysError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("Line {0} is not a `rights_id = title_key` pair")]
+    InvalidLine(usize),
+
+    #[error("Line {0} has a value that is not a 16 bytes hexadecimal number")]
+    InvalidHexValue(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_TITLE_KEYS: &str = "# Comment\n\
+        0100000000010000000000000000000a = 00112233445566778899AABBCCDDEEFF\n\
+        \n\
+        0100000000010000000000000000000a=ffeeddccbbaa99887766554433221100\n\
+        01000000000200000000000000000000 = 000102030405060708090a0b0c0d0e0f\n";
+
+    #[test]
+    fn parse_and_dump() {
+        let title_keys = TitleKeys::new(TEST_TITLE_KEYS.as_bytes()).unwrap();
+
+        assert_eq!(title_keys.entries.len(), 2);
+
+        let mut rights_id = [0; 16];
+        rights_id[0] = 0x01;
+        rights_id[5] = 0x01;
+        rights_id[15] = 0x0A;
+
+        assert_eq!(
+            title_keys.title_key(&rights_id).unwrap()[0],
+            0xFF,
+            "Later entries must replace the previous ones"
+        );
+
+        let mut buffer = vec![];
+        title_keys.dump(&mut buffer).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "0100000000010000000000000000000a = ffeeddccbbaa99887766554433221100\n\
+             01000000000200000000000000000000 = 000102030405060708090a0b0c0d0e0f\n"
+        );
+    }
+
+    #[test]
+    fn parse_invalid_line() {
+        assert!(matches!(
+            TitleKeys::new("0100 = 00\n".as_bytes()),
+            Err(TitleKeysError::InvalidHexValue(1))
+        ));
+
+        assert!(matches!(
+            TitleKeys::new("\nnot a pair\n".as_bytes()),
+            Err(TitleKeysError::InvalidLine(2))
+        ));
+    }
+}
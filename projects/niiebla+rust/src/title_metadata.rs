@@ -6,20 +6,35 @@
 
 //! Implementation of the binary file format used by Nintendo to store title metadata.
 
+use crate::parse_limits::{ParseLimitError, ParseLimits};
 use crate::signed_blob_header::{SignedBlobHeader, SignedBlobHeaderError};
+use crate::telemetry;
 use crate::title_id::TitleId;
+use crate::title_version::TitleVersion;
 use byteorder::{BE, LE, ReadBytesExt, WriteBytesExt};
+use std::collections::HashSet;
+use std::fmt::{self, Display};
 use std::io;
+use std::io::Cursor;
 use std::io::Read;
 use std::io::Seek;
 use std::io::Write;
 use std::string::FromUtf8Error;
 use thiserror::Error;
-use util::{ReadEx, WriteEx};
+use util::{ParseContext, ReadEx, WriteEx};
 
 pub mod content_selector;
+pub mod dependency_graph;
+pub mod ipc_mask;
+pub mod ratings;
+pub mod the_3ds_extras;
 
 use content_selector::{ContentSelector, ContentSelectorMethod};
+use ipc_mask::TitleMetadataIpcMask;
+use ratings::TitleMetadataRatings;
+use the_3ds_extras::The3dsTmdExtras;
+
+const CONTEXT: ParseContext = ParseContext::new("TMD");
 
 /// Manifest data regard the title itself, its structure and allowed system access (Also known as
 /// `TMD` data).
@@ -82,6 +97,10 @@ pub struct TitleMetadata {
     /// The version of the title.
     pub title_version: u16,
 
+    /// The minor version of the title, never used by the system. Changed to brute force the hash
+    /// of the title metadata when fakesigning.
+    pub title_minor_version: u16,
+
     /// The index value of the content entry where the boot data is located.
     pub boot_content_index: u16,
 
@@ -97,49 +116,87 @@ pub struct TitleMetadata {
 
 impl TitleMetadata {
     /// Create a new installable Wad representation.
-    pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, TitleMetadataError> {
+    pub fn new<T: Read + Seek>(stream: T) -> Result<Self, TitleMetadataError> {
+        Self::new_with_limits(stream, &ParseLimits::UNLIMITED)
+    }
+
+    /// Like [Self::new] but fails if the title metadata exceeds the given [ParseLimits], useful
+    /// to parse untrusted files.
+    pub fn new_with_limits<T: Read + Seek>(
+        mut stream: T,
+        limits: &ParseLimits,
+    ) -> Result<Self, TitleMetadataError> {
         let signed_blob_header = SignedBlobHeader::new(&mut stream)?;
 
-        let format_version = stream.read_u8()?;
+        let format_version =
+            CONTEXT.field(&mut stream, "format_version", |stream| stream.read_u8())?;
+
+        let certificate_authority_certificate_revocation_list_version = CONTEXT.field(
+            &mut stream,
+            "certificate_authority_certificate_revocation_list_version",
+            |stream| stream.read_u8(),
+        )?;
 
-        let certificate_authority_certificate_revocation_list_version = stream.read_u8()?;
-        let signer_certificate_revocation_list_version = stream.read_u8()?;
+        let signer_certificate_revocation_list_version = CONTEXT.field(
+            &mut stream,
+            "signer_certificate_revocation_list_version",
+            |stream| stream.read_u8(),
+        )?;
 
         // On some platforms this byte has a meaning as a bool
-        let first_reserved_byte = stream.read_bool()?;
+        let first_reserved_byte =
+            CONTEXT.field(&mut stream, "is_wii_u_vwii_only_title", |stream| {
+                stream.read_bool()
+            })?;
+
+        let system_runtime_title_id =
+            CONTEXT.field(&mut stream, "system_runtime_title_id", |stream| {
+                stream.read_u64::<BE>()
+            })?;
 
-        let system_runtime_title_id = match stream.read_u64::<BE>()? {
+        let system_runtime_title_id = match system_runtime_title_id {
             0 => None,
             title_id => Some(TitleId::new(title_id)),
         };
 
-        let title_id = TitleId::new(stream.read_u64::<BE>()?);
+        let title_id =
+            TitleId::new(CONTEXT.field(&mut stream, "title_id", |stream| stream.read_u64::<BE>())?);
 
-        let mut platform_data =
-            TitleMetadataPlatformData::new_dummy_from_identifier(stream.read_u32::<BE>()?)?;
+        let mut platform_data = TitleMetadataPlatformData::new_dummy_from_identifier(
+            CONTEXT.field(&mut stream, "platform", |stream| stream.read_u32::<BE>())?,
+        )?;
 
-        let group_id = stream.read_u16::<BE>()?;
+        let group_id = CONTEXT.field(&mut stream, "group_id", |stream| stream.read_u16::<BE>())?;
 
         match platform_data {
             TitleMetadataPlatformData::DSi | TitleMetadataPlatformData::WiiU => {
                 stream.seek_relative(62)?;
             }
 
-            TitleMetadataPlatformData::Console3ds {
-                ref mut public_save_data_size,
-                ref mut private_save_data_size,
-                ref mut srl_flag,
-            } => {
-                *public_save_data_size = stream.read_u32::<LE>()?;
-                *private_save_data_size = stream.read_u32::<LE>()?;
+            TitleMetadataPlatformData::Console3ds(ref mut extras) => {
+                let public_save_data_size =
+                    CONTEXT.field(&mut stream, "public_save_data_size", |stream| {
+                        stream.read_u32::<LE>()
+                    })?;
+
+                let private_save_data_size =
+                    CONTEXT.field(&mut stream, "private_save_data_size", |stream| {
+                        stream.read_u32::<LE>()
+                    })?;
 
                 // Skip four unknown bytes
                 stream.seek_relative(4)?;
 
-                *srl_flag = stream.read_u8()?;
+                let srl_flag = CONTEXT.field(&mut stream, "srl_flag", |stream| stream.read_u8())?;
 
                 // Skip 49 unknown bytes
                 stream.seek_relative(49)?;
+
+                *extras = The3dsTmdExtras::new_unchecked(
+                    public_save_data_size,
+                    private_save_data_size,
+                    srl_flag,
+                );
             }
 
             TitleMetadataPlatformData::Wii {
@@ -153,28 +210,53 @@ impl TitleMetadata {
                 // Skip 2 zeroed bytes
                 stream.seek_relative(2)?;
 
-                *region =
-                    TitleMetadataPlatformDataWiiRegion::from_identifier(stream.read_u16::<BE>()?)?;
+                *region = TitleMetadataPlatformDataWiiRegion::from_identifier(CONTEXT.field(
+                    &mut stream,
+                    "region",
+                    |stream| stream.read_u16::<BE>(),
+                )?)?;
 
-                *ratings = util::read_exact!(stream, 16)?;
+                *ratings =
+                    TitleMetadataRatings::new(CONTEXT.field(&mut stream, "ratings", |stream| {
+                        util::read_exact!(stream, TitleMetadataRatings::SIZE)
+                    })?);
 
                 // Skip 12 reserved bytes
                 stream.seek_relative(12)?;
 
-                *ipc_mask = util::read_exact!(stream, 12)?;
+                *ipc_mask = TitleMetadataIpcMask::new(CONTEXT.field(
+                    &mut stream,
+                    "ipc_mask",
+                    |stream| util::read_exact!(stream, TitleMetadataIpcMask::SIZE),
+                )?);
 
                 // Skip 18 reserved bytes
                 stream.seek_relative(18)?;
             }
         }
 
-        let access_rights = stream.read_u32::<BE>()?;
-        let title_version = stream.read_u16::<BE>()?;
-        let number_of_content_entries = stream.read_u16::<BE>()?;
-        let boot_content_index = stream.read_u16::<BE>()?;
+        let access_rights = CONTEXT.field(&mut stream, "access_rights", |stream| {
+            stream.read_u32::<BE>()
+        })?;
 
-        // Skip the title minor version as it was never used
-        stream.seek_relative(2)?;
+        let title_version = CONTEXT.field(&mut stream, "title_version", |stream| {
+            stream.read_u16::<BE>()
+        })?;
+
+        let number_of_content_entries =
+            CONTEXT.field(&mut stream, "number_of_content_entries", |stream| {
+                stream.read_u16::<BE>()
+            })?;
+
+        limits.check_contents(number_of_content_entries.into())?;
+
+        let boot_content_index = CONTEXT.field(&mut stream, "boot_content_index", |stream| {
+            stream.read_u16::<BE>()
+        })?;
+
+        let title_minor_version = CONTEXT.field(&mut stream, "title_minor_version", |stream| {
+            stream.read_u16::<BE>()
+        })?;
 
         let version_1_extension = match format_version {
             0 => None,
@@ -191,6 +273,8 @@ impl TitleMetadata {
             )?);
         }
 
+        telemetry::file_parsed("title_metadata");
+
         Ok(Self {
             signed_blob_header,
             certificate_authority_certificate_revocation_list_version,
@@ -200,6 +284,7 @@ impl TitleMetadata {
             platform_data,
             group_id,
             title_version,
+            title_minor_version,
             boot_content_index,
             access_rights,
             version_1_extension,
@@ -218,11 +303,7 @@ impl TitleMetadata {
         stream.write_u8(match self.platform_data {
             TitleMetadataPlatformData::DSi
             | TitleMetadataPlatformData::WiiU
-            | TitleMetadataPlatformData::Console3ds {
-                public_save_data_size: _,
-                private_save_data_size: _,
-                srl_flag: _,
-            } => 0,
+            | TitleMetadataPlatformData::Console3ds(_) => 0,
             TitleMetadataPlatformData::Wii {
                 is_wii_u_vwii_only_title,
                 region: _,
@@ -251,18 +332,14 @@ impl TitleMetadata {
                 stream.write_zeroed(62)?;
             }
 
-            TitleMetadataPlatformData::Console3ds {
-                public_save_data_size,
-                private_save_data_size,
-                srl_flag,
-            } => {
-                stream.write_u32::<LE>(*public_save_data_size)?;
-                stream.write_u32::<LE>(*private_save_data_size)?;
+            TitleMetadataPlatformData::Console3ds(extras) => {
+                stream.write_u32::<LE>(extras.public_save_data_size())?;
+                stream.write_u32::<LE>(extras.private_save_data_size())?;
 
                 // Skip four unknown bytes
                 stream.write_zeroed(4)?;
 
-                stream.write_u8(*srl_flag)?;
+                stream.write_u8(extras.srl_flag())?;
 
                 // Skip 49 unknown bytes
                 stream.write_zeroed(49)?;
@@ -278,9 +355,9 @@ impl TitleMetadata {
 
                 region.dump_identifier(&mut stream)?;
 
-                stream.write_all(ratings)?;
+                stream.write_all(ratings.bytes())?;
                 stream.write_zeroed(12)?;
-                stream.write_all(ipc_mask)?;
+                stream.write_all(ipc_mask.bytes())?;
                 stream.write_zeroed(18)?;
             }
         }
@@ -290,8 +367,7 @@ impl TitleMetadata {
         stream.write_u16::<BE>(self.content_chunk_entries.len() as u16)?;
         stream.write_u16::<BE>(self.boot_content_index)?;
 
-        // Skip the title minor version as it was never used
-        stream.seek_relative(2)?;
+        stream.write_u16::<BE>(self.title_minor_version)?;
 
         if let Some(version_1_extension) = &self.version_1_extension {
             version_1_extension.dump(&mut stream)?;
@@ -353,6 +429,60 @@ impl TitleMetadata {
         size
     }
 
+    /// Fakesign the title metadata exploiting the
+    /// [Trucha bug](https://wiibrew.org/wiki/Signing_bug), its signature is zeroed and the title
+    /// minor version is changed. Only useful on the Wii (and Wii U vWii) platform, the console
+    /// must be running an IOS patched to not check the signatures properly.
+    pub fn fakesign(&mut self) -> Result<(), TitleMetadataError> {
+        // Offset of the title minor version starting from the issuer
+        const TITLE_MINOR_VERSION_OFFSET: usize = 162;
+
+        self.signed_blob_header.zero_signature();
+
+        let mut buffer = Cursor::new(vec![]);
+        self.dump(&mut buffer)?;
+
+        let filler_offset =
+            self.signed_blob_header.size() as usize - 64 + TITLE_MINOR_VERSION_OFFSET;
+
+        self.title_minor_version = self
+            .signed_blob_header
+            .brute_force_trucha(buffer.get_mut(), filler_offset)
+            .ok_or(TitleMetadataError::FakesignFailed)?;
+
+        Ok(())
+    }
+
+    /// Like [Self::new] but fails if two contents share the same ID or index, see
+    /// [Self::check_duplicated_contents].
+    pub fn new_strict<T: Read + Seek>(stream: T) -> Result<Self, TitleMetadataError> {
+        let title_metadata = Self::new(stream)?;
+        title_metadata.check_duplicated_contents()?;
+
+        Ok(title_metadata)
+    }
+
+    /// Check that no two contents share the same ID or index, the `select_with_id` and
+    /// `select_with_index` methods silently select the first match on such titles.
+    pub fn check_duplicated_contents(&self) -> Result<(), TitleMetadataError> {
+        let mut ids = HashSet::new();
+        let mut indexes = HashSet::new();
+
+        for content_entry in &self.content_chunk_entries {
+            if !ids.insert(content_entry.id) {
+                return Err(TitleMetadataError::DuplicatedContentId(content_entry.id));
+            }
+
+            if !indexes.insert(content_entry.index) {
+                return Err(TitleMetadataError::DuplicatedContentIndex(
+                    content_entry.index,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Select the content with the given physical position.
     pub fn select_with_physical_position(&self, position: usize) -> ContentSelector {
         ContentSelector {
@@ -374,6 +504,53 @@ impl TitleMetadata {
         }
     }
 
+    /// Select all the contents with the given ID (by their physical position), useful on titles
+    /// with duplicated IDs.
+    pub fn select_all_with_id(&self, id: u32) -> Vec<ContentSelector> {
+        self.select_all_matching(|content_entry| content_entry.id == id)
+    }
+
+    /// Select all the contents with the given index (by their physical position), useful on
+    /// titles with duplicated indexes.
+    pub fn select_all_with_index(&self, index: u16) -> Vec<ContentSelector> {
+        self.select_all_matching(|content_entry| content_entry.index == index)
+    }
+
+    fn select_all_matching<F: Fn(&TitleMetadataContentEntry) -> bool>(
+        &self,
+        predicate: F,
+    ) -> Vec<ContentSelector> {
+        self.content_chunk_entries
+            .iter()
+            .enumerate()
+            .filter(|(_, content_entry)| predicate(content_entry))
+            .map(|(position, _)| self.select_with_physical_position(position))
+            .collect()
+    }
+
+    /// Select the first content with the given hash (SHA-1 or SHA-256), useful to address
+    /// contents when their IDs or indexes collide across titles.
+    ///
+    /// A SHA-1 hash also matches the zero padded SHA-1 hashes stored on Wii U titles.
+    pub fn select_with_hash(&self, hash: &[u8]) -> ContentSelector {
+        let mut stored_hash = [0; 32];
+
+        // Longer hashes will never match any content
+        let len = if hash.len() <= stored_hash.len() {
+            stored_hash[..hash.len()].copy_from_slice(hash);
+            hash.len()
+        } else {
+            0
+        };
+
+        ContentSelector {
+            method: ContentSelectorMethod::WithHash {
+                hash: stored_hash,
+                len,
+            },
+        }
+    }
+
     /// Select the first content stored inside the title (given its physicial position).
     pub fn select_first(&self) -> ContentSelector {
         self.select_with_physical_position(0)
@@ -386,6 +563,199 @@ impl TitleMetadata {
             method: ContentSelectorMethod::Last,
         }
     }
+
+    /// Get the version of the title.
+    pub fn version(&self) -> TitleVersion {
+        TitleVersion::new(self.title_version)
+    }
+
+    /// Get the sum of the sizes of all the contents of the title in bytes.
+    pub fn contents_size(&self) -> u64 {
+        self.content_chunk_entries
+            .iter()
+            .map(|content_entry| content_entry.size)
+            .sum()
+    }
+
+    /// Get the space used by the title once installed on the NAND of a Wii, in bytes and in the
+    /// "blocks" shown by the System Menu (128 KiB units).
+    ///
+    /// Each content is rounded up to a whole block, the title metadata itself (stored on the title
+    /// directory as `title.tmd`) is also counted. Shared contents are counted too, even if the
+    /// console may already have them installed.
+    pub fn install_size(&self) -> TitleInstallSize {
+        let title_metadata_size = self.size() as u64;
+
+        let blocks = self
+            .content_chunk_entries
+            .iter()
+            .map(|content_entry| content_entry.size.div_ceil(TitleInstallSize::BLOCK_SIZE))
+            .sum::<u64>()
+            + title_metadata_size.div_ceil(TitleInstallSize::BLOCK_SIZE);
+
+        TitleInstallSize {
+            bytes: self.contents_size() + title_metadata_size,
+            blocks,
+        }
+    }
+
+    /// Get the totals of the contents per kind, their aggregated sizes and the hash algorithm
+    /// used, everything a catalog needs in a single call.
+    pub fn summary(&self) -> TitleMetadataSummary {
+        let mut summary = TitleMetadataSummary {
+            normal: ContentKindTotals::default(),
+            dlc: ContentKindTotals::default(),
+            shared: ContentKindTotals::default(),
+            contents_size: self.contents_size(),
+            largest_content_size: 0,
+            install_size: self.install_size(),
+            hash_algorithm: if self.version_1_extension.is_some() {
+                ContentHashAlgorithm::Sha256
+            } else {
+                ContentHashAlgorithm::Sha1
+            },
+        };
+
+        for content_entry in &self.content_chunk_entries {
+            let totals = match content_entry.kind {
+                TitleMetadataContentEntryKind::Normal
+                | TitleMetadataContentEntryKind::NormalWiiUKind1
+                | TitleMetadataContentEntryKind::NormalWiiUKind2
+                | TitleMetadataContentEntryKind::NormalWiiUKind3 => &mut summary.normal,
+
+                TitleMetadataContentEntryKind::Dlc => &mut summary.dlc,
+                TitleMetadataContentEntryKind::Shared => &mut summary.shared,
+            };
+
+            totals.count += 1;
+            totals.size += content_entry.size;
+
+            summary.largest_content_size = summary.largest_content_size.max(content_entry.size);
+        }
+
+        summary
+    }
+}
+
+/// Aggregated data of the contents of a title, see [TitleMetadata::summary].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TitleMetadataSummary {
+    /// The normal contents, including the different kinds of normal contents of the Wii U.
+    pub normal: ContentKindTotals,
+
+    /// The downloadable contents.
+    pub dlc: ContentKindTotals,
+
+    /// The contents shared between titles.
+    pub shared: ContentKindTotals,
+
+    /// The sum of the sizes of all the contents in bytes, see [TitleMetadata::contents_size].
+    pub contents_size: u64,
+
+    /// The size of the biggest content in bytes, zero if there are no contents.
+    pub largest_content_size: u64,
+
+    /// The space used by the title once installed, see [TitleMetadata::install_size].
+    pub install_size: TitleInstallSize,
+
+    /// The algorithm used to hash the contents.
+    pub hash_algorithm: ContentHashAlgorithm,
+}
+
+/// Number of contents of a kind and the sum of their sizes, see [TitleMetadataSummary].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContentKindTotals {
+    /// The number of contents.
+    pub count: usize,
+
+    /// The sum of the sizes of the contents in bytes.
+    pub size: u64,
+}
+
+/// The algorithms used to hash the (decrypted) contents of a title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentHashAlgorithm {
+    /// SHA-1, used by the title metadata without the version 1 extension.
+    Sha1,
+
+    /// SHA-256, used by the title metadata with the version 1 extension. Wii U titles store
+    /// SHA-1 hashes padded with zeroes instead.
+    Sha256,
+}
+
+/// The space used by a title once installed, see [TitleMetadata::install_size].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TitleInstallSize {
+    /// The size of the installed files in bytes.
+    pub bytes: u64,
+
+    /// The number of blocks used, as displayed by the System Menu.
+    pub blocks: u64,
+}
+
+impl TitleInstallSize {
+    /// The size of a block of the Wii NAND as displayed by the System Menu, 128 KiB.
+    pub const BLOCK_SIZE: u64 = 128 * 1024;
+}
+
+/// Wrapper that displays a size in bytes with binary units and a single decimal.
+struct DisplaySize(u64);
+
+impl Display for DisplaySize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = UNITS[0];
+
+        for next_unit in &UNITS[1..] {
+            if size < 1024.0 {
+                break;
+            }
+
+            size /= 1024.0;
+            unit = next_unit;
+        }
+
+        write!(f, "{size:.1} {unit}")
+    }
+}
+
+/// A compact one-line summary of the title, like `[Wii] 00010001-HAGA v1.0 6 contents, 12.3 MiB`.
+impl Display for TitleMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let platform = match self.platform_data {
+            TitleMetadataPlatformData::DSi => "DSi",
+            TitleMetadataPlatformData::Wii {
+                is_wii_u_vwii_only_title: true,
+                ..
+            } => "vWii",
+            TitleMetadataPlatformData::Wii { .. } => "Wii",
+            TitleMetadataPlatformData::Console3ds(_) => "3DS",
+            TitleMetadataPlatformData::WiiU => "Wii U",
+        };
+
+        write!(f, "[{platform}] {} ", self.title_id.display_ascii())?;
+
+        if let TitleMetadataPlatformData::Console3ds(_) = self.platform_data {
+            write!(f, "{}", self.version().display_3ds())?;
+        } else {
+            write!(f, "{}", self.version())?;
+        }
+
+        let number_of_contents = self.content_chunk_entries.len();
+        let plural = if number_of_contents == 1 { "" } else { "s" };
+
+        write!(
+            f,
+            " {number_of_contents} content{plural}, {}",
+            DisplaySize(self.contents_size())
+        )
+    }
 }
 
 #[derive(Error, Debug)]
@@ -421,8 +791,23 @@ pub enum TitleMetadataError {
     #[error("The version of the title metadata is not compatible (version: {0})")]
     IncompatibleVersion(u8),
 
+    #[error("Unable to find a title minor version that fakesigns the title metadata")]
+    FakesignFailed,
+
     #[error("Content not found")]
     ContentNotFound(),
+
+    #[error("More than one content has the ID: {0}")]
+    DuplicatedContentId(u32),
+
+    #[error("More than one content has the index: {0}")]
+    DuplicatedContentIndex(u16),
+
+    #[error("The save data size is not aligned to the media unit size: {0}")]
+    UnalignedSaveDataSize(u32),
+
+    #[error("A parse limit has been exceeded: {0}")]
+    ParseLimitError(#[from] ParseLimitError),
 }
 
 #[derive(Debug)]
@@ -442,27 +827,15 @@ pub enum TitleMetadataPlatformData {
         /// The region of the title
         region: TitleMetadataPlatformDataWiiRegion,
 
-        /// The "ratings" of the title.
-        // TODO(DISCOVER)
-        ratings: [u8; 16],
+        /// The age ratings of the title.
+        ratings: TitleMetadataRatings,
 
         /// The IPC mask of the title.
-        // TODO(DISCOVER)
-        ipc_mask: [u8; 12],
+        ipc_mask: TitleMetadataIpcMask,
     },
 
     /// The title is for the Nintendo 3DS
-    Console3ds {
-        /// The size of the public save data section.
-        public_save_data_size: u32,
-
-        /// The size of the private save data section.
-        private_save_data_size: u32,
-
-        /// The SRL flags of the title.
-        // TODO(DISCOVER)
-        srl_flag: u8,
-    },
+    Console3ds(The3dsTmdExtras),
 
     /// The title is for the Nintendo Wii U
     WiiU,
@@ -475,14 +848,10 @@ impl TitleMetadataPlatformData {
             1 => Ok(Self::Wii {
                 is_wii_u_vwii_only_title: false,
                 region: TitleMetadataPlatformDataWiiRegion::RegionFree,
-                ratings: [0; 16],
-                ipc_mask: [0; 12],
-            }),
-            64 => Ok(Self::Console3ds {
-                public_save_data_size: 0,
-                private_save_data_size: 0,
-                srl_flag: 0,
+                ratings: TitleMetadataRatings::default(),
+                ipc_mask: TitleMetadataIpcMask::default(),
             }),
+            64 => Ok(Self::Console3ds(The3dsTmdExtras::default())),
 
             256 => Ok(Self::WiiU),
             identifier => Err(TitleMetadataError::UnknownPlatform(identifier)),
@@ -500,11 +869,7 @@ impl TitleMetadataPlatformData {
                 ipc_mask: _,
             } => 1,
 
-            Self::Console3ds {
-                public_save_data_size: _,
-                private_save_data_size: _,
-                srl_flag: _,
-            } => 64,
+            Self::Console3ds(_) => 64,
 
             Self::WiiU => 256,
         })?;
@@ -579,6 +944,31 @@ pub enum TitleMetadataContentEntryHashKind {
     Version1([u8; 32]),
 }
 
+impl TitleMetadataContentEntryHashKind {
+    /// Get the raw bytes of the hash.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Version0(hash) => hash,
+            Self::Version1(hash) => hash,
+        }
+    }
+
+    /// Check if the hash is equal to the given one, a SHA-1 hash also matches its zero padded
+    /// version stored on Wii U titles.
+    pub fn matches(&self, hash: &[u8]) -> bool {
+        let bytes = self.as_bytes();
+
+        if bytes == hash {
+            return true;
+        }
+
+        hash.len() == 20
+            && bytes.len() == 32
+            && bytes[..20] == *hash
+            && bytes[20..].iter().all(|&byte| byte == 0)
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 /// The kind (behaviour of the content inside the system) of the content.
 pub enum TitleMetadataContentEntryKind {
@@ -604,10 +994,17 @@ pub enum TitleMetadataContentEntryKind {
 
 impl TitleMetadataContentEntry {
     fn new<T: Read + Seek>(mut stream: T, version_1: bool) -> Result<Self, TitleMetadataError> {
-        let id = stream.read_u32::<BE>()?;
-        let index = stream.read_u16::<BE>()?;
+        let id = CONTEXT.field(&mut stream, "content_entry.id", |stream| {
+            stream.read_u32::<BE>()
+        })?;
 
-        let kind = match stream.read_u16::<BE>()? {
+        let index = CONTEXT.field(&mut stream, "content_entry.index", |stream| {
+            stream.read_u16::<BE>()
+        })?;
+
+        let kind = match CONTEXT.field(&mut stream, "content_entry.kind", |stream| {
+            stream.read_u16::<BE>()
+        })? {
             0x0001 => TitleMetadataContentEntryKind::Normal,
             0x2001 => TitleMetadataContentEntryKind::NormalWiiUKind1,
             0x2003 => TitleMetadataContentEntryKind::NormalWiiUKind2,
@@ -618,12 +1015,17 @@ impl TitleMetadataContentEntry {
             identifier => return Err(TitleMetadataError::UnknownContentEntryKind(identifier)),
         };
 
-        let size = stream.read_u64::<BE>()?;
-        let hash = if version_1 {
-            TitleMetadataContentEntryHashKind::Version1(util::read_exact!(stream, 32)?)
-        } else {
-            TitleMetadataContentEntryHashKind::Version0(util::read_exact!(stream, 20)?)
-        };
+        let size = CONTEXT.field(&mut stream, "content_entry.size", |stream| {
+            stream.read_u64::<BE>()
+        })?;
+
+        let hash = CONTEXT.field(&mut stream, "content_entry.hash", |stream| {
+            Ok(if version_1 {
+                TitleMetadataContentEntryHashKind::Version1(util::read_exact!(stream, 32)?)
+            } else {
+                TitleMetadataContentEntryHashKind::Version0(util::read_exact!(stream, 20)?)
+            })
+        })?;
 
         Ok(Self {
             id,
@@ -670,7 +1072,12 @@ pub struct TitleMetadataV1 {
 
 impl TitleMetadataV1 {
     fn new<T: Read + Seek>(mut stream: T) -> Result<Self, TitleMetadataError> {
-        let content_entries_groups_hash_sha256 = util::read_exact!(stream, 32)?;
+        let content_entries_groups_hash_sha256 = CONTEXT.field(
+            &mut stream,
+            "content_entries_groups_hash_sha256",
+            |stream| util::read_exact!(stream, 32),
+        )?;
+
         let mut content_entries_groups = [TitleMetadataV1ContentEntriesGroup::new_dummy(); 64];
 
         for group in &mut content_entries_groups {
@@ -717,10 +1124,23 @@ impl TitleMetadataV1ContentEntriesGroup {
     }
 
     fn new<T: Read + Seek>(mut stream: T) -> Result<Self, TitleMetadataError> {
-        let first_content_index = stream.read_u16::<BE>()?;
-        let content_entries_in_the_group = stream.read_u16::<BE>()?;
-
-        let content_entries_group_hash_sha256 = util::read_exact!(stream, 32)?;
+        let first_content_index = CONTEXT.field(
+            &mut stream,
+            "content_entries_group.first_content_index",
+            |stream| stream.read_u16::<BE>(),
+        )?;
+
+        let content_entries_in_the_group = CONTEXT.field(
+            &mut stream,
+            "content_entries_group.content_entries_in_the_group",
+            |stream| stream.read_u16::<BE>(),
+        )?;
+
+        let content_entries_group_hash_sha256 = CONTEXT.field(
+            &mut stream,
+            "content_entries_group.content_entries_group_hash_sha256",
+            |stream| util::read_exact!(stream, 32),
+        )?;
 
         Ok(Self {
             first_content_index,
@@ -737,3 +1157,110 @@ impl TitleMetadataV1ContentEntriesGroup {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::homebrew_title_metadata;
+
+    #[test]
+    fn display_summary() {
+        let mut title_metadata = homebrew_title_metadata(0x00010001_48414741);
+        title_metadata.title_version = 256;
+
+        for (id, size) in [(0, 12 * 1024 * 1024), (1, 300 * 1024)] {
+            title_metadata
+                .content_chunk_entries
+                .push(TitleMetadataContentEntry {
+                    id,
+                    index: id as u16,
+                    kind: TitleMetadataContentEntryKind::Normal,
+                    size,
+                    hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+                });
+        }
+
+        assert_eq!(
+            "[Wii] 00010001-HAGA v1.0 2 contents, 12.3 MiB",
+            format!("{title_metadata}")
+        );
+
+        title_metadata.content_chunk_entries.truncate(0);
+        title_metadata.platform_data = TitleMetadataPlatformData::Console3ds(Default::default());
+        title_metadata.title_version = (11 << 10) | (17 << 4);
+
+        assert_eq!(
+            "[3DS] 00010001-HAGA v11.17.0 0 contents, 0 B",
+            format!("{title_metadata}")
+        );
+    }
+
+    #[test]
+    fn install_size() {
+        let mut title_metadata = homebrew_title_metadata(0x00010001_48414741);
+
+        for (id, size) in [(0, 12 * 1024 * 1024), (1, 300 * 1024)] {
+            title_metadata
+                .content_chunk_entries
+                .push(TitleMetadataContentEntry {
+                    id,
+                    index: id as u16,
+                    kind: TitleMetadataContentEntryKind::Normal,
+                    size,
+                    hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+                });
+        }
+
+        let install_size = title_metadata.install_size();
+
+        // 96 blocks of the first content, 3 of the second one and 1 of the title metadata
+        assert_eq!(install_size.blocks, 96 + 3 + 1);
+        assert_eq!(
+            install_size.bytes,
+            (12 * 1024 + 300) * 1024 + title_metadata.size() as u64
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let mut title_metadata = homebrew_title_metadata(0x00010001_48414741);
+
+        for (id, kind, size) in [
+            (0, TitleMetadataContentEntryKind::Normal, 0x40),
+            (1, TitleMetadataContentEntryKind::Shared, 0x100),
+            (2, TitleMetadataContentEntryKind::Normal, 0x20),
+        ] {
+            title_metadata
+                .content_chunk_entries
+                .push(TitleMetadataContentEntry {
+                    id,
+                    index: id as u16,
+                    kind,
+                    size,
+                    hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+                });
+        }
+
+        let summary = title_metadata.summary();
+
+        assert_eq!(
+            summary.normal,
+            ContentKindTotals {
+                count: 2,
+                size: 0x60
+            }
+        );
+        assert_eq!(
+            summary.shared,
+            ContentKindTotals {
+                count: 1,
+                size: 0x100
+            }
+        );
+        assert_eq!(summary.dlc, ContentKindTotals::default());
+        assert_eq!(summary.contents_size, 0x160);
+        assert_eq!(summary.largest_content_size, 0x100);
+        assert_eq!(summary.install_size, title_metadata.install_size());
+        assert_eq!(summary.hash_algorithm, ContentHashAlgorithm::Sha1);
+    }
+}
@@ -22,16 +22,23 @@ pub(super) enum ContentSelectorMethod {
     WithPhysicalPosition(usize),
     WithIndex(u16),
     WithId(u32),
+    // Stored inline to keep the selector `Copy`, hashes are at most 32 bytes long
+    WithHash { hash: [u8; 32], len: usize },
     Last,
 }
 
 impl ContentSelector {
-    fn get_last(title_metadata: &TitleMetadata) -> Self {
-        Self {
-            method: ContentSelectorMethod::WithPhysicalPosition(
-                title_metadata.content_chunk_entries.len() - 1,
-            ),
-        }
+    fn get_last(title_metadata: &TitleMetadata) -> Result<Self, TitleMetadataError> {
+        // Title metadata without contents has no last content
+        let last_position = title_metadata
+            .content_chunk_entries
+            .len()
+            .checked_sub(1)
+            .ok_or_else(TitleMetadataError::ContentNotFound)?;
+
+        Ok(Self {
+            method: ContentSelectorMethod::WithPhysicalPosition(last_position),
+        })
     }
 
     /// Get the selected content entry.
@@ -40,12 +47,12 @@ impl ContentSelector {
         title_metadata: &TitleMetadata,
     ) -> Result<TitleMetadataContentEntry, TitleMetadataError> {
         if let ContentSelectorMethod::Last = self.method {
-            return Self::get_last(title_metadata).content_entry(title_metadata);
+            return Self::get_last(title_metadata)?.content_entry(title_metadata);
         }
 
         (match self.method {
             ContentSelectorMethod::WithPhysicalPosition(pos) => {
-                Some(title_metadata.content_chunk_entries[pos].clone())
+                title_metadata.content_chunk_entries.get(pos).cloned()
             }
 
             ContentSelectorMethod::WithId(id) => title_metadata
@@ -60,6 +67,12 @@ impl ContentSelector {
                 .find(|entry| entry.index == index)
                 .cloned(),
 
+            ContentSelectorMethod::WithHash { hash, len } => title_metadata
+                .content_chunk_entries
+                .iter()
+                .find(|entry| entry.hash.matches(&hash[..len]))
+                .cloned(),
+
             ContentSelectorMethod::Last => unreachable!(),
         })
         .ok_or_else(TitleMetadataError::ContentNotFound)
@@ -71,11 +84,13 @@ impl ContentSelector {
         title_metadata: &TitleMetadata,
     ) -> Result<usize, TitleMetadataError> {
         if let ContentSelectorMethod::Last = self.method {
-            return Self::get_last(title_metadata).physical_position(title_metadata);
+            return Self::get_last(title_metadata)?.physical_position(title_metadata);
         }
 
         (match self.method {
-            ContentSelectorMethod::WithPhysicalPosition(pos) => Some(pos),
+            ContentSelectorMethod::WithPhysicalPosition(pos) => {
+                (pos < title_metadata.content_chunk_entries.len()).then_some(pos)
+            }
 
             ContentSelectorMethod::WithId(id) => title_metadata
                 .content_chunk_entries
@@ -87,6 +102,11 @@ impl ContentSelector {
                 .iter()
                 .position(|entry| entry.index == index),
 
+            ContentSelectorMethod::WithHash { hash, len } => title_metadata
+                .content_chunk_entries
+                .iter()
+                .position(|entry| entry.hash.matches(&hash[..len])),
+
             ContentSelectorMethod::Last => unreachable!(),
         })
         .ok_or_else(TitleMetadataError::ContentNotFound)
@@ -97,10 +117,11 @@ impl ContentSelector {
         Ok(match self.method {
             ContentSelectorMethod::WithId(id) => id,
 
-            ContentSelectorMethod::Last => Self::get_last(title_metadata).id(title_metadata)?,
+            ContentSelectorMethod::Last => Self::get_last(title_metadata)?.id(title_metadata)?,
 
             ContentSelectorMethod::WithPhysicalPosition(_)
-            | ContentSelectorMethod::WithIndex(_) => self.content_entry(title_metadata)?.id,
+            | ContentSelectorMethod::WithIndex(_)
+            | ContentSelectorMethod::WithHash { .. } => self.content_entry(title_metadata)?.id,
         })
     }
 
@@ -109,11 +130,186 @@ impl ContentSelector {
         Ok(match self.method {
             ContentSelectorMethod::WithIndex(index) => index,
 
-            ContentSelectorMethod::Last => Self::get_last(title_metadata).index(title_metadata)?,
+            ContentSelectorMethod::Last => Self::get_last(title_metadata)?.index(title_metadata)?,
 
-            ContentSelectorMethod::WithPhysicalPosition(_) | ContentSelectorMethod::WithId(_) => {
-                self.content_entry(title_metadata)?.index
-            }
+            ContentSelectorMethod::WithPhysicalPosition(_)
+            | ContentSelectorMethod::WithId(_)
+            | ContentSelectorMethod::WithHash { .. } => self.content_entry(title_metadata)?.index,
         })
     }
+
+    /// Search the selected content entry only once, useful when the same content is accessed
+    /// repeatedly. The result is not updated if the title metadata is modified.
+    pub fn resolve(
+        &self,
+        title_metadata: &TitleMetadata,
+    ) -> Result<ResolvedContent, TitleMetadataError> {
+        let physical_position = self.physical_position(title_metadata)?;
+
+        let entry = title_metadata
+            .content_chunk_entries
+            .get(physical_position)
+            .cloned()
+            .ok_or_else(TitleMetadataError::ContentNotFound)?;
+
+        Ok(ResolvedContent {
+            physical_position,
+            entry,
+        })
+    }
+}
+
+/// A content already searched inside a [TitleMetadata], create one with
+/// [ContentSelector::resolve].
+#[derive(Clone, Debug)]
+pub struct ResolvedContent {
+    /// The physical position of the content.
+    pub physical_position: usize,
+
+    /// The entry of the content.
+    pub entry: TitleMetadataContentEntry,
+}
+
+impl ResolvedContent {
+    /// Get the ID of the content.
+    pub fn id(&self) -> u32 {
+        self.entry.id
+    }
+
+    /// Get the index of the content.
+    pub fn index(&self) -> u16 {
+        self.entry.index
+    }
+
+    /// Get a selector of the content by its physical position, selecting it does not require
+    /// searching the entries again.
+    pub fn selector(&self) -> ContentSelector {
+        ContentSelector {
+            method: ContentSelectorMethod::WithPhysicalPosition(self.physical_position),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::templates;
+    use crate::title_metadata::TitleMetadataError;
+    use crate::title_metadata::{
+        TitleMetadataContentEntry, TitleMetadataContentEntryHashKind, TitleMetadataContentEntryKind,
+    };
+
+    #[test]
+    fn select_with_hash() {
+        let mut title_metadata = templates::homebrew_channel_tmd();
+        assert!(title_metadata.select_last().id(&title_metadata).is_err());
+
+        let mut padded_sha1_hash = [0; 32];
+        padded_sha1_hash[..20].copy_from_slice(&[0xAB; 20]);
+
+        for (id, hash) in [
+            (1, TitleMetadataContentEntryHashKind::Version0([0xCD; 20])),
+            (
+                2,
+                TitleMetadataContentEntryHashKind::Version1(padded_sha1_hash),
+            ),
+        ] {
+            title_metadata
+                .content_chunk_entries
+                .push(TitleMetadataContentEntry {
+                    id,
+                    index: 0,
+                    kind: TitleMetadataContentEntryKind::Normal,
+                    size: 0,
+                    hash,
+                });
+        }
+
+        let selector = title_metadata.select_with_hash(&[0xCD; 20]);
+        assert_eq!(selector.id(&title_metadata).unwrap(), 1);
+
+        let selector = title_metadata.select_with_hash(&[0xAB; 20]);
+        assert_eq!(selector.physical_position(&title_metadata).unwrap(), 1);
+
+        let selector = title_metadata.select_with_hash(&[0xAB; 64]);
+        assert!(selector.content_entry(&title_metadata).is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let mut title_metadata = templates::homebrew_channel_tmd();
+        assert!(
+            title_metadata
+                .select_last()
+                .resolve(&title_metadata)
+                .is_err()
+        );
+
+        for id in [5, 7] {
+            title_metadata
+                .content_chunk_entries
+                .push(TitleMetadataContentEntry {
+                    id,
+                    index: id as u16 * 2,
+                    kind: TitleMetadataContentEntryKind::Normal,
+                    size: 0,
+                    hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+                });
+        }
+
+        let resolved_content = title_metadata
+            .select_with_index(14)
+            .resolve(&title_metadata)
+            .unwrap();
+
+        assert_eq!(resolved_content.physical_position, 1);
+        assert_eq!(resolved_content.id(), 7);
+        assert_eq!(
+            resolved_content
+                .selector()
+                .content_entry(&title_metadata)
+                .unwrap()
+                .id,
+            7
+        );
+    }
+
+    #[test]
+    fn duplicated_contents() {
+        let mut title_metadata = templates::homebrew_channel_tmd();
+
+        for (id, index) in [(1, 0), (2, 1), (1, 2)] {
+            title_metadata
+                .content_chunk_entries
+                .push(TitleMetadataContentEntry {
+                    id,
+                    index,
+                    kind: TitleMetadataContentEntryKind::Normal,
+                    size: 0,
+                    hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+                });
+        }
+
+        assert!(matches!(
+            title_metadata.check_duplicated_contents(),
+            Err(TitleMetadataError::DuplicatedContentId(1))
+        ));
+
+        let positions: Vec<usize> = title_metadata
+            .select_all_with_id(1)
+            .iter()
+            .map(|selector| selector.physical_position(&title_metadata).unwrap())
+            .collect();
+        assert_eq!(positions, [0, 2]);
+        assert_eq!(title_metadata.select_all_with_index(1).len(), 1);
+
+        title_metadata.content_chunk_entries[2].id = 3;
+        title_metadata.content_chunk_entries[2].index = 1;
+        assert!(matches!(
+            title_metadata.check_duplicated_contents(),
+            Err(TitleMetadataError::DuplicatedContentIndex(1))
+        ));
+
+        title_metadata.content_chunk_entries[2].index = 2;
+        assert!(title_metadata.check_duplicated_contents().is_ok());
+    }
 }
@@ -0,0 +1,389 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [TitleDependencyGraph], the dependencies between the titles of a
+//! library (like a collection of WADs) given by their [TitleMetadata]: the system runtime (IOS)
+//! each title needs and the shared contents used by many titles.
+
+use crate::title_id::TitleId;
+use crate::title_metadata::{TitleMetadata, TitleMetadataContentEntryKind};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt::Write;
+
+/// The dependencies between the titles of a library, built from their title metadata.
+///
+/// ```
+/// use zelzip_niiebla::templates;
+/// use zelzip_niiebla::title_metadata::dependency_graph::TitleDependencyGraph;
+///
+/// let title_metadata = templates::homebrew_channel_tmd();
+/// let graph = TitleDependencyGraph::from_title_metadatas([&title_metadata]);
+///
+/// // The IOS used by the channel is not on the library
+/// for dependency in graph.missing_dependencies() {
+///     println!("Missing {} (needed by {} titles)", dependency.title_id, dependency.required_by.len());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct TitleDependencyGraph {
+    titles: Vec<DependencyGraphTitle>,
+}
+
+/// A title added to a [TitleDependencyGraph].
+#[derive(Debug, Serialize)]
+pub struct DependencyGraphTitle {
+    /// The ID of the title.
+    pub title_id: TitleId,
+
+    /// The version of the title.
+    pub title_version: u16,
+
+    /// The title used as system runtime (the IOS on the Wii), `None` if the title is itself a
+    /// runtime or does not need one.
+    pub system_runtime_title_id: Option<TitleId>,
+
+    /// The hashes of the shared contents of the title, in the order they are stored.
+    #[serde(rename = "shared_contents", serialize_with = "serialize_hashes")]
+    pub shared_content_hashes: Vec<Vec<u8>>,
+}
+
+/// A title needed by others but not added to a [TitleDependencyGraph].
+#[derive(Debug, Serialize)]
+pub struct MissingDependency {
+    /// The ID of the missing title.
+    pub title_id: TitleId,
+
+    /// The titles that need it, in the order they were added.
+    pub required_by: Vec<TitleId>,
+}
+
+/// A shared content of the titles of a [TitleDependencyGraph].
+#[derive(Debug, Serialize)]
+pub struct SharedContent {
+    /// The hash that identifies the content.
+    #[serde(serialize_with = "util::hex::serialize")]
+    pub hash: Vec<u8>,
+
+    /// The titles that use it, in the order they were added.
+    pub used_by: Vec<TitleId>,
+}
+
+impl TitleDependencyGraph {
+    /// Create a new empty [TitleDependencyGraph].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new [TitleDependencyGraph] with the given titles, see [Self::add].
+    pub fn from_title_metadatas<'a, I: IntoIterator<Item = &'a TitleMetadata>>(
+        title_metadatas: I,
+    ) -> Self {
+        let mut graph = Self::new();
+
+        for title_metadata in title_metadatas {
+            graph.add(title_metadata);
+        }
+
+        graph
+    }
+
+    /// Add a title to the graph. A library can have many versions of the same title, only the
+    /// highest one is kept.
+    pub fn add(&mut self, title_metadata: &TitleMetadata) {
+        let title_id = title_metadata.title_id.inner();
+
+        // The boot2 program uses itself as runtime
+        let system_runtime_title_id = title_metadata
+            .system_runtime_title_id
+            .as_ref()
+            .map(TitleId::inner)
+            .filter(|&runtime_title_id| runtime_title_id != title_id)
+            .map(TitleId::new);
+
+        let title = DependencyGraphTitle {
+            title_id: TitleId::new(title_id),
+            title_version: title_metadata.title_version,
+            system_runtime_title_id,
+            shared_content_hashes: title_metadata
+                .content_chunk_entries
+                .iter()
+                .filter(|entry| matches!(entry.kind, TitleMetadataContentEntryKind::Shared))
+                .map(|entry| entry.hash.as_bytes().to_vec())
+                .collect(),
+        };
+
+        match self.position(title_id) {
+            Some(i) if self.titles[i].title_version < title.title_version => {
+                self.titles[i] = title;
+            }
+            Some(_) => (),
+            None => self.titles.push(title),
+        }
+    }
+
+    /// Get the titles of the graph, in the order they were added.
+    pub fn titles(&self) -> &[DependencyGraphTitle] {
+        &self.titles
+    }
+
+    /// Check if a title has been added to the graph.
+    pub fn contains(&self, title_id: &TitleId) -> bool {
+        self.position(title_id.inner()).is_some()
+    }
+
+    /// Get the titles that use the given one as system runtime.
+    pub fn dependants(&self, title_id: &TitleId) -> Vec<TitleId> {
+        self.titles
+            .iter()
+            .filter(|title| {
+                title
+                    .system_runtime_title_id
+                    .as_ref()
+                    .is_some_and(|runtime_title_id| runtime_title_id.inner() == title_id.inner())
+            })
+            .map(|title| TitleId::new(title.title_id.inner()))
+            .collect()
+    }
+
+    /// Get the runtimes needed by the titles of the graph but not added to it (like the IOS
+    /// WADs to install before the channels of a collection), in the order they are first needed.
+    pub fn missing_dependencies(&self) -> Vec<MissingDependency> {
+        let mut missing_dependencies: Vec<MissingDependency> = vec![];
+
+        for title in &self.titles {
+            let Some(runtime_title_id) = &title.system_runtime_title_id else {
+                continue;
+            };
+
+            if self.contains(runtime_title_id) {
+                continue;
+            }
+
+            let required_by = TitleId::new(title.title_id.inner());
+
+            match missing_dependencies
+                .iter_mut()
+                .find(|dependency| dependency.title_id.inner() == runtime_title_id.inner())
+            {
+                Some(dependency) => dependency.required_by.push(required_by),
+                None => missing_dependencies.push(MissingDependency {
+                    title_id: TitleId::new(runtime_title_id.inner()),
+                    required_by: vec![required_by],
+                }),
+            }
+        }
+
+        missing_dependencies
+    }
+
+    /// Get the shared contents of the titles of the graph, in the order they are first used.
+    pub fn shared_contents(&self) -> Vec<SharedContent> {
+        let mut shared_contents: Vec<SharedContent> = vec![];
+
+        for title in &self.titles {
+            for hash in &title.shared_content_hashes {
+                let used_by = TitleId::new(title.title_id.inner());
+
+                match shared_contents
+                    .iter_mut()
+                    .find(|shared_content| &shared_content.hash == hash)
+                {
+                    // The same content can be stored twice on a title
+                    Some(shared_content)
+                        if shared_content
+                            .used_by
+                            .last()
+                            .is_some_and(|last| last.inner() == used_by.inner()) => {}
+                    Some(shared_content) => shared_content.used_by.push(used_by),
+                    None => shared_contents.push(SharedContent {
+                        hash: hash.clone(),
+                        used_by: vec![used_by],
+                    }),
+                }
+            }
+        }
+
+        shared_contents
+    }
+
+    /// Serialize the graph into the [DOT language](https://graphviz.org/doc/info/lang.html) of
+    /// Graphviz. Titles point to their runtime and their shared contents, the missing runtimes
+    /// are drawn with a dashed red border.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph titles {\n");
+
+        for title in &self.titles {
+            let _ = writeln!(
+                dot,
+                "  \"{}\" [label=\"{}\\nv{}\"];",
+                title.title_id, title.title_id, title.title_version
+            );
+        }
+
+        for dependency in self.missing_dependencies() {
+            let _ = writeln!(
+                dot,
+                "  \"{}\" [style=dashed, color=red];",
+                dependency.title_id
+            );
+        }
+
+        for shared_content in self.shared_contents() {
+            let _ = writeln!(
+                dot,
+                "  \"{}\" [shape=box];",
+                util::hex::encode(&shared_content.hash)
+            );
+        }
+
+        for title in &self.titles {
+            if let Some(runtime_title_id) = &title.system_runtime_title_id {
+                let _ = writeln!(dot, "  \"{}\" -> \"{runtime_title_id}\";", title.title_id);
+            }
+
+            for hash in &title.shared_content_hashes {
+                let _ = writeln!(
+                    dot,
+                    "  \"{}\" -> \"{}\";",
+                    title.title_id,
+                    util::hex::encode(hash)
+                );
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Serialize the graph into JSON, title IDs are written like `00010001-48424346` and hashes
+    /// in lowercase hexadecimal.
+    pub fn to_json(&self) -> String {
+        // Serializing plain structs with string keys never fails
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+
+        json
+    }
+
+    fn position(&self, title_id: u64) -> Option<usize> {
+        self.titles
+            .iter()
+            .position(|title| title.title_id.inner() == title_id)
+    }
+}
+
+/// The titles and their missing dependencies.
+impl Serialize for TitleDependencyGraph {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut graph = serializer.serialize_struct("TitleDependencyGraph", 2)?;
+        graph.serialize_field("titles", &self.titles)?;
+        graph.serialize_field("missing_dependencies", &self.missing_dependencies())?;
+        graph.end()
+    }
+}
+
+fn serialize_hashes<S: Serializer>(hashes: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(hashes.iter().map(|hash| util::hex::encode(hash)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::title_metadata::{TitleMetadataContentEntry, TitleMetadataContentEntryHashKind};
+
+    fn title_metadata(title_id: u64, title_version: u16, runtime: Option<u64>) -> TitleMetadata {
+        let mut title_metadata = templates::homebrew_channel_tmd();
+        title_metadata.title_id = TitleId::new(title_id);
+        title_metadata.title_version = title_version;
+        title_metadata.system_runtime_title_id = runtime.map(TitleId::new);
+
+        title_metadata
+    }
+
+    fn shared_content(hash: u8) -> TitleMetadataContentEntry {
+        TitleMetadataContentEntry {
+            id: 0,
+            index: 0,
+            kind: TitleMetadataContentEntryKind::Shared,
+            size: 0x10,
+            hash: TitleMetadataContentEntryHashKind::Version0([hash; 20]),
+        }
+    }
+
+    #[test]
+    fn build_dependency_graph() {
+        let mut channel = title_metadata(0x00010001_48424346, 1, Some(0x00000001_0000003A));
+        channel.content_chunk_entries.push(shared_content(0xAA));
+
+        let mut other_channel = title_metadata(0x00010001_48414141, 2, Some(0x00000001_0000003A));
+        other_channel
+            .content_chunk_entries
+            .push(shared_content(0xAA));
+
+        let old_other_channel = title_metadata(0x00010001_48414141, 1, Some(0x00000001_00000024));
+        let ios = title_metadata(0x00000001_00000024, 3, None);
+        let boot2 = title_metadata(0x00000001_00000001, 4, Some(0x00000001_00000001));
+
+        let graph = TitleDependencyGraph::from_title_metadatas([
+            &channel,
+            &other_channel,
+            &old_other_channel,
+            &ios,
+            &boot2,
+        ]);
+
+        assert_eq!(graph.titles().len(), 4);
+        assert_eq!(graph.titles()[1].title_version, 2);
+        assert!(graph.titles()[3].system_runtime_title_id.is_none());
+        assert!(
+            graph
+                .dependants(&TitleId::new(0x00000001_00000024))
+                .is_empty()
+        );
+
+        let missing_dependencies = graph.missing_dependencies();
+        assert_eq!(missing_dependencies.len(), 1);
+        assert_eq!(
+            missing_dependencies[0].title_id.inner(),
+            0x00000001_0000003A
+        );
+        assert_eq!(missing_dependencies[0].required_by.len(), 2);
+
+        let shared_contents = graph.shared_contents();
+        assert_eq!(shared_contents.len(), 1);
+        assert_eq!(shared_contents[0].hash, [0xAA; 20]);
+        assert_eq!(shared_contents[0].used_by.len(), 2);
+
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(json["titles"].as_array().unwrap().len(), 4);
+        assert_eq!(
+            json["titles"][0]["system_runtime_title_id"],
+            "00000001-0000003a"
+        );
+        assert_eq!(
+            json["titles"][2]["system_runtime_title_id"],
+            serde_json::Value::Null
+        );
+        assert_eq!(json["titles"][0]["shared_contents"][0], "aa".repeat(20));
+        assert_eq!(
+            json["missing_dependencies"][0]["required_by"][1],
+            "00010001-48414141"
+        );
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph titles {\n"));
+        assert!(dot.contains("  \"00010001-48424346\" -> \"00000001-0000003a\";\n"));
+        assert!(dot.contains("  \"00000001-0000003a\" [style=dashed, color=red];\n"));
+        assert!(!dot.contains("\"00000001-00000001\" -> "));
+
+        assert_eq!(
+            TitleDependencyGraph::new().to_json(),
+            "{\n  \"titles\": [],\n  \"missing_dependencies\": []\n}\n"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [TitleMetadataIpcMask] used in the Wii platform data of a
+//! [TitleMetadata](crate::TitleMetadata).
+
+/// The IPC mask of a title, a bitfield of 96 bits where each bit allows the title to use an
+/// IPC call into the IOS.
+///
+/// The bits are numbered from the most significant bit of the first byte (bit `0`) to the least
+/// significant bit of the last byte (bit `95`).
+///
+/// The IPC call guarded by each bit has not been publicly documented (the
+/// [TMD page of WiiBrew](https://wiibrew.org/wiki/Title_metadata) only names the field), so no
+/// names are given to the bits and they are only accessed by their number.
+// TODO(DISCOVER): The IPC call guarded by each concrete bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TitleMetadataIpcMask {
+    bytes: [u8; Self::SIZE],
+}
+
+impl TitleMetadataIpcMask {
+    /// Size of the IPC mask in bytes.
+    pub const SIZE: usize = 12;
+
+    /// Number of bits of the IPC mask.
+    pub const NUMBER_OF_BITS: usize = Self::SIZE * 8;
+
+    /// Create a new [TitleMetadataIpcMask] from its raw bytes.
+    pub fn new(bytes: [u8; Self::SIZE]) -> Self {
+        Self { bytes }
+    }
+
+    /// Get the raw bytes of the IPC mask.
+    pub fn bytes(&self) -> &[u8; Self::SIZE] {
+        &self.bytes
+    }
+
+    /// Check if the IPC call of the given bit is allowed, `None` if the bit is out of bounds.
+    pub fn is_allowed(&self, bit: usize) -> Option<bool> {
+        let byte = self.bytes.get(bit / 8)?;
+
+        Some(byte & Self::bit_mask(bit) != 0)
+    }
+
+    /// Allow or disallow the IPC call of the given bit, `None` if the bit is out of bounds.
+    pub fn set_allowed(&mut self, bit: usize, allowed: bool) -> Option<()> {
+        let byte = self.bytes.get_mut(bit / 8)?;
+
+        if allowed {
+            *byte |= Self::bit_mask(bit);
+        } else {
+            *byte &= !Self::bit_mask(bit);
+        }
+
+        Some(())
+    }
+
+    /// Get the bits of the allowed IPC calls.
+    pub fn allowed_bits(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..Self::NUMBER_OF_BITS).filter(|&bit| self.is_allowed(bit) == Some(true))
+    }
+
+    /// Check if all the IPC calls are allowed.
+    pub fn is_all_allowed(&self) -> bool {
+        self.bytes == [0xFF; Self::SIZE]
+    }
+
+    /// Allow all the IPC calls, as the meaning of each bit is unknown this is the only way to
+    /// grant a concrete permission.
+    pub fn allow_all(&mut self) {
+        self.bytes = [0xFF; Self::SIZE];
+    }
+
+    fn bit_mask(bit: usize) -> u8 {
+        0b1000_0000 >> (bit % 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_and_set_bits() {
+        let mut ipc_mask = TitleMetadataIpcMask::default();
+
+        ipc_mask.set_allowed(0, true).unwrap();
+        ipc_mask.set_allowed(95, true).unwrap();
+        assert!(ipc_mask.set_allowed(96, true).is_none());
+
+        assert_eq!(ipc_mask.bytes()[0], 0x80);
+        assert_eq!(ipc_mask.bytes()[11], 0x01);
+        assert_eq!(ipc_mask.allowed_bits().collect::<Vec<_>>(), [0, 95]);
+
+        ipc_mask.allow_all();
+        ipc_mask.set_allowed(95, false).unwrap();
+
+        assert!(!ipc_mask.is_all_allowed());
+        assert_eq!(ipc_mask.is_allowed(95), Some(false));
+    }
+}
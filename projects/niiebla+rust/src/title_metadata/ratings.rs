@@ -0,0 +1,173 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [TitleMetadataRatings] used in the Wii platform data of a
+//! [TitleMetadata](crate::TitleMetadata).
+
+/// The age ratings of a title, one byte per rating agency.
+///
+/// The bytes of the unknown (or unused) agencies are kept as is, so they can be dumped back
+/// without changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TitleMetadataRatings {
+    bytes: [u8; Self::SIZE],
+}
+
+impl TitleMetadataRatings {
+    /// Size of the ratings in bytes.
+    pub const SIZE: usize = 16;
+
+    const ENABLED_FLAG: u8 = 0b1000_0000;
+    const PENDING_FLAG: u8 = 0b0100_0000;
+    const NO_AGE_RESTRICTION_FLAG: u8 = 0b0010_0000;
+    const AGE_MASK: u8 = 0b0001_1111;
+
+    /// Create a new [TitleMetadataRatings] from its raw bytes.
+    pub fn new(bytes: [u8; Self::SIZE]) -> Self {
+        Self { bytes }
+    }
+
+    /// Get the raw bytes of the ratings.
+    pub fn bytes(&self) -> &[u8; Self::SIZE] {
+        &self.bytes
+    }
+
+    /// Get the rating given by an agency, `None` if the title has not been rated by it.
+    pub fn get(&self, agency: TitleMetadataRatingAgency) -> Option<TitleMetadataAgeRating> {
+        let byte = self.bytes[agency.index()];
+
+        if byte & Self::ENABLED_FLAG == 0 {
+            return None;
+        }
+
+        Some(if byte & Self::PENDING_FLAG != 0 {
+            TitleMetadataAgeRating::Pending
+        } else if byte & Self::NO_AGE_RESTRICTION_FLAG != 0 {
+            TitleMetadataAgeRating::NoAgeRestriction
+        } else {
+            TitleMetadataAgeRating::Age(byte & Self::AGE_MASK)
+        })
+    }
+
+    /// Set the rating given by an agency, `None` marks the title as not rated by it. Ages are
+    /// truncated to five bits.
+    pub fn set(
+        &mut self,
+        agency: TitleMetadataRatingAgency,
+        rating: Option<TitleMetadataAgeRating>,
+    ) {
+        self.bytes[agency.index()] = match rating {
+            None => 0,
+            Some(TitleMetadataAgeRating::Pending) => Self::ENABLED_FLAG | Self::PENDING_FLAG,
+            Some(TitleMetadataAgeRating::NoAgeRestriction) => {
+                Self::ENABLED_FLAG | Self::NO_AGE_RESTRICTION_FLAG
+            }
+            Some(TitleMetadataAgeRating::Age(age)) => Self::ENABLED_FLAG | (age & Self::AGE_MASK),
+        };
+    }
+}
+
+impl Default for TitleMetadataRatings {
+    fn default() -> Self {
+        Self::new([0; Self::SIZE])
+    }
+}
+
+/// The rating agencies known to be stored in [TitleMetadataRatings].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleMetadataRatingAgency {
+    /// Computer Entertainment Rating Organization (Japan).
+    Cero,
+
+    /// Entertainment Software Rating Board (USA).
+    Esrb,
+
+    /// Unterhaltungssoftware Selbstkontrolle (Germany).
+    Usk,
+
+    /// Pan European Game Information (Europe).
+    Pegi,
+
+    /// Finnish variant of PEGI.
+    PegiFinland,
+
+    /// Portuguese variant of PEGI.
+    PegiPortugal,
+
+    /// British variant of PEGI (British Board of Film Classification).
+    PegiBbfc,
+
+    /// Australian Classification Board (Australia).
+    Acb,
+
+    /// Game Rating Board (South Korea).
+    Grb,
+}
+
+impl TitleMetadataRatingAgency {
+    fn index(&self) -> usize {
+        match self {
+            Self::Cero => 0,
+            Self::Esrb => 1,
+            Self::Usk => 3,
+            Self::Pegi => 4,
+            Self::PegiFinland => 5,
+            Self::PegiPortugal => 6,
+            Self::PegiBbfc => 7,
+            Self::Acb => 8,
+            Self::Grb => 9,
+        }
+    }
+}
+
+/// The rating given by an agency in [TitleMetadataRatings].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleMetadataAgeRating {
+    /// The title is still pending to be rated.
+    Pending,
+
+    /// The title is suitable for all ages.
+    NoAgeRestriction,
+
+    /// The minimum age required to play the title.
+    Age(u8),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_and_set_preserving_unknown_bytes() {
+        let mut bytes = [0; TitleMetadataRatings::SIZE];
+        bytes[0] = 0x8C;
+        bytes[2] = 0x42;
+        bytes[15] = 0xFF;
+
+        let mut ratings = TitleMetadataRatings::new(bytes);
+
+        assert_eq!(
+            ratings.get(TitleMetadataRatingAgency::Cero),
+            Some(TitleMetadataAgeRating::Age(12))
+        );
+        assert_eq!(ratings.get(TitleMetadataRatingAgency::Esrb), None);
+
+        ratings.set(
+            TitleMetadataRatingAgency::Pegi,
+            Some(TitleMetadataAgeRating::NoAgeRestriction),
+        );
+        ratings.set(TitleMetadataRatingAgency::Cero, None);
+
+        assert_eq!(
+            ratings.get(TitleMetadataRatingAgency::Pegi),
+            Some(TitleMetadataAgeRating::NoAgeRestriction)
+        );
+
+        bytes[0] = 0;
+        bytes[4] = 0xA0;
+        assert_eq!(ratings.bytes(), &bytes);
+    }
+}
@@ -0,0 +1,131 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [The3dsTmdExtras] used in the 3DS platform data of a
+//! [TitleMetadata](crate::TitleMetadata).
+
+use crate::title_metadata::TitleMetadataError;
+
+/// The data only present on the title metadata of 3DS titles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct The3dsTmdExtras {
+    public_save_data_size: u32,
+    private_save_data_size: u32,
+    srl_flag: u8,
+}
+
+impl The3dsTmdExtras {
+    /// Size of a media unit in bytes, all the save data sizes must be aligned to it.
+    pub const MEDIA_UNIT_SIZE: u32 = 0x200;
+
+    /// Size of a block (as shown on the data management of the HOME Menu) in bytes.
+    pub const BLOCK_SIZE: u32 = 0x20000;
+
+    /// Create a new [The3dsTmdExtras], the save data sizes must be aligned to
+    /// [Self::MEDIA_UNIT_SIZE].
+    pub fn new(
+        public_save_data_size: u32,
+        private_save_data_size: u32,
+        srl_flag: u8,
+    ) -> Result<Self, TitleMetadataError> {
+        let mut extras = Self::default();
+
+        extras.set_public_save_data_size(public_save_data_size)?;
+        extras.set_private_save_data_size(private_save_data_size)?;
+        extras.set_srl_flag(srl_flag);
+
+        Ok(extras)
+    }
+
+    // Titles in the wild may have unaligned sizes, they must be preserved as is
+    pub(super) fn new_unchecked(
+        public_save_data_size: u32,
+        private_save_data_size: u32,
+        srl_flag: u8,
+    ) -> Self {
+        Self {
+            public_save_data_size,
+            private_save_data_size,
+            srl_flag,
+        }
+    }
+
+    /// Get the size of the public save data section in bytes.
+    pub fn public_save_data_size(&self) -> u32 {
+        self.public_save_data_size
+    }
+
+    /// Set the size of the public save data section in bytes, it must be aligned to
+    /// [Self::MEDIA_UNIT_SIZE].
+    pub fn set_public_save_data_size(&mut self, size: u32) -> Result<(), TitleMetadataError> {
+        self.public_save_data_size = Self::check_alignment(size)?;
+
+        Ok(())
+    }
+
+    /// Get the size of the private save data section in bytes.
+    pub fn private_save_data_size(&self) -> u32 {
+        self.private_save_data_size
+    }
+
+    /// Set the size of the private save data section in bytes, it must be aligned to
+    /// [Self::MEDIA_UNIT_SIZE].
+    pub fn set_private_save_data_size(&mut self, size: u32) -> Result<(), TitleMetadataError> {
+        self.private_save_data_size = Self::check_alignment(size)?;
+
+        Ok(())
+    }
+
+    /// Get the SRL flag of the title.
+    // TODO(DISCOVER)
+    pub fn srl_flag(&self) -> u8 {
+        self.srl_flag
+    }
+
+    /// Set the SRL flag of the title.
+    pub fn set_srl_flag(&mut self, srl_flag: u8) {
+        self.srl_flag = srl_flag;
+    }
+
+    /// Get the size of the public save data section in blocks.
+    pub fn public_save_data_blocks(&self) -> u32 {
+        Self::size_to_blocks(self.public_save_data_size)
+    }
+
+    /// Get the size of the private save data section in blocks.
+    pub fn private_save_data_blocks(&self) -> u32 {
+        Self::size_to_blocks(self.private_save_data_size)
+    }
+
+    /// Convert a size in bytes into blocks, rounding up as done by the HOME Menu.
+    pub fn size_to_blocks(size: u32) -> u32 {
+        size.div_ceil(Self::BLOCK_SIZE)
+    }
+
+    fn check_alignment(size: u32) -> Result<u32, TitleMetadataError> {
+        if !size.is_multiple_of(Self::MEDIA_UNIT_SIZE) {
+            return Err(TitleMetadataError::UnalignedSaveDataSize(size));
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_and_convert_to_blocks() {
+        let mut extras = The3dsTmdExtras::new(0x80000, 0, 0).unwrap();
+
+        assert_eq!(extras.public_save_data_blocks(), 4);
+        assert!(extras.set_private_save_data_size(0x201).is_err());
+
+        extras.set_private_save_data_size(0x200).unwrap();
+        assert_eq!(extras.private_save_data_blocks(), 1);
+    }
+}
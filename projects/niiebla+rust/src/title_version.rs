@@ -0,0 +1,86 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of a newtype wrapper around the version of a title.
+
+use std::fmt::{self, Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// 16 bit value used to store the version of titles on Nintendo consoles.
+///
+/// Most platforms store the major version on the higher byte and the minor version on the lower
+/// one (`v1.0` is stored as `256`), the Nintendo 3DS splits it in three components instead, see
+/// [Self::display_3ds].
+pub struct TitleVersion(u16);
+
+impl TitleVersion {
+    /// Create a new [TitleVersion].
+    pub fn new(title_version_value: u16) -> Self {
+        Self(title_version_value)
+    }
+
+    /// Get the stored value inside the title version.
+    pub fn inner(&self) -> u16 {
+        self.0
+    }
+
+    /// Get the major version (higher byte).
+    pub fn major(&self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    /// Get the minor version (lower byte).
+    pub fn minor(&self) -> u8 {
+        (self.0 & 0xFF) as u8
+    }
+
+    /// Get a wrapper that can display the title version following the scheme of the Nintendo
+    /// 3DS (six bits of major version, six bits of minor version and four bits of micro version).
+    pub fn display_3ds(&self) -> TitleVersion3dsDisplay {
+        TitleVersion3dsDisplay(*self)
+    }
+}
+
+impl Display for TitleVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}.{}", self.major(), self.minor())
+    }
+}
+
+/// Wrapper that can display the title version following the scheme of the Nintendo 3DS.
+pub struct TitleVersion3dsDisplay(TitleVersion);
+
+impl Display for TitleVersion3dsDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.0.inner();
+
+        write!(
+            f,
+            "v{}.{}.{}",
+            value >> 10,
+            (value >> 4) & 0x3F,
+            value & 0xF
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!("v1.0", format!("{}", TitleVersion::new(256)));
+        assert_eq!("v2.3", format!("{}", TitleVersion::new(0x0203)));
+    }
+
+    #[test]
+    fn display_3ds() {
+        // 11.17.0
+        let title_version = TitleVersion::new((11 << 10) | (17 << 4));
+        assert_eq!("v11.17.0", format!("{}", title_version.display_3ds()));
+    }
+}
@@ -0,0 +1,492 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [TPL](https://wiki.tockdom.com/wiki/TPL_(File_Format)) texture format
+//! used by the Nintendo Wii (for example on the icons and banners of the titles).
+
+use crate::parse_limits::{ParseLimitError, ParseLimits};
+use byteorder::{BE, ReadBytesExt};
+use std::io::{self, Read, Seek};
+use thiserror::Error;
+use util::{ParseContext, ReadEx, StreamPin};
+
+const CONTEXT: ParseContext = ParseContext::new("TPL");
+
+const TPL_MAGIC_NUMBERS: [u8; 4] = [0x00, 0x20, 0xAF, 0x30];
+
+/// A TPL texture palette, with one or more images.
+#[derive(Debug, Clone)]
+pub struct Tpl {
+    /// The images stored inside the palette.
+    pub images: Vec<TplImage>,
+}
+
+impl Tpl {
+    /// Create a new [Tpl] by parsing a stream.
+    ///
+    /// Unlike the other formats the [ParseLimits::default] are always applied, as the size of
+    /// the images is only bounded by their dimensions (up to 16 GiB), see [Self::new_with_limits].
+    pub fn new<T: Read + Seek>(stream: T) -> Result<Self, TplError> {
+        Self::new_with_limits(stream, &ParseLimits::default())
+    }
+
+    /// Like [Self::new] but with custom [ParseLimits], the number of images is checked against
+    /// the sections limit and the size of the data of each image against the allocation limit.
+    pub fn new_with_limits<T: Read + Seek>(
+        stream: T,
+        limits: &ParseLimits,
+    ) -> Result<Self, TplError> {
+        let mut stream = StreamPin::new(stream)?;
+
+        let magic_numbers = CONTEXT.field(&mut stream, "magic_numbers", |stream| {
+            util::read_exact!(stream, 4)
+        })?;
+
+        if magic_numbers != TPL_MAGIC_NUMBERS {
+            return Err(TplError::InvalidMagicNumbers(magic_numbers));
+        }
+
+        let number_of_images = CONTEXT.field(&mut stream, "number_of_images", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+
+        let image_table_offset = CONTEXT.field(&mut stream, "image_table_offset", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+
+        limits.check_sections(number_of_images as usize)?;
+
+        let mut images = Vec::new();
+
+        for i in 0..number_of_images {
+            stream.seek_from_pin(image_table_offset as i64 + i as i64 * 8)?;
+
+            let image_header_offset =
+                CONTEXT.field(&mut stream, "image_header_offset", |stream| {
+                    stream.read_u32::<BE>()
+                })?;
+
+            stream.seek_from_pin(image_header_offset as i64)?;
+
+            let height = CONTEXT.field(&mut stream, "height", |stream| stream.read_u16::<BE>())?;
+            let width = CONTEXT.field(&mut stream, "width", |stream| stream.read_u16::<BE>())?;
+
+            let format = CONTEXT.field(&mut stream, "format", |stream| stream.read_u32::<BE>())?;
+            let format = TplImageFormat::new(format)?;
+
+            let data_offset =
+                CONTEXT.field(&mut stream, "data_offset", |stream| stream.read_u32::<BE>())?;
+
+            let data_size = format
+                .data_size(width, height)
+                .ok_or(TplError::ImageTooBig(width, height))?;
+            limits.check_alloc(data_size)?;
+
+            stream.seek_from_pin(data_offset as i64)?;
+
+            let data = CONTEXT.field(&mut stream, "data", |stream| {
+                stream.read_exact_vec(data_size)
+            })?;
+
+            images.push(TplImage {
+                width,
+                height,
+                format,
+                data,
+            });
+        }
+
+        Ok(Self { images })
+    }
+}
+
+/// An image stored inside a [Tpl].
+#[derive(Debug, Clone)]
+pub struct TplImage {
+    /// Width of the image in pixels.
+    pub width: u16,
+
+    /// Height of the image in pixels.
+    pub height: u16,
+
+    /// The format used to encode the pixels of the image.
+    pub format: TplImageFormat,
+
+    /// The encoded pixels of the image, stored in blocks.
+    pub data: Vec<u8>,
+}
+
+impl TplImage {
+    /// Decode the image into a buffer of RGBA8 pixels in row-major order, the data must be big
+    /// enough for the dimensions of the image.
+    pub fn to_rgba8(&self) -> Result<Vec<u8>, TplError> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        let data_size = self
+            .format
+            .data_size(self.width, self.height)
+            .ok_or(TplError::ImageTooBig(self.width, self.height))?;
+
+        if (self.data.len() as u64) < data_size {
+            return Err(TplError::DataTooSmall(self.data.len(), data_size));
+        }
+
+        let rgba_size = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .ok_or(TplError::ImageTooBig(self.width, self.height))?;
+
+        let (block_width, block_height) = self.format.block_dimensions();
+        let blocks_per_row = width.div_ceil(block_width);
+
+        let mut rgba = vec![0; rgba_size];
+
+        for (i, block) in self
+            .data
+            .chunks_exact(TplImageFormat::BLOCK_SIZE)
+            .enumerate()
+        {
+            let pixels = self.format.decode_block(block)?;
+
+            let block_x = (i % blocks_per_row) * block_width;
+            let block_y = (i / blocks_per_row) * block_height;
+
+            for (j, pixel) in pixels.iter().take(block_width * block_height).enumerate() {
+                let x = block_x + j % block_width;
+                let y = block_y + j / block_width;
+
+                if x < width && y < height {
+                    let position = (y * width + x) * 4;
+                    rgba[position..position + 4].copy_from_slice(pixel);
+                }
+            }
+        }
+
+        Ok(rgba)
+    }
+}
+
+/// The format used to encode the pixels of a [TplImage].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum TplImageFormat {
+    I4,
+    I8,
+    Ia4,
+    Ia8,
+    Rgb565,
+    Rgb5a3,
+    Rgba32,
+    C4,
+    C8,
+    C14x2,
+    Cmpr,
+}
+
+impl TplImageFormat {
+    /// Size of a block of pixels in bytes (doubled on [TplImageFormat::Rgba32]).
+    const BLOCK_SIZE: usize = 32;
+
+    fn new(identifier: u32) -> Result<Self, TplError> {
+        Ok(match identifier {
+            0x0 => Self::I4,
+            0x1 => Self::I8,
+            0x2 => Self::Ia4,
+            0x3 => Self::Ia8,
+            0x4 => Self::Rgb565,
+            0x5 => Self::Rgb5a3,
+            0x6 => Self::Rgba32,
+            0x8 => Self::C4,
+            0x9 => Self::C8,
+            0xA => Self::C14x2,
+            0xE => Self::Cmpr,
+
+            identifier => return Err(TplError::UnknownImageFormat(identifier)),
+        })
+    }
+
+    /// Get the width and height in pixels of a block of the format.
+    fn block_dimensions(&self) -> (usize, usize) {
+        match self {
+            Self::I4 | Self::C4 | Self::Cmpr => (8, 8),
+            Self::I8 | Self::Ia4 | Self::C8 => (8, 4),
+            Self::Ia8 | Self::Rgb565 | Self::Rgb5a3 | Self::Rgba32 | Self::C14x2 => (4, 4),
+        }
+    }
+
+    /// Get the size in bytes of the data of an image with the given dimensions, `None` on
+    /// overflow.
+    fn data_size(&self, width: u16, height: u16) -> Option<u64> {
+        let (block_width, block_height) = self.block_dimensions();
+
+        let number_of_blocks = (width as u64)
+            .div_ceil(block_width as u64)
+            .checked_mul((height as u64).div_ceil(block_height as u64))?;
+
+        // RGBA32 blocks are stored in two halves, one with the alpha and red values and the other
+        // with the green and blue values
+        let block_size = match self {
+            Self::Rgba32 => Self::BLOCK_SIZE * 2,
+            _ => Self::BLOCK_SIZE,
+        };
+
+        number_of_blocks.checked_mul(block_size as u64)
+    }
+
+    /// Decode a block of [Self::BLOCK_SIZE] bytes into its pixels in row-major order, only the
+    /// first `width * height` (of [Self::block_dimensions]) are meaningful.
+    fn decode_block(&self, block: &[u8]) -> Result<[[u8; 4]; 64], TplError> {
+        let mut pixels = [[0; 4]; 64];
+
+        match self {
+            Self::I4 => {
+                for (i, byte) in block.iter().enumerate() {
+                    pixels[i * 2] = intensity(expand_4_bits(byte >> 4));
+                    pixels[i * 2 + 1] = intensity(expand_4_bits(byte & 0xF));
+                }
+            }
+
+            Self::I8 => {
+                for (pixel, &byte) in pixels.iter_mut().zip(block) {
+                    *pixel = intensity(byte);
+                }
+            }
+
+            Self::Ia8 => {
+                for (pixel, bytes) in pixels.iter_mut().zip(block.chunks_exact(2)) {
+                    *pixel = [bytes[1], bytes[1], bytes[1], bytes[0]];
+                }
+            }
+
+            Self::Rgb565 => {
+                for (pixel, bytes) in pixels.iter_mut().zip(block.chunks_exact(2)) {
+                    *pixel = rgb565(u16::from_be_bytes([bytes[0], bytes[1]]));
+                }
+            }
+
+            Self::Rgb5a3 => {
+                for (pixel, bytes) in pixels.iter_mut().zip(block.chunks_exact(2)) {
+                    *pixel = rgb5a3(u16::from_be_bytes([bytes[0], bytes[1]]));
+                }
+            }
+
+            Self::Cmpr => {
+                // Four DXT1 sub-blocks of 4x4 pixels each, ordered from left to right and from
+                // top to bottom
+                for (i, sub_block) in block.chunks_exact(8).enumerate() {
+                    let first_color = u16::from_be_bytes([sub_block[0], sub_block[1]]);
+                    let second_color = u16::from_be_bytes([sub_block[2], sub_block[3]]);
+
+                    let palette = cmpr_palette(first_color, second_color);
+
+                    let offset_x = (i % 2) * 4;
+                    let offset_y = (i / 2) * 4;
+
+                    for (y, indices) in sub_block[4..].iter().enumerate() {
+                        for x in 0..4 {
+                            let index = (indices >> (6 - x * 2)) & 0b11;
+
+                            pixels[(offset_y + y) * 8 + offset_x + x] = palette[index as usize];
+                        }
+                    }
+                }
+            }
+
+            format => return Err(TplError::UnsupportedImageFormat(*format)),
+        }
+
+        Ok(pixels)
+    }
+}
+
+fn intensity(value: u8) -> [u8; 4] {
+    [value, value, value, 0xFF]
+}
+
+fn expand_3_bits(value: u8) -> u8 {
+    (value << 5) | (value << 2) | (value >> 1)
+}
+
+fn expand_4_bits(value: u8) -> u8 {
+    (value << 4) | value
+}
+
+fn expand_5_bits(value: u8) -> u8 {
+    (value << 3) | (value >> 2)
+}
+
+fn expand_6_bits(value: u8) -> u8 {
+    (value << 2) | (value >> 4)
+}
+
+fn rgb565(value: u16) -> [u8; 4] {
+    [
+        expand_5_bits(((value >> 11) & 0x1F) as u8),
+        expand_6_bits(((value >> 5) & 0x3F) as u8),
+        expand_5_bits((value & 0x1F) as u8),
+        0xFF,
+    ]
+}
+
+fn rgb5a3(value: u16) -> [u8; 4] {
+    // Opaque pixels are stored as RGB555 and translucent ones as RGB444 with 3 bits of alpha
+    if value & 0x8000 != 0 {
+        [
+            expand_5_bits(((value >> 10) & 0x1F) as u8),
+            expand_5_bits(((value >> 5) & 0x1F) as u8),
+            expand_5_bits((value & 0x1F) as u8),
+            0xFF,
+        ]
+    } else {
+        [
+            expand_4_bits(((value >> 8) & 0xF) as u8),
+            expand_4_bits(((value >> 4) & 0xF) as u8),
+            expand_4_bits((value & 0xF) as u8),
+            expand_3_bits(((value >> 12) & 0x7) as u8),
+        ]
+    }
+}
+
+fn cmpr_palette(first_color: u16, second_color: u16) -> [[u8; 4]; 4] {
+    let first = rgb565(first_color);
+    let second = rgb565(second_color);
+
+    let mix = |first_weight: u16, second_weight: u16| {
+        let mut color = [0xFF; 4];
+
+        for i in 0..3 {
+            color[i] = ((first[i] as u16 * first_weight + second[i] as u16 * second_weight)
+                / (first_weight + second_weight)) as u8;
+        }
+
+        color
+    };
+
+    if first_color > second_color {
+        [first, second, mix(2, 1), mix(1, 2)]
+    } else {
+        [first, second, mix(1, 1), [0; 4]]
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum TplError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("The given stream is not a TPL, invalid magic numbers: {0:?}")]
+    InvalidMagicNumbers([u8; 4]),
+
+    #[error("Unknown image format: {0:#X}")]
+    UnknownImageFormat(u32),
+
+    #[error("Decoding the image format {0:?} is not supported")]
+    UnsupportedImageFormat(TplImageFormat),
+
+    #[error("The image is too big: {0}x{1} pixels")]
+    ImageTooBig(u16, u16),
+
+    #[error("The data of the image is too small: {0} bytes (expected {1} bytes)")]
+    DataTooSmall(usize, u64),
+
+    #[error("Parse limit exceeded: {0}")]
+    ParseLimitError(#[from] ParseLimitError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::WriteBytesExt;
+    use std::io::Cursor;
+
+    fn dummy_tpl(width: u16, height: u16, format: u32, data: &[u8]) -> Vec<u8> {
+        let mut buffer = vec![];
+
+        buffer.extend_from_slice(&TPL_MAGIC_NUMBERS);
+        buffer.write_u32::<BE>(1).unwrap();
+        buffer.write_u32::<BE>(0xC).unwrap();
+
+        // Image table
+        buffer.write_u32::<BE>(0x14).unwrap();
+        buffer.write_u32::<BE>(0).unwrap();
+
+        // Image header
+        buffer.write_u16::<BE>(height).unwrap();
+        buffer.write_u16::<BE>(width).unwrap();
+        buffer.write_u32::<BE>(format).unwrap();
+        buffer.write_u32::<BE>(0x40).unwrap();
+
+        buffer.resize(0x40, 0);
+        buffer.extend_from_slice(data);
+
+        buffer
+    }
+
+    #[test]
+    fn decode_rgb5a3() {
+        let mut data = vec![0; 32];
+        // Opaque red and then half transparent white
+        data[..4].copy_from_slice(&[0xFC, 0x00, 0x4F, 0xFF]);
+
+        let tpl = Tpl::new(Cursor::new(dummy_tpl(2, 1, 0x5, &data))).unwrap();
+        let rgba = tpl.images[0].to_rgba8().unwrap();
+
+        assert_eq!(rgba, [0xFF, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x92]);
+    }
+
+    #[test]
+    fn decode_cmpr() {
+        let mut data = vec![0; 32];
+        // Black and white palette, first row of the top left sub-block uses all the colors
+        data[..8].copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00, 0b00_01_10_11, 0, 0, 0]);
+
+        let tpl = Tpl::new(Cursor::new(dummy_tpl(4, 1, 0xE, &data))).unwrap();
+        let rgba = tpl.images[0].to_rgba8().unwrap();
+
+        assert_eq!(
+            rgba,
+            [
+                0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0xAA, 0xAA, 0xAA, 0xFF, 0x55, 0x55,
+                0x55, 0xFF,
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_limits() {
+        // 16 GiB of RGBA32 data, rejected before reading it
+        let tpl = dummy_tpl(0xFFFF, 0xFFFF, 0x6, &[0; 32]);
+
+        assert!(matches!(
+            Tpl::new(Cursor::new(&tpl)),
+            Err(TplError::ParseLimitError(
+                ParseLimitError::AllocationTooBig(0x4_0000_0000, _)
+            ))
+        ));
+
+        let limits = ParseLimits {
+            max_alloc: 16,
+            ..ParseLimits::default()
+        };
+        assert!(matches!(
+            Tpl::new_with_limits(Cursor::new(dummy_tpl(2, 1, 0x5, &[0; 32])), &limits),
+            Err(TplError::ParseLimitError(_))
+        ));
+
+        let image = TplImage {
+            width: 0xFFFF,
+            height: 0xFFFF,
+            format: TplImageFormat::Rgba32,
+            data: vec![0; 64],
+        };
+        assert!(matches!(
+            image.to_rgba8(),
+            Err(TplError::DataTooSmall(64, 0x4_0000_0000))
+        ));
+    }
+}
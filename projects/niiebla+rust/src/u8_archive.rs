@@ -0,0 +1,412 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the [U8 archive](https://wiibrew.org/wiki/U8_archive) format, used by the
+//! Nintendo Wii to pack the files of the banners and of the Virtual Console titles.
+
+use byteorder::{BE, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use thiserror::Error;
+use util::{ParseContext, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("U8 archive");
+
+const U8_MAGIC_NUMBERS: u32 = 0x55AA382D;
+
+/// A U8 archive, its nodes are stored flattened in the same order as on disk: each directory is
+/// followed by all its descendants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct U8Archive {
+    /// The nodes of the archive, the first one is the root directory (with an empty name).
+    pub nodes: Vec<U8Node>,
+}
+
+/// A file or directory inside a [U8Archive].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct U8Node {
+    /// The name of the node, without its parent directories.
+    pub name: String,
+
+    /// Whether the node is a file or a directory.
+    pub kind: U8NodeKind,
+}
+
+/// The kind of a [U8Node].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum U8NodeKind {
+    /// A file with its data.
+    File(Vec<u8>),
+
+    /// A directory, its descendants are the nodes between it and `next`.
+    Directory {
+        /// Index of the parent directory.
+        parent: u32,
+
+        /// Index of the first node after the last descendant of the directory.
+        next: u32,
+    },
+}
+
+impl U8Archive {
+    const HEADER_SIZE: u64 = 0x20;
+    const NODE_SIZE: u64 = 12;
+
+    const DATA_ALIGNMENT: u64 = 0x40;
+    const FILE_ALIGNMENT: u64 = 0x20;
+
+    /// Check if the data starts with the magic numbers of a U8 archive.
+    pub fn is_u8_archive(data: &[u8]) -> bool {
+        data.starts_with(&U8_MAGIC_NUMBERS.to_be_bytes())
+    }
+
+    /// Create a new [U8Archive] by parsing a stream, the data of all the files is loaded into
+    /// memory.
+    pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, U8ArchiveError> {
+        let start = stream.stream_position()?;
+        let stream_size = stream.seek(SeekFrom::End(0))? - start;
+        stream.seek(SeekFrom::Start(start))?;
+
+        let magic_numbers = CONTEXT.field(&mut stream, "magic_numbers", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+
+        if magic_numbers != U8_MAGIC_NUMBERS {
+            return Err(U8ArchiveError::InvalidMagicNumbers(magic_numbers));
+        }
+
+        let root_node_offset = CONTEXT.field(&mut stream, "root_node_offset", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+        let nodes_size =
+            CONTEXT.field(&mut stream, "nodes_size", |stream| stream.read_u32::<BE>())?;
+
+        stream.seek(SeekFrom::Start(start + root_node_offset as u64))?;
+        let root_node = read_raw_node(&mut stream)?;
+
+        let number_of_nodes = root_node.size as u64;
+
+        if root_node.kind != 1 || number_of_nodes == 0 {
+            return Err(U8ArchiveError::InvalidRootNode);
+        }
+
+        if number_of_nodes * Self::NODE_SIZE > nodes_size as u64 {
+            return Err(U8ArchiveError::NodesOutOfBounds(number_of_nodes));
+        }
+
+        if root_node_offset as u64 + nodes_size as u64 > stream_size {
+            return Err(U8ArchiveError::NodesOutOfBounds(number_of_nodes));
+        }
+
+        let mut raw_nodes = vec![root_node];
+
+        for _ in 1..number_of_nodes {
+            raw_nodes.push(read_raw_node(&mut stream)?);
+        }
+
+        let string_table_size = nodes_size as u64 - number_of_nodes * Self::NODE_SIZE;
+
+        let mut string_table = vec![0; string_table_size as usize];
+        CONTEXT.field(&mut stream, "string_table", |stream| {
+            stream.read_exact(&mut string_table)
+        })?;
+
+        let mut nodes = Vec::with_capacity(raw_nodes.len());
+
+        for (i, raw_node) in raw_nodes.into_iter().enumerate() {
+            let name_bytes = string_table
+                .get(raw_node.name_offset as usize..)
+                .ok_or(U8ArchiveError::InvalidNameOffset(raw_node.name_offset))?;
+            let name_length = name_bytes
+                .iter()
+                .position(|&byte| byte == 0)
+                .ok_or(U8ArchiveError::InvalidNameOffset(raw_node.name_offset))?;
+
+            let name = String::from_utf8(name_bytes[..name_length].to_vec())
+                .map_err(|_| U8ArchiveError::InvalidName(i))?;
+
+            let kind = match raw_node.kind {
+                0 => {
+                    if raw_node.offset as u64 + raw_node.size as u64 > stream_size {
+                        return Err(U8ArchiveError::FileOutOfBounds(name));
+                    }
+
+                    stream.seek(SeekFrom::Start(start + raw_node.offset as u64))?;
+
+                    let mut data = vec![0; raw_node.size as usize];
+                    CONTEXT.field(&mut stream, "file_data", |stream| {
+                        stream.read_exact(&mut data)
+                    })?;
+
+                    U8NodeKind::File(data)
+                }
+
+                1 => {
+                    if raw_node.size as u64 > number_of_nodes || (raw_node.size as usize) <= i {
+                        return Err(U8ArchiveError::InvalidDirectory(name));
+                    }
+
+                    U8NodeKind::Directory {
+                        parent: raw_node.offset,
+                        next: raw_node.size,
+                    }
+                }
+
+                kind => return Err(U8ArchiveError::UnknownNodeKind(kind)),
+            };
+
+            nodes.push(U8Node { name, kind });
+        }
+
+        Ok(Self { nodes })
+    }
+
+    /// Dump the U8 archive into a stream, the offsets of the nodes and files are recalculated.
+    pub fn dump<T: Write>(&self, mut stream: T) -> Result<(), U8ArchiveError> {
+        let mut string_table = vec![];
+        let mut name_offsets = Vec::with_capacity(self.nodes.len());
+
+        for node in &self.nodes {
+            name_offsets.push(u24_field(string_table.len() as u64)?);
+            string_table.extend_from_slice(node.name.as_bytes());
+            string_table.push(0);
+        }
+
+        let nodes_size = self.nodes.len() as u64 * Self::NODE_SIZE + string_table.len() as u64;
+        let data_offset =
+            util::align_to_boundary(Self::HEADER_SIZE + nodes_size, Self::DATA_ALIGNMENT);
+
+        let mut data = vec![];
+        let mut nodes = vec![];
+
+        for (node, name_offset) in self.nodes.iter().zip(name_offsets) {
+            match &node.kind {
+                U8NodeKind::File(file_data) => {
+                    let offset = data_offset + data.len() as u64;
+
+                    nodes.write_u32::<BE>(name_offset)?;
+                    nodes.write_u32::<BE>(u32_field(offset)?)?;
+                    nodes.write_u32::<BE>(u32_field(file_data.len() as u64)?)?;
+
+                    data.extend_from_slice(file_data);
+                    data.resize(
+                        util::align_to_boundary(data.len() as u64, Self::FILE_ALIGNMENT) as usize,
+                        0,
+                    );
+                }
+
+                U8NodeKind::Directory { parent, next } => {
+                    nodes.write_u32::<BE>(1 << 24 | name_offset)?;
+                    nodes.write_u32::<BE>(*parent)?;
+                    nodes.write_u32::<BE>(*next)?;
+                }
+            }
+        }
+
+        stream.write_u32::<BE>(U8_MAGIC_NUMBERS)?;
+        stream.write_u32::<BE>(Self::HEADER_SIZE as u32)?;
+        stream.write_u32::<BE>(u32_field(nodes_size)?)?;
+        stream.write_u32::<BE>(u32_field(data_offset)?)?;
+        stream.write_zeroed(16)?;
+
+        stream.write_all(&nodes)?;
+        stream.write_all(&string_table)?;
+        stream.write_zeroed((data_offset - Self::HEADER_SIZE - nodes_size) as usize)?;
+        stream.write_all(&data)?;
+
+        Ok(())
+    }
+
+    /// Get the full paths of all the files of the archive (separated by `/` and without the
+    /// name of the root directory) and the index of their nodes.
+    pub fn file_paths(&self) -> Vec<(String, usize)> {
+        let mut paths = vec![];
+
+        // Stack of the directories being walked with the index where they end
+        let mut directories: Vec<(&str, usize)> = vec![];
+
+        for (i, node) in self.nodes.iter().enumerate().skip(1) {
+            while directories.last().is_some_and(|&(_, next)| next <= i) {
+                directories.pop();
+            }
+
+            let mut path: Vec<&str> = directories.iter().map(|&(name, _)| name).collect();
+            path.push(&node.name);
+
+            match node.kind {
+                U8NodeKind::File(_) => paths.push((path.join("/"), i)),
+                U8NodeKind::Directory { next, .. } => directories.push((&node.name, next as usize)),
+            }
+        }
+
+        paths
+    }
+
+    /// Get a mutable reference to the data of the file with the given path (see
+    /// [Self::file_paths]), `None` if not found.
+    pub fn file_mut(&mut self, path: &str) -> Option<&mut Vec<u8>> {
+        let (_, index) = self
+            .file_paths()
+            .into_iter()
+            .find(|(file_path, _)| file_path == path)?;
+
+        match &mut self.nodes[index].kind {
+            U8NodeKind::File(data) => Some(data),
+            U8NodeKind::Directory { .. } => None,
+        }
+    }
+}
+
+struct RawNode {
+    kind: u8,
+    name_offset: u32,
+    offset: u32,
+    size: u32,
+}
+
+fn read_raw_node<T: Read + Seek>(stream: &mut T) -> Result<RawNode, U8ArchiveError> {
+    let kind_and_name_offset =
+        CONTEXT.field(stream, "node_name_offset", |stream| stream.read_u32::<BE>())?;
+    let offset = CONTEXT.field(stream, "node_offset", |stream| stream.read_u32::<BE>())?;
+    let size = CONTEXT.field(stream, "node_size", |stream| stream.read_u32::<BE>())?;
+
+    Ok(RawNode {
+        kind: (kind_and_name_offset >> 24) as u8,
+        name_offset: kind_and_name_offset & 0xFF_FFFF,
+        offset,
+        size,
+    })
+}
+
+fn u24_field(value: u64) -> Result<u32, U8ArchiveError> {
+    if value > 0xFF_FFFF {
+        return Err(U8ArchiveError::ArchiveTooBig);
+    }
+
+    Ok(value as u32)
+}
+
+fn u32_field(value: u64) -> Result<u32, U8ArchiveError> {
+    u32::try_from(value).map_err(|_| U8ArchiveError::ArchiveTooBig)
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum U8ArchiveError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("The given stream is not a U8 archive, invalid magic numbers: {0:#X}")]
+    InvalidMagicNumbers(u32),
+
+    #[error("The first node of the U8 archive is not a valid root directory")]
+    InvalidRootNode,
+
+    #[error("The {0} nodes of the U8 archive do not fit inside it")]
+    NodesOutOfBounds(u64),
+
+    #[error("The offset of a name is outside the string table: {0:#X}")]
+    InvalidNameOffset(u32),
+
+    #[error("The name of the node {0} is not valid UTF-8")]
+    InvalidName(usize),
+
+    #[error("Unknown kind of node: {0}")]
+    UnknownNodeKind(u8),
+
+    #[error("The data of the file {0:?} is outside the U8 archive")]
+    FileOutOfBounds(String),
+
+    #[error("The directory {0:?} has an invalid range of descendants")]
+    InvalidDirectory(String),
+
+    #[error("The U8 archive is too big to be dumped")]
+    ArchiveTooBig,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Create an archive with the layout of the content 5 of a Virtual Console title.
+    pub(crate) fn vc_archive(rom_name: &str, rom: &[u8]) -> U8Archive {
+        let directory = |name: &str, parent, next| U8Node {
+            name: name.to_string(),
+            kind: U8NodeKind::Directory { parent, next },
+        };
+        let file = |name: &str, data: &[u8]| U8Node {
+            name: name.to_string(),
+            kind: U8NodeKind::File(data.to_vec()),
+        };
+
+        U8Archive {
+            nodes: vec![
+                directory("", 0, 6),
+                directory("content", 0, 4),
+                file("config.ini", b"[Config]\n"),
+                file(rom_name, rom),
+                directory("meta", 0, 6),
+                file("banner.bin", &[0x42; 0x30]),
+            ],
+        }
+    }
+
+    #[test]
+    fn dump_and_parse() {
+        let archive = vc_archive("game.rom", &[0xAB; 0x25]);
+
+        let mut data = vec![];
+        archive.dump(&mut data).unwrap();
+
+        assert!(U8Archive::is_u8_archive(&data));
+        assert_eq!(U8Archive::new(Cursor::new(&data)).unwrap(), archive);
+
+        // Reproducible
+        let mut second_data = vec![];
+        archive.dump(&mut second_data).unwrap();
+        assert_eq!(data, second_data);
+    }
+
+    #[test]
+    fn file_paths() {
+        let mut archive = vc_archive("game.rom", &[0xAB; 0x25]);
+
+        assert_eq!(
+            archive.file_paths(),
+            [
+                ("content/config.ini".to_string(), 2),
+                ("content/game.rom".to_string(), 3),
+                ("meta/banner.bin".to_string(), 5),
+            ]
+        );
+
+        archive.file_mut("content/game.rom").unwrap().push(0xCD);
+        assert_eq!(
+            archive.nodes[3].kind,
+            U8NodeKind::File([[0xAB; 0x25].as_slice(), &[0xCD]].concat())
+        );
+        assert!(archive.file_mut("game.rom").is_none());
+    }
+
+    #[test]
+    fn parse_truncated_archive() {
+        let mut data = vec![];
+        vc_archive("game.rom", &[0xAB; 0x25])
+            .dump(&mut data)
+            .unwrap();
+
+        data.truncate(data.len() - 0x40);
+        assert!(matches!(
+            U8Archive::new(Cursor::new(&data)),
+            Err(U8ArchiveError::FileOutOfBounds(_))
+        ));
+
+        assert!(matches!(
+            U8Archive::new(Cursor::new(&[0; 0x20])),
+            Err(U8ArchiveError::InvalidMagicNumbers(0))
+        ));
+    }
+}
@@ -0,0 +1,360 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Injection of ROMs into the [Virtual Console](https://wiibrew.org/wiki/Virtual_Console)
+//! titles of the Nintendo Wii.
+//!
+//! The emulator of a Virtual Console title loads its ROM from a [U8Archive] stored in the
+//! content with index 5 (or 6 on some titles), the archive itself and the ROM inside it can be
+//! compressed with [LZ77](crate::lz77).
+
+use crate::CryptographicMethod;
+use crate::lz77::{self, LZ77_MAGIC_NUMBERS, Lz77Error};
+use crate::title_metadata::TitleMetadataError;
+use crate::u8_archive::{U8Archive, U8ArchiveError};
+use crate::wad::installable::InstallableWadError;
+use crate::wad::{Wad, WadError};
+use std::io::{Cursor, Read, Seek, Write};
+use thiserror::Error;
+
+/// Indexes of the contents where the ROM is searched, in order.
+pub const ROM_CONTENT_INDEXES: [u16; 2] = [5, 6];
+
+/// Extensions (case insensitive) of the files detected as ROMs when no path is given.
+pub const ROM_EXTENSIONS: &[&str] = &[
+    "nes", "rom", "sfc", "smc", "z64", "n64", "v64", "gen", "md", "sgd", "pce", "sms", "gg",
+];
+
+// Name of the ROMs of the Nintendo 64 titles compressed with the unsupported romc format
+const ROMC_NAME: &str = "romc";
+
+/// Options of [swap_rom].
+#[derive(Debug, Clone)]
+pub struct SwapRomOptions {
+    /// Path of the ROM inside the U8 archive (see [U8Archive::file_paths]), if `None` the first
+    /// file named `rom` or with one of the [ROM_EXTENSIONS] is used.
+    pub rom_path: Option<String>,
+
+    /// The cryptographic method used to decrypt and encrypt the content.
+    pub cryptographic_method: CryptographicMethod,
+}
+
+impl Default for SwapRomOptions {
+    fn default() -> Self {
+        Self {
+            rom_path: None,
+            cryptographic_method: CryptographicMethod::Wii,
+        }
+    }
+}
+
+/// Where the ROM replaced by [swap_rom] was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapRomReport {
+    /// Index of the content with the U8 archive.
+    pub content_index: u16,
+
+    /// Path of the ROM inside the U8 archive.
+    pub rom_path: String,
+
+    /// If the ROM was stored compressed with LZ77 (prefixed by its magic numbers), the new one
+    /// is compressed too.
+    pub compressed_rom: bool,
+
+    /// If the whole U8 archive was stored compressed with LZ77 (it is compressed again).
+    pub compressed_archive: bool,
+}
+
+/// Replace the ROM of the Virtual Console title inside the installable WAD stream.
+///
+/// The ROM is searched in the U8 archives of the contents with the [ROM_CONTENT_INDEXES], the
+/// new ROM (and the archive) are compressed with LZ77 if the original ones were. The content
+/// entry of the title metadata is updated and the title metadata fakesigned, so the title can
+/// only be installed on a console running an IOS vulnerable to the
+/// [Trucha bug](https://wiibrew.org/wiki/Signing_bug).
+pub fn swap_rom<T: Read + Write + Seek>(
+    mut wad_stream: T,
+    new_rom: &[u8],
+    options: &SwapRomOptions,
+) -> Result<SwapRomReport, VcError> {
+    let mut wad = Wad::try_new_installable(&mut wad_stream)?;
+    let mut session = wad.edit_session(&mut wad_stream)?;
+
+    for content_index in ROM_CONTENT_INDEXES {
+        let has_content = session
+            .title_metadata
+            .content_chunk_entries
+            .iter()
+            .any(|entry| entry.index == content_index);
+
+        if !has_content {
+            continue;
+        }
+
+        let content_selector = session.title_metadata.select_with_index(content_index);
+        let data = session.decrypted_content(content_selector, options.cryptographic_method)?;
+
+        let compressed_archive = !U8Archive::is_u8_archive(&data) && lz77::is_compressed(&data);
+        let archive_magic_numbers = data.starts_with(&LZ77_MAGIC_NUMBERS);
+
+        let data = if compressed_archive {
+            lz77::decompress(&data)?
+        } else {
+            data
+        };
+
+        if !U8Archive::is_u8_archive(&data) {
+            continue;
+        }
+
+        let mut archive = U8Archive::new(Cursor::new(&data))?;
+
+        let Some(rom_path) = find_rom(&archive, options) else {
+            continue;
+        };
+
+        if file_name(&rom_path).eq_ignore_ascii_case(ROMC_NAME) {
+            return Err(VcError::UnsupportedRomCompression(rom_path));
+        }
+
+        let rom = archive
+            .file_mut(&rom_path)
+            .ok_or_else(|| VcError::RomNotFound(options.rom_path.clone()))?;
+
+        let compressed_rom = rom.starts_with(&LZ77_MAGIC_NUMBERS);
+
+        *rom = if compressed_rom {
+            [LZ77_MAGIC_NUMBERS.as_slice(), &lz77::compress(new_rom)?].concat()
+        } else {
+            new_rom.to_vec()
+        };
+
+        let mut data = vec![];
+        archive.dump(&mut data)?;
+
+        if compressed_archive {
+            data = lz77::compress(&data)?;
+
+            if archive_magic_numbers {
+                data.splice(0..0, LZ77_MAGIC_NUMBERS);
+            }
+        }
+
+        session.replace_content(content_selector, &data, options.cryptographic_method)?;
+        session.title_metadata.fakesign()?;
+
+        session.flush()?;
+
+        return Ok(SwapRomReport {
+            content_index,
+            rom_path,
+            compressed_rom,
+            compressed_archive,
+        });
+    }
+
+    Err(VcError::RomNotFound(options.rom_path.clone()))
+}
+
+fn find_rom(archive: &U8Archive, options: &SwapRomOptions) -> Option<String> {
+    let mut paths = archive.file_paths().into_iter().map(|(path, _)| path);
+
+    if let Some(rom_path) = &options.rom_path {
+        return paths.find(|path| path == rom_path);
+    }
+
+    paths.find(|path| {
+        let name = file_name(path);
+
+        name.eq_ignore_ascii_case("rom")
+            || name.eq_ignore_ascii_case(ROMC_NAME)
+            || name.rsplit_once('.').is_some_and(|(_, extension)| {
+                ROM_EXTENSIONS
+                    .iter()
+                    .any(|rom_extension| extension.eq_ignore_ascii_case(rom_extension))
+            })
+    })
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum VcError {
+    #[error("An error has occurred while parsing the WAD: {0}")]
+    WadError(#[from] WadError),
+
+    #[error("An error has occurred while editing the WAD: {0}")]
+    InstallableWadError(#[from] InstallableWadError),
+
+    #[error("An error has occurred while fakesigning the title metadata: {0}")]
+    TitleMetadataError(#[from] TitleMetadataError),
+
+    #[error("An error has occurred while parsing the U8 archive: {0}")]
+    U8ArchiveError(#[from] U8ArchiveError),
+
+    #[error("An error has occurred while (de)compressing LZ77 data: {0}")]
+    Lz77Error(#[from] Lz77Error),
+
+    #[error("No ROM found in the contents 5 and 6 of the title (searched path: {0:?})")]
+    RomNotFound(Option<String>),
+
+    #[error("The ROM {0:?} uses the romc compression, which is not supported")]
+    UnsupportedRomCompression(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::u8_archive::U8NodeKind;
+    use crate::u8_archive::tests::vc_archive;
+    use crate::wad::installable::test_wad::{self, TestWad, TestWadBuilder};
+    use sha1::{Digest, Sha1};
+
+    fn build_vc_wad(content: Vec<u8>) -> TestWad {
+        TestWadBuilder::new()
+            .certificate_chain(test_wad::certificate_chain(3))
+            .content(0, vec![0x11; 0x40])
+            .content(5, content)
+            .build()
+    }
+
+    fn parsed_content(wad_stream: &mut Cursor<Vec<u8>>, index: u16) -> Vec<u8> {
+        wad_stream.set_position(0);
+
+        let mut wad = Wad::try_new_installable(&mut *wad_stream).unwrap();
+        let session = wad.edit_session(&mut *wad_stream).unwrap();
+
+        session
+            .decrypted_content(
+                session.title_metadata.select_with_index(index),
+                CryptographicMethod::Wii,
+            )
+            .unwrap()
+    }
+
+    fn rom_of(archive_data: &[u8], path: &str) -> Vec<u8> {
+        let mut archive = U8Archive::new(Cursor::new(archive_data)).unwrap();
+
+        archive.file_mut(path).unwrap().clone()
+    }
+
+    #[test]
+    fn swap_uncompressed_rom() {
+        let mut archive_data = vec![];
+        vc_archive("game.nes", b"NES\x1A old ROM")
+            .dump(&mut archive_data)
+            .unwrap();
+
+        let TestWad { mut stream, .. } = build_vc_wad(archive_data);
+
+        let report = swap_rom(&mut stream, b"NES\x1A new ROM", &SwapRomOptions::default()).unwrap();
+
+        assert_eq!(
+            report,
+            SwapRomReport {
+                content_index: 5,
+                rom_path: "content/game.nes".to_string(),
+                compressed_rom: false,
+                compressed_archive: false,
+            }
+        );
+
+        let content = parsed_content(&mut stream, 5);
+        assert_eq!(rom_of(&content, "content/game.nes"), b"NES\x1A new ROM");
+
+        // The other contents are kept untouched
+        assert_eq!(parsed_content(&mut stream, 0), [0x11; 0x40]);
+
+        // The title metadata is fakesigned
+        stream.set_position(0);
+        let wad = Wad::try_new_installable(&mut stream).unwrap();
+        let title_metadata = wad.title_metadata(&mut stream).unwrap();
+
+        let mut title_metadata_data = Cursor::new(vec![]);
+        title_metadata.dump(&mut title_metadata_data).unwrap();
+
+        let signed_data_offset = title_metadata.signed_blob_header.size() as usize - 64;
+        assert_eq!(
+            Sha1::digest(&title_metadata_data.get_ref()[signed_data_offset..])[0],
+            0
+        );
+    }
+
+    #[test]
+    fn swap_compressed_rom() {
+        let old_rom = [
+            LZ77_MAGIC_NUMBERS.as_slice(),
+            &lz77::compress(&[0; 0x100]).unwrap(),
+        ]
+        .concat();
+        let new_rom = [0x42; 0x200];
+
+        let mut archive_data = vec![];
+        vc_archive("JADE.SGD", &old_rom)
+            .dump(&mut archive_data)
+            .unwrap();
+
+        // The whole archive is compressed too
+        let TestWad { mut stream, .. } = build_vc_wad(lz77::compress(&archive_data).unwrap());
+
+        let report = swap_rom(&mut stream, &new_rom, &SwapRomOptions::default()).unwrap();
+        assert!(report.compressed_rom);
+        assert!(report.compressed_archive);
+
+        let content = lz77::decompress(&parsed_content(&mut stream, 5)).unwrap();
+        let rom = rom_of(&content, "content/JADE.SGD");
+
+        assert!(rom.starts_with(&LZ77_MAGIC_NUMBERS));
+        assert_eq!(lz77::decompress(&rom).unwrap(), new_rom);
+    }
+
+    #[test]
+    fn swap_rom_with_path() {
+        let mut archive = vc_archive("game.nes", b"NES\x1A old ROM");
+        archive.nodes[2].kind = U8NodeKind::File(b"custom ROM".to_vec());
+
+        let mut archive_data = vec![];
+        archive.dump(&mut archive_data).unwrap();
+
+        let TestWad { mut stream, .. } = build_vc_wad(archive_data);
+
+        let options = SwapRomOptions {
+            rom_path: Some("content/config.ini".to_string()),
+            ..SwapRomOptions::default()
+        };
+
+        swap_rom(&mut stream, b"new ROM", &options).unwrap();
+
+        let content = parsed_content(&mut stream, 5);
+        assert_eq!(rom_of(&content, "content/config.ini"), b"new ROM");
+        assert_eq!(rom_of(&content, "content/game.nes"), b"NES\x1A old ROM");
+    }
+
+    #[test]
+    fn swap_rom_errors() {
+        let mut archive_data = vec![];
+        vc_archive("romc", &[0; 0x10])
+            .dump(&mut archive_data)
+            .unwrap();
+
+        let TestWad { mut stream, .. } = build_vc_wad(archive_data);
+
+        assert!(matches!(
+            swap_rom(&mut stream, &[0; 0x10], &SwapRomOptions::default()),
+            Err(VcError::UnsupportedRomCompression(path)) if path == "content/romc"
+        ));
+
+        let TestWad { mut stream, .. } = build_vc_wad(vec![0; 0x40]);
+
+        assert!(matches!(
+            swap_rom(&mut stream, &[0; 0x10], &SwapRomOptions::default()),
+            Err(VcError::RomNotFound(None))
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the version lists published by the update server of the Wii U (`tagaya`),
+//! XML documents with the latest version of every title updated by the server:
+//!
+//! ```xml
+//! <version_list format="1">
+//!   <version>1234</version>
+//!   <titles>
+//!     <title><id>0005000E10101D00</id><version>48</version></title>
+//!   </titles>
+//! </version_list>
+//! ```
+//!
+//! The number of the current list is found on a smaller document, see [VersionListInfo]. The Wii
+//! has no equivalent list, the latest version of a title is the one of the title metadata
+//! downloaded from the NUS without specifying a version.
+
+use crate::title_id::TitleId;
+use crate::title_version::TitleVersion;
+use std::io::{self, Read};
+use thiserror::Error;
+
+/// The latest versions of the titles updated by the server on a version list.
+#[derive(Debug)]
+pub struct VersionList {
+    /// The number of the list, increased every time the server updates a title.
+    pub version: u32,
+
+    /// The titles of the list, in the order they are stored.
+    pub entries: Vec<VersionListEntry>,
+}
+
+/// The latest version of a title stored on a [VersionList].
+#[derive(Debug)]
+pub struct VersionListEntry {
+    /// The ID of the title.
+    pub title_id: TitleId,
+
+    /// The latest version of the title.
+    pub title_version: TitleVersion,
+}
+
+impl VersionList {
+    /// Create a new [VersionList] by parsing a stream.
+    pub fn new<T: Read>(mut stream: T) -> Result<Self, VersionListError> {
+        let mut xml = String::new();
+        stream.read_to_string(&mut xml)?;
+
+        let xml = element(&xml, "version_list")
+            .ok_or(VersionListError::MissingElement("version_list"))?;
+
+        // The titles also have a version, only search the one of the list before them
+        let (header, titles) = xml.split_once("<titles>").unwrap_or((xml, ""));
+
+        let version = parse_element(header, "version", |value| value.parse().ok())?;
+
+        let mut entries = vec![];
+
+        for title in elements(titles, "title") {
+            let title_id = parse_element(title, "id", |value| {
+                u64::from_str_radix(value, 16).ok().map(TitleId::new)
+            })?;
+
+            let title_version = parse_element(title, "version", |value| {
+                value.parse().ok().map(TitleVersion::new)
+            })?;
+
+            entries.push(VersionListEntry {
+                title_id,
+                title_version,
+            });
+        }
+
+        Ok(Self { version, entries })
+    }
+
+    /// Get the latest version of a title, `None` if the title is not on the list.
+    pub fn latest_version(&self, title_id: &TitleId) -> Option<TitleVersion> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.title_id.inner() == title_id.inner())
+            .map(|entry| entry.title_version)
+            .max()
+    }
+}
+
+/// The number of the current [VersionList] of the server and the host where it is stored,
+/// downloaded from [Self::URL]:
+///
+/// ```xml
+/// <version_list_info>
+///   <version>1234</version>
+///   <fqdn>tagaya-wup.cdn.nintendo.net</fqdn>
+/// </version_list_info>
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionListInfo {
+    /// The number of the current list.
+    pub version: u32,
+
+    /// The host where the lists are stored.
+    pub fqdn: String,
+}
+
+impl VersionListInfo {
+    /// The URL of the info of the current list.
+    pub const URL: &str =
+        "https://tagaya.wup.shop.nintendo.net/tagaya/versionlist/EUR/EU/latest_version";
+
+    /// Create a new [VersionListInfo] by parsing a stream.
+    pub fn new<T: Read>(mut stream: T) -> Result<Self, VersionListError> {
+        let mut xml = String::new();
+        stream.read_to_string(&mut xml)?;
+
+        let xml = element(&xml, "version_list_info")
+            .ok_or(VersionListError::MissingElement("version_list_info"))?;
+
+        Ok(Self {
+            version: parse_element(xml, "version", |value| value.parse().ok())?,
+            fqdn: parse_element(xml, "fqdn", |value| Some(value.to_string()))?,
+        })
+    }
+
+    /// Get the URL of the version list with the given number, usually [Self::version] to get
+    /// the current one.
+    pub fn list_url(&self, version: u32) -> String {
+        format!(
+            "https://{}/tagaya/versionlist/EUR/EU/list/{version}.versionlist",
+            self.fqdn
+        )
+    }
+}
+
+/// Iterate the text inside every `<tag>...</tag>` element of the XML, the elements cannot have
+/// attributes nor be nested inside an element with the same tag.
+fn elements<'a>(mut xml: &'a str, tag: &str) -> impl Iterator<Item = &'a str> {
+    let start_tag = format!("<{tag}>");
+    let end_tag = format!("</{tag}>");
+
+    std::iter::from_fn(move || {
+        let start = xml.find(&start_tag)? + start_tag.len();
+        let len = xml[start..].find(&end_tag)?;
+
+        let text = &xml[start..start + len];
+        xml = &xml[start + len + end_tag.len()..];
+
+        Some(text)
+    })
+}
+
+/// Get the text inside the first element of the XML with the given tag, its attributes are
+/// ignored.
+fn element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start_tag = xml.find(&format!("<{tag}"))?;
+    let start = start_tag + xml[start_tag..].find('>')? + 1;
+    let len = xml[start..].find(&format!("</{tag}>"))?;
+
+    Some(&xml[start..start + len])
+}
+
+fn parse_element<T>(
+    xml: &str,
+    tag: &'static str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Result<T, VersionListError> {
+    let text = elements(xml, tag)
+        .next()
+        .ok_or(VersionListError::MissingElement(tag))?
+        .trim();
+
+    parse(text).ok_or_else(|| VersionListError::InvalidValue(tag, text.to_string()))
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum VersionListError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("The element `{0}` is missing")]
+    MissingElement(&'static str),
+
+    #[error("Invalid value of the element `{0}`: {1:?}")]
+    InvalidValue(&'static str, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn parse_version_list() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+            <version_list format="1">
+                <version>1234</version>
+                <titles>
+                    <title><id>0005000E10101D00</id><version>48</version></title>
+                    <title><id>0005000E1010EC00</id><version>32</version></title>
+                    <title><id>0005000E10101D00</id><version>80</version></title>
+                </titles>
+            </version_list>"#;
+
+        let version_list = VersionList::new(Cursor::new(xml)).unwrap();
+
+        assert_eq!(version_list.version, 1234);
+        assert_eq!(version_list.entries.len(), 3);
+        assert_eq!(
+            version_list.latest_version(&TitleId::new(0x0005000E_10101D00)),
+            Some(TitleVersion::new(80))
+        );
+        assert_eq!(
+            version_list.latest_version(&TitleId::new(0x0005000E_1010EC00)),
+            Some(TitleVersion::new(32))
+        );
+        assert_eq!(
+            version_list.latest_version(&TitleId::new(0x0005000E_00000000)),
+            None
+        );
+
+        assert!(matches!(
+            VersionList::new(Cursor::new(xml.replace("<id>0005000E1010EC00", "<id>HAGA"))),
+            Err(VersionListError::InvalidValue("id", _))
+        ));
+        assert!(matches!(
+            VersionList::new(Cursor::new("<titles></titles>")),
+            Err(VersionListError::MissingElement("version_list"))
+        ));
+    }
+
+    #[test]
+    fn parse_version_list_info() {
+        let xml = "<version_list_info><version>1234</version>\
+            <fqdn>tagaya-wup.cdn.nintendo.net</fqdn></version_list_info>";
+
+        let info = VersionListInfo::new(Cursor::new(xml)).unwrap();
+
+        assert_eq!(
+            info,
+            VersionListInfo {
+                version: 1234,
+                fqdn: String::from("tagaya-wup.cdn.nintendo.net"),
+            }
+        );
+        assert_eq!(
+            info.list_url(info.version),
+            "https://tagaya-wup.cdn.nintendo.net/tagaya/versionlist/EUR/EU/list/1234.versionlist"
+        );
+    }
+}
@@ -0,0 +1,512 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Read-only virtual filesystem over the packages of titles, so frontends (file browsers, FUSE
+//! mounts, etc) can explore them with paths instead of knowing each format:
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::Read;
+//! use zelzip_niiebla::vfs::{Vfs, WadVfs};
+//!
+//! # fn main() -> Result<(), zelzip_niiebla::vfs::VfsError> {
+//! let mut vfs = WadVfs::new(File::open("channel.wad")?)?;
+//!
+//! for entry in vfs.read_dir("/contents")? {
+//!     println!("{} ({} bytes)", entry.name, entry.size);
+//! }
+//!
+//! let mut content = vec![];
+//! vfs.open("/contents/0")?.read_to_end(&mut content)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Installable WADs and CIAs are supported, see [WadVfs] and [CiaVfs] for their layouts.
+
+use crate::CryptographicMethod;
+use crate::TitleMetadata;
+#[cfg(feature = "cia")]
+use crate::cia::header::CiaHeaderError;
+use crate::ticket::{PreSwitchTicket, PreSwitchTicketError};
+use crate::title_metadata::TitleMetadataError;
+use crate::wad::installable::{InstallableWad, InstallableWadError};
+use crate::wad::{Wad, WadError};
+use std::io::{self, Read, Seek, SeekFrom};
+use thiserror::Error;
+use util::View;
+
+#[cfg(feature = "cia")]
+mod cia;
+
+#[cfg(feature = "cia")]
+pub use cia::CiaVfs;
+
+/// A read-only filesystem, the paths are absolute and separated by `/` (`/contents/0`), the
+/// trailing and repeated separators are ignored.
+pub trait Vfs {
+    /// Get the entry at the given path.
+    fn metadata(&mut self, path: &str) -> Result<VfsEntry, VfsError>;
+
+    /// List the entries of the directory at the given path.
+    fn read_dir(&mut self, path: &str) -> Result<Vec<VfsEntry>, VfsError>;
+
+    /// Read the data of the file at the given path starting at `offset`. Returns the number of
+    /// read bytes, zero after the end of the file.
+    fn read_at(&mut self, path: &str, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError>;
+
+    /// Open the file at the given path as a stream.
+    fn open(&mut self, path: &str) -> Result<VfsFile<'_, Self>, VfsError>
+    where
+        Self: Sized,
+    {
+        let entry = self.metadata(path)?;
+
+        if entry.kind == VfsEntryKind::Directory {
+            return Err(VfsError::IsADirectory(path.to_string()));
+        }
+
+        Ok(VfsFile {
+            vfs: self,
+            path: path.to_string(),
+            size: entry.size,
+            position: 0,
+        })
+    }
+}
+
+/// A file or directory of a [Vfs].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VfsEntry {
+    /// The name of the entry, empty for the root directory.
+    pub name: String,
+
+    /// Either if the entry is a file or a directory.
+    pub kind: VfsEntryKind,
+
+    /// The size of the data of a file in bytes, zero for directories.
+    pub size: u64,
+}
+
+impl VfsEntry {
+    fn file(name: &str, size: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: VfsEntryKind::File,
+            size,
+        }
+    }
+
+    fn directory(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: VfsEntryKind::Directory,
+            size: 0,
+        }
+    }
+}
+
+/// The kind of a [VfsEntry].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VfsEntryKind {
+    /// A file with data.
+    File,
+
+    /// A directory with other entries.
+    Directory,
+}
+
+/// A file opened with [Vfs::open], read with [Vfs::read_at].
+pub struct VfsFile<'a, V: Vfs> {
+    vfs: &'a mut V,
+    path: String,
+    size: u64,
+    position: u64,
+}
+
+impl<V: Vfs> Read for VfsFile<'_, V> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read_bytes = self
+            .vfs
+            .read_at(&self.path, self.position, buf)
+            .map_err(io::Error::other)?;
+
+        self.position += read_bytes as u64;
+
+        Ok(read_bytes)
+    }
+}
+
+impl<V: Vfs> Seek for VfsFile<'_, V> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.position)
+    }
+}
+
+/// A node of the layout of a [WadVfs].
+#[derive(Debug, Clone, Copy)]
+enum WadNode {
+    Root,
+    Contents,
+    CertificateChain,
+    Ticket,
+    TitleMetadata,
+    Footer,
+    Content(usize),
+}
+
+/// [Vfs] over an installable WAD, with the layout:
+///
+/// - `/cert.bin`: the certificate chain.
+/// - `/ticket.tik`: the ticket.
+/// - `/tmd.bin`: the title metadata.
+/// - `/footer.bin`: the footer, only if the WAD has one.
+/// - `/contents/<index>`: the decrypted data of each content, named with its index in decimal.
+///
+/// The names of the files are the same as the ones of [InstallableWad::split].
+pub struct WadVfs<T: Read + Seek> {
+    stream: T,
+    wad: InstallableWad,
+    ticket: PreSwitchTicket,
+    title_metadata: TitleMetadata,
+    cryptographic_method: CryptographicMethod,
+}
+
+impl<T: Read + Seek> WadVfs<T> {
+    /// Name of the directory with the contents.
+    pub const CONTENTS_DIRECTORY_NAME: &str = "contents";
+
+    /// Create a new [WadVfs] by parsing the stream of a WAD, its contents are decrypted with
+    /// [CryptographicMethod::Wii].
+    pub fn new(stream: T) -> Result<Self, VfsError> {
+        Self::new_with_cryptographic_method(stream, CryptographicMethod::Wii)
+    }
+
+    /// Like [Self::new] but decrypting the contents with the given [CryptographicMethod] (like
+    /// the one of the DSi).
+    pub fn new_with_cryptographic_method(
+        mut stream: T,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<Self, VfsError> {
+        let wad = Wad::try_new_installable(&mut stream)?;
+        let ticket = wad.ticket(&mut stream).map_err(InstallableWadError::from)?;
+        let title_metadata = wad
+            .title_metadata(&mut stream)
+            .map_err(InstallableWadError::from)?;
+
+        Ok(Self {
+            stream,
+            wad,
+            ticket,
+            title_metadata,
+            cryptographic_method,
+        })
+    }
+
+    /// Get the parsed WAD.
+    pub fn wad(&self) -> &InstallableWad {
+        &self.wad
+    }
+
+    /// Get the parsed title metadata of the WAD.
+    pub fn title_metadata(&self) -> &TitleMetadata {
+        &self.title_metadata
+    }
+
+    /// Consume the filesystem and get back the stream of the WAD.
+    pub fn into_inner(self) -> T {
+        self.stream
+    }
+
+    fn resolve(&self, path: &str) -> Result<WadNode, VfsError> {
+        let components: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+
+        let node = match components[..] {
+            [] => Some(WadNode::Root),
+            [Self::CONTENTS_DIRECTORY_NAME] => Some(WadNode::Contents),
+            [InstallableWad::SPLIT_CERTIFICATE_CHAIN_FILE_NAME] => Some(WadNode::CertificateChain),
+            [InstallableWad::SPLIT_TICKET_FILE_NAME] => Some(WadNode::Ticket),
+            [InstallableWad::SPLIT_TITLE_METADATA_FILE_NAME] => Some(WadNode::TitleMetadata),
+            [InstallableWad::SPLIT_FOOTER_FILE_NAME] if self.wad.footer_size > 0 => {
+                Some(WadNode::Footer)
+            }
+            [Self::CONTENTS_DIRECTORY_NAME, name] => name
+                .parse::<u16>()
+                .ok()
+                // Reject aliases like `00` or `+0`
+                .filter(|index| index.to_string() == name)
+                .and_then(|index| {
+                    self.title_metadata
+                        .content_chunk_entries
+                        .iter()
+                        .position(|entry| entry.index == index)
+                })
+                .map(WadNode::Content),
+            _ => None,
+        };
+
+        node.ok_or_else(|| VfsError::NotFound(path.to_string()))
+    }
+
+    fn entry(&self, node: WadNode) -> VfsEntry {
+        match node {
+            WadNode::Root => VfsEntry::directory(""),
+            WadNode::Contents => VfsEntry::directory(Self::CONTENTS_DIRECTORY_NAME),
+            WadNode::CertificateChain => VfsEntry::file(
+                InstallableWad::SPLIT_CERTIFICATE_CHAIN_FILE_NAME,
+                self.wad.certificate_chain_size.into(),
+            ),
+            WadNode::Ticket => VfsEntry::file(
+                InstallableWad::SPLIT_TICKET_FILE_NAME,
+                self.wad.ticket_size.into(),
+            ),
+            WadNode::TitleMetadata => VfsEntry::file(
+                InstallableWad::SPLIT_TITLE_METADATA_FILE_NAME,
+                self.wad.title_metadata_size.into(),
+            ),
+            WadNode::Footer => VfsEntry::file(
+                InstallableWad::SPLIT_FOOTER_FILE_NAME,
+                self.wad.footer_size.into(),
+            ),
+            WadNode::Content(physical_position) => {
+                let content_entry = &self.title_metadata.content_chunk_entries[physical_position];
+                VfsEntry::file(&content_entry.index.to_string(), content_entry.size)
+            }
+        }
+    }
+
+    /// Seek the stream to the start of a section stored as is.
+    fn seek_section(&mut self, node: WadNode) -> Result<(), InstallableWadError> {
+        match node {
+            WadNode::CertificateChain => self.wad.seek_certificate_chain(&mut self.stream)?,
+            WadNode::Ticket => self.wad.seek_ticket(&mut self.stream)?,
+            WadNode::TitleMetadata => self.wad.seek_title_metadata(&mut self.stream)?,
+            WadNode::Footer => self
+                .wad
+                .seek_footer(&mut self.stream, &self.title_metadata)?,
+            WadNode::Root | WadNode::Contents | WadNode::Content(_) => (),
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Read + Seek> Vfs for WadVfs<T> {
+    fn metadata(&mut self, path: &str) -> Result<VfsEntry, VfsError> {
+        let node = self.resolve(path)?;
+        Ok(self.entry(node))
+    }
+
+    fn read_dir(&mut self, path: &str) -> Result<Vec<VfsEntry>, VfsError> {
+        let nodes = match self.resolve(path)? {
+            WadNode::Root => {
+                let mut nodes = vec![
+                    WadNode::CertificateChain,
+                    WadNode::Ticket,
+                    WadNode::TitleMetadata,
+                ];
+
+                if self.wad.footer_size > 0 {
+                    nodes.push(WadNode::Footer);
+                }
+
+                nodes.push(WadNode::Contents);
+                nodes
+            }
+
+            WadNode::Contents => (0..self.title_metadata.content_chunk_entries.len())
+                .map(WadNode::Content)
+                .collect(),
+
+            _ => return Err(VfsError::NotADirectory(path.to_string())),
+        };
+
+        Ok(nodes.into_iter().map(|node| self.entry(node)).collect())
+    }
+
+    fn read_at(&mut self, path: &str, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+        let node = self.resolve(path)?;
+        let entry = self.entry(node);
+
+        if entry.kind == VfsEntryKind::Directory {
+            return Err(VfsError::IsADirectory(path.to_string()));
+        }
+
+        if offset >= entry.size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let len = (entry.size - offset).min(buf.len() as u64) as usize;
+        let buf = &mut buf[..len];
+
+        if let WadNode::Content(physical_position) = node {
+            let selector = self
+                .title_metadata
+                .select_with_physical_position(physical_position);
+
+            // The whole last block is needed to decrypt the end of an unaligned content
+            self.wad
+                .seek_content(&mut self.stream, &self.title_metadata, selector)?;
+            let encrypted_content_view = View::new(
+                &mut self.stream,
+                util::align_to_boundary(entry.size, 16) as usize,
+            )?;
+
+            let mut decrypted_content_view = self
+                .ticket
+                .cryptographic_stream(
+                    encrypted_content_view,
+                    &self.title_metadata,
+                    selector,
+                    self.cryptographic_method,
+                )
+                .map_err(InstallableWadError::from)?;
+
+            decrypted_content_view.seek(SeekFrom::Start(offset))?;
+            decrypted_content_view.read_exact(buf)?;
+        } else {
+            self.seek_section(node)?;
+            self.stream.seek(SeekFrom::Current(offset as i64))?;
+            self.stream.read_exact(buf)?;
+        }
+
+        Ok(len)
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum VfsError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("No such file or directory: {0:?}")]
+    NotFound(String),
+
+    #[error("Not a directory: {0:?}")]
+    NotADirectory(String),
+
+    #[error("Is a directory: {0:?}")]
+    IsADirectory(String),
+
+    #[error("WAD error: {0}")]
+    Wad(#[from] WadError),
+
+    #[error("Installable WAD error: {0}")]
+    InstallableWad(#[from] InstallableWadError),
+
+    #[cfg(feature = "cia")]
+    #[error("CIA header error: {0}")]
+    CiaHeader(#[from] CiaHeaderError),
+
+    #[error("Ticket error: {0}")]
+    Ticket(#[from] PreSwitchTicketError),
+
+    #[error("Title metadata error: {0}")]
+    TitleMetadata(#[from] TitleMetadataError),
+
+    #[error("Invalid layout of the package: {0}")]
+    InvalidLayout(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title_metadata::TitleMetadataContentEntryKind;
+    use crate::wad::installable::test_wad::{self, TestWad, TestWadBuilder};
+    use std::io::Cursor;
+
+    #[test]
+    fn browse_wad() {
+        let plaintexts: [Vec<u8>; 2] = [
+            (0..0x25).collect(),
+            (0..0x1234).map(|i| (i * 7) as u8).collect(),
+        ];
+
+        let mut builder = TestWadBuilder::new();
+
+        for ((id, index), plaintext) in [(0x10, 0), (0x20, 3)].into_iter().zip(&plaintexts) {
+            builder = builder.content_with_entry(
+                test_wad::content_entry(id, index, TitleMetadataContentEntryKind::Normal, 0),
+                plaintext.clone(),
+            );
+        }
+
+        let TestWad { stream, ticket, .. } = builder.build();
+
+        let mut vfs = WadVfs::new(stream).unwrap();
+
+        let names: Vec<String> = vfs
+            .read_dir("/")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["cert.bin", "ticket.tik", "tmd.bin", "contents"]);
+
+        assert_eq!(
+            vfs.read_dir("/contents/").unwrap(),
+            [VfsEntry::file("0", 0x25), VfsEntry::file("3", 0x1234),]
+        );
+
+        let mut data = vec![];
+        vfs.open("/contents/3")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, plaintexts[1]);
+
+        // Random access into the middle of a block
+        let mut file = vfs.open("contents//3").unwrap();
+        file.seek(SeekFrom::Start(0x1001)).unwrap();
+        let mut buf = [0; 0x40];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, plaintexts[1][0x1001..0x1041]);
+
+        let mut data = vec![];
+        vfs.open("/ticket.tik")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        let mut ticket_data = vec![];
+        ticket.dump(Cursor::new(&mut ticket_data)).unwrap();
+        assert_eq!(data, ticket_data);
+
+        assert!(matches!(
+            vfs.open("/contents/1"),
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            vfs.open("/contents/03"),
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            vfs.open("/footer.bin"),
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            vfs.open("/contents"),
+            Err(VfsError::IsADirectory(_))
+        ));
+        assert!(matches!(
+            vfs.read_dir("/tmd.bin"),
+            Err(VfsError::NotADirectory(_))
+        ));
+    }
+}
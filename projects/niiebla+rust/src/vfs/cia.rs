@@ -0,0 +1,425 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of [CiaVfs], the [Vfs] over a CIA.
+
+use crate::CryptographicMethod;
+use crate::TitleMetadata;
+use crate::cia::CiaHeader;
+use crate::ticket::PreSwitchTicket;
+use crate::vfs::{Vfs, VfsEntry, VfsEntryKind, VfsError};
+use crate::wad::installable::InstallableWad;
+use std::io::{Read, Seek, SeekFrom};
+use util::View;
+
+/// A node of the layout of a [CiaVfs].
+#[derive(Debug, Clone, Copy)]
+enum CiaNode {
+    Root,
+    Contents,
+    CertificateChain,
+    Ticket,
+    TitleMetadata,
+    Meta,
+    // The physical position on the title metadata and the offset inside the CIA
+    Content(usize, u64),
+}
+
+/// [Vfs] over a CIA, with the same layout as a [WadVfs](crate::vfs::WadVfs):
+///
+/// - `/cert.bin`: the certificate chain.
+/// - `/ticket.tik`: the ticket.
+/// - `/tmd.bin`: the title metadata.
+/// - `/meta.bin`: the meta section, only if the CIA has one (see
+///   [CiaMeta](crate::cia::CiaMeta)).
+/// - `/contents/<index>`: the data of each content stored on the CIA, named with its index in
+///   decimal. Decrypted only if the filesystem was created with
+///   [Self::new_with_cryptographic_method], as it needs the keys of the 3DS.
+pub struct CiaVfs<T: Read + Seek> {
+    stream: T,
+    header: CiaHeader,
+    ticket: PreSwitchTicket,
+    title_metadata: TitleMetadata,
+    cryptographic_method: Option<CryptographicMethod>,
+
+    // The physical position on the title metadata and the offset of each stored content
+    contents: Vec<(usize, u64)>,
+}
+
+impl<T: Read + Seek> CiaVfs<T> {
+    /// Name of the directory with the contents.
+    pub const CONTENTS_DIRECTORY_NAME: &str = "contents";
+
+    /// Name of the file with the meta section.
+    pub const META_FILE_NAME: &str = "meta.bin";
+
+    /// Create a new [CiaVfs] by parsing the stream of a CIA, its contents are kept as stored
+    /// (usually encrypted).
+    pub fn new(stream: T) -> Result<Self, VfsError> {
+        Self::with_optional_cryptographic_method(stream, None)
+    }
+
+    /// Like [Self::new] but decrypting the contents with the given [CryptographicMethod] (like
+    /// the one of the 3DS).
+    pub fn new_with_cryptographic_method(
+        stream: T,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<Self, VfsError> {
+        Self::with_optional_cryptographic_method(stream, Some(cryptographic_method))
+    }
+
+    fn with_optional_cryptographic_method(
+        mut stream: T,
+        cryptographic_method: Option<CryptographicMethod>,
+    ) -> Result<Self, VfsError> {
+        let header = CiaHeader::new(&mut stream)?;
+
+        stream.seek(SeekFrom::Start(header.ticket_offset()))?;
+        let ticket = PreSwitchTicket::new(&mut stream)?;
+
+        stream.seek(SeekFrom::Start(header.title_metadata_offset()))?;
+        let title_metadata = TitleMetadata::new(&mut stream)?;
+
+        let mut contents = vec![];
+        let mut offset = header.content_offset();
+
+        for (physical_position, content_entry) in
+            title_metadata.content_chunk_entries.iter().enumerate()
+        {
+            if !header.has_content(content_entry.index) {
+                continue;
+            }
+
+            contents.push((physical_position, offset));
+
+            offset = offset
+                .checked_add(CiaHeader::align(content_entry.size))
+                .ok_or_else(|| VfsError::InvalidLayout(String::from("content sizes overflow")))?;
+        }
+
+        Ok(Self {
+            stream,
+            header,
+            ticket,
+            title_metadata,
+            cryptographic_method,
+            contents,
+        })
+    }
+
+    /// Get the parsed header of the CIA.
+    pub fn header(&self) -> &CiaHeader {
+        &self.header
+    }
+
+    /// Get the parsed title metadata of the CIA.
+    pub fn title_metadata(&self) -> &TitleMetadata {
+        &self.title_metadata
+    }
+
+    /// Consume the filesystem and get back the stream of the CIA.
+    pub fn into_inner(self) -> T {
+        self.stream
+    }
+
+    fn resolve(&self, path: &str) -> Result<CiaNode, VfsError> {
+        let components: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+
+        let node = match components[..] {
+            [] => Some(CiaNode::Root),
+            [Self::CONTENTS_DIRECTORY_NAME] => Some(CiaNode::Contents),
+            [InstallableWad::SPLIT_CERTIFICATE_CHAIN_FILE_NAME] => Some(CiaNode::CertificateChain),
+            [InstallableWad::SPLIT_TICKET_FILE_NAME] => Some(CiaNode::Ticket),
+            [InstallableWad::SPLIT_TITLE_METADATA_FILE_NAME] => Some(CiaNode::TitleMetadata),
+            [Self::META_FILE_NAME] if self.header.meta_size > 0 => Some(CiaNode::Meta),
+            [Self::CONTENTS_DIRECTORY_NAME, name] => name
+                .parse::<u16>()
+                .ok()
+                // Reject aliases like `00` or `+0`
+                .filter(|index| index.to_string() == name)
+                .and_then(|index| {
+                    self.contents.iter().find(|(physical_position, _)| {
+                        self.title_metadata.content_chunk_entries[*physical_position].index == index
+                    })
+                })
+                .map(|&(physical_position, offset)| CiaNode::Content(physical_position, offset)),
+            _ => None,
+        };
+
+        node.ok_or_else(|| VfsError::NotFound(path.to_string()))
+    }
+
+    fn entry(&self, node: CiaNode) -> VfsEntry {
+        match node {
+            CiaNode::Root => VfsEntry::directory(""),
+            CiaNode::Contents => VfsEntry::directory(Self::CONTENTS_DIRECTORY_NAME),
+            CiaNode::CertificateChain => VfsEntry::file(
+                InstallableWad::SPLIT_CERTIFICATE_CHAIN_FILE_NAME,
+                self.header.certificate_chain_size.into(),
+            ),
+            CiaNode::Ticket => VfsEntry::file(
+                InstallableWad::SPLIT_TICKET_FILE_NAME,
+                self.header.ticket_size.into(),
+            ),
+            CiaNode::TitleMetadata => VfsEntry::file(
+                InstallableWad::SPLIT_TITLE_METADATA_FILE_NAME,
+                self.header.title_metadata_size.into(),
+            ),
+            CiaNode::Meta => VfsEntry::file(Self::META_FILE_NAME, self.header.meta_size.into()),
+            CiaNode::Content(physical_position, _) => {
+                let content_entry = &self.title_metadata.content_chunk_entries[physical_position];
+                VfsEntry::file(&content_entry.index.to_string(), content_entry.size)
+            }
+        }
+    }
+
+    /// Get the offset of a node stored as a file inside the CIA.
+    fn offset(&self, node: CiaNode) -> Result<u64, VfsError> {
+        Ok(match node {
+            CiaNode::CertificateChain => self.header.certificate_chain_offset(),
+            CiaNode::Ticket => self.header.ticket_offset(),
+            CiaNode::TitleMetadata => self.header.title_metadata_offset(),
+            CiaNode::Meta => self.header.meta_offset().ok_or_else(|| {
+                VfsError::InvalidLayout(String::from("meta section out of bounds"))
+            })?,
+            CiaNode::Content(_, offset) => offset,
+            CiaNode::Root | CiaNode::Contents => 0,
+        })
+    }
+}
+
+impl<T: Read + Seek> Vfs for CiaVfs<T> {
+    fn metadata(&mut self, path: &str) -> Result<VfsEntry, VfsError> {
+        let node = self.resolve(path)?;
+        Ok(self.entry(node))
+    }
+
+    fn read_dir(&mut self, path: &str) -> Result<Vec<VfsEntry>, VfsError> {
+        let nodes = match self.resolve(path)? {
+            CiaNode::Root => {
+                let mut nodes = vec![
+                    CiaNode::CertificateChain,
+                    CiaNode::Ticket,
+                    CiaNode::TitleMetadata,
+                ];
+
+                if self.header.meta_size > 0 {
+                    nodes.push(CiaNode::Meta);
+                }
+
+                nodes.push(CiaNode::Contents);
+                nodes
+            }
+
+            CiaNode::Contents => self
+                .contents
+                .iter()
+                .map(|&(physical_position, offset)| CiaNode::Content(physical_position, offset))
+                .collect(),
+
+            _ => return Err(VfsError::NotADirectory(path.to_string())),
+        };
+
+        Ok(nodes.into_iter().map(|node| self.entry(node)).collect())
+    }
+
+    fn read_at(&mut self, path: &str, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+        let node = self.resolve(path)?;
+        let entry = self.entry(node);
+
+        if entry.kind == VfsEntryKind::Directory {
+            return Err(VfsError::IsADirectory(path.to_string()));
+        }
+
+        if offset >= entry.size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let len = (entry.size - offset).min(buf.len() as u64) as usize;
+        let buf = &mut buf[..len];
+
+        self.stream.seek(SeekFrom::Start(self.offset(node)?))?;
+
+        if let (CiaNode::Content(physical_position, _), Some(cryptographic_method)) =
+            (node, self.cryptographic_method)
+        {
+            let selector = self
+                .title_metadata
+                .select_with_physical_position(physical_position);
+
+            // The whole last block is needed to decrypt the end of an unaligned content
+            let encrypted_content_view = View::new(
+                &mut self.stream,
+                util::align_to_boundary(entry.size, 16) as usize,
+            )?;
+
+            let mut decrypted_content_view = self.ticket.cryptographic_stream(
+                encrypted_content_view,
+                &self.title_metadata,
+                selector,
+                cryptographic_method,
+            )?;
+
+            decrypted_content_view.seek(SeekFrom::Start(offset))?;
+            decrypted_content_view.read_exact(buf)?;
+        } else {
+            self.stream.seek(SeekFrom::Current(offset as i64))?;
+            self.stream.read_exact(buf)?;
+        }
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::installable::test_wad::{self, TestWad, TestWadBuilder};
+    use std::io::{Cursor, Write};
+
+    /// Build a CIA with the contents at indexes 0 and 2 out of three, and a meta section.
+    fn test_cia(plaintexts: &[Vec<u8>; 2]) -> Vec<u8> {
+        let TestWad {
+            wad,
+            mut stream,
+            ticket,
+            title_metadata,
+        } = TestWadBuilder::new()
+            .content(0, plaintexts[0].clone())
+            .content(1, [0xFF; 0x10])
+            .content(2, plaintexts[1].clone())
+            .build();
+
+        let mut certificate_chain = vec![];
+        test_wad::certificate_chain(CiaHeader::NUMBER_OF_CERTIFICATES_STORED)
+            .dump(Cursor::new(&mut certificate_chain))
+            .unwrap();
+
+        let mut ticket_data = vec![];
+        ticket.dump(Cursor::new(&mut ticket_data)).unwrap();
+
+        let mut title_metadata_data = vec![];
+        title_metadata
+            .dump(Cursor::new(&mut title_metadata_data))
+            .unwrap();
+
+        let mut contents = vec![];
+
+        for index in [0, 2] {
+            let selector = title_metadata.select_with_index(index);
+            let size = selector.content_entry(&title_metadata).unwrap().size;
+
+            // The encrypted data is padded to the AES block size
+            wad.seek_content(&mut stream, &title_metadata, selector)
+                .unwrap();
+            let mut encrypted = vec![0; util::align_to_boundary(size, 16) as usize];
+            stream.read_exact(&mut encrypted).unwrap();
+
+            encrypted.resize(CiaHeader::align(size) as usize, 0);
+            contents.extend(encrypted);
+        }
+
+        let mut header = CiaHeader {
+            certificate_chain_size: certificate_chain.len() as u32,
+            ticket_size: ticket_data.len() as u32,
+            title_metadata_size: title_metadata_data.len() as u32,
+            meta_size: 0x20,
+            content_size: contents.len() as u64,
+            ..CiaHeader::default()
+        };
+        header.set_content(0, true);
+        header.set_content(2, true);
+
+        let mut cia = vec![];
+        header.dump(&mut cia).unwrap();
+
+        for section in [
+            certificate_chain,
+            ticket_data,
+            title_metadata_data,
+            contents,
+            vec![0xEE; 0x20],
+        ] {
+            cia.resize(CiaHeader::align(cia.len() as u64) as usize, 0);
+            cia.write_all(&section).unwrap();
+        }
+
+        cia
+    }
+
+    #[test]
+    fn browse_cia() {
+        let plaintexts: [Vec<u8>; 2] = [
+            (0..0x25).collect(),
+            (0..0x1234).map(|i| (i * 7) as u8).collect(),
+        ];
+
+        let cia = test_cia(&plaintexts);
+
+        let mut vfs = CiaVfs::new_with_cryptographic_method(
+            Cursor::new(cia.clone()),
+            CryptographicMethod::Wii,
+        )
+        .unwrap();
+
+        let names: Vec<String> = vfs
+            .read_dir("/")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(
+            names,
+            ["cert.bin", "ticket.tik", "tmd.bin", "meta.bin", "contents"]
+        );
+
+        // The content at index 1 is not stored
+        assert_eq!(
+            vfs.read_dir("/contents").unwrap(),
+            [VfsEntry::file("0", 0x25), VfsEntry::file("2", 0x1234)]
+        );
+        assert!(matches!(
+            vfs.open("/contents/1"),
+            Err(VfsError::NotFound(_))
+        ));
+
+        let mut data = vec![];
+        vfs.open("/contents/2")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, plaintexts[1]);
+
+        let mut file = vfs.open("/contents/2").unwrap();
+        file.seek(SeekFrom::Start(0x1001)).unwrap();
+        let mut buf = [0; 0x40];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, plaintexts[1][0x1001..0x1041]);
+
+        let mut data = vec![];
+        vfs.open("/meta.bin")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, [0xEE; 0x20]);
+
+        // Without a cryptographic method the contents are kept encrypted
+        let mut vfs = CiaVfs::new(Cursor::new(cia)).unwrap();
+
+        let mut data = vec![];
+        vfs.open("/contents/0")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data.len(), 0x25);
+        assert_ne!(data, plaintexts[0]);
+
+        assert!(matches!(
+            CiaVfs::new(Cursor::new(vec![0; 0x40])),
+            Err(VfsError::CiaHeader(_))
+        ));
+    }
+}
@@ -6,15 +6,25 @@
 
 //! Implementation of the binary file format used by Nintendo to store titles without discs.
 
+pub mod backup;
 pub mod installable;
+mod parts;
 
+use crate::signed_blob_header::{SignedBlobHeader, SignedBlobHeaderError};
+use crate::telemetry;
+use crate::title_id::TitleId;
 use crate::wad::installable::{InstallableWad, InstallableWadError};
+use byteorder::{BE, ReadBytesExt};
+use std::fs::{File, OpenOptions};
 use std::io;
 use std::io::Read;
 use std::io::Seek;
+use std::path::Path;
 use thiserror::Error;
 
 const INSTALLABLE_WAD_MAGIC_NUMBERS: [u8; 8] = [0x00, 0x00, 0x00, 0x20, 0x49, 0x73, 0x00, 0x00];
+const BOOT2_INSTALLABLE_WAD_MAGIC_NUMBERS: [u8; 8] =
+    [0x00, 0x00, 0x00, 0x20, 0x69, 0x62, 0x00, 0x00];
 
 /// Represent the different kinds of WAD files that are known to have been used on the Nintendo
 /// Wii.
@@ -38,6 +48,9 @@ pub enum WadError {
     #[error("An error has occurred while parsing an installable Wad: {0}")]
     InstallableWadParseError(#[from] InstallableWadError),
 
+    #[error("An error has occurred while skipping a signed blob header: {0}")]
+    SignedBlobHeaderError(#[from] SignedBlobHeaderError),
+
     #[error("Unknown WAD format")]
     UnknownWadFormatError,
 
@@ -47,6 +60,23 @@ pub enum WadError {
 
 impl Wad {
     /// Create a new [Wad] by parsing a stream.
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use zelzip_niiebla::Wad;
+    /// use zelzip_niiebla::templates::{self, HOMEBREW_CHANNEL_TITLE_ID};
+    ///
+    /// # fn main() -> Result<(), zelzip_niiebla::Error> {
+    /// let wad_data = templates::homebrew_wad(HOMEBREW_CHANNEL_TITLE_ID, &[b"Homebrew channel"])?;
+    /// let mut stream = Cursor::new(wad_data);
+    ///
+    /// match Wad::new(&mut stream)? {
+    ///     Wad::Installable(wad) => assert_eq!(wad.certificate_chain_size, 0),
+    ///     _ => unreachable!("The template is an installable WAD"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, WadError> {
         let mut magic_numbers_buffer = [0; 8];
         stream.read_exact(&mut magic_numbers_buffer)?;
@@ -55,9 +85,12 @@ impl Wad {
         stream.rewind()?;
 
         match magic_numbers_buffer {
-            INSTALLABLE_WAD_MAGIC_NUMBERS => Ok(Self::Installable(unsafe {
-                InstallableWad::new(&mut stream)?
-            })),
+            INSTALLABLE_WAD_MAGIC_NUMBERS | BOOT2_INSTALLABLE_WAD_MAGIC_NUMBERS => {
+                let installable_wad = unsafe { InstallableWad::new(&mut stream)? };
+                telemetry::file_parsed("wad");
+
+                Ok(Self::Installable(installable_wad))
+            }
 
             _ => Err(WadError::UnknownWadFormatError),
         }
@@ -72,4 +105,115 @@ impl Wad {
             _ => Err(WadError::UndesiredWadFormat),
         }
     }
+
+    /// Open the installable WAD file at the given path, for both reading and writing, returning
+    /// its parsed header and the file itself (rewound to its start) to be used as its stream.
+    ///
+    /// ```no_run
+    /// use zelzip_niiebla::Wad;
+    ///
+    /// # fn main() -> Result<(), zelzip_niiebla::Error> {
+    /// let (wad, mut wad_file) = Wad::open("title.wad")?;
+    /// let ticket = wad.ticket(&mut wad_file)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<(InstallableWad, File), WadError> {
+        Self::open_with_options(path, OpenOptions::new().read(true).write(true))
+    }
+
+    /// Like [Self::open] but the file is opened only for reading.
+    pub fn open_readonly<P: AsRef<Path>>(path: P) -> Result<(InstallableWad, File), WadError> {
+        Self::open_with_options(path, OpenOptions::new().read(true))
+    }
+
+    fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<(InstallableWad, File), WadError> {
+        let mut file = options.open(path)?;
+
+        let wad = Self::try_new_installable(&mut file)?;
+        file.rewind()?;
+
+        Ok((wad, file))
+    }
+
+    /// Get the basic data of an installable WAD by reading only its header and a few fixed
+    /// offsets of its title metadata, way faster than parsing the whole title metadata or
+    /// ticket when listing lots of files.
+    pub fn quick_info<T: Read + Seek>(mut stream: T) -> Result<WadQuickInfo, WadError> {
+        let header = Self::try_new_installable(&mut stream)?;
+
+        header
+            .seek_title_metadata(&mut stream)
+            .map_err(InstallableWadError::from)?;
+
+        SignedBlobHeader::skip(&mut stream)?;
+
+        stream.seek_relative(0xC)?;
+        let title_id = TitleId::new(stream.read_u64::<BE>()?);
+
+        stream.seek_relative(0x5C - 0xC - 8)?;
+        let title_version = stream.read_u16::<BE>()?;
+
+        Ok(WadQuickInfo {
+            header,
+            title_id,
+            title_version,
+        })
+    }
+}
+
+/// Basic data of an installable WAD, see [Wad::quick_info].
+#[derive(Debug)]
+pub struct WadQuickInfo {
+    /// The header of the WAD, with its kind and the sizes of its sections.
+    pub header: InstallableWad,
+
+    /// The ID of the title stored in the WAD.
+    pub title_id: TitleId,
+
+    /// The version of the title stored in the WAD.
+    pub title_version: u16,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::installable::test_wad;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn open() {
+        let path = std::env::temp_dir().join(format!("niiebla_open_{}.wad", std::process::id()));
+
+        let wad = InstallableWad {
+            certificate_chain_size: 0xA00,
+            ticket_size: 0x2A4,
+            title_metadata_size: 0x208,
+            ..test_wad::header()
+        };
+
+        let mut file = File::create(&path).unwrap();
+        wad.dump(&mut file).unwrap();
+        drop(file);
+
+        let (opened_wad, mut file) = Wad::open(&path).unwrap();
+        assert_eq!(opened_wad.ticket_size, 0x2A4);
+        assert_eq!(file.stream_position().unwrap(), 0);
+        file.write_all(&[0x00]).unwrap();
+
+        let (_, mut file) = Wad::open_readonly(&path).unwrap();
+        assert!(file.write_all(&[0x00]).is_err());
+
+        fs::write(&path, [0xFF; 64]).unwrap();
+        assert!(matches!(
+            Wad::open_readonly(&path),
+            Err(WadError::UnknownWadFormatError)
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
 }
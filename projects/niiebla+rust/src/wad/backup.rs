@@ -0,0 +1,375 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the header of the backup WADs stored by the System Menu at the SD card
+//! (`private/wii/title/<ID>/content.bin`).
+//!
+//! The file has two parts: the header and the icon of the banner of the channel (encrypted with
+//! the SD key, common to all the consoles), and the "Bk" header followed by the title metadata
+//! and the contents (encrypted with the PRNG key of the console). The second part is signed with
+//! a key certified by the NG key of the console, both certificates are stored at the end.
+//!
+//! A backup can be created from an installable WAD with [export] (only with the `imet` and
+//! `u8_archive` features).
+
+#[cfg(all(feature = "imet", feature = "u8_archive"))]
+mod export;
+
+#[cfg(all(feature = "imet", feature = "u8_archive"))]
+pub use export::export;
+
+use crate::title_id::TitleId;
+use crate::title_metadata::TitleMetadata;
+#[cfg(feature = "u8_archive")]
+use crate::u8_archive::U8ArchiveError;
+#[cfg(all(feature = "imet", feature = "u8_archive"))]
+use crate::wad::InstallableWadError;
+use byteorder::{BE, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Seek, Write};
+use std::path::PathBuf;
+use thiserror::Error;
+use util::{ParseContext, WriteEx};
+
+const CONTEXT: ParseContext = ParseContext::new("backup WAD header");
+
+/// The keys unique to a console needed to create its backups, stored at its OTP memory and its
+/// SEEPROM (like at the `keys.bin` file dumped by BootMii).
+#[derive(Clone)]
+pub struct ConsoleKeys {
+    /// The NG ID of the console.
+    pub ng_id: u32,
+
+    /// The ID of the NG key of the console.
+    pub ng_key_id: u32,
+
+    /// The NG private key of the console (ECC over `sect233r1`).
+    pub ng_private_key: [u8; 30],
+
+    /// The signature of the NG certificate of the console, made by Nintendo.
+    pub ng_signature: [u8; 60],
+
+    /// The key used to encrypt the contents of the backups (also known as the RNG key).
+    pub prng_key: [u8; 16],
+
+    /// The MAC address of the console.
+    pub mac_address: [u8; 6],
+}
+
+impl std::fmt::Debug for ConsoleKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the keys
+        f.debug_struct("ConsoleKeys")
+            .field("ng_id", &self.ng_id)
+            .field("ng_key_id", &self.ng_key_id)
+            .field("mac_address", &self.mac_address)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Get the path where the backup of a title must be stored, relative to the root of the SD
+/// card (`private/wii/title/<ID>/content.bin`, with the lower half of the title ID as ASCII).
+pub fn sd_path(title_id: &TitleId) -> PathBuf {
+    let lower_half = title_id.lower_half().to_be_bytes();
+
+    // Titles without a printable ID are stored with the ID in hexadecimal
+    let directory = if lower_half.iter().all(u8::is_ascii_alphanumeric) {
+        lower_half.iter().map(|byte| *byte as char).collect()
+    } else {
+        format!("{:08x}", title_id.lower_half())
+    };
+
+    ["private", "wii", "title", &directory, "content.bin"]
+        .iter()
+        .collect()
+}
+
+/// The "Bk" header of a backup WAD.
+#[derive(Debug)]
+pub struct BackupWadHeader {
+    /// The NG ID of the console that created the backup.
+    pub ng_id: u32,
+
+    /// The number of contents stored in the backup.
+    pub number_of_included_contents: u32,
+
+    /// The size of the contents stored in the backup.
+    pub included_contents_size: u32,
+
+    /// The size of the title metadata stored in the backup.
+    pub title_metadata_size: u32,
+
+    /// The size of all the contents of the title (stored or not).
+    pub contents_size: u32,
+
+    /// The total size of the backup.
+    pub total_size: u32,
+
+    /// Bitfield of the indexes of the contents stored in the backup (LSB-first).
+    pub included_contents: [u8; 64],
+
+    /// The ID of the title.
+    pub title_id: TitleId,
+
+    /// The MAC address of the console that created the backup.
+    pub mac_address: [u8; 6],
+}
+
+impl BackupWadHeader {
+    const SIZE: u32 = 0x70;
+    const MAGIC_NUMBERS: &[u8; 2] = b"Bk";
+    const VERSION: u16 = 1;
+
+    /// Create a new [BackupWadHeader] by parsing a stream.
+    pub fn new<T: Read + Seek>(mut stream: T) -> Result<Self, BackupWadError> {
+        let header_size =
+            CONTEXT.field(&mut stream, "header_size", |stream| stream.read_u32::<BE>())?;
+
+        if header_size != Self::SIZE {
+            return Err(BackupWadError::UnknownHeaderSize(header_size));
+        }
+
+        let magic_numbers = CONTEXT.field(&mut stream, "magic_numbers", |stream| {
+            util::read_exact!(stream, 2)
+        })?;
+
+        if &magic_numbers != Self::MAGIC_NUMBERS {
+            return Err(BackupWadError::InvalidMagicNumbers(magic_numbers));
+        }
+
+        let version = CONTEXT.field(&mut stream, "version", |stream| stream.read_u16::<BE>())?;
+
+        if version != Self::VERSION {
+            return Err(BackupWadError::UnknownVersion(version));
+        }
+
+        let ng_id = CONTEXT.field(&mut stream, "ng_id", |stream| stream.read_u32::<BE>())?;
+
+        let number_of_included_contents =
+            CONTEXT.field(&mut stream, "number_of_included_contents", |stream| {
+                stream.read_u32::<BE>()
+            })?;
+
+        let included_contents_size =
+            CONTEXT.field(&mut stream, "included_contents_size", |stream| {
+                stream.read_u32::<BE>()
+            })?;
+
+        let title_metadata_size = CONTEXT.field(&mut stream, "title_metadata_size", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+
+        let contents_size = CONTEXT.field(&mut stream, "contents_size", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+
+        let total_size =
+            CONTEXT.field(&mut stream, "total_size", |stream| stream.read_u32::<BE>())?;
+
+        let included_contents = CONTEXT.field(&mut stream, "included_contents", |stream| {
+            util::read_exact!(stream, 64)
+        })?;
+
+        let title_id =
+            TitleId::new(CONTEXT.field(&mut stream, "title_id", |stream| stream.read_u64::<BE>())?);
+
+        let mac_address = CONTEXT.field(&mut stream, "mac_address", |stream| {
+            util::read_exact!(stream, 6)
+        })?;
+
+        // Skip 2 bytes of padding
+        stream.seek_relative(2)?;
+
+        Ok(Self {
+            ng_id,
+            number_of_included_contents,
+            included_contents_size,
+            title_metadata_size,
+            contents_size,
+            total_size,
+            included_contents,
+            title_id,
+            mac_address,
+        })
+    }
+
+    /// Create the header of a backup of all the contents of a title, made by the console with the
+    /// given NG ID and MAC address.
+    ///
+    /// # Errors
+    /// Fails with [BackupWadError::TitleTooBig] if the backup does not fit in the 32 bit size
+    /// fields of the header.
+    pub fn from_title_metadata(
+        title_metadata: &TitleMetadata,
+        ng_id: u32,
+        mac_address: [u8; 6],
+    ) -> Result<Self, BackupWadError> {
+        let mut included_contents = [0; 64];
+        let mut contents_size = 0u64;
+
+        for entry in &title_metadata.content_chunk_entries {
+            if let Some(byte) = included_contents.get_mut(entry.index as usize / 8) {
+                *byte |= 1 << (entry.index % 8);
+            }
+
+            contents_size = contents_size
+                .checked_add(util::align_to_boundary(entry.size, 64))
+                .ok_or(BackupWadError::TitleTooBig(u64::MAX))?;
+        }
+
+        let title_metadata_size = title_metadata.size();
+
+        let total_size = (Self::SIZE as u64)
+            .checked_add(util::align_to_boundary(title_metadata_size as u64, 64))
+            .and_then(|size| size.checked_add(contents_size))
+            .ok_or(BackupWadError::TitleTooBig(u64::MAX))?;
+
+        // The total size is the biggest one
+        let total_size =
+            u32::try_from(total_size).map_err(|_| BackupWadError::TitleTooBig(total_size))?;
+        let contents_size = contents_size as u32;
+
+        Ok(Self {
+            ng_id,
+            number_of_included_contents: title_metadata.content_chunk_entries.len() as u32,
+            included_contents_size: contents_size,
+            title_metadata_size,
+            contents_size,
+            total_size,
+            included_contents,
+            title_id: TitleId::new(title_metadata.title_id.inner()),
+            mac_address,
+        })
+    }
+
+    /// Dump into a stream.
+    pub fn dump<T: Write>(&self, mut stream: T) -> io::Result<()> {
+        stream.write_u32::<BE>(Self::SIZE)?;
+        stream.write_all(Self::MAGIC_NUMBERS)?;
+        stream.write_u16::<BE>(Self::VERSION)?;
+        stream.write_u32::<BE>(self.ng_id)?;
+        stream.write_u32::<BE>(self.number_of_included_contents)?;
+        stream.write_u32::<BE>(self.included_contents_size)?;
+        stream.write_u32::<BE>(self.title_metadata_size)?;
+        stream.write_u32::<BE>(self.contents_size)?;
+        stream.write_u32::<BE>(self.total_size)?;
+        stream.write_all(&self.included_contents)?;
+        self.title_id.dump(&mut stream)?;
+        stream.write_all(&self.mac_address)?;
+
+        // Skip 2 bytes of padding
+        stream.write_zeroed(2)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum BackupWadError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("Unknown backup WAD header size: {0}")]
+    UnknownHeaderSize(u32),
+
+    #[error("Invalid magic numbers of the backup WAD header: {0:?}")]
+    InvalidMagicNumbers([u8; 2]),
+
+    #[error("Unknown backup WAD version: {0}")]
+    UnknownVersion(u16),
+
+    #[error("The backup ({0} bytes) does not fit in the 32 bit size fields of its header")]
+    TitleTooBig(u64),
+
+    #[cfg(all(feature = "imet", feature = "u8_archive"))]
+    #[error("Installable WAD error: {0}")]
+    InstallableWadError(#[from] InstallableWadError),
+
+    #[cfg(feature = "u8_archive")]
+    #[error("U8 archive error: {0}")]
+    U8ArchiveError(#[from] U8ArchiveError),
+
+    #[error("The content with index 0 of the title has no banner")]
+    MissingBanner,
+
+    #[error("The banner of the title has no icon")]
+    MissingIcon,
+
+    #[error("The NG private key of the console is not valid")]
+    InvalidNgPrivateKey,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::homebrew_channel_tmd;
+    use std::io::Cursor;
+
+    #[test]
+    fn round_trip() {
+        let header = BackupWadHeader::from_title_metadata(
+            &homebrew_channel_tmd(),
+            0x0403AC68,
+            [0x00, 0x17, 0xAB, 0x12, 0x34, 0x56],
+        )
+        .unwrap();
+
+        let mut buffer = Cursor::new(vec![]);
+        header.dump(&mut buffer).unwrap();
+        assert_eq!(buffer.get_ref().len() as u32, BackupWadHeader::SIZE);
+
+        buffer.set_position(0);
+        let parsed_header = BackupWadHeader::new(&mut buffer).unwrap();
+
+        assert_eq!(parsed_header.ng_id, header.ng_id);
+        assert_eq!(parsed_header.total_size, header.total_size);
+        assert_eq!(parsed_header.title_id.inner(), header.title_id.inner());
+    }
+
+    #[test]
+    fn sd_path() {
+        assert_eq!(
+            super::sd_path(&TitleId::new(0x00010001_48415858)),
+            PathBuf::from("private/wii/title/HAXX/content.bin")
+        );
+        assert_eq!(
+            super::sd_path(&TitleId::new(0x00010002_00000001)),
+            PathBuf::from("private/wii/title/00000001/content.bin")
+        );
+    }
+
+    #[test]
+    fn title_too_big() {
+        use crate::title_metadata::TitleMetadataContentEntryKind;
+        use crate::wad::installable::test_wad;
+
+        let mut title_metadata = homebrew_channel_tmd();
+        title_metadata
+            .content_chunk_entries
+            .push(test_wad::content_entry(
+                0,
+                0,
+                TitleMetadataContentEntryKind::Normal,
+                u32::MAX as u64,
+            ));
+
+        assert!(matches!(
+            BackupWadHeader::from_title_metadata(&title_metadata, 0, [0; 6]),
+            Err(BackupWadError::TitleTooBig(_))
+        ));
+        // The sizes overflow before being checked
+        title_metadata.content_chunk_entries =
+            vec![
+                test_wad::content_entry(0, 0, TitleMetadataContentEntryKind::Normal, u64::MAX / 2);
+                2
+            ];
+
+        assert!(matches!(
+            BackupWadHeader::from_title_metadata(&title_metadata, 0, [0; 6]),
+            Err(BackupWadError::TitleTooBig(_))
+        ));
+    }
+}
@@ -0,0 +1,467 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::CryptographicMethod;
+use crate::TitleMetadata;
+use crate::certificate_chain::signer::EccSigner;
+use crate::content_transform::{AesCbcEncrypt, ContentPipeline, Truncate};
+use crate::imet::Imet;
+use crate::ticket::PreSwitchTicket;
+use crate::title_id::TitleId;
+use crate::u8_archive::U8Archive;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use crate::wad::backup::{BackupWadError, BackupWadHeader, ConsoleKeys};
+use byteorder::{BE, WriteBytesExt};
+use md5::Md5;
+use sha1::{Digest, Sha1};
+use std::io::{self, Cursor, Read, Seek, Write};
+use util::WriteEx;
+
+/// Key used to encrypt the first part of the backups, the same one on all the consoles.
+const SD_KEY: [u8; 16] = [
+    0xAB, 0x01, 0xB9, 0xD8, 0xE1, 0x62, 0x2B, 0x08, 0xAF, 0xBA, 0xD8, 0x4D, 0xBF, 0xC2, 0xA5, 0x5D,
+];
+
+const SD_IV: [u8; 16] = [
+    0x21, 0x67, 0x12, 0xE6, 0xAA, 0x1F, 0x68, 0x9F, 0x95, 0xC5, 0xA2, 0x23, 0x24, 0xDC, 0x6A, 0x98,
+];
+
+/// Value of the hash field of the first part while it is being hashed.
+const MD5_BLANKER: [u8; 16] = [
+    0x0E, 0x65, 0x37, 0x81, 0x99, 0xBE, 0x45, 0x17, 0xAB, 0x06, 0xEC, 0x22, 0x45, 0x1A, 0x57, 0x93,
+];
+
+const BANNER_HEADER_SIZE: usize = 0x640;
+const ICON_PATH: &str = "meta/icon.bin";
+
+/// The backups are made (and signed) by the System Menu.
+const SYSTEM_MENU_TITLE_ID: u64 = 0x0000000100000002;
+
+const CERTIFICATE_SIZE: usize = 0x180;
+const CERTIFICATE_SIGNED_DATA_OFFSET: usize = 0x80;
+const ROOT_ISSUER: &str = "Root-CA00000001-MS00000002";
+
+/// Export all the contents of the title of the WAD as a backup made by the console of the given
+/// keys, the data written into `output` must be stored at the SD card in
+/// [sd_path](crate::wad::backup::sd_path).
+///
+/// The banner and the icon of the channel are taken from the content with index 0, so only
+/// channels can be exported. The contents are decrypted with the title key of the ticket stored
+/// in the WAD and encrypted again with the PRNG key of the console, the backup is signed with a
+/// key certified by the NG key of the console.
+pub fn export<T: Read + Seek, W: Write>(
+    wad: &InstallableWad,
+    mut wad_stream: T,
+    console_keys: &ConsoleKeys,
+    mut output: W,
+) -> Result<(), BackupWadError> {
+    let ng_signer =
+        EccSigner::new(&console_keys.ng_private_key).ok_or(BackupWadError::InvalidNgPrivateKey)?;
+
+    let ticket = wad
+        .ticket(&mut wad_stream)
+        .map_err(InstallableWadError::from)?;
+    let title_metadata = wad
+        .title_metadata(&mut wad_stream)
+        .map_err(InstallableWadError::from)?;
+
+    let banner = read_content(wad, &mut wad_stream, &ticket, &title_metadata, 0)?;
+    let banner_part = banner_part(&title_metadata.title_id, &banner)?;
+
+    let mut encrypted_banner_part = vec![];
+    ContentPipeline::new()
+        .then(AesCbcEncrypt::new(SD_KEY, SD_IV))
+        .run(banner_part.as_slice(), &mut encrypted_banner_part)?;
+
+    output.write_all(&encrypted_banner_part)?;
+
+    let header = BackupWadHeader::from_title_metadata(
+        &title_metadata,
+        console_keys.ng_id,
+        console_keys.mac_address,
+    )?;
+
+    let mut title_metadata_data = Cursor::new(vec![]);
+    title_metadata.dump(&mut title_metadata_data)?;
+
+    // Only the second part of the backup is signed
+    let mut output = HashingWriter {
+        stream: output,
+        hasher: Sha1::new(),
+    };
+
+    let mut header_data = vec![];
+    header.dump(&mut header_data)?;
+    write_aligned(&mut output, &header_data)?;
+    write_aligned(&mut output, title_metadata_data.get_ref())?;
+
+    for (i, content_entry) in title_metadata.content_chunk_entries.iter().enumerate() {
+        let content_selector = title_metadata.select_with_physical_position(i);
+        wad.seek_content(&mut wad_stream, &title_metadata, content_selector)?;
+
+        // The encrypted data is padded to the AES block size
+        let encrypted_len = util::align_to_boundary(content_entry.size, 16);
+
+        let mut iv = [0; 16];
+        iv[..2].copy_from_slice(&content_entry.index.to_be_bytes());
+
+        let written_bytes = ContentPipeline::new()
+            .then(
+                ticket
+                    .decryption_transform(
+                        &title_metadata,
+                        content_selector,
+                        CryptographicMethod::Wii,
+                    )
+                    .map_err(InstallableWadError::from)?,
+            )
+            .then(Truncate::new(content_entry.size))
+            .then(AesCbcEncrypt::new(console_keys.prng_key, iv))
+            .run((&mut wad_stream).take(encrypted_len), &mut output)?;
+
+        output
+            .write_zeroed((util::align_to_boundary(written_bytes, 64) - written_bytes) as usize)?;
+    }
+
+    let HashingWriter {
+        stream: mut output,
+        hasher,
+    } = output;
+
+    let ap_signer = EccSigner::from_seed(
+        &[
+            console_keys.ng_private_key.as_slice(),
+            &title_metadata.title_id.inner().to_be_bytes(),
+        ]
+        .concat(),
+    );
+
+    let ng_certificate = certificate(
+        ROOT_ISSUER,
+        &format!("NG{:08x}", console_keys.ng_id),
+        console_keys.ng_key_id,
+        &ng_signer.public_key(),
+        &console_keys.ng_signature,
+    );
+
+    let mut ap_certificate = certificate(
+        &format!("{ROOT_ISSUER}-NG{:08x}", console_keys.ng_id),
+        &format!("AP{SYSTEM_MENU_TITLE_ID:016x}"),
+        0,
+        &ap_signer.public_key(),
+        &[0; 60],
+    );
+    let ap_signature = ng_signer.sign(&ap_certificate[CERTIFICATE_SIGNED_DATA_OFFSET..]);
+    ap_certificate[4..64].copy_from_slice(&ap_signature);
+
+    // The System Menu signs the hash of the hash of the data
+    write_aligned(&mut output, &ap_signer.sign(&hasher.finalize()))?;
+    output.write_all(&ng_certificate)?;
+    output.write_all(&ap_certificate)?;
+
+    Ok(())
+}
+
+/// Create the first part of the backup (before its encryption): its header with the IMET header
+/// of the banner, followed by the icon of the channel.
+fn banner_part(title_id: &TitleId, banner: &[u8]) -> Result<Vec<u8>, BackupWadError> {
+    let imet_position =
+        Imet::position_in_content(banner).ok_or(BackupWadError::MissingBanner)? as usize;
+    let archive_position = imet_position + Imet::SIZE as usize;
+
+    let imet_data = banner
+        .get(imet_position..archive_position)
+        .ok_or(BackupWadError::MissingBanner)?;
+
+    // Check the hash of the IMET header
+    Imet::new(Cursor::new(imet_data)).map_err(InstallableWadError::from)?;
+
+    let mut archive = U8Archive::new(Cursor::new(&banner[archive_position..]))?;
+    let icon = archive
+        .file_mut(ICON_PATH)
+        .ok_or(BackupWadError::MissingIcon)?;
+
+    let icon_size =
+        u32::try_from(icon.len()).map_err(|_| BackupWadError::TitleTooBig(icon.len() as u64))?;
+
+    let mut data = Vec::with_capacity(BANNER_HEADER_SIZE + icon.len());
+    title_id.dump(&mut data)?;
+    data.write_u32::<BE>(icon_size)?;
+    data.write_all(&MD5_BLANKER)?;
+    data.write_all(&Md5::digest(icon.as_slice()))?;
+
+    // Unknown field and the IDs of two titles referenced by the channel, unused
+    data.write_zeroed(4 + 16)?;
+
+    data.write_all(imet_data)?;
+
+    let header_hash = Md5::digest(&data);
+    data[0x0C..0x1C].copy_from_slice(&header_hash);
+
+    data.extend_from_slice(icon);
+    data.resize(util::align_to_boundary(data.len() as u64, 64) as usize, 0);
+
+    Ok(data)
+}
+
+fn read_content<T: Read + Seek>(
+    wad: &InstallableWad,
+    mut wad_stream: T,
+    ticket: &PreSwitchTicket,
+    title_metadata: &TitleMetadata,
+    index: u16,
+) -> Result<Vec<u8>, InstallableWadError> {
+    let content_selector = title_metadata.select_with_index(index);
+    let content_entry = content_selector.content_entry(title_metadata)?;
+    wad.seek_content(&mut wad_stream, title_metadata, content_selector)?;
+
+    let mut data = vec![];
+    ContentPipeline::new()
+        .then(ticket.decryption_transform(
+            title_metadata,
+            content_selector,
+            CryptographicMethod::Wii,
+        )?)
+        .then(Truncate::new(content_entry.size))
+        .run(
+            (&mut wad_stream).take(util::align_to_boundary(content_entry.size, 16)),
+            &mut data,
+        )?;
+
+    Ok(data)
+}
+
+/// Create an ECC certificate with the layout used by the consoles (different from the one of
+/// [Certificate](crate::certificate_chain::Certificate), the signature is followed by 64 bytes
+/// of padding).
+fn certificate(
+    issuer: &str,
+    identity: &str,
+    key_id: u32,
+    public_key: &[u8; 60],
+    signature: &[u8; 60],
+) -> [u8; CERTIFICATE_SIZE] {
+    let mut certificate = [0; CERTIFICATE_SIZE];
+
+    // ECDSA with SHA-1 as kind of signature and ECC as kind of key
+    certificate[..4].copy_from_slice(&0x00010002u32.to_be_bytes());
+    certificate[4..64].copy_from_slice(signature);
+    certificate[0x80..0x80 + issuer.len()].copy_from_slice(issuer.as_bytes());
+    certificate[0xC0..0xC4].copy_from_slice(&2u32.to_be_bytes());
+    certificate[0xC4..0xC4 + identity.len()].copy_from_slice(identity.as_bytes());
+    certificate[0x104..0x108].copy_from_slice(&key_id.to_be_bytes());
+    certificate[0x108..0x144].copy_from_slice(public_key);
+
+    certificate
+}
+
+/// Write the data followed by zeroes up to the next multiple of 64 bytes.
+fn write_aligned<W: Write>(mut stream: W, data: &[u8]) -> io::Result<()> {
+    stream.write_all(data)?;
+    stream.write_zeroed(util::align_to_boundary(data.len() as u64, 64) as usize - data.len())
+}
+
+/// Writer that hashes all the data written through it.
+struct HashingWriter<W> {
+    stream: W,
+    hasher: Sha1,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written_bytes = self.stream.write(buf)?;
+        self.hasher.update(&buf[..written_bytes]);
+
+        Ok(written_bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate_chain::verifier::{CertificateVerifier, EccVerifier};
+    use crate::content_transform::AesCbcDecrypt;
+    use crate::signed_blob_header::SignedBlobHeaderSignature;
+    use crate::u8_archive::{U8Node, U8NodeKind};
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+
+    fn console_keys() -> ConsoleKeys {
+        let mut ng_private_key = [0; 30];
+        ng_private_key[28..].copy_from_slice(&[0x5E, 0xED]);
+
+        ConsoleKeys {
+            ng_id: 0x0403AC68,
+            ng_key_id: 0x12345678,
+            ng_private_key,
+            ng_signature: [0x42; 60],
+            prng_key: [0x33; 16],
+            mac_address: [0x00, 0x17, 0xAB, 0x12, 0x34, 0x56],
+        }
+    }
+
+    fn banner(icon: &[u8]) -> Vec<u8> {
+        let imet = Imet {
+            file_sizes: [icon.len() as u32, 0, 0],
+            flag: 0,
+            names: [const { String::new() }; Imet::NUMBER_OF_NAMES],
+        };
+
+        let archive = U8Archive {
+            nodes: vec![
+                U8Node {
+                    name: String::new(),
+                    kind: U8NodeKind::Directory { parent: 0, next: 3 },
+                },
+                U8Node {
+                    name: String::from("meta"),
+                    kind: U8NodeKind::Directory { parent: 0, next: 3 },
+                },
+                U8Node {
+                    name: String::from("icon.bin"),
+                    kind: U8NodeKind::File(icon.to_vec()),
+                },
+            ],
+        };
+
+        let mut banner = vec![];
+        imet.dump(&mut banner).unwrap();
+        archive.dump(&mut banner).unwrap();
+
+        banner
+    }
+
+    fn decrypt(key: [u8; 16], iv: [u8; 16], data: &[u8]) -> Vec<u8> {
+        ContentPipeline::new()
+            .then(AesCbcDecrypt::new(key, iv))
+            .run_in_memory(data)
+            .unwrap()
+    }
+
+    #[test]
+    fn export_backup() {
+        let icon = vec![0x77; 0x50];
+        let banner = banner(&icon);
+        let content = vec![0x99; 0x123];
+
+        let TestWad {
+            wad,
+            mut stream,
+            title_metadata,
+            ..
+        } = TestWadBuilder::new()
+            .content(0, banner.clone())
+            .content(1, content.clone())
+            .build();
+
+        let console_keys = console_keys();
+
+        stream.set_position(0);
+        let mut backup = vec![];
+        export(&wad, &mut stream, &console_keys, &mut backup).unwrap();
+
+        // The first part has the header of the banner and the icon
+        let banner_part_size = BANNER_HEADER_SIZE + 0x80;
+        let banner_part = decrypt(SD_KEY, SD_IV, &backup[..banner_part_size]);
+
+        assert_eq!(
+            &banner_part[..8],
+            &title_metadata.title_id.inner().to_be_bytes()
+        );
+        assert_eq!(&banner_part[8..12], &(icon.len() as u32).to_be_bytes());
+        assert_eq!(
+            &banner_part[0x1C..0x2C],
+            Md5::digest(icon.as_slice()).as_slice()
+        );
+        assert_eq!(&banner_part[0x40..BANNER_HEADER_SIZE], &banner[..0x600]);
+        assert_eq!(&banner_part[BANNER_HEADER_SIZE..][..icon.len()], &icon);
+
+        let mut header = banner_part[..BANNER_HEADER_SIZE].to_vec();
+        header[0x0C..0x1C].copy_from_slice(&MD5_BLANKER);
+        assert_eq!(&banner_part[0x0C..0x1C], Md5::digest(&header).as_slice());
+
+        // The second part has the "Bk" header, the title metadata and the contents
+        let backup_part = &backup[banner_part_size..];
+        let header = BackupWadHeader::new(Cursor::new(backup_part)).unwrap();
+
+        assert_eq!(header.ng_id, console_keys.ng_id);
+        assert_eq!(header.mac_address, console_keys.mac_address);
+        assert_eq!(header.number_of_included_contents, 2);
+        assert_eq!(header.included_contents[0], 0b11);
+
+        let title_metadata_size = util::align_to_boundary(title_metadata.size() as u64, 64);
+        let contents_position = 0x80 + title_metadata_size as usize;
+        let content_position =
+            contents_position + util::align_to_boundary(banner.len() as u64, 64) as usize;
+
+        let mut iv = [0; 16];
+        iv[1] = 1;
+        let decrypted_content = decrypt(
+            console_keys.prng_key,
+            iv,
+            &backup_part[content_position..content_position + 0x130],
+        );
+        assert_eq!(&decrypted_content[..content.len()], &content);
+
+        // The signature and the certificates follow the contents
+        let signed_size = content_position + 0x140;
+        let signature: [u8; 60] = backup_part[signed_size..signed_size + 60]
+            .try_into()
+            .unwrap();
+        let ng_certificate = &backup_part[signed_size + 0x40..][..CERTIFICATE_SIZE];
+        let ap_certificate = &backup_part[signed_size + 0x40 + CERTIFICATE_SIZE..];
+        assert_eq!(ap_certificate.len(), CERTIFICATE_SIZE);
+
+        let ng_verifier = CertificateVerifier::Ecc(
+            EccVerifier::new(ng_certificate[0x108..0x144].try_into().unwrap()).unwrap(),
+        );
+        let ap_verifier = CertificateVerifier::Ecc(
+            EccVerifier::new(ap_certificate[0x108..0x144].try_into().unwrap()).unwrap(),
+        );
+
+        let ap_signature = SignedBlobHeaderSignature::EcdsaSha1(Box::new(
+            ap_certificate[4..64].try_into().unwrap(),
+        ));
+        assert!(
+            ng_verifier
+                .verify(&ap_signature, &ap_certificate[0x80..])
+                .unwrap()
+        );
+
+        let signature = SignedBlobHeaderSignature::EcdsaSha1(Box::new(signature));
+        assert!(
+            ap_verifier
+                .verify(&signature, &Sha1::digest(&backup_part[..signed_size]))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn export_without_banner() {
+        let TestWad {
+            wad, mut stream, ..
+        } = TestWadBuilder::new().content(0, vec![0; 0x700]).build();
+
+        stream.set_position(0);
+        assert!(matches!(
+            export(&wad, &mut stream, &console_keys(), io::sink()),
+            Err(BackupWadError::MissingBanner)
+        ));
+
+        let keys = ConsoleKeys {
+            ng_private_key: [0; 30],
+            ..console_keys()
+        };
+        assert!(matches!(
+            export(&wad, &mut stream, &keys, io::sink()),
+            Err(BackupWadError::InvalidNgPrivateKey)
+        ));
+    }
+}
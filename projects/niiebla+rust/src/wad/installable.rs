@@ -6,24 +6,68 @@
 
 //! Implementation of a installable WAD file.
 
+mod backup;
+mod boot2;
 mod certificate_chain;
 mod content;
+mod convert;
+mod copy;
+mod edit_session;
+mod footer;
+mod journal;
+mod manifest;
+mod merge;
+mod nand;
+mod padding;
+mod rekey;
+#[cfg(feature = "imet")]
+mod rename;
+mod shift;
+mod split;
 mod ticket;
 mod title_metadata;
+mod trial;
+mod truncation;
+mod trust;
+
+#[cfg(test)]
+pub(crate) mod test_wad;
+
+pub use boot2::Boot2Info;
+pub use convert::VwiiConversionOptions;
+pub use edit_session::EditSession;
+pub use footer::{WadFooter, WadFooterTimestamp};
+pub use journal::{WadJournal, WadJournalEntry, WadSection};
+pub use manifest::{WadManifest, WadManifestEntry, WadManifestSection};
+pub use nand::ExtractedNand;
+pub use shift::ShiftOptions;
+pub use split::SplitChecksums;
+pub use trust::{TrustIssue, TrustReport, TrustSignedBlob};
 
 use crate::TitleMetadata;
 use crate::certificate_chain::CertificateChainError;
-use crate::ticket::PreSwitchTicketError;
+#[cfg(feature = "imet")]
+use crate::imet::ImetError;
+use crate::parse_limits::{ParseLimitError, ParseLimits};
+use crate::ticket::{PreSwitchTicket, PreSwitchTicketError};
 use crate::title_metadata::TitleMetadataError;
 use byteorder::{BE, ReadBytesExt, WriteBytesExt};
 use std::io;
 use std::io::Read;
 use std::io::Seek;
+use std::io::SeekFrom;
 use std::io::Write;
+use std::path::PathBuf;
 use thiserror::Error;
+use util::Cancelled;
+use util::ParseContext;
+use util::SpillBuffer;
 use util::StreamPin;
+use util::View;
 use util::WriteEx;
 
+const CONTEXT: ParseContext = ParseContext::new("installable WAD header");
+
 /// A WAD that stores a title that can be installed into the system.
 #[derive(Debug)]
 pub struct InstallableWad {
@@ -51,12 +95,31 @@ pub struct InstallableWad {
 
 #[derive(Debug)]
 struct ContentsStore {
-    contents: Vec<Vec<u8>>,
+    // The encrypted contents one after the other, moved into a temporary file if too big
+    buffer: SpillBuffer,
+
+    // Position inside the buffer and size of each content, in the order they are restored
+    contents: Vec<(u64, u64)>,
+
     first_content_physical_position: usize,
 }
 
+impl ContentsStore {
+    /// Read the data of a stored content into memory.
+    fn read_content(&mut self, i: usize) -> io::Result<Vec<u8>> {
+        let (position, size) = self.contents[i];
+
+        let mut data = vec![0; size as usize];
+        self.buffer.seek(SeekFrom::Start(position))?;
+        self.buffer.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+}
+
 impl InstallableWad {
     const HEADER_SIZE: u64 = 64;
+    const HEADER_SIZE_FIELD: u32 = 32;
     const SECTION_BOUNDARY: u64 = 64;
     const NUMBER_OF_CERTIFICATES_STORED: usize = 3;
 
@@ -64,29 +127,46 @@ impl InstallableWad {
         util::align_to_boundary(value as u64, Self::SECTION_BOUNDARY)
     }
 
+    /// Narrow the size of a section into the 32 bit size fields of the header.
+    fn size_field(section: &'static str, size: u64) -> Result<u32, InstallableWadError> {
+        u32::try_from(size).map_err(|_| InstallableWadError::SectionTooBig(section, size))
+    }
+
     /// Create a new installable Wad representation.
     ///
     /// # Safety
     /// The given buffer is assumed to be from an installable WAD.
     pub(crate) unsafe fn new<T: Read + Seek>(mut stream: T) -> Result<Self, InstallableWadError> {
-        let header_size = stream.read_u32::<BE>()?;
+        let header_size =
+            CONTEXT.field(&mut stream, "header_size", |stream| stream.read_u32::<BE>())?;
         let kind = InstallableWadKind::new(&mut stream)?;
 
-        let format_version = stream.read_u16::<BE>()?;
+        let format_version = CONTEXT.field(&mut stream, "format_version", |stream| {
+            stream.read_u16::<BE>()
+        })?;
 
         if format_version != 0 {
             return Err(InstallableWadError::UnknownFormatVersion(format_version));
         }
 
-        let certificate_chain_size = stream.read_u32::<BE>()?;
+        let certificate_chain_size =
+            CONTEXT.field(&mut stream, "certificate_chain_size", |stream| {
+                stream.read_u32::<BE>()
+            })?;
 
         // Skip four reserved bytes
         stream.seek_relative(4)?;
 
-        let ticket_size = stream.read_u32::<BE>()?;
-        let title_metadata_size = stream.read_u32::<BE>()?;
-        let content_size = stream.read_u32::<BE>()?;
-        let footer_size = stream.read_u32::<BE>()?;
+        let ticket_size =
+            CONTEXT.field(&mut stream, "ticket_size", |stream| stream.read_u32::<BE>())?;
+        let title_metadata_size = CONTEXT.field(&mut stream, "title_metadata_size", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+        let content_size = CONTEXT.field(&mut stream, "content_size", |stream| {
+            stream.read_u32::<BE>()
+        })?;
+        let footer_size =
+            CONTEXT.field(&mut stream, "footer_size", |stream| stream.read_u32::<BE>())?;
 
         Ok(Self {
             header_size,
@@ -103,8 +183,8 @@ impl InstallableWad {
     pub fn dump<T: Write + Seek>(&self, stream: T) -> io::Result<()> {
         let mut stream = StreamPin::new(stream)?;
 
-        stream.write_u32::<BE>(32)?;
-        write!(stream, "Is")?;
+        stream.write_u32::<BE>(Self::HEADER_SIZE_FIELD)?;
+        stream.write_all(self.kind.magic())?;
         stream.write_u16::<BE>(0)?;
         stream.write_u32::<BE>(self.certificate_chain_size)?;
         stream.write_zeroed(4)?;
@@ -118,13 +198,66 @@ impl InstallableWad {
         Ok(())
     }
 
+    /// Recompute the sizes stored in the header from the given ticket and title metadata, the size
+    /// of the content blobs is taken from the entries of the title metadata. The header is not
+    /// written into the stream of the WAD.
+    ///
+    /// All the `write_*_safe(...)` methods already do this, useful after editing the sections of
+    /// the WAD by other means.
+    ///
+    /// # Errors
+    /// Fails with [InstallableWadError::SectionTooBig] if the contents do not fit in the 32 bit
+    /// size field of the header.
+    pub fn recompute_sizes(
+        &mut self,
+        ticket: &PreSwitchTicket,
+        title_metadata: &TitleMetadata,
+    ) -> Result<(), InstallableWadError> {
+        self.ticket_size = ticket.size();
+        self.recompute_title_metadata_sizes(title_metadata)
+    }
+
+    /// Check that the sizes of the sections stored in the header do not exceed the maximum
+    /// allocation of the given [ParseLimits], as they are read into memory by some methods (like
+    /// the footer).
+    pub fn check_limits(&self, limits: &ParseLimits) -> Result<(), InstallableWadError> {
+        for size in [
+            self.certificate_chain_size,
+            self.ticket_size,
+            self.title_metadata_size,
+            self.footer_size,
+        ] {
+            limits.check_alloc(size.into())?;
+        }
+
+        Ok(())
+    }
+
+    fn recompute_title_metadata_sizes(
+        &mut self,
+        title_metadata: &TitleMetadata,
+    ) -> Result<(), InstallableWadError> {
+        let content_size = title_metadata
+            .content_chunk_entries
+            .iter()
+            .try_fold(0, |size: u64, entry| size.checked_add(entry.size))
+            .ok_or(InstallableWadError::SectionTooBig("contents", u64::MAX))?;
+
+        self.header_size = Self::HEADER_SIZE_FIELD;
+        self.title_metadata_size = title_metadata.size();
+        self.content_size = Self::size_field("contents", content_size)?;
+
+        Ok(())
+    }
+
     fn store_contents<T: Read + Write + Seek>(
         &mut self,
         mut stream: T,
         title_metadata: &TitleMetadata,
         first_content_physical_position: usize,
     ) -> Result<Option<ContentsStore>, InstallableWadError> {
-        let mut all_contents_bytes = vec![];
+        let mut buffer = SpillBuffer::default();
+        let mut contents = vec![];
 
         let number_of_entries = title_metadata.content_chunk_entries.len();
 
@@ -133,19 +266,21 @@ impl InstallableWad {
         }
 
         for i in first_content_physical_position..title_metadata.content_chunk_entries.len() {
-            let mut view = self.encrypted_content_view(
-                &mut stream,
-                title_metadata,
-                title_metadata.select_with_physical_position(i),
-            )?;
+            let content_selector = title_metadata.select_with_physical_position(i);
+            self.seek_content(&mut stream, title_metadata, content_selector)?;
+
+            // The last block of the encrypted data goes past the size of the content
+            let len = content_selector.content_entry(title_metadata)?.size;
+            let mut view = View::new(&mut stream, util::align_to_boundary(len, 16) as usize)?;
 
-            let mut content_bytes = vec![];
-            view.read_to_end(&mut content_bytes)?;
-            all_contents_bytes.push(content_bytes);
+            let position = buffer.stream_position()?;
+            let size = io::copy(&mut view, &mut buffer)?;
+            contents.push((position, size));
         }
 
         Ok(Some(ContentsStore {
-            contents: all_contents_bytes,
+            buffer,
+            contents,
             first_content_physical_position,
         }))
     }
@@ -154,9 +289,9 @@ impl InstallableWad {
         &mut self,
         stream: &mut StreamPin<T>,
         title_metadata: &TitleMetadata,
-        contents_store: &Option<ContentsStore>,
+        contents_store: Option<ContentsStore>,
     ) -> Result<(), InstallableWadError> {
-        if let Some(contents_store) = contents_store {
+        if let Some(mut contents_store) = contents_store {
             self.seek_content(
                 &mut *stream,
                 title_metadata,
@@ -164,8 +299,12 @@ impl InstallableWad {
                     .select_with_physical_position(contents_store.first_content_physical_position),
             )?;
 
-            for bytes in &contents_store.contents {
-                stream.write_all(bytes)?;
+            for (position, size) in contents_store.contents {
+                contents_store.buffer.seek(SeekFrom::Start(position))?;
+                io::copy(
+                    &mut Read::by_ref(&mut contents_store.buffer).take(size),
+                    &mut *stream,
+                )?;
                 stream.align_zeroed(Self::SECTION_BOUNDARY)?;
             }
         };
@@ -203,10 +342,62 @@ pub enum InstallableWadError {
 
     #[error("Unknown format version: {0}")]
     UnknownFormatVersion(u16),
+
+    #[error("The {0} ({1} bytes) do not fit in the 32 bit size fields of the WAD header")]
+    SectionTooBig(&'static str, u64),
+
+    #[error("The WADs to merge are not of the same title")]
+    TitleIdMismatch,
+
+    #[error("The new order of the contents is not a permutation of all of them")]
+    InvalidContentOrder,
+
+    #[error("The index of the content is out of the bounds of the ticket access permissions: {0}")]
+    ContentIndexOutOfBounds(u16),
+
+    #[error("The footer of the WAD is not a valid build stamp")]
+    InvalidFooter,
+
+    #[error("The hash of the content does not match the one of the title metadata (ID: {0:#X})")]
+    ContentHashMismatch(u32),
+
+    #[error("A parse limit has been exceeded: {0}")]
+    ParseLimitError(#[from] ParseLimitError),
+
+    #[error("The checksum of the exported file does not match: {0}")]
+    ExportChecksumMismatch(String),
+
+    #[error("The checksum file lists a file outside of the exported directory: {0}")]
+    InvalidExportFileName(String),
+
+    #[error("The WAD is not a boot2 WAD")]
+    NotABoot2Wad,
+
+    #[error("The boot content of the boot2 WAD is invalid: {0}")]
+    InvalidBoot2Content(&'static str),
+
+    #[error("The WAD is truncated, expected at least {0} bytes but found {1}")]
+    TruncatedWad(u64, u64),
+
+    #[error("{0}")]
+    Cancelled(#[from] Cancelled),
+
+    #[cfg(feature = "imet")]
+    #[error("IMET error: {0}")]
+    ImetError(#[from] ImetError),
+
+    #[error("The content with index 0 of the title has no banner")]
+    MissingBanner,
+
+    #[error("Unable to restore the backup {0:?} after a failed edit ({1}): {2}")]
+    BackupNotRestored(PathBuf, Box<Self>, Box<Self>),
+
+    #[error("The edit session has no data for the content at the physical position {0}")]
+    MissingContentData(usize),
 }
 
 /// Ways a WAD can install a title.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InstallableWadKind {
     /// Install it as usual.
     Normal,
@@ -216,8 +407,8 @@ pub enum InstallableWadKind {
 }
 
 impl InstallableWadKind {
-    fn new<T: Read>(mut stream: T) -> Result<Self, InstallableWadError> {
-        let bytes = util::read_exact!(stream, 2)?;
+    fn new<T: Read + Seek>(mut stream: T) -> Result<Self, InstallableWadError> {
+        let bytes = CONTEXT.field(&mut stream, "kind", |stream| util::read_exact!(stream, 2))?;
 
         Ok(match &bytes {
             b"Is" => Self::Normal,
@@ -227,4 +418,51 @@ impl InstallableWadKind {
             _ => return Err(InstallableWadError::UnknownInstallableWadTypeError(bytes)),
         })
     }
+
+    /// The bytes that identify the kind inside the header of the WAD.
+    fn magic(&self) -> &'static [u8; 2] {
+        match self {
+            Self::Normal => b"Is",
+            Self::Boot2 => b"ib",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::title_metadata::TitleMetadataContentEntryKind;
+    use test_wad::content_entry;
+
+    #[test]
+    fn recompute_sizes_of_big_titles() {
+        let ticket = templates::homebrew_channel_ticket();
+        let mut title_metadata = templates::homebrew_channel_tmd();
+
+        for id in 0..2 {
+            title_metadata.content_chunk_entries.push(content_entry(
+                id,
+                id as u16,
+                TitleMetadataContentEntryKind::Normal,
+                0x7FFF_FFFF,
+            ));
+        }
+
+        let mut wad = test_wad::header();
+
+        wad.recompute_sizes(&ticket, &title_metadata).unwrap();
+        assert_eq!(wad.content_size, 0xFFFF_FFFE);
+
+        // Used to wrap around
+        title_metadata.content_chunk_entries[1].size = 0x8000_0001;
+
+        assert!(matches!(
+            wad.recompute_sizes(&ticket, &title_metadata),
+            Err(InstallableWadError::SectionTooBig(
+                "contents",
+                0x1_0000_0000
+            ))
+        ));
+    }
 }
@@ -0,0 +1,221 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+impl InstallableWad {
+    /// Extension appended to the path of a WAD to get the path of its backup, see
+    /// [Self::with_backup].
+    pub const BACKUP_EXTENSION: &str = "bak";
+
+    /// Get the path of the backup of the WAD at the given path (`title.wad.bak`).
+    pub fn backup_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut backup_path = OsString::from(path.as_ref());
+        backup_path.push(".");
+        backup_path.push(Self::BACKUP_EXTENSION);
+
+        PathBuf::from(backup_path)
+    }
+
+    /// Run an in-place edit of the WAD file (like [Self::write_ticket_safe_file]) protected by a
+    /// backup, useful when the file is the only copy of the WAD.
+    ///
+    /// The whole file at `path` (the one opened as `file`) is first copied to
+    /// [Self::backup_path]. If the edit fails midway the data of the backup is copied back and
+    /// the header is parsed again, leaving both the file and `self` as they were before the
+    /// edit. The backup is removed once the edit (or the restore) finishes, if the restore
+    /// also fails it is kept and [InstallableWadError::BackupNotRestored] is returned.
+    ///
+    /// A backup that already exists may be left by an interrupted edit and be the only intact
+    /// copy of the WAD, it is never overwritten: the method fails with an
+    /// [io::ErrorKind::AlreadyExists] error without touching anything.
+    ///
+    /// ```no_run
+    /// use zelzip_niiebla::Wad;
+    ///
+    /// # fn main() -> Result<(), zelzip_niiebla::Error> {
+    /// let (mut wad, mut wad_file) = Wad::open("title.wad")?;
+    ///
+    /// let mut ticket = wad.ticket(&mut wad_file)?;
+    /// let title_metadata = wad.title_metadata(&mut wad_file)?;
+    /// ticket.fakesign()?;
+    ///
+    /// wad.with_backup(&mut wad_file, "title.wad", |wad, wad_file| {
+    ///     wad.write_ticket_safe_file(wad_file, &ticket, &title_metadata)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_backup<P, R, F>(
+        &mut self,
+        file: &mut File,
+        path: P,
+        edit: F,
+    ) -> Result<R, InstallableWadError>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&mut Self, &mut File) -> Result<R, InstallableWadError>,
+    {
+        let backup_path = Self::backup_path(path);
+
+        let mut backup = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&backup_path)?;
+
+        if let Err(err) = create_backup(file, &mut backup) {
+            drop(backup);
+            fs::remove_file(&backup_path)?;
+
+            return Err(err.into());
+        }
+
+        let err = match edit(self, file) {
+            Ok(result) => {
+                drop(backup);
+                fs::remove_file(&backup_path)?;
+
+                return Ok(result);
+            }
+
+            Err(err) => err,
+        };
+
+        let restored = restore_backup(&mut backup, file)
+            .map_err(InstallableWadError::from)
+            // SAFETY: The file has the same data as when `self` was parsed
+            .and_then(|()| unsafe { Self::new(&mut *file) });
+
+        match restored {
+            Ok(wad) => *self = wad,
+            Err(restore_err) => {
+                return Err(InstallableWadError::BackupNotRestored(
+                    backup_path,
+                    Box::new(err),
+                    Box::new(restore_err),
+                ));
+            }
+        }
+
+        drop(backup);
+        fs::remove_file(&backup_path)?;
+
+        Err(err)
+    }
+}
+
+/// Copy the whole file into the backup, keeping the position of the file.
+fn create_backup(file: &mut File, backup: &mut File) -> io::Result<()> {
+    let position = file.stream_position()?;
+
+    file.rewind()?;
+    io::copy(file, backup)?;
+
+    // The backup must be on the disk before the file starts to be modified
+    backup.sync_all()?;
+
+    file.seek(SeekFrom::Start(position))?;
+
+    Ok(())
+}
+
+/// Copy the whole backup back into the file, leaving the file at its start.
+fn restore_backup(backup: &mut File, file: &mut File) -> io::Result<()> {
+    backup.rewind()?;
+    file.rewind()?;
+
+    let len = io::copy(backup, file)?;
+    file.set_len(len)?;
+    file.sync_all()?;
+
+    file.rewind()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Wad;
+    use crate::templates;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+    use std::io::Write;
+
+    #[test]
+    fn restore_backup_after_failed_edit() {
+        let path = std::env::temp_dir().join(format!("niiebla_backup_{}.wad", std::process::id()));
+        let backup_path = InstallableWad::backup_path(&path);
+        assert_eq!(
+            backup_path.file_name().unwrap().to_str().unwrap(),
+            format!("niiebla_backup_{}.wad.bak", std::process::id())
+        );
+
+        let TestWad {
+            stream,
+            title_metadata,
+            ..
+        } = TestWadBuilder::new().content(0, vec![0x42; 0x30]).build();
+
+        let original_data = stream.into_inner();
+        fs::write(&path, &original_data).unwrap();
+
+        let (mut wad, mut file) = Wad::open(&path).unwrap();
+
+        let mut new_ticket = templates::homebrew_channel_ticket();
+        new_ticket.encrypted_title_key = [0x42; 16];
+
+        // The edit fails after writing garbage into the file
+        let result = wad.with_backup(&mut file, &path, |wad, file| {
+            wad.write_ticket_safe_file(file, &new_ticket, &title_metadata)?;
+            wad.content_size = 0;
+
+            file.rewind()?;
+            file.write_all(&[0xFF; 0x100])?;
+
+            Err::<(), _>(InstallableWadError::InvalidFooter)
+        });
+
+        assert!(matches!(result, Err(InstallableWadError::InvalidFooter)));
+        assert_eq!(fs::read(&path).unwrap(), original_data);
+        assert_eq!(wad.content_size, 0x30);
+        assert!(!backup_path.exists());
+
+        wad.with_backup(&mut file, &path, |wad, file| {
+            wad.write_ticket_safe_file(file, &new_ticket, &title_metadata)
+        })
+        .unwrap();
+
+        file.rewind().unwrap();
+        assert_eq!(
+            wad.ticket(&mut file).unwrap().encrypted_title_key,
+            [0x42; 16]
+        );
+        assert!(!backup_path.exists());
+
+        // An existing backup is never overwritten
+        fs::write(&backup_path, b"Old backup").unwrap();
+        let edited_data = fs::read(&path).unwrap();
+
+        let result = wad.with_backup(&mut file, &path, |_, file| {
+            file.write_all(&[0xFF; 0x100])?;
+            Ok(())
+        });
+
+        assert!(matches!(
+            result,
+            Err(InstallableWadError::IoError(err)) if err.kind() == io::ErrorKind::AlreadyExists
+        ));
+        assert_eq!(fs::read(&path).unwrap(), edited_data);
+        assert_eq!(fs::read(&backup_path).unwrap(), b"Old backup");
+
+        fs::remove_file(&backup_path).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+}
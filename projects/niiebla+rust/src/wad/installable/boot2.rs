@@ -0,0 +1,260 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::title_metadata::TitleMetadata;
+use crate::title_version::TitleVersion;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use crate::wad::installable::InstallableWadKind;
+use crate::{CryptographicMethod, PreSwitchTicket};
+use byteorder::{BE, ReadBytesExt};
+use std::io::{Cursor, Read, Seek};
+
+/// The title ID of boot2 (`00000001-00000001`).
+const BOOT2_TITLE_ID: u64 = 0x00000001_00000001;
+
+/// The data stored in the header of the boot content of a boot2 WAD, see
+/// [InstallableWad::boot2_info].
+///
+/// The boot content embeds its own certificate chain, ticket and title metadata (read by boot1
+/// to verify it) before the encrypted ELF loader.
+#[derive(Debug)]
+pub struct Boot2Info {
+    /// The offset of the data (the ELF loader) inside the boot content.
+    pub data_offset: u32,
+
+    /// The size of the embedded certificate chain.
+    pub certificate_chain_size: u32,
+
+    /// The size of the embedded ticket.
+    pub ticket_size: u32,
+
+    /// The size of the embedded title metadata.
+    pub title_metadata_size: u32,
+
+    /// The title metadata embedded inside the boot content.
+    pub title_metadata: TitleMetadata,
+}
+
+impl Boot2Info {
+    const HEADER_SIZE: u32 = 0x20;
+
+    /// Get the version of boot2 encoded inside the boot content (the version of its embedded
+    /// title metadata, `boot2v4` is stored as `4`).
+    pub fn version(&self) -> TitleVersion {
+        self.title_metadata.version()
+    }
+
+    /// Check that the embedded title metadata is paired with the title metadata of the WAD: same
+    /// title ID, same version and same contents (number and sizes).
+    ///
+    /// A mismatch means that the WAD was modified without updating the boot content (or the other
+    /// way around), installing it will most likely brick the console.
+    pub fn validate(&self, title_metadata: &TitleMetadata) -> Result<(), InstallableWadError> {
+        let embedded_title_metadata = &self.title_metadata;
+
+        if embedded_title_metadata.title_id.inner() != title_metadata.title_id.inner() {
+            return Err(InstallableWadError::InvalidBoot2Content(
+                "the title ID of the embedded title metadata does not match",
+            ));
+        }
+
+        if embedded_title_metadata.title_version != title_metadata.title_version {
+            return Err(InstallableWadError::InvalidBoot2Content(
+                "the version of the embedded title metadata does not match",
+            ));
+        }
+
+        let embedded_sizes = embedded_title_metadata
+            .content_chunk_entries
+            .iter()
+            .map(|content_entry| content_entry.size);
+
+        let sizes = title_metadata
+            .content_chunk_entries
+            .iter()
+            .map(|content_entry| content_entry.size);
+
+        if !embedded_sizes.eq(sizes) {
+            return Err(InstallableWadError::InvalidBoot2Content(
+                "the contents of the embedded title metadata do not match",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl InstallableWad {
+    /// Parse the header of the boot content of a boot2 WAD (the content selected by the boot
+    /// index of the title metadata), failing if the WAD is not a boot2 one or if the sections
+    /// of the header do not fit inside the content.
+    ///
+    /// Installing a malformed boot2 bricks the console, [Boot2Info::validate] should also be
+    /// checked before doing so.
+    pub fn boot2_info<T: Read + Seek>(
+        &self,
+        stream: T,
+        ticket: &PreSwitchTicket,
+        title_metadata: &TitleMetadata,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<Boot2Info, InstallableWadError> {
+        if !matches!(self.kind, InstallableWadKind::Boot2)
+            || title_metadata.title_id.inner() != BOOT2_TITLE_ID
+        {
+            return Err(InstallableWadError::NotABoot2Wad);
+        }
+
+        let selector = title_metadata.select_with_index(title_metadata.boot_content_index);
+        let content_size = selector.content_entry(title_metadata)?.size;
+
+        let mut content = self.decrypted_content_view(
+            stream,
+            ticket,
+            title_metadata,
+            cryptographic_method,
+            selector,
+        )?;
+
+        let header_size = content.read_u32::<BE>()?;
+        let data_offset = content.read_u32::<BE>()?;
+        let certificate_chain_size = content.read_u32::<BE>()?;
+        let ticket_size = content.read_u32::<BE>()?;
+        let title_metadata_size = content.read_u32::<BE>()?;
+
+        if header_size != Boot2Info::HEADER_SIZE {
+            return Err(InstallableWadError::InvalidBoot2Content(
+                "unknown size of the header",
+            ));
+        }
+
+        let sections_end = header_size as u64
+            + certificate_chain_size as u64
+            + ticket_size as u64
+            + title_metadata_size as u64;
+
+        if sections_end > data_offset as u64 || data_offset as u64 > content_size {
+            return Err(InstallableWadError::InvalidBoot2Content(
+                "the sections of the header do not fit inside the content",
+            ));
+        }
+
+        content.rewind()?;
+
+        let mut header_bytes = vec![0; data_offset as usize];
+        content.read_exact(&mut header_bytes)?;
+
+        let mut header_bytes = Cursor::new(header_bytes);
+        header_bytes
+            .set_position(header_size as u64 + certificate_chain_size as u64 + ticket_size as u64);
+
+        let embedded_title_metadata = TitleMetadata::new(&mut header_bytes)?;
+
+        Ok(Boot2Info {
+            data_offset,
+            certificate_chain_size,
+            ticket_size,
+            title_metadata_size,
+            title_metadata: embedded_title_metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::title_metadata::TitleMetadataContentEntryKind;
+    use crate::wad::installable::test_wad::{self, TestWad, TestWadBuilder};
+    use byteorder::WriteBytesExt;
+    use std::io::Write;
+
+    /// Create the boot content with the embedded ticket and title metadata and some data.
+    fn boot_content(ticket: &PreSwitchTicket, title_version: u16) -> Vec<u8> {
+        let mut embedded_title_metadata = templates::homebrew_title_metadata(BOOT2_TITLE_ID);
+        embedded_title_metadata.title_version = title_version;
+        embedded_title_metadata
+            .content_chunk_entries
+            .push(test_wad::content_entry(
+                0,
+                0,
+                TitleMetadataContentEntryKind::Normal,
+                0,
+            ));
+
+        let data_offset = Boot2Info::HEADER_SIZE + ticket.size() + embedded_title_metadata.size();
+        // The encryption works with blocks of 16 bytes
+        let content_size = util::align_to_boundary(data_offset as u64 + 0x40, 16);
+        embedded_title_metadata.content_chunk_entries[0].size = content_size;
+
+        let mut content = Cursor::new(vec![]);
+        content.write_u32::<BE>(Boot2Info::HEADER_SIZE).unwrap();
+        content.write_u32::<BE>(data_offset).unwrap();
+        content.write_u32::<BE>(0).unwrap();
+        content.write_u32::<BE>(ticket.size()).unwrap();
+        content
+            .write_u32::<BE>(embedded_title_metadata.size())
+            .unwrap();
+        content.write_all(&[0; 12]).unwrap();
+
+        ticket.dump(&mut content).unwrap();
+        embedded_title_metadata.dump(&mut content).unwrap();
+        content
+            .write_all(&vec![0xAA; (content_size - data_offset as u64) as usize])
+            .unwrap();
+
+        content.into_inner()
+    }
+
+    #[test]
+    fn boot2_info_and_validation() {
+        let ticket = templates::homebrew_ticket(BOOT2_TITLE_ID);
+        let content = boot_content(&ticket, 4);
+
+        let mut title_metadata = templates::homebrew_title_metadata(BOOT2_TITLE_ID);
+        title_metadata.title_version = 4;
+
+        let TestWad {
+            mut wad,
+            mut stream,
+            ticket,
+            mut title_metadata,
+        } = TestWadBuilder::with_title(ticket, title_metadata)
+            .kind(InstallableWadKind::Boot2)
+            .content(0, content)
+            .build();
+
+        let boot2_info = wad
+            .boot2_info(
+                &mut stream,
+                &ticket,
+                &title_metadata,
+                CryptographicMethod::Wii,
+            )
+            .unwrap();
+
+        assert_eq!(boot2_info.version().inner(), 4);
+        assert_eq!(boot2_info.ticket_size, ticket.size());
+        boot2_info.validate(&title_metadata).unwrap();
+
+        title_metadata.title_version = 5;
+        assert!(matches!(
+            boot2_info.validate(&title_metadata),
+            Err(InstallableWadError::InvalidBoot2Content(_))
+        ));
+
+        wad.kind = InstallableWadKind::Normal;
+        assert!(matches!(
+            wad.boot2_info(
+                &mut stream,
+                &ticket,
+                &title_metadata,
+                CryptographicMethod::Wii
+            ),
+            Err(InstallableWadError::NotABoot2Wad)
+        ));
+    }
+}
@@ -7,10 +7,11 @@
 use crate::certificate_chain::{CertificateChain, CertificateChainError};
 use crate::wad::InstallableWad;
 use crate::wad::InstallableWadError;
+use crate::wad::installable::ShiftOptions;
 use crate::{PreSwitchTicket, TitleMetadata};
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
-use util::{StreamPin, View};
+use util::{BufferedSeekReader, StreamPin, View};
 
 impl InstallableWad {
     /// Seek the stream of the WAD to the start of the certificate chain.
@@ -41,7 +42,12 @@ impl InstallableWad {
     ) -> Result<CertificateChain, CertificateChainError> {
         self.seek_certificate_chain(&mut stream)?;
 
-        CertificateChain::new(&mut stream, Self::NUMBER_OF_CERTIFICATES_STORED)
+        let mut stream = BufferedSeekReader::new(stream)?;
+        let certificate_chain =
+            CertificateChain::new(&mut stream, Self::NUMBER_OF_CERTIFICATES_STORED)?;
+        stream.into_inner()?;
+
+        Ok(certificate_chain)
     }
 
     /// Write a new certificate chain into the stream of a WAD.
@@ -78,10 +84,37 @@ impl InstallableWad {
         new_certificate_chain: &CertificateChain,
         ticket: &PreSwitchTicket,
         title_metadata: &TitleMetadata,
+    ) -> Result<(), InstallableWadError> {
+        self.write_certificate_chain_safe_with_options(
+            stream,
+            new_certificate_chain,
+            ticket,
+            title_metadata,
+            &ShiftOptions::default(),
+        )
+    }
+
+    /// Like [Self::write_certificate_chain_safe] but the trailing data is realigned following the
+    /// given [ShiftOptions].
+    pub fn write_certificate_chain_safe_with_options<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        new_certificate_chain: &CertificateChain,
+        ticket: &PreSwitchTicket,
+        title_metadata: &TitleMetadata,
+        options: &ShiftOptions,
     ) -> Result<(), InstallableWadError> {
         let mut stream = StreamPin::new(stream)?;
 
-        let contents = self.store_contents(&mut stream, title_metadata, 0)?;
+        let contents = self.store_contents_with_options(&mut stream, title_metadata, options)?;
+        self.certificate_chain_size = new_certificate_chain.size();
+        self.recompute_sizes(ticket, title_metadata)?;
+
+        let contents_end =
+            self.restore_contents_with_options(&mut stream, title_metadata, contents, options)?;
+
+        // The raw writes align the sections relative to the current position of the stream
+        stream.go_to_pin()?;
 
         unsafe {
             self.write_certificate_chain_raw(new_certificate_chain, &mut stream)?;
@@ -89,7 +122,9 @@ impl InstallableWad {
             self.write_title_metadata_raw(title_metadata, &mut stream)?;
         }
 
-        self.restore_contents(&mut stream, title_metadata, &contents)?;
+        if let Some(contents_end) = contents_end {
+            stream.seek(SeekFrom::Start(contents_end))?;
+        }
 
         Ok(())
     }
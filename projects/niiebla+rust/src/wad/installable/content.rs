@@ -4,12 +4,15 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+use crate::ContentSelector;
+use crate::CryptographicMethod;
+use crate::content_transform::ContentPipeline;
+use crate::format_compat::FormatCompat;
+use crate::ticket::KeyCache;
 use crate::title_metadata::{
     TitleMetadataContentEntry, TitleMetadataContentEntryHashKind, TitleMetadataContentEntryKind,
 };
 use crate::wad::installable::{InstallableWad, InstallableWadError};
-use crate::ContentSelector;
-use crate::CryptographicMethod;
 use crate::{PreSwitchTicket, TitleMetadata};
 use sha1::{Digest, Sha1};
 use sha2::Sha256;
@@ -22,11 +25,31 @@ use util::{StreamPin, View};
 impl InstallableWad {
     /// Seek the stream of the WAD to the start of the desired content.
     pub fn seek_content<T: Read + Seek>(
+        &self,
+        stream: T,
+        title_metadata: &TitleMetadata,
+        selector: ContentSelector,
+    ) -> Result<(), InstallableWadError> {
+        self.seek_content_with_compat(stream, title_metadata, selector, &FormatCompat::STRICT)
+    }
+
+    /// Like [Self::seek_content] but with [FormatCompat::unaligned_contents] the contents are
+    /// searched back to back if they are not aligned (see [Self::has_aligned_contents]).
+    pub fn seek_content_with_compat<T: Read + Seek>(
         &self,
         mut stream: T,
         title_metadata: &TitleMetadata,
         selector: ContentSelector,
+        compat: &FormatCompat,
     ) -> Result<(), InstallableWadError> {
+        let boundary = if compat.unaligned_contents
+            && !self.has_aligned_contents(&mut stream, title_metadata)?
+        {
+            1
+        } else {
+            Self::SECTION_BOUNDARY
+        };
+
         // The header is always aligned to the boundary
         let mut content_offset = Self::HEADER_SIZE
             + Self::align_u64(self.certificate_chain_size)
@@ -41,7 +64,7 @@ impl InstallableWad {
                 return Ok(());
             }
 
-            content_offset += util::align_to_boundary(content_entry.size, Self::SECTION_BOUNDARY);
+            content_offset += util::align_to_boundary(content_entry.size, boundary);
         }
 
         Err(InstallableWadError::TitleMetadataEntryNotFoundError)
@@ -49,6 +72,9 @@ impl InstallableWad {
 
     /// Create a [View] into the desired content stored inside the WAD stream. Be aware that the
     /// stream will be only of the encrypted data, [Self::decrypted_content_view] may be prefered.
+    ///
+    /// The encrypted data is padded to the AES block size, so the view is up to 15 bytes longer
+    /// than the size of the content.
     pub fn encrypted_content_view<T: Read + Seek>(
         &self,
         mut stream: T,
@@ -58,12 +84,46 @@ impl InstallableWad {
         self.seek_content(&mut stream, title_metadata, selector)?;
         let entry = selector.content_entry(title_metadata)?;
 
-        Ok(View::new(stream, entry.size as usize)?)
+        Ok(View::new(
+            stream,
+            util::align_to_boundary(entry.size, 16) as usize,
+        )?)
     }
 
     /// Create a [View] into the desired content stored inside the WAD stream. Decryption is done
     /// in place, be aware that **zero caching is implemented on the [AesCbcStream] type, wrapping
     /// the stream on a [std::io::BufReader] may be useful.
+    ///
+    /// The whole padded encrypted data is read but the view ends at the size of the content.
+    ///
+    /// ```
+    /// use std::io::{Cursor, Read};
+    /// use zelzip_niiebla::templates::{self, HOMEBREW_CHANNEL_TITLE_ID};
+    /// use zelzip_niiebla::{CryptographicMethod, Wad};
+    ///
+    /// # fn main() -> Result<(), zelzip_niiebla::Error> {
+    /// let wad_data = templates::homebrew_wad(HOMEBREW_CHANNEL_TITLE_ID, &[b"Homebrew channel"])?;
+    /// let mut stream = Cursor::new(wad_data);
+    /// let wad = Wad::try_new_installable(&mut stream)?;
+    ///
+    /// let ticket = wad.ticket(&mut stream)?;
+    /// let title_metadata = wad.title_metadata(&mut stream)?;
+    /// let content_selector = title_metadata.select_with_physical_position(0);
+    ///
+    /// let mut content = vec![];
+    /// wad.decrypted_content_view(
+    ///     &mut stream,
+    ///     &ticket,
+    ///     &title_metadata,
+    ///     CryptographicMethod::Wii,
+    ///     content_selector,
+    /// )?
+    /// .read_to_end(&mut content)?;
+    ///
+    /// assert_eq!(content, b"Homebrew channel");
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn decrypted_content_view<T: Read + Seek>(
         &self,
         stream: T,
@@ -71,77 +131,224 @@ impl InstallableWad {
         title_metadata: &TitleMetadata,
         cryptographic_method: CryptographicMethod,
         selector: ContentSelector,
-    ) -> Result<AesCbcStream<View<T>>, InstallableWadError> {
+    ) -> Result<View<AesCbcStream<View<T>>>, InstallableWadError> {
         let content_view = self.encrypted_content_view(stream, title_metadata, selector)?;
 
-        Ok(ticket.cryptographic_stream(
-            content_view,
+        Self::truncated_content_view(
+            ticket.cryptographic_stream(
+                content_view,
+                title_metadata,
+                selector,
+                cryptographic_method,
+            )?,
             title_metadata,
             selector,
-            cryptographic_method,
-        )?)
+        )
+    }
+
+    /// Like [Self::decrypted_content_view] but the title key is taken from the given
+    /// [KeyCache], useful when reading the contents of many WADs that share their tickets.
+    pub fn decrypted_content_view_with_cache<T: Read + Seek>(
+        &self,
+        stream: T,
+        ticket: &PreSwitchTicket,
+        title_metadata: &TitleMetadata,
+        cryptographic_method: CryptographicMethod,
+        selector: ContentSelector,
+        key_cache: &KeyCache,
+    ) -> Result<View<AesCbcStream<View<T>>>, InstallableWadError> {
+        let content_view = self.encrypted_content_view(stream, title_metadata, selector)?;
+
+        Self::truncated_content_view(
+            ticket.cryptographic_stream_with_cache(
+                content_view,
+                title_metadata,
+                selector,
+                cryptographic_method,
+                key_cache,
+            )?,
+            title_metadata,
+            selector,
+        )
+    }
+
+    /// Like [Self::decrypted_content_view] but the content is searched with
+    /// [Self::seek_content_with_compat].
+    pub fn decrypted_content_view_with_compat<T: Read + Seek>(
+        &self,
+        mut stream: T,
+        ticket: &PreSwitchTicket,
+        title_metadata: &TitleMetadata,
+        cryptographic_method: CryptographicMethod,
+        selector: ContentSelector,
+        compat: &FormatCompat,
+    ) -> Result<View<AesCbcStream<View<T>>>, InstallableWadError> {
+        self.seek_content_with_compat(&mut stream, title_metadata, selector, compat)?;
+        let entry = selector.content_entry(title_metadata)?;
+
+        let content_view = View::new(stream, util::align_to_boundary(entry.size, 16) as usize)?;
+
+        Self::truncated_content_view(
+            ticket.cryptographic_stream(
+                content_view,
+                title_metadata,
+                selector,
+                cryptographic_method,
+            )?,
+            title_metadata,
+            selector,
+        )
+    }
+
+    /// Limit the decrypted stream of a content to its size, hiding the padding to the AES block
+    /// size.
+    fn truncated_content_view<T: Read + Seek>(
+        decrypted_stream: AesCbcStream<View<T>>,
+        title_metadata: &TitleMetadata,
+        selector: ContentSelector,
+    ) -> Result<View<AesCbcStream<View<T>>>, InstallableWadError> {
+        let entry = selector.content_entry(title_metadata)?;
+
+        Ok(View::new(decrypted_stream, entry.size as usize)?)
     }
 
     /// Get a builder to modify the contents stored in the WAD.
-    pub fn modify_content<'a, 'b, 'c, T: Read + Write + Seek + Any + Sized>(
+    pub fn modify_content<'a, 'b, T: Read + Write + Seek + Any + Sized>(
         &'a mut self,
         stream: &'b mut T,
-    ) -> ModifyContentBuilder<'a, 'b, 'c, T> {
+    ) -> ModifyContentBuilder<'a, 'b, T> {
         ModifyContentBuilder {
             wad: self,
             wad_stream: stream,
-            new_id: None,
-            new_index: None,
-            new_kind: None,
-            ticket: None,
-            cryptographic_method: None,
+            new_id: Missing,
+            new_index: Missing,
+            new_kind: Missing,
+            cryptography: Missing,
             trim_if_is_file: false,
         }
     }
 }
 
-pub struct ModifyContentBuilder<'a, 'b, 'c, T: Read + Write + Seek + Any> {
+/// Marker of a setting not yet provided to a [ModifyContentBuilder].
+#[derive(Clone, Copy, Debug)]
+pub struct Missing;
+
+/// Marker of a setting already provided to a [ModifyContentBuilder].
+#[derive(Clone, Copy, Debug)]
+pub struct Provided<V>(V);
+
+/// A setting of a [ModifyContentBuilder] that may have not been provided.
+pub trait OptionalSetting<V> {
+    /// Get the value of the setting if it was provided.
+    fn get(&self) -> Option<V>;
+}
+
+impl<V> OptionalSetting<V> for Missing {
+    fn get(&self) -> Option<V> {
+        None
+    }
+}
+
+impl<V: Copy> OptionalSetting<V> for Provided<V> {
+    fn get(&self) -> Option<V> {
+        Some(self.0)
+    }
+}
+
+/// Builder to modify the contents stored in a WAD, the settings required by each operation are
+/// tracked on its type so calling them without providing its settings fails to compile.
+pub struct ModifyContentBuilder<
+    'a,
+    'b,
+    T: Read + Write + Seek + Any,
+    Id = Missing,
+    Index = Missing,
+    Kind = Missing,
+    Crypto = Missing,
+> {
     wad: &'a mut InstallableWad,
     wad_stream: &'b mut T,
-    new_id: Option<u32>,
-    new_index: Option<u16>,
-    new_kind: Option<TitleMetadataContentEntryKind>,
-    ticket: Option<&'c PreSwitchTicket>,
-    cryptographic_method: Option<CryptographicMethod>,
+    new_id: Id,
+    new_index: Index,
+    new_kind: Kind,
+    cryptography: Crypto,
     trim_if_is_file: bool,
 }
 
-impl<'c, T: Read + Write + Seek + Any> ModifyContentBuilder<'_, '_, 'c, T> {
+impl<'a, 'b, T: Read + Write + Seek + Any, Id, Index, Kind, Crypto>
+    ModifyContentBuilder<'a, 'b, T, Id, Index, Kind, Crypto>
+{
     pub fn set_cryptography(
-        &mut self,
-        ticket: &'c PreSwitchTicket,
+        self,
+        ticket: &PreSwitchTicket,
         crytographic_method: CryptographicMethod,
-    ) -> &mut Self {
-        self.ticket = Some(ticket);
-        self.cryptographic_method = Some(crytographic_method);
-
-        self
+    ) -> ModifyContentBuilder<
+        'a,
+        'b,
+        T,
+        Id,
+        Index,
+        Kind,
+        Provided<(&PreSwitchTicket, CryptographicMethod)>,
+    > {
+        ModifyContentBuilder {
+            wad: self.wad,
+            wad_stream: self.wad_stream,
+            new_id: self.new_id,
+            new_index: self.new_index,
+            new_kind: self.new_kind,
+            cryptography: Provided((ticket, crytographic_method)),
+            trim_if_is_file: self.trim_if_is_file,
+        }
     }
 
-    pub fn set_id(&mut self, id: u32) -> &mut Self {
-        self.new_id = Some(id);
-
-        self
+    pub fn set_id(
+        self,
+        id: u32,
+    ) -> ModifyContentBuilder<'a, 'b, T, Provided<u32>, Index, Kind, Crypto> {
+        ModifyContentBuilder {
+            wad: self.wad,
+            wad_stream: self.wad_stream,
+            new_id: Provided(id),
+            new_index: self.new_index,
+            new_kind: self.new_kind,
+            cryptography: self.cryptography,
+            trim_if_is_file: self.trim_if_is_file,
+        }
     }
 
-    pub fn set_index(&mut self, index: u16) -> &mut Self {
-        self.new_index = Some(index);
-
-        self
+    pub fn set_index(
+        self,
+        index: u16,
+    ) -> ModifyContentBuilder<'a, 'b, T, Id, Provided<u16>, Kind, Crypto> {
+        ModifyContentBuilder {
+            wad: self.wad,
+            wad_stream: self.wad_stream,
+            new_id: self.new_id,
+            new_index: Provided(index),
+            new_kind: self.new_kind,
+            cryptography: self.cryptography,
+            trim_if_is_file: self.trim_if_is_file,
+        }
     }
 
-    pub fn set_kind(&mut self, kind: TitleMetadataContentEntryKind) -> &mut Self {
-        self.new_kind = Some(kind);
-
-        self
+    pub fn set_kind(
+        self,
+        kind: TitleMetadataContentEntryKind,
+    ) -> ModifyContentBuilder<'a, 'b, T, Id, Index, Provided<TitleMetadataContentEntryKind>, Crypto>
+    {
+        ModifyContentBuilder {
+            wad: self.wad,
+            wad_stream: self.wad_stream,
+            new_id: self.new_id,
+            new_index: self.new_index,
+            new_kind: Provided(kind),
+            cryptography: self.cryptography,
+            trim_if_is_file: self.trim_if_is_file,
+        }
     }
 
-    pub fn trim_if_file(&mut self, flag: bool) -> &mut Self {
+    pub fn trim_if_file(mut self, flag: bool) -> Self {
         self.trim_if_is_file = flag;
 
         self
@@ -151,10 +358,7 @@ impl<'c, T: Read + Write + Seek + Any> ModifyContentBuilder<'_, '_, 'c, T> {
         &mut self,
         title_metadata: &mut TitleMetadata,
     ) -> Result<(), InstallableWadError> {
-        self.wad.content_size = title_metadata
-            .content_chunk_entries
-            .iter()
-            .fold(0, |acc, entry| acc + entry.size as u32);
+        self.wad.recompute_title_metadata_sizes(title_metadata)?;
 
         self.wad_stream.rewind()?;
         self.wad.dump(&mut self.wad_stream)?;
@@ -162,31 +366,126 @@ impl<'c, T: Read + Write + Seek + Any> ModifyContentBuilder<'_, '_, 'c, T> {
         Ok(())
     }
 
-    #[allow(clippy::expect_used)]
-    pub fn add<S: Read + Write + Seek>(
+    pub fn remove(
         &mut self,
-        mut new_data: S,
+        content_selector: ContentSelector,
         title_metadata: &mut TitleMetadata,
     ) -> Result<(), InstallableWadError> {
-        let id = self
-            .new_id
-            .expect("Missing ID, use `.set_id()` on the builder");
+        let mut wad_stream = StreamPin::new(&mut self.wad_stream)?;
+        let physical_position = content_selector.physical_position(title_metadata)?;
+
+        let mut contents =
+            self.wad
+                .store_contents(&mut wad_stream, title_metadata, physical_position + 1)?;
 
-        let index = self
-            .new_index
-            .expect("Missing index, use `.set_index()` on the builder");
+        if let Some(ref mut contents) = contents {
+            contents.first_content_physical_position -= 1;
+        }
 
-        let kind = self
-            .new_kind
-            .expect("Missing kind, use `.set_kind()` on the builder");
+        title_metadata
+            .content_chunk_entries
+            .remove(physical_position);
 
-        let ticket = self
-            .ticket
-            .expect("Missing ticket, use `.set_cryptography()` on the builder");
+        self.wad
+            .write_title_metadata_safe(&mut wad_stream, title_metadata)?;
 
-        let cryptographic_method = self
-            .cryptographic_method
-            .expect("Missing cryptographic method, use `.set_cryptography()` on the builder");
+        self.wad
+            .restore_contents(&mut wad_stream, title_metadata, contents)?;
+
+        if self.trim_if_is_file {
+            if let Some(file) = (self.wad_stream as &mut dyn Any).downcast_mut::<File>() {
+                let len = file.stream_position()?;
+
+                file.set_len(len)?;
+            }
+        }
+
+        self.sync_wad_header_content_size(title_metadata)?;
+
+        Ok(())
+    }
+
+    /// Rewrite the contents (and their title metadata entries) in a new physical order, like
+    /// putting the boot content first to speed up streamed installations. Every content must be
+    /// selected exactly once.
+    ///
+    /// The contents are moved while still encrypted, their IDs and indexes (and so the boot
+    /// content index of the title metadata) are kept unchanged.
+    pub fn reorder(
+        &mut self,
+        new_order: &[ContentSelector],
+        title_metadata: &mut TitleMetadata,
+    ) -> Result<(), InstallableWadError> {
+        let number_of_entries = title_metadata.content_chunk_entries.len();
+
+        if new_order.len() != number_of_entries {
+            return Err(InstallableWadError::InvalidContentOrder);
+        }
+
+        let mut old_positions = Vec::with_capacity(number_of_entries);
+        let mut already_selected = vec![false; number_of_entries];
+
+        for content_selector in new_order {
+            let physical_position = content_selector.physical_position(title_metadata)?;
+
+            if already_selected[physical_position] {
+                return Err(InstallableWadError::InvalidContentOrder);
+            }
+
+            already_selected[physical_position] = true;
+            old_positions.push(physical_position);
+        }
+
+        let mut wad_stream = StreamPin::new(&mut self.wad_stream)?;
+
+        let Some(mut contents) = self
+            .wad
+            .store_contents(&mut wad_stream, title_metadata, 0)?
+        else {
+            return Ok(());
+        };
+
+        let old_contents = std::mem::take(&mut contents.contents);
+        let old_entries = std::mem::take(&mut title_metadata.content_chunk_entries);
+
+        for old_position in old_positions {
+            contents.contents.push(old_contents[old_position]);
+            title_metadata
+                .content_chunk_entries
+                .push(old_entries[old_position].clone());
+        }
+
+        // The size of the title metadata does not change, it can be written in place
+        self.wad.seek_title_metadata(&mut wad_stream)?;
+        title_metadata.dump(&mut wad_stream)?;
+
+        self.wad
+            .restore_contents(&mut wad_stream, title_metadata, Some(contents))?;
+
+        Ok(())
+    }
+}
+
+impl<T: Read + Write + Seek + Any>
+    ModifyContentBuilder<
+        '_,
+        '_,
+        T,
+        Provided<u32>,
+        Provided<u16>,
+        Provided<TitleMetadataContentEntryKind>,
+        Provided<(&PreSwitchTicket, CryptographicMethod)>,
+    >
+{
+    pub fn add<S: Read + Write + Seek>(
+        &mut self,
+        mut new_data: S,
+        title_metadata: &mut TitleMetadata,
+    ) -> Result<(), InstallableWadError> {
+        let Provided(id) = self.new_id;
+        let Provided(index) = self.new_index;
+        let Provided(kind) = self.new_kind;
+        let Provided((ticket, cryptographic_method)) = self.cryptography;
 
         let mut wad_stream = StreamPin::new(&mut self.wad_stream)?;
         let content_selector = title_metadata.select_last();
@@ -215,79 +514,52 @@ impl<'c, T: Read + Write + Seek + Any> ModifyContentBuilder<'_, '_, 'c, T> {
 
         title_metadata.content_chunk_entries.push(entry);
 
-        let mut wad_stream = ticket.cryptographic_stream(
-            &mut wad_stream,
-            title_metadata,
-            content_selector,
-            cryptographic_method,
-        )?;
+        let encrypted_data = ContentPipeline::new()
+            .then(ticket.encryption_transform(
+                title_metadata,
+                content_selector,
+                cryptographic_method,
+            )?)
+            .run_in_memory(&new_data_vec)?;
 
-        wad_stream.write(&new_data_vec)?;
+        wad_stream.write_all(&encrypted_data)?;
 
         // Modifing the title metadata must be done at the end to avoid issues with the position of
-        // the stream (writing on the start of the WAD by accident)
-        let mut wad_stream = wad_stream.into_inner();
-        self.wad
-            .write_title_metadata_safe(&mut wad_stream, title_metadata)?;
-
-        self.sync_wad_header_content_size(title_metadata)?;
-
-        Ok(())
-    }
-
-    pub fn remove(
-        &mut self,
-        content_selector: ContentSelector,
-        title_metadata: &mut TitleMetadata,
-    ) -> Result<(), InstallableWadError> {
-        let mut wad_stream = StreamPin::new(&mut self.wad_stream)?;
-        let physical_position = content_selector.physical_position(title_metadata)?;
-
-        let mut contents =
-            self.wad
-                .store_contents(&mut wad_stream, title_metadata, physical_position + 1)?;
-
-        if let Some(ref mut contents) = contents {
-            contents.first_content_physical_position -= 1;
-        }
-
-        title_metadata
-            .content_chunk_entries
-            .remove(physical_position);
-
+        // the stream (writing on the start of the WAD by accident), the contents are realigned
+        // relative to the start of the WAD
+        wad_stream.go_to_pin()?;
         self.wad
             .write_title_metadata_safe(&mut wad_stream, title_metadata)?;
 
-        self.wad
-            .restore_contents(&mut wad_stream, title_metadata, &contents)?;
-
-        if self.trim_if_is_file {
-            if let Some(file) = (self.wad_stream as &mut dyn Any).downcast_mut::<File>() {
-                let len = file.stream_position()?;
-
-                file.set_len(len)?;
-            }
-        }
-
         self.sync_wad_header_content_size(title_metadata)?;
 
         Ok(())
     }
+}
 
-    #[allow(clippy::expect_used)]
+impl<
+    T: Read + Write + Seek + Any,
+    Id: OptionalSetting<u32>,
+    Index: OptionalSetting<u16>,
+    Kind: OptionalSetting<TitleMetadataContentEntryKind>,
+>
+    ModifyContentBuilder<
+        '_,
+        '_,
+        T,
+        Id,
+        Index,
+        Kind,
+        Provided<(&PreSwitchTicket, CryptographicMethod)>,
+    >
+{
     pub fn replace<S: Read + Write + Seek>(
         &mut self,
         mut new_data: S,
         content_selector: ContentSelector,
         title_metadata: &mut TitleMetadata,
     ) -> Result<(), InstallableWadError> {
-        let ticket = self
-            .ticket
-            .expect("Missing ticket, use `.set_cryptography()` on the builder");
-
-        let cryptographic_method = self
-            .cryptographic_method
-            .expect("Missing cryptographic method, use `.set_cryptography()` on the builder");
+        let Provided((ticket, cryptographic_method)) = self.cryptography;
 
         let mut wad_stream = StreamPin::new(&mut self.wad_stream)?;
         let physical_position = content_selector.physical_position(title_metadata)?;
@@ -310,15 +582,15 @@ impl<'c, T: Read + Write + Seek + Any> ModifyContentBuilder<'_, '_, 'c, T> {
         title_metadata_entry.hash = hash;
         title_metadata_entry.size = new_data_vec.len() as u64;
 
-        if let Some(id) = self.new_id {
+        if let Some(id) = self.new_id.get() {
             title_metadata_entry.id = id;
         }
 
-        if let Some(index) = self.new_index {
+        if let Some(index) = self.new_index.get() {
             title_metadata_entry.index = index;
         }
 
-        if let Some(kind) = self.new_kind {
+        if let Some(kind) = self.new_kind.get() {
             title_metadata_entry.kind = kind;
         }
 
@@ -328,24 +600,143 @@ impl<'c, T: Read + Write + Seek + Any> ModifyContentBuilder<'_, '_, 'c, T> {
         self.wad
             .seek_content(&mut wad_stream, title_metadata, content_selector)?;
 
-        let mut wad_stream = ticket.cryptographic_stream(
-            &mut wad_stream,
-            title_metadata,
-            content_selector,
-            cryptographic_method,
-        )?;
-
-        wad_stream.write(&new_data_vec)?;
-
-        let wad_stream = wad_stream.into_inner();
+        let encrypted_data = ContentPipeline::new()
+            .then(ticket.encryption_transform(
+                title_metadata,
+                content_selector,
+                cryptographic_method,
+            )?)
+            .run_in_memory(&new_data_vec)?;
 
+        wad_stream.write_all(&encrypted_data)?;
         wad_stream.align_position(InstallableWad::SECTION_BOUNDARY)?;
 
         self.wad
-            .restore_contents(wad_stream, title_metadata, &contents)?;
+            .restore_contents(&mut wad_stream, title_metadata, contents)?;
 
         self.sync_wad_header_content_size(title_metadata)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+
+    #[test]
+    fn read_unaligned_contents() {
+        let contents: [Vec<u8>; 2] = [
+            (0..0x25).collect(),
+            (0..0x1001).map(|i| (i * 7) as u8).collect(),
+        ];
+
+        let TestWad {
+            wad,
+            mut stream,
+            ticket,
+            title_metadata,
+        } = TestWadBuilder::new()
+            .content(0, contents[0].clone())
+            .content(1, contents[1].clone())
+            .build();
+
+        for (position, content) in contents.iter().enumerate() {
+            let selector = title_metadata.select_with_physical_position(position);
+
+            let mut encrypted_data = vec![];
+            wad.encrypted_content_view(&mut stream, &title_metadata, selector)
+                .unwrap()
+                .read_to_end(&mut encrypted_data)
+                .unwrap();
+            assert_eq!(
+                encrypted_data.len() as u64,
+                util::align_to_boundary(content.len() as u64, 16)
+            );
+
+            let mut data = vec![];
+            wad.decrypted_content_view(
+                &mut stream,
+                &ticket,
+                &title_metadata,
+                CryptographicMethod::Wii,
+                selector,
+            )
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+            assert_eq!(&data, content);
+
+            // The last partial block from the middle of the content
+            let mut view = wad
+                .decrypted_content_view_with_compat(
+                    &mut stream,
+                    &ticket,
+                    &title_metadata,
+                    CryptographicMethod::Wii,
+                    selector,
+                    &FormatCompat::STRICT,
+                )
+                .unwrap();
+            view.seek(SeekFrom::Start(0x1A)).unwrap();
+
+            let mut data = vec![];
+            view.read_to_end(&mut data).unwrap();
+            assert_eq!(data, content[0x1A..]);
+        }
+    }
+
+    #[test]
+    fn reorder_contents() {
+        let TestWad {
+            mut wad,
+            mut stream,
+            ticket,
+            mut title_metadata,
+        } = TestWadBuilder::new()
+            .content(0, vec![0; 0x10])
+            .content(1, vec![1; 0x50])
+            .content(2, vec![2; 0x20])
+            .build();
+
+        let new_order = [
+            title_metadata.select_with_id(2),
+            title_metadata.select_with_id(0),
+            title_metadata.select_with_id(1),
+        ];
+
+        stream.set_position(0);
+        wad.modify_content(&mut stream)
+            .reorder(&new_order, &mut title_metadata)
+            .unwrap();
+
+        stream.set_position(0);
+        let parsed_title_metadata = wad.title_metadata(&mut stream).unwrap();
+
+        for (position, id) in [2, 0, 1].into_iter().enumerate() {
+            assert_eq!(parsed_title_metadata.content_chunk_entries[position].id, id);
+
+            let mut data = vec![];
+            wad.decrypted_content_view(
+                &mut stream,
+                &ticket,
+                &parsed_title_metadata,
+                CryptographicMethod::Wii,
+                parsed_title_metadata.select_with_physical_position(position),
+            )
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+            assert!(data.iter().all(|byte| *byte == id as u8));
+        }
+
+        let invalid_order = [new_order[0], new_order[0], new_order[1]];
+        assert!(matches!(
+            wad.modify_content(&mut stream)
+                .reorder(&invalid_order, &mut title_metadata),
+            Err(InstallableWadError::InvalidContentOrder)
+        ));
+    }
+}
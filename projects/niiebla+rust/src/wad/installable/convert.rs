@@ -0,0 +1,177 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::CryptographicMethod;
+use crate::title_id::TitleId;
+use crate::title_metadata::TitleMetadataPlatformData;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use std::io::{Read, Seek, Write};
+use util::StreamPin;
+
+/// Higher half of the title ID of the IOSes.
+const IOS_HIGHER_HALF: u32 = 0x00000001;
+
+/// The IOSes installed on the vWii of the Wii U.
+const VWII_IOSES: &[u32] = &[
+    9, 12, 13, 14, 15, 17, 21, 22, 28, 31, 33, 34, 35, 36, 37, 38, 41, 43, 45, 46, 48, 53, 55, 56,
+    57, 58, 59, 61, 62, 80,
+];
+
+/// Options of [InstallableWad::convert_to_vwii_with_options].
+#[derive(Debug, Clone)]
+pub struct VwiiConversionOptions {
+    /// The IOS used by the titles that run on an IOS missing on the vWii.
+    pub fallback_ios: u32,
+}
+
+impl Default for VwiiConversionOptions {
+    fn default() -> Self {
+        // Available on all the vWii consoles and with USB 2.0 support
+        Self { fallback_ios: 58 }
+    }
+}
+
+impl InstallableWad {
+    /// Convert the WAD of a Wii title to be installed on the vWii of the Wii U, see
+    /// [Self::convert_to_vwii_with_options].
+    pub fn convert_to_vwii<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+    ) -> Result<(), InstallableWadError> {
+        self.convert_to_vwii_with_options(stream, &VwiiConversionOptions::default())
+    }
+
+    /// Convert the WAD of a Wii title to be installed on the vWii of the Wii U:
+    /// - The title is marked as only for the vWii.
+    /// - The title key is encrypted with the vWii common key, the contents are not re-encrypted
+    ///   as the title key is kept.
+    /// - The IOS used by the title is replaced with [VwiiConversionOptions::fallback_ios] if it
+    ///   is not installed on the vWii.
+    ///
+    /// The ticket and title metadata are fakesigned so the title can only be installed on a
+    /// console running an IOS vulnerable to the
+    /// [Trucha bug](https://wiibrew.org/wiki/Signing_bug).
+    ///
+    /// # Errors
+    /// Fails with [InstallableWadError::NotAWiiTitle] if the title is not for the Wii platform.
+    pub fn convert_to_vwii_with_options<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        options: &VwiiConversionOptions,
+    ) -> Result<(), InstallableWadError> {
+        let mut stream = StreamPin::new(stream)?;
+
+        let mut ticket = self.ticket(&mut stream)?;
+        let mut title_metadata = self.title_metadata(&mut stream)?;
+
+        let TitleMetadataPlatformData::Wii {
+            is_wii_u_vwii_only_title,
+            ..
+        } = &mut title_metadata.platform_data
+        else {
+            return Err(InstallableWadError::NotAWiiTitle);
+        };
+
+        *is_wii_u_vwii_only_title = true;
+
+        if let Some(system_runtime_title_id) = &mut title_metadata.system_runtime_title_id {
+            if system_runtime_title_id.higher_half() == IOS_HIGHER_HALF
+                && !VWII_IOSES.contains(&system_runtime_title_id.lower_half())
+            {
+                *system_runtime_title_id =
+                    TitleId::new_with_halfs(IOS_HIGHER_HALF, options.fallback_ios);
+            }
+        }
+
+        let title_key = ticket.decrypt_title_key(CryptographicMethod::Wii)?;
+        ticket.common_key_kind_index = 2;
+        ticket.encrypt_title_key(title_key, CryptographicMethod::Wii)?;
+
+        title_metadata.fakesign()?;
+        ticket.fakesign()?;
+
+        stream.go_to_pin()?;
+        self.write_title_metadata_safe(&mut stream, &title_metadata)?;
+
+        stream.go_to_pin()?;
+        self.write_ticket_safe(&mut stream, &ticket, &title_metadata)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+
+    #[test]
+    fn convert_to_vwii() {
+        let mut title_metadata = templates::homebrew_channel_tmd();
+
+        // IOS 70 is not installed on the vWii
+        title_metadata.system_runtime_title_id = Some(TitleId::new(0x00000001_00000046));
+
+        let plaintext: Vec<u8> = (0..0x40).collect();
+
+        let TestWad {
+            mut wad,
+            mut stream,
+            ticket,
+            ..
+        } = TestWadBuilder::with_title(templates::homebrew_channel_ticket(), title_metadata)
+            .content(0, plaintext.clone())
+            .build();
+
+        wad.convert_to_vwii(&mut stream).unwrap();
+
+        stream.set_position(0);
+        let converted_ticket = wad.ticket(&mut stream).unwrap();
+        let converted_title_metadata = wad.title_metadata(&mut stream).unwrap();
+
+        assert_eq!(converted_ticket.common_key_kind_index, 2);
+        assert_eq!(
+            converted_ticket
+                .decrypt_title_key(CryptographicMethod::Wii)
+                .unwrap(),
+            ticket.decrypt_title_key(CryptographicMethod::Wii).unwrap()
+        );
+        converted_ticket
+            .check_common_key_kind(&converted_title_metadata)
+            .unwrap();
+
+        assert!(matches!(
+            converted_title_metadata.platform_data,
+            TitleMetadataPlatformData::Wii {
+                is_wii_u_vwii_only_title: true,
+                ..
+            }
+        ));
+        assert_eq!(
+            converted_title_metadata
+                .system_runtime_title_id
+                .as_ref()
+                .map(TitleId::inner),
+            Some(0x00000001_0000003A)
+        );
+
+        let mut data = vec![0; plaintext.len()];
+        wad.decrypted_content_view(
+            &mut stream,
+            &converted_ticket,
+            &converted_title_metadata,
+            CryptographicMethod::Wii,
+            converted_title_metadata.select_with_physical_position(0),
+        )
+        .unwrap()
+        .read_exact(&mut data)
+        .unwrap();
+
+        assert_eq!(data, plaintext);
+    }
+}
@@ -0,0 +1,290 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::CryptographicMethod;
+use crate::content_transform::{ContentHasher, ContentPipeline, Truncate};
+use crate::format_compat::FormatCompat;
+use crate::telemetry;
+use crate::title_metadata::{TitleMetadataContentEntry, TitleMetadataContentEntryHashKind};
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+impl InstallableWad {
+    /// Copy the WAD stored in `reader` byte by byte into `writer`, the hash of each content is
+    /// validated (after decrypting it with the given method) while being copied. Only the ticket
+    /// and the title metadata are parsed, the contents are read only once and streamed without
+    /// loading them into memory. Returns the number of copied bytes.
+    ///
+    /// A content with an invalid hash fails with [InstallableWadError::ContentHashMismatch], its
+    /// data may already be partially written into `writer`.
+    ///
+    /// If `trim` is set the data after the footer (garbage added by some tools) is not copied.
+    ///
+    /// The reader is assumed to have the WAD at its start.
+    pub fn copy_to<R: Read + Seek, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+        cryptographic_method: CryptographicMethod,
+        trim: bool,
+    ) -> Result<u64, InstallableWadError> {
+        let ticket = self.ticket(&mut reader)?;
+        let title_metadata = self.title_metadata(&mut reader)?;
+
+        // The header is always aligned to the boundary
+        let mut offset = Self::HEADER_SIZE
+            + Self::align_u64(self.certificate_chain_size)
+            + Self::align_u64(self.ticket_size)
+            + Self::align_u64(self.title_metadata_size);
+
+        let mut copied_bytes = Self::copy_range(&mut reader, &mut writer, 0, offset)?;
+
+        for (i, content_entry) in title_metadata.content_chunk_entries.iter().enumerate() {
+            let content_selector = title_metadata.select_with_physical_position(i);
+            let aligned_size = util::align_to_boundary(content_entry.size, Self::SECTION_BOUNDARY);
+
+            // The encrypted data is padded to the AES block size
+            let encrypted_len = util::align_to_boundary(content_entry.size, 16);
+
+            reader.seek(SeekFrom::Start(offset))?;
+
+            // The encrypted data is copied as it is read, the decrypted one is only hashed
+            let mut tee = TeeReader {
+                reader: (&mut reader).take(encrypted_len),
+                writer: &mut writer,
+                copied_bytes: 0,
+            };
+            let mut hasher = ContentHasher::for_hash(&content_entry.hash);
+
+            ContentPipeline::new()
+                .then(ticket.decryption_transform(
+                    &title_metadata,
+                    content_selector,
+                    cryptographic_method,
+                )?)
+                .then(Truncate::new(content_entry.size))
+                .then(&mut hasher)
+                .run(&mut tee, io::sink())?;
+
+            if hasher.digest().as_bytes() != content_entry.hash.as_bytes() {
+                telemetry::verification_failed("content_hash");
+                return Err(InstallableWadError::ContentHashMismatch(content_entry.id));
+            }
+
+            copied_bytes += tee.copied_bytes;
+            copied_bytes += io::copy(
+                &mut (&mut reader).take(aligned_size - encrypted_len),
+                &mut writer,
+            )?;
+            offset += aligned_size;
+        }
+
+        copied_bytes +=
+            Self::copy_range(&mut reader, &mut writer, offset, self.footer_size as u64)?;
+
+        if !trim {
+            copied_bytes += io::copy(&mut reader, &mut writer)?;
+        }
+
+        Ok(copied_bytes)
+    }
+
+    fn copy_range<R: Read + Seek, W: Write>(
+        mut reader: R,
+        mut writer: W,
+        offset: u64,
+        size: u64,
+    ) -> io::Result<u64> {
+        reader.seek(SeekFrom::Start(offset))?;
+
+        io::copy(&mut reader.take(size), &mut writer)
+    }
+
+    pub(super) fn has_valid_hash<T: Read>(
+        content: T,
+        content_entry: &TitleMetadataContentEntry,
+    ) -> io::Result<bool> {
+        Self::has_valid_hash_with_compat(content, content_entry, &FormatCompat::STRICT)
+    }
+
+    pub(super) fn has_valid_hash_with_compat<T: Read>(
+        mut content: T,
+        content_entry: &TitleMetadataContentEntry,
+        compat: &FormatCompat,
+    ) -> io::Result<bool> {
+        let is_valid = match content_entry.hash {
+            TitleMetadataContentEntryHashKind::Version0(hash) => {
+                let mut hasher = Sha1::new();
+                io::copy(&mut content, &mut hasher)?;
+
+                hasher.finalize()[..] == hash
+            }
+
+            // Both hashes are computed in a single pass, the content may be too big to read twice
+            TitleMetadataContentEntryHashKind::Version1(hash)
+                if compat.padded_sha1_hashes && hash[20..].iter().all(|&byte| byte == 0) =>
+            {
+                let mut hasher = (Sha1::new(), Sha256::new());
+                io::copy(&mut content, &mut DualHasher(&mut hasher))?;
+
+                hasher.0.finalize()[..] == hash[..20] || hasher.1.finalize()[..] == hash
+            }
+
+            TitleMetadataContentEntryHashKind::Version1(hash) => {
+                let mut hasher = Sha256::new();
+                io::copy(&mut content, &mut hasher)?;
+
+                hasher.finalize()[..] == hash
+            }
+        };
+
+        if !is_valid {
+            telemetry::verification_failed("content_hash");
+        }
+
+        Ok(is_valid)
+    }
+}
+
+/// Writes all the data read from the reader into the writer.
+struct TeeReader<R, W> {
+    reader: R,
+    writer: W,
+    copied_bytes: u64,
+}
+
+impl<R: Read, W: Write> Read for TeeReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read_bytes = self.reader.read(buf)?;
+
+        self.writer.write_all(&buf[..read_bytes])?;
+        self.copied_bytes += read_bytes as u64;
+
+        Ok(read_bytes)
+    }
+}
+
+/// Feeds the written data into both hashers.
+struct DualHasher<'a>(&'a mut (Sha1, Sha256));
+
+impl Write for DualHasher<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.0.update(buf);
+        self.0.1.update(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title_metadata::TitleMetadataContentEntryKind;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+
+    #[test]
+    fn copy_to() {
+        let TestWad {
+            wad, mut stream, ..
+        } = TestWadBuilder::new()
+            .content(0, vec![0x42; 0x30])
+            .content(1, vec![0x24; 0x45])
+            .build();
+
+        // The padding of the last content is needed to tell the garbage apart
+        let len = util::align_to_boundary(stream.get_ref().len() as u64, 64);
+        stream.get_mut().resize(len as usize, 0);
+
+        let original_data = stream.get_ref().clone();
+
+        let mut copy = vec![];
+        let copied_bytes = wad
+            .copy_to(&mut stream, &mut copy, CryptographicMethod::Wii, true)
+            .unwrap();
+
+        assert_eq!(copy, original_data);
+        assert_eq!(copied_bytes, original_data.len() as u64);
+
+        // The garbage after the footer is only copied without trimming
+        stream.get_mut().extend_from_slice(b"Garbage");
+
+        let mut copy = vec![];
+        stream.set_position(0);
+        wad.copy_to(&mut stream, &mut copy, CryptographicMethod::Wii, true)
+            .unwrap();
+        assert_eq!(copy, original_data);
+
+        let mut copy = vec![];
+        stream.set_position(0);
+        wad.copy_to(&mut stream, &mut copy, CryptographicMethod::Wii, false)
+            .unwrap();
+        assert_eq!(copy, stream.get_ref().as_slice());
+
+        // The contents are decrypted with the given method
+        stream.set_position(0);
+        assert!(matches!(
+            wad.copy_to(
+                &mut stream,
+                io::sink(),
+                CryptographicMethod::WiiDevelopment,
+                true
+            ),
+            Err(InstallableWadError::ContentHashMismatch(0))
+        ));
+
+        // Last byte of the encrypted data of the last content
+        let last_content_byte = original_data.len() - 0x80 + 0x4F;
+        stream.get_mut()[last_content_byte] ^= 0xFF;
+
+        stream.set_position(0);
+        assert!(matches!(
+            wad.copy_to(&mut stream, io::sink(), CryptographicMethod::Wii, true),
+            Err(InstallableWadError::ContentHashMismatch(1))
+        ));
+    }
+
+    #[test]
+    fn accept_padded_sha1_hashes() {
+        let content = [0x42; 0x30];
+
+        let mut padded_sha1_hash = [0; 32];
+        padded_sha1_hash[..20].copy_from_slice(&Sha1::digest(content));
+
+        let mut content_entry = TitleMetadataContentEntry {
+            id: 0,
+            index: 0,
+            kind: TitleMetadataContentEntryKind::Normal,
+            size: content.len() as u64,
+            hash: TitleMetadataContentEntryHashKind::Version1(padded_sha1_hash),
+        };
+
+        let compat = FormatCompat {
+            padded_sha1_hashes: true,
+            ..FormatCompat::default()
+        };
+
+        assert!(!InstallableWad::has_valid_hash(&content[..], &content_entry).unwrap());
+        assert!(
+            InstallableWad::has_valid_hash_with_compat(&content[..], &content_entry, &compat)
+                .unwrap()
+        );
+
+        // The SHA-256 hash is still accepted
+        content_entry.hash =
+            TitleMetadataContentEntryHashKind::Version1(Sha256::digest(content).into());
+        assert!(
+            InstallableWad::has_valid_hash_with_compat(&content[..], &content_entry, &compat)
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,385 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::certificate_chain::CertificateChain;
+use crate::content_transform::{ContentPipeline, Truncate};
+use crate::title_metadata::{
+    TitleMetadataContentEntry, TitleMetadataContentEntryHashKind, TitleMetadataContentEntryKind,
+};
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use crate::{ContentSelector, CryptographicMethod, PreSwitchTicket, TitleMetadata};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use util::StreamPin;
+
+impl InstallableWad {
+    /// Start an [EditSession] over the WAD stream, all the sections of the WAD are loaded into
+    /// memory.
+    pub fn edit_session<T: Read + Write + Seek>(
+        &mut self,
+        mut stream: T,
+    ) -> Result<EditSession<'_, T>, InstallableWadError> {
+        let certificate_chain = self.certificate_chain(&mut stream)?;
+        let ticket = self.ticket(&mut stream)?;
+        let title_metadata = self.title_metadata(&mut stream)?;
+
+        let mut contents = vec![];
+
+        if let Some(mut contents_store) = self.store_contents(&mut stream, &title_metadata, 0)? {
+            for i in 0..contents_store.contents.len() {
+                contents.push(contents_store.read_content(i)?);
+            }
+        }
+
+        let mut footer = vec![];
+
+        // An empty view cannot be created
+        if self.footer_size != 0 {
+            self.footer_view(&mut stream, &title_metadata)?
+                .read_to_end(&mut footer)?;
+        }
+
+        Ok(EditSession {
+            wad: self,
+            stream,
+            certificate_chain,
+            ticket,
+            title_metadata,
+            contents,
+            footer,
+        })
+    }
+}
+
+/// A set of edits over a WAD done in memory, the contents are read only once and all the changes
+/// are written in a single pass by [Self::flush], avoiding the rewrite of the trailing data done
+/// by each `write_*_safe(...)` method.
+///
+/// The certificate chain, ticket and title metadata can be freely modified, the contents must be
+/// modified using the methods of the session to keep the entries of the title metadata in sync.
+pub struct EditSession<'a, T: Read + Write + Seek> {
+    wad: &'a mut InstallableWad,
+    stream: T,
+
+    /// The certificate chain of the WAD.
+    pub certificate_chain: CertificateChain,
+
+    /// The ticket of the WAD.
+    pub ticket: PreSwitchTicket,
+
+    /// The title metadata of the WAD.
+    pub title_metadata: TitleMetadata,
+
+    // Encrypted contents sorted by their physical position
+    contents: Vec<Vec<u8>>,
+    footer: Vec<u8>,
+}
+
+impl<T: Read + Write + Seek> EditSession<'_, T> {
+    /// Get the encrypted data of the selected content.
+    pub fn encrypted_content(
+        &self,
+        content_selector: ContentSelector,
+    ) -> Result<&[u8], InstallableWadError> {
+        let physical_position = content_selector.physical_position(&self.title_metadata)?;
+
+        self.contents
+            .get(physical_position)
+            .map(Vec::as_slice)
+            .ok_or(InstallableWadError::MissingContentData(physical_position))
+    }
+
+    /// Get the decrypted data of the selected content.
+    pub fn decrypted_content(
+        &self,
+        content_selector: ContentSelector,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<Vec<u8>, InstallableWadError> {
+        let encrypted_content = self.encrypted_content(content_selector)?;
+        let size = content_selector.content_entry(&self.title_metadata)?.size;
+
+        Ok(ContentPipeline::new()
+            .then(self.ticket.decryption_transform(
+                &self.title_metadata,
+                content_selector,
+                cryptographic_method,
+            )?)
+            .then(Truncate::new(size))
+            .run_in_memory(encrypted_content)?)
+    }
+
+    /// Add a new content at the end of the WAD, its entry is appended to the title metadata.
+    pub fn add_content(
+        &mut self,
+        id: u32,
+        index: u16,
+        kind: TitleMetadataContentEntryKind,
+        data: &[u8],
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<(), InstallableWadError> {
+        self.title_metadata
+            .content_chunk_entries
+            .push(TitleMetadataContentEntry {
+                id,
+                index,
+                kind,
+                hash: self.hash(data),
+                size: data.len() as u64,
+            });
+
+        let encrypted_content = self.encrypt(
+            data,
+            self.title_metadata.select_last(),
+            cryptographic_method,
+        )?;
+        self.contents.push(encrypted_content);
+
+        Ok(())
+    }
+
+    /// Replace the data of the selected content, its entry on the title metadata is updated.
+    pub fn replace_content(
+        &mut self,
+        content_selector: ContentSelector,
+        data: &[u8],
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<(), InstallableWadError> {
+        let physical_position = content_selector.physical_position(&self.title_metadata)?;
+
+        // The entries of the title metadata can be modified outside of the session
+        if physical_position >= self.contents.len() {
+            return Err(InstallableWadError::MissingContentData(physical_position));
+        }
+
+        let hash = self.hash(data);
+        let entry = self
+            .title_metadata
+            .content_chunk_entries
+            .get_mut(physical_position)
+            .ok_or(InstallableWadError::TitleMetadataEntryNotFoundError)?;
+
+        entry.hash = hash;
+        entry.size = data.len() as u64;
+
+        self.contents[physical_position] =
+            self.encrypt(data, content_selector, cryptographic_method)?;
+
+        Ok(())
+    }
+
+    /// Remove the selected content and its entry on the title metadata.
+    pub fn remove_content(
+        &mut self,
+        content_selector: ContentSelector,
+    ) -> Result<(), InstallableWadError> {
+        let physical_position = content_selector.physical_position(&self.title_metadata)?;
+
+        if physical_position >= self.contents.len() {
+            return Err(InstallableWadError::MissingContentData(physical_position));
+        }
+
+        self.title_metadata
+            .content_chunk_entries
+            .remove(physical_position);
+        self.contents.remove(physical_position);
+
+        Ok(())
+    }
+
+    /// Write all the sections of the WAD into the stream and give it back, positioned at the end
+    /// of the WAD.
+    pub fn flush(mut self) -> Result<T, InstallableWadError> {
+        self.wad.certificate_chain_size = self.certificate_chain.size();
+        self.wad
+            .recompute_sizes(&self.ticket, &self.title_metadata)?;
+        self.wad.footer_size = InstallableWad::size_field("footer", self.footer.len() as u64)?;
+
+        self.stream.rewind()?;
+        let mut stream = StreamPin::new(&mut self.stream)?;
+
+        self.wad.dump(&mut stream)?;
+
+        self.certificate_chain.dump(&mut stream)?;
+        stream.align_zeroed(InstallableWad::SECTION_BOUNDARY)?;
+
+        self.ticket.dump(&mut stream)?;
+        stream.align_zeroed(InstallableWad::SECTION_BOUNDARY)?;
+
+        self.title_metadata.dump(&mut stream)?;
+        stream.align_zeroed(InstallableWad::SECTION_BOUNDARY)?;
+
+        for content in &self.contents {
+            stream.write_all(content)?;
+            stream.align_zeroed(InstallableWad::SECTION_BOUNDARY)?;
+        }
+
+        stream.write_all(&self.footer)?;
+
+        Ok(self.stream)
+    }
+
+    fn hash(&self, data: &[u8]) -> TitleMetadataContentEntryHashKind {
+        if self.title_metadata.version_1_extension.is_some() {
+            TitleMetadataContentEntryHashKind::Version1(Sha256::digest(data).into())
+        } else {
+            TitleMetadataContentEntryHashKind::Version0(Sha1::digest(data).into())
+        }
+    }
+
+    fn encrypt(
+        &self,
+        data: &[u8],
+        content_selector: ContentSelector,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<Vec<u8>, InstallableWadError> {
+        Ok(ContentPipeline::new()
+            .then(self.ticket.encryption_transform(
+                &self.title_metadata,
+                content_selector,
+                cryptographic_method,
+            )?)
+            .run_in_memory(data)?)
+    }
+}
+
+impl EditSession<'_, &mut File> {
+    /// Like [Self::flush] but will also trim the size of the file to avoid garbage data or
+    /// useless zeroes.
+    pub fn flush_file(self) -> Result<(), InstallableWadError> {
+        let file = self.flush()?;
+
+        let new_file_size = file.stream_position()?;
+        file.set_len(new_file_size)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::installable::test_wad::{self, TestWad, TestWadBuilder};
+
+    fn build_wad() -> TestWad {
+        TestWadBuilder::new()
+            .certificate_chain(test_wad::certificate_chain(3))
+            .content(0, vec![0x11; 0x40])
+            .content(1, vec![0x22; 0x20])
+            .build()
+    }
+
+    #[test]
+    fn open_and_flush_unchanged() {
+        let TestWad {
+            mut wad,
+            mut stream,
+            ..
+        } = build_wad();
+
+        let original_data = stream.get_ref().clone();
+
+        let session = wad.edit_session(&mut stream).unwrap();
+        assert_eq!(session.contents.len(), 2);
+
+        session.flush().unwrap();
+
+        // Only the padding after the last content is added
+        let data = stream.get_ref();
+        assert!(data.starts_with(&original_data));
+        assert!(data[original_data.len()..].iter().all(|byte| *byte == 0));
+        assert_eq!(data.len() as u64 % InstallableWad::SECTION_BOUNDARY, 0);
+    }
+
+    #[test]
+    fn replace_add_and_remove_contents() {
+        let TestWad {
+            mut wad,
+            mut stream,
+            ..
+        } = build_wad();
+
+        let mut session = wad.edit_session(&mut stream).unwrap();
+
+        session
+            .replace_content(
+                session.title_metadata.select_with_index(1),
+                &[0x33; 0x50],
+                CryptographicMethod::Wii,
+            )
+            .unwrap();
+        session
+            .add_content(
+                2,
+                2,
+                TitleMetadataContentEntryKind::Normal,
+                &[0x44; 0x10],
+                CryptographicMethod::Wii,
+            )
+            .unwrap();
+        session
+            .remove_content(session.title_metadata.select_with_index(0))
+            .unwrap();
+
+        session.title_metadata.fakesign().unwrap();
+        session.flush().unwrap();
+
+        stream.set_position(0);
+        let mut wad = crate::wad::Wad::try_new_installable(&mut stream).unwrap();
+        let session = wad.edit_session(&mut stream).unwrap();
+
+        assert_eq!(session.title_metadata.content_chunk_entries.len(), 2);
+
+        for (index, expected) in [(1, [0x33; 0x50].as_slice()), (2, &[0x44; 0x10])] {
+            let content_selector = session.title_metadata.select_with_index(index);
+
+            assert_eq!(
+                session
+                    .decrypted_content(content_selector, CryptographicMethod::Wii)
+                    .unwrap(),
+                expected
+            );
+            assert!(matches!(
+                content_selector.content_entry(&session.title_metadata).unwrap().hash,
+                TitleMetadataContentEntryHashKind::Version0(hash)
+                    if hash == Sha1::digest(expected).as_slice()
+            ));
+        }
+    }
+
+    #[test]
+    fn entries_without_data() {
+        let TestWad {
+            mut wad,
+            mut stream,
+            ..
+        } = build_wad();
+
+        let mut session = wad.edit_session(&mut stream).unwrap();
+
+        // An entry added outside of the session has no data
+        let mut entry = session.title_metadata.content_chunk_entries[0].clone();
+        entry.index = 5;
+        session.title_metadata.content_chunk_entries.push(entry);
+
+        let content_selector = session.title_metadata.select_with_index(5);
+
+        assert!(matches!(
+            session.encrypted_content(content_selector),
+            Err(InstallableWadError::MissingContentData(2))
+        ));
+        assert!(matches!(
+            session.replace_content(content_selector, &[0; 0x10], CryptographicMethod::Wii),
+            Err(InstallableWadError::MissingContentData(2))
+        ));
+        assert!(matches!(
+            session.remove_content(content_selector),
+            Err(InstallableWadError::MissingContentData(2))
+        ));
+    }
+}
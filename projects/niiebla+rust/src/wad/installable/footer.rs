@@ -0,0 +1,327 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::title_metadata::TitleMetadata;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use util::{StreamPin, View};
+
+impl InstallableWad {
+    /// Seek the stream of the WAD to the start of the footer.
+    pub fn seek_footer<T: Seek>(
+        &self,
+        mut stream: T,
+        title_metadata: &TitleMetadata,
+    ) -> Result<(), InstallableWadError> {
+        // The header is always aligned to the boundary
+        let mut footer_offset = Self::HEADER_SIZE
+            + Self::align_u64(self.certificate_chain_size)
+            + Self::align_u64(self.ticket_size)
+            + Self::align_u64(self.title_metadata_size);
+
+        for content_entry in &title_metadata.content_chunk_entries {
+            footer_offset += util::align_to_boundary(content_entry.size, Self::SECTION_BOUNDARY);
+        }
+
+        stream.seek(SeekFrom::Start(footer_offset))?;
+        Ok(())
+    }
+
+    /// Create a [View] into the footer stored inside the WAD stream.
+    pub fn footer_view<T: Read + Seek>(
+        &self,
+        mut stream: T,
+        title_metadata: &TitleMetadata,
+    ) -> Result<View<T>, InstallableWadError> {
+        self.seek_footer(&mut stream, title_metadata)?;
+
+        Ok(View::new(stream, self.footer_size as usize)?)
+    }
+
+    /// Parse the build stamp stored in the footer of the WAD, `None` if the WAD has no footer.
+    pub fn footer<T: Read + Seek>(
+        &self,
+        stream: T,
+        title_metadata: &TitleMetadata,
+    ) -> Result<Option<WadFooter>, InstallableWadError> {
+        if self.footer_size == 0 {
+            return Ok(None);
+        }
+
+        let view = self.footer_view(stream, title_metadata)?;
+
+        Ok(Some(WadFooter::parse(view, self.footer_size)?))
+    }
+
+    /// Write a new footer at the end of the WAD stream, the size of the footer in the header
+    /// will be updated.
+    ///
+    /// If the new footer is smaller than the old one the trailing bytes of the old one are kept,
+    /// using [Self::write_footer_file] may be preferred.
+    pub fn write_footer<T: Write + Seek>(
+        &mut self,
+        stream: T,
+        title_metadata: &TitleMetadata,
+        new_footer: &WadFooter,
+    ) -> Result<(), InstallableWadError> {
+        let mut data = vec![];
+        new_footer.dump(&mut data)?;
+
+        self.write_footer_bytes(stream, title_metadata, &data)
+    }
+
+    /// Write raw data as the footer at the end of the WAD stream, the size of the footer in the
+    /// header will be updated.
+    pub(super) fn write_footer_bytes<T: Write + Seek>(
+        &mut self,
+        stream: T,
+        title_metadata: &TitleMetadata,
+        data: &[u8],
+    ) -> Result<(), InstallableWadError> {
+        let mut stream = StreamPin::new(stream)?;
+
+        self.seek_footer(&mut stream, title_metadata)?;
+        stream.write_all(data)?;
+
+        let footer_end = stream.stream_position()?;
+
+        self.footer_size = Self::size_field("footer", data.len() as u64)?;
+
+        stream.seek_from_pin(0)?;
+        self.dump(&mut stream)?;
+
+        stream.seek(SeekFrom::Start(footer_end))?;
+
+        Ok(())
+    }
+
+    /// Like [Self::write_footer] but will also trim the size of the file to avoid garbage data.
+    pub fn write_footer_file(
+        &mut self,
+        file: &mut File,
+        title_metadata: &TitleMetadata,
+        new_footer: &WadFooter,
+    ) -> Result<(), InstallableWadError> {
+        self.write_footer(&mut *file, title_metadata, new_footer)?;
+
+        let new_file_size = file.stream_position()?;
+        file.set_len(new_file_size)?;
+
+        Ok(())
+    }
+}
+
+/// The build stamp found in the footer of official WADs, an ASCII text with the time the WAD was
+/// created followed by the name of its builder (one per line).
+// TODO(DISCOVER): If the footer of official WADs can store more data than the build stamp
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WadFooter {
+    /// The name of the builder (tool, user or machine) that created the WAD.
+    pub builder: String,
+
+    /// The time when the WAD was created.
+    pub timestamp: WadFooterTimestamp,
+}
+
+impl WadFooter {
+    /// Create a new [WadFooter] to be written into a repacked WAD.
+    pub fn new(builder: &str, timestamp: WadFooterTimestamp) -> Self {
+        Self {
+            builder: builder.to_string(),
+            timestamp,
+        }
+    }
+
+    /// Parse a footer of the given size from a stream, trailing null bytes are ignored.
+    pub fn parse<T: Read>(stream: T, size: u32) -> Result<Self, InstallableWadError> {
+        let mut bytes = vec![];
+        stream.take(size as u64).read_to_end(&mut bytes)?;
+
+        let text = String::from_utf8(bytes).map_err(|_| InstallableWadError::InvalidFooter)?;
+        let text = text.trim_end_matches('\0');
+
+        let (timestamp, builder) = text.split_once('\n').unwrap_or((text, ""));
+        let timestamp =
+            WadFooterTimestamp::parse(timestamp).ok_or(InstallableWadError::InvalidFooter)?;
+
+        Ok(Self::new(builder.trim_end_matches('\n'), timestamp))
+    }
+
+    /// Dump into a stream.
+    pub fn dump<T: Write>(&self, mut stream: T) -> io::Result<()> {
+        write!(stream, "{}\n{}\n", self.timestamp, self.builder)?;
+
+        Ok(())
+    }
+
+    /// Get the size of the footer in bytes.
+    pub fn size(&self) -> u32 {
+        (self.timestamp.to_string().len() + self.builder.len() + 2) as u32
+    }
+}
+
+/// The time when a WAD was created, written on its footer with the format of the C `ctime`
+/// function (`Thu Sep 18 18:23:56 2008`). The time zone is not stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WadFooterTimestamp {
+    /// The year (`2008`).
+    pub year: u16,
+
+    /// The month, starting from one for January.
+    pub month: u8,
+
+    /// The day of the month, starting from one.
+    pub day: u8,
+
+    /// The hour, from zero to 23.
+    pub hour: u8,
+
+    /// The minute, from zero to 59.
+    pub minute: u8,
+
+    /// The second, from zero to 60 (leap seconds).
+    pub second: u8,
+}
+
+impl WadFooterTimestamp {
+    const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    /// Parse a timestamp with the format of `ctime`, `None` if it's not valid. The name of the
+    /// day of the week is checked but not stored, it's computed again from the date.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut fields = text.split_whitespace();
+
+        let weekday = fields.next()?;
+        let month = fields.next()?;
+        let day = fields.next()?;
+        let time = fields.next()?;
+        let year = fields.next()?;
+
+        if fields.next().is_some() || !Self::WEEKDAYS.contains(&weekday) {
+            return None;
+        }
+
+        let mut time = time.split(':').map(|number| number.parse::<u8>().ok());
+        let mut next_number = || time.next().flatten();
+
+        let timestamp = Self {
+            year: year.parse().ok()?,
+            month: Self::MONTHS.iter().position(|name| *name == month)? as u8 + 1,
+            day: day.parse().ok()?,
+            hour: next_number()?,
+            minute: next_number()?,
+            second: next_number()?,
+        };
+
+        if time.next().is_some() || !timestamp.is_valid() {
+            return None;
+        }
+
+        Some(timestamp)
+    }
+
+    /// Check if all the fields are inside their ranges.
+    pub fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second <= 60
+    }
+
+    /// Get the day of the week, starting from zero for Sunday.
+    pub fn weekday(&self) -> u8 {
+        // Sakamoto's method
+        const MONTH_OFFSETS: [u32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+
+        let month = self.month.clamp(1, 12) as u32;
+        let year = self.year as u32 - u32::from(month < 3 && self.year > 0);
+
+        ((year + year / 4 - year / 100
+            + year / 400
+            + MONTH_OFFSETS[month as usize - 1]
+            + self.day as u32)
+            % 7) as u8
+    }
+}
+
+impl fmt::Display for WadFooterTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {:2} {:02}:{:02}:{:02} {}",
+            Self::WEEKDAYS[self.weekday() as usize],
+            Self::MONTHS[self.month.clamp(1, 12) as usize - 1],
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+            self.year
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const TIMESTAMP: WadFooterTimestamp = WadFooterTimestamp {
+        year: 2008,
+        month: 9,
+        day: 18,
+        hour: 18,
+        minute: 23,
+        second: 56,
+    };
+
+    #[test]
+    fn round_trip() {
+        let footer = WadFooter::new("zelzip", TIMESTAMP);
+
+        let mut buffer = Cursor::new(vec![]);
+        footer.dump(&mut buffer).unwrap();
+        assert_eq!(buffer.get_ref(), b"Thu Sep 18 18:23:56 2008\nzelzip\n");
+        assert_eq!(buffer.get_ref().len() as u32, footer.size());
+
+        // Official footers are padded with zeroes
+        buffer.get_mut().extend([0; 16]);
+        let size = buffer.get_ref().len() as u32;
+        buffer.set_position(0);
+
+        assert_eq!(WadFooter::parse(&mut buffer, size).unwrap(), footer);
+
+        assert!(matches!(
+            WadFooter::parse(&b"Not a timestamp\nzelzip\n"[..], 23),
+            Err(InstallableWadError::InvalidFooter)
+        ));
+    }
+
+    #[test]
+    fn timestamp() {
+        assert_eq!(
+            WadFooterTimestamp::parse("Thu Sep 18 18:23:56 2008"),
+            Some(TIMESTAMP)
+        );
+        assert_eq!(TIMESTAMP.weekday(), 4);
+
+        // The days of a single digit are padded with a space
+        let timestamp = WadFooterTimestamp::parse("Mon Mar  1 00:00:00 2010").unwrap();
+        assert_eq!(timestamp.weekday(), 1);
+        assert_eq!(timestamp.to_string(), "Mon Mar  1 00:00:00 2010");
+
+        assert_eq!(WadFooterTimestamp::parse("Thu Sep 18 18:23 2008"), None);
+        assert_eq!(WadFooterTimestamp::parse("Thu Sep 32 18:23:56 2008"), None);
+        assert_eq!(WadFooterTimestamp::parse("Thu Foo 18 18:23:56 2008"), None);
+        assert_eq!(WadFooterTimestamp::parse("Thu Sep 18 24:23:56 2008"), None);
+    }
+}
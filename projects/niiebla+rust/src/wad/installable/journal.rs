@@ -0,0 +1,248 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::certificate_chain::CertificateChain;
+use crate::ticket::PreSwitchTicket;
+use crate::title_metadata::TitleMetadata;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use std::io::{Read, Seek, Write};
+
+/// A section of an installable WAD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WadSection {
+    /// The header of the WAD.
+    Header,
+
+    /// The certificate chain.
+    CertificateChain,
+
+    /// The ticket.
+    Ticket,
+
+    /// The title metadata.
+    TitleMetadata,
+
+    /// The content blobs.
+    Contents,
+}
+
+/// A section written again by a safe write of a WAD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WadJournalEntry {
+    /// The written section.
+    pub section: WadSection,
+
+    /// The size of the section before the write.
+    pub old_size: u64,
+
+    /// The size of the section after the write.
+    pub new_size: u64,
+
+    /// The number of bytes written into the stream for the section, including the padding.
+    pub moved_bytes: u64,
+}
+
+/// The list of sections written by a safe write of a WAD (`write_*_safe_with_journal(...)`), in
+/// the order they are written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WadJournal {
+    /// The written sections.
+    pub entries: Vec<WadJournalEntry>,
+}
+
+impl WadJournal {
+    /// Get the total number of bytes written into the stream.
+    pub fn moved_bytes(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.moved_bytes).sum()
+    }
+
+    /// Get the entry of a section, `None` if the section was not written.
+    pub fn entry(&self, section: WadSection) -> Option<&WadJournalEntry> {
+        self.entries.iter().find(|entry| entry.section == section)
+    }
+}
+
+impl InstallableWad {
+    /// Like [Self::write_certificate_chain_safe] but returns a [WadJournal] of the written
+    /// sections. If `dry_run` is set nothing is written, useful to estimate the cost of the
+    /// operation beforehand.
+    pub fn write_certificate_chain_safe_with_journal<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        new_certificate_chain: &CertificateChain,
+        ticket: &PreSwitchTicket,
+        title_metadata: &TitleMetadata,
+        dry_run: bool,
+    ) -> Result<WadJournal, InstallableWadError> {
+        let journal = WadJournal {
+            entries: vec![
+                self.header_journal_entry(),
+                Self::journal_entry(
+                    WadSection::CertificateChain,
+                    self.certificate_chain_size,
+                    new_certificate_chain.size(),
+                ),
+                Self::journal_entry(WadSection::Ticket, self.ticket_size, ticket.size()),
+                Self::journal_entry(
+                    WadSection::TitleMetadata,
+                    self.title_metadata_size,
+                    title_metadata.size(),
+                ),
+                self.contents_journal_entry(title_metadata),
+            ],
+        };
+
+        if !dry_run {
+            self.write_certificate_chain_safe(
+                stream,
+                new_certificate_chain,
+                ticket,
+                title_metadata,
+            )?;
+        }
+
+        Ok(journal)
+    }
+
+    /// Like [Self::write_ticket_safe] but returns a [WadJournal] of the written sections. If
+    /// `dry_run` is set nothing is written, useful to estimate the cost of the operation
+    /// beforehand.
+    pub fn write_ticket_safe_with_journal<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        new_ticket: &PreSwitchTicket,
+        title_metadata: &TitleMetadata,
+        dry_run: bool,
+    ) -> Result<WadJournal, InstallableWadError> {
+        let journal = WadJournal {
+            entries: vec![
+                self.header_journal_entry(),
+                Self::journal_entry(WadSection::Ticket, self.ticket_size, new_ticket.size()),
+                Self::journal_entry(
+                    WadSection::TitleMetadata,
+                    self.title_metadata_size,
+                    title_metadata.size(),
+                ),
+                self.contents_journal_entry(title_metadata),
+            ],
+        };
+
+        if !dry_run {
+            self.write_ticket_safe(stream, new_ticket, title_metadata)?;
+        }
+
+        Ok(journal)
+    }
+
+    /// Like [Self::write_title_metadata_safe] but returns a [WadJournal] of the written sections.
+    /// If `dry_run` is set nothing is written, useful to estimate the cost of the operation
+    /// beforehand.
+    pub fn write_title_metadata_safe_with_journal<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        new_title_metadata: &TitleMetadata,
+        dry_run: bool,
+    ) -> Result<WadJournal, InstallableWadError> {
+        let journal = WadJournal {
+            entries: vec![
+                self.header_journal_entry(),
+                Self::journal_entry(
+                    WadSection::TitleMetadata,
+                    self.title_metadata_size,
+                    new_title_metadata.size(),
+                ),
+                self.contents_journal_entry(new_title_metadata),
+            ],
+        };
+
+        if !dry_run {
+            self.write_title_metadata_safe(stream, new_title_metadata)?;
+        }
+
+        Ok(journal)
+    }
+
+    fn journal_entry(section: WadSection, old_size: u32, new_size: u32) -> WadJournalEntry {
+        WadJournalEntry {
+            section,
+            old_size: old_size as u64,
+            new_size: new_size as u64,
+            moved_bytes: Self::align_u64(new_size),
+        }
+    }
+
+    fn header_journal_entry(&self) -> WadJournalEntry {
+        WadJournalEntry {
+            section: WadSection::Header,
+            old_size: self.header_size as u64,
+            new_size: Self::HEADER_SIZE_FIELD as u64,
+            moved_bytes: Self::HEADER_SIZE,
+        }
+    }
+
+    // All the content blobs are stored in memory and written again after the other sections
+    fn contents_journal_entry(&self, title_metadata: &TitleMetadata) -> WadJournalEntry {
+        let new_size = title_metadata
+            .content_chunk_entries
+            .iter()
+            .map(|entry| entry.size)
+            .sum();
+
+        let moved_bytes = title_metadata
+            .content_chunk_entries
+            .iter()
+            .map(|entry| util::align_to_boundary(entry.size, Self::SECTION_BOUNDARY))
+            .sum();
+
+        WadJournalEntry {
+            section: WadSection::Contents,
+            old_size: self.content_size as u64,
+            new_size,
+            moved_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::wad::installable::test_wad;
+    use std::io::Cursor;
+
+    #[test]
+    fn dry_run_journal() {
+        let ticket = templates::homebrew_channel_ticket();
+        let title_metadata = templates::homebrew_channel_tmd();
+
+        let mut wad = InstallableWad {
+            ticket_size: 0x100,
+            title_metadata_size: title_metadata.size(),
+            ..test_wad::header()
+        };
+
+        let mut stream = Cursor::new(vec![]);
+
+        let journal = wad
+            .write_ticket_safe_with_journal(&mut stream, &ticket, &title_metadata, true)
+            .unwrap();
+
+        assert!(stream.get_ref().is_empty());
+        assert_eq!(wad.ticket_size, 0x100);
+
+        let ticket_entry = journal.entry(WadSection::Ticket).unwrap();
+        assert_eq!(ticket_entry.old_size, 0x100);
+        assert_eq!(ticket_entry.new_size, ticket.size() as u64);
+
+        assert!(journal.entry(WadSection::CertificateChain).is_none());
+        assert_eq!(
+            journal.moved_bytes(),
+            64 + util::align_to_boundary(ticket.size() as u64, 64)
+                + util::align_to_boundary(title_metadata.size() as u64, 64)
+        );
+    }
+}
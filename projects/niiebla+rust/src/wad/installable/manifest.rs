@@ -0,0 +1,270 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::title_metadata::TitleMetadata;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use util::View;
+
+/// A record of the hashes of every section of a WAD and of how and when they were computed,
+/// useful to prove that an archived WAD has not been modified (chain of custody).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WadManifest {
+    /// The name of the file the WAD was read from, if known.
+    pub source_file_name: Option<String>,
+
+    /// The version of NiiEBLA used to generate the manifest.
+    pub library_version: String,
+
+    /// The time when the manifest was generated, in seconds since the Unix epoch.
+    pub generated_at: u64,
+
+    /// The hashed sections, in the order they are stored inside the WAD.
+    #[serde(rename = "sections")]
+    pub entries: Vec<WadManifestEntry>,
+}
+
+/// The hash of a section of a WAD stored on a [WadManifest].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WadManifestEntry {
+    /// The hashed section.
+    #[serde(flatten)]
+    pub section: WadManifestSection,
+
+    /// The offset of the section inside the WAD.
+    pub offset: u64,
+
+    /// The size of the section in bytes, without its padding.
+    pub size: u64,
+
+    /// The SHA-256 hash of the section, without its padding.
+    #[serde(serialize_with = "util::hex::serialize")]
+    pub sha256: [u8; 32],
+}
+
+/// A section of a WAD hashed by a [WadManifest].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "section", rename_all = "snake_case")]
+pub enum WadManifestSection {
+    /// The header of the WAD.
+    Header,
+
+    /// The certificate chain.
+    CertificateChain,
+
+    /// The ticket.
+    Ticket,
+
+    /// The title metadata.
+    TitleMetadata,
+
+    /// A content, hashed while still encrypted.
+    Content {
+        /// The ID of the content.
+        id: u32,
+
+        /// The index of the content.
+        index: u16,
+    },
+
+    /// The footer.
+    Footer,
+}
+
+impl WadManifestSection {
+    /// Get the name of the section used on the JSON representation of the manifest.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Header => "header",
+            Self::CertificateChain => "certificate_chain",
+            Self::Ticket => "ticket",
+            Self::TitleMetadata => "title_metadata",
+            Self::Content { .. } => "content",
+            Self::Footer => "footer",
+        }
+    }
+}
+
+impl WadManifest {
+    /// Get the entry of a section, `None` if the section was not hashed.
+    pub fn entry(&self, section: WadManifestSection) -> Option<&WadManifestEntry> {
+        self.entries.iter().find(|entry| entry.section == section)
+    }
+
+    /// Serialize the manifest into JSON, hashes are written in lowercase hexadecimal.
+    pub fn to_json(&self) -> String {
+        // Serializing plain structs with string keys never fails
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+
+        json
+    }
+}
+
+impl InstallableWad {
+    /// Generate a [WadManifest] of the WAD, hashing every section stored inside the stream
+    /// (contents are hashed while still encrypted).
+    pub fn manifest<T: Read + Seek>(
+        &self,
+        mut stream: T,
+        title_metadata: &TitleMetadata,
+        source_file_name: Option<&str>,
+    ) -> Result<WadManifest, InstallableWadError> {
+        let mut entries = vec![];
+        let mut offset = 0;
+
+        let mut sections = vec![
+            (WadManifestSection::Header, Self::HEADER_SIZE),
+            (
+                WadManifestSection::CertificateChain,
+                self.certificate_chain_size as u64,
+            ),
+            (WadManifestSection::Ticket, self.ticket_size as u64),
+            (
+                WadManifestSection::TitleMetadata,
+                self.title_metadata_size as u64,
+            ),
+        ];
+
+        for content_entry in &title_metadata.content_chunk_entries {
+            sections.push((
+                WadManifestSection::Content {
+                    id: content_entry.id,
+                    index: content_entry.index,
+                },
+                content_entry.size,
+            ));
+        }
+
+        if self.footer_size != 0 {
+            sections.push((WadManifestSection::Footer, self.footer_size as u64));
+        }
+
+        for (section, size) in sections {
+            stream.seek(SeekFrom::Start(offset))?;
+
+            let mut hasher = Sha256::new();
+
+            // Views cannot be empty
+            if size != 0 {
+                io::copy(&mut View::new(&mut stream, size as usize)?, &mut hasher)?;
+            }
+
+            entries.push(WadManifestEntry {
+                section,
+                offset,
+                size,
+                sha256: hasher.finalize().into(),
+            });
+
+            offset += util::align_to_boundary(size, Self::SECTION_BOUNDARY);
+        }
+
+        let generated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        Ok(WadManifest {
+            source_file_name: source_file_name.map(String::from),
+            library_version: String::from(env!("CARGO_PKG_VERSION")),
+            generated_at,
+            entries,
+        })
+    }
+
+    /// Embed the JSON representation of a manifest into the footer of the WAD, replacing the
+    /// old one (like [Self::write_footer]).
+    ///
+    /// Be aware that the header and the footer will no longer match the hashes of the embedded
+    /// manifest if it was generated from the same WAD, as the size of the footer changes.
+    pub fn embed_manifest<T: Write + Seek>(
+        &mut self,
+        stream: T,
+        title_metadata: &TitleMetadata,
+        manifest: &WadManifest,
+    ) -> Result<(), InstallableWadError> {
+        self.write_footer_bytes(stream, title_metadata, manifest.to_json().as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title_metadata::TitleMetadataContentEntryKind;
+    use crate::wad::installable::test_wad::{self, TestWad, TestWadBuilder};
+
+    #[test]
+    fn generate_and_embed_manifest() {
+        let TestWad {
+            mut wad,
+            mut stream,
+            title_metadata,
+            ..
+        } = TestWadBuilder::new()
+            .content_with_entry(
+                test_wad::content_entry(7, 0, TitleMetadataContentEntryKind::Normal, 0),
+                [0xAA; 0x10],
+            )
+            .build();
+
+        let content_selector = title_metadata.select_with_id(7);
+
+        let mut encrypted_content = vec![];
+        wad.encrypted_content_view(&mut stream, &title_metadata, content_selector)
+            .unwrap()
+            .read_to_end(&mut encrypted_content)
+            .unwrap();
+
+        wad.seek_content(&mut stream, &title_metadata, content_selector)
+            .unwrap();
+        let content_offset = stream.position();
+
+        let manifest = wad
+            .manifest(&mut stream, &title_metadata, Some("test \"1\".wad"))
+            .unwrap();
+
+        assert_eq!(manifest.entries.len(), 5);
+        assert_eq!(manifest.entry(WadManifestSection::Footer), None);
+
+        let content_entry = manifest
+            .entry(WadManifestSection::Content { id: 7, index: 0 })
+            .unwrap();
+        assert_eq!(content_entry.offset, content_offset);
+        assert_eq!(
+            content_entry.sha256,
+            <[u8; 32]>::from(Sha256::digest(encrypted_content))
+        );
+
+        let json = manifest.to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["source_file_name"], "test \"1\".wad");
+        assert_eq!(value["sections"].as_array().unwrap().len(), 5);
+        assert_eq!(value["sections"][0]["section"], "header");
+        assert_eq!(value["sections"][0].get("id"), None);
+
+        let content_value = &value["sections"][4];
+        assert_eq!(content_value["section"], "content");
+        assert_eq!(content_value["id"], 7);
+        assert_eq!(content_value["index"], 0);
+        assert_eq!(content_value["offset"], content_offset);
+        assert_eq!(
+            content_value["sha256"],
+            util::hex::encode(&content_entry.sha256)
+        );
+
+        stream.set_position(0);
+        wad.embed_manifest(&mut stream, &title_metadata, &manifest)
+            .unwrap();
+
+        assert_eq!(wad.footer_size as usize, json.len());
+        assert!(stream.get_ref().ends_with(json.as_bytes()));
+    }
+}
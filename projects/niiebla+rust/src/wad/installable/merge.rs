@@ -0,0 +1,280 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::CryptographicMethod;
+use crate::content_transform::{ContentPipeline, Truncate};
+use crate::ticket::PreSwitchTicket;
+use crate::title_id::{TitleId, WiiTitleKind};
+use crate::title_metadata::TitleMetadata;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use std::any::Any;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+impl InstallableWad {
+    /// Merge the contents of other WAD of the same title into this one (like the parts of a DLC
+    /// distributed as separate WADs), producing a single installable package. The other WAD can
+    /// also be a DLC (`00010005`) of this title when this one is a disc (`00010000`) or a
+    /// channel (`00010001`) with the same lower title ID (see [Self::is_mergeable_title]).
+    ///
+    /// The contents whose index is not already present are decrypted, encrypted again with the
+    /// title key of `ticket` and added at the end of the WAD, their entries are appended to
+    /// `title_metadata` and access to them is granted on `ticket`. Returns the number of merged
+    /// contents.
+    ///
+    /// Be aware that both the ticket and the title metadata will need to be signed again
+    /// (usually fakesigned).
+    pub fn merge<T: Read + Write + Seek + Any, S: Read + Seek>(
+        &mut self,
+        stream: &mut T,
+        ticket: &mut PreSwitchTicket,
+        title_metadata: &mut TitleMetadata,
+        other_wad: &Self,
+        mut other_stream: S,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<usize, InstallableWadError> {
+        let other_ticket = other_wad.ticket(&mut other_stream)?;
+        let other_title_metadata = other_wad.title_metadata(&mut other_stream)?;
+
+        if !Self::is_mergeable_title(&title_metadata.title_id, &other_title_metadata.title_id) {
+            return Err(InstallableWadError::TitleIdMismatch);
+        }
+
+        // Each modification moves the stream away from the start of the WAD
+        let wad_position = stream.stream_position()?;
+        let mut number_of_merged_contents = 0;
+
+        for (i, content_entry) in other_title_metadata
+            .content_chunk_entries
+            .iter()
+            .enumerate()
+        {
+            if title_metadata
+                .content_chunk_entries
+                .iter()
+                .any(|entry| entry.index == content_entry.index)
+            {
+                continue;
+            }
+
+            let content_selector = other_title_metadata.select_with_physical_position(i);
+
+            // The encrypted data is padded to the AES block size
+            let mut data = vec![];
+            ContentPipeline::new()
+                .then(other_ticket.decryption_transform(
+                    &other_title_metadata,
+                    content_selector,
+                    cryptographic_method,
+                )?)
+                .then(Truncate::new(content_entry.size))
+                .run(
+                    other_wad.encrypted_content_view(
+                        &mut other_stream,
+                        &other_title_metadata,
+                        content_selector,
+                    )?,
+                    &mut data,
+                )?;
+
+            stream.seek(SeekFrom::Start(wad_position))?;
+            self.modify_content(stream)
+                .set_cryptography(ticket, cryptographic_method)
+                .set_id(content_entry.id)
+                .set_index(content_entry.index)
+                .set_kind(content_entry.kind)
+                .add(Cursor::new(data), title_metadata)?;
+
+            ticket.set_content_access(content_entry.index, true).ok_or(
+                InstallableWadError::ContentIndexOutOfBounds(content_entry.index),
+            )?;
+
+            number_of_merged_contents += 1;
+        }
+
+        if number_of_merged_contents > 0 {
+            stream.seek(SeekFrom::Start(wad_position))?;
+            self.write_ticket_safe(&mut *stream, ticket, title_metadata)?;
+        }
+
+        Ok(number_of_merged_contents)
+    }
+
+    /// Check if the contents of the other title can be merged into the base title: both are the
+    /// same title, or the other one is a DLC of the base disc or channel. The four character
+    /// code of the lower title ID of a DLC is the one of its title with the first character
+    /// lowercased (`00010005-735A4245` is a DLC of `00010000-535A4245`, `sZBE` and `SZBE`).
+    pub fn is_mergeable_title(base_title_id: &TitleId, other_title_id: &TitleId) -> bool {
+        if base_title_id.inner() == other_title_id.inner() {
+            return true;
+        }
+
+        matches!(
+            base_title_id.wii_kind(),
+            WiiTitleKind::Disc | WiiTitleKind::Channel
+        ) && other_title_id.wii_kind() == WiiTitleKind::Dlc
+            && base_title_id
+                .lower_half()
+                .to_be_bytes()
+                .eq_ignore_ascii_case(&other_title_id.lower_half().to_be_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+
+    const GAME_TITLE_ID: u64 = 0x00010001_57414245;
+    const DLC_TITLE_ID: u64 = 0x00010005_77414245;
+
+    fn build_wad(title_id: u64, contents: &[(u32, &[u8])]) -> TestWad {
+        let mut builder = TestWadBuilder::with_title(
+            templates::homebrew_ticket(title_id),
+            templates::homebrew_title_metadata(title_id),
+        );
+
+        for (id, data) in contents {
+            builder = builder.content(*id, data.to_vec());
+        }
+
+        builder.build()
+    }
+
+    #[test]
+    fn merge_dlc_into_its_title() {
+        let TestWad {
+            mut wad,
+            mut stream,
+            mut ticket,
+            mut title_metadata,
+        } = build_wad(GAME_TITLE_ID, &[(0, &[0x11; 0x40])]);
+
+        // The DLC shares the content with index 0 (its banner) with the title
+        let dlc = build_wad(
+            DLC_TITLE_ID,
+            &[(0, &[0x22; 0x40]), (1, &[0x33; 0x25]), (2, &[0x44; 0x53])],
+        );
+
+        // The contents of the DLC are not accessible by default
+        ticket.set_content_access(1, false).unwrap();
+        ticket.set_content_access(2, false).unwrap();
+
+        let merged = wad
+            .merge(
+                &mut stream,
+                &mut ticket,
+                &mut title_metadata,
+                &dlc.wad,
+                dlc.stream,
+                CryptographicMethod::Wii,
+            )
+            .unwrap();
+
+        assert_eq!(merged, 2);
+        assert_eq!(title_metadata.content_chunk_entries.len(), 3);
+        assert!(ticket.can_access_content(1));
+        assert!(ticket.can_access_content(2));
+
+        for (index, expected) in [
+            (0, [0x11; 0x40].as_slice()),
+            (1, &[0x33; 0x25]),
+            (2, &[0x44; 0x53]),
+        ] {
+            let mut content = vec![];
+
+            stream.set_position(0);
+            wad.decrypted_content_view(
+                &mut stream,
+                &ticket,
+                &title_metadata,
+                CryptographicMethod::Wii,
+                title_metadata.select_with_index(index),
+            )
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+
+            assert_eq!(content, expected);
+
+            // The hash is computed over the data without the padding of the encryption
+            let content_entry = title_metadata
+                .select_with_index(index)
+                .content_entry(&title_metadata)
+                .unwrap();
+            assert!(InstallableWad::has_valid_hash(content.as_slice(), &content_entry).unwrap());
+        }
+
+        // The ticket and title metadata were written into the WAD
+        stream.set_position(0);
+        let wad = crate::wad::Wad::try_new_installable(&mut stream).unwrap();
+        assert_eq!(
+            wad.title_metadata(&mut stream)
+                .unwrap()
+                .content_chunk_entries
+                .len(),
+            3
+        );
+        assert!(wad.ticket(&mut stream).unwrap().can_access_content(2));
+    }
+
+    #[test]
+    fn merge_unrelated_title() {
+        let TestWad {
+            mut wad,
+            mut stream,
+            mut ticket,
+            mut title_metadata,
+        } = build_wad(GAME_TITLE_ID, &[(0, &[0x11; 0x40])]);
+
+        // A DLC of other title
+        let other = build_wad(0x00010005_77414246, &[(1, &[0x33; 0x20])]);
+
+        assert!(matches!(
+            wad.merge(
+                &mut stream,
+                &mut ticket,
+                &mut title_metadata,
+                &other.wad,
+                other.stream,
+                CryptographicMethod::Wii,
+            ),
+            Err(InstallableWadError::TitleIdMismatch)
+        ));
+    }
+
+    #[test]
+    fn mergeable_titles() {
+        let title_id = |value| TitleId::new(value);
+        let game = title_id(GAME_TITLE_ID);
+
+        assert!(InstallableWad::is_mergeable_title(&game, &game));
+        assert!(InstallableWad::is_mergeable_title(
+            &game,
+            &title_id(DLC_TITLE_ID)
+        ));
+        assert!(InstallableWad::is_mergeable_title(
+            &title_id(0x00010000_535A4245),
+            &title_id(0x00010005_735A4245)
+        ));
+
+        // A DLC cannot be the base title
+        assert!(!InstallableWad::is_mergeable_title(
+            &title_id(DLC_TITLE_ID),
+            &game
+        ));
+        // Other title with the same code
+        assert!(!InstallableWad::is_mergeable_title(
+            &game,
+            &title_id(0x00010008_57414245)
+        ));
+        assert!(!InstallableWad::is_mergeable_title(
+            &game,
+            &title_id(0x00010005_77414246)
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::CryptographicMethod;
+use crate::content_transform::{ContentPipeline, Truncate};
+use crate::title_id::TitleId;
+use crate::title_metadata::{TitleMetadataContentEntryKind, TitleMetadataPlatformData};
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use byteorder::{BE, ReadBytesExt, WriteBytesExt};
+use sha1::{Digest, Sha1};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+
+/// The filesystem of the NAND of a Nintendo Wii extracted into a directory, the layout used by
+/// EmuNAND and by emulators like Dolphin.
+///
+/// The files inside are stored decrypted, raw NAND images (encrypted with the keys of the console)
+/// are not supported.
+#[derive(Debug, Clone)]
+pub struct ExtractedNand {
+    root: PathBuf,
+}
+
+impl ExtractedNand {
+    /// First UID given by the system to an installed title.
+    pub const FIRST_UID: u32 = 0x1000;
+
+    const UID_ENTRY_SIZE: usize = 12;
+    const CONTENT_MAP_ENTRY_SIZE: usize = 28;
+
+    /// Create a new [ExtractedNand] on the given root directory.
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Get the root directory of the NAND.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the directory of a title (`/title/<higher half>/<lower half>`).
+    pub fn title_path(&self, title_id: &TitleId) -> PathBuf {
+        self.root
+            .join("title")
+            .join(format!("{:08x}", title_id.higher_half()))
+            .join(format!("{:08x}", title_id.lower_half()))
+    }
+
+    /// Get the path of the ticket of a title (`/ticket/<higher half>/<lower half>.tik`).
+    pub fn ticket_path(&self, title_id: &TitleId) -> PathBuf {
+        self.root
+            .join("ticket")
+            .join(format!("{:08x}", title_id.higher_half()))
+            .join(format!("{:08x}.tik", title_id.lower_half()))
+    }
+
+    /// Get the path of the configuration of the system (`/shared2/sys/SYSCONF`).
+    pub fn sysconf_path(&self) -> PathBuf {
+        self.root.join("shared2").join("sys").join("SYSCONF")
+    }
+
+    /// Get the UID assigned to a title on `/sys/uid.sys`, `None` if it has never been installed.
+    pub fn uid(&self, title_id: &TitleId) -> io::Result<Option<u32>> {
+        Ok(self
+            .uid_entries()?
+            .into_iter()
+            .find(|(id, _)| *id == title_id.inner())
+            .map(|(_, uid)| uid))
+    }
+
+    fn uid_entries(&self) -> io::Result<Vec<(u64, u32)>> {
+        let path = self.root.join("sys").join("uid.sys");
+
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let data = fs::read(path)?;
+        let mut stream = Cursor::new(&data);
+        let mut entries = vec![];
+
+        for _ in 0..data.len() / Self::UID_ENTRY_SIZE {
+            entries.push((stream.read_u64::<BE>()?, stream.read_u32::<BE>()?));
+        }
+
+        Ok(entries)
+    }
+
+    /// Add the title to `/sys/uid.sys` if it is not already there, returns its UID.
+    fn register_uid(&self, title_id: &TitleId) -> io::Result<u32> {
+        let entries = self.uid_entries()?;
+
+        if let Some((_, uid)) = entries.iter().find(|(id, _)| *id == title_id.inner()) {
+            return Ok(*uid);
+        }
+
+        let uid = entries
+            .iter()
+            .map(|(_, uid)| uid + 1)
+            .max()
+            .unwrap_or(Self::FIRST_UID);
+
+        let sys_path = self.root.join("sys");
+        fs::create_dir_all(&sys_path)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(sys_path.join("uid.sys"))?;
+
+        // Discard any trailing garbage so the new entry stays aligned
+        file.set_len((entries.len() * Self::UID_ENTRY_SIZE) as u64)?;
+
+        title_id.dump(&mut file)?;
+        file.write_u32::<BE>(uid)?;
+
+        Ok(uid)
+    }
+
+    /// Store a shared content on `/shared1` and register it on `/shared1/content.map` if no
+    /// content with the same hash is already there.
+    fn add_shared_content(&self, data: &[u8]) -> io::Result<()> {
+        let shared_path = self.root.join("shared1");
+        fs::create_dir_all(&shared_path)?;
+
+        let content_map_path = shared_path.join("content.map");
+        let mut content_map = if content_map_path.exists() {
+            fs::read(&content_map_path)?
+        } else {
+            vec![]
+        };
+
+        content_map.truncate(
+            content_map.len() / Self::CONTENT_MAP_ENTRY_SIZE * Self::CONTENT_MAP_ENTRY_SIZE,
+        );
+
+        let hash = Sha1::digest(data);
+
+        if content_map
+            .chunks_exact(Self::CONTENT_MAP_ENTRY_SIZE)
+            .any(|entry| entry[8..] == hash[..])
+        {
+            return Ok(());
+        }
+
+        // The names are the position on the map in lowercase hexadecimal
+        let name = format!("{:08x}", content_map.len() / Self::CONTENT_MAP_ENTRY_SIZE);
+
+        fs::write(shared_path.join(format!("{name}.app")), data)?;
+
+        content_map.extend_from_slice(name.as_bytes());
+        content_map.extend_from_slice(&hash);
+        fs::write(content_map_path, content_map)?;
+
+        Ok(())
+    }
+}
+
+impl InstallableWad {
+    /// Install the title stored in the WAD into an extracted NAND like the system would, the
+    /// ticket is written into `/ticket`, the title metadata and the contents into `/title` (the
+    /// shared ones into `/shared1`) and the title is registered on `/sys/uid.sys`. Returns the
+    /// UID of the title.
+    ///
+    /// The contents are decrypted and their hashes validated before being written. The save data
+    /// directory of the title is created but its previous data (if any) is kept.
+    pub fn install_into_nand<T: Read + Seek>(
+        &self,
+        mut stream: T,
+        nand: &ExtractedNand,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<u32, InstallableWadError> {
+        let ticket = self.ticket(&mut stream)?;
+        let title_metadata = self.title_metadata(&mut stream)?;
+
+        let TitleMetadataPlatformData::Wii { .. } = title_metadata.platform_data else {
+            return Err(InstallableWadError::NotAWiiTitle);
+        };
+
+        let title_id = &title_metadata.title_id;
+        let title_path = nand.title_path(title_id);
+        let content_path = title_path.join("content");
+
+        fs::create_dir_all(&content_path)?;
+        fs::create_dir_all(title_path.join("data"))?;
+
+        for (i, content_entry) in title_metadata.content_chunk_entries.iter().enumerate() {
+            let content_selector = title_metadata.select_with_physical_position(i);
+
+            // The encrypted data is padded to the AES block size
+            let mut data = vec![];
+            ContentPipeline::new()
+                .then(ticket.decryption_transform(
+                    &title_metadata,
+                    content_selector,
+                    cryptographic_method,
+                )?)
+                .then(Truncate::new(content_entry.size))
+                .run(
+                    self.encrypted_content_view(&mut stream, &title_metadata, content_selector)?,
+                    &mut data,
+                )?;
+
+            if !Self::has_valid_hash(data.as_slice(), content_entry)? {
+                return Err(InstallableWadError::ContentHashMismatch(content_entry.id));
+            }
+
+            if let TitleMetadataContentEntryKind::Shared = content_entry.kind {
+                nand.add_shared_content(&data)?;
+            } else {
+                fs::write(
+                    content_path.join(Self::split_content_file_name(content_entry.id)),
+                    data,
+                )?;
+            }
+        }
+
+        let mut view = self.title_metadata_view(&mut stream)?;
+        let mut file = File::create(content_path.join("title.tmd"))?;
+        io::copy(&mut view, &mut file)?;
+
+        let ticket_path = nand.ticket_path(title_id);
+        if let Some(parent) = ticket_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut view = self.ticket_view(&mut stream)?;
+        let mut file = File::create(ticket_path)?;
+        io::copy(&mut view, &mut file)?;
+
+        Ok(nand.register_uid(title_id)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::installable::test_wad::{self, TestWad, TestWadBuilder};
+
+    #[test]
+    fn install_into_extracted_nand() {
+        let TestWad {
+            wad,
+            mut stream,
+            title_metadata,
+            ..
+        } = TestWadBuilder::new()
+            // Sizes that are not a multiple of the AES block size
+            .content(0, vec![0; 0x35])
+            .content_with_entry(
+                test_wad::content_entry(1, 1, TitleMetadataContentEntryKind::Shared, 0),
+                vec![1; 0x2B],
+            )
+            .build();
+
+        let root = std::env::temp_dir().join(format!("niiebla_nand_{}", std::process::id()));
+        let nand = ExtractedNand::new(&root);
+
+        assert_eq!(
+            wad.install_into_nand(&mut stream, &nand, CryptographicMethod::Wii)
+                .unwrap(),
+            0x1000
+        );
+        // Installing it again must not register the title twice
+        assert_eq!(
+            wad.install_into_nand(&mut stream, &nand, CryptographicMethod::Wii)
+                .unwrap(),
+            0x1000
+        );
+
+        let title_path = nand.title_path(&title_metadata.title_id);
+        assert_eq!(
+            fs::read(title_path.join("content/00000000.app")).unwrap(),
+            [0; 0x35]
+        );
+        assert!(!title_path.join("content/00000001.app").exists());
+        assert!(title_path.join("content/title.tmd").exists());
+        assert!(nand.ticket_path(&title_metadata.title_id).exists());
+
+        assert_eq!(
+            fs::read(root.join("shared1/00000000.app")).unwrap(),
+            [1; 0x2B]
+        );
+        assert_eq!(
+            fs::read(root.join("shared1/content.map")).unwrap().len(),
+            ExtractedNand::CONTENT_MAP_ENTRY_SIZE
+        );
+        assert_eq!(
+            fs::read(root.join("sys/uid.sys")).unwrap().len(),
+            ExtractedNand::UID_ENTRY_SIZE
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
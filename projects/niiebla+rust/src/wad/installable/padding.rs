@@ -0,0 +1,272 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::title_metadata::TitleMetadata;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use std::io::{Read, Seek, SeekFrom, Write};
+use util::{ReadEx, StreamPin};
+
+impl InstallableWad {
+    /// Check if the contents stored inside the WAD stream are aligned to the section boundary
+    /// (with zeroed padding between them), as expected by the system and the rest of this API.
+    pub fn has_aligned_contents<T: Read + Seek>(
+        &self,
+        mut stream: T,
+        title_metadata: &TitleMetadata,
+    ) -> Result<bool, InstallableWadError> {
+        let stream_len = stream.seek(SeekFrom::End(0))?;
+
+        // The header is always aligned to the boundary
+        let mut content_offset = Self::HEADER_SIZE
+            + Self::align_u64(self.certificate_chain_size)
+            + Self::align_u64(self.ticket_size)
+            + Self::align_u64(self.title_metadata_size);
+
+        let number_of_entries = title_metadata.content_chunk_entries.len();
+
+        for (i, content_entry) in title_metadata.content_chunk_entries.iter().enumerate() {
+            let content_end = content_offset + content_entry.size;
+            let aligned_content_end = util::align_to_boundary(content_end, Self::SECTION_BOUNDARY);
+
+            // The padding after the last content may be missing if the WAD has no footer
+            let expected_end = if i + 1 == number_of_entries {
+                content_end
+            } else {
+                aligned_content_end
+            };
+
+            if expected_end > stream_len {
+                return Ok(false);
+            }
+
+            let mut padding = vec![0; (expected_end - content_end) as usize];
+
+            stream.seek(SeekFrom::Start(content_end))?;
+            stream.read_exact(&mut padding)?;
+
+            // Misaligned contents have the data of the next content where the padding should be
+            if padding.iter().any(|&byte| byte != 0) {
+                return Ok(false);
+            }
+
+            content_offset = aligned_content_end;
+        }
+
+        Ok(true)
+    }
+
+    /// Realign the contents stored inside the WAD stream if they were stored back to back
+    /// without padding (common on WADs created by buggy tools) or zero their padding if it has
+    /// garbage, the footer is moved after them and the size of the contents in the header is
+    /// updated. Returns `true` if the contents were rewritten.
+    ///
+    /// The contents are assumed to be aligned if the stream is big enough to store them (and
+    /// the footer) aligned, otherwise they are assumed to be stored back to back.
+    ///
+    /// The stream is assumed to be positioned at the start of the WAD.
+    pub fn normalize_padding<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        title_metadata: &TitleMetadata,
+    ) -> Result<bool, InstallableWadError> {
+        let mut stream = StreamPin::new(stream)?;
+
+        if self.has_aligned_contents(&mut stream, title_metadata)? {
+            return Ok(false);
+        }
+
+        let contents_offset = Self::HEADER_SIZE
+            + Self::align_u64(self.certificate_chain_size)
+            + Self::align_u64(self.ticket_size)
+            + Self::align_u64(self.title_metadata_size);
+
+        stream.seek(SeekFrom::End(0))?;
+        let stream_len = stream.relative_position()? as u64;
+
+        // End of the contents if stored aligned, the padding after the last one may be missing
+        // if the WAD has no footer
+        let aligned_contents_end = title_metadata.content_chunk_entries.iter().fold(
+            contents_offset,
+            |content_offset, content_entry| {
+                util::align_to_boundary(content_offset, Self::SECTION_BOUNDARY) + content_entry.size
+            },
+        );
+
+        let aligned_end = if self.footer_size == 0 {
+            aligned_contents_end
+        } else {
+            util::align_to_boundary(aligned_contents_end, Self::SECTION_BOUNDARY)
+                + self.footer_size as u64
+        };
+
+        let is_aligned = aligned_end <= stream_len;
+
+        let mut contents = Vec::with_capacity(title_metadata.content_chunk_entries.len());
+        let mut content_offset = contents_offset;
+
+        for content_entry in &title_metadata.content_chunk_entries {
+            stream.seek_from_pin(content_offset as i64)?;
+            contents.push(stream.read_exact_vec(content_entry.size)?);
+
+            content_offset += if is_aligned {
+                util::align_to_boundary(content_entry.size, Self::SECTION_BOUNDARY)
+            } else {
+                content_entry.size
+            };
+        }
+
+        stream.seek_from_pin(content_offset as i64)?;
+
+        let mut footer = vec![];
+        (&mut stream)
+            .take(self.footer_size as u64)
+            .read_to_end(&mut footer)?;
+
+        stream.seek_from_pin(contents_offset as i64)?;
+
+        for content_bytes in &contents {
+            stream.write_all(content_bytes)?;
+            stream.align_zeroed(Self::SECTION_BOUNDARY)?;
+        }
+
+        stream.write_all(&footer)?;
+
+        self.content_size = Self::size_field(
+            "contents",
+            contents
+                .iter()
+                .map(|content_bytes| content_bytes.len() as u64)
+                .sum(),
+        )?;
+        self.footer_size = Self::size_field("footer", footer.len() as u64)?;
+
+        stream.seek_from_pin(0)?;
+        self.dump(&mut stream)?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CryptographicMethod;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+    use std::io::Cursor;
+
+    fn build_wad() -> TestWad {
+        TestWadBuilder::new()
+            .content(0, vec![0x11; 0x30])
+            .content(1, vec![0x22; 0x50])
+            .build()
+    }
+
+    fn content_offset(test_wad: &mut TestWad, physical_position: usize) -> u64 {
+        let selector = test_wad
+            .title_metadata
+            .select_with_physical_position(physical_position);
+
+        test_wad
+            .wad
+            .seek_content(&mut test_wad.stream, &test_wad.title_metadata, selector)
+            .unwrap();
+
+        test_wad.stream.position()
+    }
+
+    fn assert_contents(test_wad: &mut TestWad) {
+        for (physical_position, expected) in [(0, [0x11; 0x30].as_slice()), (1, &[0x22; 0x50])] {
+            let mut content = vec![0; expected.len()];
+
+            test_wad
+                .wad
+                .decrypted_content_view(
+                    &mut test_wad.stream,
+                    &test_wad.ticket,
+                    &test_wad.title_metadata,
+                    CryptographicMethod::Wii,
+                    test_wad
+                        .title_metadata
+                        .select_with_physical_position(physical_position),
+                )
+                .unwrap()
+                .read_exact(&mut content)
+                .unwrap();
+
+            assert_eq!(content, expected);
+        }
+    }
+
+    #[test]
+    fn normalize_dirty_padding() {
+        let mut test_wad = build_wad();
+        let first_content_end = content_offset(&mut test_wad, 0) + 0x30;
+
+        // Garbage between the contents, where the padding should be zeroed
+        test_wad.stream.get_mut()[first_content_end as usize..first_content_end as usize + 0x10]
+            .fill(0xFF);
+
+        test_wad.stream.set_position(0);
+        assert!(
+            !test_wad
+                .wad
+                .has_aligned_contents(&mut test_wad.stream, &test_wad.title_metadata)
+                .unwrap()
+        );
+
+        test_wad.stream.set_position(0);
+        assert!(
+            test_wad
+                .wad
+                .normalize_padding(&mut test_wad.stream, &test_wad.title_metadata)
+                .unwrap()
+        );
+
+        assert_eq!(
+            test_wad.stream.get_ref()
+                [first_content_end as usize..first_content_end as usize + 0x10],
+            [0; 0x10]
+        );
+        assert_contents(&mut test_wad);
+
+        // Already normalized
+        test_wad.stream.set_position(0);
+        assert!(
+            !test_wad
+                .wad
+                .normalize_padding(&mut test_wad.stream, &test_wad.title_metadata)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn normalize_back_to_back_contents() {
+        let mut test_wad = build_wad();
+        let original_data = test_wad.stream.get_ref().clone();
+
+        let first_content_end = content_offset(&mut test_wad, 0) as usize + 0x30;
+        let second_content_start = content_offset(&mut test_wad, 1) as usize;
+
+        // Remove the padding after the first content, as done by some buggy tools
+        let mut data = original_data.clone();
+        data.drain(first_content_end..second_content_start);
+        test_wad.stream = Cursor::new(data);
+
+        assert!(
+            test_wad
+                .wad
+                .normalize_padding(&mut test_wad.stream, &test_wad.title_metadata)
+                .unwrap()
+        );
+
+        assert_eq!(
+            test_wad.stream.get_ref()[..original_data.len()],
+            original_data[..]
+        );
+        assert_contents(&mut test_wad);
+    }
+}
@@ -0,0 +1,337 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::CryptographicMethod;
+use crate::TitleMetadata;
+use crate::ticket::PreSwitchTicket;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use std::io::{Read, Seek, SeekFrom, Write};
+use util::{Aes128CbcDec, Aes128CbcEnc, CancellationToken, Cancelled, StreamPin};
+
+impl InstallableWad {
+    /// Size of the chunks re-encrypted at once, must be a multiple of the AES block size.
+    const REKEY_CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Re-encrypt every content of the WAD with the title key of `new_ticket` (previously
+    /// encrypted with the one of `old_ticket`) and replace the ticket stored in the WAD with
+    /// `new_ticket`. Needed when replacing a console-unique ticket with a common one.
+    ///
+    /// The contents are streamed in small chunks and rewritten in place, the hashes of the title
+    /// metadata are taken from the decrypted data so they are still valid after the operation.
+    /// They are validated before writing anything, a wrong `old_ticket` fails with
+    /// [InstallableWadError::ContentHashMismatch] without modifying the WAD.
+    pub fn rekey<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        old_ticket: &PreSwitchTicket,
+        new_ticket: &PreSwitchTicket,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<(), InstallableWadError> {
+        self.rekey_with_cancellation(
+            stream,
+            old_ticket,
+            new_ticket,
+            cryptographic_method,
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Like [Self::rekey] but can be stopped with the given [CancellationToken], failing with
+    /// [InstallableWadError::Cancelled].
+    ///
+    /// A cancelled rekey leaves the WAD untouched, the chunks already re-encrypted are reverted
+    /// back to the old title key (taking at most the same time already spent). Once all the
+    /// contents are re-encrypted the operation is not cancelled anymore.
+    pub fn rekey_with_cancellation<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        old_ticket: &PreSwitchTicket,
+        new_ticket: &PreSwitchTicket,
+        cryptographic_method: CryptographicMethod,
+        cancellation_token: &CancellationToken,
+    ) -> Result<(), InstallableWadError> {
+        let mut stream = StreamPin::new(stream)?;
+        let title_metadata = self.title_metadata(&mut stream)?;
+
+        for (i, content_entry) in title_metadata.content_chunk_entries.iter().enumerate() {
+            cancellation_token.check()?;
+
+            // The view decrypts the whole padded last block but ends at the size of the content
+            let decrypted_content_view = self.decrypted_content_view(
+                &mut stream,
+                old_ticket,
+                &title_metadata,
+                cryptographic_method,
+                title_metadata.select_with_physical_position(i),
+            )?;
+
+            if !Self::has_valid_hash(decrypted_content_view, content_entry)? {
+                return Err(InstallableWadError::ContentHashMismatch(content_entry.id));
+            }
+        }
+
+        let old_title_key = old_ticket.decrypt_title_key(cryptographic_method)?;
+        let new_title_key = new_ticket.decrypt_title_key(cryptographic_method)?;
+
+        for (i, content_entry) in title_metadata.content_chunk_entries.iter().enumerate() {
+            // The encrypted data is padded to the AES block size
+            let len = util::align_to_boundary(content_entry.size, 16);
+
+            let reencrypted_len = self.reencrypt_content(
+                &mut stream,
+                &title_metadata,
+                i,
+                (old_title_key, new_title_key),
+                len,
+                Some(cancellation_token),
+            )?;
+
+            if reencrypted_len < len {
+                // A prefix of a CBC stream can be decrypted on its own
+                self.reencrypt_content(
+                    &mut stream,
+                    &title_metadata,
+                    i,
+                    (new_title_key, old_title_key),
+                    reencrypted_len,
+                    None,
+                )?;
+
+                for (j, content_entry) in
+                    title_metadata.content_chunk_entries[..i].iter().enumerate()
+                {
+                    self.reencrypt_content(
+                        &mut stream,
+                        &title_metadata,
+                        j,
+                        (new_title_key, old_title_key),
+                        util::align_to_boundary(content_entry.size, 16),
+                        None,
+                    )?;
+                }
+
+                return Err(Cancelled.into());
+            }
+        }
+
+        stream.go_to_pin()?;
+        self.write_ticket_safe(&mut stream, new_ticket, &title_metadata)?;
+
+        Ok(())
+    }
+
+    /// Decrypt the first `len` bytes of the content with the first key and encrypt them with
+    /// the second one, in place. Stops before a chunk if the token gets cancelled, returns the
+    /// number of re-encrypted bytes.
+    fn reencrypt_content<T: Read + Write + Seek>(
+        &self,
+        stream: &mut StreamPin<T>,
+        title_metadata: &TitleMetadata,
+        physical_position: usize,
+        (from_title_key, to_title_key): ([u8; 16], [u8; 16]),
+        len: u64,
+        cancellation_token: Option<&CancellationToken>,
+    ) -> Result<u64, InstallableWadError> {
+        let content_selector = title_metadata.select_with_physical_position(physical_position);
+        let iv = PreSwitchTicket::content_iv(title_metadata, content_selector)?;
+
+        // The ciphers keep the CBC chaining between the chunks
+        let mut decryptor = Aes128CbcDec::new(&from_title_key.into(), &iv.into());
+        let mut encryptor = Aes128CbcEnc::new(&to_title_key.into(), &iv.into());
+
+        self.seek_content(&mut *stream, title_metadata, content_selector)?;
+        let start = stream.stream_position()?;
+
+        let mut buffer = vec![0; Self::REKEY_CHUNK_SIZE];
+        let mut offset = 0;
+
+        while offset < len {
+            if cancellation_token.is_some_and(CancellationToken::is_cancelled) {
+                break;
+            }
+
+            let chunk_size = (len - offset).min(buffer.len() as u64) as usize;
+            let chunk = &mut buffer[..chunk_size];
+
+            stream.seek(SeekFrom::Start(start + offset))?;
+            stream.read_exact(chunk)?;
+
+            for block in chunk.chunks_exact_mut(16) {
+                decryptor.decrypt_block_mut(block.into());
+                encryptor.encrypt_block_mut(block.into());
+            }
+
+            stream.seek(SeekFrom::Start(start + offset))?;
+            stream.write_all(chunk)?;
+
+            offset += chunk_size as u64;
+        }
+
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+
+    #[test]
+    fn rekey() {
+        // Bigger than a chunk to test the chaining between them
+        let plaintext: Vec<u8> = (0..InstallableWad::REKEY_CHUNK_SIZE + 0x30)
+            .map(|i| i as u8)
+            .collect();
+
+        let TestWad {
+            mut wad,
+            mut stream,
+            ticket: old_ticket,
+            title_metadata,
+        } = TestWadBuilder::new().content(0, plaintext.clone()).build();
+
+        let mut new_ticket = templates::homebrew_channel_ticket();
+        new_ticket.encrypted_title_key = [0x42; 16];
+
+        let mut wrong_ticket = templates::homebrew_channel_ticket();
+        wrong_ticket.encrypted_title_key = [0x24; 16];
+
+        stream.set_position(0);
+        let untouched_stream = stream.clone();
+        assert!(matches!(
+            wad.rekey(
+                &mut stream,
+                &wrong_ticket,
+                &new_ticket,
+                CryptographicMethod::Wii
+            ),
+            Err(InstallableWadError::ContentHashMismatch(0))
+        ));
+        assert_eq!(stream.get_ref(), untouched_stream.get_ref());
+
+        let cancellation_token = CancellationToken::new();
+        cancellation_token.cancel();
+
+        stream.set_position(0);
+        assert!(matches!(
+            wad.rekey_with_cancellation(
+                &mut stream,
+                &old_ticket,
+                &new_ticket,
+                CryptographicMethod::Wii,
+                &cancellation_token,
+            ),
+            Err(InstallableWadError::Cancelled(Cancelled))
+        ));
+        assert_eq!(stream.get_ref(), untouched_stream.get_ref());
+
+        // What a cancelled rekey does to revert the partially re-encrypted content
+        let keys = (
+            old_ticket
+                .decrypt_title_key(CryptographicMethod::Wii)
+                .unwrap(),
+            new_ticket
+                .decrypt_title_key(CryptographicMethod::Wii)
+                .unwrap(),
+        );
+        let mut pinned_stream = StreamPin::new(&mut stream).unwrap();
+        wad.reencrypt_content(&mut pinned_stream, &title_metadata, 0, keys, 0x20, None)
+            .unwrap();
+        wad.reencrypt_content(
+            &mut pinned_stream,
+            &title_metadata,
+            0,
+            (keys.1, keys.0),
+            0x20,
+            None,
+        )
+        .unwrap();
+        assert_eq!(stream.get_ref(), untouched_stream.get_ref());
+
+        stream.set_position(0);
+        wad.rekey(
+            &mut stream,
+            &old_ticket,
+            &new_ticket,
+            CryptographicMethod::Wii,
+        )
+        .unwrap();
+
+        stream.set_position(0);
+        let ticket = wad.ticket(&mut stream).unwrap();
+        let title_metadata = wad.title_metadata(&mut stream).unwrap();
+
+        let mut data = vec![];
+        wad.decrypted_content_view(
+            &mut stream,
+            &ticket,
+            &title_metadata,
+            CryptographicMethod::Wii,
+            title_metadata.select_with_physical_position(0),
+        )
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+
+        assert_eq!(ticket.encrypted_title_key, [0x42; 16]);
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn rekey_unaligned_contents() {
+        let plaintexts: [Vec<u8>; 2] = [
+            (0..0x25).collect(),
+            (0..InstallableWad::REKEY_CHUNK_SIZE + 0x13)
+                .map(|i| (i * 3) as u8)
+                .collect(),
+        ];
+
+        let TestWad {
+            mut wad,
+            mut stream,
+            ticket: old_ticket,
+            ..
+        } = TestWadBuilder::new()
+            .content(0, plaintexts[0].clone())
+            .content(1, plaintexts[1].clone())
+            .build();
+
+        let mut new_ticket = templates::homebrew_channel_ticket();
+        new_ticket.encrypted_title_key = [0x42; 16];
+
+        stream.set_position(0);
+        wad.rekey(
+            &mut stream,
+            &old_ticket,
+            &new_ticket,
+            CryptographicMethod::Wii,
+        )
+        .unwrap();
+
+        stream.set_position(0);
+        let ticket = wad.ticket(&mut stream).unwrap();
+        let title_metadata = wad.title_metadata(&mut stream).unwrap();
+
+        for (position, plaintext) in plaintexts.iter().enumerate() {
+            let mut data = vec![];
+            wad.decrypted_content_view(
+                &mut stream,
+                &ticket,
+                &title_metadata,
+                CryptographicMethod::Wii,
+                title_metadata.select_with_physical_position(position),
+            )
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+            assert_eq!(&data, plaintext);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::CryptographicMethod;
+use crate::imet::Imet;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use std::io::{Cursor, Read, Seek, Write};
+
+impl InstallableWad {
+    /// Rename the channel inside the WAD stream, setting the name of all the languages of the
+    /// [Imet] header of its banner (see [Imet::set_name]).
+    ///
+    /// The content with index 0 (the one with the banner) is rewritten and its entry of the title
+    /// metadata updated, the title metadata is fakesigned so the title can only be installed on
+    /// a console running an IOS vulnerable to the
+    /// [Trucha bug](https://wiibrew.org/wiki/Signing_bug).
+    pub fn rename_channel<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        name: &str,
+    ) -> Result<(), InstallableWadError> {
+        let mut session = self.edit_session(stream)?;
+        let content_selector = session.title_metadata.select_with_index(0);
+
+        let mut data = session.decrypted_content(content_selector, CryptographicMethod::Wii)?;
+
+        let imet_position =
+            Imet::position_in_content(&data).ok_or(InstallableWadError::MissingBanner)? as usize;
+
+        let mut imet = Imet::new(Cursor::new(&data[imet_position..]))?;
+        imet.set_name(name)?;
+        imet.dump(&mut data[imet_position..])?;
+
+        session.replace_content(content_selector, &data, CryptographicMethod::Wii)?;
+        session.title_metadata.fakesign()?;
+
+        session.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imet::ImetLanguage;
+    use crate::wad::installable::test_wad::{self, TestWad, TestWadBuilder};
+    use sha1::{Digest, Sha1};
+
+    #[test]
+    fn rename_channel() {
+        let mut imet = Imet {
+            file_sizes: [0x20, 0x20, 0x20],
+            flag: 0,
+            names: [const { String::new() }; Imet::NUMBER_OF_NAMES],
+        };
+        imet.set_name("Old Name").unwrap();
+
+        // The rest of the banner (the U8 archive) must be kept untouched
+        let mut banner = vec![];
+        imet.dump(&mut banner).unwrap();
+        banner.extend([0x55; 0x40]);
+
+        // The edit session parses the certificate chain, a WAD always stores three certificates
+        let TestWad {
+            mut wad,
+            mut stream,
+            ticket,
+            ..
+        } = TestWadBuilder::new()
+            .certificate_chain(test_wad::certificate_chain(3))
+            .content(0, banner.clone())
+            .build();
+
+        wad.rename_channel(&mut stream, "New Name").unwrap();
+
+        stream.set_position(0);
+        let title_metadata = wad.title_metadata(&mut stream).unwrap();
+
+        let mut data = vec![];
+        wad.decrypted_content_view(
+            &mut stream,
+            &ticket,
+            &title_metadata,
+            CryptographicMethod::Wii,
+            title_metadata.select_with_index(0),
+        )
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+
+        let imet = Imet::new(Cursor::new(&data)).unwrap();
+
+        assert_eq!(imet.name(ImetLanguage::English), "New Name");
+        assert_eq!(data[Imet::SIZE as usize..], banner[Imet::SIZE as usize..]);
+
+        let mut title_metadata_data = Cursor::new(vec![]);
+        title_metadata.dump(&mut title_metadata_data).unwrap();
+
+        let signed_data_offset = title_metadata.signed_blob_header.size() as usize - 64;
+        assert_eq!(
+            Sha1::digest(&title_metadata_data.get_ref()[signed_data_offset..])[0],
+            0
+        );
+
+        // The hash of the title metadata must match the new content
+        stream.set_position(0);
+        wad.copy_to(&mut stream, std::io::sink(), CryptographicMethod::Wii, true)
+            .unwrap();
+
+        stream.set_position(0);
+        assert!(matches!(
+            wad.rename_channel(&mut stream, &"A".repeat(43)),
+            Err(InstallableWadError::ImetError(_))
+        ));
+    }
+}
@@ -0,0 +1,384 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::title_metadata::TitleMetadata;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use crate::wad::installable::ContentsStore;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use util::{CancellationToken, SpillBuffer, StreamPin};
+
+/// Tuning of how the `write_*_safe_with_options(...)` methods move the contents when a section
+/// before them changes its size.
+///
+/// By default the contents are copied into memory, big WADs (or Wii U packages) may need to use
+/// a temporary file or move the contents in place to be edited on machines with low memory.
+#[derive(Debug, Clone)]
+pub struct ShiftOptions {
+    /// Size of the chunks in which the contents are copied when using a temporary file or moving
+    /// them in place, the maximum memory used by those strategies.
+    pub chunk_size: usize,
+
+    /// Total size of the contents from which they are copied into a temporary file instead of
+    /// into memory, `None` to always copy them into memory.
+    pub temporary_file_threshold: Option<u64>,
+
+    /// Move the contents inside the stream itself without any intermediate copy, the direction
+    /// of the copy is chosen to never overwrite data that has not been moved yet. Takes
+    /// precedence over [Self::temporary_file_threshold].
+    ///
+    /// Be aware that the WAD is left corrupted if the move fails halfway.
+    pub in_place: bool,
+
+    /// Token to stop the write, it is only checked while the contents are being saved (before
+    /// anything is written) so a cancelled write fails with [InstallableWadError::Cancelled]
+    /// leaving the WAD untouched. Moving the contents in place cannot be cancelled once started.
+    pub cancellation_token: Option<CancellationToken>,
+}
+
+impl ShiftOptions {
+    /// Default size of the chunks, 1 MiB.
+    pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+    /// Options that move the contents in place, without copying them into memory.
+    pub fn in_place() -> Self {
+        Self {
+            in_place: true,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for ShiftOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            temporary_file_threshold: None,
+            in_place: false,
+            cancellation_token: None,
+        }
+    }
+}
+
+/// The contents of a WAD saved to be written again after a section before them is resized.
+pub(super) enum ShiftedContents {
+    Empty,
+    Memory(ContentsStore),
+    Buffered(SpillBuffer, Vec<u64>),
+    InPlace { old_offset: u64, sizes: Vec<u64> },
+}
+
+impl InstallableWad {
+    fn contents_offset(&self) -> u64 {
+        // The header is always aligned to the boundary
+        Self::HEADER_SIZE
+            + Self::align_u64(self.certificate_chain_size)
+            + Self::align_u64(self.ticket_size)
+            + Self::align_u64(self.title_metadata_size)
+    }
+
+    /// Save all the contents following the given options, must be called before changing the
+    /// sizes of the sections.
+    pub(super) fn store_contents_with_options<T: Read + Write + Seek>(
+        &mut self,
+        stream: &mut StreamPin<T>,
+        title_metadata: &TitleMetadata,
+        options: &ShiftOptions,
+    ) -> Result<ShiftedContents, InstallableWadError> {
+        let cancellation_token = options.cancellation_token.as_ref();
+
+        if let Some(cancellation_token) = cancellation_token {
+            cancellation_token.check()?;
+        }
+
+        // The last block of the encrypted data goes past the size of the content
+        let sizes: Vec<u64> = title_metadata
+            .content_chunk_entries
+            .iter()
+            .map(|content_entry| util::align_to_boundary(content_entry.size, 16))
+            .collect();
+
+        if sizes.is_empty() {
+            return Ok(ShiftedContents::Empty);
+        }
+
+        if options.in_place {
+            return Ok(ShiftedContents::InPlace {
+                old_offset: self.contents_offset(),
+                sizes,
+            });
+        }
+
+        if let Some(threshold) = options.temporary_file_threshold {
+            let mut buffer = SpillBuffer::new(threshold);
+
+            for (i, size) in sizes.iter().enumerate() {
+                self.seek_content(
+                    &mut *stream,
+                    title_metadata,
+                    title_metadata.select_with_physical_position(i),
+                )?;
+
+                copy_chunked(
+                    &mut *stream,
+                    &mut buffer,
+                    *size,
+                    options.chunk_size,
+                    cancellation_token,
+                )?;
+            }
+
+            return Ok(ShiftedContents::Buffered(buffer, sizes));
+        }
+
+        let contents_store = self.store_contents(&mut *stream, title_metadata, 0)?;
+
+        // Copying into memory is fast, checking after it is enough
+        if let Some(cancellation_token) = cancellation_token {
+            cancellation_token.check()?;
+        }
+
+        Ok(match contents_store {
+            Some(contents_store) => ShiftedContents::Memory(contents_store),
+            None => ShiftedContents::Empty,
+        })
+    }
+
+    /// Write the saved contents at their new position, must be called after changing the sizes
+    /// of the sections but before writing them (the old contents may be overwritten otherwise).
+    /// Returns the position of the end of the contents, if any.
+    pub(super) fn restore_contents_with_options<T: Read + Write + Seek>(
+        &mut self,
+        stream: &mut StreamPin<T>,
+        title_metadata: &TitleMetadata,
+        contents: ShiftedContents,
+        options: &ShiftOptions,
+    ) -> Result<Option<u64>, InstallableWadError> {
+        let new_offset = self.contents_offset();
+
+        match contents {
+            ShiftedContents::Empty => return Ok(None),
+
+            ShiftedContents::Memory(contents_store) => {
+                self.restore_contents(stream, title_metadata, Some(contents_store))?;
+            }
+
+            ShiftedContents::Buffered(mut buffer, sizes) => {
+                buffer.rewind()?;
+                stream.seek(SeekFrom::Start(new_offset))?;
+
+                for size in sizes {
+                    copy_chunked(&mut buffer, &mut *stream, size, options.chunk_size, None)?;
+                    stream.align_zeroed(Self::SECTION_BOUNDARY)?;
+                }
+            }
+
+            ShiftedContents::InPlace { old_offset, sizes } => {
+                // The padding after the last content may be missing, it is written again
+                let region_size = sizes.iter().enumerate().fold(0, |acc, (i, size)| {
+                    if i + 1 == sizes.len() {
+                        acc + size
+                    } else {
+                        acc + util::align_to_boundary(*size, Self::SECTION_BOUNDARY)
+                    }
+                });
+
+                move_region(
+                    &mut *stream,
+                    old_offset,
+                    new_offset,
+                    region_size,
+                    options.chunk_size,
+                )?;
+
+                stream.seek(SeekFrom::Start(new_offset + region_size))?;
+                stream.align_zeroed(Self::SECTION_BOUNDARY)?;
+            }
+        }
+
+        Ok(Some(stream.stream_position()?))
+    }
+}
+
+/// Copy `len` bytes from the reader into the writer using a buffer of at most `chunk_size`, the
+/// cancellation token (if any) is checked before each chunk.
+fn copy_chunked<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    len: u64,
+    chunk_size: usize,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<(), InstallableWadError> {
+    let mut buffer = vec![0; chunk_size.clamp(1, len.max(1) as usize)];
+    let mut remaining = len;
+
+    while remaining > 0 {
+        if let Some(cancellation_token) = cancellation_token {
+            cancellation_token.check()?;
+        }
+
+        let chunk_len = remaining.min(buffer.len() as u64) as usize;
+
+        reader.read_exact(&mut buffer[..chunk_len])?;
+        writer.write_all(&buffer[..chunk_len])?;
+
+        remaining -= chunk_len as u64;
+    }
+
+    Ok(())
+}
+
+/// Move a region of the stream to other position, the regions may overlap.
+fn move_region<T: Read + Write + Seek>(
+    mut stream: T,
+    from: u64,
+    to: u64,
+    len: u64,
+    chunk_size: usize,
+) -> io::Result<()> {
+    if from == to || len == 0 {
+        return Ok(());
+    }
+
+    let mut buffer = vec![0; chunk_size.clamp(1, len as usize)];
+    let chunk_size = buffer.len() as u64;
+
+    // Moving backwards the start of the region is copied first and moving forwards the end is,
+    // this way no data is overwritten before being copied
+    let mut moved = 0;
+
+    while moved < len {
+        let chunk_len = (len - moved).min(chunk_size);
+
+        let chunk_offset = if to < from {
+            moved
+        } else {
+            len - moved - chunk_len
+        };
+
+        let chunk = &mut buffer[..chunk_len as usize];
+
+        stream.seek(SeekFrom::Start(from + chunk_offset))?;
+        stream.read_exact(chunk)?;
+
+        stream.seek(SeekFrom::Start(to + chunk_offset))?;
+        stream.write_all(chunk)?;
+
+        moved += chunk_len;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signed_blob_header::SignedBlobHeaderSignature;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder, certificate_chain};
+    use std::io::Cursor;
+
+    #[test]
+    fn shift_contents_with_all_strategies() {
+        let strategies = [
+            ShiftOptions::default(),
+            ShiftOptions {
+                chunk_size: 7,
+                temporary_file_threshold: Some(0),
+                in_place: false,
+                cancellation_token: None,
+            },
+            ShiftOptions {
+                chunk_size: 7,
+                ..ShiftOptions::in_place()
+            },
+        ];
+
+        for options in strategies {
+            let TestWad {
+                mut wad,
+                mut stream,
+                mut ticket,
+                title_metadata,
+            } = TestWadBuilder::new()
+                .content(0, vec![0xAA; 0x45])
+                .content(1, vec![0xBB; 0x100])
+                .content(2, vec![0xCC; 0x3])
+                .build();
+
+            // The whole encrypted data, including the padding of its last block
+            let encrypted_content = |wad: &InstallableWad, stream: &mut Cursor<Vec<u8>>, i| {
+                let selector = title_metadata.select_with_physical_position(i);
+                let size = selector.content_entry(&title_metadata).unwrap().size;
+
+                wad.seek_content(&mut *stream, &title_metadata, selector)
+                    .unwrap();
+
+                let mut data = vec![0; util::align_to_boundary(size, 16) as usize];
+                stream.read_exact(&mut data).unwrap();
+
+                data
+            };
+
+            let contents: Vec<Vec<u8>> = (0..3)
+                .map(|i| encrypted_content(&wad, &mut stream, i))
+                .collect();
+
+            for number_of_certificates in [3, 1, 0] {
+                stream.set_position(0);
+                wad.write_certificate_chain_safe_with_options(
+                    &mut stream,
+                    &certificate_chain(number_of_certificates),
+                    &ticket,
+                    &title_metadata,
+                    &options,
+                )
+                .unwrap();
+
+                let contents_end = wad.contents_offset() + 0x80 + 0x100 + 0x10;
+                assert_eq!(stream.position(), util::align_to_boundary(contents_end, 64));
+
+                for (i, content) in contents.iter().enumerate() {
+                    assert_eq!(&encrypted_content(&wad, &mut stream, i), content);
+                }
+            }
+
+            // A bigger signature also moves the contents
+            ticket.signed_blob_header.signature =
+                SignedBlobHeaderSignature::Rsa4096Sha1(Box::new([0; 512]));
+
+            stream.set_position(0);
+            wad.write_ticket_safe_with_options(&mut stream, &ticket, &title_metadata, &options)
+                .unwrap();
+            assert_eq!(wad.ticket_size, ticket.size());
+
+            for (i, content) in contents.iter().enumerate() {
+                assert_eq!(&encrypted_content(&wad, &mut stream, i), content);
+            }
+
+            let cancellation_token = CancellationToken::new();
+            cancellation_token.cancel();
+
+            let cancelled_options = ShiftOptions {
+                cancellation_token: Some(cancellation_token),
+                ..options
+            };
+
+            stream.set_position(0);
+            let untouched_stream = stream.clone();
+            assert!(matches!(
+                wad.write_certificate_chain_safe_with_options(
+                    &mut stream,
+                    &certificate_chain(2),
+                    &ticket,
+                    &title_metadata,
+                    &cancelled_options,
+                ),
+                Err(InstallableWadError::Cancelled(_))
+            ));
+            assert_eq!(stream.get_ref(), untouched_stream.get_ref());
+        }
+    }
+}
@@ -0,0 +1,503 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::CryptographicMethod;
+use crate::content_transform::{ContentPipeline, Truncate};
+use crate::title_metadata::TitleMetadata;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use crate::wad::installable::InstallableWadKind;
+use sha1::{Digest, Sha1};
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek, Write};
+use std::path::Path;
+use util::StreamPin;
+
+/// Checksum files written along the files of a split WAD, see
+/// [InstallableWad::split_with_checksums].
+///
+/// Both kinds follow the format of the `sha1sum` tool (`<hash>  <file name>`), so they can also
+/// be checked with `sha1sum -c`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitChecksums {
+    /// Do not write any checksum.
+    None,
+
+    /// Write a `<file name>.sha1` file next to every file.
+    Sidecar,
+
+    /// Write a single [InstallableWad::SPLIT_CHECKSUMS_FILE_NAME] file with the checksums of
+    /// all the files.
+    Combined,
+}
+
+impl InstallableWad {
+    /// Name of the file where the kind of the WAD is stored when splitting it, the two bytes that
+    /// identify it inside the header.
+    pub const SPLIT_KIND_FILE_NAME: &str = "kind.bin";
+
+    /// Name of the file where the certificate chain is stored when splitting a WAD.
+    pub const SPLIT_CERTIFICATE_CHAIN_FILE_NAME: &str = "cert.bin";
+
+    /// Name of the file where the ticket is stored when splitting a WAD.
+    pub const SPLIT_TICKET_FILE_NAME: &str = "ticket.tik";
+
+    /// Name of the file where the title metadata is stored when splitting a WAD.
+    pub const SPLIT_TITLE_METADATA_FILE_NAME: &str = "tmd.bin";
+
+    /// Name of the file where the footer is stored when splitting a WAD.
+    pub const SPLIT_FOOTER_FILE_NAME: &str = "footer.bin";
+
+    /// Name of the file where the checksums of all the files are stored when splitting a WAD with
+    /// [SplitChecksums::Combined].
+    pub const SPLIT_CHECKSUMS_FILE_NAME: &str = "SHA1SUMS";
+
+    /// Extension of the sidecar checksum files written with [SplitChecksums::Sidecar].
+    pub const SPLIT_CHECKSUM_EXTENSION: &str = "sha1";
+
+    /// Get the name of the file where a content is stored when splitting a WAD, its ID in
+    /// lowercase hexadecimal with the `.app` extension.
+    pub fn split_content_file_name(id: u32) -> String {
+        format!("{id:08x}.app")
+    }
+
+    /// Write every section of the WAD as a separate file inside the given directory, the contents
+    /// are kept encrypted. See [Self::join] for the inverse operation.
+    pub fn split<T: Read + Seek, P: AsRef<Path>>(
+        &self,
+        stream: T,
+        out_dir: P,
+    ) -> Result<(), InstallableWadError> {
+        self.split_with_checksums(stream, out_dir, SplitChecksums::None)
+    }
+
+    /// Like [Self::split] but also writes the SHA-1 checksums of the written files, useful for
+    /// archival. See [Self::verify_export] to check them again.
+    pub fn split_with_checksums<T: Read + Seek, P: AsRef<Path>>(
+        &self,
+        mut stream: T,
+        out_dir: P,
+        checksums: SplitChecksums,
+    ) -> Result<(), InstallableWadError> {
+        let out_dir = out_dir.as_ref();
+        fs::create_dir_all(out_dir)?;
+
+        let mut file_names = vec![
+            String::from(Self::SPLIT_KIND_FILE_NAME),
+            String::from(Self::SPLIT_CERTIFICATE_CHAIN_FILE_NAME),
+            String::from(Self::SPLIT_TICKET_FILE_NAME),
+            String::from(Self::SPLIT_TITLE_METADATA_FILE_NAME),
+        ];
+
+        fs::write(out_dir.join(Self::SPLIT_KIND_FILE_NAME), self.kind.magic())?;
+
+        let mut view = self.take_certificate_chain(&mut stream)?;
+        let mut file = File::create(out_dir.join(Self::SPLIT_CERTIFICATE_CHAIN_FILE_NAME))?;
+        io::copy(&mut view, &mut file)?;
+
+        let mut view = self.ticket_view(&mut stream)?;
+        let mut file = File::create(out_dir.join(Self::SPLIT_TICKET_FILE_NAME))?;
+        io::copy(&mut view, &mut file)?;
+
+        let mut view = self.title_metadata_view(&mut stream)?;
+        let mut file = File::create(out_dir.join(Self::SPLIT_TITLE_METADATA_FILE_NAME))?;
+        io::copy(&mut view, &mut file)?;
+
+        let title_metadata = self.title_metadata(&mut stream)?;
+
+        for (i, content_entry) in title_metadata.content_chunk_entries.iter().enumerate() {
+            let mut view = self.encrypted_content_view(
+                &mut stream,
+                &title_metadata,
+                title_metadata.select_with_physical_position(i),
+            )?;
+
+            let file_name = Self::split_content_file_name(content_entry.id);
+            let mut file = File::create(out_dir.join(&file_name))?;
+            io::copy(&mut view, &mut file)?;
+
+            file_names.push(file_name);
+        }
+
+        // Not all WADs have a footer
+        if self.footer_size > 0 {
+            let mut view = self.footer_view(&mut stream, &title_metadata)?;
+            let mut file = File::create(out_dir.join(Self::SPLIT_FOOTER_FILE_NAME))?;
+            io::copy(&mut view, &mut file)?;
+
+            file_names.push(String::from(Self::SPLIT_FOOTER_FILE_NAME));
+        }
+
+        match checksums {
+            SplitChecksums::None => (),
+
+            SplitChecksums::Sidecar => {
+                for file_name in &file_names {
+                    let line = checksum_line(out_dir, file_name)?;
+
+                    fs::write(
+                        out_dir.join(format!("{file_name}.{}", Self::SPLIT_CHECKSUM_EXTENSION)),
+                        line,
+                    )?;
+                }
+            }
+
+            SplitChecksums::Combined => {
+                let mut lines = String::new();
+
+                for file_name in &file_names {
+                    lines.push_str(&checksum_line(out_dir, file_name)?);
+                }
+
+                fs::write(out_dir.join(Self::SPLIT_CHECKSUMS_FILE_NAME), lines)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the decrypted data of every content of the WAD as a separate file inside the given
+    /// directory, named with [Self::split_content_file_name]. The contents are decrypted with the
+    /// title key of the ticket stored in the WAD, their hashes are not checked (see
+    /// [Self::trust_report]). Returns the number of written contents.
+    pub fn extract_contents<T: Read + Seek, P: AsRef<Path>>(
+        &self,
+        mut stream: T,
+        out_dir: P,
+        cryptographic_method: CryptographicMethod,
+    ) -> Result<usize, InstallableWadError> {
+        let out_dir = out_dir.as_ref();
+        fs::create_dir_all(out_dir)?;
+
+        let ticket = self.ticket(&mut stream)?;
+        let title_metadata = self.title_metadata(&mut stream)?;
+
+        for (i, content_entry) in title_metadata.content_chunk_entries.iter().enumerate() {
+            let content_selector = title_metadata.select_with_physical_position(i);
+            self.seek_content(&mut stream, &title_metadata, content_selector)?;
+
+            // The encrypted data is padded to the AES block size
+            let encrypted_len = util::align_to_boundary(content_entry.size, 16);
+
+            let file = File::create(out_dir.join(Self::split_content_file_name(content_entry.id)))?;
+
+            ContentPipeline::new()
+                .then(ticket.decryption_transform(
+                    &title_metadata,
+                    content_selector,
+                    cryptographic_method,
+                )?)
+                .then(Truncate::new(content_entry.size))
+                .run((&mut stream).take(encrypted_len), file)?;
+        }
+
+        Ok(title_metadata.content_chunk_entries.len())
+    }
+
+    /// Check the checksums written by [Self::split_with_checksums] inside a directory, both the
+    /// combined file and the sidecar ones are checked. Returns the number of checked files,
+    /// failing with [InstallableWadError::ExportChecksumMismatch] on the first file that does not
+    /// match.
+    pub fn verify_export<P: AsRef<Path>>(in_dir: P) -> Result<usize, InstallableWadError> {
+        let in_dir = in_dir.as_ref();
+        let mut checksum_files = vec![];
+
+        for entry in fs::read_dir(in_dir)? {
+            let path = entry?.path();
+
+            let is_checksum_file = path
+                .file_name()
+                .is_some_and(|file_name| file_name == Self::SPLIT_CHECKSUMS_FILE_NAME)
+                || path
+                    .extension()
+                    .is_some_and(|extension| extension == Self::SPLIT_CHECKSUM_EXTENSION);
+
+            if is_checksum_file {
+                checksum_files.push(path);
+            }
+        }
+
+        let mut number_of_checked_files = 0;
+
+        for checksum_file in checksum_files {
+            for line in fs::read_to_string(checksum_file)?.lines() {
+                let Some((expected_hash, file_name)) = line.split_once("  ") else {
+                    continue;
+                };
+
+                // The checksum files come from outside, do not read anything out of the directory
+                if file_name.contains("..") || file_name.contains(['/', '\\']) {
+                    return Err(InstallableWadError::InvalidExportFileName(String::from(
+                        file_name,
+                    )));
+                }
+
+                if checksum_line(in_dir, file_name)? != format!("{expected_hash}  {file_name}\n") {
+                    return Err(InstallableWadError::ExportChecksumMismatch(String::from(
+                        file_name,
+                    )));
+                }
+
+                number_of_checked_files += 1;
+            }
+        }
+
+        Ok(number_of_checked_files)
+    }
+
+    /// Create a new WAD from a directory with the layout written by [Self::split]. The footer
+    /// file is optional, without the kind file the WAD is a [InstallableWadKind::Normal] one.
+    pub fn join<P: AsRef<Path>, T: Write + Seek>(
+        in_dir: P,
+        stream: T,
+    ) -> Result<Self, InstallableWadError> {
+        let in_dir = in_dir.as_ref();
+        let mut stream = StreamPin::new(stream)?;
+
+        let kind = match File::open(in_dir.join(Self::SPLIT_KIND_FILE_NAME)) {
+            Ok(file) => InstallableWadKind::new(file)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => InstallableWadKind::Normal,
+            Err(err) => return Err(err.into()),
+        };
+
+        let certificate_chain = fs::read(in_dir.join(Self::SPLIT_CERTIFICATE_CHAIN_FILE_NAME))?;
+        let ticket = fs::read(in_dir.join(Self::SPLIT_TICKET_FILE_NAME))?;
+        let title_metadata = fs::read(in_dir.join(Self::SPLIT_TITLE_METADATA_FILE_NAME))?;
+
+        let footer = match fs::read(in_dir.join(Self::SPLIT_FOOTER_FILE_NAME)) {
+            Ok(footer) => footer,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err.into()),
+        };
+
+        let parsed_title_metadata = TitleMetadata::new(Cursor::new(&title_metadata))?;
+
+        let mut wad = Self {
+            header_size: Self::HEADER_SIZE_FIELD,
+            kind,
+            certificate_chain_size: Self::size_field(
+                "certificate chain",
+                certificate_chain.len() as u64,
+            )?,
+            ticket_size: Self::size_field("ticket", ticket.len() as u64)?,
+            title_metadata_size: Self::size_field("title metadata", title_metadata.len() as u64)?,
+            content_size: 0,
+            footer_size: Self::size_field("footer", footer.len() as u64)?,
+        };
+
+        // Leave space for the header, it will be written when all the sizes are known
+        stream.seek_from_pin(Self::HEADER_SIZE as i64)?;
+
+        for bytes in [&certificate_chain, &ticket, &title_metadata] {
+            stream.write_all(bytes)?;
+            stream.align_zeroed(Self::SECTION_BOUNDARY)?;
+        }
+
+        let mut content_size: u64 = 0;
+
+        for content_entry in &parsed_title_metadata.content_chunk_entries {
+            let mut file =
+                File::open(in_dir.join(Self::split_content_file_name(content_entry.id)))?;
+            io::copy(&mut file, &mut stream)?;
+
+            // The files keep the padding of the encrypted data, the size field does not
+            content_size = content_size
+                .checked_add(content_entry.size)
+                .ok_or(InstallableWadError::SectionTooBig("contents", u64::MAX))?;
+
+            stream.align_zeroed(Self::SECTION_BOUNDARY)?;
+        }
+
+        wad.content_size = Self::size_field("contents", content_size)?;
+
+        stream.write_all(&footer)?;
+
+        stream.seek_from_pin(0)?;
+        wad.dump(&mut stream)?;
+
+        Ok(wad)
+    }
+}
+
+/// Get the line of a file on the format of the `sha1sum` tool.
+fn checksum_line(dir: &Path, file_name: &str) -> io::Result<String> {
+    let mut hasher = Sha1::new();
+    io::copy(&mut File::open(dir.join(file_name))?, &mut hasher)?;
+
+    let hash = util::hex::encode(&hasher.finalize());
+
+    Ok(format!("{hash}  {file_name}\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::installable::test_wad::{self, TestWad, TestWadBuilder};
+
+    #[test]
+    fn split_with_checksums_and_verify() {
+        let TestWad {
+            wad, mut stream, ..
+        } = TestWadBuilder::new()
+            .certificate_chain(test_wad::certificate_chain(3))
+            .build();
+
+        for (i, checksums) in [SplitChecksums::Sidecar, SplitChecksums::Combined]
+            .into_iter()
+            .enumerate()
+        {
+            let out_dir =
+                std::env::temp_dir().join(format!("niiebla_split_{}_{i}", std::process::id()));
+
+            stream.set_position(0);
+            wad.split_with_checksums(&mut stream, &out_dir, checksums)
+                .unwrap();
+
+            // Kind, certificate chain, ticket and title metadata
+            assert_eq!(InstallableWad::verify_export(&out_dir).unwrap(), 4);
+
+            fs::write(out_dir.join(InstallableWad::SPLIT_TICKET_FILE_NAME), [0; 4]).unwrap();
+
+            assert!(matches!(
+                InstallableWad::verify_export(&out_dir),
+                Err(InstallableWadError::ExportChecksumMismatch(file_name))
+                    if file_name == InstallableWad::SPLIT_TICKET_FILE_NAME
+            ));
+
+            fs::remove_dir_all(out_dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn verify_export_outside_directory() {
+        let out_dir = std::env::temp_dir().join(format!("niiebla_verify_{}", std::process::id()));
+        fs::create_dir_all(&out_dir).unwrap();
+
+        for file_name in ["../secret", "/etc/passwd", "sub\\file"] {
+            fs::write(
+                out_dir.join(InstallableWad::SPLIT_CHECKSUMS_FILE_NAME),
+                format!("{}  {file_name}\n", "0".repeat(40)),
+            )
+            .unwrap();
+
+            assert!(matches!(
+                InstallableWad::verify_export(&out_dir),
+                Err(InstallableWadError::InvalidExportFileName(name)) if name == file_name
+            ));
+        }
+
+        fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn extract_contents() {
+        let data: Vec<u8> = (0..0x25).collect();
+
+        let TestWad {
+            wad, mut stream, ..
+        } = TestWadBuilder::new().content(0x2A, data.clone()).build();
+
+        let out_dir = std::env::temp_dir().join(format!("niiebla_extract_{}", std::process::id()));
+
+        assert_eq!(
+            wad.extract_contents(&mut stream, &out_dir, CryptographicMethod::Wii)
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            fs::read(out_dir.join(InstallableWad::split_content_file_name(0x2A))).unwrap(),
+            data
+        );
+
+        fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn split_and_join_round_trip() {
+        let TestWad {
+            wad, mut stream, ..
+        } = TestWadBuilder::new()
+            .certificate_chain(test_wad::certificate_chain(3))
+            .content(0, vec![0xAB; 0x40])
+            .content(1, vec![0xCD; 0x25])
+            .build();
+
+        let out_dir = std::env::temp_dir().join(format!("niiebla_join_{}", std::process::id()));
+        wad.split(&mut stream, &out_dir).unwrap();
+
+        let mut joined_stream = Cursor::new(vec![]);
+        let joined = InstallableWad::join(&out_dir, &mut joined_stream).unwrap();
+
+        assert_eq!(joined.kind, InstallableWadKind::Normal);
+        assert_eq!(joined.certificate_chain_size, wad.certificate_chain_size);
+        assert_eq!(joined.ticket_size, wad.ticket_size);
+        assert_eq!(joined.title_metadata_size, wad.title_metadata_size);
+        assert_eq!(joined.content_size, wad.content_size);
+        assert_eq!(joined.footer_size, 0);
+
+        // Splitting the joined WAD again must give back the same files
+        let resplit_dir =
+            std::env::temp_dir().join(format!("niiebla_resplit_{}", std::process::id()));
+        joined_stream.set_position(0);
+        joined.split(&mut joined_stream, &resplit_dir).unwrap();
+
+        for entry in fs::read_dir(&out_dir).unwrap() {
+            let file_name = entry.unwrap().file_name();
+
+            assert_eq!(
+                fs::read(out_dir.join(&file_name)).unwrap(),
+                fs::read(resplit_dir.join(&file_name)).unwrap()
+            );
+        }
+
+        assert_eq!(
+            fs::read_dir(&out_dir).unwrap().count(),
+            fs::read_dir(&resplit_dir).unwrap().count()
+        );
+
+        fs::remove_dir_all(resplit_dir).unwrap();
+        fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn split_and_join_boot2() {
+        let TestWad {
+            wad, mut stream, ..
+        } = TestWadBuilder::new()
+            .kind(InstallableWadKind::Boot2)
+            .certificate_chain(test_wad::certificate_chain(3))
+            .content(0, vec![0xEF; 0x25])
+            .build();
+
+        let out_dir =
+            std::env::temp_dir().join(format!("niiebla_join_boot2_{}", std::process::id()));
+        wad.split(&mut stream, &out_dir).unwrap();
+
+        let mut joined_stream = Cursor::new(vec![]);
+        let joined = InstallableWad::join(&out_dir, &mut joined_stream).unwrap();
+        assert_eq!(joined.kind, InstallableWadKind::Boot2);
+
+        // The kind is also written into the header of the joined WAD
+        joined_stream.set_position(0);
+        let reparsed = crate::Wad::try_new_installable(&mut joined_stream).unwrap();
+        assert_eq!(reparsed.kind, InstallableWadKind::Boot2);
+
+        fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn size_field_too_big() {
+        assert_eq!(
+            InstallableWad::size_field("footer", 0xFFFF_FFFF).unwrap(),
+            0xFFFF_FFFF
+        );
+
+        assert!(matches!(
+            InstallableWad::size_field("footer", 0x1_0000_0000),
+            Err(InstallableWadError::SectionTooBig("footer", 0x1_0000_0000))
+        ));
+    }
+}
@@ -0,0 +1,200 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Builder of in-memory installable WADs shared by the tests of the crate.
+
+use crate::certificate_chain::{
+    Certificate, CertificateChain, CertificateKey, CertificateKeyValue,
+};
+use crate::signed_blob_header::{SignedBlobHeader, SignedBlobHeaderSignature};
+use crate::templates;
+use crate::title_metadata::{
+    TitleMetadataContentEntry, TitleMetadataContentEntryHashKind, TitleMetadataContentEntryKind,
+};
+use crate::wad::installable::{InstallableWad, InstallableWadKind};
+use crate::{CryptographicMethod, PreSwitchTicket, TitleMetadata};
+use std::io::{Cursor, Seek, SeekFrom};
+
+/// A WAD header with all the sizes set to zero.
+pub(crate) fn header() -> InstallableWad {
+    InstallableWad {
+        header_size: 0x20,
+        kind: InstallableWadKind::Normal,
+        certificate_chain_size: 0,
+        ticket_size: 0,
+        title_metadata_size: 0,
+        content_size: 0,
+        footer_size: 0,
+    }
+}
+
+/// A title metadata content entry with a zeroed hash.
+pub(crate) fn content_entry(
+    id: u32,
+    index: u16,
+    kind: TitleMetadataContentEntryKind,
+    size: u64,
+) -> TitleMetadataContentEntry {
+    TitleMetadataContentEntry {
+        id,
+        index,
+        kind,
+        size,
+        hash: TitleMetadataContentEntryHashKind::Version0([0; 20]),
+    }
+}
+
+/// A certificate chain of unsigned certificates, only useful to fill the section.
+pub(crate) fn certificate_chain(number_of_certificates: usize) -> CertificateChain {
+    let certificate = Certificate {
+        signed_blob_header: SignedBlobHeader {
+            signature: SignedBlobHeaderSignature::Rsa2048Sha1(Box::new([0; 256])),
+            issuer: String::from("Root"),
+        },
+        identity: String::from("CA00000001"),
+        key: CertificateKey {
+            id: 0,
+            value: CertificateKeyValue::Rsa2048(Box::new([0; 260])),
+        },
+    };
+
+    CertificateChain {
+        certificates: vec![certificate; number_of_certificates],
+    }
+}
+
+/// A WAD built by [TestWadBuilder], its stream is positioned at the start.
+pub(crate) struct TestWad {
+    pub wad: InstallableWad,
+    pub stream: Cursor<Vec<u8>>,
+    pub ticket: PreSwitchTicket,
+    pub title_metadata: TitleMetadata,
+}
+
+/// Builder of a WAD of the Homebrew Channel (or any other title), the contents are encrypted with
+/// [CryptographicMethod::Wii].
+pub(crate) struct TestWadBuilder {
+    kind: InstallableWadKind,
+    ticket: PreSwitchTicket,
+    title_metadata: TitleMetadata,
+    certificate_chain: Option<CertificateChain>,
+    contents: Vec<(TitleMetadataContentEntry, Vec<u8>)>,
+}
+
+impl TestWadBuilder {
+    pub fn new() -> Self {
+        Self::with_title(
+            templates::homebrew_channel_ticket(),
+            templates::homebrew_channel_tmd(),
+        )
+    }
+
+    pub fn with_title(ticket: PreSwitchTicket, title_metadata: TitleMetadata) -> Self {
+        Self {
+            kind: InstallableWadKind::Normal,
+            ticket,
+            title_metadata,
+            certificate_chain: None,
+            contents: vec![],
+        }
+    }
+
+    pub fn kind(mut self, kind: InstallableWadKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn certificate_chain(mut self, certificate_chain: CertificateChain) -> Self {
+        self.certificate_chain = Some(certificate_chain);
+        self
+    }
+
+    /// Add a normal content, the ID is also used as its index.
+    pub fn content(self, id: u32, data: impl Into<Vec<u8>>) -> Self {
+        self.content_with_entry(
+            content_entry(id, id as u16, TitleMetadataContentEntryKind::Normal, 0),
+            data,
+        )
+    }
+
+    /// Add a content with the ID, index and kind of the given entry, its size and hash are
+    /// overwritten.
+    pub fn content_with_entry(
+        mut self,
+        entry: TitleMetadataContentEntry,
+        data: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.contents.push((entry, data.into()));
+        self
+    }
+
+    pub fn build(self) -> TestWad {
+        let Self {
+            kind,
+            ticket,
+            mut title_metadata,
+            certificate_chain,
+            contents,
+        } = self;
+
+        // Placeholder sizes until the contents are replaced, an empty view cannot be created
+        for (entry, _) in &contents {
+            title_metadata
+                .content_chunk_entries
+                .push(TitleMetadataContentEntry {
+                    size: 0x10,
+                    ..entry.clone()
+                });
+        }
+
+        let mut wad = InstallableWad {
+            kind,
+            ticket_size: ticket.size(),
+            ..header()
+        };
+
+        let mut stream = Cursor::new(vec![]);
+        wad.dump(&mut stream).unwrap();
+        stream.seek(SeekFrom::Start(64)).unwrap();
+        ticket.dump(&mut stream).unwrap();
+
+        stream.set_position(0);
+        wad.write_title_metadata_safe(&mut stream, &title_metadata)
+            .unwrap();
+
+        if let Some(certificate_chain) = certificate_chain {
+            stream.set_position(0);
+            wad.write_certificate_chain_safe(
+                &mut stream,
+                &certificate_chain,
+                &ticket,
+                &title_metadata,
+            )
+            .unwrap();
+        }
+
+        for (i, (_, data)) in contents.into_iter().enumerate() {
+            stream.set_position(0);
+            wad.modify_content(&mut stream)
+                .set_cryptography(&ticket, CryptographicMethod::Wii)
+                .replace(
+                    Cursor::new(data),
+                    title_metadata.select_with_physical_position(i),
+                    &mut title_metadata,
+                )
+                .unwrap();
+        }
+
+        stream.set_position(0);
+
+        TestWad {
+            wad,
+            stream,
+            ticket,
+            title_metadata,
+        }
+    }
+}
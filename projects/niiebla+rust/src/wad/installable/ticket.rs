@@ -5,11 +5,14 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use crate::TitleMetadata;
+use crate::parse_limits::ParseLimits;
 use crate::ticket::{PreSwitchTicket, PreSwitchTicketError};
 use crate::wad::InstallableWad;
 use crate::wad::InstallableWadError;
+use crate::wad::installable::ShiftOptions;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
+use util::BufferedSeekReader;
 use util::StreamPin;
 use util::View;
 
@@ -35,12 +38,26 @@ impl InstallableWad {
 
     /// Parse the ticket stored inside the WAD stream.
     pub fn ticket<T: Read + Seek>(
+        &self,
+        stream: T,
+    ) -> Result<PreSwitchTicket, PreSwitchTicketError> {
+        self.ticket_with_limits(stream, &ParseLimits::UNLIMITED)
+    }
+
+    /// Like [Self::ticket] but following the given [ParseLimits], see
+    /// [PreSwitchTicket::new_with_limits].
+    pub fn ticket_with_limits<T: Read + Seek>(
         &self,
         mut stream: T,
+        limits: &ParseLimits,
     ) -> Result<PreSwitchTicket, PreSwitchTicketError> {
         self.seek_ticket(&mut stream)?;
 
-        PreSwitchTicket::new(stream)
+        let mut stream = BufferedSeekReader::new(stream)?;
+        let ticket = PreSwitchTicket::new_with_limits(&mut stream, limits)?;
+        stream.into_inner()?;
+
+        Ok(ticket)
     }
 
     /// Write a new ticket into the stream of a WAD. The internal WAD data will be modified to
@@ -77,17 +94,43 @@ impl InstallableWad {
         stream: T,
         new_ticket: &PreSwitchTicket,
         title_metadata: &TitleMetadata,
+    ) -> Result<(), InstallableWadError> {
+        self.write_ticket_safe_with_options(
+            stream,
+            new_ticket,
+            title_metadata,
+            &ShiftOptions::default(),
+        )
+    }
+
+    /// Like [Self::write_ticket_safe] but the trailing data is realigned following the given
+    /// [ShiftOptions].
+    pub fn write_ticket_safe_with_options<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        new_ticket: &PreSwitchTicket,
+        title_metadata: &TitleMetadata,
+        options: &ShiftOptions,
     ) -> Result<(), InstallableWadError> {
         let mut stream = StreamPin::new(stream)?;
 
-        let contents = self.store_contents(&mut stream, title_metadata, 0)?;
+        let contents = self.store_contents_with_options(&mut stream, title_metadata, options)?;
+        self.recompute_sizes(new_ticket, title_metadata)?;
+
+        let contents_end =
+            self.restore_contents_with_options(&mut stream, title_metadata, contents, options)?;
+
+        // The raw writes align the sections relative to the current position of the stream
+        stream.go_to_pin()?;
 
         unsafe {
             self.write_ticket_raw(new_ticket, &mut stream)?;
             self.write_title_metadata_raw(title_metadata, &mut stream)?;
         }
 
-        self.restore_contents(&mut stream, title_metadata, &contents)?;
+        if let Some(contents_end) = contents_end {
+            stream.seek(SeekFrom::Start(contents_end))?;
+        }
 
         Ok(())
     }
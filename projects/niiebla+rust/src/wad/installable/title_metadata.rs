@@ -4,12 +4,14 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+use crate::parse_limits::ParseLimits;
 use crate::title_metadata::{TitleMetadata, TitleMetadataError};
 use crate::wad::InstallableWad;
 use crate::wad::InstallableWadError;
+use crate::wad::installable::ShiftOptions;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
-use util::{StreamPin, View};
+use util::{BufferedSeekReader, StreamPin, View};
 
 impl InstallableWad {
     /// Seek the stream of the WAD to the start of the title metadata.
@@ -35,12 +37,29 @@ impl InstallableWad {
 
     /// Parse the title metadata stored inside the WAD stream.
     pub fn title_metadata<T: Read + Seek>(
+        &self,
+        stream: T,
+    ) -> Result<TitleMetadata, TitleMetadataError> {
+        self.title_metadata_with_limits(stream, &ParseLimits::UNLIMITED)
+    }
+
+    /// Like [Self::title_metadata] but following the given [ParseLimits], see
+    /// [TitleMetadata::new_with_limits].
+    pub fn title_metadata_with_limits<T: Read + Seek>(
         &self,
         mut stream: T,
+        limits: &ParseLimits,
     ) -> Result<TitleMetadata, TitleMetadataError> {
         self.seek_title_metadata(&mut stream)?;
 
-        TitleMetadata::new(&mut stream)
+        // Parsing is done with lots of small reads and seeks
+        let mut stream = BufferedSeekReader::new(stream)?;
+        let title_metadata = TitleMetadata::new_with_limits(&mut stream, limits)?;
+
+        // Leave the stream at the end of the title metadata, not where the read-ahead stopped
+        stream.into_inner()?;
+
+        Ok(title_metadata)
     }
 
     /// Write a new title metadata into the stream of a WAD.
@@ -78,16 +97,41 @@ impl InstallableWad {
         &mut self,
         stream: T,
         new_title_metadata: &TitleMetadata,
+    ) -> Result<(), InstallableWadError> {
+        self.write_title_metadata_safe_with_options(
+            stream,
+            new_title_metadata,
+            &ShiftOptions::default(),
+        )
+    }
+
+    /// Like [Self::write_title_metadata_safe] but the trailing data is realigned following the
+    /// given [ShiftOptions].
+    pub fn write_title_metadata_safe_with_options<T: Read + Write + Seek>(
+        &mut self,
+        stream: T,
+        new_title_metadata: &TitleMetadata,
+        options: &ShiftOptions,
     ) -> Result<(), InstallableWadError> {
         let mut stream = StreamPin::new(stream)?;
 
-        let contents = self.store_contents(&mut stream, new_title_metadata, 0)?;
+        let contents =
+            self.store_contents_with_options(&mut stream, new_title_metadata, options)?;
+        self.recompute_title_metadata_sizes(new_title_metadata)?;
+
+        let contents_end =
+            self.restore_contents_with_options(&mut stream, new_title_metadata, contents, options)?;
+
+        // The raw writes align the sections relative to the current position of the stream
+        stream.go_to_pin()?;
 
         unsafe {
             self.write_title_metadata_raw(new_title_metadata, &mut stream)?;
         }
 
-        self.restore_contents(&mut stream, new_title_metadata, &contents)?;
+        if let Some(contents_end) = contents_end {
+            stream.seek(SeekFrom::Start(contents_end))?;
+        }
 
         Ok(())
     }
@@ -0,0 +1,99 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::ticket::PreSwitchTicketLimitEntry;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use std::io::{Read, Seek, Write};
+
+impl InstallableWad {
+    /// Turn the title inside the WAD stream into a trial that can only be played the given
+    /// number of minutes, any other limit of the ticket is removed.
+    ///
+    /// Both the ticket and the title metadata are fakesigned, so the title can only be installed
+    /// on a console running an IOS vulnerable to the
+    /// [Trucha bug](https://wiibrew.org/wiki/Signing_bug).
+    pub fn make_trial<T: Read + Write + Seek>(
+        &mut self,
+        mut stream: T,
+        minutes: u32,
+    ) -> Result<(), InstallableWadError> {
+        let mut ticket = self.ticket(&mut stream)?;
+        let mut title_metadata = self.title_metadata(&mut stream)?;
+
+        ticket.limit_entries = [const { PreSwitchTicketLimitEntry::NoLimit { kind: 0 } }; 8];
+        ticket.limit_entries[0] = PreSwitchTicketLimitEntry::TimeLimit { minutes };
+
+        ticket.fakesign()?;
+        title_metadata.fakesign()?;
+
+        self.write_ticket_safe(&mut stream, &ticket, &title_metadata)?;
+        self.write_title_metadata_safe(&mut stream, &title_metadata)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+    use sha1::{Digest, Sha1};
+    use std::io::Cursor;
+
+    #[test]
+    fn make_trial() {
+        let TestWad {
+            mut wad,
+            mut stream,
+            mut ticket,
+            title_metadata,
+        } = TestWadBuilder::new().content(0, vec![0x11; 0x40]).build();
+
+        // Any previous limit is removed
+        ticket.limit_entries[3] = PreSwitchTicketLimitEntry::LaunchLimit {
+            number_of_launches: 5,
+        };
+        wad.write_ticket_safe(&mut stream, &ticket, &title_metadata)
+            .unwrap();
+
+        stream.set_position(0);
+        wad.make_trial(&mut stream, 30).unwrap();
+
+        stream.set_position(0);
+        let wad = crate::wad::Wad::try_new_installable(&mut stream).unwrap();
+        let ticket = wad.ticket(&mut stream).unwrap();
+        let title_metadata = wad.title_metadata(&mut stream).unwrap();
+
+        assert!(matches!(
+            ticket.limit_entries[0],
+            PreSwitchTicketLimitEntry::TimeLimit { minutes: 30 }
+        ));
+        assert!(
+            ticket.limit_entries[1..]
+                .iter()
+                .all(|entry| matches!(entry, PreSwitchTicketLimitEntry::NoLimit { kind: 0 }))
+        );
+
+        let mut ticket_data = Cursor::new(vec![]);
+        ticket.dump(&mut ticket_data).unwrap();
+
+        let signed_data_offset = ticket.signed_blob_header.size() as usize - 64;
+        assert_eq!(
+            Sha1::digest(&ticket_data.get_ref()[signed_data_offset..])[0],
+            0
+        );
+
+        let mut title_metadata_data = Cursor::new(vec![]);
+        title_metadata.dump(&mut title_metadata_data).unwrap();
+
+        let signed_data_offset = title_metadata.signed_blob_header.size() as usize - 64;
+        assert_eq!(
+            Sha1::digest(&title_metadata_data.get_ref()[signed_data_offset..])[0],
+            0
+        );
+    }
+}
@@ -0,0 +1,162 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::format_compat::FormatCompat;
+use crate::title_metadata::TitleMetadata;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use std::io::{Seek, SeekFrom};
+
+impl InstallableWad {
+    /// Get the offset where the last content stored inside the WAD ends (without its padding),
+    /// computed from the sizes of the header and the entries of the title metadata.
+    pub fn contents_end(&self, title_metadata: &TitleMetadata) -> u64 {
+        let first_content_offset = Self::HEADER_SIZE
+            + Self::align_u64(self.certificate_chain_size)
+            + Self::align_u64(self.ticket_size)
+            + Self::align_u64(self.title_metadata_size);
+
+        let Some((last_entry, entries)) = title_metadata.content_chunk_entries.split_last() else {
+            return first_content_offset;
+        };
+
+        entries.iter().fold(first_content_offset, |offset, entry| {
+            offset + util::align_to_boundary(entry.size, Self::SECTION_BOUNDARY)
+        }) + last_entry.size
+    }
+
+    /// Check that the WAD stream is long enough to hold all the contents listed on the title
+    /// metadata, failing with [InstallableWadError::TruncatedWad] otherwise.
+    ///
+    /// Useful to detect incomplete downloads before the decryption of the contents fails with
+    /// less meaningful errors.
+    pub fn check_truncation<T: Seek>(
+        &self,
+        stream: T,
+        title_metadata: &TitleMetadata,
+    ) -> Result<(), InstallableWadError> {
+        self.check_truncation_with_compat(stream, title_metadata, &FormatCompat::STRICT)
+    }
+
+    /// Like [Self::check_truncation] but with [FormatCompat::unaligned_contents] the stream only
+    /// needs to be long enough to hold the contents stored back to back.
+    pub fn check_truncation_with_compat<T: Seek>(
+        &self,
+        mut stream: T,
+        title_metadata: &TitleMetadata,
+        compat: &FormatCompat,
+    ) -> Result<(), InstallableWadError> {
+        let expected_len = if compat.unaligned_contents {
+            let first_content_offset = Self::HEADER_SIZE
+                + Self::align_u64(self.certificate_chain_size)
+                + Self::align_u64(self.ticket_size)
+                + Self::align_u64(self.title_metadata_size);
+
+            title_metadata
+                .content_chunk_entries
+                .iter()
+                .fold(first_content_offset, |offset, entry| offset + entry.size)
+        } else {
+            self.contents_end(title_metadata)
+        };
+
+        let stream_len = stream.seek(SeekFrom::End(0))?;
+
+        if stream_len < expected_len {
+            return Err(InstallableWadError::TruncatedWad(expected_len, stream_len));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::title_metadata::TitleMetadataContentEntryKind;
+    use crate::wad::installable::test_wad;
+    use std::io::Cursor;
+
+    #[test]
+    fn detect_truncated_wad() {
+        let mut title_metadata = templates::homebrew_channel_tmd();
+
+        for (id, size) in [(0, 0x10), (1, 0x20)] {
+            title_metadata
+                .content_chunk_entries
+                .push(test_wad::content_entry(
+                    id,
+                    id as u16,
+                    TitleMetadataContentEntryKind::Normal,
+                    size,
+                ));
+        }
+
+        let wad = InstallableWad {
+            content_size: 0x30,
+            ..test_wad::header()
+        };
+
+        // Header, first content with its padding and the second content
+        assert_eq!(wad.contents_end(&title_metadata), 64 + 64 + 0x20);
+
+        let mut stream = Cursor::new(vec![0; 64 + 64 + 0x20]);
+        wad.check_truncation(&mut stream, &title_metadata).unwrap();
+
+        stream.get_mut().truncate(64 + 64 + 0x1F);
+        assert!(matches!(
+            wad.check_truncation(&mut stream, &title_metadata),
+            Err(InstallableWadError::TruncatedWad(0xA0, 0x9F))
+        ));
+    }
+
+    #[test]
+    fn accept_unaligned_contents() {
+        let mut title_metadata = templates::homebrew_channel_tmd();
+
+        for (id, size) in [(0, 0x10), (1, 0x20)] {
+            title_metadata
+                .content_chunk_entries
+                .push(test_wad::content_entry(
+                    id,
+                    id as u16,
+                    TitleMetadataContentEntryKind::Normal,
+                    size,
+                ));
+        }
+
+        let wad = InstallableWad {
+            content_size: 0x30,
+            ..test_wad::header()
+        };
+
+        // Both contents back to back, without padding
+        let mut data = vec![0; 64];
+        data.extend([0xAA; 0x10]);
+        data.extend([0xBB; 0x20]);
+        let mut stream = Cursor::new(data);
+
+        assert!(wad.check_truncation(&mut stream, &title_metadata).is_err());
+        wad.check_truncation_with_compat(&mut stream, &title_metadata, &FormatCompat::PERMISSIVE)
+            .unwrap();
+
+        let selector = title_metadata.select_with_physical_position(1);
+
+        wad.seek_content(&mut stream, &title_metadata, selector)
+            .unwrap();
+        assert_eq!(stream.position(), 64 + 64);
+
+        wad.seek_content_with_compat(
+            &mut stream,
+            &title_metadata,
+            selector,
+            &FormatCompat::PERMISSIVE,
+        )
+        .unwrap();
+        assert_eq!(stream.position(), 64 + 0x10);
+    }
+}
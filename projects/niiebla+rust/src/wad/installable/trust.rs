@@ -0,0 +1,397 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::CryptographicMethod;
+use crate::certificate_chain::{CertificateChain, CertificateChainError};
+use crate::format_compat::FormatCompat;
+use crate::signed_blob_header::SignedBlobHeader;
+use crate::telemetry;
+use crate::wad::InstallableWad;
+use crate::wad::InstallableWadError;
+use sha1::{Digest, Sha1};
+use std::io::{Cursor, Read, Seek};
+use util::StreamPin;
+
+/// The authenticity of a WAD as checked by [InstallableWad::trust_report].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrustReport {
+    /// Every signature is valid and made by the certificate chain of the WAD, and every content
+    /// matches its hash.
+    Legit,
+
+    /// The WAD is intact but some blobs are fakesigned, it can only be installed on a console
+    /// running an IOS patched to not check the signatures properly.
+    Fakesigned {
+        /// The fakesigned blobs.
+        blobs: Vec<TrustSignedBlob>,
+    },
+
+    /// The WAD cannot be installed as is.
+    Broken {
+        /// Every problem found, in the order they are stored inside the WAD.
+        reasons: Vec<TrustIssue>,
+    },
+}
+
+impl TrustReport {
+    /// Check if the WAD is [TrustReport::Legit].
+    pub fn is_legit(&self) -> bool {
+        matches!(self, Self::Legit)
+    }
+}
+
+/// A signed blob of a WAD checked by [InstallableWad::trust_report].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrustSignedBlob {
+    /// A certificate of the certificate chain, with its identity (like `CP00000004`).
+    Certificate(String),
+
+    /// The ticket.
+    Ticket,
+
+    /// The title metadata.
+    TitleMetadata,
+}
+
+/// A problem found by [InstallableWad::trust_report] that makes a WAD
+/// [TrustReport::Broken].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrustIssue {
+    /// The signature of the blob is neither valid nor fakesigned.
+    InvalidSignature(TrustSignedBlob),
+
+    /// The certificate that issued the blob (like `Root-CA00000001-XS00000003`) is not on the
+    /// certificate chain of the WAD.
+    UnknownIssuer(TrustSignedBlob, String),
+
+    /// The hash of the content does not match the one of the title metadata (with the ID of
+    /// the content).
+    ContentHashMismatch(u32),
+
+    /// The WAD stream is not long enough to hold all its contents, with the expected and the
+    /// actual sizes in bytes.
+    Truncated(u64, u64),
+}
+
+/// The state of the signature of a single blob.
+enum SignatureStatus {
+    Valid,
+    Fakesigned,
+    Invalid,
+    UnknownIssuer(String),
+}
+
+impl InstallableWad {
+    /// Check the authenticity of the WAD in a single call: the certificate chain, the
+    /// signatures of the ticket and the title metadata (detecting fakesigned ones) and the
+    /// hashes of every content.
+    ///
+    /// The public key of the root certificate is not stored in the WAD, the chain is only
+    /// validated up to the certificates issued by `Root` (usually `CA00000001`), which are
+    /// trusted as is.
+    pub fn trust_report<T: Read + Seek>(
+        &self,
+        stream: T,
+    ) -> Result<TrustReport, InstallableWadError> {
+        self.trust_report_with_compat(stream, &FormatCompat::STRICT)
+    }
+
+    /// Like [Self::trust_report] but accepting the quirks of the given [FormatCompat] when
+    /// locating and checking the contents.
+    pub fn trust_report_with_compat<T: Read + Seek>(
+        &self,
+        stream: T,
+        compat: &FormatCompat,
+    ) -> Result<TrustReport, InstallableWadError> {
+        let mut stream = StreamPin::new(stream)?;
+
+        let certificate_chain = self.certificate_chain(&mut stream)?;
+        let ticket = self.ticket(&mut stream)?;
+        let title_metadata = self.title_metadata(&mut stream)?;
+
+        let mut blobs = vec![];
+
+        for certificate in &certificate_chain.certificates {
+            // Signed with the private key of the root certificate, not available
+            if certificate.signed_blob_header.issuer == "Root" {
+                continue;
+            }
+
+            let mut blob = vec![];
+            certificate.dump(Cursor::new(&mut blob))?;
+
+            blobs.push((
+                TrustSignedBlob::Certificate(certificate.identity.clone()),
+                blob,
+            ));
+        }
+
+        let mut blob = vec![0; self.ticket_size as usize];
+        self.ticket_view(&mut stream)?.read_exact(&mut blob)?;
+        blobs.push((TrustSignedBlob::Ticket, blob));
+
+        let mut blob = vec![0; self.title_metadata_size as usize];
+        self.title_metadata_view(&mut stream)?
+            .read_exact(&mut blob)?;
+        blobs.push((TrustSignedBlob::TitleMetadata, blob));
+
+        let mut reasons = vec![];
+        let mut fakesigned_blobs = vec![];
+
+        for (signed_blob, blob) in blobs {
+            match signature_status(&blob, &certificate_chain)? {
+                SignatureStatus::Valid => (),
+                SignatureStatus::Fakesigned => fakesigned_blobs.push(signed_blob),
+                SignatureStatus::Invalid => reasons.push(TrustIssue::InvalidSignature(signed_blob)),
+                SignatureStatus::UnknownIssuer(issuer) => {
+                    reasons.push(TrustIssue::UnknownIssuer(signed_blob, issuer));
+                }
+            }
+        }
+
+        match self.check_truncation_with_compat(&mut stream, &title_metadata, compat) {
+            Ok(()) => {
+                for (i, content_entry) in title_metadata.content_chunk_entries.iter().enumerate() {
+                    // The hashes are of the data without the padding of its last AES block
+                    let decrypted_content_view = self.decrypted_content_view_with_compat(
+                        &mut stream,
+                        &ticket,
+                        &title_metadata,
+                        CryptographicMethod::Wii,
+                        title_metadata.select_with_physical_position(i),
+                        compat,
+                    )?;
+
+                    if !Self::has_valid_hash_with_compat(
+                        decrypted_content_view,
+                        content_entry,
+                        compat,
+                    )? {
+                        reasons.push(TrustIssue::ContentHashMismatch(content_entry.id));
+                    }
+                }
+            }
+
+            Err(InstallableWadError::TruncatedWad(expected_len, stream_len)) => {
+                reasons.push(TrustIssue::Truncated(expected_len, stream_len));
+            }
+
+            Err(err) => return Err(err),
+        }
+
+        Ok(if !reasons.is_empty() {
+            TrustReport::Broken { reasons }
+        } else if !fakesigned_blobs.is_empty() {
+            TrustReport::Fakesigned {
+                blobs: fakesigned_blobs,
+            }
+        } else {
+            TrustReport::Legit
+        })
+    }
+}
+
+/// Check the signature of a dumped blob against the certificate that issued it.
+fn signature_status(
+    blob: &[u8],
+    certificate_chain: &CertificateChain,
+) -> Result<SignatureStatus, CertificateChainError> {
+    let signed_blob_header = SignedBlobHeader::new(Cursor::new(blob))?;
+
+    // The issuer is stored at the end of the header
+    let signed_data_offset = signed_blob_header.size() as usize - 64;
+    let signed_data = blob.get(signed_data_offset..).unwrap_or_default();
+
+    if signed_blob_header.is_signature_zeroed() && Sha1::digest(signed_data)[0] == 0 {
+        return Ok(SignatureStatus::Fakesigned);
+    }
+
+    // The issuer is the path of certificates that lead to the signer (like
+    // `Root-CA00000001-XS00000003`), the last one being the signer itself
+    let issuer = &signed_blob_header.issuer;
+
+    let Some((signer_issuer, signer_identity)) = issuer.rsplit_once('-') else {
+        return Ok(SignatureStatus::UnknownIssuer(issuer.clone()));
+    };
+
+    let Some(certificate) = certificate_chain.certificates.iter().find(|certificate| {
+        certificate.identity == signer_identity
+            && certificate.signed_blob_header.issuer == signer_issuer
+    }) else {
+        return Ok(SignatureStatus::UnknownIssuer(issuer.clone()));
+    };
+
+    // A signature of a kind different to the one of the key is not valid either
+    let is_valid = certificate
+        .verifier()
+        .and_then(|verifier| verifier.verify(&signed_blob_header.signature, signed_data))
+        .unwrap_or(false);
+
+    if !is_valid {
+        telemetry::verification_failed("signature");
+        return Ok(SignatureStatus::Invalid);
+    }
+
+    Ok(SignatureStatus::Valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PreSwitchTicket;
+    use crate::TitleMetadata;
+    use crate::certificate_chain::{Certificate, CertificateKey, CertificateKeyValue};
+    use crate::signed_blob_header::SignedBlobHeaderSignature;
+    use crate::wad::installable::test_wad::{TestWad, TestWadBuilder};
+    use std::io::Write;
+
+    /// DER encoded `DigestInfo` prefix of a SHA-1 hash.
+    const SHA1_DIGEST_INFO_PREFIX: [u8; 15] = [
+        0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2B, 0x0E, 0x03, 0x02, 0x1A, 0x05, 0x00, 0x04, 0x14,
+    ];
+
+    /// With a public exponent of one the signature is the padded digest itself.
+    fn sign<const N: usize>(data: &[u8]) -> Box<[u8; N]> {
+        let mut signature = Box::new([0xFF; N]);
+        signature[0] = 0x00;
+        signature[1] = 0x01;
+        signature[N - 36] = 0x00;
+        signature[N - 35..N - 20].copy_from_slice(&SHA1_DIGEST_INFO_PREFIX);
+        signature[N - 20..].copy_from_slice(&Sha1::digest(data));
+
+        signature
+    }
+
+    fn signed_data<F: FnOnce(&mut Cursor<Vec<u8>>)>(header: &SignedBlobHeader, dump: F) -> Vec<u8> {
+        let mut blob = Cursor::new(vec![]);
+        dump(&mut blob);
+
+        blob.into_inner()[header.size() as usize - 64..].to_vec()
+    }
+
+    fn certificate(issuer: &str, identity: &str, value: CertificateKeyValue) -> Certificate {
+        let mut certificate = Certificate {
+            signed_blob_header: SignedBlobHeader {
+                signature: SignedBlobHeaderSignature::Rsa4096Sha1(Box::new([0; 512])),
+                issuer: issuer.to_string(),
+            },
+            identity: identity.to_string(),
+            key: CertificateKey { id: 0, value },
+        };
+
+        let data = signed_data(&certificate.signed_blob_header, |blob| {
+            certificate.dump(blob).unwrap();
+        });
+        certificate.signed_blob_header.signature =
+            SignedBlobHeaderSignature::Rsa4096Sha1(sign(&data));
+
+        certificate
+    }
+
+    fn rsa2048_key() -> CertificateKeyValue {
+        let mut value = [0xFF; 256 + 4];
+        value[256..].copy_from_slice(&1_u32.to_be_bytes());
+
+        CertificateKeyValue::Rsa2048(Box::new(value))
+    }
+
+    fn sign_ticket(ticket: &mut PreSwitchTicket) {
+        let data = signed_data(&ticket.signed_blob_header, |blob| {
+            ticket.dump(blob).unwrap()
+        });
+        ticket.signed_blob_header.signature = SignedBlobHeaderSignature::Rsa2048Sha1(sign(&data));
+    }
+
+    fn sign_title_metadata(title_metadata: &mut TitleMetadata) {
+        let data = signed_data(&title_metadata.signed_blob_header, |blob| {
+            title_metadata.dump(blob).unwrap();
+        });
+        title_metadata.signed_blob_header.signature =
+            SignedBlobHeaderSignature::Rsa2048Sha1(sign(&data));
+    }
+
+    #[test]
+    fn trust_report() {
+        let mut ca_key = [0xFF; 512 + 4];
+        ca_key[512..].copy_from_slice(&1_u32.to_be_bytes());
+
+        let certificate_chain = CertificateChain {
+            certificates: vec![
+                certificate(
+                    "Root",
+                    "CA00000001",
+                    CertificateKeyValue::Rsa4096(Box::new(ca_key)),
+                ),
+                certificate("Root-CA00000001", "CP00000004", rsa2048_key()),
+                certificate("Root-CA00000001", "XS00000003", rsa2048_key()),
+            ],
+        };
+
+        let TestWad {
+            mut wad,
+            mut stream,
+            mut ticket,
+            mut title_metadata,
+        } = TestWadBuilder::new()
+            // Sizes that are not a multiple of the AES block size
+            .content(0, vec![0x42; 0x35])
+            .content(1, vec![0x24; 0x1F])
+            .build();
+
+        sign_ticket(&mut ticket);
+        sign_title_metadata(&mut title_metadata);
+
+        stream.set_position(0);
+        wad.write_certificate_chain_safe(&mut stream, &certificate_chain, &ticket, &title_metadata)
+            .unwrap();
+
+        stream.set_position(0);
+        assert!(wad.trust_report(&mut stream).unwrap().is_legit());
+
+        ticket.fakesign().unwrap();
+        stream.set_position(0);
+        wad.write_ticket_safe(&mut stream, &ticket, &title_metadata)
+            .unwrap();
+
+        stream.set_position(0);
+        assert_eq!(
+            wad.trust_report(&mut stream).unwrap(),
+            TrustReport::Fakesigned {
+                blobs: vec![TrustSignedBlob::Ticket]
+            }
+        );
+
+        title_metadata.signed_blob_header.issuer = String::from("Root-CA00000001-CP0000000B");
+
+        stream.set_position(0);
+        wad.write_title_metadata_safe(&mut stream, &title_metadata)
+            .unwrap();
+
+        // Corrupt the content
+        stream.set_position(0);
+        wad.seek_content(
+            &mut stream,
+            &title_metadata,
+            title_metadata.select_with_physical_position(0),
+        )
+        .unwrap();
+        stream.write_all(&[0; 16]).unwrap();
+
+        stream.set_position(0);
+        assert_eq!(
+            wad.trust_report(&mut stream).unwrap(),
+            TrustReport::Broken {
+                reasons: vec![
+                    TrustIssue::UnknownIssuer(
+                        TrustSignedBlob::TitleMetadata,
+                        String::from("Root-CA00000001-CP0000000B")
+                    ),
+                    TrustIssue::ContentHashMismatch(0),
+                ]
+            }
+        );
+    }
+}
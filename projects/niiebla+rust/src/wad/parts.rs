@@ -0,0 +1,168 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::wad::installable::{InstallableWad, InstallableWadError};
+use crate::wad::{Wad, WadError};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+
+impl Wad {
+    /// Max size of a file stored on a FAT32 filesystem (4 GiB minus one byte), the usual
+    /// `chunk_size` of [Self::split_file].
+    pub const FAT32_MAX_FILE_SIZE: u64 = 0xFFFF_FFFF;
+
+    /// Get the path of a part of a WAD split with [Self::split_file], the first one is
+    /// `title.wad.part1`.
+    pub fn part_path<P: AsRef<Path>>(path: P, number: usize) -> PathBuf {
+        let mut part_path = OsString::from(path.as_ref());
+        part_path.push(format!(".part{number}"));
+
+        PathBuf::from(part_path)
+    }
+
+    /// Split the installable WAD at the given path into parts of at most `chunk_size` bytes
+    /// (`title.wad.part1`, `title.wad.part2`, etc), to store WADs bigger than
+    /// [Self::FAT32_MAX_FILE_SIZE] on FAT32 SD cards. Returns the paths of the written parts.
+    ///
+    /// The parts are plain slices of the WAD, concatenating them (like installers do, or
+    /// [Self::join_files]) gives back the original file. Nothing is added to the WAD, the sizes
+    /// of the sections stored on its header are enough to detect a missing part when joining it.
+    pub fn split_file<P: AsRef<Path>>(path: P, chunk_size: u64) -> Result<Vec<PathBuf>, WadError> {
+        if chunk_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The size of the parts cannot be zero",
+            )
+            .into());
+        }
+
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+
+        // Only split valid WADs
+        Self::try_new_installable(&mut file)?;
+        file.rewind()?;
+
+        let len = file.metadata()?.len();
+        let mut part_paths = vec![];
+
+        for number in 1..=len.div_ceil(chunk_size).max(1) {
+            let part_path = Self::part_path(path, number as usize);
+
+            io::copy(
+                &mut Read::by_ref(&mut file).take(chunk_size),
+                &mut File::create(&part_path)?,
+            )?;
+
+            part_paths.push(part_path);
+        }
+
+        Ok(part_paths)
+    }
+
+    /// Get the paths of the parts of a split WAD that exist (from `title.wad.part1` up to the
+    /// first missing one).
+    pub fn find_parts<P: AsRef<Path>>(path: P) -> Vec<PathBuf> {
+        (1..)
+            .map(|number| Self::part_path(&path, number))
+            .take_while(|part_path| part_path.is_file())
+            .collect()
+    }
+
+    /// Join the parts of a WAD split with [Self::split_file] (in order) into the file at
+    /// `out_path`, returns the joined installable WAD.
+    ///
+    /// The joined file is checked against the header of the WAD, if a part is missing it fails
+    /// with [InstallableWadError::TruncatedWad] and the joined file is removed.
+    pub fn join_files<P: AsRef<Path>, Q: AsRef<Path>>(
+        parts: &[P],
+        out_path: Q,
+    ) -> Result<InstallableWad, WadError> {
+        let out_path = out_path.as_ref();
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(out_path)?;
+
+        let joined_wad = Self::join_into(parts, &mut file);
+
+        if joined_wad.is_err() {
+            drop(file);
+            fs::remove_file(out_path)?;
+        }
+
+        joined_wad
+    }
+
+    fn join_into<P: AsRef<Path>>(parts: &[P], file: &mut File) -> Result<InstallableWad, WadError> {
+        for part in parts {
+            io::copy(&mut File::open(part)?, file)?;
+        }
+
+        file.rewind()?;
+        let wad = Self::try_new_installable(&mut *file)?;
+
+        let title_metadata = wad
+            .title_metadata(&mut *file)
+            .map_err(InstallableWadError::from)?;
+        wad.check_truncation(&mut *file, &title_metadata)?;
+
+        Ok(wad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::installable::test_wad::TestWadBuilder;
+
+    #[test]
+    fn split_and_join_files() {
+        let dir = std::env::temp_dir().join(format!("niiebla_parts_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("title.wad");
+
+        let data = TestWadBuilder::new()
+            .content(0, vec![0x42; 0x1234])
+            .build()
+            .stream
+            .into_inner();
+        fs::write(&path, &data).unwrap();
+
+        assert!(matches!(
+            Wad::split_file(&path, 0),
+            Err(WadError::IoError(_))
+        ));
+
+        let part_paths = Wad::split_file(&path, 0x400).unwrap();
+
+        assert_eq!(part_paths.len(), data.len().div_ceil(0x400));
+        assert_eq!(part_paths[0], dir.join("title.wad.part1"));
+        assert_eq!(fs::metadata(&part_paths[0]).unwrap().len(), 0x400);
+        assert_eq!(Wad::find_parts(&path), part_paths);
+
+        let joined_path = dir.join("joined.wad");
+        let joined_wad = Wad::join_files(&part_paths, &joined_path).unwrap();
+
+        assert_eq!(joined_wad.content_size, 0x1234);
+        assert_eq!(fs::read(&joined_path).unwrap(), data);
+
+        // Without the last part
+        assert!(matches!(
+            Wad::join_files(&part_paths[..part_paths.len() - 1], &joined_path),
+            Err(WadError::InstallableWadParseError(
+                InstallableWadError::TruncatedWad(..)
+            ))
+        ));
+        assert!(!joined_path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
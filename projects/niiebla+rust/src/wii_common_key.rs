@@ -22,6 +22,10 @@ pub enum WiiCommonKeyKind {
 
     /// Key used on the virtual Wii console (vWii) inside the Nintendo Wii U.
     WiiUvWii,
+
+    /// Key used on development consoles (NDEV and RVT units), titles signed with the SDK use it
+    /// instead of [Self::Normal].
+    Development,
 }
 
 #[derive(Error, Debug)]
@@ -29,6 +33,9 @@ pub enum WiiCommonKeyKind {
 pub enum CommonKeyKindError {
     #[error("Unknown common key index: {0}")]
     UnknownCommonKeyIndex(u8),
+
+    #[error("Unknown common key index of development consoles: {0}")]
+    UnknownDevelopmentCommonKeyIndex(u8),
 }
 
 impl WiiCommonKeyKind {
@@ -43,10 +50,24 @@ impl WiiCommonKeyKind {
         })
     }
 
+    /// Get a common key of development consoles given its "common key index" (identifier). Only
+    /// the development counterpart of [Self::Normal] is known.
+    pub const fn new_development(identifier: u8) -> Result<Self, CommonKeyKindError> {
+        Ok(match identifier {
+            0 => Self::Development,
+
+            identifier => {
+                return Err(CommonKeyKindError::UnknownDevelopmentCommonKeyIndex(
+                    identifier,
+                ));
+            }
+        })
+    }
+
     /// Get the identifier associated with the given common key.
     pub fn dump_identifier<T: Write>(&self, mut stream: T) -> io::Result<()> {
         stream.write_u8(match self {
-            Self::Normal => 0,
+            Self::Normal | Self::Development => 0,
             Self::Korean => 1,
             Self::WiiUvWii => 2,
         })?;
@@ -69,6 +90,10 @@ impl WiiCommonKeyKind {
                 0x30, 0xbf, 0xc7, 0x6e, 0x7c, 0x19, 0xaf, 0xbb, 0x23, 0x16, 0x33, 0x30, 0xce, 0xd7,
                 0xc2, 0x8d,
             ],
+            Self::Development => [
+                0xa1, 0x60, 0x4a, 0x6a, 0x71, 0x23, 0xb5, 0x29, 0xae, 0x8b, 0xec, 0x32, 0xc8, 0x16,
+                0xfc, 0xaa,
+            ],
         }
     }
 }
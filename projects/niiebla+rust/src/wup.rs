@@ -0,0 +1,305 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Implementation of the folder layout used to install Wii U titles with homebrew installers
+//! (like WUP Installer GX2).
+
+use crate::certificate_chain::{Certificate, CertificateChain, CertificateChainError};
+use crate::ticket::{PreSwitchTicket, PreSwitchTicketError};
+use crate::title_metadata::{
+    TitleMetadata, TitleMetadataContentEntry, TitleMetadataError, TitleMetadataPlatformData,
+};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use util::StreamPin;
+
+/// Writer of a Wii U title into a folder with the WUP layout: `title.tmd`, `title.tik`,
+/// `title.cert` and the encrypted contents as `<id>.app` (with their hashes as `<id>.h3`).
+#[derive(Debug)]
+pub struct WupExport {
+    out_dir: PathBuf,
+}
+
+impl WupExport {
+    /// Name of the file where the title metadata is stored.
+    pub const TITLE_METADATA_FILE_NAME: &str = "title.tmd";
+
+    /// Name of the file where the ticket is stored.
+    pub const TICKET_FILE_NAME: &str = "title.tik";
+
+    /// Name of the file where the certificate chain is stored.
+    pub const CERTIFICATE_CHAIN_FILE_NAME: &str = "title.cert";
+
+    /// Create the folder and write the title metadata, ticket and certificate chain into it. The
+    /// contents must be added with [Self::write_content].
+    pub fn new<P: AsRef<Path>>(
+        out_dir: P,
+        title_metadata: &TitleMetadata,
+        ticket: &PreSwitchTicket,
+        certificate_chain: &CertificateChain,
+    ) -> Result<Self, WupExportError> {
+        if !matches!(
+            title_metadata.platform_data,
+            TitleMetadataPlatformData::WiiU
+        ) {
+            return Err(WupExportError::NotAWiiUTitle);
+        }
+
+        let out_dir = out_dir.as_ref().to_path_buf();
+        fs::create_dir_all(&out_dir)?;
+
+        title_metadata.dump(File::create(out_dir.join(Self::TITLE_METADATA_FILE_NAME))?)?;
+        ticket.dump(File::create(out_dir.join(Self::TICKET_FILE_NAME))?)?;
+        certificate_chain.dump(File::create(
+            out_dir.join(Self::CERTIFICATE_CHAIN_FILE_NAME),
+        )?)?;
+
+        Ok(Self { out_dir })
+    }
+
+    /// Regenerate the certificate chain of a title (the `title.cert` file) from the certificates
+    /// appended by the CDN after its title metadata (the `tmd` file) and its ticket (the `cetk`
+    /// file).
+    ///
+    /// The chain is sorted like the one of the installers: first the certificates of the issuer of
+    /// the title metadata (`CA00000003` and `CP0000000b`), then the ones of the issuer of the
+    /// ticket not already present (`XS0000000c`).
+    pub fn regenerate_certificate_chain<T: Read + Seek, U: Read + Seek>(
+        mut title_metadata_stream: T,
+        mut ticket_stream: U,
+    ) -> Result<CertificateChain, WupExportError> {
+        let title_metadata_start = title_metadata_stream.stream_position()?;
+        let title_metadata = TitleMetadata::new(&mut title_metadata_stream)?;
+        title_metadata_stream.seek(SeekFrom::Start(
+            title_metadata_start + title_metadata.size() as u64,
+        ))?;
+
+        let ticket_start = ticket_stream.stream_position()?;
+        let ticket = PreSwitchTicket::new(&mut ticket_stream)?;
+        ticket_stream.seek(SeekFrom::Start(ticket_start + ticket.size() as u64))?;
+
+        let mut appended_certificates = parse_appended_certificates(title_metadata_stream)?;
+        appended_certificates.extend(parse_appended_certificates(ticket_stream)?);
+
+        let mut certificates: Vec<Certificate> = vec![];
+
+        // Issuers have the form `Root-CA00000003-CP0000000b`, the root is not stored
+        for identity in [
+            &title_metadata.signed_blob_header.issuer,
+            &ticket.signed_blob_header.issuer,
+        ]
+        .into_iter()
+        .flat_map(|issuer| issuer.split('-').skip(1))
+        {
+            if certificates
+                .iter()
+                .any(|certificate| certificate.identity == identity)
+            {
+                continue;
+            }
+
+            let certificate = appended_certificates
+                .iter()
+                .find(|certificate| certificate.identity == identity)
+                .ok_or_else(|| WupExportError::MissingCertificate(identity.to_string()))?;
+
+            certificates.push(certificate.clone());
+        }
+
+        Ok(CertificateChain { certificates })
+    }
+
+    /// Get the name of the file where a content is stored, its ID in lowercase hexadecimal with
+    /// the `.app` extension.
+    pub fn content_file_name(id: u32) -> String {
+        format!("{id:08x}.app")
+    }
+
+    /// Get the name of the file where the H3 hashes of a content are stored, its ID in lowercase
+    /// hexadecimal with the `.h3` extension.
+    pub fn content_h3_file_name(id: u32) -> String {
+        format!("{id:08x}.h3")
+    }
+
+    /// Write the data of a content (still encrypted) into the folder.
+    pub fn write_content<T: Read>(
+        &self,
+        content_entry: &TitleMetadataContentEntry,
+        mut stream: T,
+    ) -> Result<(), WupExportError> {
+        let mut file = File::create(self.out_dir.join(Self::content_file_name(content_entry.id)))?;
+
+        let size = io::copy(&mut stream, &mut file)?;
+
+        if size != content_entry.size {
+            return Err(WupExportError::ContentSizeMismatch {
+                id: content_entry.id,
+                expected: content_entry.size,
+                found: size,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Write the H3 hashes of a hashed content into the folder, they cannot be regenerated
+    /// without decrypting the content so they must be provided (usually downloaded from the NUS
+    /// alongside the content).
+    pub fn write_content_h3<T: Read>(
+        &self,
+        content_entry: &TitleMetadataContentEntry,
+        mut stream: T,
+    ) -> Result<(), WupExportError> {
+        let mut file = File::create(
+            self.out_dir
+                .join(Self::content_h3_file_name(content_entry.id)),
+        )?;
+
+        io::copy(&mut stream, &mut file)?;
+
+        Ok(())
+    }
+}
+
+/// Parse the certificates stored from the current position of the stream up to its end.
+fn parse_appended_certificates<T: Read + Seek>(
+    stream: T,
+) -> Result<Vec<Certificate>, WupExportError> {
+    let mut stream = StreamPin::new(stream)?;
+    let mut certificates = vec![];
+
+    let end = stream.seek(SeekFrom::End(0))?;
+    stream.go_to_pin()?;
+
+    while stream.stream_position()? < end {
+        certificates.push(Certificate::new(&mut stream)?);
+        stream.align_position(64)?;
+    }
+
+    Ok(certificates)
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum WupExportError {
+    #[error("An IO error has occurred: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("The given title is not for the Wii U platform")]
+    NotAWiiUTitle,
+
+    #[error(
+        "The size of the content {id:08x} does not match its entry: expected {expected} bytes, found {found}"
+    )]
+    ContentSizeMismatch { id: u32, expected: u64, found: u64 },
+
+    #[error("Title metadata error: {0}")]
+    TitleMetadataError(#[from] TitleMetadataError),
+
+    #[error("Ticket error: {0}")]
+    TicketError(#[from] PreSwitchTicketError),
+
+    #[error("Certificate chain error: {0}")]
+    CertificateChainError(#[from] CertificateChainError),
+
+    #[error("The certificate {0} is not appended to the title metadata nor the ticket")]
+    MissingCertificate(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate_chain::{CertificateKey, CertificateKeyValue};
+    use crate::signed_blob_header::{SignedBlobHeader, SignedBlobHeaderSignature};
+    use crate::templates;
+    use std::io::Cursor;
+
+    fn certificate(issuer: &str, identity: &str) -> Certificate {
+        Certificate {
+            signed_blob_header: SignedBlobHeader {
+                signature: SignedBlobHeaderSignature::Rsa2048Sha1(Box::new([0; 256])),
+                issuer: issuer.to_string(),
+            },
+            identity: identity.to_string(),
+            key: CertificateKey {
+                id: 0,
+                value: CertificateKeyValue::Rsa2048(Box::new([0; 260])),
+            },
+        }
+    }
+
+    /// Dump a blob followed by the given certificates, like the files downloaded from the CDN.
+    fn with_certificates(
+        dump: impl FnOnce(&mut Cursor<Vec<u8>>),
+        certificates: &[Certificate],
+    ) -> Cursor<Vec<u8>> {
+        let mut stream = Cursor::new(vec![]);
+        dump(&mut stream);
+
+        CertificateChain {
+            certificates: certificates.to_vec(),
+        }
+        .dump(&mut stream)
+        .unwrap();
+
+        stream.set_position(0);
+        stream
+    }
+
+    #[test]
+    fn regenerate_certificate_chain() {
+        let ca = certificate("Root", "CA00000003");
+        let cp = certificate("Root-CA00000003", "CP0000000b");
+        let xs = certificate("Root-CA00000003", "XS0000000c");
+
+        let mut title_metadata = templates::homebrew_channel_tmd();
+        title_metadata.platform_data = TitleMetadataPlatformData::WiiU;
+        title_metadata.signed_blob_header.issuer = String::from("Root-CA00000003-CP0000000b");
+
+        let mut ticket = templates::homebrew_channel_ticket();
+        ticket.signed_blob_header.issuer = String::from("Root-CA00000003-XS0000000c");
+
+        let title_metadata_stream = with_certificates(
+            |stream| title_metadata.dump(stream).unwrap(),
+            &[cp.clone(), ca.clone()],
+        );
+        let ticket_stream =
+            with_certificates(|stream| ticket.dump(stream).unwrap(), &[xs.clone(), ca]);
+
+        let certificate_chain =
+            WupExport::regenerate_certificate_chain(title_metadata_stream, ticket_stream).unwrap();
+
+        let identities: Vec<_> = certificate_chain
+            .certificates
+            .iter()
+            .map(|certificate| certificate.identity.as_str())
+            .collect();
+
+        assert_eq!(identities, ["CA00000003", "CP0000000b", "XS0000000c"]);
+
+        let out_dir = std::env::temp_dir().join(format!("niiebla_wup_{}", std::process::id()));
+        WupExport::new(&out_dir, &title_metadata, &ticket, &certificate_chain).unwrap();
+
+        let title_cert = fs::read(out_dir.join(WupExport::CERTIFICATE_CHAIN_FILE_NAME)).unwrap();
+        let parsed = CertificateChain::new(Cursor::new(title_cert), 3).unwrap();
+
+        assert_eq!(parsed.certificates, certificate_chain.certificates);
+
+        fs::remove_dir_all(out_dir).unwrap();
+
+        // The CA certificate is missing
+        let title_metadata_stream =
+            with_certificates(|stream| title_metadata.dump(stream).unwrap(), &[cp]);
+        let ticket_stream = with_certificates(|stream| ticket.dump(stream).unwrap(), &[xs]);
+
+        assert!(matches!(
+            WupExport::regenerate_certificate_chain(title_metadata_stream, ticket_stream),
+            Err(WupExportError::MissingCertificate(identity)) if identity == "CA00000003"
+        ));
+    }
+}
@@ -0,0 +1,205 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Compatibility tests against the outputs of other established tools (like libWiiPy or
+//! Sharpii) over the same inputs, to catch subtle misinterpretations of the formats.
+//!
+//! The expected values are recorded in `tests/reference_tools/expectations.json`. Most inputs
+//! are copyrighted so they are not stored in the repository, only the freely distributable ones
+//! are kept at `tests/reference_tools/fixtures` (like a homebrew channel made with
+//! [templates::homebrew_wad](zelzip_niiebla::templates::homebrew_wad) whose values were
+//! recorded by parsing and decrypting it with Python and OpenSSL). Run the full suite with:
+//!
+//! ```sh
+//! NIIEBLA_REFERENCE_FIXTURES=path/to/wads cargo test -p zelzip_niiebla --features reference_tests
+//! ```
+//!
+//! Inputs are searched first on `NIIEBLA_REFERENCE_FIXTURES` (if set) and then on the fixtures
+//! of the repository, a missing input fails the suite.
+//!
+//! Each entry of `wads` has the following shape (hashes and keys as lowercase hexadecimal):
+//!
+//! ```json
+//! {
+//!   "file": "IOS58-64-v6176.wad",
+//!   "tool": "libWiiPy 0.6.0",
+//!   "title_id": "000000010000003a",
+//!   "title_version": 6176,
+//!   "ticket": {
+//!     "ticket_id": "0001000000000000",
+//!     "common_key_index": 0,
+//!     "encrypted_title_key": "..."
+//!   },
+//!   "contents": [{ "id": 0, "index": 0, "kind": "normal", "size": 64, "hash": "..." }]
+//! }
+//! ```
+//!
+//! Besides the parsed values, the dump of the parsed ticket and title metadata must match the
+//! original bytes.
+
+#![cfg(feature = "reference_tests")]
+
+use serde_json::Value;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zelzip_niiebla::Wad;
+use zelzip_niiebla::title_metadata::TitleMetadataContentEntryKind;
+
+const EXPECTATIONS: &str = include_str!("reference_tools/expectations.json");
+
+const BUNDLED_FIXTURES_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/reference_tools/fixtures"
+);
+
+/// Get the directories where the inputs are searched, failing if any of them is empty.
+fn fixtures_paths() -> Vec<PathBuf> {
+    let mut fixtures_paths = vec![];
+
+    if let Some(path) = std::env::var_os("NIIEBLA_REFERENCE_FIXTURES") {
+        fixtures_paths.push(PathBuf::from(path));
+    }
+
+    fixtures_paths.push(PathBuf::from(BUNDLED_FIXTURES_PATH));
+
+    for path in &fixtures_paths {
+        let is_empty = std::fs::read_dir(path)
+            .unwrap_or_else(|error| panic!("Unable to read the fixtures at {path:?}: {error}"))
+            .next()
+            .is_none();
+
+        assert!(!is_empty, "The fixtures directory {path:?} is empty");
+    }
+
+    fixtures_paths
+}
+
+fn find_fixture(fixtures_paths: &[PathBuf], file: &str) -> PathBuf {
+    fixtures_paths
+        .iter()
+        .map(|path| path.join(file))
+        .find(|path| Path::exists(path))
+        .unwrap_or_else(|| panic!("The input {file} is not on any of {fixtures_paths:?}"))
+}
+
+#[test]
+fn installable_wads() {
+    let fixtures_paths = fixtures_paths();
+
+    let expectations: Value = serde_json::from_str(EXPECTATIONS).unwrap();
+    let expectations = expectations["wads"].as_array().unwrap();
+
+    assert!(!expectations.is_empty(), "No expectations are recorded");
+
+    for expectation in expectations {
+        let file = expectation["file"].as_str().unwrap();
+        let context = format!("{file} (recorded with {})", expectation["tool"]);
+
+        let (wad, mut wad_file) = Wad::open_readonly(find_fixture(&fixtures_paths, file)).unwrap();
+        let ticket = wad.ticket(&mut wad_file).unwrap();
+        let title_metadata = wad.title_metadata(&mut wad_file).unwrap();
+
+        assert_eq!(
+            format!("{:016x}", title_metadata.title_id.inner()),
+            expectation["title_id"].as_str().unwrap(),
+            "{context}"
+        );
+        assert_eq!(
+            title_metadata.title_version as u64,
+            expectation["title_version"].as_u64().unwrap(),
+            "{context}"
+        );
+
+        let expected_ticket = &expectation["ticket"];
+        assert_eq!(
+            format!("{:016x}", ticket.ticket_id),
+            expected_ticket["ticket_id"].as_str().unwrap(),
+            "{context}"
+        );
+        assert_eq!(
+            ticket.common_key_kind_index as u64,
+            expected_ticket["common_key_index"].as_u64().unwrap(),
+            "{context}"
+        );
+        assert_eq!(
+            util::hex::encode(&ticket.encrypted_title_key),
+            expected_ticket["encrypted_title_key"].as_str().unwrap(),
+            "{context}"
+        );
+
+        let expected_contents = expectation["contents"].as_array().unwrap();
+        assert_eq!(
+            title_metadata.content_chunk_entries.len(),
+            expected_contents.len(),
+            "{context}"
+        );
+
+        for (content_entry, expected_content) in title_metadata
+            .content_chunk_entries
+            .iter()
+            .zip(expected_contents)
+        {
+            let kind = match content_entry.kind {
+                TitleMetadataContentEntryKind::Dlc => "dlc",
+                TitleMetadataContentEntryKind::Shared => "shared",
+                _ => "normal",
+            };
+
+            assert_eq!(
+                content_entry.id as u64,
+                expected_content["id"].as_u64().unwrap(),
+                "{context}"
+            );
+            assert_eq!(
+                content_entry.index as u64,
+                expected_content["index"].as_u64().unwrap(),
+                "{context}"
+            );
+            assert_eq!(
+                kind,
+                expected_content["kind"].as_str().unwrap(),
+                "{context}"
+            );
+            assert_eq!(
+                content_entry.size,
+                expected_content["size"].as_u64().unwrap(),
+                "{context}"
+            );
+            assert_eq!(
+                util::hex::encode(content_entry.hash.as_bytes()),
+                expected_content["hash"].as_str().unwrap(),
+                "{context}"
+            );
+        }
+
+        let mut original_ticket = vec![];
+        wad.ticket_view(&mut wad_file)
+            .unwrap()
+            .read_to_end(&mut original_ticket)
+            .unwrap();
+
+        let mut dumped_ticket = vec![];
+        ticket
+            .dump(&mut std::io::Cursor::new(&mut dumped_ticket))
+            .unwrap();
+        assert_eq!(dumped_ticket, original_ticket, "{context}: ticket dump");
+
+        let mut original_title_metadata = vec![];
+        wad.title_metadata_view(&mut wad_file)
+            .unwrap()
+            .read_to_end(&mut original_title_metadata)
+            .unwrap();
+
+        let mut dumped_title_metadata = vec![];
+        title_metadata
+            .dump(&mut std::io::Cursor::new(&mut dumped_title_metadata))
+            .unwrap();
+        assert_eq!(
+            dumped_title_metadata, original_title_metadata,
+            "{context}: title metadata dump"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+#
+# SPDX-License-Identifier: MPL-2.0
+{...}: {
+  title = "ZELZIP NiiEBLA CLI";
+
+  links = {};
+
+  body =
+    # markdown
+    ''
+      Command line tool to inspect and edit the file formats supported by the [NiiEBLA library](https://docs.rs/zelzip_niiebla).
+
+      ## Usage
+      Rename the channel stored inside a WAD (the WAD is modified in place):
+
+      ```sh
+      niiebla rename channel.wad --title "New Name"
+      ```
+
+      The banner of the channel is updated, the hash of its content on the title metadata is recalculated and the title metadata is fakesigned, so the WAD can only be installed on a console running an IOS vulnerable to the [Trucha bug](https://wiibrew.org/wiki/Signing_bug).
+    '';
+}
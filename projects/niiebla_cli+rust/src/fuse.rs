@@ -0,0 +1,371 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! [Filesystem] of [fuser] over a [Vfs], only with the read-only operations needed to browse it.
+
+use fuser::consts::FOPEN_KEEP_CACHE;
+use fuser::{
+    FileAttr, FileType, Filesystem, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry, ReplyOpen,
+    Request,
+};
+use niiebla::vfs::{Vfs, VfsEntry, VfsEntryKind, VfsError};
+use std::ffi::OsStr;
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::error;
+
+/// The inode of the root directory.
+const ROOT_ID: u64 = 1;
+
+/// Time the kernel can cache the entries and their attributes, the filesystem never changes.
+const TTL: Duration = Duration::from_secs(60);
+
+/// Read-only [Filesystem] over a [Vfs], the inodes are assigned on demand to the paths found.
+pub struct VfsFilesystem<V: Vfs> {
+    vfs: V,
+
+    // The path of each inode, starting at the one of the root
+    paths: Vec<String>,
+
+    // The owner of every file
+    uid: u32,
+    gid: u32,
+}
+
+impl<V: Vfs> VfsFilesystem<V> {
+    /// Create a new [VfsFilesystem] whose files are owned by the given user and group.
+    pub fn new(vfs: V, uid: u32, gid: u32) -> Self {
+        Self {
+            vfs,
+            paths: vec![String::from("/")],
+            uid,
+            gid,
+        }
+    }
+
+    fn lookup_attr(&mut self, parent: u64, name: &OsStr) -> Result<FileAttr, i32> {
+        let name = name.to_str().ok_or(libc::ENOENT)?;
+        let path = child_path(&self.path(parent)?, name);
+
+        let entry = self.vfs.metadata(&path).map_err(errno)?;
+        let ino = self.inode(&path);
+
+        Ok(self.attr(ino, &entry))
+    }
+
+    fn getattr_attr(&mut self, ino: u64) -> Result<FileAttr, i32> {
+        let path = self.path(ino)?;
+        let entry = self.vfs.metadata(&path).map_err(errno)?;
+
+        Ok(self.attr(ino, &entry))
+    }
+
+    /// Check that the inode can be opened read-only with the expected kind, returns the flags of
+    /// the opened file.
+    fn open_flags(&mut self, ino: u64, flags: i32, kind: VfsEntryKind) -> Result<u32, i32> {
+        if flags & libc::O_ACCMODE != libc::O_RDONLY {
+            return Err(libc::EROFS);
+        }
+
+        let path = self.path(ino)?;
+        let entry = self.vfs.metadata(&path).map_err(errno)?;
+
+        match (kind, entry.kind) {
+            (VfsEntryKind::File, VfsEntryKind::File) => Ok(FOPEN_KEEP_CACHE),
+            (VfsEntryKind::Directory, VfsEntryKind::Directory) => Ok(0),
+            (VfsEntryKind::File, VfsEntryKind::Directory) => Err(libc::EISDIR),
+            (VfsEntryKind::Directory, VfsEntryKind::File) => Err(libc::ENOTDIR),
+        }
+    }
+
+    fn read_data(&mut self, ino: u64, offset: i64, size: u32) -> Result<Vec<u8>, i32> {
+        let offset = u64::try_from(offset).map_err(|_| libc::EINVAL)?;
+        let path = self.path(ino)?;
+
+        let mut data = vec![0; size as usize];
+        let mut len = 0;
+
+        while len < data.len() {
+            let read_bytes = self
+                .vfs
+                .read_at(&path, offset + len as u64, &mut data[len..])
+                .map_err(errno)?;
+
+            if read_bytes == 0 {
+                break;
+            }
+
+            len += read_bytes;
+        }
+
+        data.truncate(len);
+        Ok(data)
+    }
+
+    /// Get the inode, kind and name of every entry of a directory, including `.` and `..`.
+    fn dir_entries(&mut self, ino: u64) -> Result<Vec<(u64, FileType, String)>, i32> {
+        let path = self.path(ino)?;
+        let parent_path = match path.trim_end_matches('/').rsplit_once('/') {
+            Some(("", _)) | None => String::from("/"),
+            Some((parent_path, _)) => parent_path.to_string(),
+        };
+
+        let mut entries = vec![
+            (ino, FileType::Directory, String::from(".")),
+            (
+                self.inode(&parent_path),
+                FileType::Directory,
+                String::from(".."),
+            ),
+        ];
+
+        for entry in self.vfs.read_dir(&path).map_err(errno)? {
+            let ino = self.inode(&child_path(&path, &entry.name));
+            entries.push((ino, file_type(entry.kind), entry.name));
+        }
+
+        Ok(entries)
+    }
+
+    fn attr(&self, ino: u64, entry: &VfsEntry) -> FileAttr {
+        let (perm, nlink) = match entry.kind {
+            VfsEntryKind::File => (0o444, 1),
+            VfsEntryKind::Directory => (0o555, 2),
+        };
+
+        FileAttr {
+            ino,
+            size: entry.size,
+            blocks: entry.size.div_ceil(512),
+            atime: UNIX_EPOCH,
+            mtime: UNIX_EPOCH,
+            ctime: UNIX_EPOCH,
+            crtime: UNIX_EPOCH,
+            kind: file_type(entry.kind),
+            perm,
+            nlink,
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: 4096,
+            flags: 0,
+        }
+    }
+
+    fn path(&self, ino: u64) -> Result<String, i32> {
+        ino.checked_sub(ROOT_ID)
+            .and_then(|i| self.paths.get(i as usize))
+            .cloned()
+            .ok_or(libc::ENOENT)
+    }
+
+    fn inode(&mut self, path: &str) -> u64 {
+        let i = self
+            .paths
+            .iter()
+            .position(|known_path| known_path == path)
+            .unwrap_or_else(|| {
+                self.paths.push(path.to_string());
+                self.paths.len() - 1
+            });
+
+        i as u64 + ROOT_ID
+    }
+}
+
+impl<V: Vfs> Filesystem for VfsFilesystem<V> {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        match self.lookup_attr(parent, name) {
+            Ok(attr) => reply.entry(&TTL, &attr, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+        match self.getattr_attr(ino) {
+            Ok(attr) => reply.attr(&TTL, &attr),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        // No handle is needed, the reads use the inode
+        match self.open_flags(ino, flags, VfsEntryKind::File) {
+            Ok(open_flags) => reply.opened(0, open_flags),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        match self.read_data(ino, offset, size) {
+            Ok(data) => reply.data(&data),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn opendir(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        match self.open_flags(ino, flags, VfsEntryKind::Directory) {
+            Ok(open_flags) => reply.opened(0, open_flags),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let entries = match self.dir_entries(ino) {
+            Ok(entries) => entries,
+            Err(errno) => return reply.error(errno),
+        };
+
+        for (i, (ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            // The offset is the one of the next entry, stop once the buffer is full
+            if reply.add(ino, i as i64 + 1, kind, name) {
+                break;
+            }
+        }
+
+        reply.ok();
+    }
+}
+
+fn child_path(path: &str, name: &str) -> String {
+    format!("{}/{name}", path.trim_end_matches('/'))
+}
+
+fn file_type(kind: VfsEntryKind) -> FileType {
+    match kind {
+        VfsEntryKind::File => FileType::RegularFile,
+        VfsEntryKind::Directory => FileType::Directory,
+    }
+}
+
+fn errno(err: VfsError) -> i32 {
+    match err {
+        VfsError::NotFound(_) => libc::ENOENT,
+        VfsError::NotADirectory(_) => libc::ENOTDIR,
+        VfsError::IsADirectory(_) => libc::EISDIR,
+        err => {
+            error!("{err}");
+            libc::EIO
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use niiebla::templates;
+    use niiebla::vfs::WadVfs;
+    use std::ffi::OsString;
+    use std::io::Cursor;
+    use std::os::unix::ffi::OsStringExt;
+
+    fn filesystem(contents: &[&[u8]]) -> VfsFilesystem<WadVfs<Cursor<Vec<u8>>>> {
+        let wad = templates::homebrew_wad(templates::HOMEBREW_CHANNEL_TITLE_ID, contents).unwrap();
+        VfsFilesystem::new(WadVfs::new(Cursor::new(wad)).unwrap(), 1000, 100)
+    }
+
+    #[test]
+    fn browse_wad() {
+        let content: Vec<u8> = (0..0x1234).map(|i| (i * 3) as u8).collect();
+        let mut filesystem = filesystem(&[b"banner", &content]);
+
+        let contents = filesystem
+            .lookup_attr(ROOT_ID, OsStr::new("contents"))
+            .unwrap();
+        assert_eq!(contents.kind, FileType::Directory);
+        assert_eq!(contents.perm, 0o555);
+
+        let names: Vec<String> = filesystem
+            .dir_entries(contents.ino)
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, name)| name)
+            .collect();
+        assert_eq!(names, [".", "..", "0", "1"]);
+
+        let file = filesystem
+            .lookup_attr(contents.ino, OsStr::new("1"))
+            .unwrap();
+        assert_eq!(file.kind, FileType::RegularFile);
+        assert_eq!(file.size, 0x1234);
+        assert_eq!((file.uid, file.gid), (1000, 100));
+
+        // The inodes are stable between lookups
+        assert_eq!(filesystem.getattr_attr(file.ino).unwrap(), file);
+        assert_eq!(
+            filesystem
+                .lookup_attr(contents.ino, OsStr::new("1"))
+                .unwrap()
+                .ino,
+            file.ino
+        );
+
+        assert_eq!(
+            filesystem.open_flags(file.ino, libc::O_RDONLY, VfsEntryKind::File),
+            Ok(FOPEN_KEEP_CACHE)
+        );
+        assert_eq!(
+            filesystem.read_data(file.ino, 0x1000, 0x1000).unwrap(),
+            content[0x1000..]
+        );
+        assert_eq!(filesystem.read_data(file.ino, 0x2000, 0x10), Ok(vec![]));
+    }
+
+    #[test]
+    fn errors() {
+        let mut filesystem = filesystem(&[b"banner"]);
+
+        assert_eq!(
+            filesystem.lookup_attr(ROOT_ID, OsStr::new("missing.bin")),
+            Err(libc::ENOENT)
+        );
+        assert_eq!(filesystem.getattr_attr(42), Err(libc::ENOENT));
+
+        // Names that are not valid UTF-8 cannot exist
+        assert_eq!(
+            filesystem.lookup_attr(ROOT_ID, &OsString::from_vec(vec![0xFF])),
+            Err(libc::ENOENT)
+        );
+
+        let ticket = filesystem
+            .lookup_attr(ROOT_ID, OsStr::new("ticket.tik"))
+            .unwrap();
+
+        assert_eq!(
+            filesystem.open_flags(ticket.ino, libc::O_RDWR, VfsEntryKind::File),
+            Err(libc::EROFS)
+        );
+        assert_eq!(
+            filesystem.open_flags(ticket.ino, libc::O_RDONLY, VfsEntryKind::Directory),
+            Err(libc::ENOTDIR)
+        );
+        assert_eq!(
+            filesystem.open_flags(ROOT_ID, libc::O_RDONLY, VfsEntryKind::File),
+            Err(libc::EISDIR)
+        );
+        assert_eq!(filesystem.dir_entries(ticket.ino), Err(libc::ENOTDIR));
+        assert_eq!(
+            filesystem.read_data(ticket.ino, -1, 0x10),
+            Err(libc::EINVAL)
+        );
+    }
+}
@@ -0,0 +1,53 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Command line tool to inspect and edit the file formats supported by NiiEBLA.
+
+use clap::{arg, command, value_parser, Command};
+use color_eyre::eyre::ContextCompat;
+use color_eyre::Result;
+use niiebla::Wad;
+use std::path::PathBuf;
+use tracing::info;
+use util::setup_logging_for_cli;
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    setup_logging_for_cli();
+
+    let matches = command!()
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("rename")
+                .about("Rename the channel stored inside a WAD, the title metadata is fakesigned")
+                .arg(
+                    arg!(<WAD> "Path of the WAD, modified in place")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(--title <NAME> "New name of the channel, used for all the languages")
+                        .required(true),
+                ),
+        )
+        .get_matches();
+
+    if let Some(matches) = matches.subcommand_matches("rename") {
+        let wad_path = matches
+            .get_one::<PathBuf>("WAD")
+            .wrap_err("Missing the path of the WAD")?;
+
+        let title = matches
+            .get_one::<String>("title")
+            .wrap_err("Missing the new name of the channel")?;
+
+        let (mut wad, mut wad_file) = Wad::open(wad_path)?;
+
+        info!("Renaming the channel of {wad_path:?} to {title:?}");
+        wad.rename_channel(&mut wad_file, title)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Mount a WAD or a CIA as a read-only directory with its decrypted contents and metadata files,
+//! to inspect it with regular shell tools. Only available on Linux, unmount it with
+//! `fusermount3 -u` (or `umount` as root).
+
+#[cfg(target_os = "linux")]
+mod fuse;
+
+use clap::{arg, command, value_parser};
+use color_eyre::eyre::{ContextCompat, WrapErr};
+use color_eyre::Result;
+use niiebla::cia::CiaHeader;
+use niiebla::seed_db::The3dsKeys;
+use niiebla::vfs::{CiaVfs, Vfs, WadVfs};
+use niiebla::CryptographicMethod;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use util::setup_logging_for_cli;
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    setup_logging_for_cli();
+
+    let matches = command!()
+        .name("niiebla-mount")
+        .about(
+            "Mount a WAD or a CIA as a read-only directory of its decrypted contents and metadata files",
+        )
+        .arg(arg!(<PACKAGE> "Path of the WAD or CIA").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(<MOUNTPOINT> "Existing directory where the package is mounted")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(--"3ds-keys" <FILE> "Keys of the 3DS to decrypt the contents of a CIA: the keyX and the six keyYs of the keyslot 0x3D, stored back to back")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .get_matches();
+
+    let package_path = matches
+        .get_one::<PathBuf>("PACKAGE")
+        .wrap_err("Missing the path of the package")?;
+
+    let mountpoint = matches
+        .get_one::<PathBuf>("MOUNTPOINT")
+        .wrap_err("Missing the mount point")?;
+
+    let mut package_file = BufReader::new(File::open(package_path)?);
+
+    // The first field of a CIA is the size of its header, a WAD starts with a different one
+    let mut magic = [0; 4];
+    package_file.read_exact(&mut magic)?;
+    package_file.seek(SeekFrom::Start(0))?;
+
+    if u32::from_le_bytes(magic) == CiaHeader::SIZE {
+        let vfs = if let Some(keys_path) = matches.get_one::<PathBuf>("3ds-keys") {
+            CiaVfs::new_with_cryptographic_method(
+                package_file,
+                CryptographicMethod::The3ds(read_3ds_keys(keys_path)?),
+            )?
+        } else {
+            warn!("No 3DS keys given, the contents of the CIA are mounted as stored");
+            CiaVfs::new(package_file)?
+        };
+
+        mount(vfs, package_path, mountpoint)
+    } else {
+        let vfs = WadVfs::new(package_file)?;
+        mount(vfs, package_path, mountpoint)
+    }
+}
+
+fn read_3ds_keys(path: &Path) -> Result<The3dsKeys> {
+    let mut file = File::open(path)?;
+
+    let mut keys = The3dsKeys {
+        ticket_key_x: [0; 16],
+        ticket_common_key_ys: [[0; 16]; 6],
+    };
+
+    file.read_exact(&mut keys.ticket_key_x)?;
+
+    for key_y in &mut keys.ticket_common_key_ys {
+        file.read_exact(key_y)?;
+    }
+
+    Ok(keys)
+}
+
+#[cfg(target_os = "linux")]
+fn mount<V: Vfs + Send + 'static>(vfs: V, package_path: &Path, mountpoint: &Path) -> Result<()> {
+    use fuser::MountOption;
+    use std::os::unix::fs::MetadataExt;
+
+    // The files are owned by the same user and group as the mount point
+    let mountpoint_metadata = std::fs::metadata(mountpoint)?;
+    let filesystem =
+        fuse::VfsFilesystem::new(vfs, mountpoint_metadata.uid(), mountpoint_metadata.gid());
+
+    info!("Mounting {package_path:?} at {mountpoint:?}, unmount it to exit");
+
+    fuser::mount2(
+        filesystem,
+        mountpoint,
+        &[
+            MountOption::RO,
+            MountOption::FSName(String::from("niiebla")),
+            MountOption::Subtype(String::from("niiebla")),
+        ],
+    )
+    .wrap_err("Unable to mount the FUSE filesystem")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mount<V: Vfs>(_vfs: V, _package_path: &Path, _mountpoint: &Path) -> Result<()> {
+    color_eyre::eyre::bail!("Mounting with FUSE is only supported on Linux")
+}
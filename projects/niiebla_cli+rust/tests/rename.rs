@@ -0,0 +1,116 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Tests of the `rename` command, run against the built `niiebla` binary.
+
+use color_eyre::Result;
+use niiebla::certificate_chain::{Certificate, CertificateKey, CertificateKeyValue};
+use niiebla::imet::{Imet, ImetLanguage};
+use niiebla::signed_blob_header::{SignedBlobHeader, SignedBlobHeaderSignature};
+use niiebla::{templates, CertificateChain, CryptographicMethod, Wad};
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Build a WAD of the Homebrew Channel whose only content is a banner with the given name.
+fn channel_wad(name: &str) -> Result<Vec<u8>> {
+    let mut imet = Imet {
+        file_sizes: [0x20, 0x20, 0x20],
+        flag: 0,
+        names: [const { String::new() }; Imet::NUMBER_OF_NAMES],
+    };
+    imet.set_name(name)?;
+
+    let mut banner = vec![];
+    imet.dump(&mut banner)?;
+    banner.extend([0x55; 0x40]);
+
+    let mut stream = Cursor::new(templates::homebrew_wad(
+        templates::HOMEBREW_CHANNEL_TITLE_ID,
+        &[&banner],
+    )?);
+
+    // The command edits the WAD in a session that needs the three certificates of the chain
+    let certificate = Certificate {
+        signed_blob_header: SignedBlobHeader {
+            signature: SignedBlobHeaderSignature::Rsa2048Sha1(Box::new([0; 256])),
+            issuer: String::from("Root"),
+        },
+        identity: String::from("CA00000001"),
+        key: CertificateKey {
+            id: 0,
+            value: CertificateKeyValue::Rsa2048(Box::new([0; 260])),
+        },
+    };
+    let certificate_chain = CertificateChain {
+        certificates: vec![certificate; 3],
+    };
+
+    let mut wad = Wad::try_new_installable(&mut stream)?;
+    let ticket = wad.ticket(&mut stream)?;
+    let title_metadata = wad.title_metadata(&mut stream)?;
+
+    stream.set_position(0);
+    wad.write_certificate_chain_safe(&mut stream, &certificate_chain, &ticket, &title_metadata)?;
+
+    Ok(stream.into_inner())
+}
+
+fn channel_name(wad_path: &Path) -> Result<String> {
+    let (wad, mut wad_file) = Wad::open_readonly(wad_path)?;
+    let ticket = wad.ticket(&mut wad_file)?;
+    let title_metadata = wad.title_metadata(&mut wad_file)?;
+
+    let mut banner = vec![];
+    wad.decrypted_content_view(
+        &mut wad_file,
+        &ticket,
+        &title_metadata,
+        CryptographicMethod::Wii,
+        title_metadata.select_with_index(0),
+    )?
+    .read_to_end(&mut banner)?;
+
+    let imet = Imet::new(Cursor::new(&banner))?;
+    Ok(imet.name(ImetLanguage::English).to_string())
+}
+
+fn rename(wad_path: &Path, title: &str) -> Result<Output> {
+    Ok(Command::new(env!("CARGO_BIN_EXE_niiebla"))
+        .arg("rename")
+        .arg(wad_path)
+        .args(["--title", title])
+        .output()?)
+}
+
+#[test]
+fn rename_channel() {
+    let wad_path =
+        std::env::temp_dir().join(format!("niiebla_cli_rename_{}.wad", std::process::id()));
+    std::fs::write(&wad_path, channel_wad("Old Name").unwrap()).unwrap();
+
+    let output = rename(&wad_path, "New Name").unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(channel_name(&wad_path).unwrap(), "New Name");
+
+    // Names longer than the banner allows are rejected without touching the WAD
+    let output = rename(&wad_path, &"A".repeat(43)).unwrap();
+    assert!(!output.status.success());
+    assert_eq!(channel_name(&wad_path).unwrap(), "New Name");
+
+    std::fs::remove_file(&wad_path).unwrap();
+}
+
+#[test]
+fn rename_needs_title() {
+    let output = Command::new(env!("CARGO_BIN_EXE_niiebla"))
+        .args(["rename", "channel.wad"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--title"));
+}
@@ -10,6 +10,8 @@ use std::io::{Read, Seek, SeekFrom, Write};
 
 /// Decryptor of AES-128 encrypted bytes.
 pub type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+/// Encryptor of AES-128 bytes.
 pub type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
 
 /// Stream of AES-128 encrypted bytes.
@@ -17,11 +19,28 @@ pub struct AesCbcStream<T> {
     stream: T,
     decryptor: Aes128CbcDec,
     encryptor: Aes128CbcEnc,
+
+    /// The position inside the stream where the encrypted bytes start.
+    base_offset: u64,
 }
 
 impl<T> AesCbcStream<T> {
     /// Create a new decryption stream.
     pub fn new(stream: T, key: [u8; 16], iv: [u8; 16]) -> Result<Self, io::Error> {
+        Self::new_with_base_offset(stream, key, iv, 0)
+    }
+
+    /// Like [Self::new] but the encrypted bytes start at the given position of the stream
+    /// instead of at its start, useful to wrap views (or whole files) where the position zero is
+    /// not the start of the encrypted data. The `iv` is the one of the first encrypted block.
+    ///
+    /// The positions of the stream are not changed, reading before the base offset fails.
+    pub fn new_with_base_offset(
+        stream: T,
+        key: [u8; 16],
+        iv: [u8; 16],
+        base_offset: u64,
+    ) -> Result<Self, io::Error> {
         let decryptor = Aes128CbcDec::new(&key.into(), &iv.into());
         let encryptor = Aes128CbcEnc::new(&key.into(), &iv.into());
 
@@ -29,6 +48,7 @@ impl<T> AesCbcStream<T> {
             stream,
             decryptor,
             encryptor,
+            base_offset,
         })
     }
 
@@ -43,15 +63,18 @@ impl<T: Read + Seek> Read for AesCbcStream<T> {
         let original_position = self.stream.stream_position()?;
         let stream_len = self.stream.seek(SeekFrom::End(0))? + 1;
 
-        // If the position is not aligned to a block then align it to the previous 16 byte boundary
-        let start_position = if original_position == 0 {
-            original_position
-        } else if original_position % 16 == 0 {
-            original_position - 16
-        } else {
-            crate::align_to_boundary(original_position, 16) - 16
+        let Some(relative_position) = original_position.checked_sub(self.base_offset) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Unable to read before the start of the encrypted bytes",
+            ));
         };
 
+        // Blocks are decrypted using the previous one as its IV, so the read starts one block
+        // before the block of the position (only the first block uses the original IV)
+        let block_position = relative_position - relative_position % 16;
+        let start_position = self.base_offset + block_position.saturating_sub(16);
+
         let start_padding = original_position - start_position;
 
         let mut buf_len = buf.len() as u64;
@@ -69,7 +92,23 @@ impl<T: Read + Seek> Read for AesCbcStream<T> {
         let mut encrypted_buffer = vec![0; len].into_boxed_slice();
         let mut decrypted_buffer = vec![0; len].into_boxed_slice();
 
-        self.stream.read(&mut encrypted_buffer)?;
+        // The inner stream may return less bytes than asked without being at its end, the whole
+        // last block is needed to decrypt it
+        let mut read_bytes = 0;
+
+        while read_bytes < len {
+            match self.stream.read(&mut encrypted_buffer[read_bytes..]) {
+                Ok(0) => break,
+                Ok(n) => read_bytes += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        // A truncated stream would otherwise be "read" forever as decrypted garbage
+        if (read_bytes as u64) < start_padding + buf_len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
 
         self.decryptor
             .clone()
@@ -84,7 +123,11 @@ impl<T: Read + Seek> Read for AesCbcStream<T> {
             buf[i] = *value
         }
 
-        Ok((buf_len) as usize)
+        // The encrypted buffer may have been read past the bytes returned
+        self.stream
+            .seek(SeekFrom::Start(original_position + buf_len))?;
+
+        Ok(buf_len as usize)
     }
 }
 
@@ -109,3 +152,116 @@ impl<T: Seek> Seek for AesCbcStream<T> {
         self.stream.seek(pos)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::View;
+    use std::io::Cursor;
+
+    const KEY: [u8; 16] = [0x11; 16];
+    const IV: [u8; 16] = [0x22; 16];
+
+    fn plaintext() -> Vec<u8> {
+        (0..64).collect()
+    }
+
+    /// A buffer with the encrypted plaintext stored at the given offset.
+    fn encrypted_buffer(offset: usize) -> Vec<u8> {
+        let mut ciphertext = vec![0; 64];
+
+        Aes128CbcEnc::new(&KEY.into(), &IV.into())
+            .encrypt_padded_b2b_mut::<NoPadding>(&plaintext(), &mut ciphertext)
+            .unwrap();
+
+        let mut buffer = vec![0xFF; offset];
+        buffer.append(&mut ciphertext);
+        buffer.append(&mut vec![0xFF; 23]);
+
+        buffer
+    }
+
+    #[test]
+    fn read_view_starting_mid_file() {
+        let mut stream = Cursor::new(encrypted_buffer(100));
+        stream.set_position(100);
+
+        let view = View::new(&mut stream, 64).unwrap();
+        let mut aes_stream = AesCbcStream::new(view, KEY, IV).unwrap();
+
+        let mut bytes = [0; 5];
+        aes_stream.seek(SeekFrom::Start(20)).unwrap();
+        aes_stream.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, plaintext()[20..25]);
+
+        // Consecutive reads of sizes not multiple of the block size
+        aes_stream.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, plaintext()[25..30]);
+
+        let mut bytes = vec![];
+        aes_stream.rewind().unwrap();
+        aes_stream.read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, plaintext());
+    }
+
+    /// A stream that returns at most one byte on every read.
+    struct OneByteReader(Cursor<Vec<u8>>);
+
+    impl Read for OneByteReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    impl Seek for OneByteReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    #[test]
+    fn read_from_short_reads() {
+        let stream = OneByteReader(Cursor::new(encrypted_buffer(0)));
+        let mut aes_stream = AesCbcStream::new(stream, KEY, IV).unwrap();
+
+        let mut bytes = [0; 37];
+        aes_stream.seek(SeekFrom::Start(19)).unwrap();
+        aes_stream.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, plaintext()[19..56]);
+
+        // A stream that ends in the middle of a block is still truncated
+        let mut ciphertext = encrypted_buffer(0);
+        ciphertext.truncate(40);
+
+        let stream = OneByteReader(Cursor::new(ciphertext));
+        let mut aes_stream = AesCbcStream::new(stream, KEY, IV).unwrap();
+
+        let mut bytes = [0; 64];
+        assert_eq!(
+            aes_stream.read(&mut bytes).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn read_with_base_offset() {
+        let mut stream = Cursor::new(encrypted_buffer(100));
+
+        // The view starts 7 bytes before the encrypted bytes
+        stream.set_position(93);
+        let view = View::new(&mut stream, 7 + 64).unwrap();
+        let mut aes_stream = AesCbcStream::new_with_base_offset(view, KEY, IV, 7).unwrap();
+
+        let mut bytes = [0; 40];
+        aes_stream.seek(SeekFrom::Start(7 + 17)).unwrap();
+        aes_stream.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, plaintext()[17..57]);
+
+        aes_stream.rewind().unwrap();
+        assert_eq!(
+            aes_stream.read(&mut bytes).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
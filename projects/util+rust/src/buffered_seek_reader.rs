@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::cmp;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Read-ahead buffered wrapper of a stream ([Read] and [Seek]).
+///
+/// Unlike [std::io::BufReader] the buffer is not discarded on every seek, seeking inside the
+/// range already read (like when jumping around small headers and tables) is free. The inner
+/// stream is only seeked when data outside the buffer is needed.
+///
+/// The inner stream **must not be modified** while wrapped, the buffered data would get stale.
+pub struct BufferedSeekReader<T: Read + Seek> {
+    inner: T,
+    buffer: Box<[u8]>,
+
+    // Position on the inner stream of the first byte of the buffer
+    buffer_position: u64,
+    filled: usize,
+
+    position: u64,
+    inner_position: u64,
+}
+
+impl<T: Read + Seek> BufferedSeekReader<T> {
+    /// Default capacity of the buffer in bytes.
+    pub const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+    /// Create a new [BufferedSeekReader] with the default capacity.
+    pub fn new(stream: T) -> io::Result<Self> {
+        Self::with_capacity(Self::DEFAULT_CAPACITY, stream)
+    }
+
+    /// Create a new [BufferedSeekReader] with the given capacity of the buffer.
+    pub fn with_capacity(capacity: usize, mut stream: T) -> io::Result<Self> {
+        let position = stream.stream_position()?;
+
+        Ok(Self {
+            inner: stream,
+            buffer: vec![0; capacity].into_boxed_slice(),
+            buffer_position: position,
+            filled: 0,
+            position,
+            inner_position: position,
+        })
+    }
+
+    /// Consume the [BufferedSeekReader] and get back the wrapped stream, positioned where the
+    /// reader was (not where the read-ahead stopped).
+    pub fn into_inner(mut self) -> io::Result<T> {
+        self.sync_inner_position()?;
+
+        Ok(self.inner)
+    }
+
+    fn buffered(&self) -> &[u8] {
+        let buffer_end = self.buffer_position + self.filled as u64;
+
+        if self.position < self.buffer_position || self.position >= buffer_end {
+            return &[];
+        }
+
+        &self.buffer[(self.position - self.buffer_position) as usize..self.filled]
+    }
+
+    fn sync_inner_position(&mut self) -> io::Result<()> {
+        if self.inner_position != self.position {
+            self.inner.seek(SeekFrom::Start(self.position))?;
+            self.inner_position = self.position;
+        }
+
+        Ok(())
+    }
+
+    fn fill_buffer(&mut self) -> io::Result<()> {
+        self.sync_inner_position()?;
+
+        self.filled = self.inner.read(&mut self.buffer)?;
+        self.buffer_position = self.position;
+        self.inner_position = self.position + self.filled as u64;
+
+        Ok(())
+    }
+}
+
+impl<T: Read + Seek> Read for BufferedSeekReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffered().is_empty() {
+            // Bypass the buffer on big reads, it would be copied for nothing
+            if buf.len() >= self.buffer.len() {
+                self.sync_inner_position()?;
+
+                let read_bytes = self.inner.read(buf)?;
+                self.position += read_bytes as u64;
+                self.inner_position = self.position;
+
+                return Ok(read_bytes);
+            }
+
+            self.fill_buffer()?;
+        }
+
+        let buffered = self.buffered();
+        let read_bytes = cmp::min(buffered.len(), buf.len());
+
+        buf[..read_bytes].copy_from_slice(&buffered[..read_bytes]);
+        self.position += read_bytes as u64;
+
+        Ok(read_bytes)
+    }
+}
+
+impl<T: Read + Seek> Seek for BufferedSeekReader<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(position) => position,
+
+            SeekFrom::Current(offset) => {
+                self.position.checked_add_signed(offset).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Seeked into a negative or overflowing offset",
+                    )
+                })?
+            }
+
+            // The length of the stream is unknown, let the inner stream resolve it
+            SeekFrom::End(_) => {
+                self.inner_position = self.inner.seek(pos)?;
+                self.inner_position
+            }
+        };
+
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Stream that counts the number of reads done on it.
+    struct CountingStream {
+        inner: Cursor<Vec<u8>>,
+        reads: usize,
+    }
+
+    impl Read for CountingStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for CountingStream {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn seek_inside_the_buffer() {
+        let stream = CountingStream {
+            inner: Cursor::new((0..=255).collect()),
+            reads: 0,
+        };
+
+        let mut reader = BufferedSeekReader::with_capacity(64, stream).unwrap();
+
+        let mut data = [0; 4];
+        reader.read_exact(&mut data).unwrap();
+        assert_eq!(data, [0, 1, 2, 3]);
+
+        reader.seek(SeekFrom::Start(32)).unwrap();
+        reader.read_exact(&mut data).unwrap();
+        assert_eq!(data, [32, 33, 34, 35]);
+
+        reader.seek(SeekFrom::Current(-34)).unwrap();
+        reader.read_exact(&mut data).unwrap();
+        assert_eq!(data, [2, 3, 4, 5]);
+
+        assert_eq!(reader.inner.reads, 1);
+
+        reader.seek(SeekFrom::End(-1)).unwrap();
+        reader.read_exact(&mut data[..1]).unwrap();
+        assert_eq!(data[0], 255);
+
+        let mut stream = reader.into_inner().unwrap();
+        assert_eq!(stream.stream_position().unwrap(), 256);
+        assert_eq!(stream.reads, 2);
+    }
+}
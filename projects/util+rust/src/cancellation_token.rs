@@ -0,0 +1,101 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared flag used to ask a long-running operation to stop, usually from other thread (like
+/// the one of a GUI).
+///
+/// All the clones of a token share the same flag, the operations only check it at points where
+/// stopping leaves a well-defined state, see the documentation of each operation.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a new [CancellationToken] not yet cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operations using this token (or any of its clones) to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fail with [Cancelled] if the token has been cancelled.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            return Err(Cancelled);
+        }
+
+        Ok(())
+    }
+}
+
+/// Error returned by the operations stopped with a [CancellationToken].
+///
+/// When converted into an [io::Error] it can be recovered with [Cancelled::from_io_error].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl Cancelled {
+    /// Try to get the [Cancelled] error wrapped inside an [io::Error].
+    pub fn from_io_error(error: &io::Error) -> Option<Self> {
+        error.get_ref()?.downcast_ref::<Self>().copied()
+    }
+}
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The operation has been cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+impl From<Cancelled> for io::Error {
+    fn from(error: Cancelled) -> Self {
+        Self::other(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_shared_between_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+
+        assert!(token.check().is_ok());
+
+        clone.cancel();
+
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(Cancelled));
+    }
+
+    #[test]
+    fn recover_from_io_error() {
+        let err: io::Error = Cancelled.into();
+
+        assert_eq!(Cancelled::from_io_error(&err), Some(Cancelled));
+        assert_eq!(
+            Cancelled::from_io_error(&io::Error::other("Other error")),
+            None
+        );
+    }
+}
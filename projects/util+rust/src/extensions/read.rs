@@ -4,9 +4,10 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+use crate::StreamPin;
 use byteorder::ReadBytesExt;
 use std::io;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
 
 /// Extension trait of [Read] with useful miscellaneous operations.
 pub trait ReadEx: Read {
@@ -22,6 +23,37 @@ pub trait ReadEx: Read {
             )),
         }
     }
+
+    /// Read exactly `len` bytes into a new buffer.
+    ///
+    /// Unlike allocating a zeroed buffer and using [Read::read_exact], the buffer grows with the
+    /// data actually read, so a corrupt size cannot exhaust the memory before reaching the end
+    /// of the stream.
+    fn read_exact_vec(&mut self, len: u64) -> io::Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.take(len).read_to_end(&mut buffer)?;
+
+        if (buffer.len() as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+
+        Ok(buffer)
+    }
+
+    /// Read exactly enough bytes to fill the buffer starting at the given offset of the stream.
+    /// The position of the stream is restored after the read.
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> io::Result<()>
+    where
+        Self: Seek,
+    {
+        let mut stream = StreamPin::new(self)?;
+
+        stream.seek(SeekFrom::Start(offset))?;
+        stream.read_exact(buffer)?;
+        stream.go_to_pin()?;
+
+        Ok(())
+    }
 }
 
 impl<T: ?Sized + Read> ReadEx for T {}
@@ -48,4 +80,29 @@ mod tests {
         let mut buffer = Cursor::new([77, 255]);
         assert!(buffer.read_bool().is_err())
     }
+
+    #[test]
+    fn read_exact_vec_truncated() {
+        let mut buffer = Cursor::new([1, 2, 3]);
+        assert_eq!(buffer.read_exact_vec(2).unwrap(), [1, 2]);
+
+        let error = buffer.read_exact_vec(u64::MAX).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_at_keeps_position() {
+        let mut buffer = Cursor::new([1, 2, 3, 4]);
+        buffer.set_position(1);
+
+        let mut bytes = [0; 2];
+        buffer.read_at(2, &mut bytes).unwrap();
+        assert_eq!(bytes, [3, 4]);
+        assert_eq!(buffer.position(), 1);
+
+        assert_eq!(
+            buffer.read_at(3, &mut bytes).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
 }
@@ -4,9 +4,10 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+use crate::StreamPin;
 use byteorder::WriteBytesExt;
 use std::io;
-use std::io::Write;
+use std::io::{Seek, SeekFrom, Write};
 
 /// Extension trait of [Write] with useful miscellaneous operations.
 pub trait WriteEx: Write {
@@ -33,6 +34,21 @@ pub trait WriteEx: Write {
 
         Ok(())
     }
+
+    /// Write a buffer of bytes at the given offset of the stream, useful to patch a field
+    /// already written. The position of the stream is restored after the write.
+    fn write_at(&mut self, offset: u64, buffer: &[u8]) -> io::Result<()>
+    where
+        Self: Seek,
+    {
+        let mut stream = StreamPin::new(self)?;
+
+        stream.seek(SeekFrom::Start(offset))?;
+        stream.write_all(buffer)?;
+        stream.go_to_pin()?;
+
+        Ok(())
+    }
 }
 
 impl<T: Write> WriteEx for T {}
@@ -40,6 +56,7 @@ impl<T: Write> WriteEx for T {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Cursor;
 
     #[test]
     fn write_zeroed_three_times() {
@@ -88,4 +105,16 @@ mod tests {
 
         assert_eq!(buffer, [1, 2, 0]);
     }
+
+    #[test]
+    fn write_at_keeps_position() {
+        let mut buffer = Cursor::new(vec![1, 2, 3, 4]);
+        buffer.set_position(3);
+        buffer.write_at(1, &[5, 6]).unwrap();
+
+        assert_eq!(buffer.position(), 3);
+
+        buffer.write_all(&[7]).unwrap();
+        assert_eq!(buffer.get_ref(), &[1, 5, 6, 7]);
+    }
 }
@@ -0,0 +1,714 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Minimal read-only [FAT](https://en.wikipedia.org/wiki/File_Allocation_Table) filesystem layer
+//! (FAT12 and FAT16), the building block of formats like the VFF files of the Nintendo Wii or the
+//! NAND of the Nintendo DSi.
+
+use crate::ReadEx;
+use byteorder::{ByteOrder, LE};
+use std::cmp;
+use std::io::{self, Read, Seek, SeekFrom};
+
+const DIRECTORY_ENTRY_SIZE: usize = 32;
+
+const ATTRIBUTE_READ_ONLY: u8 = 0x01;
+const ATTRIBUTE_VOLUME_LABEL: u8 = 0x08;
+const ATTRIBUTE_DIRECTORY: u8 = 0x10;
+const ATTRIBUTE_LONG_NAME: u8 = 0x0F;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// The layout of a FAT filesystem, usually stored in its boot sector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FatGeometry {
+    /// Size of a sector in bytes.
+    pub bytes_per_sector: u16,
+
+    /// Number of sectors of a cluster.
+    pub sectors_per_cluster: u8,
+
+    /// Number of sectors before the first FAT (including the boot sector).
+    pub reserved_sectors: u16,
+
+    /// Number of copies of the FAT.
+    pub number_of_fats: u8,
+
+    /// Maximum number of entries of the root directory.
+    pub root_directory_entries: u16,
+
+    /// Total number of sectors of the filesystem.
+    pub total_sectors: u32,
+
+    /// Number of sectors of each FAT.
+    pub sectors_per_fat: u16,
+}
+
+impl FatGeometry {
+    /// Parse the geometry from the BIOS parameter block of a boot sector.
+    pub fn from_boot_sector<T: Read>(mut stream: T) -> io::Result<Self> {
+        let mut boot_sector = [0; 0x24];
+        stream.read_exact(&mut boot_sector)?;
+
+        let total_sectors = match LE::read_u16(&boot_sector[0x13..]) {
+            // Big filesystems store the number of sectors in a 32 bits field
+            0 => LE::read_u32(&boot_sector[0x20..]),
+            total_sectors => total_sectors as u32,
+        };
+
+        Ok(Self {
+            bytes_per_sector: LE::read_u16(&boot_sector[0x0B..]),
+            sectors_per_cluster: boot_sector[0x0D],
+            reserved_sectors: LE::read_u16(&boot_sector[0x0E..]),
+            number_of_fats: boot_sector[0x10],
+            root_directory_entries: LE::read_u16(&boot_sector[0x11..]),
+            total_sectors,
+            sectors_per_fat: LE::read_u16(&boot_sector[0x16..]),
+        })
+    }
+
+    /// Get the size of a cluster in bytes.
+    pub fn cluster_size(&self) -> u64 {
+        self.bytes_per_sector as u64 * self.sectors_per_cluster as u64
+    }
+
+    /// Get the kind of FAT used by the filesystem (given by its number of clusters).
+    pub fn kind(&self) -> io::Result<FatKind> {
+        match self.number_of_clusters() {
+            0..4085 => Ok(FatKind::Fat12),
+            4085..65525 => Ok(FatKind::Fat16),
+            _ => Err(invalid_data("FAT32 filesystems are not supported")),
+        }
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        if !self.bytes_per_sector.is_power_of_two()
+            || self.bytes_per_sector < DIRECTORY_ENTRY_SIZE as u16
+        {
+            return Err(invalid_data("Invalid number of bytes per sector"));
+        }
+
+        if self.sectors_per_cluster == 0 || self.number_of_fats == 0 {
+            return Err(invalid_data("Invalid FAT geometry"));
+        }
+
+        Ok(())
+    }
+
+    fn fat_offset(&self) -> u64 {
+        self.reserved_sectors as u64 * self.bytes_per_sector as u64
+    }
+
+    fn root_directory_offset(&self) -> u64 {
+        self.fat_offset()
+            + self.number_of_fats as u64
+                * self.sectors_per_fat as u64
+                * self.bytes_per_sector as u64
+    }
+
+    fn root_directory_size(&self) -> u64 {
+        self.root_directory_entries as u64 * DIRECTORY_ENTRY_SIZE as u64
+    }
+
+    fn data_offset(&self) -> u64 {
+        self.root_directory_offset()
+            + crate::align_to_boundary(self.root_directory_size(), self.bytes_per_sector as u64)
+    }
+
+    fn number_of_clusters(&self) -> u64 {
+        let total_size = self.total_sectors as u64 * self.bytes_per_sector as u64;
+
+        total_size.saturating_sub(self.data_offset()) / self.cluster_size()
+    }
+}
+
+/// The kinds of FAT supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatKind {
+    /// Entries of 12 bits.
+    Fat12,
+
+    /// Entries of 16 bits.
+    Fat16,
+}
+
+/// A read-only FAT filesystem stored inside a stream.
+pub struct Fat<T: Read + Seek> {
+    stream: T,
+    start_position: u64,
+    geometry: FatGeometry,
+    kind: FatKind,
+    table: Vec<u8>,
+}
+
+impl<T: Read + Seek> Fat<T> {
+    /// Create a new [Fat] reading its geometry from the boot sector at the current position of
+    /// the stream.
+    pub fn new(mut stream: T) -> io::Result<Self> {
+        let start_position = stream.stream_position()?;
+        let geometry = FatGeometry::from_boot_sector(&mut stream)?;
+
+        stream.seek(SeekFrom::Start(start_position))?;
+
+        Self::with_geometry(stream, geometry)
+    }
+
+    /// Create a new [Fat] with a known geometry (for filesystems without a boot sector), the
+    /// filesystem starts at the current position of the stream.
+    pub fn with_geometry(mut stream: T, geometry: FatGeometry) -> io::Result<Self> {
+        geometry.validate()?;
+
+        let start_position = stream.stream_position()?;
+        let kind = geometry.kind()?;
+
+        stream.seek(SeekFrom::Start(start_position + geometry.fat_offset()))?;
+        let table = stream
+            .read_exact_vec(geometry.sectors_per_fat as u64 * geometry.bytes_per_sector as u64)?;
+
+        Ok(Self {
+            stream,
+            start_position,
+            geometry,
+            kind,
+            table,
+        })
+    }
+
+    /// Get the geometry of the filesystem.
+    pub fn geometry(&self) -> &FatGeometry {
+        &self.geometry
+    }
+
+    /// Get the kind of FAT used by the filesystem.
+    pub fn kind(&self) -> FatKind {
+        self.kind
+    }
+
+    /// Get the entries of the root directory.
+    pub fn root_directory(&mut self) -> io::Result<FatEntries> {
+        self.stream.seek(SeekFrom::Start(
+            self.start_position + self.geometry.root_directory_offset(),
+        ))?;
+
+        let data = self
+            .stream
+            .read_exact_vec(self.geometry.root_directory_size())?;
+
+        Ok(FatEntries::new(data))
+    }
+
+    /// Get the entries of a directory.
+    pub fn directory(&mut self, entry: &FatEntry) -> io::Result<FatEntries> {
+        if !entry.is_directory() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The given entry is not a directory",
+            ));
+        }
+
+        let mut data = Vec::new();
+
+        for offset in self.cluster_offsets(entry.first_cluster)? {
+            self.stream.seek(SeekFrom::Start(offset))?;
+            data.append(&mut self.stream.read_exact_vec(self.geometry.cluster_size())?);
+        }
+
+        Ok(FatEntries::new(data))
+    }
+
+    /// Create a view into the data of a file, following its cluster chain.
+    pub fn file_view(&mut self, entry: &FatEntry) -> io::Result<FatFileView<&mut T>> {
+        if entry.is_directory() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The given entry is a directory",
+            ));
+        }
+
+        let cluster_offsets = self.cluster_offsets(entry.first_cluster)?;
+
+        if (cluster_offsets.len() as u64 * self.geometry.cluster_size()) < entry.size as u64 {
+            return Err(invalid_data("The cluster chain is shorter than the file"));
+        }
+
+        Ok(FatFileView {
+            stream: &mut self.stream,
+            cluster_offsets,
+            cluster_size: self.geometry.cluster_size(),
+            size: entry.size as u64,
+            position: 0,
+        })
+    }
+
+    fn next_cluster(&self, cluster: u32) -> Option<u32> {
+        let cluster = cluster as usize;
+
+        let (next_cluster, end_of_chain) = match self.kind {
+            FatKind::Fat12 => {
+                let offset = cluster + cluster / 2;
+                let value = LE::read_u16(self.table.get(offset..offset + 2)?);
+
+                // Two entries are packed into three bytes
+                let next_cluster = if cluster.is_multiple_of(2) {
+                    value & 0xFFF
+                } else {
+                    value >> 4
+                };
+
+                (next_cluster as u32, 0xFF8)
+            }
+
+            FatKind::Fat16 => {
+                let offset = cluster * 2;
+                let value = LE::read_u16(self.table.get(offset..offset + 2)?);
+
+                (value as u32, 0xFFF8)
+            }
+        };
+
+        (next_cluster < end_of_chain).then_some(next_cluster)
+    }
+
+    fn cluster_offsets(&self, first_cluster: u32) -> io::Result<Vec<u64>> {
+        let number_of_clusters = self.geometry.number_of_clusters();
+        let data_offset = self.start_position + self.geometry.data_offset();
+
+        let mut cluster_offsets = Vec::new();
+
+        // Empty files have no clusters
+        let mut cluster = (first_cluster != 0).then_some(first_cluster);
+
+        while let Some(current_cluster) = cluster {
+            // The first two entries of the FAT are reserved
+            if current_cluster < 2 || current_cluster as u64 >= number_of_clusters + 2 {
+                return Err(invalid_data("Cluster out of the bounds of the filesystem"));
+            }
+
+            // A longer chain can only be a loop
+            if cluster_offsets.len() as u64 >= number_of_clusters {
+                return Err(invalid_data("The cluster chain has a loop"));
+            }
+
+            cluster_offsets
+                .push(data_offset + (current_cluster as u64 - 2) * self.geometry.cluster_size());
+
+            cluster = self.next_cluster(current_cluster);
+        }
+
+        Ok(cluster_offsets)
+    }
+}
+
+/// An entry (file or directory) of a directory of a [Fat] filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FatEntry {
+    /// The long name of the entry, or its short name if it has none.
+    pub name: String,
+
+    /// The short (8.3) name of the entry.
+    pub short_name: String,
+
+    /// The raw attributes of the entry.
+    pub attributes: u8,
+
+    /// The first cluster of the data of the entry.
+    pub first_cluster: u32,
+
+    /// The size of the file in bytes, always zero on directories.
+    pub size: u32,
+}
+
+impl FatEntry {
+    /// Check if the entry is a directory.
+    pub fn is_directory(&self) -> bool {
+        self.attributes & ATTRIBUTE_DIRECTORY != 0
+    }
+
+    /// Check if the entry is read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.attributes & ATTRIBUTE_READ_ONLY != 0
+    }
+}
+
+/// Iterator over the entries of a directory of a [Fat] filesystem, the `.` and `..` entries are
+/// skipped.
+pub struct FatEntries {
+    data: Vec<u8>,
+    position: usize,
+    long_name_parts: Vec<(u8, u8, [u16; 13])>,
+}
+
+impl FatEntries {
+    fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            position: 0,
+            long_name_parts: Vec::new(),
+        }
+    }
+
+    fn long_name(&mut self, short_name_bytes: &[u8]) -> Option<String> {
+        let checksum = short_name_checksum(short_name_bytes);
+
+        // Long names of deleted or replaced entries may be left behind
+        if self.long_name_parts.is_empty()
+            || self
+                .long_name_parts
+                .iter()
+                .any(|(_, part_checksum, _)| *part_checksum != checksum)
+        {
+            return None;
+        }
+
+        self.long_name_parts.sort_by_key(|(order, _, _)| *order);
+
+        let name = self
+            .long_name_parts
+            .iter()
+            .flat_map(|(_, _, characters)| characters)
+            .copied()
+            .take_while(|&character| character != 0x0000 && character != 0xFFFF)
+            .collect::<Vec<_>>();
+
+        Some(String::from_utf16_lossy(&name))
+    }
+}
+
+impl Iterator for FatEntries {
+    type Item = FatEntry;
+
+    fn next(&mut self) -> Option<FatEntry> {
+        loop {
+            let record: [u8; DIRECTORY_ENTRY_SIZE] = self
+                .data
+                .get(self.position..self.position + DIRECTORY_ENTRY_SIZE)?
+                .try_into()
+                .ok()?;
+            self.position += DIRECTORY_ENTRY_SIZE;
+
+            // End of the directory
+            if record[0] == 0x00 {
+                self.position = self.data.len();
+                return None;
+            }
+
+            let attributes = record[11];
+
+            // Deleted entry
+            if record[0] == 0xE5 {
+                self.long_name_parts.clear();
+                continue;
+            }
+
+            if attributes == ATTRIBUTE_LONG_NAME {
+                let mut characters = [0; 13];
+
+                for (character, bytes) in characters.iter_mut().zip(
+                    record[1..11]
+                        .chunks_exact(2)
+                        .chain(record[14..26].chunks_exact(2))
+                        .chain(record[28..32].chunks_exact(2)),
+                ) {
+                    *character = LE::read_u16(bytes);
+                }
+
+                // The last part of a long name is stored first
+                if record[0] & 0x40 != 0 {
+                    self.long_name_parts.clear();
+                }
+
+                self.long_name_parts
+                    .push((record[0] & 0x1F, record[13], characters));
+
+                continue;
+            }
+
+            let long_name = self.long_name(&record[0..11]);
+            self.long_name_parts.clear();
+
+            if attributes & ATTRIBUTE_VOLUME_LABEL != 0 || record[0] == b'.' {
+                continue;
+            }
+
+            let short_name = short_name(&record);
+
+            return Some(FatEntry {
+                name: long_name.unwrap_or_else(|| short_name.clone()),
+                short_name,
+                attributes,
+                first_cluster: LE::read_u16(&record[26..]) as u32,
+                size: LE::read_u32(&record[28..]),
+            });
+        }
+    }
+}
+
+fn short_name_checksum(short_name_bytes: &[u8]) -> u8 {
+    short_name_bytes.iter().fold(0u8, |checksum, &byte| {
+        checksum.rotate_right(1).wrapping_add(byte)
+    })
+}
+
+fn short_name(record: &[u8]) -> String {
+    // Flags set by Windows NT to store lowercase 8.3 names
+    const LOWERCASE_BASE: u8 = 0x08;
+    const LOWERCASE_EXTENSION: u8 = 0x10;
+
+    let mut base = record[0..8].to_vec();
+
+    // 0xE5 is a valid first character of some encodings, stored as 0x05
+    if base[0] == 0x05 {
+        base[0] = 0xE5;
+    }
+
+    let decode = |bytes: &[u8], lowercase: bool| {
+        let text = bytes
+            .iter()
+            .map(|&byte| byte as char)
+            .collect::<String>()
+            .trim_end_matches(' ')
+            .to_string();
+
+        if lowercase {
+            text.to_lowercase()
+        } else {
+            text
+        }
+    };
+
+    let base = decode(&base, record[12] & LOWERCASE_BASE != 0);
+    let extension = decode(&record[8..11], record[12] & LOWERCASE_EXTENSION != 0);
+
+    if extension.is_empty() {
+        base
+    } else {
+        format!("{base}.{extension}")
+    }
+}
+
+/// A view into the data of a file of a [Fat] filesystem, the clusters of the file do not need to
+/// be contiguous.
+pub struct FatFileView<T: Read + Seek> {
+    stream: T,
+    cluster_offsets: Vec<u64>,
+    cluster_size: u64,
+    size: u64,
+    position: u64,
+}
+
+impl<T: Read + Seek> FatFileView<T> {
+    /// Get the size of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    /// Check if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+impl<T: Read + Seek> Read for FatFileView<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+
+        let cluster_index = (self.position / self.cluster_size) as usize;
+        let offset_in_cluster = self.position % self.cluster_size;
+
+        // Never read past the end of the current cluster, the next one may be anywhere
+        let max_bytes_to_read = cmp::min(
+            self.cluster_size - offset_in_cluster,
+            self.size - self.position,
+        );
+        let max_bytes_to_read = cmp::min(max_bytes_to_read, buf.len() as u64) as usize;
+
+        self.stream.seek(SeekFrom::Start(
+            self.cluster_offsets[cluster_index] + offset_in_cluster,
+        ))?;
+
+        let read_bytes = self.stream.read(&mut buf[..max_bytes_to_read])?;
+        self.position += read_bytes as u64;
+
+        Ok(read_bytes)
+    }
+}
+
+impl<T: Read + Seek> Seek for FatFileView<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_position = match pos {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+        };
+
+        self.position = new_position.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Seeked into a negative offset")
+        })?;
+
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const SECTOR_SIZE: usize = 512;
+
+    fn set_fat12_entry(table: &mut [u8], cluster: usize, value: u16) {
+        let offset = cluster + cluster / 2;
+        let mut entry = LE::read_u16(&table[offset..]);
+
+        if cluster.is_multiple_of(2) {
+            entry = (entry & 0xF000) | value;
+        } else {
+            entry = (entry & 0x000F) | (value << 4);
+        }
+
+        LE::write_u16(&mut table[offset..], entry);
+    }
+
+    fn directory_record(name: &[u8; 11], attributes: u8, cluster: u16, size: u32) -> [u8; 32] {
+        let mut record = [0; 32];
+
+        record[0..11].copy_from_slice(name);
+        record[11] = attributes;
+        LE::write_u16(&mut record[26..], cluster);
+        LE::write_u32(&mut record[28..], size);
+
+        record
+    }
+
+    fn long_name_record(order: u8, short_name: &[u8; 11], characters: &[u16; 13]) -> [u8; 32] {
+        let mut record = [0; 32];
+
+        record[0] = order;
+        record[11] = ATTRIBUTE_LONG_NAME;
+        record[13] = short_name_checksum(short_name);
+
+        for (i, character) in characters.iter().enumerate() {
+            let offset = match i {
+                0..5 => 1 + i * 2,
+                5..11 => 14 + (i - 5) * 2,
+                _ => 28 + (i - 11) * 2,
+            };
+
+            LE::write_u16(&mut record[offset..], *character);
+        }
+
+        record
+    }
+
+    /// A FAT12 image with one sector per cluster: a fragmented file in the root directory and a
+    /// subdirectory with a long named file.
+    fn dummy_image() -> Vec<u8> {
+        let mut image = vec![0; 64 * SECTOR_SIZE];
+
+        // Boot sector
+        LE::write_u16(&mut image[0x0B..], SECTOR_SIZE as u16);
+        image[0x0D] = 1;
+        LE::write_u16(&mut image[0x0E..], 1);
+        image[0x10] = 1;
+        LE::write_u16(&mut image[0x11..], 16);
+        LE::write_u16(&mut image[0x13..], 64);
+        LE::write_u16(&mut image[0x16..], 1);
+
+        // FAT, clusters start on the fourth sector
+        let table = &mut image[SECTOR_SIZE..2 * SECTOR_SIZE];
+        set_fat12_entry(table, 2, 4);
+        set_fat12_entry(table, 4, 0xFFF);
+        set_fat12_entry(table, 3, 0xFFF);
+        set_fat12_entry(table, 5, 0xFFF);
+
+        let root_directory = 2 * SECTOR_SIZE;
+        image[root_directory..root_directory + 32].copy_from_slice(&directory_record(
+            b"HELLO   TXT",
+            0,
+            2,
+            600,
+        ));
+        image[root_directory + 32..root_directory + 64].copy_from_slice(&directory_record(
+            b"SUB        ",
+            ATTRIBUTE_DIRECTORY,
+            3,
+            0,
+        ));
+
+        let cluster_offset = |cluster: usize| (cluster + 1) * SECTOR_SIZE;
+
+        image[cluster_offset(2)..cluster_offset(3)].fill(b'A');
+        image[cluster_offset(4)..cluster_offset(5)].fill(b'B');
+
+        let short_name = b"ALONGN~1BIN";
+
+        // Long names are NUL terminated and padded with 0xFFFF
+        let mut long_name_parts = [[0xFFFF; 13]; 2];
+        for (i, character) in "A long name.bin".encode_utf16().chain([0]).enumerate() {
+            long_name_parts[i / 13][i % 13] = character;
+        }
+
+        let subdirectory = cluster_offset(3);
+        image[subdirectory..subdirectory + 32].copy_from_slice(&directory_record(
+            b".          ",
+            ATTRIBUTE_DIRECTORY,
+            3,
+            0,
+        ));
+        image[subdirectory + 32..subdirectory + 64].copy_from_slice(&long_name_record(
+            0x42,
+            short_name,
+            &long_name_parts[1],
+        ));
+        image[subdirectory + 64..subdirectory + 96].copy_from_slice(&long_name_record(
+            0x01,
+            short_name,
+            &long_name_parts[0],
+        ));
+        image[subdirectory + 96..subdirectory + 128]
+            .copy_from_slice(&directory_record(short_name, 0, 5, 3));
+
+        image[cluster_offset(5)..cluster_offset(5) + 3].copy_from_slice(b"xyz");
+
+        image
+    }
+
+    #[test]
+    fn read_files_and_directories() {
+        let mut fat = Fat::new(Cursor::new(dummy_image())).unwrap();
+        assert_eq!(fat.kind(), FatKind::Fat12);
+
+        let entries = fat.root_directory().unwrap().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "HELLO.TXT");
+
+        let mut data = vec![];
+        fat.file_view(&entries[0])
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(data.len(), 600);
+        assert!(data[..SECTOR_SIZE].iter().all(|&byte| byte == b'A'));
+        assert!(data[SECTOR_SIZE..].iter().all(|&byte| byte == b'B'));
+
+        let entries = fat.directory(&entries[1]).unwrap().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "A long name.bin");
+        assert_eq!(entries[0].short_name, "ALONGN~1.BIN");
+
+        let mut view = fat.file_view(&entries[0]).unwrap();
+        view.seek(SeekFrom::End(-2)).unwrap();
+
+        let mut data = vec![];
+        view.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"yz");
+    }
+}
@@ -0,0 +1,70 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Conversion between bytes and their hexadecimal representation, like the one of the hashes and
+//! keys stored on text files.
+
+/// Encode the bytes as lowercase hexadecimal digits, two per byte.
+pub fn encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut text = String::with_capacity(bytes.len() * 2);
+
+    for byte in bytes {
+        text.push(DIGITS[(byte >> 4) as usize] as char);
+        text.push(DIGITS[(byte & 0xF) as usize] as char);
+    }
+
+    text
+}
+
+/// Decode the hexadecimal digits (lowercase or uppercase) into exactly `N` bytes. Returns `None`
+/// if the text has any other character or a length different to `N * 2`.
+pub fn decode<const N: usize>(text: &str) -> Option<[u8; N]> {
+    let text = text.as_bytes();
+
+    if text.len() != N * 2 {
+        return None;
+    }
+
+    let mut bytes = [0; N];
+
+    for (byte, digits) in bytes.iter_mut().zip(text.chunks_exact(2)) {
+        *byte = (digit_value(digits[0])? << 4) | digit_value(digits[1])?;
+    }
+
+    Some(bytes)
+}
+
+/// Serialize the bytes as a string of lowercase hexadecimal digits, to be used with
+/// `#[serde(serialize_with = "util::hex::serialize")]`.
+pub fn serialize<T: AsRef<[u8]>, S: serde::Serializer>(
+    bytes: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&encode(bytes.as_ref()))
+}
+
+fn digit_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_decode() {
+        assert_eq!(encode(&[0x00, 0x1F, 0xA0, 0xFF]), "001fa0ff");
+        assert_eq!(encode(&[]), "");
+
+        assert_eq!(decode("001fa0ff"), Some([0x00, 0x1F, 0xA0, 0xFF]));
+        assert_eq!(decode("001FA0FF"), Some([0x00, 0x1F, 0xA0, 0xFF]));
+        assert_eq!(decode::<4>("001fa0f"), None);
+        assert_eq!(decode::<4>("001fa0fg"), None);
+        assert_eq!(decode::<2>("+1+f"), None);
+    }
+}
@@ -47,8 +47,8 @@ macro_rules! read_exact {
 /// assert_eq!(string, "Hi!");
 /// ```
 ///
-/// # Safety
-/// This macro panics if the string is not valid UTF-8.
+/// # Errors
+/// An [std::io::ErrorKind::InvalidData] error is returned if the string is not valid UTF-8.
 macro_rules! read_string {
     ($stream: ident, $num_of_bytes: expr) => {
         'scope: {
@@ -59,10 +59,8 @@ macro_rules! read_string {
                 Err(err) => break 'scope Err(err),
             };
 
-            let string = String::from_null_terminated_bytes(&buf)
-                .expect("The given buffer is not an UTF-8 stream");
-
-            Ok(string)
+            String::from_null_terminated_bytes(&buf)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
         }
     };
 }
@@ -0,0 +1,157 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, Seek};
+
+/// Lightweight helper to attach positional information (byte offset, field and structure names)
+/// to the IO errors raised while parsing a binary structure.
+///
+/// The wrapped [io::Error] keeps its original [io::ErrorKind], the positional data can be
+/// recovered by downcasting its inner error into a [ParseContextError].
+#[derive(Debug, Clone, Copy)]
+pub struct ParseContext {
+    structure: &'static str,
+}
+
+impl ParseContext {
+    /// Create a new [ParseContext] for the structure with the given name.
+    pub const fn new(structure: &'static str) -> Self {
+        Self { structure }
+    }
+
+    /// Run a read operation over the stream, if it fails its error will be wrapped with the
+    /// position of the stream before the read and the name of the field being parsed.
+    pub fn field<S: Seek + ?Sized, T>(
+        &self,
+        stream: &mut S,
+        field: &'static str,
+        read: impl FnOnce(&mut S) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let offset = stream.stream_position()?;
+
+        read(stream).map_err(|source| {
+            // Avoid wrapping twice errors from nested structures, the innermost context is the
+            // most precise one
+            if source
+                .get_ref()
+                .is_some_and(|inner| inner.is::<ParseContextError>())
+            {
+                return source;
+            }
+
+            io::Error::new(
+                source.kind(),
+                ParseContextError {
+                    structure: self.structure,
+                    field,
+                    offset,
+                    source,
+                },
+            )
+        })
+    }
+}
+
+/// Error with positional information, see [ParseContext].
+#[derive(Debug)]
+pub struct ParseContextError {
+    structure: &'static str,
+    field: &'static str,
+    offset: u64,
+    source: io::Error,
+}
+
+impl ParseContextError {
+    /// Get the name of the structure that was being parsed.
+    pub fn structure(&self) -> &'static str {
+        self.structure
+    }
+
+    /// Get the name of the field that was being parsed.
+    pub fn field(&self) -> &'static str {
+        self.field
+    }
+
+    /// Get the position of the stream when the parsing of the field started.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Try to get the positional information attached to an [io::Error].
+    pub fn from_io_error(error: &io::Error) -> Option<&Self> {
+        error.get_ref()?.downcast_ref::<Self>()
+    }
+}
+
+impl Display for ParseContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "at offset {:#X}, while parsing field `{}` of {}: {}",
+            self.offset, self.field, self.structure, self.source
+        )
+    }
+}
+
+impl Error for ParseContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{ReadBytesExt, BE};
+    use std::io::Cursor;
+
+    const CONTEXT: ParseContext = ParseContext::new("TMD");
+
+    #[test]
+    fn field_ok() {
+        let mut stream = Cursor::new([0, 1]);
+
+        assert_eq!(
+            CONTEXT
+                .field(&mut stream, "version", |stream| stream.read_u16::<BE>())
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn field_error_offset() {
+        let mut stream = Cursor::new([0, 1, 2]);
+        stream.seek_relative(2).unwrap();
+
+        let err = CONTEXT
+            .field(&mut stream, "version", |stream| stream.read_u16::<BE>())
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let context = ParseContextError::from_io_error(&err).unwrap();
+        assert_eq!(context.offset(), 2);
+        assert_eq!(context.field(), "version");
+        assert_eq!(context.structure(), "TMD");
+    }
+
+    #[test]
+    fn field_error_nested() {
+        let mut stream = Cursor::new([0]);
+
+        let err = ParseContext::new("Outer")
+            .field(&mut stream, "inner", |stream| {
+                CONTEXT.field(stream, "version", |stream| stream.read_u16::<BE>())
+            })
+            .unwrap_err();
+
+        let context = ParseContextError::from_io_error(&err).unwrap();
+        assert_eq!(context.structure(), "TMD");
+    }
+}
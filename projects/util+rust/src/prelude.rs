@@ -0,0 +1,16 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! The commonly used types and extension traits of the crate, import all of them at once with:
+//!
+//! ```
+//! use zelzip_util::prelude::*;
+//! ```
+
+pub use crate::{
+    AesCbcStream, BufferedSeekReader, CancellationToken, Cancelled, ParseContext, ReadEx,
+    RecallView, SpillBuffer, StreamPin, StringEx, View, WriteEx,
+};
@@ -22,11 +22,9 @@ pub struct RecallView<T: Seek> {
 impl<T: Seek> RecallView<T> {
     /// Create a new [View].
     ///
-    /// # Safety
-    /// The length must be greater than zero.
+    /// # Errors
+    /// An [io::ErrorKind::InvalidInput] error is returned if the length is zero.
     pub fn new(stream: T, len: usize) -> io::Result<Self> {
-        assert!(len > 0);
-
         let mut view = View::new(stream, len)?;
         let original_position = view.stream_position()?;
 
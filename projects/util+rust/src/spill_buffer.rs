@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Buffer that starts in memory and transparently moves its data into a temporary file once its
+/// size reaches a threshold, bounding the memory used to hold big amounts of data.
+///
+/// The temporary file is removed when the buffer is dropped.
+#[derive(Debug)]
+pub struct SpillBuffer {
+    threshold: u64,
+    inner: SpillBufferInner,
+}
+
+#[derive(Debug)]
+enum SpillBufferInner {
+    Memory(Cursor<Vec<u8>>),
+    File(TemporaryFile),
+}
+
+impl SpillBuffer {
+    /// Default threshold, 64 MiB.
+    pub const DEFAULT_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+    /// Create a new empty [SpillBuffer] that spills into a temporary file once its size reaches
+    /// the given threshold (in bytes).
+    pub fn new(threshold: u64) -> Self {
+        Self {
+            threshold,
+            inner: SpillBufferInner::Memory(Cursor::new(vec![])),
+        }
+    }
+
+    /// Check if the data has been moved into a temporary file.
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.inner, SpillBufferInner::File(_))
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        let SpillBufferInner::Memory(cursor) = &self.inner else {
+            return Ok(());
+        };
+
+        let mut temporary_file = TemporaryFile::new()?;
+        temporary_file.file.write_all(cursor.get_ref())?;
+        temporary_file
+            .file
+            .seek(SeekFrom::Start(cursor.position()))?;
+
+        self.inner = SpillBufferInner::File(temporary_file);
+
+        Ok(())
+    }
+}
+
+impl Default for SpillBuffer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_THRESHOLD)
+    }
+}
+
+impl Read for SpillBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            SpillBufferInner::Memory(cursor) => cursor.read(buf),
+            SpillBufferInner::File(temporary_file) => temporary_file.file.read(buf),
+        }
+    }
+}
+
+impl Write for SpillBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let SpillBufferInner::Memory(cursor) = &self.inner {
+            let end = cursor.position().saturating_add(buf.len() as u64);
+
+            if !buf.is_empty() && end.max(cursor.get_ref().len() as u64) >= self.threshold {
+                self.spill()?;
+            }
+        }
+
+        match &mut self.inner {
+            SpillBufferInner::Memory(cursor) => cursor.write(buf),
+            SpillBufferInner::File(temporary_file) => temporary_file.file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            SpillBufferInner::Memory(cursor) => cursor.flush(),
+            SpillBufferInner::File(temporary_file) => temporary_file.file.flush(),
+        }
+    }
+}
+
+impl Seek for SpillBuffer {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match &mut self.inner {
+            SpillBufferInner::Memory(cursor) => cursor.seek(pos),
+            SpillBufferInner::File(temporary_file) => temporary_file.file.seek(pos),
+        }
+    }
+}
+
+/// A file inside the temporary directory of the system, it is removed when dropped.
+#[derive(Debug)]
+struct TemporaryFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl TemporaryFile {
+    fn new() -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "zelzip-spill-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        Ok(Self { file, path })
+    }
+}
+
+impl Drop for TemporaryFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spill_when_reaching_the_threshold() {
+        let mut buffer = SpillBuffer::new(8);
+
+        buffer.write_all(&[1, 2, 3, 4]).unwrap();
+        assert!(!buffer.is_spilled());
+
+        buffer.seek(SeekFrom::Start(2)).unwrap();
+        buffer.write_all(&[5, 6, 7, 8, 9, 10]).unwrap();
+        assert!(buffer.is_spilled());
+
+        let SpillBufferInner::File(temporary_file) = &buffer.inner else {
+            unreachable!();
+        };
+        let path = temporary_file.path.clone();
+        assert!(path.exists());
+
+        buffer.write_all(&[11]).unwrap();
+        buffer.rewind().unwrap();
+
+        let mut data = vec![];
+        buffer.read_to_end(&mut data).unwrap();
+        assert_eq!(data, [1, 2, 5, 6, 7, 8, 9, 10, 11]);
+
+        drop(buffer);
+        assert!(!path.exists());
+    }
+}
@@ -8,6 +8,14 @@
 //!
 //! Has partial support for `no_std` mode by disabling the default `std` feature flag. Extra suport
 //! for "alloc-compatible" `no_std` environments is available by enabling the `alloc` feature flag.
+//!
+//! # Threading model
+//! None of the stream wrappers ([View], [RecallView], [StreamPin], [AesCbcStream],
+//! [BufferedSeekReader], [SpillBuffer] and [fat::FatFileView]) hold shared state, they are
+//! [Send] and/or [Sync] exactly when the stream they wrap is. A wrapper over a [std::fs::File] or
+//! a [std::io::Cursor] can be moved into other thread, but a single stream must not be used from
+//! several threads at the same time as its position is shared: open one stream per thread
+//! instead. [CancellationToken] is always [Send] and [Sync], it is meant to be shared.
 
 mod extensions;
 mod macros;
@@ -16,16 +24,27 @@ mod macros;
 pub use extensions::*;
 
 mod aes;
+mod buffered_seek_reader;
+mod cancellation_token;
+pub mod fat;
+pub mod hex;
 pub mod logging;
+mod parse_context;
+pub mod prelude;
 mod recall_view;
+mod spill_buffer;
 mod stream_pin;
 mod view;
 
-pub use aes::{Aes128CbcDec, AesCbcStream};
+pub use aes::{Aes128CbcDec, Aes128CbcEnc, AesCbcStream};
+pub use buffered_seek_reader::BufferedSeekReader;
+pub use cancellation_token::{CancellationToken, Cancelled};
 pub use logging::setup_logging_for_cli;
+pub use parse_context::{ParseContext, ParseContextError};
 pub use recall_view::RecallView;
+pub use spill_buffer::SpillBuffer;
 pub use stream_pin::StreamPin;
-pub use view::View;
+pub use view::{View, ViewError};
 
 /// Align a value to the next multiple of the given boundary.
 pub fn align_to_boundary(value: u64, boundary: u64) -> u64 {
@@ -40,6 +59,57 @@ pub fn align_to_boundary(value: u64, boundary: u64) -> u64 {
 mod tests {
     use super::*;
 
+    // The stream wrappers must propagate the auto traits of the inner stream
+    mod auto_traits {
+        use super::*;
+        use static_assertions::{assert_impl_all, assert_not_impl_any};
+        use std::cell::Cell;
+        use std::fs::File;
+        use std::io::{self, Cursor, Read, Seek, SeekFrom};
+        use std::marker::PhantomData;
+        use std::rc::Rc;
+
+        // Stream with the same auto traits as its marker
+        struct Stream<Marker>(Cursor<Vec<u8>>, PhantomData<Marker>);
+
+        impl<Marker> Read for Stream<Marker> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+
+        impl<Marker> Seek for Stream<Marker> {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        type SendOnly = Stream<Cell<()>>;
+        type NotSend = Stream<Rc<()>>;
+
+        assert_impl_all!(View<File>: Send, Sync);
+        assert_impl_all!(RecallView<File>: Send, Sync);
+        assert_impl_all!(StreamPin<File>: Send, Sync);
+        assert_impl_all!(AesCbcStream<File>: Send, Sync);
+        assert_impl_all!(BufferedSeekReader<File>: Send, Sync);
+        assert_impl_all!(fat::FatFileView<File>: Send, Sync);
+        assert_impl_all!(SpillBuffer: Send, Sync);
+        assert_impl_all!(CancellationToken: Send, Sync);
+
+        assert_impl_all!(View<SendOnly>: Send);
+        assert_impl_all!(StreamPin<SendOnly>: Send);
+        assert_impl_all!(AesCbcStream<SendOnly>: Send);
+        assert_not_impl_any!(View<SendOnly>: Sync);
+        assert_not_impl_any!(StreamPin<SendOnly>: Sync);
+        assert_not_impl_any!(AesCbcStream<SendOnly>: Sync);
+
+        assert_not_impl_any!(View<NotSend>: Send, Sync);
+        assert_not_impl_any!(RecallView<NotSend>: Send, Sync);
+        assert_not_impl_any!(StreamPin<NotSend>: Send, Sync);
+        assert_not_impl_any!(AesCbcStream<NotSend>: Send, Sync);
+        assert_not_impl_any!(BufferedSeekReader<NotSend>: Send, Sync);
+    }
+
     #[test]
     fn align_to_boundary_unaligned_value() {
         assert_eq!(align_to_boundary(117, 64), 128);
@@ -5,6 +5,8 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use std::cmp;
+use std::error::Error;
+use std::fmt::{self, Display};
 use std::io;
 use std::io::{Read, Seek, SeekFrom, Write};
 
@@ -23,18 +25,30 @@ pub struct View<T: Seek> {
 impl<T: Seek> View<T> {
     /// Create a new [View].
     ///
-    /// # Safety
-    /// The length must be greater than zero.
+    /// # Errors
+    /// An [io::ErrorKind::InvalidInput] error is returned if the length is zero or if the end of
+    /// the view would not fit inside an [u64], in the latter case its inner error is a
+    /// [ViewError::PositionOverflow].
     pub fn new(mut stream: T, len: usize) -> io::Result<Self> {
-        assert!(len > 0);
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The length of a view must be greater than zero",
+            ));
+        }
 
         let start_position = stream.stream_position()?;
 
-        Ok(Self {
+        let view = Self {
             inner: stream,
             start_position,
             len,
-        })
+        };
+
+        // Reject hostile lengths early instead of on the first seek
+        view.end_position()?;
+
+        Ok(view)
     }
 
     /// Consume the [View] and get back the wrapped stream.
@@ -42,34 +56,92 @@ impl<T: Seek> View<T> {
         self.inner
     }
 
-    fn end_position(&mut self) -> u64 {
-        self.start_position + self.len as u64 - 1
+    /// Absolute position of the last byte of the view.
+    fn end_position(&self) -> io::Result<u64> {
+        // The length is public, it may have been changed to zero after the creation of the view
+        let last_byte = (self.len as u64)
+            .checked_sub(1)
+            .ok_or(ViewError::NegativeOffset)?;
+
+        Ok(self
+            .start_position
+            .checked_add(last_byte)
+            .ok_or(ViewError::PositionOverflow)?)
     }
 
     fn relative_position(&mut self) -> io::Result<u64> {
-        Ok(self.inner.stream_position()? - self.start_position)
+        Ok(self
+            .inner
+            .stream_position()?
+            .checked_sub(self.start_position)
+            .ok_or(ViewError::NegativeOffset)?)
     }
 
-    fn calc_position_from(&self, position: u64, value: i64) -> Result<u64, io::Error> {
-        const SEEK_NEGATIVE_OFFSET_ERROR_MESAGE: &str = "Seeked into a negative offset";
+    /// Same as [View::relative_position] but clamped to an [usize], used to bound reads and
+    /// writes.
+    fn relative_position_usize(&mut self) -> io::Result<usize> {
+        // Any position that doesn't fit is out of bounds anyway
+        Ok(usize::try_from(self.relative_position()?).unwrap_or(usize::MAX))
+    }
 
-        let new_position: u64 = (position as i64 + value)
-            .try_into()
-            .map_err(|_| io::Error::other(SEEK_NEGATIVE_OFFSET_ERROR_MESAGE))?;
+    fn calc_position_from(&self, position: u64, value: i64) -> io::Result<u64> {
+        let new_position = position.checked_add_signed(value).ok_or(if value < 0 {
+            ViewError::NegativeOffset
+        } else {
+            ViewError::PositionOverflow
+        })?;
 
         if new_position < self.start_position {
-            return Err(io::Error::other(SEEK_NEGATIVE_OFFSET_ERROR_MESAGE));
+            return Err(ViewError::NegativeOffset.into());
         }
 
         Ok(new_position)
     }
 }
 
+/// Error raised when a position of a [View] (or a [crate::RecallView]) cannot be represented,
+/// all the arithmetic of the views is checked so hostile lengths and offsets cannot wrap around.
+///
+/// It's returned wrapped inside an [io::Error] of kind [io::ErrorKind::InvalidInput], use
+/// [ViewError::from_io_error] to recover it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewError {
+    /// The position would be before the start of the view.
+    NegativeOffset,
+
+    /// The absolute position on the wrapped stream would not fit inside an [u64].
+    PositionOverflow,
+}
+
+impl ViewError {
+    /// Try to get the [ViewError] wrapped inside an [io::Error].
+    pub fn from_io_error(error: &io::Error) -> Option<Self> {
+        error.get_ref()?.downcast_ref::<Self>().copied()
+    }
+}
+
+impl Display for ViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NegativeOffset => "Seeked into a negative offset",
+            Self::PositionOverflow => "Seeked into an offset bigger than the maximum stream size",
+        })
+    }
+}
+
+impl Error for ViewError {}
+
+impl From<ViewError> for io::Error {
+    fn from(error: ViewError) -> Self {
+        Self::new(io::ErrorKind::InvalidInput, error)
+    }
+}
+
 impl<T: Read + Seek> Read for View<T> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let max_bytes_to_read = cmp::min(
             // Just read 0 bytes if the seek position is out of bounds
-            self.len.saturating_sub(self.relative_position()? as usize),
+            self.len.saturating_sub(self.relative_position_usize()?),
             buf.len(),
         );
 
@@ -81,7 +153,7 @@ impl<T: Write + Seek> Write for View<T> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let max_bytes_to_write = cmp::min(
             // Just write 0 bytes if the seek position is out of bounds
-            self.len.saturating_sub(self.relative_position()? as usize),
+            self.len.saturating_sub(self.relative_position_usize()?),
             buf.len(),
         );
 
@@ -96,7 +168,10 @@ impl<T: Write + Seek> Write for View<T> {
 impl<T: Seek> Seek for View<T> {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         let new_position = match pos {
-            SeekFrom::Start(value) => self.start_position + value,
+            SeekFrom::Start(value) => self
+                .start_position
+                .checked_add(value)
+                .ok_or(ViewError::PositionOverflow)?,
 
             SeekFrom::Current(value) => {
                 let position = self.inner.stream_position()?;
@@ -104,7 +179,7 @@ impl<T: Seek> Seek for View<T> {
             }
 
             SeekFrom::End(value) => {
-                let position = self.end_position();
+                let position = self.end_position()?;
                 self.calc_position_from(position, value)?
             }
         };
@@ -117,6 +192,7 @@ impl<T: Seek> Seek for View<T> {
 
 #[cfg(test)]
 mod tests {
+    mod overflow;
     mod read;
     mod seek_end;
     mod seek_relative;
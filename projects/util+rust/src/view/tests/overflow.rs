@@ -0,0 +1,96 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use super::*;
+use std::io::Cursor;
+
+/// Stream positioned anywhere, even past the limits of a [Cursor].
+struct FarStream(u64);
+
+impl Seek for FarStream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Start(value) => self.0 = value,
+            _ => unreachable!("Views only seek with absolute positions"),
+        }
+
+        Ok(self.0)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.0)
+    }
+}
+
+#[test]
+fn new_overflowing_len() {
+    let err = View::new(FarStream(u64::MAX - 1), 3)
+        .err()
+        .expect("The view should be rejected");
+
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(
+        ViewError::from_io_error(&err),
+        Some(ViewError::PositionOverflow)
+    );
+}
+
+#[test]
+fn new_len_reaching_the_end() {
+    let mut view = View::new(FarStream(u64::MAX - 1), 2).unwrap();
+
+    assert_eq!(view.seek(SeekFrom::End(0)).unwrap(), 1);
+}
+
+#[test]
+fn seek_start_overflow() {
+    let mut view = View::new(FarStream(10), 3).unwrap();
+    let err = view.seek(SeekFrom::Start(u64::MAX)).unwrap_err();
+
+    assert_eq!(
+        ViewError::from_io_error(&err),
+        Some(ViewError::PositionOverflow)
+    );
+}
+
+#[test]
+fn seek_relative_overflow() {
+    let mut view = View::new(FarStream(u64::MAX - 10), 3).unwrap();
+    let err = view.seek(SeekFrom::Current(i64::MAX)).unwrap_err();
+
+    assert_eq!(
+        ViewError::from_io_error(&err),
+        Some(ViewError::PositionOverflow)
+    );
+}
+
+#[test]
+fn seek_relative_negative_offset() {
+    let buffer = Cursor::new(vec![1, 2, 3, 4, 5]);
+
+    let mut view = View::new(buffer, 3).unwrap();
+    let err = view.seek(SeekFrom::Current(i64::MIN)).unwrap_err();
+
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(
+        ViewError::from_io_error(&err),
+        Some(ViewError::NegativeOffset)
+    );
+}
+
+#[test]
+fn seek_end_zeroed_len() {
+    let buffer = Cursor::new(vec![1, 2, 3, 4, 5]);
+
+    let mut view = View::new(buffer, 3).unwrap();
+    view.len = 0;
+
+    let err = view.seek(SeekFrom::End(0)).unwrap_err();
+    assert_eq!(
+        ViewError::from_io_error(&err),
+        Some(ViewError::NegativeOffset)
+    );
+}